@@ -28,7 +28,7 @@ pub struct ContactDto {
 }
 
 #[rustfmt::skip]
-#[derive(Deserialize, Serialize, Clone)]
+#[derive(Deserialize, Serialize, Clone, PartialEq)]
 pub enum CountryCode { TW, TH, IN, ID, US, Unknown }
 
 impl From<CountryCode> for String {
@@ -82,6 +82,7 @@ pub enum OrderLinePayUpdateErrorReason {
     NotExist,
     InvalidQuantity,
     Omitted,
+    Cancelled,
 }
 #[derive(Serialize, Deserialize, Debug)]
 pub struct OrderLinePayUpdateErrorDto {
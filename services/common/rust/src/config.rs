@@ -157,17 +157,51 @@ pub enum App3rdPartyCfg {
 }
 
 #[allow(non_camel_case_types)]
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Deserialize, Debug, Clone, PartialEq)]
 pub enum AppDbServerType {
     MariaDB,
     PostgreSQL,
 }
 
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub enum AppDbServerRole {
+    // accepts both read and write operations, orders are distributed by hash
+    // of order ID among all primaries with `order_shard` enabled
+    Primary { order_shard: bool },
+    // read-only, replicates data from the primary with the given alias
+    Replica { primary: String },
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum AppInMemFsyncPolicy {
+    // flush every record of write-ahead log to storage device
+    Always,
+    // flush at most once per given milliseconds, the records appended
+    // in between might be lost on power failure, note the elapsed time is
+    // checked only when next record is appended
+    Periodic(u32),
+    // leave it to page cache of the operating system
+    Never,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct AppInMemPersistCfg {
+    // directory of write-ahead log and snapshot files, relative path
+    // starts from service base path
+    #[serde(deserialize_with = "jsn_deny_empty_string")]
+    pub path: String,
+    pub fsync: AppInMemFsyncPolicy,
+    // number of operations appended to the log before taking a snapshot
+    pub snapshot_interval: u32,
+}
+
 #[derive(Deserialize, Debug)]
 pub struct AppInMemoryDbCfg {
     #[serde(deserialize_with = "jsn_deny_empty_string")]
     pub alias: String,
     pub max_items: u32,
+    // data is volatile if persistence is not configured
+    pub persistence: Option<AppInMemPersistCfg>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -180,6 +214,8 @@ pub struct AppDbServerCfg {
     pub idle_timeout_secs: u16,
     pub confidentiality_path: String,
     pub db_name: String,
+    // primary without order shard if omitted
+    pub role: Option<AppDbServerRole>,
 }
 
 #[allow(non_camel_case_types)]
@@ -190,6 +226,25 @@ pub enum AppDataStoreCfg {
     DbServer(AppDbServerCfg),
 }
 
+#[derive(Deserialize)]
+#[serde(tag = "_type")]
+pub enum AppLockCacheCfg {
+    InMemory {
+        expiry_secs: u16,
+    },
+    // lock table in SQL database, the alias has to be present in `data_store`
+    DbServer {
+        #[serde(deserialize_with = "jsn_deny_empty_string")]
+        alias: String,
+        expiry_secs: u16,
+    },
+}
+
+#[derive(Deserialize)]
+pub struct AppCacheCfg {
+    pub order_sync_lock: AppLockCacheCfg,
+}
+
 #[derive(Deserialize)]
 pub struct ApiServerCfg {
     pid_file: PIDfileCfg,
@@ -203,6 +258,7 @@ pub struct ApiServerCfg {
     pub auth: AppAuthCfg,
     pub confidentiality: AppConfidentialCfg,
     pub third_parties: Option<Vec<Arc<App3rdPartyCfg>>>,
+    pub cache: Option<AppCacheCfg>,
 }
 
 pub struct AppBasepathCfg {
@@ -276,6 +332,7 @@ impl AppConfig {
                         Self::_check_rpc(&jsnobj.rpc)?;
                         Self::_check_logging(&jsnobj.logging)?;
                         Self::_check_datastore(&jsnobj.data_store, limit)?;
+                        Self::_check_cache(jsnobj.cache.as_ref(), &jsnobj.data_store)?;
                         Ok(jsnobj)
                     }
                     Err(e) => Err(AppCfgError {
@@ -394,6 +451,16 @@ impl AppConfig {
                         };
                         return Err(e);
                     }
+                    let invalid_persist = c.persistence.as_ref().map(|p| {
+                        p.snapshot_interval == 0 || p.fsync == AppInMemFsyncPolicy::Periodic(0)
+                    });
+                    if invalid_persist == Some(true) {
+                        let e = AppCfgError {
+                            detail: Some("inmem-persistence".to_string()),
+                            code: AppErrorCode::InvalidInput,
+                        };
+                        return Err(e);
+                    }
                 }
                 AppDataStoreCfg::DbServer(c) => {
                     let lmt_conn = limit.num_db_conns;
@@ -414,8 +481,70 @@ impl AppConfig {
                 }
             }
         } // end of loop
-        Ok(())
+        Self::_check_db_replica(obj)
     } // end of _check_datastore
+
+    fn _check_db_replica(obj: &[AppDataStoreCfg]) -> DefaultResult<(), AppCfgError> {
+        let db_cfgs = obj
+            .iter()
+            .filter_map(|d| match d {
+                AppDataStoreCfg::DbServer(c) => Some(c),
+                AppDataStoreCfg::InMemory(_) => None,
+            })
+            .collect::<Vec<_>>();
+        // each replica has to follow existing primary of the same server type
+        let invalid = db_cfgs.iter().find_map(|c| match c.role.as_ref() {
+            Some(AppDbServerRole::Replica { primary }) => {
+                let found = db_cfgs.iter().any(|p| {
+                    p.alias.as_str() == primary.as_str()
+                        && p.srv_type == c.srv_type
+                        && !matches!(p.role, Some(AppDbServerRole::Replica { .. }))
+                });
+                if found {
+                    None
+                } else {
+                    Some(format!("db-replica:{}, primary:{}", c.alias, primary))
+                }
+            }
+            _others => None,
+        });
+        if let Some(msg) = invalid {
+            Err(AppCfgError {
+                detail: Some(msg),
+                code: AppErrorCode::MissingDataStore,
+            })
+        } else {
+            Ok(())
+        }
+    } // end of _check_db_replica
+
+    fn _check_cache(
+        obj: Option<&AppCacheCfg>,
+        dstore_cfgs: &[AppDataStoreCfg],
+    ) -> DefaultResult<(), AppCfgError> {
+        let (expiry_secs, maybe_alias) = match obj.map(|c| &c.order_sync_lock) {
+            None => return Ok(()),
+            Some(AppLockCacheCfg::InMemory { expiry_secs }) => (*expiry_secs, None),
+            Some(AppLockCacheCfg::DbServer { alias, expiry_secs }) => {
+                (*expiry_secs, Some(alias.as_str()))
+            }
+        };
+        let dstore_found = maybe_alias.map_or(true, |a| {
+            dstore_cfgs.iter().any(|d| match d {
+                AppDataStoreCfg::DbServer(c) => c.alias.as_str() == a,
+                AppDataStoreCfg::InMemory(_) => false,
+            })
+        });
+        let result = if expiry_secs == 0 {
+            Err((Some("lock-expiry".to_string()), AppErrorCode::InvalidInput))
+        } else if !dstore_found {
+            let msg = format!("lock-dstore-alias:{}", maybe_alias.unwrap_or(""));
+            Err((Some(msg), AppErrorCode::MissingDataStore))
+        } else {
+            Ok(())
+        };
+        result.map_err(|(detail, code)| AppCfgError { detail, code })
+    } // end of _check_cache
 } // end of impl AppConfig
 
 struct ExpectNonEmptyString {
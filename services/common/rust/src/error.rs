@@ -39,6 +39,7 @@ pub enum AppErrorCode {
     DataCorruption,
     HttpHandshakeFailure,
     ProductNotExist,
+    DataConflict, // concurrent modification on the same record
     IOerror(std::io::ErrorKind),
 } // end of AppErrorCode
 
@@ -1,7 +1,10 @@
 mod ut_common;
 use std::collections::HashMap;
 
-use ecommerce_common::config::{App3rdPartyCfg, AppCfgHardLimit, AppCfgInitArgs, AppConfig};
+use ecommerce_common::config::{
+    App3rdPartyCfg, AppCfgHardLimit, AppCfgInitArgs, AppConfig, AppDataStoreCfg, AppDbServerRole,
+    AppLockCacheCfg,
+};
 use ecommerce_common::constant::env_vars::{CFG_FILEPATH, SERVICE_BASEPATH, SYS_BASEPATH};
 use ecommerce_common::error::{AppCfgError, AppErrorCode};
 
@@ -111,6 +114,26 @@ fn parse_ext_cfg_file_ok() {
             })
             .count();
     }
+    let db_roles = actual
+        .data_store
+        .iter()
+        .filter_map(|d| match d {
+            AppDataStoreCfg::DbServer(c) => c.role.as_ref(),
+            AppDataStoreCfg::InMemory(_) => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(db_roles.len(), 2);
+    assert!(matches!(
+        db_roles[0],
+        AppDbServerRole::Primary { order_shard: true }
+    ));
+    let cond = matches!(db_roles[1], AppDbServerRole::Replica { primary }
+        if primary.as_str() == "storage-big-table");
+    assert!(cond);
+    let lock_cfg = actual.cache.as_ref().map(|c| &c.order_sync_lock);
+    let cond = matches!(lock_cfg, Some(AppLockCacheCfg::DbServer { alias, expiry_secs })
+        if alias.as_str() == "storage-big-table" && *expiry_secs == 25);
+    assert!(cond);
 } // end of fn parse_ext_cfg_file_ok
 
 fn _parse_ext_cfg_file_error_common(cfg_filepath: &str, expect_err: AppErrorCode) -> AppCfgError {
@@ -185,3 +208,27 @@ fn parse_ext_cfg_file_dstore_exceed_limit() {
         AppErrorCode::ExceedingMaxLimit,
     );
 }
+
+#[test]
+fn parse_ext_cfg_file_dstore_invalid_persistence() {
+    _parse_ext_cfg_file_error_common(
+        "config_dstore_inmem_invalid_persist.json",
+        AppErrorCode::InvalidInput,
+    );
+}
+
+#[test]
+fn parse_ext_cfg_file_cache_nonexist_dstore() {
+    _parse_ext_cfg_file_error_common(
+        "config_cache_lock_nonexist_dstore.json",
+        AppErrorCode::MissingDataStore,
+    );
+}
+
+#[test]
+fn parse_ext_cfg_file_dstore_replica_nonexist_primary() {
+    _parse_ext_cfg_file_error_common(
+        "config_dstore_replica_nonexist_primary.json",
+        AppErrorCode::MissingDataStore,
+    );
+}
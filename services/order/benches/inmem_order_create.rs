@@ -0,0 +1,216 @@
+// Throughput of concurrent order creation on the in-memory data store, while
+// other tasks keep reading carts from unrelated tables. Each round creates the
+// same number of orders with different number of concurrent tasks, then
+// repeats the round with cart readers running in the background.
+//
+// run with `cargo bench --bench inmem_order_create`
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, FixedOffset, Local as LocalTime};
+use rust_decimal::Decimal;
+
+use ecommerce_common::api::dto::CurrencyDto;
+use ecommerce_common::model::BaseProductIdentity;
+
+use order::datastore::{AbstInMemoryDStore, AppInMemoryDStore};
+use order::model::{
+    CartLineModel, CartModel, CurrencyModel, OrderCurrencyModel, OrderLineAppliedPolicyModel,
+    OrderLineIdentity, OrderLineModel, OrderLineModelSet, OrderLinePriceModel,
+    OrderLineQuantityModel, ProdAttriPriceModel, ProductStockModel, StockLevelModelSet,
+    StockQuantityModel, StoreStockModel,
+};
+use order::repository::{
+    AbsCartRepo, AbsOrderRepo, AppStockRepoReserveReturn, CartInMemRepo, OrderInMemRepo,
+};
+use order::AppInMemoryDbCfg;
+
+const NUM_STORES: u32 = 8;
+const NUM_PRODUCTS: u64 = 8;
+const NUM_ORDERS: usize = 480;
+const NUM_CART_READERS: usize = 4;
+const NUM_CARTS: u32 = 32;
+const WRITER_CONCURRENCY: [usize; 4] = [1, 4, 8, 16];
+
+struct BenchContext {
+    order_repo: Arc<Box<dyn AbsOrderRepo>>,
+    cart_repo: Arc<Box<dyn AbsCartRepo>>,
+}
+
+fn reserve_cb(ms: &mut StockLevelModelSet, req: &OrderLineModelSet) -> AppStockRepoReserveReturn {
+    let result = ms.try_reserve(req);
+    if result.is_empty() {
+        Ok(())
+    } else {
+        Err(Ok(result))
+    }
+}
+
+async fn setup_context() -> BenchContext {
+    let cfg = AppInMemoryDbCfg {
+        alias: "bench".to_string(),
+        max_items: 1_000_000,
+        persistence: None,
+    };
+    let dstore: Box<dyn AbstInMemoryDStore> = Box::new(AppInMemoryDStore::new(&cfg).unwrap());
+    let dstore = Arc::new(dstore);
+    let timenow = LocalTime::now().fixed_offset();
+    let order_repo = OrderInMemRepo::new(dstore.clone(), timenow).await.unwrap();
+    let cart_repo = CartInMemRepo::new(dstore).await.unwrap();
+    let expiry = (timenow + Duration::from_secs(86400 * 30)).to_utc();
+    let stores = (1..=NUM_STORES)
+        .map(|store_id| StoreStockModel {
+            store_id,
+            products: (1..=NUM_PRODUCTS)
+                .map(|id_| ProductStockModel {
+                    id_,
+                    expiry,
+                    quantity: StockQuantityModel::new(1_000_000, 0, 0, None),
+                    is_create: true,
+                })
+                .collect(),
+        })
+        .collect();
+    let stock_repo = order_repo.stock();
+    stock_repo
+        .save(StockLevelModelSet { stores })
+        .await
+        .unwrap();
+    for owner in 1..=NUM_CARTS {
+        let new_lines = (1..=NUM_PRODUCTS)
+            .map(|product_id| CartLineModel {
+                id_: BaseProductIdentity {
+                    store_id: owner % NUM_STORES + 1,
+                    product_id,
+                },
+                chosen_attr: Vec::new(),
+                qty_req: 2,
+            })
+            .collect();
+        let cart = CartModel {
+            owner,
+            seq_num: 0,
+            title: format!("bench-cart-{owner}"),
+            saved_lines: Vec::new(),
+            new_lines,
+        };
+        cart_repo.update(cart).await.unwrap();
+    }
+    BenchContext {
+        order_repo: Arc::new(Box::new(order_repo)),
+        cart_repo: Arc::new(Box::new(cart_repo)),
+    }
+} // end of fn setup_context
+
+fn gen_order(label: &str, seq: usize, timenow: DateTime<FixedOffset>) -> OrderLineModelSet {
+    let store_id = (seq as u32) % NUM_STORES + 1;
+    let reserved_until = timenow + Duration::from_secs(3600);
+    let lines = (0..2u64)
+        .map(|idx| {
+            let product_id = (seq as u64 + idx) % NUM_PRODUCTS + 1;
+            let id_ = OrderLineIdentity::from((store_id, product_id, 0));
+            let price = OrderLinePriceModel::from((15, 15));
+            let policy = OrderLineAppliedPolicyModel {
+                reserved_until,
+                warranty_until: reserved_until,
+                version: 0,
+            };
+            let qty = OrderLineQuantityModel {
+                reserved: 1,
+                paid: 0,
+                paid_last_update: None,
+                cancelled: 0,
+                delivered: 0,
+            };
+            let attrs_charge = ProdAttriPriceModel::from((timenow, None));
+            OrderLineModel::from((id_, price, policy, qty, attrs_charge))
+        })
+        .collect::<Vec<_>>();
+    let rate = CurrencyModel {
+        name: CurrencyDto::TWD,
+        rate: Decimal::new(32, 0),
+    };
+    let currency = OrderCurrencyModel {
+        buyer: rate.clone(),
+        sellers: HashMap::from([(store_id, rate)]),
+    };
+    let oid = format!("{label}{seq:06x}");
+    let args = (oid, 126, timenow, currency, lines);
+    OrderLineModelSet::try_from(args).unwrap()
+}
+
+// return number of orders created per second, and number of cart reads
+// per second if there are readers running in the background
+async fn run_round(
+    ctx: &BenchContext,
+    label: &str,
+    num_writers: usize,
+    with_readers: bool,
+) -> (f64, f64) {
+    let timenow = LocalTime::now().fixed_offset();
+    let stop = Arc::new(AtomicBool::new(false));
+    let num_reads = Arc::new(AtomicUsize::new(0));
+    let readers = (0..if with_readers { NUM_CART_READERS } else { 0 })
+        .map(|idx| {
+            let (repo, stop, num_reads) = (ctx.cart_repo.clone(), stop.clone(), num_reads.clone());
+            tokio::spawn(async move {
+                let mut owner = idx as u32;
+                while !stop.load(Ordering::Relaxed) {
+                    let cart = repo.fetch_cart(owner % NUM_CARTS + 1, 0).await.unwrap();
+                    assert!(!cart.saved_lines.is_empty());
+                    num_reads.fetch_add(1, Ordering::Relaxed);
+                    owner += 1;
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    let t0 = Instant::now();
+    let writers = (0..num_writers)
+        .map(|w_idx| {
+            let stock_repo = ctx.order_repo.stock();
+            let label = label.to_string();
+            tokio::spawn(async move {
+                for seq in (w_idx..NUM_ORDERS).step_by(num_writers) {
+                    let ol_set = gen_order(label.as_str(), seq, timenow);
+                    let result = stock_repo.try_reserve(reserve_cb, &ol_set).await;
+                    assert!(result.is_ok());
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    for w in writers {
+        w.await.unwrap();
+    }
+    let elapsed = t0.elapsed().as_secs_f64();
+    stop.store(true, Ordering::Relaxed);
+    for r in readers {
+        r.await.unwrap();
+    }
+    let orders_per_sec = NUM_ORDERS as f64 / elapsed;
+    let reads_per_sec = num_reads.load(Ordering::Relaxed) as f64 / elapsed;
+    (orders_per_sec, reads_per_sec)
+} // end of fn run_round
+
+fn main() {
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(8)
+        .enable_all()
+        .build()
+        .unwrap();
+    rt.block_on(async {
+        println!("writers | orders/s | orders/s (with readers) | cart reads/s");
+        for (r_idx, num_writers) in WRITER_CONCURRENCY.into_iter().enumerate() {
+            // fresh data store for each round, stock level rows keep growing
+            // with number of reservations
+            let ctx = setup_context().await;
+            let label = format!("bench{r_idx}a");
+            let (ops_alone, _) = run_round(&ctx, label.as_str(), num_writers, false).await;
+            let ctx = setup_context().await;
+            let label = format!("bench{r_idx}b");
+            let (ops_mixed, reads) = run_round(&ctx, label.as_str(), num_writers, true).await;
+            println!("{num_writers:7} | {ops_alone:8.1} | {ops_mixed:23.1} | {reads:12.1}");
+        }
+    });
+}
@@ -1,12 +1,15 @@
-use std::cell::{RefCell, RefMut};
-use std::collections::HashMap;
-use std::marker::{Send, Sync};
+use std::borrow::Cow;
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeSet, HashMap};
+use std::marker::{PhantomData, Send, Sync};
 use std::result::Result as DefaultResult;
-use std::vec;
+use std::sync::{Arc, RwLock as SyncRwLock};
 
 use async_trait::async_trait;
-use tokio::sync::{Mutex, MutexGuard};
+use tokio::sync::{OwnedRwLockReadGuard, OwnedRwLockWriteGuard, RwLock};
 
+use super::in_mem_index::{AppInMemIdxKeyType, AppInMemIdxRange, InMemIndex, InMemTable};
+use super::in_mem_persist::{InMemPersistence, InMemWalRecord};
 use crate::error::AppError;
 use ecommerce_common::config::AppInMemoryDbCfg;
 use ecommerce_common::error::AppErrorCode;
@@ -16,18 +19,29 @@ use ecommerce_common::error::AppErrorCode;
 // application callers are responsible to maintain the structure
 // of each row in each table. Each element of a row is stringified
 // regardless of its original types (integer, floating-point number)
-type InnerKey = String;
-type InnerTableLabel = String;
-type InnerRow = Vec<String>;
-type InnerTable = HashMap<InnerKey, InnerRow>;
-type AllTable = HashMap<InnerTableLabel, InnerTable>;
+pub(super) type InnerKey = String;
+pub(super) type InnerTableLabel = String;
+pub(super) type InnerRow = Vec<String>;
+pub(super) type InnerTable = HashMap<InnerKey, InnerRow>;
+pub(super) type AllTable = HashMap<InnerTableLabel, InnerTable>;
+type InnerTableLock = Arc<RwLock<InMemTable>>;
+type InnerReadGuards = Vec<(InnerTableLabel, OwnedRwLockReadGuard<InMemTable>)>;
+type InnerWriteGuards = Vec<(InnerTableLabel, OwnedRwLockWriteGuard<InMemTable>)>;
 pub type AppInMemUpdateData = AllTable;
 pub type AppInMemDeleteInfo = InnerTable; // list of IDs per table
 pub type AppInMemFetchKeys = InnerTable; // list of IDs per table
 pub type AppInMemFetchedSingleRow = InnerRow; // list of IDs per table
 pub type AppInMemFetchedSingleTable = InnerTable; // list of IDs per table
 pub type AppInMemFetchedData = AllTable; // TODO, rename to data set
-pub type AppInMemDstoreLock<'a> = MutexGuard<'a, RefCell<AppInMemFetchedData>>;
+
+// write locks of all the tables specified in `fetch_acquire()`, callers can
+// save only to these tables in `save_release()`, if a table is going to be
+// written but not read, add its label with empty ID list to the fetch keys.
+pub struct AppInMemDstoreLock<'a> {
+    // sorted by table label, which is also the order of lock acquisition
+    guards: InnerWriteGuards,
+    _store: PhantomData<&'a ()>,
+}
 
 pub trait AbsDStoreFilterKeyOp: Send + Sync {
     fn filter(&self, k: &InnerKey, v: &InnerRow) -> bool;
@@ -35,7 +49,7 @@ pub trait AbsDStoreFilterKeyOp: Send + Sync {
 
 #[async_trait]
 pub trait AbstInMemoryDStore: Send + Sync {
-    fn new(cfg: &AppInMemoryDbCfg) -> Self
+    fn new(cfg: &AppInMemoryDbCfg) -> DefaultResult<Self, AppError>
     where
         Self: Sized;
     async fn create_table(&self, label: &str) -> DefaultResult<(), AppError>;
@@ -48,6 +62,24 @@ pub trait AbstInMemoryDStore: Send + Sync {
         tbl_label: InnerTableLabel,
         op: &dyn AbsDStoreFilterKeyOp,
     ) -> DefaultResult<Vec<InnerKey>, AppError>;
+    // declare ordered secondary index on a column of existing table, the
+    // index is built from current rows and maintained on save / delete,
+    // declaring the same index again has no effect.
+    async fn create_index(
+        &self,
+        tbl_label: &str,
+        idx_label: &str,
+        col_idx: usize,
+        key_type: AppInMemIdxKeyType,
+    ) -> DefaultResult<(), AppError>;
+    // keys of the rows whose indexed column falls in the given range, in
+    // ascending order of the index
+    async fn range_keys(
+        &self,
+        tbl_label: InnerTableLabel,
+        idx_label: &str,
+        range: AppInMemIdxRange,
+    ) -> DefaultResult<Vec<InnerKey>, AppError>;
     // read-modify-write semantic, for atomic operation
     async fn fetch_acquire<'a>(
         &'a self,
@@ -61,135 +93,241 @@ pub trait AbstInMemoryDStore: Send + Sync {
 }
 
 // make it visible for testing purpose, this type could be limited in super module.
+//
+// Each table is guarded by its own read/write lock, so operations on different
+// tables can run concurrently. To avoid deadlock, any operation involving
+// multiple tables always acquires the locks in ascending order of table labels.
 pub struct AppInMemoryDStore {
     max_items_per_table: u32,
-    // TODO, replace with read/write lock
-    table_map: Mutex<RefCell<AllTable>>,
+    // the lock of table map is held only for looking up or inserting a table,
+    // never across any `await` point
+    table_map: SyncRwLock<HashMap<InnerTableLabel, InnerTableLock>>,
+    persist: Option<InMemPersistence>,
 }
 
 impl AppInMemoryDStore {
-    async fn try_get_table(&self) -> MutexGuard<RefCell<AllTable>> {
-        self.table_map.lock().await
-    }
-    fn _check_capacity(&self, _map: &AllTable) -> DefaultResult<(), AppError> {
-        let mut invalid = _map
-            .iter()
-            .filter(|(_, table)| self.max_items_per_table as usize <= table.len());
-        if let Some((label, _)) = invalid.next() {
-            let msg = format!("{}, {}, {}", module_path!(), line!(), label);
-            Err(AppError {
-                detail: Some(msg.to_string()),
-                code: AppErrorCode::ExceedingMaxLimit,
+    // recover all tables from the persistence files if configured,
+    // relative path of the files starts from the given base path
+    pub fn try_build(
+        cfg: &AppInMemoryDbCfg,
+        service_basepath: &str,
+    ) -> DefaultResult<Self, AppError> {
+        let (persist, t_map) = if let Some(c) = cfg.persistence.as_ref() {
+            let (p, t) = InMemPersistence::try_open(c, service_basepath)?;
+            (Some(p), t)
+        } else {
+            (None, HashMap::new())
+        };
+        let t_map = t_map
+            .into_iter()
+            .map(|(label, rows)| {
+                let table = InMemTable {
+                    rows,
+                    indexes: HashMap::new(),
+                };
+                (label, Arc::new(RwLock::new(table)))
             })
+            .collect();
+        Ok(Self {
+            table_map: SyncRwLock::new(t_map),
+            max_items_per_table: cfg.max_items,
+            persist,
+        })
+    }
+    // write-ahead, callers apply the mutation to the tables only after it is
+    // logged successfully
+    fn log_mutation(&self, record: &InMemWalRecord) -> DefaultResult<(), AppError> {
+        if let Some(p) = self.persist.as_ref() {
+            p.append(record)
         } else {
             Ok(())
         }
     }
-    fn _check_table_existence(
-        _map: &AllTable,
-        keys: Vec<&InnerTableLabel>,
-    ) -> DefaultResult<(), AppError> {
-        let mut invalid = keys
+    // the mutation has been logged and applied, failure of taking snapshot
+    // is not reported to the caller, it is retried on next mutation since
+    // the log still covers all the operations
+    fn compact_log(&self, held: &InnerWriteGuards) {
+        if let Some(p) = self.persist.as_ref() {
+            let _ = p.try_compact(|| self.snapshot_tables(held));
+        }
+    }
+    // copy all tables for snapshot without blocking, tables locked by the
+    // caller are read from the given guards, return `None` if any other
+    // table is currently locked by others, the snapshot will be postponed.
+    fn snapshot_tables(&self, held: &InnerWriteGuards) -> Option<AllTable> {
+        let t_map = self.table_map.try_read().ok()?;
+        t_map
+            .iter()
+            .map(|(label, lock)| {
+                let table = if let Some((_, g)) = held.iter().find(|(l, _)| l == label) {
+                    g.rows.clone()
+                } else {
+                    lock.try_read().ok()?.rows.clone()
+                };
+                Some((label.clone(), table))
+            })
+            .collect()
+    }
+
+    // look up the tables in ascending order of their labels, report error
+    // if any of them does not exist
+    fn try_get_tables<'b>(
+        &self,
+        labels: impl Iterator<Item = &'b InnerTableLabel>,
+    ) -> DefaultResult<Vec<(InnerTableLabel, InnerTableLock)>, AppError> {
+        let t_map = self.table_map.read().map_err(|e| AppError {
+            code: AppErrorCode::AcquireLockFailure,
+            detail: Some(e.to_string()),
+        })?;
+        labels
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .map(|label| {
+                t_map
+                    .get(label.as_str())
+                    .map(|t| (label.clone(), t.clone()))
+                    .ok_or(AppError {
+                        detail: Some(label.to_string()),
+                        code: AppErrorCode::DataTableNotExist,
+                    })
+            })
+            .collect()
+    }
+    async fn read_tables<'b>(
+        &self,
+        labels: impl Iterator<Item = &'b InnerTableLabel>,
+    ) -> DefaultResult<InnerReadGuards, AppError> {
+        let locks = self.try_get_tables(labels)?;
+        let mut guards = Vec::with_capacity(locks.len());
+        for (label, lock) in locks {
+            guards.push((label, lock.read_owned().await));
+        }
+        Ok(guards)
+    }
+    async fn write_tables<'b>(
+        &self,
+        labels: impl Iterator<Item = &'b InnerTableLabel>,
+    ) -> DefaultResult<InnerWriteGuards, AppError> {
+        let locks = self.try_get_tables(labels)?;
+        let mut guards = Vec::with_capacity(locks.len());
+        for (label, lock) in locks {
+            guards.push((label, lock.write_owned().await));
+        }
+        Ok(guards)
+    }
+
+    // capacity is checked only in the tables which are going to be updated
+    fn _check_capacity(&self, guards: &InnerWriteGuards) -> DefaultResult<(), AppError> {
+        let mut invalid = guards
             .iter()
-            .filter(|label| !_map.contains_key(label.as_str()));
-        if let Some(d) = invalid.next() {
+            .filter(|(_, table)| self.max_items_per_table as usize <= table.rows.len());
+        if let Some((label, _)) = invalid.next() {
+            let msg = format!("{}, {}, {}", module_path!(), line!(), label);
             Err(AppError {
-                detail: Some(d.to_string()),
-                code: AppErrorCode::DataTableNotExist,
+                detail: Some(msg.to_string()),
+                code: AppErrorCode::ExceedingMaxLimit,
             })
         } else {
             Ok(())
         }
     }
-    fn fetch_common(
-        mut _map: RefMut<AllTable>,
+    fn fetch_common<T>(
+        guards: &[(InnerTableLabel, T)],
         _info: AppInMemFetchKeys,
-    ) -> DefaultResult<AppInMemFetchedData, AppError> {
-        let unchecked_labels = _info.keys().collect::<Vec<&InnerTableLabel>>();
-        #[allow(clippy::explicit_auto_deref)]
-        Self::_check_table_existence(&*_map, unchecked_labels)?;
+    ) -> AppInMemFetchedData
+    where
+        T: std::ops::Deref<Target = InMemTable>,
+    {
         let rs_a = _info
-            .iter()
+            .into_iter()
             .map(|(label, ids)| {
-                let table = _map.get_mut(label.as_str()).unwrap();
+                let (_, table) = guards.iter().find(|(l, _)| *l == label).unwrap();
                 let rs_t = ids
-                    .iter()
-                    .filter(|id| table.contains_key(id.as_str()))
-                    .map(|id| {
-                        let row = table.get(id).unwrap();
-                        (id.clone(), row.clone())
-                    })
-                    .collect::<Vec<(InnerKey, InnerRow)>>();
-                let rs_t = HashMap::from_iter(rs_t);
-                (label.clone(), rs_t)
+                    .into_iter()
+                    .filter_map(|id| table.rows.get(id.as_str()).map(|row| (id, row.clone())))
+                    .collect::<InnerTable>();
+                (label, rs_t)
             })
-            .collect::<Vec<(InnerTableLabel, InnerTable)>>();
-        let rs_a = HashMap::from_iter(rs_a);
-        Ok(rs_a)
+            .collect::<AllTable>();
+        rs_a
     }
     fn save_common(
         &self,
-        mut _map: RefMut<AllTable>,
+        mut guards: InnerWriteGuards,
         _data: AppInMemUpdateData,
     ) -> DefaultResult<usize, AppError> {
-        let unchecked_labels = _data.keys().collect::<Vec<&InnerTableLabel>>();
-        Self::_check_table_existence(&_map, unchecked_labels)?;
-        self._check_capacity(&_map)?;
-        let tot_cnt = _data
-            .iter()
-            .map(|(label, d_grp)| {
-                let table = _map.get_mut(label.as_str()).unwrap();
-                d_grp
-                    .iter()
-                    .map(|(id, row)| {
-                        table.insert(id.clone(), row.clone());
-                    })
-                    .count()
+        let mut invalid = _data
+            .keys()
+            .filter(|label| !guards.iter().any(|(l, _)| l == *label));
+        if let Some(label) = invalid.next() {
+            return Err(AppError {
+                detail: Some(format!("table-not-locked, {}", label)),
+                code: AppErrorCode::AcquireLockFailure,
+            });
+        }
+        guards.retain(|(l, _)| _data.contains_key(l));
+        self._check_capacity(&guards)?;
+        let record = InMemWalRecord::Save {
+            data: Cow::Borrowed(&_data),
+        };
+        self.log_mutation(&record)?;
+        let tot_cnt = guards
+            .iter_mut()
+            .map(|(label, table)| {
+                let d_grp = _data.get(label.as_str()).unwrap();
+                d_grp.iter().map(|(id, row)| table.upsert(id, row)).count()
             })
             .sum();
-        self._check_capacity(&_map)?;
+        self.compact_log(&guards);
+        // the rows have been inserted regardless of the capacity check below
+        self._check_capacity(&guards)?;
         Ok(tot_cnt)
     }
 } // end of impl AppInMemoryDStore
 
 #[async_trait]
 impl AbstInMemoryDStore for AppInMemoryDStore {
-    fn new(cfg: &AppInMemoryDbCfg) -> Self {
-        let t_map = HashMap::new();
-        let t_map = Mutex::new(RefCell::new(t_map));
-        Self {
-            table_map: t_map,
-            max_items_per_table: cfg.max_items,
-        }
+    // the persistence path is resolved from current working directory,
+    // use `try_build()` instead to specify the base path
+    fn new(cfg: &AppInMemoryDbCfg) -> DefaultResult<Self, AppError> {
+        Self::try_build(cfg, "")
     }
 
     async fn create_table(&self, label: &str) -> DefaultResult<(), AppError> {
-        let guard = self.try_get_table().await;
-        let mut _map = guard.borrow_mut();
-        if !_map.contains_key(label) {
-            let newtable = HashMap::new();
-            _map.insert(label.to_string(), newtable);
-        }
+        {
+            let mut t_map = self.table_map.write().map_err(|e| AppError {
+                code: AppErrorCode::AcquireLockFailure,
+                detail: Some(e.to_string()),
+            })?;
+            if t_map.contains_key(label) {
+                return Ok(());
+            }
+            let record = InMemWalRecord::CreateTable {
+                label: Cow::Owned(label.to_string()),
+            };
+            self.log_mutation(&record)?;
+            let newtable = Arc::new(RwLock::new(InMemTable::default()));
+            t_map.insert(label.to_string(), newtable);
+        } // release the lock of table map before compacting the log, which
+          // might take snapshot and read the table map again
+        self.compact_log(&Vec::new());
         Ok(())
     }
 
     async fn delete(&self, _info: AppInMemDeleteInfo) -> DefaultResult<usize, AppError> {
-        let guard = self.try_get_table().await;
-        let mut _map = guard.borrow_mut();
-        let unchecked_labels = _info.keys().collect::<Vec<&InnerTableLabel>>();
-        #[allow(clippy::explicit_auto_deref)]
-        Self::_check_table_existence(&*_map, unchecked_labels)?;
-        let tot_cnt = _info
-            .iter()
-            .map(|(label, ids)| {
-                let table = _map.get_mut(label.as_str()).unwrap();
-                ids.iter()
-                    .map(|id| {
-                        table.remove(id);
-                    })
-                    .count()
+        let mut guards = self.write_tables(_info.keys()).await?;
+        let record = InMemWalRecord::Delete {
+            info: Cow::Borrowed(&_info),
+        };
+        self.log_mutation(&record)?;
+        let tot_cnt = guards
+            .iter_mut()
+            .map(|(label, table)| {
+                let ids = _info.get(label.as_str()).unwrap();
+                ids.iter().map(|id| table.remove(id)).count()
             })
             .sum();
+        self.compact_log(&guards);
         Ok(tot_cnt)
     }
 
@@ -197,28 +335,32 @@ impl AbstInMemoryDStore for AppInMemoryDStore {
         &self,
         _info: AppInMemFetchKeys,
     ) -> DefaultResult<AppInMemFetchedData, AppError> {
-        let guard = self.try_get_table().await;
-        Self::fetch_common(guard.borrow_mut(), _info)
+        let guards = self.read_tables(_info.keys()).await?;
+        Ok(Self::fetch_common(&guards, _info))
     }
     async fn fetch_acquire<'a>(
         &'a self,
         _info: AppInMemFetchKeys,
     ) -> DefaultResult<(AppInMemFetchedData, AppInMemDstoreLock<'a>), AppError> {
-        let guard = self.try_get_table().await;
-        let rs_a = Self::fetch_common(guard.borrow_mut(), _info)?;
-        Ok((rs_a, guard))
+        let guards = self.write_tables(_info.keys()).await?;
+        let rs_a = Self::fetch_common(&guards, _info);
+        let lock = AppInMemDstoreLock {
+            guards,
+            _store: PhantomData,
+        };
+        Ok((rs_a, lock))
     }
 
     async fn save(&self, _data: AppInMemUpdateData) -> DefaultResult<usize, AppError> {
-        let guard = self.try_get_table().await;
-        self.save_common(guard.borrow_mut(), _data)
+        let guards = self.write_tables(_data.keys()).await?;
+        self.save_common(guards, _data)
     }
     fn save_release(
         &self,
         _data: AppInMemUpdateData,
         lock: AppInMemDstoreLock,
     ) -> DefaultResult<usize, AppError> {
-        self.save_common(lock.borrow_mut(), _data)
+        self.save_common(lock.guards, _data)
     }
 
     async fn filter_keys(
@@ -226,14 +368,10 @@ impl AbstInMemoryDStore for AppInMemoryDStore {
         tbl_label: InnerTableLabel,
         op: &dyn AbsDStoreFilterKeyOp,
     ) -> DefaultResult<Vec<InnerKey>, AppError> {
-        let guard = self.try_get_table().await;
-        let _map = guard.borrow();
-        let unchecked_labels = vec![&tbl_label];
-        // the type `Ref` supports deref coercion , so compiler implicitly adds the dereference
-        // syntax `*` prior to `_map`, in other words `&_map` is automatically translated to `&*_map`
-        Self::_check_table_existence(&_map, unchecked_labels)?;
-        let table = _map.get(tbl_label.as_str()).unwrap();
+        let guards = self.read_tables([&tbl_label].into_iter()).await?;
+        let (_, table) = guards.first().unwrap();
         let out = table
+            .rows
             .iter()
             .filter_map(|(k, v)| {
                 if op.filter(k, v) {
@@ -245,4 +383,48 @@ impl AbstInMemoryDStore for AppInMemoryDStore {
             .collect();
         Ok(out)
     }
+
+    async fn create_index(
+        &self,
+        tbl_label: &str,
+        idx_label: &str,
+        col_idx: usize,
+        key_type: AppInMemIdxKeyType,
+    ) -> DefaultResult<(), AppError> {
+        let tbl_label = tbl_label.to_string();
+        let mut guards = self.write_tables([&tbl_label].into_iter()).await?;
+        let (_, table) = guards.first_mut().unwrap();
+        let InMemTable { rows, indexes } = &mut **table;
+        match indexes.entry(idx_label.to_string()) {
+            Entry::Occupied(e) => {
+                if e.get().same_definition(col_idx, key_type) {
+                    Ok(())
+                } else {
+                    Err(AppError {
+                        code: AppErrorCode::InvalidInput,
+                        detail: Some(format!("index-conflict, {}, {}", tbl_label, idx_label)),
+                    })
+                }
+            }
+            Entry::Vacant(e) => {
+                e.insert(InMemIndex::new(col_idx, key_type, rows));
+                Ok(())
+            }
+        }
+    }
+
+    async fn range_keys(
+        &self,
+        tbl_label: InnerTableLabel,
+        idx_label: &str,
+        range: AppInMemIdxRange,
+    ) -> DefaultResult<Vec<InnerKey>, AppError> {
+        let guards = self.read_tables([&tbl_label].into_iter()).await?;
+        let (_, table) = guards.first().unwrap();
+        let index = table.indexes.get(idx_label).ok_or(AppError {
+            code: AppErrorCode::InvalidInput,
+            detail: Some(format!("index-not-exist, {}, {}", tbl_label, idx_label)),
+        })?;
+        Ok(index.range(range))
+    }
 } // end of AppInMemoryDStore
@@ -0,0 +1,126 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ops::Bound;
+
+use chrono::{DateTime, Utc};
+
+use super::in_mem::{InnerKey, InnerRow, InnerTable};
+
+// Ordered secondary index of the in-memory data store. Each index refers to
+// single column of a table, the stringified column value is parsed to the key
+// type declared in the index, so the order of the keys is consistent with the
+// original data type (e.g. time with different timezone offsets).
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AppInMemIdxKeyType {
+    DateTime, // RFC3339 format
+    UInteger,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AppInMemIdxKey {
+    DateTime(DateTime<Utc>),
+    UInteger(u64),
+}
+
+pub type AppInMemIdxRange = (Bound<AppInMemIdxKey>, Bound<AppInMemIdxKey>);
+
+pub(super) struct InMemIndex {
+    col_idx: usize,
+    key_type: AppInMemIdxKeyType,
+    entries: BTreeMap<AppInMemIdxKey, BTreeSet<InnerKey>>,
+}
+
+impl InMemIndex {
+    pub(super) fn new(col_idx: usize, key_type: AppInMemIdxKeyType, rows: &InnerTable) -> Self {
+        let mut obj = Self {
+            col_idx,
+            key_type,
+            entries: BTreeMap::new(),
+        };
+        rows.iter().for_each(|(id, row)| obj.insert(id, row));
+        obj
+    }
+
+    pub(super) fn same_definition(&self, col_idx: usize, key_type: AppInMemIdxKeyType) -> bool {
+        (self.col_idx == col_idx) && (self.key_type == key_type)
+    }
+
+    // rows with empty or malformed value in the indexed column are skipped
+    fn key_of(&self, row: &InnerRow) -> Option<AppInMemIdxKey> {
+        let raw = row.get(self.col_idx)?.as_str();
+        match self.key_type {
+            AppInMemIdxKeyType::DateTime => DateTime::parse_from_rfc3339(raw)
+                .ok()
+                .map(|t| AppInMemIdxKey::DateTime(t.to_utc())),
+            AppInMemIdxKeyType::UInteger => raw.parse::<u64>().ok().map(AppInMemIdxKey::UInteger),
+        }
+    }
+
+    pub(super) fn insert(&mut self, id: &InnerKey, row: &InnerRow) {
+        if let Some(k) = self.key_of(row) {
+            self.entries.entry(k).or_default().insert(id.clone());
+        }
+    }
+
+    pub(super) fn remove(&mut self, id: &InnerKey, row: &InnerRow) {
+        if let Some(k) = self.key_of(row) {
+            if let Some(ids) = self.entries.get_mut(&k) {
+                ids.remove(id);
+                if ids.is_empty() {
+                    self.entries.remove(&k);
+                }
+            }
+        }
+    }
+
+    pub(super) fn range(&self, range: AppInMemIdxRange) -> Vec<InnerKey> {
+        if !Self::valid_range(&range) {
+            return Vec::new();
+        }
+        self.entries
+            .range(range)
+            .flat_map(|(_k, ids)| ids.iter().cloned())
+            .collect()
+    }
+
+    // `BTreeMap::range()` panics on start bound greater than end bound, or
+    // both bounds are excluded and equal, which should be treated as empty
+    // range instead.
+    fn valid_range(range: &AppInMemIdxRange) -> bool {
+        match range {
+            (Bound::Included(s), Bound::Included(e)) => s <= e,
+            (Bound::Included(s), Bound::Excluded(e))
+            | (Bound::Excluded(s), Bound::Included(e))
+            | (Bound::Excluded(s), Bound::Excluded(e)) => s < e,
+            _others => true,
+        }
+    }
+} // end of impl InMemIndex
+
+// rows of a table with all the indexes maintained on save / delete
+#[derive(Default)]
+pub(super) struct InMemTable {
+    pub(super) rows: InnerTable,
+    pub(super) indexes: HashMap<String, InMemIndex>,
+}
+
+impl InMemTable {
+    pub(super) fn upsert(&mut self, id: &InnerKey, row: &InnerRow) {
+        if let Some(old) = self.rows.insert(id.clone(), row.clone()) {
+            self.indexes
+                .values_mut()
+                .for_each(|idx| idx.remove(id, &old));
+        }
+        self.indexes
+            .values_mut()
+            .for_each(|idx| idx.insert(id, row));
+    }
+
+    pub(super) fn remove(&mut self, id: &InnerKey) {
+        if let Some(old) = self.rows.remove(id) {
+            self.indexes
+                .values_mut()
+                .for_each(|idx| idx.remove(id, &old));
+        }
+    }
+}
@@ -0,0 +1,257 @@
+use std::borrow::Cow;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::result::Result as DefaultResult;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use ecommerce_common::config::{AppInMemFsyncPolicy, AppInMemPersistCfg};
+use ecommerce_common::error::AppErrorCode;
+
+use super::in_mem::{AllTable, InnerTable, InnerTableLabel};
+use crate::error::AppError;
+
+// Durability layer of the in-memory data store. Every mutation is appended
+// to a write-ahead log as one JSON line before it is applied to the tables,
+// the whole data set is dumped to a snapshot file once number of logged
+// operations reaches the configured interval, then the log is truncated.
+//
+// Note this module performs blocking file I/O while the caller holds the
+// table locks, which is acceptable for the in-memory store since it is
+// meant for testing or small deployments. For the same reason there is no
+// background timer, the periodic fsync policy is checked only when next
+// record is appended, the last records before the store becomes idle stay
+// in page cache of the operating system until then.
+
+const WAL_FILENAME: &str = "wal.jsonl";
+const SNAPSHOT_FILENAME: &str = "snapshot.json";
+const SNAPSHOT_TMP_FILENAME: &str = "snapshot.json.tmp";
+
+// Replaying each record has to be idempotent, because the process might
+// crash after a snapshot is saved but before the log is truncated.
+// The data is borrowed from the caller when appending to the log, and owned
+// when recovered from the log.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "op")]
+pub(super) enum InMemWalRecord<'a> {
+    CreateTable { label: Cow<'a, InnerTableLabel> },
+    Save { data: Cow<'a, AllTable> },
+    Delete { info: Cow<'a, InnerTable> },
+}
+
+impl InMemWalRecord<'_> {
+    fn apply(self, tables: &mut AllTable) {
+        match self {
+            Self::CreateTable { label } => {
+                tables.entry(label.into_owned()).or_default();
+            }
+            Self::Save { data } => data.into_owned().into_iter().for_each(|(label, rows)| {
+                tables.entry(label).or_default().extend(rows);
+            }),
+            Self::Delete { info } => info.into_owned().into_iter().for_each(|(label, ids)| {
+                if let Some(table) = tables.get_mut(label.as_str()) {
+                    ids.iter().for_each(|id| {
+                        table.remove(id);
+                    });
+                }
+            }),
+        }
+    }
+}
+
+struct WalState {
+    file: File,
+    // length of the log file which ends with complete records
+    len: u64,
+    num_ops: u32,
+    last_synced: Instant,
+}
+
+pub(super) struct InMemPersistence {
+    dir: PathBuf,
+    fsync: AppInMemFsyncPolicy,
+    snapshot_interval: u32,
+    // always acquired after the table lock
+    state: Mutex<WalState>,
+}
+
+fn io_error(e: std::io::Error, path: &Path) -> AppError {
+    AppError {
+        code: AppErrorCode::IOerror(e.kind()),
+        detail: Some(format!("{}, {}", path.display(), e)),
+    }
+}
+
+fn corrupted_error(detail: String) -> AppError {
+    AppError {
+        code: AppErrorCode::DataCorruption,
+        detail: Some(detail),
+    }
+}
+
+impl InMemPersistence {
+    // load the latest snapshot, replay the write-ahead log on top of it,
+    // return the recovered tables along with the persistence handle
+    pub(super) fn try_open(
+        cfg: &AppInMemPersistCfg,
+        service_basepath: &str,
+    ) -> DefaultResult<(Self, AllTable), AppError> {
+        // `join()` discards the base path if the configured path is absolute
+        let dir = Path::new(service_basepath).join(cfg.path.as_str());
+        fs::create_dir_all(&dir).map_err(|e| io_error(e, &dir))?;
+        let mut tables = Self::load_snapshot(&dir)?;
+        let num_ops = Self::replay_wal(&dir, &mut tables)?;
+        let wal_path = dir.join(WAL_FILENAME);
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&wal_path)
+            .map_err(|e| io_error(e, &wal_path))?;
+        let len = file.metadata().map_err(|e| io_error(e, &wal_path))?.len();
+        let state = WalState {
+            file,
+            len,
+            num_ops,
+            last_synced: Instant::now(),
+        };
+        let obj = Self {
+            dir,
+            fsync: cfg.fsync,
+            snapshot_interval: cfg.snapshot_interval,
+            state: Mutex::new(state),
+        };
+        Ok((obj, tables))
+    }
+
+    fn load_snapshot(dir: &Path) -> DefaultResult<AllTable, AppError> {
+        let path = dir.join(SNAPSHOT_FILENAME);
+        match File::open(&path) {
+            Ok(f) => serde_json::from_reader::<_, AllTable>(BufReader::new(f))
+                .map_err(|e| corrupted_error(format!("{}, {}", path.display(), e))),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(AllTable::new()),
+            Err(e) => Err(io_error(e, &path)),
+        }
+    }
+
+    fn replay_wal(dir: &Path, tables: &mut AllTable) -> DefaultResult<u32, AppError> {
+        let path = dir.join(WAL_FILENAME);
+        let f = match File::open(&path) {
+            Ok(f) => f,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(io_error(e, &path)),
+        };
+        let mut lines = BufReader::new(f).lines().peekable();
+        let mut num_ops = 0u32;
+        while let Some(line) = lines.next() {
+            let line = line.map_err(|e| io_error(e, &path))?;
+            if line.is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<InMemWalRecord>(line.as_str()) {
+                Ok(rec) => {
+                    rec.apply(tables);
+                    num_ops += 1;
+                }
+                // the last record might be partially written when the
+                // process crashed, the operation is considered lost
+                Err(_e) if lines.peek().is_none() => break,
+                Err(e) => {
+                    let detail = format!("{}, record:{}, {}", path.display(), num_ops, e);
+                    return Err(corrupted_error(detail));
+                }
+            }
+        }
+        Ok(num_ops)
+    }
+
+    // caller should invoke this function before the mutation is applied to
+    // the tables, and skip the mutation if this function returns error.
+    pub(super) fn append(&self, record: &InMemWalRecord) -> DefaultResult<(), AppError> {
+        let wal_path = self.dir.join(WAL_FILENAME);
+        let mut line = serde_json::to_vec(record).map_err(|e| corrupted_error(e.to_string()))?;
+        line.push(b'\n');
+        let mut guard = self.lock_state()?;
+        let state = &mut *guard;
+        let need_sync = match self.fsync {
+            AppInMemFsyncPolicy::Always => true,
+            AppInMemFsyncPolicy::Periodic(millis) => {
+                state.last_synced.elapsed() >= Duration::from_millis(millis as u64)
+            }
+            AppInMemFsyncPolicy::Never => false,
+        };
+        let mut result = state.file.write_all(line.as_slice());
+        if result.is_ok() && need_sync {
+            result = state.file.sync_data();
+        }
+        if let Err(e) = result {
+            // discard the record, the mutation will not be applied so it
+            // should not be replayed on recovery either
+            let _ = state.file.set_len(state.len);
+            return Err(io_error(e, &wal_path));
+        }
+        state.len += line.len() as u64;
+        state.num_ops += 1;
+        if need_sync {
+            state.last_synced = Instant::now();
+        }
+        Ok(())
+    }
+
+    // caller should invoke this function after the logged mutation is applied
+    // and before the table locks are released. `snapshot_src` returns all the
+    // tables for taking snapshot, or `None` if they are not available at the
+    // moment (e.g. locked by others, whose mutations might be logged but not
+    // applied yet), in such case the snapshot will be postponed.
+    pub(super) fn try_compact<F>(&self, snapshot_src: F) -> DefaultResult<(), AppError>
+    where
+        F: FnOnce() -> Option<AllTable>,
+    {
+        let mut guard = self.lock_state()?;
+        let state = &mut *guard;
+        if state.num_ops < self.snapshot_interval {
+            return Ok(());
+        }
+        if let Some(tables) = snapshot_src() {
+            let wal_path = self.dir.join(WAL_FILENAME);
+            self.save_snapshot(&tables)?;
+            // all the logged operations are covered by the snapshot
+            state.file.set_len(0).map_err(|e| io_error(e, &wal_path))?;
+            state.len = 0;
+            state.num_ops = 0;
+            state.file.sync_all().map_err(|e| io_error(e, &wal_path))?;
+            state.last_synced = Instant::now();
+        }
+        Ok(())
+    }
+
+    fn lock_state(&self) -> DefaultResult<MutexGuard<'_, WalState>, AppError> {
+        self.state.lock().map_err(|e| AppError {
+            code: AppErrorCode::AcquireLockFailure,
+            detail: Some(e.to_string()),
+        })
+    }
+
+    // write to temporary file then rename it, so the previous snapshot is
+    // still intact if the process crashed in the middle of writing
+    fn save_snapshot(&self, tables: &AllTable) -> DefaultResult<(), AppError> {
+        let tmp_path = self.dir.join(SNAPSHOT_TMP_FILENAME);
+        let final_path = self.dir.join(SNAPSHOT_FILENAME);
+        let f = File::create(&tmp_path).map_err(|e| io_error(e, &tmp_path))?;
+        let mut writer = BufWriter::new(f);
+        serde_json::to_writer(&mut writer, tables)
+            .map_err(|e| corrupted_error(format!("{}, {}", tmp_path.display(), e)))?;
+        let f = writer
+            .into_inner()
+            .map_err(|e| io_error(e.into_error(), &tmp_path))?;
+        f.sync_all().map_err(|e| io_error(e, &tmp_path))?;
+        fs::rename(&tmp_path, &final_path).map_err(|e| io_error(e, &final_path))?;
+        // persist the rename itself, not supported on some platforms
+        if let Ok(d) = File::open(&self.dir) {
+            let _ = d.sync_all();
+        }
+        Ok(())
+    }
+} // end of impl InMemPersistence
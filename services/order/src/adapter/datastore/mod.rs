@@ -1,4 +1,8 @@
 mod in_mem;
+mod in_mem_index;
+mod in_mem_persist;
+#[cfg(feature = "postgres")]
+mod pg_db;
 #[cfg(feature = "mariadb")]
 mod sql_db;
 
@@ -7,8 +11,10 @@ use std::result::Result as DefaultResult;
 use std::sync::Arc;
 
 use ecommerce_common::confidentiality::AbstractConfidentiality;
-use ecommerce_common::config::{AppDataStoreCfg, AppDbServerCfg};
-#[cfg(not(feature = "mariadb"))]
+#[cfg(not(all(feature = "mariadb", feature = "postgres")))]
+use ecommerce_common::config::AppDbServerCfg;
+use ecommerce_common::config::{AppDataStoreCfg, AppDbServerType};
+#[cfg(not(all(feature = "mariadb", feature = "postgres")))]
 use ecommerce_common::error::AppErrorCode;
 use ecommerce_common::logging::{app_log_event, AppLogContext, AppLogLevel};
 
@@ -18,6 +24,9 @@ pub use in_mem::{
     AppInMemFetchKeys, AppInMemFetchedData, AppInMemFetchedSingleRow, AppInMemFetchedSingleTable,
     AppInMemUpdateData, AppInMemoryDStore,
 };
+pub use in_mem_index::{AppInMemIdxKey, AppInMemIdxKeyType, AppInMemIdxRange};
+#[cfg(feature = "postgres")]
+pub use pg_db::AppPostgreSqlStore;
 #[cfg(feature = "mariadb")]
 pub use sql_db::AppMariaDbStore;
 
@@ -43,32 +52,63 @@ impl AppMariaDbStore {
     }
 } // end of impl AppMariaDbStore
 
+#[cfg(not(feature = "postgres"))]
+pub struct AppPostgreSqlStore {}
+
+#[cfg(not(feature = "postgres"))]
+impl AppPostgreSqlStore {
+    pub fn try_build(
+        cfg: &AppDbServerCfg,
+        _confidential: Arc<Box<dyn AbstractConfidentiality>>,
+        _logctx: Arc<AppLogContext>,
+    ) -> DefaultResult<Self, AppError> {
+        let detail = format!(
+            "sql-db, type:{:?}, alias:{}",
+            cfg.srv_type,
+            cfg.alias.as_str()
+        );
+        Err(AppError {
+            code: AppErrorCode::FeatureDisabled,
+            detail: Some(detail),
+        })
+    }
+} // end of impl AppPostgreSqlStore
+
 #[allow(clippy::type_complexity)]
 pub(crate) fn build_context(
     logctx: Arc<AppLogContext>,
     cfg: &Vec<AppDataStoreCfg>,
+    service_basepath: &str,
     confidential: Arc<Box<dyn AbstractConfidentiality>>,
 ) -> DefaultResult<
     (
         Option<Box<dyn AbstInMemoryDStore>>,
         Option<Vec<AppMariaDbStore>>,
+        Option<Vec<AppPostgreSqlStore>>,
     ),
     Vec<AppError>,
 > {
     let mut inmem = None;
     let mut sqldb = None;
+    let mut pgdb = None;
     let mut errors = Vec::new();
     for c in cfg {
         match c {
             AppDataStoreCfg::InMemory(d) => {
-                let item: Box<dyn AbstInMemoryDStore> = Box::new(AppInMemoryDStore::new(d));
-                inmem = Some(item);
-            }
-            AppDataStoreCfg::DbServer(d) => {
-                if sqldb.is_none() {
-                    sqldb = Some(Vec::new());
+                match AppInMemoryDStore::try_build(d, service_basepath) {
+                    Ok(v) => {
+                        let item: Box<dyn AbstInMemoryDStore> = Box::new(v);
+                        inmem = Some(item);
+                    }
+                    Err(e) => {
+                        app_log_event!(logctx, AppLogLevel::ERROR, "{:?}", e);
+                        errors.push(e);
+                    }
                 }
-                if let Some(lst) = &mut sqldb {
+            }
+            AppDataStoreCfg::DbServer(d) => match d.srv_type {
+                AppDbServerType::MariaDB => {
+                    let lst = sqldb.get_or_insert_with(Vec::new);
                     match AppMariaDbStore::try_build(d, confidential.clone(), logctx.clone()) {
                         Ok(item) => {
                             lst.push(item);
@@ -79,11 +119,23 @@ pub(crate) fn build_context(
                         }
                     }
                 }
-            }
+                AppDbServerType::PostgreSQL => {
+                    let lst = pgdb.get_or_insert_with(Vec::new);
+                    match AppPostgreSqlStore::try_build(d, confidential.clone(), logctx.clone()) {
+                        Ok(item) => {
+                            lst.push(item);
+                        }
+                        Err(e) => {
+                            app_log_event!(logctx, AppLogLevel::ERROR, "{:?}", e);
+                            errors.push(e);
+                        }
+                    }
+                }
+            },
         }
     }
     if errors.is_empty() {
-        Ok((inmem, sqldb))
+        Ok((inmem, sqldb, pgdb))
     } else {
         Err(errors)
     }
@@ -0,0 +1,188 @@
+use std::boxed::Box;
+use std::env;
+use std::result::Result as DefaultResult;
+use std::sync::Arc;
+
+use serde::Deserialize;
+use std::time::Duration;
+
+use chrono::Local;
+use deadpool::managed::{
+    Manager, Metrics, Object, Pool, PoolConfig, QueueMode, RecycleError, Timeouts,
+};
+use deadpool::Runtime;
+use sqlx::error::Error as SqlxError;
+use sqlx::postgres::{PgConnectOptions, PgConnection};
+use sqlx::{ConnectOptions, Connection}; //traits for generic connection methods
+
+use ecommerce_common::confidentiality::AbstractConfidentiality;
+use ecommerce_common::config::{AppDbServerCfg, AppDbServerType};
+use ecommerce_common::error::AppErrorCode;
+use ecommerce_common::logging::{app_log_event, AppLogContext, AppLogLevel};
+
+use crate::error::AppError;
+
+#[allow(non_snake_case)]
+#[derive(Deserialize)]
+struct DbSecret {
+    USER: String,
+    PASSWORD: String,
+}
+
+struct PgManager {
+    conn_opts: PgConnectOptions,
+    logctx: Arc<AppLogContext>,
+    idle_timeout: Duration,
+}
+
+pub struct AppPostgreSqlStore {
+    pub alias: String,
+    pool: Pool<PgManager>,
+    logctx: Arc<AppLogContext>,
+}
+
+impl Manager for PgManager {
+    type Type = PgConnection;
+    type Error = SqlxError;
+
+    async fn create(&self) -> DefaultResult<Self::Type, Self::Error> {
+        self.conn_opts.connect().await
+    }
+    async fn recycle(
+        &self,
+        obj: &mut Self::Type,
+        metrics: &Metrics,
+    ) -> DefaultResult<(), RecycleError<SqlxError>> {
+        let last_time_used = metrics.last_used();
+        if last_time_used > self.idle_timeout {
+            let msg = std::borrow::Cow::Owned("idle-timed-out".to_string());
+            return Err(RecycleError::Message(msg));
+        }
+        let t0 = Local::now().fixed_offset();
+        let result = obj.ping().await;
+        let t1 = Local::now().fixed_offset();
+        let lctx = self.logctx.as_ref();
+        if let Err(e) = &result {
+            let td = t1 - t0;
+            app_log_event!(lctx, AppLogLevel::WARNING, "{:?}, td: {:?}", e, td);
+        }
+        result.map_err(RecycleError::Backend)
+    }
+}
+
+impl std::fmt::Debug for PgManager {
+    fn fmt(&self, _f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Ok(())
+    }
+}
+
+impl AppPostgreSqlStore {
+    pub fn try_build(
+        cfg: &AppDbServerCfg,
+        confidential: Arc<Box<dyn AbstractConfidentiality>>,
+        logctx: Arc<AppLogContext>,
+    ) -> DefaultResult<Self, AppError> {
+        if !matches!(cfg.srv_type, AppDbServerType::PostgreSQL) {
+            let detail = format!("db-cfg-server-type: {:?}", cfg.srv_type);
+            return Err(AppError {
+                code: AppErrorCode::InvalidInput,
+                detail: Some(detail),
+            });
+        }
+        let (d_host, d_port): (String, u16) = {
+            const EXPECTED_LABELS: [&str; 2] = ["DB_HOST", "DB_PORT"];
+            let mut host: Option<String> = None;
+            let mut port: Option<u16> = None;
+            for (k, v) in env::vars().filter(|(k, _v)| EXPECTED_LABELS.contains(&k.as_str())) {
+                match k.as_str() {
+                    "DB_HOST" => host = Some(v),
+                    "DB_PORT" => {
+                        port = Some(v.parse().map_err(|e| AppError {
+                            code: AppErrorCode::InvalidInput,
+                            detail: Some(format!(
+                                "db-cfg-port-envvar-error: {:?}, value: {}",
+                                e, v
+                            )),
+                        })?)
+                    }
+                    _ => {}
+                }
+            }
+            let d_host = host.ok_or_else(|| AppError {
+                code: AppErrorCode::InvalidInput,
+                detail: Some("DB_HOST environment variable not set".to_string()),
+            })?;
+            let d_port = port.ok_or_else(|| AppError {
+                code: AppErrorCode::InvalidInput,
+                detail: Some("DB_PORT environment variable not set".to_string()),
+            })?;
+            (d_host, d_port)
+        };
+        let serial = confidential.try_get_payload(cfg.confidentiality_path.as_str())?;
+        let conn_opts = match serde_json::from_str::<DbSecret>(serial.as_str()) {
+            Ok(s) => PgConnectOptions::new()
+                .host(d_host.as_str())
+                .port(d_port)
+                .username(s.USER.as_str())
+                .password(s.PASSWORD.as_str())
+                .database(cfg.db_name.as_str()),
+            Err(e) => {
+                let detail = e.to_string() + ", secret-parsing-error, source: AppPostgreSqlStore";
+                return Err(AppError {
+                    code: AppErrorCode::InvalidJsonFormat,
+                    detail: Some(detail),
+                });
+            }
+        };
+
+        let mgr = PgManager {
+            conn_opts,
+            idle_timeout: Duration::new(cfg.idle_timeout_secs as u64, 0),
+            logctx: logctx.clone(),
+        };
+        let timeouts = Timeouts {
+            wait: Some(Duration::new(cfg.acquire_timeout_secs as u64, 0)), // wait for internal slots available
+            create: Some(Duration::new(cfg.acquire_timeout_secs as u64, 0)),
+            recycle: Some(Duration::new(5u64, 0)),
+        };
+        let queue_mode = QueueMode::Fifo;
+        let poolcfg = PoolConfig {
+            timeouts,
+            queue_mode,
+            max_size: cfg.max_conns as usize,
+        };
+        let pool = Pool::builder(mgr)
+            .config(poolcfg)
+            .runtime(Runtime::Tokio1)
+            .build()
+            .map_err(|e| AppError {
+                code: AppErrorCode::MissingDataStore,
+                detail: Some(e.to_string()),
+            })?;
+        Ok(Self {
+            pool,
+            logctx,
+            alias: cfg.alias.clone(),
+        })
+    } // end of fn try-build
+
+    pub async fn acquire(
+        &self,
+    ) -> DefaultResult<Object<impl Manager<Type = PgConnection, Error = SqlxError>>, AppError> {
+        // Note
+        // due to unknown timeout issue in `sqlx` pool,  as discussed in the github repo,
+        // https://github.com/launchbadge/sqlx/discussions/3232 ,
+        // this application switches to `deadpool` for connection management
+        let result = self.pool.get().await;
+        let lctx = &self.logctx;
+        let status = &self.pool.status();
+        app_log_event!(lctx, AppLogLevel::DEBUG, "pool:{:?}", status,);
+        result.map_err(|e| {
+            app_log_event!(lctx, AppLogLevel::ERROR, "pool:{:?}, e:{:?}", status, e);
+            AppError {
+                code: AppErrorCode::DatabaseServerBusy,
+                detail: Some(e.to_string()),
+            }
+        })
+    }
+} // end of impl AppPostgreSqlStore
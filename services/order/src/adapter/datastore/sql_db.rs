@@ -16,7 +16,7 @@ use sqlx::mysql::{MySqlConnectOptions, MySqlConnection};
 use sqlx::{ConnectOptions, Connection}; //traits for generic connection methods
 
 use ecommerce_common::confidentiality::AbstractConfidentiality;
-use ecommerce_common::config::{AppDbServerCfg, AppDbServerType};
+use ecommerce_common::config::{AppDbServerCfg, AppDbServerRole, AppDbServerType};
 use ecommerce_common::error::AppErrorCode;
 use ecommerce_common::logging::{app_log_event, AppLogContext, AppLogLevel};
 
@@ -27,6 +27,9 @@ use crate::error::AppError;
 struct DbSecret {
     USER: String,
     PASSWORD: String,
+    // optional, overrides the environment variables `DB_HOST` and `DB_PORT`
+    HOST: Option<String>,
+    PORT: Option<u16>,
 }
 
 struct MariaDbManager {
@@ -37,6 +40,7 @@ struct MariaDbManager {
 
 pub struct AppMariaDbStore {
     pub alias: String,
+    pub role: AppDbServerRole,
     pool: Pool<MariaDbManager>,
     logctx: Arc<AppLogContext>,
 }
@@ -89,6 +93,16 @@ impl AppMariaDbStore {
                 detail: Some(detail),
             });
         }
+        let secret = {
+            let serial = confidential.try_get_payload(cfg.confidentiality_path.as_str())?;
+            serde_json::from_str::<DbSecret>(serial.as_str()).map_err(|e| {
+                let detail = e.to_string() + ", secret-parsing-error, source: AppMariaDbStore";
+                AppError {
+                    code: AppErrorCode::InvalidJsonFormat,
+                    detail: Some(detail),
+                }
+            })?
+        };
         let (d_host, d_port): (String, u16) = {
             const EXPECTED_LABELS: [&str; 2] = ["DB_HOST", "DB_PORT"];
             let mut host: Option<String> = None;
@@ -108,32 +122,23 @@ impl AppMariaDbStore {
                     _ => {}
                 }
             }
-            let d_host = host.ok_or_else(|| AppError {
+            // each replica could run in different server, which is specified in the secret
+            let d_host = secret.HOST.clone().or(host).ok_or_else(|| AppError {
                 code: AppErrorCode::InvalidInput,
                 detail: Some("DB_HOST environment variable not set".to_string()),
             })?;
-            let d_port = port.ok_or_else(|| AppError {
+            let d_port = secret.PORT.or(port).ok_or_else(|| AppError {
                 code: AppErrorCode::InvalidInput,
                 detail: Some("DB_PORT environment variable not set".to_string()),
             })?;
             (d_host, d_port)
         };
-        let serial = confidential.try_get_payload(cfg.confidentiality_path.as_str())?;
-        let conn_opts = match serde_json::from_str::<DbSecret>(serial.as_str()) {
-            Ok(s) => MySqlConnectOptions::new()
-                .host(d_host.as_str())
-                .port(d_port)
-                .username(s.USER.as_str())
-                .password(s.PASSWORD.as_str())
-                .database(cfg.db_name.as_str()),
-            Err(e) => {
-                let detail = e.to_string() + ", secret-parsing-error, source: AppMariaDbStore";
-                return Err(AppError {
-                    code: AppErrorCode::InvalidJsonFormat,
-                    detail: Some(detail),
-                });
-            }
-        };
+        let conn_opts = MySqlConnectOptions::new()
+            .host(d_host.as_str())
+            .port(d_port)
+            .username(secret.USER.as_str())
+            .password(secret.PASSWORD.as_str())
+            .database(cfg.db_name.as_str());
 
         let mgr = MariaDbManager {
             conn_opts,
@@ -163,6 +168,10 @@ impl AppMariaDbStore {
             pool,
             logctx,
             alias: cfg.alias.clone(),
+            role: cfg
+                .role
+                .clone()
+                .unwrap_or(AppDbServerRole::Primary { order_shard: false }),
         })
     } // end of fn try-build
 
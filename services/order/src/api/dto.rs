@@ -9,7 +9,7 @@ pub struct ShippingOptionDto {
     pub method: ShippingMethod,
 }
 
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, Clone, PartialEq)]
 pub enum ShippingMethod {
     UPS,
     FedEx,
@@ -46,7 +46,16 @@ pub struct ShippingDto {
     pub option: Vec<ShippingOptionDto>,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+// quantity of an order line sent in a shipment, the seller ID is kept in
+// the shipment which contains the line
+#[derive(Deserialize, Serialize)]
+pub struct ShipmentLineDto {
+    pub product_id: u64,
+    pub attr_set_seq: u16,
+    pub qty: u32,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(untagged)]
 pub enum ProdAttrValueDto {
     Int(i32),
@@ -4,9 +4,9 @@ use chrono::offset::FixedOffset;
 use chrono::DateTime;
 use serde::{Deserialize, Serialize};
 
-use ecommerce_common::api::dto::CurrencyDto;
+use ecommerce_common::api::dto::{CountryCode, CurrencyDto};
 
-use crate::api::dto::{ProdAttrValueDto, ShippingDto};
+use crate::api::dto::{ProdAttrValueDto, ShipmentLineDto, ShippingDto, ShippingMethod};
 
 #[derive(Deserialize)]
 pub struct ProductPriceDeleteDto {
@@ -128,3 +128,61 @@ pub struct StockReturnErrorDto {
     pub product_id: u64,
     pub reason: StockReturnErrorReason,
 }
+
+#[derive(Deserialize)]
+pub struct ShipmentCreateReqDto {
+    pub order_id: String,
+    pub seller_id: u32,
+    pub carrier: ShippingMethod,
+    pub tracking_num: String,
+    pub lines: Vec<ShipmentLineDto>,
+}
+
+#[derive(Serialize, Debug)]
+pub enum ShipmentErrorReason {
+    InvalidCarrier,
+    InvalidTrackingNum,
+    DuplicateTrackingNum,
+    EmptyLines,
+}
+
+#[derive(Serialize, Debug)]
+pub enum ShipmentLineErrorReason {
+    NotExist,
+    InvalidQuantity,
+    DuplicateLine,
+    QtyLimitExceed,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ShipmentLineErrorDto {
+    pub product_id: u64,
+    pub attr_set_seq: u16,
+    pub reason: ShipmentLineErrorReason,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ShipmentErrorDto {
+    pub reason: Option<ShipmentErrorReason>,
+    pub lines: Vec<ShipmentLineErrorDto>,
+}
+
+#[derive(Deserialize)]
+pub struct StoreShippingFeeDto {
+    pub country: CountryCode, // destination
+    pub amount: u32,          // smallest unit in seller's currency
+}
+
+#[derive(Deserialize)]
+pub struct StoreShippingMethodDto {
+    pub method: ShippingMethod,
+    pub fees: Vec<StoreShippingFeeDto>,
+}
+
+// the storefront service sends all the shipping methods supported by a
+// store at a time, which replaces the methods saved previously
+#[derive(Deserialize)]
+pub struct StoreShippingSyncDto {
+    pub store_id: u32,
+    pub methods: Vec<StoreShippingMethodDto>,
+}
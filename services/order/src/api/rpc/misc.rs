@@ -2,12 +2,14 @@ use std::vec::Vec;
 
 use ecommerce_common::logging::{app_log_event, AppLogLevel};
 
-use crate::repository::{app_repo_currency, app_repo_product_price};
+use crate::repository::{app_repo_currency, app_repo_product_price, app_repo_store_shipping};
 use crate::rpc::AppRpcClientReqProperty;
-use crate::usecase::{CurrencyRateRefreshUseCase, EditProductPriceUseCase};
+use crate::usecase::{
+    CurrencyRateRefreshUseCase, EditProductPriceUseCase, EditStoreShippingUseCase,
+};
 use crate::AppSharedState;
 
-use super::dto::ProductPriceDto;
+use super::dto::{ProductPriceDto, StoreShippingSyncDto};
 use super::{build_error_response, PyCelery};
 
 pub(super) async fn store_products(
@@ -77,3 +79,38 @@ pub(super) async fn currency_refresh(
     };
     resp.to_string().into_bytes()
 }
+
+pub(super) async fn store_shipping(
+    req: AppRpcClientReqProperty,
+    shr_state: AppSharedState,
+) -> Vec<u8> {
+    let ds = shr_state.datastore();
+    let logctx = shr_state.log_context().clone();
+    let task_id = match PyCelery::get_task_id(&req) {
+        Ok(t) => t,
+        Err(e) => {
+            return build_error_response(e).to_string().into_bytes();
+        }
+    };
+    let repo = match app_repo_store_shipping(ds).await {
+        Ok(r) => r,
+        Err(e) => {
+            return PyCelery::error_response(task_id, e)
+                .to_string()
+                .into_bytes();
+        }
+    };
+    let result = PyCelery::deserialize_req::<Vec<String>, StoreShippingSyncDto>(&req.msgbody);
+    let s = match result {
+        Ok((_arg, data)) => {
+            let result = EditStoreShippingUseCase::execute(repo, data, logctx).await;
+            if let Err(e) = result {
+                PyCelery::error_response(task_id, e)
+            } else {
+                PyCelery::build_response(task_id.as_str(), "SUCCESS")
+            }
+        }
+        Err(e) => PyCelery::error_response(task_id, e),
+    };
+    s.to_string().into_bytes()
+}
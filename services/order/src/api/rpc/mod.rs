@@ -41,6 +41,7 @@ pub async fn route_to_handler(
         RpcConst::EDIT_PRODUCT_PRICE => Ok(misc::store_products(req, shr_state).await),
         RpcConst::STOCK_LEVEL_EDIT => Ok(stock_level::inventory_edit(req, shr_state).await),
         RpcConst::CURRENCY_RATE_REFRESH => Ok(misc::currency_refresh(req, shr_state).await),
+        RpcConst::STORE_SHIPPING_SYNC => Ok(misc::store_shipping(req, shr_state).await),
         RpcConst::STOCK_RETURN_CANCELLED => {
             Ok(stock_level::inventory_return_cancelled(req, shr_state).await)
         }
@@ -59,6 +60,7 @@ pub async fn route_to_handler(
         RpcConst::ORDER_RSV_DISCARD_UNPAID => {
             Ok(order_status::discard_unpaid_lines(req, shr_state).await)
         }
+        RpcConst::ORDER_SHIPMENT_CREATE => Ok(order_status::create_shipment(req, shr_state).await),
         _others => {
             let err = AppError {
                 code: AppErrorCode::NotImplemented,
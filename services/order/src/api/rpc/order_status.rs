@@ -11,13 +11,13 @@ use ecommerce_common::api::rpc::dto::{
 use ecommerce_common::error::AppErrorCode;
 use ecommerce_common::logging::{app_log_event, AppLogLevel};
 
-use crate::api::rpc::dto::OrderReplicaInventoryDto;
+use crate::api::rpc::dto::{OrderReplicaInventoryDto, ShipmentCreateReqDto, ShipmentErrorDto};
 use crate::error::AppError;
 use crate::repository::{app_repo_order, app_repo_order_return};
 use crate::rpc::AppRpcClientReqProperty;
 use crate::usecase::{
     OrderDiscardUnpaidItemsUseCase, OrderPaymentUpdateUseCase, OrderReplicaInventoryUseCase,
-    OrderReplicaPaymentUseCase, OrderReplicaRefundUseCase,
+    OrderReplicaPaymentUseCase, OrderReplicaRefundUseCase, OrderShipmentCreateUseCase,
 };
 use crate::AppSharedState;
 
@@ -197,3 +197,30 @@ pub(super) async fn discard_unpaid_lines(
     };
     resp.to_string().into_bytes()
 }
+
+pub(super) async fn create_shipment(
+    req: AppRpcClientReqProperty,
+    shr_state: AppSharedState,
+) -> Vec<u8> {
+    let resp = match common_setup!(
+        ShipmentCreateReqDto,
+        shr_state,
+        app_repo_order,
+        req.msgbody.as_slice()
+    ) {
+        Ok((v, repo)) => {
+            let uc = OrderShipmentCreateUseCase { repo };
+            match uc.execute(v).await {
+                Ok(uc_resp) => {
+                    // the result is empty on success, or contains the reasons
+                    // why the shipment is rejected
+                    let r = rpc::base_response::<ShipmentErrorDto>(2, "SUCCESS", uc_resp.err());
+                    r.unwrap()
+                }
+                Err(e) => build_error_response(e),
+            }
+        }
+        Err(e) => build_error_response(e),
+    };
+    resp.to_string().into_bytes()
+}
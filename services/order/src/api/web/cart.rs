@@ -1,23 +1,40 @@
 use axum::debug_handler;
-use axum::extract::{Json as ExtractJson, Path as ExtractPath, State as ExtractState};
+use axum::extract::{
+    Json as ExtractJson, Path as ExtractPath, Query as ExtractQuery, State as ExtractState,
+};
 use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
 use axum::response::IntoResponse;
+use serde::Deserialize;
 
+use ecommerce_common::api::dto::CurrencyDto;
 use ecommerce_common::logging::{app_log_event, AppLogLevel};
 
 use crate::constant::HTTP_CONTENT_TYPE_JSON;
-use crate::repository::app_repo_cart;
+use crate::repository::{
+    app_repo_cart, app_repo_currency, app_repo_order, app_repo_product_policy,
+    app_repo_product_price, app_repo_store_shipping,
+};
 use crate::usecase::{
+    CheckoutCartUsKsResult, CheckoutCartUseCase, CreateOrderUsKsErr, CreateOrderUseCase,
     DiscardCartUsKsResult, DiscardCartUseCase, ModifyCartLineUseCase, ModifyCartUsKsResult,
-    RetrieveCartUsKsResult, RetrieveCartUseCase,
+    PreviewCartUsKsResult, PreviewCartUseCase, RetrieveCartShareUsKsResult,
+    RetrieveCartShareUseCase, RetrieveCartUsKsResult, RetrieveCartUseCase, ShareCartUsKsResult,
+    ShareCartUseCase,
 };
 use crate::{AppAuthedClaim, AppSharedState};
 
-use super::dto::CartDto;
+use super::dto::{CartCheckoutReqDto, CartDto, CartShareDto};
+
+#[derive(Deserialize)]
+pub(super) struct CartOwnerQueryParam {
+    // access a cart shared by another user, default to the authenticated user
+    pub owner: Option<u32>,
+}
 
 #[debug_handler(state=AppSharedState)]
 pub(super) async fn modify_lines(
     ExtractPath(seq_num): ExtractPath<u8>,
+    ExtractQuery(q_params): ExtractQuery<CartOwnerQueryParam>,
     authed_usr: AppAuthedClaim,
     ExtractState(appstate): ExtractState<AppSharedState>,
     ExtractJson(req_body): ExtractJson<CartDto>,
@@ -35,14 +52,16 @@ pub(super) async fn modify_lines(
             return (StatusCode::INTERNAL_SERVER_ERROR, hdr_map, default_body);
         }
     };
+    let owner = q_params.owner.unwrap_or(authed_usr.profile);
     let uc = ModifyCartLineUseCase {
         repo,
         authed_usr,
         log_ctx: logctx.clone(),
     };
-    let (status, resp_body) = match uc.execute(seq_num, req_body).await {
+    let (status, resp_body) = match uc.execute(owner, seq_num, req_body).await {
         ModifyCartUsKsResult::Success => (StatusCode::OK, default_body),
         ModifyCartUsKsResult::NotFound => (StatusCode::NOT_FOUND, default_body),
+        ModifyCartUsKsResult::PermissionDenied => (StatusCode::FORBIDDEN, default_body),
         ModifyCartUsKsResult::QuotaExceed(e) => {
             (StatusCode::BAD_REQUEST, serde_json::to_string(&e).unwrap())
         }
@@ -57,6 +76,7 @@ pub(super) async fn modify_lines(
 #[debug_handler(state=AppSharedState)]
 pub(super) async fn discard(
     ExtractPath(seq_num): ExtractPath<u8>,
+    ExtractQuery(q_params): ExtractQuery<CartOwnerQueryParam>,
     authed_usr: AppAuthedClaim,
     ExtractState(appstate): ExtractState<AppSharedState>,
 ) -> impl IntoResponse {
@@ -68,10 +88,12 @@ pub(super) async fn discard(
             return (StatusCode::INTERNAL_SERVER_ERROR, HeaderMap::new());
         }
     };
+    let owner = q_params.owner.unwrap_or(authed_usr.profile);
     let uc = DiscardCartUseCase { repo, authed_usr };
-    let status = match uc.execute(seq_num).await {
+    let status = match uc.execute(owner, seq_num).await {
         DiscardCartUsKsResult::Success => StatusCode::NO_CONTENT,
         DiscardCartUsKsResult::NotFound => StatusCode::GONE,
+        DiscardCartUsKsResult::PermissionDenied => StatusCode::FORBIDDEN,
         DiscardCartUsKsResult::ServerError(e) => {
             app_log_event!(logctx, AppLogLevel::ERROR, "{:?}", e);
             StatusCode::INTERNAL_SERVER_ERROR
@@ -83,6 +105,7 @@ pub(super) async fn discard(
 #[debug_handler(state=AppSharedState)]
 pub(super) async fn retrieve(
     ExtractPath(seq_num): ExtractPath<u8>,
+    ExtractQuery(q_params): ExtractQuery<CartOwnerQueryParam>,
     authed_usr: AppAuthedClaim,
     ExtractState(appstate): ExtractState<AppSharedState>,
 ) -> impl IntoResponse {
@@ -102,10 +125,12 @@ pub(super) async fn retrieve(
             return (StatusCode::INTERNAL_SERVER_ERROR, hdr_map, default_body);
         }
     };
+    let owner = q_params.owner.unwrap_or(authed_usr.profile);
     let uc = RetrieveCartUseCase { repo, authed_usr };
-    let (status, resp_body) = match uc.execute(seq_num).await {
+    let (status, resp_body) = match uc.execute(owner, seq_num).await {
         RetrieveCartUsKsResult::Success(v) => (StatusCode::OK, serde_json::to_string(&v).unwrap()),
         RetrieveCartUsKsResult::NotFound => (StatusCode::NOT_FOUND, default_body),
+        RetrieveCartUsKsResult::PermissionDenied => (StatusCode::FORBIDDEN, default_body),
         RetrieveCartUsKsResult::ServerError(e) => {
             app_log_event!(logctx, AppLogLevel::ERROR, "{:?}", e);
             (StatusCode::INTERNAL_SERVER_ERROR, default_body)
@@ -113,3 +138,202 @@ pub(super) async fn retrieve(
     };
     (status, hdr_map, resp_body)
 }
+
+#[debug_handler(state=AppSharedState)]
+pub(super) async fn share(
+    ExtractPath(seq_num): ExtractPath<u8>,
+    authed_usr: AppAuthedClaim,
+    ExtractState(appstate): ExtractState<AppSharedState>,
+    ExtractJson(req_body): ExtractJson<CartShareDto>,
+) -> impl IntoResponse {
+    let resp_ctype_val = HeaderValue::from_str(HTTP_CONTENT_TYPE_JSON).unwrap();
+    let mut hdr_map = HeaderMap::new();
+    hdr_map.insert(header::CONTENT_TYPE, resp_ctype_val);
+    let default_body = "{}".to_string();
+    let logctx = appstate.log_context().clone();
+
+    let repo = match app_repo_cart(appstate.datastore()).await {
+        Ok(v) => v,
+        Err(e) => {
+            app_log_event!(logctx, AppLogLevel::ERROR, "{:?}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, hdr_map, default_body);
+        }
+    };
+    let uc = ShareCartUseCase { repo, authed_usr };
+    let (status, resp_body) = match uc.execute(seq_num, req_body).await {
+        ShareCartUsKsResult::Success => (StatusCode::OK, default_body),
+        ShareCartUsKsResult::NotFound => (StatusCode::NOT_FOUND, default_body),
+        ShareCartUsKsResult::InvalidReq(e) => {
+            (StatusCode::BAD_REQUEST, serde_json::to_string(&e).unwrap())
+        }
+        ShareCartUsKsResult::QuotaExceed(e) => {
+            (StatusCode::FORBIDDEN, serde_json::to_string(&e).unwrap())
+        }
+        ShareCartUsKsResult::ServerError(e) => {
+            app_log_event!(logctx, AppLogLevel::ERROR, "{:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, default_body)
+        }
+    };
+    (status, hdr_map, resp_body)
+} // end of fn share
+
+#[debug_handler(state=AppSharedState)]
+pub(super) async fn retrieve_share(
+    ExtractPath(seq_num): ExtractPath<u8>,
+    authed_usr: AppAuthedClaim,
+    ExtractState(appstate): ExtractState<AppSharedState>,
+) -> impl IntoResponse {
+    let resp_ctype_val = HeaderValue::from_str(HTTP_CONTENT_TYPE_JSON).unwrap();
+    let mut hdr_map = HeaderMap::new();
+    hdr_map.insert(header::CONTENT_TYPE, resp_ctype_val);
+    let default_body = "{}".to_string();
+    let logctx = appstate.log_context().clone();
+
+    let repo = match app_repo_cart(appstate.datastore()).await {
+        Ok(v) => v,
+        Err(e) => {
+            app_log_event!(logctx, AppLogLevel::ERROR, "{:?}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, hdr_map, default_body);
+        }
+    };
+    let uc = RetrieveCartShareUseCase { repo, authed_usr };
+    let (status, resp_body) = match uc.execute(seq_num).await {
+        RetrieveCartShareUsKsResult::Success(v) => {
+            (StatusCode::OK, serde_json::to_string(&v).unwrap())
+        }
+        RetrieveCartShareUsKsResult::NotFound => (StatusCode::NOT_FOUND, default_body),
+        RetrieveCartShareUsKsResult::ServerError(e) => {
+            app_log_event!(logctx, AppLogLevel::ERROR, "{:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, default_body)
+        }
+    };
+    (status, hdr_map, resp_body)
+} // end of fn retrieve_share
+
+#[derive(Deserialize)]
+pub(super) struct PreviewCartQueryParam {
+    // currency in buyer's local region
+    pub currency: CurrencyDto,
+}
+
+#[debug_handler(state=AppSharedState)]
+pub(super) async fn preview(
+    ExtractPath(seq_num): ExtractPath<u8>,
+    ExtractQuery(q_params): ExtractQuery<PreviewCartQueryParam>,
+    authed_usr: AppAuthedClaim,
+    ExtractState(appstate): ExtractState<AppSharedState>,
+) -> impl IntoResponse {
+    let resp_ctype_val = HeaderValue::from_str(HTTP_CONTENT_TYPE_JSON).unwrap();
+    let mut hdr_map = HeaderMap::new();
+    hdr_map.insert(header::CONTENT_TYPE, resp_ctype_val);
+    let default_body = "{}".to_string();
+    let logctx = appstate.log_context().clone();
+
+    let ds = appstate.datastore();
+    let results = (
+        app_repo_cart(ds.clone()).await,
+        app_repo_currency(ds.clone()).await,
+        app_repo_order(ds.clone()).await,
+        app_repo_product_price(ds.clone()).await,
+        app_repo_product_policy(ds).await,
+    );
+    let uc = match results {
+        (Ok(repo_cart), Ok(repo_currex), Ok(repo_order), Ok(repo_price), Ok(repo_policy)) => {
+            PreviewCartUseCase {
+                repo_cart,
+                repo_currex,
+                repo_price,
+                repo_policy,
+                repo_stock: repo_order.stock(),
+                authed_usr,
+            }
+        }
+        (r0, r1, r2, r3, r4) => {
+            let errmsgs = [r0.err(), r1.err(), r2.err(), r3.err(), r4.err()]
+                .into_iter()
+                .flatten()
+                .map(|e| e.to_string())
+                .collect::<Vec<_>>();
+            app_log_event!(logctx, AppLogLevel::ERROR, "{:?}", errmsgs);
+            return (StatusCode::INTERNAL_SERVER_ERROR, hdr_map, default_body);
+        }
+    };
+    let (status, resp_body) = match uc.execute(seq_num, q_params.currency).await {
+        PreviewCartUsKsResult::Success(v) => (StatusCode::OK, serde_json::to_string(&v).unwrap()),
+        PreviewCartUsKsResult::NotFound => (StatusCode::NOT_FOUND, default_body),
+        PreviewCartUsKsResult::ServerError(e) => {
+            app_log_event!(logctx, AppLogLevel::ERROR, "{:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, default_body)
+        }
+    };
+    (status, hdr_map, resp_body)
+} // end of fn preview
+
+#[debug_handler(state=AppSharedState)]
+pub(super) async fn checkout(
+    ExtractPath(seq_num): ExtractPath<u8>,
+    authed_usr: AppAuthedClaim,
+    ExtractState(appstate): ExtractState<AppSharedState>,
+    ExtractJson(req_body): ExtractJson<CartCheckoutReqDto>,
+) -> impl IntoResponse {
+    let resp_ctype_val = HeaderValue::from_str(HTTP_CONTENT_TYPE_JSON).unwrap();
+    let mut hdr_map = HeaderMap::new();
+    hdr_map.insert(header::CONTENT_TYPE, resp_ctype_val);
+    let default_body = "{}".to_string();
+    let logctx = appstate.log_context().clone();
+
+    let ds = appstate.datastore();
+    let results = (
+        app_repo_cart(ds.clone()).await,
+        app_repo_currency(ds.clone()).await,
+        app_repo_order(ds.clone()).await,
+        app_repo_product_price(ds.clone()).await,
+        app_repo_product_policy(ds.clone()).await,
+        app_repo_store_shipping(ds).await,
+    );
+    let (repo, repo_currex, repo_order, repo_price, repo_policy, repo_shipping) = match results {
+        (Ok(r0), Ok(r1), Ok(r2), Ok(r3), Ok(r4), Ok(r5)) => (r0, r1, r2, r3, r4, r5),
+        (r0, r1, r2, r3, r4, r5) => {
+            let errmsgs = [r0.err(), r1.err(), r2.err(), r3.err(), r4.err(), r5.err()]
+                .into_iter()
+                .flatten()
+                .map(|e| e.to_string())
+                .collect::<Vec<_>>();
+            app_log_event!(logctx, AppLogLevel::ERROR, "{:?}", errmsgs);
+            return (StatusCode::INTERNAL_SERVER_ERROR, hdr_map, default_body);
+        }
+    };
+    let uc_create_order = CreateOrderUseCase {
+        glb_state: appstate,
+        repo_currex,
+        repo_order,
+        repo_price,
+        repo_policy,
+        repo_shipping,
+        auth_claim: authed_usr,
+    };
+    let uc = CheckoutCartUseCase {
+        repo,
+        log_ctx: logctx.clone(),
+        uc_create_order,
+    };
+    let (status, resp_body) = match uc.execute(seq_num, req_body).await {
+        CheckoutCartUsKsResult::Success(v) => {
+            (StatusCode::CREATED, serde_json::to_string(&v).unwrap())
+        }
+        CheckoutCartUsKsResult::NotFound => (StatusCode::NOT_FOUND, default_body),
+        CheckoutCartUsKsResult::CreateOrderError(e) => match e {
+            CreateOrderUsKsErr::ReqContent(v) => {
+                (StatusCode::BAD_REQUEST, serde_json::to_string(&v).unwrap())
+            }
+            CreateOrderUsKsErr::Quota(v) => {
+                (StatusCode::FORBIDDEN, serde_json::to_string(&v).unwrap())
+            }
+            CreateOrderUsKsErr::Server(es) => {
+                app_log_event!(logctx, AppLogLevel::ERROR, "{:?}", es);
+                (StatusCode::INTERNAL_SERVER_ERROR, default_body)
+            }
+        },
+    };
+    (status, hdr_map, resp_body)
+} // end of fn checkout
@@ -2,14 +2,15 @@ use serde::{Deserialize, Serialize};
 
 use ecommerce_common::api::dto::{
     BillingDto, CurrencyDto, GenericRangeErrorDto, OrderCurrencySnapshotDto, OrderLinePayDto,
+    PayAmountDto,
 };
 use ecommerce_common::api::web::dto::{
     BillingErrorDto, ContactErrorDto, PhyAddrErrorDto, QuotaResourceErrorDto,
 };
 
-use crate::api::dto::{ProdAttrValueDto, ShippingDto};
+use crate::api::dto::{ProdAttrValueDto, ShipmentLineDto, ShippingDto, ShippingMethod};
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct OlineProductAttrDto {
     pub label_id: String,
     pub value: ProdAttrValueDto,
@@ -30,6 +31,13 @@ pub struct OrderLineReturnReqDto {
     pub quantity: u32,
 }
 
+#[derive(Deserialize, Serialize)]
+pub struct OrderLineCancelReqDto {
+    pub seller_id: u32,
+    pub product_id: u64,
+    pub attr_set_seq: u16,
+}
+
 // TODO , extra field to indicate whether to discard specific line
 pub type CartLineDto = OrderLineRsvReqDto;
 
@@ -39,6 +47,30 @@ pub struct CartDto {
     pub lines: Vec<CartLineDto>,
 }
 
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Debug)]
+pub enum CartAccessDto {
+    Read,
+    Edit,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct CartCollaboratorDto {
+    pub usr_id: u32,
+    pub access: CartAccessDto,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct CartShareDto {
+    // users other than the owner who can access the cart
+    pub collaborators: Vec<CartCollaboratorDto>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct CartShareErrorDto {
+    // the owner, or users which appear more than once in the request
+    pub usr_ids: Vec<u32>,
+}
+
 #[derive(Deserialize, Serialize)]
 pub enum OrderLineCreateErrorReason {
     NotExist,
@@ -56,6 +88,22 @@ pub enum OrderLineReturnErrorReason {
     DuplicateReturn,
 }
 
+#[derive(Deserialize, Serialize, Debug)]
+pub enum OrderLineCancelErrorReason {
+    NotExist,
+    AlreadyPaid,
+    ReservationExpired,
+    DuplicateCancel,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct OrderLineCancelErrorDto {
+    pub seller_id: u32,
+    pub product_id: u64,
+    pub attr_set_seq: u16,
+    pub reason: OrderLineCancelErrorReason,
+}
+
 #[derive(Deserialize, Serialize)]
 pub struct OrderLineCreateErrNonExistDto {
     pub product_policy: bool,
@@ -120,6 +168,39 @@ pub struct OrderCreateReqData {
     pub shipping: ShippingReqDto,
 }
 
+#[derive(Deserialize, Serialize)]
+pub struct CartLinePreviewDto {
+    pub seller_id: u32,
+    pub product_id: u64,
+    pub applied_attr: Option<Vec<OlineProductAttrDto>>,
+    pub quantity: u32,
+    // current price converted with buyer's currency exchange rate, the unit
+    // price includes extra charge of the chosen attributes
+    pub amount: Option<PayAmountDto>,
+    pub attr_charge: Option<String>,
+    // the fields below indicate the line cannot be purchased as it is
+    pub nonexist: Option<OrderLineCreateErrNonExistDto>,
+    pub attr_vals: Option<Vec<String>>,
+    pub shortage: Option<u32>,
+    pub rsv_limit: Option<GenericRangeErrorDto>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct CartPreviewRespDto {
+    pub title: String,
+    pub currency: CurrencyDto,
+    pub lines: Vec<CartLinePreviewDto>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct CartCheckoutReqDto {
+    // order lines are loaded from the saved cart, the rest is the same as
+    // the request of creating a new order
+    pub currency: CurrencyDto,
+    pub billing: BillingReqDto,
+    pub shipping: ShippingReqDto,
+}
+
 #[derive(Deserialize, Serialize)]
 pub struct OrderCreateRespOkDto {
     pub order_id: String,
@@ -143,9 +224,88 @@ pub struct OrderEditReqData {
     pub shipping: ShippingReqDto,
 }
 
+#[derive(Deserialize, Serialize)]
+pub struct OrderLineQuantityDto {
+    pub reserved: u32,
+    pub paid: u32,
+    pub paid_last_update: Option<String>, // date-time formatted in RFC3339 spec
+    pub cancelled: u32,
+    pub delivered: u32,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct OrderLineAppliedPolicyDto {
+    pub version: u32,
+    pub reserved_until: String,
+    pub warranty_until: String,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct OrderLineDetailDto {
+    pub seller_id: u32,
+    pub product_id: u64,
+    pub attr_set_seq: u16,
+    pub quantity: OrderLineQuantityDto,
+    pub policy: OrderLineAppliedPolicyDto,
+    // price amount converted with buyer's currency exchange rate
+    pub amount: PayAmountDto,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct OrderDetailRespDto {
+    pub order_id: String,
+    pub usr_id: u32,
+    pub time: String, // date-time formatted in RFC3339 spec
+    pub currency: OrderCurrencySnapshotDto,
+    pub lines: Vec<OrderLineDetailDto>,
+    pub billing: BillingDto,
+    pub shipping: ShippingDto,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ShipmentDto {
+    pub seller_id: u32,
+    pub carrier: ShippingMethod,
+    pub tracking_num: String,
+    pub create_time: String, // date-time formatted in RFC3339 spec
+    pub received_time: Option<String>,
+    pub lines: Vec<ShipmentLineDto>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ShipmentReceiptReqDto {
+    pub seller_id: u32,
+    pub tracking_num: String,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub enum ShipmentReceiptErrorReason {
+    NotExist,
+    AlreadyReceived,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ShipmentReceiptErrorDto {
+    pub reason: ShipmentReceiptErrorReason,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct OrderSummaryDto {
+    pub order_id: String,
+    pub time: String, // date-time formatted in RFC3339 spec
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct OrderListRespDto {
+    pub total: usize,
+    pub page: u32,
+    pub orders: Vec<OrderSummaryDto>,
+}
+
 #[derive(Deserialize)]
 pub struct ProductPolicyDto {
-    pub product_id: u64, // TODO, new field `seller_id` u32
+    pub seller_id: u32,
+    pub product_id: u64,
     pub auto_cancel_secs: u32,
     pub warranty_hours: u32,
     pub max_num_rsv: Option<u16>,
@@ -165,9 +325,27 @@ pub struct ProductPolicyNumRsvLimitDto {
 
 #[derive(Serialize, PartialEq, Debug)]
 pub struct ProductPolicyClientErrorDto {
+    pub seller_id: u32,
     pub product_id: u64,
     pub err_type: String, // convert from AppError
     pub auto_cancel_secs: Option<ProductPolicyClientLimitDto>,
     pub warranty_hours: Option<ProductPolicyClientLimitDto>,
     pub num_rsv: Option<ProductPolicyNumRsvLimitDto>,
 }
+
+#[derive(Deserialize, Serialize)]
+pub struct ProductPolicyVersionDto {
+    pub version: u32,
+    pub effective_from: String,
+    pub auto_cancel_secs: u32,
+    pub warranty_hours: u32,
+    pub max_num_rsv: u16,
+    pub min_num_rsv: u16,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ProductPolicyHistoryRespDto {
+    pub seller_id: u32,
+    pub product_id: u64,
+    pub versions: Vec<ProductPolicyVersionDto>,
+}
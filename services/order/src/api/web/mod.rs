@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 
-use axum::routing::{delete, get, patch, post, MethodRouter};
+use axum::routing::{delete, get, patch, post, put, MethodRouter};
 
 use crate::constant::api::web as WebConst;
 use crate::{AppSharedState, WebApiHdlrLabel};
@@ -21,17 +21,39 @@ pub fn route_table() -> ApiRouteTableType {
         WebConst::ADD_PRODUCT_POLICY,
         post(product_policy::post_handler),
     );
+    out.insert(
+        WebConst::PRODUCT_POLICY_HISTORY,
+        get(product_policy::history_handler),
+    );
     out.insert(WebConst::CREATE_NEW_ORDER, post(order::create_handler));
     out.insert(
         WebConst::ACCESS_EXISTING_ORDER,
         patch(order::edit_billing_shipping_handler),
     );
+    out.insert(WebConst::RETRIEVE_ORDER, get(order::retrieve_handler));
+    out.insert(WebConst::LIST_ORDERS, get(order::list_handler));
     out.insert(
         WebConst::RETURN_OLINES_REQ,
         patch(order::return_lines_request_handler),
     );
+    out.insert(
+        WebConst::CANCEL_UNPAID_OLINES,
+        patch(order::cancel_unpaid_lines_handler),
+    );
+    out.insert(
+        WebConst::RETRIEVE_ORDER_SHIPMENTS,
+        get(order::list_shipments_handler),
+    );
+    out.insert(
+        WebConst::CONFIRM_SHIPMENT_RECEIPT,
+        patch(order::confirm_shipment_receipt_handler),
+    );
     out.insert(WebConst::MODIFY_CART_LINES, patch(cart::modify_lines));
     out.insert(WebConst::DISCARD_CART, delete(cart::discard));
     out.insert(WebConst::RETRIEVE_CART_LINES, get(cart::retrieve));
+    out.insert(WebConst::SHARE_CART, put(cart::share));
+    out.insert(WebConst::RETRIEVE_CART_SHARE, get(cart::retrieve_share));
+    out.insert(WebConst::PREVIEW_CART, get(cart::preview));
+    out.insert(WebConst::CHECKOUT_CART, post(cart::checkout));
     out
 }
@@ -7,19 +7,26 @@ use axum::http::{
     StatusCode as HttpStatusCode,
 };
 use axum::response::IntoResponse;
+use chrono::{DateTime, FixedOffset};
 use serde::Deserialize;
 use serde_json;
 
 use ecommerce_common::logging::{app_log_event, AppLogLevel};
 
-use crate::api::web::dto::{OrderCreateReqData, OrderEditReqData, OrderLineReturnReqDto};
+use crate::api::web::dto::{
+    OrderCreateReqData, OrderEditReqData, OrderLineCancelReqDto, OrderLineReturnReqDto,
+    ShipmentReceiptReqDto,
+};
 use crate::constant as AppConst;
 use crate::repository::{
     app_repo_currency, app_repo_order, app_repo_order_return, app_repo_product_policy,
-    app_repo_product_price,
+    app_repo_product_price, app_repo_store_shipping,
 };
 use crate::usecase::{
-    CreateOrderUsKsErr, CreateOrderUseCase, ReturnLinesReqUcOutput, ReturnLinesReqUseCase,
+    CancelUnpaidLinesUcOutput, CancelUnpaidLinesUseCase, CreateOrderUsKsErr, CreateOrderUseCase,
+    OrderListUcOutput, OrderListUseCase, OrderRetrieveUcOutput, OrderRetrieveUseCase,
+    OrderShipmentListUcOutput, OrderShipmentListUseCase, OrderShipmentReceiptUcOutput,
+    OrderShipmentReceiptUseCase, ReturnLinesReqUcOutput, ReturnLinesReqUseCase,
 };
 use crate::{AppAuthedClaim, AppSharedState};
 
@@ -38,14 +45,18 @@ pub(super) async fn create_handler(
         app_repo_currency(ds.clone()).await,
         app_repo_order(ds.clone()).await,
         app_repo_product_price(ds.clone()).await,
-        app_repo_product_policy(ds).await,
+        app_repo_product_policy(ds.clone()).await,
+        app_repo_store_shipping(ds).await,
     );
     let (resp_status_code, serial_resp_body) =
-        if let (Ok(repo_currex), Ok(repo_o), Ok(repo_price), Ok(repo_policy)) = results {
+        if let (Ok(repo_currex), Ok(repo_o), Ok(repo_price), Ok(repo_policy), Ok(repo_shipping)) =
+            results
+        {
             let uc = CreateOrderUseCase {
                 glb_state: _appstate,
                 repo_price,
                 repo_policy,
+                repo_shipping,
                 repo_currex,
                 repo_order: repo_o,
                 auth_claim: authed,
@@ -191,6 +202,65 @@ pub(super) async fn return_lines_request_handler(
     (status_code, hdr_map, resp_body)
 } // end of return_lines_request_handler
 
+#[debug_handler(state=AppSharedState)]
+pub(super) async fn cancel_unpaid_lines_handler(
+    ExtractPath(oid): ExtractPath<String>,
+    authed_claim: AppAuthedClaim,
+    ExtractState(_app_state): ExtractState<AppSharedState>,
+    ExtractJson(req_body): ExtractJson<Vec<OrderLineCancelReqDto>>,
+) -> impl IntoResponse {
+    let logctx = _app_state.log_context().clone();
+    let usr_prof_id = authed_claim.profile;
+    let ds = _app_state.datastore();
+    let (status_code, resp_body) = match app_repo_order(ds).await {
+        Ok(o_repo) => {
+            let uc = CancelUnpaidLinesUseCase {
+                authed_claim,
+                o_repo,
+                logctx: logctx.clone(),
+            };
+            match uc.execute(oid.clone(), req_body).await {
+                Ok(output) => match output {
+                    CancelUnpaidLinesUcOutput::Success => (HttpStatusCode::OK, r#"{}"#.to_string()),
+                    CancelUnpaidLinesUcOutput::InvalidOwner => {
+                        (HttpStatusCode::FORBIDDEN, r#"{}"#.to_string())
+                    }
+                    CancelUnpaidLinesUcOutput::InvalidRequest(errors) => {
+                        let serialized = serde_json::to_string(&errors).unwrap();
+                        (HttpStatusCode::BAD_REQUEST, serialized)
+                    }
+                },
+                Err(e) => {
+                    app_log_event!(
+                        logctx,
+                        AppLogLevel::ERROR,
+                        "internal error from use-case, oid:{}, user:{}, reason:{:?}",
+                        oid.as_str(),
+                        usr_prof_id,
+                        e
+                    );
+                    (HttpStatusCode::INTERNAL_SERVER_ERROR, r#"{}"#.to_string())
+                }
+            }
+        }
+        Err(e) => {
+            app_log_event!(
+                logctx,
+                AppLogLevel::ERROR,
+                "failed to init order repo, oid:{}, user:{}, reason:{:?}",
+                oid,
+                usr_prof_id,
+                e
+            );
+            (HttpStatusCode::INTERNAL_SERVER_ERROR, r#"{}"#.to_string())
+        }
+    };
+    let resp_ctype_val = HttpHeaderValue::from_str(AppConst::HTTP_CONTENT_TYPE_JSON).unwrap();
+    let hdr_kv_pairs = [(HttpHeader::CONTENT_TYPE, resp_ctype_val)];
+    let hdr_map = HttpHeaderMap::from_iter(hdr_kv_pairs);
+    (status_code, hdr_map, resp_body)
+} // end of cancel_unpaid_lines_handler
+
 #[derive(Deserialize)]
 pub(super) struct EditInfoEnableFlag {
     pub billing: Option<bool>,
@@ -221,3 +291,255 @@ pub(super) async fn edit_billing_shipping_handler(
     );
     (HttpStatusCode::OK, hdr_map, serial_resp_body)
 }
+
+#[debug_handler(state=AppSharedState)]
+pub(super) async fn retrieve_handler(
+    ExtractPath(oid): ExtractPath<String>,
+    authed_claim: AppAuthedClaim,
+    ExtractState(_app_state): ExtractState<AppSharedState>,
+) -> impl IntoResponse {
+    let logctx = _app_state.log_context().clone();
+    let usr_prof_id = authed_claim.profile;
+    let ds = _app_state.datastore();
+    let (status_code, resp_body) = match app_repo_order(ds).await {
+        Ok(o_repo) => {
+            let uc = OrderRetrieveUseCase {
+                authed_claim,
+                o_repo,
+            };
+            match uc.execute(oid.clone()).await {
+                Ok(output) => match output {
+                    OrderRetrieveUcOutput::Success(detail) => {
+                        let serialized = serde_json::to_string(detail.as_ref()).unwrap();
+                        (HttpStatusCode::OK, serialized)
+                    }
+                    OrderRetrieveUcOutput::NotFound => {
+                        (HttpStatusCode::NOT_FOUND, r#"{}"#.to_string())
+                    }
+                    OrderRetrieveUcOutput::InvalidOwner => {
+                        (HttpStatusCode::FORBIDDEN, r#"{}"#.to_string())
+                    }
+                },
+                Err(e) => {
+                    app_log_event!(
+                        logctx,
+                        AppLogLevel::ERROR,
+                        "internal error from use-case, oid:{}, user:{}, reason:{:?}",
+                        oid.as_str(),
+                        usr_prof_id,
+                        e
+                    );
+                    (HttpStatusCode::INTERNAL_SERVER_ERROR, r#"{}"#.to_string())
+                }
+            }
+        }
+        Err(e) => {
+            app_log_event!(
+                logctx,
+                AppLogLevel::ERROR,
+                "failed to init order repo, oid:{}, user:{}, reason:{:?}",
+                oid,
+                usr_prof_id,
+                e
+            );
+            (HttpStatusCode::INTERNAL_SERVER_ERROR, r#"{}"#.to_string())
+        }
+    };
+    let resp_ctype_val = HttpHeaderValue::from_str(AppConst::HTTP_CONTENT_TYPE_JSON).unwrap();
+    let hdr_kv_pairs = [(HttpHeader::CONTENT_TYPE, resp_ctype_val)];
+    let hdr_map = HttpHeaderMap::from_iter(hdr_kv_pairs);
+    (status_code, hdr_map, resp_body)
+} // end of retrieve_handler
+
+#[derive(Deserialize)]
+pub(super) struct ListOrdersQueryParam {
+    // date-time formatted in RFC3339 spec
+    pub start: DateTime<FixedOffset>,
+    pub end: DateTime<FixedOffset>,
+    pub page: Option<u32>,
+    pub size: Option<u16>,
+}
+
+#[debug_handler(state=AppSharedState)]
+pub(super) async fn list_handler(
+    ExtractQuery(param): ExtractQuery<ListOrdersQueryParam>,
+    authed_claim: AppAuthedClaim,
+    ExtractState(_app_state): ExtractState<AppSharedState>,
+) -> impl IntoResponse {
+    let logctx = _app_state.log_context().clone();
+    let usr_prof_id = authed_claim.profile;
+    let ds = _app_state.datastore();
+    let ListOrdersQueryParam {
+        start,
+        end,
+        page,
+        size,
+    } = param;
+    let page = page.unwrap_or(1);
+    let size = size.unwrap_or(AppConst::hard_limit::MAX_ORDERS_PER_PAGE);
+    let (status_code, resp_body) = match app_repo_order(ds).await {
+        Ok(o_repo) => {
+            let uc = OrderListUseCase {
+                authed_claim,
+                o_repo,
+            };
+            match uc.execute(start, end, page, size).await {
+                Ok(output) => match output {
+                    OrderListUcOutput::Success(resp) => {
+                        let serialized = serde_json::to_string(&resp).unwrap();
+                        (HttpStatusCode::OK, serialized)
+                    }
+                    OrderListUcOutput::InvalidRequest => {
+                        (HttpStatusCode::BAD_REQUEST, r#"{}"#.to_string())
+                    }
+                },
+                Err(e) => {
+                    app_log_event!(
+                        logctx,
+                        AppLogLevel::ERROR,
+                        "internal error from use-case, user:{}, reason:{:?}",
+                        usr_prof_id,
+                        e
+                    );
+                    (HttpStatusCode::INTERNAL_SERVER_ERROR, r#"{}"#.to_string())
+                }
+            }
+        }
+        Err(e) => {
+            app_log_event!(
+                logctx,
+                AppLogLevel::ERROR,
+                "failed to init order repo, user:{}, reason:{:?}",
+                usr_prof_id,
+                e
+            );
+            (HttpStatusCode::INTERNAL_SERVER_ERROR, r#"{}"#.to_string())
+        }
+    };
+    let resp_ctype_val = HttpHeaderValue::from_str(AppConst::HTTP_CONTENT_TYPE_JSON).unwrap();
+    let hdr_kv_pairs = [(HttpHeader::CONTENT_TYPE, resp_ctype_val)];
+    let hdr_map = HttpHeaderMap::from_iter(hdr_kv_pairs);
+    (status_code, hdr_map, resp_body)
+} // end of list_handler
+
+#[debug_handler(state=AppSharedState)]
+pub(super) async fn list_shipments_handler(
+    ExtractPath(oid): ExtractPath<String>,
+    authed_claim: AppAuthedClaim,
+    ExtractState(_app_state): ExtractState<AppSharedState>,
+) -> impl IntoResponse {
+    let logctx = _app_state.log_context().clone();
+    let usr_prof_id = authed_claim.profile;
+    let ds = _app_state.datastore();
+    let (status_code, resp_body) = match app_repo_order(ds).await {
+        Ok(o_repo) => {
+            let uc = OrderShipmentListUseCase {
+                authed_claim,
+                o_repo,
+            };
+            match uc.execute(oid.clone()).await {
+                Ok(output) => match output {
+                    OrderShipmentListUcOutput::Success(resp) => {
+                        let serialized = serde_json::to_string(&resp).unwrap();
+                        (HttpStatusCode::OK, serialized)
+                    }
+                    OrderShipmentListUcOutput::NotFound => {
+                        (HttpStatusCode::NOT_FOUND, r#"{}"#.to_string())
+                    }
+                    OrderShipmentListUcOutput::InvalidOwner => {
+                        (HttpStatusCode::FORBIDDEN, r#"{}"#.to_string())
+                    }
+                },
+                Err(e) => {
+                    app_log_event!(
+                        logctx,
+                        AppLogLevel::ERROR,
+                        "internal error from use-case, oid:{}, user:{}, reason:{:?}",
+                        oid.as_str(),
+                        usr_prof_id,
+                        e
+                    );
+                    (HttpStatusCode::INTERNAL_SERVER_ERROR, r#"{}"#.to_string())
+                }
+            }
+        }
+        Err(e) => {
+            app_log_event!(
+                logctx,
+                AppLogLevel::ERROR,
+                "failed to init order repo, oid:{}, user:{}, reason:{:?}",
+                oid,
+                usr_prof_id,
+                e
+            );
+            (HttpStatusCode::INTERNAL_SERVER_ERROR, r#"{}"#.to_string())
+        }
+    };
+    let resp_ctype_val = HttpHeaderValue::from_str(AppConst::HTTP_CONTENT_TYPE_JSON).unwrap();
+    let hdr_kv_pairs = [(HttpHeader::CONTENT_TYPE, resp_ctype_val)];
+    let hdr_map = HttpHeaderMap::from_iter(hdr_kv_pairs);
+    (status_code, hdr_map, resp_body)
+} // end of list_shipments_handler
+
+#[debug_handler(state=AppSharedState)]
+pub(super) async fn confirm_shipment_receipt_handler(
+    ExtractPath(oid): ExtractPath<String>,
+    authed_claim: AppAuthedClaim,
+    ExtractState(_app_state): ExtractState<AppSharedState>,
+    ExtractJson(req_body): ExtractJson<ShipmentReceiptReqDto>,
+) -> impl IntoResponse {
+    let logctx = _app_state.log_context().clone();
+    let usr_prof_id = authed_claim.profile;
+    let ds = _app_state.datastore();
+    let (status_code, resp_body) = match app_repo_order(ds).await {
+        Ok(o_repo) => {
+            let uc = OrderShipmentReceiptUseCase {
+                authed_claim,
+                o_repo,
+            };
+            match uc.execute(oid.clone(), req_body).await {
+                Ok(output) => match output {
+                    OrderShipmentReceiptUcOutput::Success => {
+                        (HttpStatusCode::OK, r#"{}"#.to_string())
+                    }
+                    OrderShipmentReceiptUcOutput::NotFound => {
+                        (HttpStatusCode::NOT_FOUND, r#"{}"#.to_string())
+                    }
+                    OrderShipmentReceiptUcOutput::InvalidOwner => {
+                        (HttpStatusCode::FORBIDDEN, r#"{}"#.to_string())
+                    }
+                    OrderShipmentReceiptUcOutput::InvalidRequest(e) => {
+                        let serialized = serde_json::to_string(&e).unwrap();
+                        (HttpStatusCode::BAD_REQUEST, serialized)
+                    }
+                },
+                Err(e) => {
+                    app_log_event!(
+                        logctx,
+                        AppLogLevel::ERROR,
+                        "internal error from use-case, oid:{}, user:{}, reason:{:?}",
+                        oid.as_str(),
+                        usr_prof_id,
+                        e
+                    );
+                    (HttpStatusCode::INTERNAL_SERVER_ERROR, r#"{}"#.to_string())
+                }
+            }
+        }
+        Err(e) => {
+            app_log_event!(
+                logctx,
+                AppLogLevel::ERROR,
+                "failed to init order repo, oid:{}, user:{}, reason:{:?}",
+                oid,
+                usr_prof_id,
+                e
+            );
+            (HttpStatusCode::INTERNAL_SERVER_ERROR, r#"{}"#.to_string())
+        }
+    };
+    let resp_ctype_val = HttpHeaderValue::from_str(AppConst::HTTP_CONTENT_TYPE_JSON).unwrap();
+    let hdr_kv_pairs = [(HttpHeader::CONTENT_TYPE, resp_ctype_val)];
+    let hdr_map = HttpHeaderMap::from_iter(hdr_kv_pairs);
+    (status_code, hdr_map, resp_body)
+} // end of confirm_shipment_receipt_handler
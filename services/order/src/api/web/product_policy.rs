@@ -2,20 +2,24 @@ use std::result::Result as DefaultResult;
 use std::vec::Vec;
 
 use axum::debug_handler;
-use axum::extract::{Json as ExtractJson, State as ExtractState};
+use axum::extract::{Json as ExtractJson, Path as ExtractPath, State as ExtractState};
 use axum::http::{
     header as HttpHeader, HeaderMap as HttpHeaderMap, HeaderValue as HttpHeaderValue,
     StatusCode as HttpStatusCode,
 };
 use axum::response::IntoResponse;
 
+use ecommerce_common::api::rpc::dto::{StoreProfileReplicaDto, StoreProfileReplicaReqDto};
 use ecommerce_common::error::AppErrorCode;
+use ecommerce_common::logging::{app_log_event, AppLogLevel};
 
 use crate::api::rpc::PyCelery;
 use crate::api::web::dto::ProductPolicyDto;
 use crate::error::AppError;
+use crate::repository::app_repo_product_policy;
 use crate::usecase::{
     EditProductPolicyResult, EditProductPolicyUseCase, ProductInfoReq, ProductInfoResp,
+    ProductPolicyHistoryUcOutput, ProductPolicyHistoryUseCase,
 };
 use crate::{constant as AppConst, AppAuthedClaim, AppSharedState};
 
@@ -39,6 +43,7 @@ fn presenter(result: EditProductPolicyResult) -> impl IntoResponse {
             let s = match ec {
                 AppErrorCode::RpcRemoteUnavail => HttpStatusCode::SERVICE_UNAVAILABLE,
                 AppErrorCode::RpcRemoteInvalidReply => HttpStatusCode::NOT_IMPLEMENTED,
+                AppErrorCode::DataConflict => HttpStatusCode::CONFLICT,
                 _others => HttpStatusCode::INTERNAL_SERVER_ERROR,
             };
             (s, default_body)
@@ -60,11 +65,17 @@ pub(super) async fn post_handler(
     // if RPC client handler trait adds serialize / deserialize methods with generic
     // type parameter , it will make the code more complex,
     // TODO, find better approach to improve code quality
-    let rpc_deserialize_msg = if rpc_ctx.label() == "dummy" {
+    let is_dummy = rpc_ctx.label() == "dummy";
+    let rpc_deserialize_msg = if is_dummy {
         _rpc_deserialize_dummy
     } else {
         PyCelery::deserialize_reply::<ProductInfoResp>
     };
+    let rpc_deserialize_store_msg = if is_dummy {
+        _rpc_deserialize_dummy
+    } else {
+        PyCelery::deserialize_reply::<StoreProfileReplicaDto>
+    };
     let input = EditProductPolicyUseCase {
         data: req_body,
         log,
@@ -73,12 +84,77 @@ pub(super) async fn post_handler(
         authed_usr,
         rpc_deserialize_msg,
         rpc_serialize_msg: PyCelery::serialize::<ProductInfoReq>,
+        rpc_deserialize_store_msg,
+        rpc_serialize_store_msg: PyCelery::serialize::<StoreProfileReplicaReqDto>,
     };
     let result = input.execute().await;
     presenter(result)
 } // end of endpoint
 
-fn _rpc_deserialize_dummy(_raw: &Vec<u8>) -> DefaultResult<ProductInfoResp, AppError> {
+#[debug_handler(state = AppSharedState)]
+pub(super) async fn history_handler(
+    ExtractPath((seller_id, product_id)): ExtractPath<(u32, u64)>,
+    authed_usr: AppAuthedClaim,
+    ExtractState(appstate): ExtractState<AppSharedState>,
+) -> impl IntoResponse {
+    let logctx = appstate.log_context().clone();
+    let ds = appstate.datastore();
+    let rpc_ctx = appstate.rpc();
+    let rpc_deserialize_msg = if rpc_ctx.label() == "dummy" {
+        _rpc_deserialize_dummy
+    } else {
+        PyCelery::deserialize_reply::<StoreProfileReplicaDto>
+    };
+    let (status_code, resp_body) = match app_repo_product_policy(ds).await {
+        Ok(repo) => {
+            let uc = ProductPolicyHistoryUseCase {
+                repo,
+                log: logctx.clone(),
+                rpc_ctx,
+                authed_usr,
+                rpc_deserialize_msg,
+                rpc_serialize_msg: PyCelery::serialize::<StoreProfileReplicaReqDto>,
+            };
+            match uc.execute(seller_id, product_id).await {
+                Ok(ProductPolicyHistoryUcOutput::Success(resp)) => {
+                    let serialized = serde_json::to_string(&resp).unwrap();
+                    (HttpStatusCode::OK, serialized)
+                }
+                Ok(ProductPolicyHistoryUcOutput::NotFound) => {
+                    (HttpStatusCode::NOT_FOUND, r#"{}"#.to_string())
+                }
+                Ok(ProductPolicyHistoryUcOutput::PermissionDeny) => {
+                    (HttpStatusCode::FORBIDDEN, r#"{}"#.to_string())
+                }
+                Err(e) => {
+                    let status = match &e.code {
+                        AppErrorCode::RpcRemoteUnavail => HttpStatusCode::SERVICE_UNAVAILABLE,
+                        _others => HttpStatusCode::INTERNAL_SERVER_ERROR,
+                    };
+                    app_log_event!(
+                        logctx,
+                        AppLogLevel::ERROR,
+                        "seller:{}, product:{}, reason:{:?}",
+                        seller_id,
+                        product_id,
+                        e
+                    );
+                    (status, r#"{}"#.to_string())
+                }
+            }
+        }
+        Err(e) => {
+            app_log_event!(logctx, AppLogLevel::ERROR, "repo-init-error:{:?}", e);
+            (HttpStatusCode::INTERNAL_SERVER_ERROR, r#"{}"#.to_string())
+        }
+    };
+    let resp_ctype_val = HttpHeaderValue::from_str(AppConst::HTTP_CONTENT_TYPE_JSON).unwrap();
+    let hdr_kv_pairs = [(HttpHeader::CONTENT_TYPE, resp_ctype_val)];
+    let hdr_map = HttpHeaderMap::from_iter(hdr_kv_pairs);
+    (status_code, hdr_map, resp_body)
+} // end of fn history_handler
+
+fn _rpc_deserialize_dummy<T>(_raw: &Vec<u8>) -> DefaultResult<T, AppError> {
     Err(AppError {
         code: AppErrorCode::RpcRemoteInvalidReply,
         detail: None,
@@ -94,6 +94,7 @@ pub enum AppAuthQuotaMatCode {
     NumEmails,
     NumOrderLines,
     NumProductPolicies,
+    NumCartCollaborators,
 }
 
 #[derive(Deserialize, Serialize, Clone)]
@@ -181,6 +182,7 @@ impl TryFrom<u8> for AppAuthQuotaMatCode {
             2 => Ok(Self::NumEmails),
             3 => Ok(Self::NumOrderLines),
             4 => Ok(Self::NumProductPolicies),
+            5 => Ok(Self::NumCartCollaborators),
             _others => Err(value),
         }
     }
@@ -192,6 +194,7 @@ impl From<AppAuthQuotaMatCode> for u8 {
             AppAuthQuotaMatCode::NumEmails => 2,
             AppAuthQuotaMatCode::NumOrderLines => 3,
             AppAuthQuotaMatCode::NumProductPolicies => 4,
+            AppAuthQuotaMatCode::NumCartCollaborators => 5,
         }
     }
 }
@@ -203,7 +206,7 @@ impl<'de> Deserialize<'de> for AppAuthQuotaMatCode {
         let val = u8::deserialize(raw)?;
         match Self::try_from(val) {
             Ok(code) => Ok(code),
-            Err(val) => Err(quota_matcode_deserialize_error::<D>(val, (1, 4))),
+            Err(val) => Err(quota_matcode_deserialize_error::<D>(val, (1, 5))),
         }
     }
 }
@@ -12,6 +12,8 @@ pub mod hard_limit {
     pub const MAX_SECONDS_DB_IDLE: u16 = 600u16;
     pub const MIN_SECS_INTVL_REQ: u16 = 3;
     pub const MAX_NUM_CARTS_PER_USER: u8 = 5; // TODO, configurable in user-mgt app
+    pub const MAX_ORDERS_PER_PAGE: u16 = 100;
+    pub const MAX_DAYS_LIST_ORDERS: i64 = 366;
 }
 
 pub(crate) mod api {
@@ -26,12 +28,22 @@ pub(crate) mod api {
 
     impl web {
         pub(crate) const ADD_PRODUCT_POLICY: WebApiHdlrLabel = "modify_product_policy";
+        pub(crate) const PRODUCT_POLICY_HISTORY: WebApiHdlrLabel = "product_policy_history";
         pub(crate) const CREATE_NEW_ORDER: WebApiHdlrLabel = "create_new_order";
         pub(crate) const ACCESS_EXISTING_ORDER: WebApiHdlrLabel = "access_existing_order";
+        pub(crate) const RETRIEVE_ORDER: WebApiHdlrLabel = "retrieve_order";
+        pub(crate) const LIST_ORDERS: WebApiHdlrLabel = "list_orders";
         pub(crate) const RETURN_OLINES_REQ: WebApiHdlrLabel = "return_lines_request";
+        pub(crate) const CANCEL_UNPAID_OLINES: WebApiHdlrLabel = "cancel_unpaid_lines";
         pub(crate) const RETRIEVE_CART_LINES: WebApiHdlrLabel = "retrieve_cart_lines";
         pub(crate) const MODIFY_CART_LINES: WebApiHdlrLabel = "modify_cart_lines";
         pub(crate) const DISCARD_CART: WebApiHdlrLabel = "discard_cart";
+        pub(crate) const SHARE_CART: WebApiHdlrLabel = "share_cart";
+        pub(crate) const RETRIEVE_CART_SHARE: WebApiHdlrLabel = "retrieve_cart_share";
+        pub(crate) const PREVIEW_CART: WebApiHdlrLabel = "preview_cart";
+        pub(crate) const CHECKOUT_CART: WebApiHdlrLabel = "checkout_cart";
+        pub(crate) const RETRIEVE_ORDER_SHIPMENTS: WebApiHdlrLabel = "retrieve_order_shipments";
+        pub(crate) const CONFIRM_SHIPMENT_RECEIPT: WebApiHdlrLabel = "confirm_shipment_receipt";
     }
 
     #[allow(non_camel_case_types)]
@@ -40,6 +52,7 @@ pub(crate) mod api {
     impl rpc {
         pub(crate) const EDIT_PRODUCT_PRICE: WebApiHdlrLabel = "update_store_products";
         pub(crate) const CURRENCY_RATE_REFRESH: WebApiHdlrLabel = "currency_exrate_refresh";
+        pub(crate) const STORE_SHIPPING_SYNC: WebApiHdlrLabel = "sync_store_shipping";
         pub(crate) const STOCK_LEVEL_EDIT: WebApiHdlrLabel = "stock_level_edit";
         pub(crate) const STOCK_RETURN_CANCELLED: WebApiHdlrLabel = "stock_return_cancelled";
         pub(crate) const ORDER_RSV_READ_INVENTORY: WebApiHdlrLabel =
@@ -50,6 +63,7 @@ pub(crate) mod api {
             "order_reserved_update_payment";
         pub(crate) const ORDER_RSV_DISCARD_UNPAID: WebApiHdlrLabel =
             "order_reserved_discard_unpaid";
+        pub(crate) const ORDER_SHIPMENT_CREATE: WebApiHdlrLabel = "order_shipment_create";
 
         pub(crate) fn extract_handler_label(path: &str) -> DefaultResult<&str, AppError> {
             let mut tokens = path.split('.').collect::<Vec<&str>>();
@@ -92,6 +106,7 @@ pub(crate) mod api {
             let valid_labels = [
                 Self::EDIT_PRODUCT_PRICE,
                 Self::CURRENCY_RATE_REFRESH,
+                Self::STORE_SHIPPING_SYNC,
                 Self::STOCK_LEVEL_EDIT,
                 Self::STOCK_RETURN_CANCELLED,
                 Self::ORDER_RSV_READ_INVENTORY,
@@ -99,6 +114,7 @@ pub(crate) mod api {
                 Self::ORDER_RET_READ_REFUND,
                 Self::ORDER_RSV_UPDATE_PAYMENT,
                 Self::ORDER_RSV_DISCARD_UNPAID,
+                Self::ORDER_SHIPMENT_CREATE,
             ];
             if valid_labels.contains(&label) {
                 Ok(label)
@@ -1,5 +1,10 @@
 use ecommerce_common::error::{AppCfgError, AppConfidentialityError, AppErrorCode};
 use std::fmt::{Debug, Display};
+#[cfg(any(feature = "mariadb", feature = "postgres"))]
+use std::io::ErrorKind;
+
+#[cfg(any(feature = "mariadb", feature = "postgres"))]
+use sqlx::error::Error as SqlxError;
 
 #[derive(Debug, Clone)]
 pub struct AppError {
@@ -43,3 +48,77 @@ impl From<(AppErrorCode, String)> for AppError {
         }
     }
 }
+
+#[cfg(any(feature = "mariadb", feature = "postgres"))]
+impl AppError {
+    // for append-only tables, unique-key violation means other client has
+    // inserted the same record concurrently
+    pub(crate) fn from_sqlx_unique_conflict(value: SqlxError) -> Self {
+        match &value {
+            SqlxError::Database(e) if e.is_unique_violation() => Self {
+                code: AppErrorCode::DataConflict,
+                detail: Some(e.to_string()),
+            },
+            _others => Self::from(value),
+        }
+    }
+}
+
+#[cfg(any(feature = "mariadb", feature = "postgres"))]
+impl From<SqlxError> for AppError {
+    fn from(value: SqlxError) -> Self {
+        let (code, detail) = match value {
+            SqlxError::Configuration(e) => (
+                AppErrorCode::InvalidInput,
+                e.to_string() + " invalid-db-config",
+            ),
+            SqlxError::Io(e) => (
+                AppErrorCode::IOerror(e.kind()),
+                e.to_string() + " io-err-sql-db",
+            ),
+            SqlxError::Database(e) => (AppErrorCode::RemoteDbServerFailure, e.to_string()),
+            SqlxError::Protocol(errmsg) => (AppErrorCode::IOerror(ErrorKind::InvalidData), errmsg),
+            SqlxError::Decode(e) => (AppErrorCode::DataCorruption, e.to_string()),
+            SqlxError::ColumnDecode { index, source } => (
+                AppErrorCode::DataCorruption,
+                source.to_string() + ", when decoding column at idx " + index.as_str(),
+            ),
+            SqlxError::Tls(e) => (
+                AppErrorCode::IOerror(ErrorKind::NotConnected),
+                e.to_string(),
+            ),
+            SqlxError::TypeNotFound { type_name } => {
+                (AppErrorCode::InvalidInput, type_name + " wrong-col-typ")
+            }
+            SqlxError::ColumnNotFound(col_name) => (
+                AppErrorCode::IOerror(ErrorKind::NotFound),
+                col_name + "no-col",
+            ),
+            SqlxError::RowNotFound => (
+                AppErrorCode::IOerror(ErrorKind::NotFound),
+                "no-row".to_string(),
+            ),
+            SqlxError::ColumnIndexOutOfBounds { index, len } => (
+                AppErrorCode::InvalidInput,
+                format!("req-idx:{}, limit:{}", index, len),
+            ),
+            SqlxError::PoolTimedOut => (
+                AppErrorCode::DatabaseServerBusy,
+                "no-conn-avail".to_string(),
+            ),
+            SqlxError::PoolClosed => (AppErrorCode::Unknown, "pool-closed".to_string()),
+            SqlxError::WorkerCrashed => (
+                AppErrorCode::Unknown,
+                "low-level-db-worker-crashed".to_string(),
+            ),
+            _others => (
+                AppErrorCode::Unknown,
+                "internal-implementation-issue".to_string(),
+            ),
+        };
+        Self {
+            code,
+            detail: Some(detail),
+        }
+    } // end of fn from
+} // end of impl AppError
@@ -14,8 +14,8 @@ pub mod usecase;
 use ecommerce_common::confidentiality::AbstractConfidentiality;
 pub use ecommerce_common::config::{
     ApiServerCfg, AppAuthCfg, AppBasepathCfg, AppCfgHardLimit, AppCfgInitArgs, AppConfidentialCfg,
-    AppConfig, AppInMemoryDbCfg, AppLogHandlerCfg, AppLoggerCfg, AppLoggingCfg, AppRpcAmqpCfg,
-    AppRpcCfg, WebApiListenCfg, WebApiRouteCfg,
+    AppConfig, AppInMemFsyncPolicy, AppInMemPersistCfg, AppInMemoryDbCfg, AppLogHandlerCfg,
+    AppLoggerCfg, AppLoggingCfg, AppRpcAmqpCfg, AppRpcCfg, WebApiListenCfg, WebApiRouteCfg,
 };
 use ecommerce_common::logging::AppLogContext;
 
@@ -41,6 +41,7 @@ type WebApiHdlrLabel = &'static str;
 pub struct AppDataStoreContext {
     pub in_mem: Option<Arc<Box<dyn datastore::AbstInMemoryDStore>>>,
     pub sql_dbs: Option<Vec<Arc<datastore::AppMariaDbStore>>>,
+    pub pg_dbs: Option<Vec<Arc<datastore::AppPostgreSqlStore>>>,
 } // TODO, rename sql_dbs
 
 // global state shared by all threads
@@ -68,15 +69,21 @@ impl AppSharedState {
         let log = Arc::new(log);
         let _rpc_ctx =
             rpc::build_context(&cfg.api_server.rpc, log.clone(), confidential.clone()).unwrap();
-        let (in_mem, sql_dbs) = datastore::build_context(
+        let (in_mem, sql_dbs, pg_dbs) = datastore::build_context(
             log.clone(),
             &cfg.api_server.data_store,
+            cfg.basepath.service.as_str(),
             confidential.clone(),
         )
         .unwrap();
         let in_mem = in_mem.map(Arc::new);
         let sql_dbs = sql_dbs.map(|m| m.into_iter().map(Arc::new).collect());
-        let ds_ctx = Arc::new(AppDataStoreContext { in_mem, sql_dbs });
+        let pg_dbs = pg_dbs.map(|m| m.into_iter().map(Arc::new).collect());
+        let ds_ctx = Arc::new(AppDataStoreContext {
+            in_mem,
+            sql_dbs,
+            pg_dbs,
+        });
         let auth_keys = AppAuthKeystore::new(&cfg.api_server.auth);
         let currency_ex = app_currency_context(
             &cfg.basepath,
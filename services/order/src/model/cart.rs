@@ -1,14 +1,35 @@
+use std::collections::HashMap;
+use std::result::Result as DefaultResult;
+
+use rust_decimal::Decimal;
+
+use ecommerce_common::api::dto::{GenericRangeErrorDto, PayAmountDto};
+use ecommerce_common::error::AppErrorCode;
 use ecommerce_common::model::BaseProductIdentity;
 
-use crate::api::web::dto::{CartDto, CartLineDto}; // OlineProductAttrDto
+use super::{
+    OrderCurrencyModel, OrderLineModel, OrderLinePriceModel, ProdAttriPriceModel,
+    ProductPolicyModelSet, ProductPriceModel, ProductPriceModelSet, StockLevelModelSet,
+};
+use crate::api::web::dto::{
+    CartAccessDto, CartCollaboratorDto, CartDto, CartLineDto, CartLinePreviewDto,
+    CartPreviewRespDto, CartShareDto, CartShareErrorDto, OlineProductAttrDto,
+    OrderLineCreateErrNonExistDto, OrderLineRsvReqDto,
+};
+use crate::error::AppError;
+
+// number of items requested so far in a cart for each product, lines with
+// different attributes of the same product consume the same stock
+type CartStockDemand = HashMap<(u32, u64), u32>;
 
 pub struct CartLineModel {
     pub id_: BaseProductIdentity,
-    // TODO, add following field which represents product variant (e.g.
-    // specific size, color, etc.) attributes, no need to resolve the
-    // chosen attributes in cart line, they might be changed later on
-    // before checkout.
-    //// chosen_attr: Option<Vec<OlineProductAttrDto>>,
+    // product variant (e.g. specific size, color, etc.) attributes, no need
+    // to resolve the chosen attributes in cart line, they might be changed
+    // later on before checkout. A cart line is identified by the product ID
+    // together with the chosen attribute set, the attributes are kept sorted
+    // so the serialized form is consistent in data store.
+    pub chosen_attr: Vec<OlineProductAttrDto>,
     pub qty_req: u32,
     // current price and stock level are excluded
 }
@@ -19,35 +40,220 @@ pub struct CartModel {
     pub title: String,
     pub saved_lines: Vec<CartLineModel>,
     pub new_lines: Vec<CartLineModel>,
-    // TODO, add fields which indicate
-    // - extra cost amount for tax,
-    // - sharable flag, can be shared among users
-    // - list of user IDs that can edit the cart
+    // TODO, add fields which indicate extra cost amount for tax,
+    // users who can access the cart are kept in `CartShareModel`
+}
+
+pub struct CartCollaboratorModel {
+    pub usr_id: u32,
+    pub access: CartAccessDto,
+}
+
+// list of users other than the owner who can read or edit the cart, the
+// owner always has full access
+pub struct CartShareModel {
+    pub owner: u32,
+    pub seq_num: u8,
+    pub collaborators: Vec<CartCollaboratorModel>,
 }
 
 impl From<CartLineDto> for CartLineModel {
     fn from(value: CartLineDto) -> Self {
+        let mut chosen_attr = value.applied_attr.unwrap_or_default();
+        Self::sort_attributes(&mut chosen_attr);
         Self {
             id_: BaseProductIdentity {
                 store_id: value.seller_id,
                 product_id: value.product_id,
             },
+            chosen_attr,
             qty_req: value.quantity,
         }
     }
 }
 
+// the cart line DTO is also the request of an order line, so the chosen
+// attributes are carried over when a cart is converted to an order
 impl From<CartLineModel> for CartLineDto {
     fn from(value: CartLineModel) -> CartLineDto {
+        let applied_attr = if value.chosen_attr.is_empty() {
+            None
+        } else {
+            Some(value.chosen_attr)
+        };
         CartLineDto {
             product_id: value.id_.product_id,
             seller_id: value.id_.store_id,
             quantity: value.qty_req,
-            applied_attr: None, // TODO, finish implementation
+            applied_attr,
         }
     }
 }
 
+impl From<&CartLineModel> for CartLineDto {
+    fn from(value: &CartLineModel) -> CartLineDto {
+        let applied_attr = if value.chosen_attr.is_empty() {
+            None
+        } else {
+            Some(value.chosen_attr.clone())
+        };
+        CartLineDto {
+            product_id: value.id_.product_id,
+            seller_id: value.id_.store_id,
+            quantity: value.qty_req,
+            applied_attr,
+        }
+    }
+}
+
+impl CartLineModel {
+    fn preview(
+        self,
+        ms_policy: &ProductPolicyModelSet,
+        ms_price: &[ProductPriceModelSet],
+        ms_stock: &StockLevelModelSet,
+        currency_m: &OrderCurrencyModel,
+        demand: &mut CartStockDemand,
+    ) -> DefaultResult<CartLinePreviewDto, AppError> {
+        let (store_id, product_id, qty) = (self.id_.store_id, self.id_.product_id, self.qty_req);
+        let req = OrderLineRsvReqDto::from(&self);
+        let policym = ms_policy
+            .policies
+            .iter()
+            .find(|m| m.store_id == store_id && m.product_id == product_id);
+        let pricem = ms_price
+            .iter()
+            .filter(|ms| ms.store_id == store_id)
+            .flat_map(|ms| ms.items.iter())
+            .find(|m| m.product_id() == product_id);
+        let num_avail = ms_stock.num_avail(store_id, product_id);
+
+        let rsv_limit = policym.and_then(|plc| {
+            OrderLineModel::validate_rsv_limit(&req, plc)
+                .err()
+                .map(|_e| GenericRangeErrorDto {
+                    max_: plc.max_num_rsv,
+                    min_: plc.min_num_rsv,
+                    given: qty,
+                })
+        });
+        let shortage = num_avail.and_then(|total_avail| {
+            let num_taken = demand.entry((store_id, product_id)).or_default();
+            let remain = total_avail.saturating_sub(*num_taken);
+            *num_taken += qty;
+            if remain < qty {
+                Some(qty - remain)
+            } else {
+                None
+            }
+        });
+        let (amount, attr_charge, attr_vals) = if let Some(m) = pricem {
+            let unknown_attrs = self.unknown_attributes(m);
+            if unknown_attrs.is_empty() {
+                let (amount, charge) = Self::preview_price(&req, m, currency_m)?;
+                let charge = if self.chosen_attr.is_empty() {
+                    None
+                } else {
+                    Some(charge)
+                };
+                (Some(amount), charge, None)
+            } else {
+                (None, None, Some(unknown_attrs))
+            }
+        } else {
+            (None, None, None)
+        };
+        let nonexist = if policym.is_none() || pricem.is_none() || num_avail.is_none() {
+            Some(OrderLineCreateErrNonExistDto {
+                product_policy: policym.is_none(),
+                product_price: pricem.is_none(),
+                stock_seller: num_avail.is_none(),
+            })
+        } else {
+            None
+        };
+        let CartLineDto {
+            seller_id,
+            product_id,
+            quantity,
+            applied_attr,
+        } = req;
+        Ok(CartLinePreviewDto {
+            seller_id,
+            product_id,
+            applied_attr,
+            quantity,
+            amount,
+            attr_charge,
+            nonexist,
+            attr_vals,
+            shortage,
+            rsv_limit,
+        })
+    } // end of fn preview
+
+    fn preview_price(
+        req: &OrderLineRsvReqDto,
+        pricem: &ProductPriceModel,
+        currency_m: &OrderCurrencyModel,
+    ) -> DefaultResult<(PayAmountDto, String), AppError> {
+        let (lineprice, attrprice) = OrderLinePriceModel::finalize_price(req, pricem)?;
+        let curr_ex = currency_m.to_buyer_rate(req.seller_id)?;
+        let fraction_limit = curr_ex.name.amount_fraction_scale();
+        let charge = Decimal::new(attrprice.total_amount()? as i64, 0u32) * curr_ex.rate;
+        let charge = charge.trunc_with_scale(fraction_limit).to_string();
+        Ok((lineprice.into_paym_dto(curr_ex), charge))
+    }
+
+    // chosen attributes which are not available in current price of the product
+    fn unknown_attributes(&self, pricem: &ProductPriceModel) -> Vec<String> {
+        let avail = pricem.attrs_charge().applied_attributes();
+        Self::attr_keys(&self.chosen_attr)
+            .into_iter()
+            .filter(|k| !avail.contains(k))
+            .collect()
+    }
+
+    pub fn sort_attributes(attrs: &mut [OlineProductAttrDto]) {
+        attrs.sort_by_cached_key(|a| ProdAttriPriceModel::map_key(a.label_id.as_str(), &a.value));
+    }
+
+    fn attr_keys(attrs: &[OlineProductAttrDto]) -> Vec<String> {
+        attrs
+            .iter()
+            .map(|a| ProdAttriPriceModel::map_key(a.label_id.as_str(), &a.value))
+            .collect()
+    }
+
+    /// check whether the given attributes are the same set as the chosen
+    /// attributes of this line, regardless of their order.
+    pub fn match_attributes(&self, given: &[OlineProductAttrDto]) -> bool {
+        let mut keys = Self::attr_keys(given);
+        keys.sort();
+        Self::attr_keys(&self.chosen_attr) == keys
+    }
+
+    pub fn attr_set_key(&self) -> String {
+        Self::attr_keys(&self.chosen_attr).join(",")
+    }
+
+    pub(crate) fn serialize_attrs(&self) -> DefaultResult<String, AppError> {
+        serde_json::to_string(&self.chosen_attr).map_err(|e| AppError {
+            code: AppErrorCode::DataCorruption,
+            detail: Some(format!("cart-line-attr-serialize : {:?}", e)),
+        })
+    }
+
+    pub(crate) fn deserialize_attrs(
+        raw: &str,
+    ) -> DefaultResult<Vec<OlineProductAttrDto>, AppError> {
+        serde_json::from_str::<Vec<OlineProductAttrDto>>(raw).map_err(|e| AppError {
+            code: AppErrorCode::DataCorruption,
+            detail: Some(format!("cart-line-attr-deserialize : {:?}", e)),
+        })
+    }
+} // end of impl CartLineModel
+
 impl From<CartModel> for CartDto {
     fn from(value: CartModel) -> CartDto {
         CartDto {
@@ -65,25 +271,150 @@ impl CartModel {
     pub fn update(&mut self, data: CartDto) {
         let (new_title, d_lines) = (data.title, data.lines);
         self.title = new_title;
-        self.new_lines = d_lines
+        self.new_lines.clear();
+        // lines with the same product and attribute set in the request are
+        // merged, the quantity of the last one is applied
+        d_lines
             .into_iter()
-            .filter_map(|d| {
-                let result = self.get_line_mut(&d);
-                if let Some(v) = result {
+            .map(|d| {
+                if let Some(v) = self.get_line_mut(&d) {
                     v.qty_req = d.quantity;
-                    None
                 } else {
-                    Some(d)
+                    self.new_lines.push(CartLineModel::from(d));
                 }
             })
-            .map(CartLineModel::from)
-            .collect::<Vec<_>>();
+            .count();
     }
 
+    // a saved line matches only if the product and the chosen attribute set are the same
     fn get_line_mut(&mut self, item: &CartLineDto) -> Option<&mut CartLineModel> {
-        let result = self.saved_lines.iter_mut().find(|obj| {
-            obj.id_.store_id == item.seller_id && obj.id_.product_id == item.product_id
-        });
+        let given_attrs = item.applied_attr.as_deref().unwrap_or_default();
+        let result = self
+            .saved_lines
+            .iter_mut()
+            .chain(self.new_lines.iter_mut())
+            .find(|obj| {
+                obj.id_.store_id == item.seller_id
+                    && obj.id_.product_id == item.product_id
+                    && obj.match_attributes(given_attrs)
+            });
         result
     }
+
+    pub fn preview(
+        self,
+        ms_policy: &ProductPolicyModelSet,
+        ms_price: &[ProductPriceModelSet],
+        ms_stock: &StockLevelModelSet,
+        currency_m: &OrderCurrencyModel,
+    ) -> DefaultResult<CartPreviewRespDto, AppError> {
+        let Self {
+            title, saved_lines, ..
+        } = self;
+        let mut demand = CartStockDemand::new();
+        let lines = saved_lines
+            .into_iter()
+            .map(|line| line.preview(ms_policy, ms_price, ms_stock, currency_m, &mut demand))
+            .collect::<DefaultResult<Vec<_>, AppError>>()?;
+        Ok(CartPreviewRespDto {
+            title,
+            currency: currency_m.buyer.name.clone(),
+            lines,
+        })
+    }
+
+    // only the lines to convert are kept in the model after this call, lines
+    // with zero quantity are left in the cart on checkout
+    pub fn checkout_lines(&mut self) -> Vec<OrderLineRsvReqDto> {
+        self.saved_lines.retain(|l| l.qty_req > 0);
+        self.saved_lines
+            .iter()
+            .map(OrderLineRsvReqDto::from)
+            .collect()
+    }
 } // end of impl CartModel
+
+impl CartCollaboratorModel {
+    pub(crate) fn access_label(&self) -> &'static str {
+        match self.access {
+            CartAccessDto::Read => "read",
+            CartAccessDto::Edit => "edit",
+        }
+    }
+
+    pub(crate) fn try_from_label(usr_id: u32, label: &str) -> DefaultResult<Self, AppError> {
+        let access = match label {
+            "read" => CartAccessDto::Read,
+            "edit" => CartAccessDto::Edit,
+            _others => {
+                return Err(AppError {
+                    code: AppErrorCode::DataCorruption,
+                    detail: Some(format!("cart-access-label : {label}")),
+                })
+            }
+        };
+        Ok(Self { usr_id, access })
+    }
+}
+
+impl CartShareModel {
+    pub fn try_from_req(
+        owner: u32,
+        seq_num: u8,
+        data: CartShareDto,
+    ) -> DefaultResult<Self, CartShareErrorDto> {
+        let mut usr_ids = Vec::new();
+        let mut collaborators: Vec<CartCollaboratorModel> = Vec::new();
+        data.collaborators
+            .into_iter()
+            .map(|d| {
+                let duplicate = collaborators.iter().any(|c| c.usr_id == d.usr_id);
+                if d.usr_id == owner || duplicate {
+                    usr_ids.push(d.usr_id);
+                } else {
+                    collaborators.push(CartCollaboratorModel {
+                        usr_id: d.usr_id,
+                        access: d.access,
+                    });
+                }
+            })
+            .count();
+        if usr_ids.is_empty() {
+            Ok(Self {
+                owner,
+                seq_num,
+                collaborators,
+            })
+        } else {
+            usr_ids.sort();
+            usr_ids.dedup();
+            Err(CartShareErrorDto { usr_ids })
+        }
+    }
+
+    // edit access implies read access
+    pub fn permitted(&self, usr_id: u32, required: CartAccessDto) -> bool {
+        if usr_id == self.owner {
+            return true;
+        }
+        self.collaborators
+            .iter()
+            .find(|c| c.usr_id == usr_id)
+            .map(|c| c.access == CartAccessDto::Edit || required == CartAccessDto::Read)
+            .unwrap_or(false)
+    }
+} // end of impl CartShareModel
+
+impl From<CartShareModel> for CartShareDto {
+    fn from(value: CartShareModel) -> CartShareDto {
+        let collaborators = value
+            .collaborators
+            .into_iter()
+            .map(|c| CartCollaboratorDto {
+                usr_id: c.usr_id,
+                access: c.access,
+            })
+            .collect();
+        CartShareDto { collaborators }
+    }
+}
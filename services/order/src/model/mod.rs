@@ -3,9 +3,11 @@ mod currency;
 mod order;
 mod product_policy;
 mod product_price;
+mod shipment;
 mod stock_level;
+mod store_shipping;
 
-pub use cart::{CartLineModel, CartModel};
+pub use cart::{CartCollaboratorModel, CartLineModel, CartModel, CartShareModel};
 pub use currency::{CurrencyModel, CurrencyModelSet, OrderCurrencyModel};
 pub use order::{
     OlineDupError, OrderLineAppliedPolicyModel, OrderLineIdentity, OrderLineModel,
@@ -14,7 +16,9 @@ pub use order::{
 };
 pub use product_policy::{ProductPolicyModel, ProductPolicyModelSet};
 pub use product_price::{ProdAttriPriceModel, ProductPriceModel, ProductPriceModelSet};
+pub use shipment::{ShipmentLineModel, ShipmentModel};
 pub use stock_level::{
     ProductStockIdentity, ProductStockIdentity2, ProductStockModel, StockLevelModelSet,
     StockQtyRsvModel, StockQuantityModel, StoreStockModel,
 };
+pub use store_shipping::{ShippingFeeModel, StoreShipMethodModel, StoreShippingModel};
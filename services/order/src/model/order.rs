@@ -18,10 +18,13 @@ use ecommerce_common::model::BaseProductIdentity;
 use crate::api::dto::{ShippingDto, ShippingMethod, ShippingOptionDto};
 use crate::api::rpc::dto::{
     InventoryEditStockLevelDto, OrderLineStockReservingDto, OrderLineStockReturningDto,
+    StockLevelReturnDto,
 };
 use crate::api::web::dto::{
-    OrderCreateRespOkDto, OrderLineCreateErrorDto, OrderLineCreateErrorReason,
-    OrderLineReturnErrorDto, OrderLineReturnErrorReason, OrderLineReturnReqDto, OrderLineRsvReqDto,
+    OrderCreateRespOkDto, OrderDetailRespDto, OrderLineAppliedPolicyDto, OrderLineCancelErrorDto,
+    OrderLineCancelErrorReason, OrderLineCreateErrorDto, OrderLineCreateErrorReason,
+    OrderLineDetailDto, OrderLineQuantityDto, OrderLineReturnErrorDto, OrderLineReturnErrorReason,
+    OrderLineReturnReqDto, OrderLineRsvReqDto, ShipOptionMethodErrorReason,
     ShipOptionSellerErrorReason, ShippingErrorDto, ShippingOptionErrorDto,
 };
 
@@ -30,7 +33,9 @@ use crate::error::AppError;
 use crate::generate_custom_uid;
 
 use super::product_price::ProdAttriPriceModel;
-use super::{CurrencyModel, OrderCurrencyModel, ProductPolicyModel, ProductPriceModel};
+use super::{
+    CurrencyModel, OrderCurrencyModel, ProductPolicyModel, ProductPriceModel, StoreShippingModel,
+};
 
 pub struct ShippingOptionModel {
     pub seller_id: u32,
@@ -52,6 +57,8 @@ pub struct OrderLineIdentity {
 }
 
 pub struct OrderLineAppliedPolicyModel {
+    // version of the product policy applied when the order line was created
+    pub version: u32,
     pub reserved_until: DateTime<FixedOffset>,
     pub warranty_until: DateTime<FixedOffset>,
 }
@@ -68,7 +75,13 @@ pub struct OrderLineQuantityModel {
     pub reserved: u32,
     pub paid: u32,
     pub paid_last_update: Option<DateTime<FixedOffset>>,
-} // TODO, record number of items delivered
+    // number of unpaid items cancelled by buyer before the reservation expires,
+    // nonzero value means the line no longer accepts any payment
+    pub cancelled: u32,
+    // number of paid items which the buyer has confirmed receipt, only these
+    // items can be returned
+    pub delivered: u32,
+}
 
 pub struct OrderLineModel {
     id_: OrderLineIdentity,
@@ -112,10 +125,13 @@ impl From<ShippingOptionModel> for ShippingOptionDto {
 impl TryFrom<ShippingOptionDto> for ShippingOptionModel {
     type Error = ShippingOptionErrorDto;
     fn try_from(value: ShippingOptionDto) -> DefaultResult<Self, Self::Error> {
-        if value.seller_id == 0 {
+        let seller_err = (value.seller_id == 0).then_some(ShipOptionSellerErrorReason::Empty);
+        let method_err = matches!(value.method, ShippingMethod::Unknown)
+            .then_some(ShipOptionMethodErrorReason::Empty);
+        if seller_err.is_some() || method_err.is_some() {
             let e = Self::Error {
-                method: None,
-                seller_id: Some(ShipOptionSellerErrorReason::Empty),
+                method: method_err,
+                seller_id: seller_err,
             };
             Err(e)
         } else {
@@ -123,7 +139,8 @@ impl TryFrom<ShippingOptionDto> for ShippingOptionModel {
                 seller_id: value.seller_id,
                 method: value.method,
             })
-        } // TODO, will check whether the seller supports specific delivery service
+        } // whether the seller supports the delivery service is checked
+          // later in `ShippingModel::validate_options()`
     }
 }
 impl ShippingOptionModel {
@@ -162,6 +179,45 @@ impl ShippingOptionModel {
     }
 } // end of impl ShippingOptionModel
 
+impl ShippingModel {
+    /// reject the options whose seller does not deliver items with the chosen
+    /// method, or to the country of the shipping address. Each element of `saved`
+    /// contains shipping methods supported by a seller.
+    pub fn validate_options(
+        &self,
+        saved: &[StoreShippingModel],
+    ) -> DefaultResult<(), Vec<Option<ShippingOptionErrorDto>>> {
+        let dst = self.address.as_ref().map(|a| &a.country);
+        let results = self
+            .option
+            .iter()
+            .map(|opt| {
+                let result = saved.iter().find(|m| m.store_id == opt.seller_id);
+                if let Some(store) = result {
+                    if store.support(&opt.method, dst) {
+                        None
+                    } else {
+                        Some(ShippingOptionErrorDto {
+                            seller_id: None,
+                            method: Some(ShipOptionMethodErrorReason::NotSupport),
+                        })
+                    }
+                } else {
+                    Some(ShippingOptionErrorDto {
+                        seller_id: Some(ShipOptionSellerErrorReason::NotSupport),
+                        method: None,
+                    })
+                }
+            })
+            .collect::<Vec<_>>();
+        if results.iter().any(Option::is_some) {
+            Err(results)
+        } else {
+            Ok(())
+        }
+    }
+} // end of impl ShippingModel
+
 impl From<ShippingModel> for ShippingDto {
     fn from(value: ShippingModel) -> ShippingDto {
         let (contact, pa, opt) = (value.contact.into(), value.address, value.option);
@@ -242,14 +298,17 @@ impl OrderLineIdentity {
     pub fn attrs_seq_num(&self) -> u16 {
         self._attr_set_req
     }
-    fn compare_raw(&self, d: (u32, u64, u16)) -> bool {
+    pub(super) fn compare_raw(&self, d: (u32, u64, u16)) -> bool {
         (self.store_id() == d.0) && (self.product_id() == d.1) && (self.attrs_seq_num() == d.2)
     }
 }
 
 impl OrderLineQuantityModel {
     pub fn has_unpaid(&self) -> bool {
-        self.reserved > self.paid
+        self.reserved > (self.paid + self.cancelled)
+    }
+    pub fn num_unpaid(&self) -> u32 {
+        self.reserved - self.paid - self.cancelled
     }
 }
 
@@ -260,7 +319,7 @@ impl From<(u32, u32)> for OrderLinePriceModel {
 }
 
 impl OrderLinePriceModel {
-    fn finalize_price(
+    pub(super) fn finalize_price(
         data: &OrderLineRsvReqDto,
         pricem: &ProductPriceModel,
     ) -> DefaultResult<(Self, ProdAttriPriceModel), AppError> {
@@ -283,7 +342,7 @@ impl OrderLinePriceModel {
         Ok((obj, attrprice))
     }
 
-    fn into_paym_dto(self, curr_ex: CurrencyModel) -> PayAmountDto {
+    pub(super) fn into_paym_dto(self, curr_ex: CurrencyModel) -> PayAmountDto {
         let fraction_limit = curr_ex.name.amount_fraction_scale();
         let p_unit_seller = Decimal::new(self.unit() as i64, 0u32);
         let p_total_seller = Decimal::new(self.total() as i64, 0u32);
@@ -329,6 +388,8 @@ impl OrderLineModel {
     ) -> DefaultResult<(), AppError> {
         let result = if data.product_id != policym.product_id {
             Err("product-policy, id")
+        } else if data.seller_id != policym.store_id {
+            Err("product-policy, store")
         } else if data.product_id != pricem.product_id() {
             Err("product-price, id")
         } else {
@@ -339,7 +400,7 @@ impl OrderLineModel {
             detail: Some(msg.to_string()),
         })
     }
-    fn validate_rsv_limit(
+    pub(super) fn validate_rsv_limit(
         data: &OrderLineRsvReqDto,
         policym: &ProductPolicyModel,
     ) -> DefaultResult<(), AppError> {
@@ -393,8 +454,11 @@ impl OrderLineModel {
             reserved: data.quantity,
             paid: 0,
             paid_last_update: None,
+            cancelled: 0,
+            delivered: 0,
         };
         let policy = OrderLineAppliedPolicyModel {
+            version: policym.version,
             reserved_until,
             warranty_until,
         };
@@ -427,7 +491,9 @@ impl OrderLineModel {
                 });
                 let possible_error = if let Some(m) = result {
                     let new_paid_qty = m.qty.paid + d.qty;
-                    if m.qty.reserved >= new_paid_qty {
+                    if m.qty.cancelled > 0 {
+                        Some(OrderLinePayUpdateErrorReason::Cancelled)
+                    } else if m.qty.reserved >= new_paid_qty {
                         if let Some(old_dt) = m.qty.paid_last_update.as_ref() {
                             if old_dt < &charge_time {
                                 m.qty.paid = new_paid_qty;
@@ -457,13 +523,80 @@ impl OrderLineModel {
             .collect()
     } // end of update_payments
 
-    pub(crate) fn num_reserved(&self, time_now: DateTime<FixedOffset>) -> u32 {
-        if time_now < self.policy.reserved_until {
-            self.qty.reserved
-        } else {
-            self.qty.paid
+    // errors for the lines which have been cancelled, reported when the lines are
+    // cancelled concurrently after `update_payments()` examined them
+    pub fn pay_cancelled_errors(models: &[OrderLineModel]) -> Vec<OrderLinePayUpdateErrorDto> {
+        models
+            .iter()
+            .filter(|m| m.qty.cancelled > 0)
+            .map(|m| OrderLinePayUpdateErrorDto {
+                seller_id: m.id().store_id(),
+                product_id: m.id().product_id(),
+                attr_set_seq: m.id().attrs_seq_num(),
+                reason: OrderLinePayUpdateErrorReason::Cancelled,
+            })
+            .collect()
+    }
+
+    pub fn cancel_unpaid(
+        models: &mut [OrderLineModel],
+        pids: Vec<OrderLineIdentity>,
+        time_now: DateTime<FixedOffset>,
+    ) -> Vec<OrderLineCancelErrorDto> {
+        let errors = pids
+            .iter()
+            .filter_map(|pid| {
+                let result = models.iter().find(|m| m.id() == pid);
+                let possible_error = if let Some(m) = result {
+                    if m.qty.cancelled > 0 {
+                        Some(OrderLineCancelErrorReason::DuplicateCancel)
+                    } else if !m.qty.has_unpaid() {
+                        Some(OrderLineCancelErrorReason::AlreadyPaid)
+                    } else if m.policy.reserved_until <= time_now {
+                        // the expired reservation will be discarded by scheduled job
+                        Some(OrderLineCancelErrorReason::ReservationExpired)
+                    } else {
+                        None
+                    }
+                } else {
+                    Some(OrderLineCancelErrorReason::NotExist)
+                };
+                possible_error.map(|reason| OrderLineCancelErrorDto {
+                    seller_id: pid.store_id(),
+                    product_id: pid.product_id(),
+                    attr_set_seq: pid.attrs_seq_num(),
+                    reason,
+                })
+            })
+            .collect::<Vec<_>>();
+        if errors.is_empty() {
+            models
+                .iter_mut()
+                .filter(|m| pids.contains(m.id()))
+                .for_each(|m| m.qty.cancelled = m.qty.num_unpaid());
+        }
+        errors
+    } // end of fn cancel_unpaid
+
+    // stock reserved for the cancelled quantity of the lines, to be returned
+    // along with the cancellation
+    pub fn cancelled_stock_return(oid: &str, models: &[OrderLineModel]) -> StockLevelReturnDto {
+        let items = models
+            .iter()
+            .filter(|m| m.qty.cancelled > 0)
+            .map(|m| InventoryEditStockLevelDto {
+                store_id: m.id().store_id(),
+                product_id: m.id().product_id(),
+                qty_add: m.qty.cancelled as i32,
+                expiry: m.policy.reserved_until,
+            })
+            .collect::<Vec<_>>();
+        StockLevelReturnDto {
+            items,
+            order_id: oid.to_string(),
         }
     }
+
     pub fn id(&self) -> &OrderLineIdentity {
         &self.id_
     }
@@ -491,6 +624,36 @@ impl OrderLineModel {
             amount: price.into_paym_dto(curr_m),
         } // TODO, add attribute pricing, and attr-set-seq-num to this dto object
     }
+
+    fn into_detail_dto(self, curr_m: CurrencyModel) -> OrderLineDetailDto {
+        let Self {
+            id_,
+            price,
+            policy,
+            qty,
+            attrs_charge: _,
+        } = self;
+        let quantity = OrderLineQuantityDto {
+            reserved: qty.reserved,
+            paid: qty.paid,
+            paid_last_update: qty.paid_last_update.map(|t| t.to_rfc3339()),
+            cancelled: qty.cancelled,
+            delivered: qty.delivered,
+        };
+        let policy = OrderLineAppliedPolicyDto {
+            version: policy.version,
+            reserved_until: policy.reserved_until.to_rfc3339(),
+            warranty_until: policy.warranty_until.to_rfc3339(),
+        };
+        OrderLineDetailDto {
+            seller_id: id_.store_id(),
+            product_id: id_.product_id(),
+            attr_set_seq: id_.attrs_seq_num(),
+            quantity,
+            policy,
+            amount: price.into_paym_dto(curr_m),
+        }
+    }
 } // end of impl OrderLineModel
 
 impl From<OrderLineModel> for OrderLineStockReservingDto {
@@ -505,8 +668,8 @@ impl From<OrderLineModel> for OrderLineStockReservingDto {
 
 impl<'a> From<&'a OrderLineModel> for InventoryEditStockLevelDto {
     fn from(value: &'a OrderLineModel) -> InventoryEditStockLevelDto {
-        assert!(value.qty.reserved >= value.qty.paid);
-        let num_returning = (value.qty.reserved - value.qty.paid) as i32;
+        assert!(value.qty.reserved >= (value.qty.paid + value.qty.cancelled));
+        let num_returning = value.qty.num_unpaid() as i32;
         InventoryEditStockLevelDto {
             store_id: value.id_.store_id(),
             product_id: value.id_.product_id(),
@@ -654,6 +817,40 @@ impl OrderLineModelSet {
             Err(errors.remove(0))
         }
     }
+    pub(crate) fn detail_dto(
+        oid: String,
+        usr_id: u32,
+        ctime: DateTime<FixedOffset>,
+        olines: Vec<OrderLineModel>,
+        currency_m: OrderCurrencyModel,
+        billing: BillingModel,
+        shipping: ShippingModel,
+    ) -> DefaultResult<OrderDetailRespDto, AppError> {
+        let mut errors = Vec::new();
+        let lines = olines
+            .into_iter()
+            .filter_map(|line| {
+                currency_m
+                    .to_buyer_rate(line.id_.store_id())
+                    .map_err(|e| errors.push(e))
+                    .ok()
+                    .map(|rate| line.into_detail_dto(rate))
+            })
+            .collect::<Vec<_>>();
+        if errors.is_empty() {
+            Ok(OrderDetailRespDto {
+                order_id: oid,
+                usr_id,
+                time: ctime.to_rfc3339(),
+                currency: currency_m.into(),
+                lines,
+                billing: billing.into(),
+                shipping: shipping.into(),
+            })
+        } else {
+            Err(errors.remove(0))
+        }
+    }
     pub fn id(&self) -> &String {
         &self.order_id
     }
@@ -669,7 +866,7 @@ impl OrderLineModelSet {
     pub fn lines(&self) -> &[OrderLineModel] {
         &self.lines
     }
-    #[cfg(feature = "mariadb")]
+    #[cfg(any(feature = "mariadb", feature = "postgres"))]
     pub(crate) fn append_lines(&mut self, new: Vec<OrderLineModel>) {
         self.lines.extend(new);
     }
@@ -762,7 +959,7 @@ impl OrderReturnModel {
                             0u32
                         };
                         let tot_num_return = num_returned + d.quantity;
-                        if tot_num_return > oline.num_reserved(time_now) {
+                        if tot_num_return > oline.qty.delivered {
                             Some(OrderLineReturnErrorReason::QtyLimitExceed)
                         } else if let Some(r) = result {
                             if r.qty.contains_key(&time_now) {
@@ -2,32 +2,39 @@ use std::cmp::PartialEq;
 use std::result::Result as DefaultResult;
 use std::vec::Vec;
 
+use chrono::{DateTime, FixedOffset};
+
 use ecommerce_common::error::AppErrorCode;
 
 use crate::api::web::dto::{
     ProductPolicyClientErrorDto, ProductPolicyClientLimitDto, ProductPolicyDto,
-    ProductPolicyNumRsvLimitDto,
+    ProductPolicyHistoryRespDto, ProductPolicyNumRsvLimitDto, ProductPolicyVersionDto,
 };
 use crate::error::AppError;
 
 #[derive(Debug)]
 pub struct ProductPolicyModel {
+    // each store front could configure its own policy even with the same product
+    pub store_id: u32,
     pub product_id: u64,
+    // policy is append-only, every change is saved as new version, so the order
+    // lines created under previous version can still refer to it.
+    pub version: u32,
+    pub effective_from: DateTime<FixedOffset>,
     pub auto_cancel_secs: u32,
     pub warranty_hours: u32,
     // indicate max/min number of items for each type of product
     // per order transaction.
     pub max_num_rsv: u16,
     pub min_num_rsv: u16,
-    // TODO, add following field:
-    // - `store_id`: u32, each store front could configure its own policy
-    //    even with the same product.
     pub is_create: bool,
 }
 
 impl PartialEq for ProductPolicyModel {
     fn eq(&self, other: &Self) -> bool {
-        (self.product_id == other.product_id)
+        (self.store_id == other.store_id)
+            && (self.product_id == other.product_id)
+            && (self.version == other.version)
             && (self.auto_cancel_secs == other.auto_cancel_secs)
             && (self.warranty_hours == other.warranty_hours)
             && (self.max_num_rsv == other.max_num_rsv)
@@ -48,6 +55,7 @@ impl ProductPolicyModelSet {
     ) -> DefaultResult<(), Vec<ProductPolicyClientErrorDto>> {
         if newdata.is_empty() {
             let ce = ProductPolicyClientErrorDto {
+                seller_id: 0u32,
                 product_id: 0u64,
                 auto_cancel_secs: None,
                 warranty_hours: None,
@@ -89,6 +97,7 @@ impl ProductPolicyModelSet {
 
                 if num_rsv.is_some() || auto_cancel_secs.is_some() || warranty_hours.is_some() {
                     let ce = ProductPolicyClientErrorDto {
+                        seller_id: item.seller_id,
                         product_id: item.product_id,
                         auto_cancel_secs,
                         warranty_hours,
@@ -108,7 +117,15 @@ impl ProductPolicyModelSet {
         }
     } // end of fn validate
 
-    pub fn update(mut self, newdata: Vec<ProductPolicyDto>) -> DefaultResult<Self, AppError> {
+    /// append new version of policy for each product, the previous versions are
+    /// kept unchanged. The policies in `self` are expected to be latest versions
+    /// of the products, no new version is added if given data is the same as the
+    /// latest version.
+    pub fn update(
+        mut self,
+        newdata: Vec<ProductPolicyDto>,
+        time_now: DateTime<FixedOffset>,
+    ) -> DefaultResult<Self, AppError> {
         let zero_num_rsv = 0u16;
         let mut _new_objs = newdata
             .into_iter()
@@ -117,28 +134,66 @@ impl ProductPolicyModelSet {
                 let min_num_rsv = item.min_num_rsv.take().unwrap_or(zero_num_rsv);
                 let result = self
                     .policies
-                    .iter_mut()
-                    .find(|o| o.product_id == item.product_id);
-                if let Some(obj) = result {
-                    obj.auto_cancel_secs = item.auto_cancel_secs;
-                    obj.warranty_hours = item.warranty_hours;
-                    obj.max_num_rsv = max_num_rsv;
-                    obj.min_num_rsv = min_num_rsv;
-                    None
+                    .iter()
+                    .filter(|o| o.store_id == item.seller_id && o.product_id == item.product_id)
+                    .max_by_key(|o| o.version);
+                let version = if let Some(obj) = result {
+                    let unchanged = (obj.auto_cancel_secs == item.auto_cancel_secs)
+                        && (obj.warranty_hours == item.warranty_hours)
+                        && (obj.max_num_rsv == max_num_rsv)
+                        && (obj.min_num_rsv == min_num_rsv);
+                    if unchanged {
+                        return None;
+                    }
+                    obj.version + 1
                 } else {
-                    Some(ProductPolicyModel {
-                        is_create: true,
-                        product_id: item.product_id,
-                        max_num_rsv,
-                        min_num_rsv,
-                        auto_cancel_secs: item.auto_cancel_secs,
-                        warranty_hours: item.warranty_hours,
-                    })
-                }
+                    0
+                };
+                Some(ProductPolicyModel {
+                    is_create: true,
+                    store_id: item.seller_id,
+                    product_id: item.product_id,
+                    version,
+                    effective_from: time_now,
+                    max_num_rsv,
+                    min_num_rsv,
+                    auto_cancel_secs: item.auto_cancel_secs,
+                    warranty_hours: item.warranty_hours,
+                })
             })
             .collect();
         self.policies.append(&mut _new_objs);
         Ok(self)
     } // end of fn update
-      // TODO, consider append-only approach, for the order lines which apply previous setup
+
+    pub(crate) fn history_dto(
+        self,
+        seller_id: u32,
+        product_id: u64,
+    ) -> ProductPolicyHistoryRespDto {
+        let mut policies = self.policies;
+        policies.sort_by_key(|m| m.version);
+        let versions = policies
+            .into_iter()
+            .map(ProductPolicyVersionDto::from)
+            .collect();
+        ProductPolicyHistoryRespDto {
+            seller_id,
+            product_id,
+            versions,
+        }
+    }
 } // end of impl ProductPolicyModelSet
+
+impl From<ProductPolicyModel> for ProductPolicyVersionDto {
+    fn from(value: ProductPolicyModel) -> Self {
+        Self {
+            version: value.version,
+            effective_from: value.effective_from.to_rfc3339(),
+            auto_cancel_secs: value.auto_cancel_secs,
+            warranty_hours: value.warranty_hours,
+            max_num_rsv: value.max_num_rsv,
+            min_num_rsv: value.min_num_rsv,
+        }
+    }
+}
@@ -96,7 +96,7 @@ impl<'a> TryFrom<&'a ProdAttrPriceSetDto> for ProdAttriPriceModel {
 }
 
 impl ProdAttriPriceModel {
-    fn map_key(label_id: &str, value: &ProdAttrValueDto) -> String {
+    pub(super) fn map_key(label_id: &str, value: &ProdAttrValueDto) -> String {
         let val_str = match value {
             ProdAttrValueDto::Int(n) => n.to_string(),
             ProdAttrValueDto::Str(s) => s.clone(),
@@ -189,18 +189,18 @@ impl ProductPriceModel {
     pub fn product_id(&self) -> u64 {
         self.product_id
     }
-    #[cfg(feature = "mariadb")]
+    #[cfg(any(feature = "mariadb", feature = "postgres"))]
     pub(crate) fn start_after(&self) -> DateTime<FixedOffset> {
         self.start_after
     }
-    #[cfg(feature = "mariadb")]
+    #[cfg(any(feature = "mariadb", feature = "postgres"))]
     pub(crate) fn end_before(&self) -> DateTime<FixedOffset> {
         self.end_before
     }
     pub(crate) fn attrs_charge(&self) -> &ProdAttriPriceModel {
         &self.attributes
     }
-    #[cfg(feature = "mariadb")]
+    #[cfg(any(feature = "mariadb", feature = "postgres"))]
     pub(crate) fn split_by_update_state(ms: Vec<Self>) -> (Vec<Self>, Vec<Self>) {
         let (mut l_add, mut l_modify) = (vec![], vec![]);
         ms.into_iter()
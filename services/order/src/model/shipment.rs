@@ -0,0 +1,217 @@
+use std::result::Result as DefaultResult;
+use std::vec::Vec;
+
+use chrono::{DateTime, FixedOffset};
+
+use crate::api::dto::{ShipmentLineDto, ShippingMethod};
+use crate::api::rpc::dto::{
+    ShipmentCreateReqDto, ShipmentErrorDto, ShipmentErrorReason, ShipmentLineErrorDto,
+    ShipmentLineErrorReason,
+};
+use crate::api::web::dto::{ShipmentDto, ShipmentReceiptErrorReason};
+
+use super::OrderLineModel;
+
+// the tracking number is also part of the key to the shipment in
+// data store, limit it to short alphanumeric string
+const HARD_LIMIT_TRACKING_NUM_SIZE: usize = 32;
+
+pub struct ShipmentLineModel {
+    pub product_id: u64,
+    pub attr_set_seq: u16,
+    pub qty: u32,
+}
+
+pub struct ShipmentModel {
+    pub seller_id: u32,
+    pub carrier: ShippingMethod,
+    pub tracking_num: String,
+    pub create_time: DateTime<FixedOffset>,
+    // set once the buyer confirms receipt of all the items in the shipment
+    pub received_time: Option<DateTime<FixedOffset>>,
+    pub lines: Vec<ShipmentLineModel>,
+}
+
+impl From<ShipmentLineDto> for ShipmentLineModel {
+    fn from(value: ShipmentLineDto) -> Self {
+        Self {
+            product_id: value.product_id,
+            attr_set_seq: value.attr_set_seq,
+            qty: value.qty,
+        }
+    }
+}
+
+impl From<ShipmentLineModel> for ShipmentLineDto {
+    fn from(value: ShipmentLineModel) -> Self {
+        Self {
+            product_id: value.product_id,
+            attr_set_seq: value.attr_set_seq,
+            qty: value.qty,
+        }
+    }
+}
+
+impl From<ShipmentModel> for ShipmentDto {
+    fn from(value: ShipmentModel) -> Self {
+        let ShipmentModel {
+            seller_id,
+            carrier,
+            tracking_num,
+            create_time,
+            received_time,
+            lines,
+        } = value;
+        Self {
+            seller_id,
+            carrier,
+            tracking_num,
+            create_time: create_time.to_rfc3339(),
+            received_time: received_time.map(|t| t.to_rfc3339()),
+            lines: lines.into_iter().map(ShipmentLineDto::from).collect(),
+        }
+    }
+}
+
+impl ShipmentModel {
+    pub fn try_from_req(
+        data: ShipmentCreateReqDto,
+        create_time: DateTime<FixedOffset>,
+    ) -> DefaultResult<Self, ShipmentErrorDto> {
+        let ShipmentCreateReqDto {
+            order_id: _,
+            seller_id,
+            carrier,
+            tracking_num,
+            lines,
+        } = data;
+        let tracking_num_valid = !tracking_num.is_empty()
+            && tracking_num.len() <= HARD_LIMIT_TRACKING_NUM_SIZE
+            && tracking_num.chars().all(|c| c.is_ascii_alphanumeric());
+        let reason = if matches!(carrier, ShippingMethod::Unknown) {
+            Some(ShipmentErrorReason::InvalidCarrier)
+        } else if !tracking_num_valid {
+            Some(ShipmentErrorReason::InvalidTrackingNum)
+        } else if lines.is_empty() {
+            Some(ShipmentErrorReason::EmptyLines)
+        } else {
+            None
+        };
+        let line_errors = lines
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, d)| {
+                let dup = lines[..idx]
+                    .iter()
+                    .any(|p| p.product_id == d.product_id && p.attr_set_seq == d.attr_set_seq);
+                let opt = if d.qty == 0 {
+                    Some(ShipmentLineErrorReason::InvalidQuantity)
+                } else if dup {
+                    Some(ShipmentLineErrorReason::DuplicateLine)
+                } else {
+                    None
+                };
+                opt.map(|reason| ShipmentLineErrorDto {
+                    product_id: d.product_id,
+                    attr_set_seq: d.attr_set_seq,
+                    reason,
+                })
+            })
+            .collect::<Vec<_>>();
+        if reason.is_some() || !line_errors.is_empty() {
+            return Err(ShipmentErrorDto {
+                reason,
+                lines: line_errors,
+            });
+        }
+        Ok(Self {
+            seller_id,
+            carrier,
+            tracking_num,
+            create_time,
+            received_time: None,
+            lines: lines.into_iter().map(ShipmentLineModel::from).collect(),
+        })
+    } // end of fn try_from_req
+
+    fn num_shipped(saved: &[Self], seller_id: u32, product_id: u64, attr_set_seq: u16) -> u32 {
+        saved
+            .iter()
+            .filter(|s| s.seller_id == seller_id)
+            .flat_map(|s| s.lines.iter())
+            .filter(|l| l.product_id == product_id && l.attr_set_seq == attr_set_seq)
+            .map(|l| l.qty)
+            .sum::<u32>()
+    }
+
+    /// check the new shipment against the order lines and the shipments saved
+    /// previously in the same order, only paid items can be shipped and each
+    /// of them is shipped at most once.
+    pub fn validate(
+        &self,
+        o_lines: &[OrderLineModel],
+        saved: &[Self],
+    ) -> DefaultResult<(), ShipmentErrorDto> {
+        let dup_tracking = saved
+            .iter()
+            .any(|s| s.seller_id == self.seller_id && s.tracking_num == self.tracking_num);
+        if dup_tracking {
+            return Err(ShipmentErrorDto {
+                reason: Some(ShipmentErrorReason::DuplicateTrackingNum),
+                lines: Vec::new(),
+            });
+        }
+        let line_errors = self
+            .lines
+            .iter()
+            .filter_map(|d| {
+                let args = (self.seller_id, d.product_id, d.attr_set_seq);
+                let result = o_lines.iter().find(|m| m.id().compare_raw(args));
+                let opt = if let Some(oline) = result {
+                    let shipped = Self::num_shipped(saved, args.0, args.1, args.2);
+                    if (shipped + d.qty) > oline.qty.paid {
+                        Some(ShipmentLineErrorReason::QtyLimitExceed)
+                    } else {
+                        None
+                    }
+                } else {
+                    Some(ShipmentLineErrorReason::NotExist)
+                };
+                opt.map(|reason| ShipmentLineErrorDto {
+                    product_id: d.product_id,
+                    attr_set_seq: d.attr_set_seq,
+                    reason,
+                })
+            })
+            .collect::<Vec<_>>();
+        if line_errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ShipmentErrorDto {
+                reason: None,
+                lines: line_errors,
+            })
+        }
+    } // end of fn validate
+
+    pub fn confirm_receipt(
+        &mut self,
+        o_lines: &mut [OrderLineModel],
+        time_now: DateTime<FixedOffset>,
+    ) -> DefaultResult<(), ShipmentReceiptErrorReason> {
+        if self.received_time.is_some() {
+            return Err(ShipmentReceiptErrorReason::AlreadyReceived);
+        }
+        self.lines
+            .iter()
+            .map(|d| {
+                let args = (self.seller_id, d.product_id, d.attr_set_seq);
+                if let Some(oline) = o_lines.iter_mut().find(|m| m.id().compare_raw(args)) {
+                    oline.qty.delivered += d.qty;
+                }
+            })
+            .count();
+        self.received_time = Some(time_now);
+        Ok(())
+    }
+} // end of impl ShipmentModel
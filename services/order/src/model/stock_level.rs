@@ -481,6 +481,19 @@ impl StockLevelModelSet {
         self.return_common(data, StoreStockModel::return_by_expiry)
     }
 
+    // total number of items available for a product across all expiry time,
+    // `None` if the store or the product does not exist in the model set
+    pub fn num_avail(&self, store_id: u32, product_id: u64) -> Option<u32> {
+        let store = self.stores.iter().find(|m| m.store_id == store_id)?;
+        let mut items = store
+            .products
+            .iter()
+            .filter(|p| p.id_ == product_id)
+            .peekable();
+        items.peek()?;
+        Some(items.map(|p| p.quantity.num_avail()).sum())
+    }
+
     fn sort_by_expiry(&mut self, ascending: bool) {
         // to ensure the items that expire soon will be taken first
         self.stores
@@ -0,0 +1,98 @@
+use std::result::Result as DefaultResult;
+use std::vec::Vec;
+
+use ecommerce_common::api::dto::CountryCode;
+use ecommerce_common::error::AppErrorCode;
+
+use crate::api::dto::ShippingMethod;
+use crate::api::rpc::dto::{StoreShippingFeeDto, StoreShippingMethodDto, StoreShippingSyncDto};
+use crate::error::AppError;
+
+pub struct ShippingFeeModel {
+    pub country: CountryCode, // destination
+    pub amount: u32,          // smallest unit in seller's currency
+}
+
+pub struct StoreShipMethodModel {
+    pub method: ShippingMethod,
+    pub fees: Vec<ShippingFeeModel>,
+}
+
+// delivery services supported by a store, synchronized from the storefront
+// service, the buyer can only choose one of these methods in a new order
+pub struct StoreShippingModel {
+    pub store_id: u32,
+    pub methods: Vec<StoreShipMethodModel>,
+}
+
+impl From<StoreShippingFeeDto> for ShippingFeeModel {
+    fn from(value: StoreShippingFeeDto) -> Self {
+        Self {
+            country: value.country,
+            amount: value.amount,
+        }
+    }
+}
+
+impl TryFrom<StoreShippingMethodDto> for StoreShipMethodModel {
+    type Error = String;
+    fn try_from(value: StoreShippingMethodDto) -> DefaultResult<Self, Self::Error> {
+        let StoreShippingMethodDto { method, fees } = value;
+        let label = String::from(method.clone());
+        if matches!(method, ShippingMethod::Unknown) {
+            return Err(format!("invalid-method: {label}"));
+        }
+        if fees.is_empty() {
+            return Err(format!("empty-fees, method: {label}"));
+        }
+        let invalid = fees.iter().enumerate().any(|(idx, d)| {
+            matches!(d.country, CountryCode::Unknown)
+                || fees[..idx].iter().any(|p| p.country == d.country)
+        });
+        if invalid {
+            return Err(format!("invalid-destination, method: {label}"));
+        }
+        let fees = fees.into_iter().map(ShippingFeeModel::from).collect();
+        Ok(Self { method, fees })
+    }
+}
+
+impl TryFrom<StoreShippingSyncDto> for StoreShippingModel {
+    type Error = AppError;
+    fn try_from(value: StoreShippingSyncDto) -> DefaultResult<Self, Self::Error> {
+        let StoreShippingSyncDto { store_id, methods } = value;
+        let dup = methods
+            .iter()
+            .enumerate()
+            .any(|(idx, d)| methods[..idx].iter().any(|p| p.method == d.method));
+        if store_id == 0 || dup {
+            let detail = format!("store:{store_id}, dup-method:{dup}");
+            return Err(AppError {
+                code: AppErrorCode::InvalidInput,
+                detail: Some(detail),
+            });
+        }
+        let methods = methods
+            .into_iter()
+            .map(StoreShipMethodModel::try_from)
+            .collect::<DefaultResult<Vec<_>, String>>()
+            .map_err(|msg| AppError {
+                code: AppErrorCode::InvalidInput,
+                detail: Some(format!("store:{store_id}, {msg}")),
+            })?;
+        Ok(Self { store_id, methods })
+    }
+}
+
+impl StoreShippingModel {
+    /// check whether the store delivers items to the destination country
+    /// using the given method, the destination is omitted if the buyer
+    /// does not provide physical address.
+    pub fn support(&self, method: &ShippingMethod, dst: Option<&CountryCode>) -> bool {
+        self.methods
+            .iter()
+            .find(|m| &m.method == method)
+            .map(|m| dst.is_none_or(|c| m.fees.iter().any(|f| &f.country == c)))
+            .unwrap_or(false)
+    }
+}
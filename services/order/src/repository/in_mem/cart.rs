@@ -12,7 +12,7 @@ use crate::datastore::{
     AppInMemFetchedSingleTable,
 };
 use crate::error::AppError;
-use crate::model::{CartLineModel, CartModel};
+use crate::model::{CartCollaboratorModel, CartLineModel, CartModel, CartShareModel};
 use crate::repository::AbsCartRepo;
 
 #[allow(non_snake_case)]
@@ -44,15 +44,23 @@ mod CartTable {
 
 #[allow(non_snake_case)]
 mod CartLineTable {
-    use super::{AppInMemFetchedSingleTable, BaseProductIdentity, CartModel, HashMap};
+    use super::{AppInMemFetchedSingleTable, CartLineModel, CartModel, HashMap};
 
     #[allow(clippy::redundant_static_lifetimes)]
     pub(super) const LABEL: &'static str = "cart_line";
 
     pub(super) struct UpdateArg(pub(super) CartModel);
+    pub(super) struct DeleteArg(pub(super) CartModel);
 
-    fn pkey(usr_id: u32, seq: u8, id_: BaseProductIdentity) -> String {
-        format!("{}-{}-{}-{}", usr_id, seq, id_.store_id, id_.product_id)
+    // the chosen attribute set is appended to the key, so different variants
+    // of the same product can be saved in separate lines
+    fn pkey(usr_id: u32, seq: u8, line: &CartLineModel) -> String {
+        let id_ = &line.id_;
+        let attr_set = line.attr_set_key();
+        format!(
+            "{}-{}-{}-{}-{}",
+            usr_id, seq, id_.store_id, id_.product_id, attr_set
+        )
     }
 
     impl From<UpdateArg> for AppInMemFetchedSingleTable {
@@ -65,16 +73,50 @@ mod CartLineTable {
             );
             saved_lines.extend(new_lines);
             let iter0 = saved_lines.into_iter().map(|line| {
-                let (id_, qty) = (line.id_, line.qty_req);
-                let key = pkey(usr_id, seq, id_);
-                let row = vec![qty.to_string()];
+                let key = pkey(usr_id, seq, &line);
+                let attrs = line.serialize_attrs().unwrap();
+                let row = vec![line.qty_req.to_string(), attrs];
                 (key, row)
             });
             HashMap::from_iter(iter0)
         }
     }
+    impl From<DeleteArg> for Vec<String> {
+        fn from(value: DeleteArg) -> Vec<String> {
+            let (usr_id, seq) = (value.0.owner, value.0.seq_num);
+            value
+                .0
+                .saved_lines
+                .iter()
+                .map(|line| pkey(usr_id, seq, line))
+                .collect()
+        }
+    }
 } // end of inner-mod CartLineTable
 
+#[allow(non_snake_case)]
+mod CartShareTable {
+    use super::{AppInMemFetchedSingleTable, CartShareModel, HashMap};
+
+    #[allow(clippy::redundant_static_lifetimes)]
+    pub(super) const LABEL: &'static str = "cart_share";
+
+    pub(super) struct UpdateArg(pub(super) CartShareModel);
+
+    impl From<UpdateArg> for AppInMemFetchedSingleTable {
+        fn from(value: UpdateArg) -> AppInMemFetchedSingleTable {
+            let (owner, seq, collaborators) =
+                (value.0.owner, value.0.seq_num, value.0.collaborators);
+            let iter0 = collaborators.into_iter().map(|c| {
+                let key = format!("{}-{}-{}", owner, seq, c.usr_id);
+                let row = vec![c.access_label().to_string()];
+                (key, row)
+            });
+            HashMap::from_iter(iter0)
+        }
+    }
+} // end of inner-mod CartShareTable
+
 struct InnerFilterKeyOp {
     usr_id: u32,
     seq_num: u8,
@@ -117,17 +159,29 @@ impl TryFrom<(String, Vec<String>)> for CartLineModel {
             tokens.next().unwrap().parse().unwrap(),
         );
         let qty_req = row.remove(0).parse().unwrap();
+        let chosen_attr = CartLineModel::deserialize_attrs(row.remove(0).as_str())?;
         let out = CartLineModel {
             id_: BaseProductIdentity {
                 store_id,
                 product_id,
             },
+            chosen_attr,
             qty_req,
         };
         Ok(out)
     }
 }
 
+impl TryFrom<(String, Vec<String>)> for CartCollaboratorModel {
+    type Error = AppError;
+    fn try_from(value: (String, Vec<String>)) -> DefaultResult<Self, Self::Error> {
+        let (key, row) = (value.0, value.1);
+        // skip first two token, owner and seq-num
+        let usr_id = key.split('-').nth(2).unwrap().parse().unwrap();
+        CartCollaboratorModel::try_from_label(usr_id, row[0].as_str())
+    }
+}
+
 impl From<(String, Vec<String>, Vec<CartLineModel>)> for CartModel {
     fn from(value: (String, Vec<String>, Vec<CartLineModel>)) -> Self {
         let (key, mut row, saved_lines) = (value.0, value.1, value.2);
@@ -163,10 +217,19 @@ impl AbsCartRepo for CartInMemRepo {
         Ok(num_saved)
     }
     async fn discard(&self, owner: u32, seq: u8) -> DefaultResult<(), AppError> {
-        let info = self.filter_keys(owner, seq, None).await?;
+        let mut info = self.filter_keys(owner, seq, None).await?;
+        // the access granted to other users is removed in the same operation
+        let share_info = self.filter_share_keys(owner, seq).await?;
+        info.extend(share_info);
         let _num_affected = self.datastore.delete(info).await?;
         Ok(())
     }
+    async fn remove_lines(&self, obj: CartModel) -> DefaultResult<usize, AppError> {
+        let keys = CartLineTable::DeleteArg(obj).into();
+        let info = HashMap::from([(CartLineTable::LABEL.to_string(), keys)]);
+        let num_removed = self.datastore.delete(info).await?;
+        Ok(num_removed)
+    }
     async fn num_lines_saved(&self, owner: u32, seq: u8) -> DefaultResult<usize, AppError> {
         let info = self.filter_keys(owner, seq, None).await?;
         let mut result = self.datastore.fetch(info).await?;
@@ -188,15 +251,58 @@ impl AbsCartRepo for CartInMemRepo {
         let info = self.filter_keys(owner, seq, Some(pids)).await?;
         self.fetch_common(owner, seq, info).await
     }
+
+    async fn update_share(&self, obj: CartShareModel) -> DefaultResult<usize, AppError> {
+        let info = self.filter_share_keys(obj.owner, obj.seq_num).await?;
+        let _num_removed = self.datastore.delete(info).await?;
+        if obj.collaborators.is_empty() {
+            return Ok(0);
+        }
+        let rows = CartShareTable::UpdateArg(obj).into();
+        let data = HashMap::from([(CartShareTable::LABEL.to_string(), rows)]);
+        let num_saved = self.datastore.save(data).await?;
+        Ok(num_saved)
+    }
+
+    async fn fetch_share(&self, owner: u32, seq: u8) -> DefaultResult<CartShareModel, AppError> {
+        let info = self.filter_share_keys(owner, seq).await?;
+        let mut result = self.datastore.fetch(info).await?;
+        let rows = result.remove(CartShareTable::LABEL).unwrap();
+        let collaborators = rows
+            .into_iter()
+            .map(CartCollaboratorModel::try_from)
+            .collect::<DefaultResult<Vec<_>, AppError>>()?;
+        Ok(CartShareModel {
+            owner,
+            seq_num: seq,
+            collaborators,
+        })
+    }
 } // end of impl AbsCartRepo for CartInMemRepo
 
 impl CartInMemRepo {
     pub async fn new(m: Arc<Box<dyn AbstInMemoryDStore>>) -> DefaultResult<Self, AppError> {
         m.create_table(CartTable::LABEL).await?;
         m.create_table(CartLineTable::LABEL).await?;
+        m.create_table(CartShareTable::LABEL).await?;
         Ok(Self { datastore: m })
     }
 
+    async fn filter_share_keys(
+        &self,
+        owner: u32,
+        seq_num: u8,
+    ) -> DefaultResult<AppInMemFetchKeys, AppError> {
+        let op = InnerFilterKeyOp {
+            usr_id: owner,
+            seq_num,
+            pids: None,
+        };
+        let tbl_name = CartShareTable::LABEL.to_string();
+        let keys = self.datastore.filter_keys(tbl_name.clone(), &op).await?;
+        Ok(HashMap::from([(tbl_name, keys)]))
+    }
+
     async fn filter_keys(
         &self,
         usr_id: u32,
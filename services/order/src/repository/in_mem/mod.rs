@@ -5,5 +5,6 @@ pub(super) mod order;
 pub(super) mod product_policy;
 pub(super) mod product_price;
 pub(super) mod stock_level;
+pub(super) mod store_shipping;
 
 use self::stock_level::StockLvlInMemRepo;
@@ -1,5 +1,6 @@
 use std::boxed::Box;
 use std::collections::HashMap;
+use std::ops::Bound;
 use std::result::Result as DefaultResult;
 use std::str::FromStr;
 use std::sync::Arc;
@@ -15,18 +16,27 @@ use ecommerce_common::error::AppErrorCode;
 use ecommerce_common::model::order::{BillingModel, ContactModel, PhyAddrModel};
 
 use crate::api::dto::ShippingMethod;
-use crate::datastore::{AbstInMemoryDStore, AppInMemFetchedSingleRow, AppInMemFetchedSingleTable};
+use crate::datastore::{
+    AbstInMemoryDStore, AppInMemFetchedSingleRow, AppInMemFetchedSingleTable, AppInMemIdxKey,
+    AppInMemIdxKeyType, AppInMemIdxRange,
+};
 use crate::error::AppError;
 use crate::model::{
     CurrencyModel, OrderCurrencyModel, OrderLineAppliedPolicyModel, OrderLineIdentity,
     OrderLineModel, OrderLineModelSet, OrderLinePriceModel, OrderLineQuantityModel,
-    ProdAttriPriceModel, ShippingModel, ShippingOptionModel,
+    ProdAttriPriceModel, ProductStockIdentity2, ShipmentLineModel, ShipmentModel, ShippingModel,
+    ShippingOptionModel,
 };
 
 use super::super::{
-    AbsOrderRepo, AbsOrderStockRepo, AppOrderFetchRangeCallback, AppOrderRepoUpdateLinesUserFunc,
+    AbsOrderRepo, AbsOrderStockRepo, AppOrderFetchRangeCallback, AppOrderRepoCancelLinesReturn,
+    AppOrderRepoCancelLinesUserFunc, AppOrderRepoOwnedIdsReturn,
+    AppOrderRepoShipmentCreateUserFunc, AppOrderRepoShipmentReceiptUserFunc,
+    AppOrderRepoUpdateLinesUserFunc, AppStockRepoReturnUserFunc,
 };
 use super::StockLvlInMemRepo;
+use crate::api::rpc::dto::ShipmentErrorDto;
+use crate::api::web::dto::ShipmentReceiptErrorReason;
 
 struct InnerTopLvlWrapper(u32, DateTime<FixedOffset>, CurrencyDto, Decimal);
 struct ContactModelWrapper(ContactModel);
@@ -128,10 +138,12 @@ mod _orderline {
     use crate::model::OrderLineModel;
 
     pub(super) const TABLE_LABEL: &str = "order_line_reserved";
+    pub(super) const IDX_RSV_TIME: &str = "idx_rsv_time";
     #[rustfmt::skip]
     pub(super) enum InMemColIdx {
         SellerID, ProductId, AttrSetSeq, QtyReserved, PriceUnit, PriceTotal, PolicyReserved, PolicyWarranty,
-        QtyPaid, QtyPaidLastUpdate, AttrLastUpdate, AttrPriceMap, TotNumColumns,
+        QtyPaid, QtyPaidLastUpdate, QtyCancelled, AttrLastUpdate, AttrPriceMap, PolicyVersion,
+        QtyDelivered, TotNumColumns,
     } // TODO, new column for attr-set-seq-num
     impl From<InMemColIdx> for usize {
         fn from(value: InMemColIdx) -> usize {
@@ -148,7 +160,10 @@ mod _orderline {
                 InMemColIdx::AttrLastUpdate => 9,
                 InMemColIdx::AttrPriceMap => 10,
                 InMemColIdx::AttrSetSeq => 11,
-                InMemColIdx::TotNumColumns => 12,
+                InMemColIdx::QtyCancelled => 12,
+                InMemColIdx::PolicyVersion => 13,
+                InMemColIdx::QtyDelivered => 14,
+                InMemColIdx::TotNumColumns => 15,
             }
         }
     }
@@ -221,6 +236,7 @@ mod _order_toplvl_meta {
     use super::{AppInMemFetchedSingleRow, HashMap, OrderLineModelSet};
 
     pub(super) const TABLE_LABEL: &str = "order_toplvl_meta";
+    pub(super) const IDX_CREATE_TIME: &str = "idx_create_time";
     #[rustfmt::skip]
     pub(super) enum InMemColIdx {
         OwnerUsrID, CreateTime, BuyerCurrencyLabel,
@@ -250,8 +266,42 @@ mod _order_toplvl_meta {
     } // end of fn to_inmem_tbl
 } // end of inner module _order_toplvl_meta
 
+mod _shipment {
+    use super::{AppInMemFetchedSingleRow, HashMap, ShipmentModel};
+
+    pub(super) const TABLE_LABEL: &str = "order_shipment";
+    #[rustfmt::skip]
+    pub(super) enum InMemColIdx {
+        SellerID, Carrier, TrackingNum, CreateTime, ReceivedTime, Lines,
+        TotNumColumns,
+    }
+    impl From<InMemColIdx> for usize {
+        fn from(value: InMemColIdx) -> usize {
+            match value {
+                InMemColIdx::SellerID => 0,
+                InMemColIdx::Carrier => 1,
+                InMemColIdx::TrackingNum => 2,
+                InMemColIdx::CreateTime => 3,
+                InMemColIdx::ReceivedTime => 4,
+                InMemColIdx::Lines => 5,
+                InMemColIdx::TotNumColumns => 6,
+            }
+        }
+    }
+    pub(super) fn inmem_pkey(oid: &str, seller_id: u32, tracking_num: &str) -> String {
+        format!("{oid}-{seller_id}-{tracking_num}")
+    }
+    pub(super) fn to_inmem_tbl(
+        oid: &str,
+        data: ShipmentModel,
+    ) -> HashMap<String, AppInMemFetchedSingleRow> {
+        let pkey = inmem_pkey(oid, data.seller_id, data.tracking_num.as_str());
+        HashMap::from([(pkey, data.into())])
+    }
+} // end of inner module _shipment
+
 mod _pkey_partial_label {
-    use super::{DateTime, FixedOffset};
+    use super::{AppInMemIdxKey, AppInMemIdxRange, Bound, DateTime, FixedOffset};
     use crate::datastore::AbsDStoreFilterKeyOp;
 
     pub(super) const BILLING: &str = "billing";
@@ -272,17 +322,14 @@ mod _pkey_partial_label {
             cond
         }
     }
-    pub(super) struct InMemDStoreFilterTimeRangeOp {
-        pub t0: DateTime<FixedOffset>,
-        pub t1: DateTime<FixedOffset>,
-        pub col_idx: usize, // column which stores the time to compare with
-    }
-    impl AbsDStoreFilterKeyOp for InMemDStoreFilterTimeRangeOp {
-        fn filter(&self, _k: &String, row: &Vec<String>) -> bool {
-            let time_mid = row.get(self.col_idx).unwrap();
-            let time_mid = DateTime::parse_from_rfc3339(time_mid.as_str()).unwrap();
-            (self.t0 < time_mid) && (time_mid < self.t1)
-        }
+    // range of time-based index, both ends are excluded
+    pub(super) fn time_range(
+        t0: DateTime<FixedOffset>,
+        t1: DateTime<FixedOffset>,
+    ) -> AppInMemIdxRange {
+        let t0 = AppInMemIdxKey::DateTime(t0.to_utc());
+        let t1 = AppInMemIdxKey::DateTime(t1.to_utc());
+        (Bound::Excluded(t0), Bound::Excluded(t1))
     }
 } // end of mod _pkey_partial_label
 
@@ -325,6 +372,14 @@ impl From<&OrderLineModel> for AppInMemFetchedSingleRow {
                 _orderline::InMemColIdx::QtyPaidLastUpdate,
                 _paid_last_update,
             ),
+            (
+                _orderline::InMemColIdx::QtyCancelled,
+                value.qty.cancelled.to_string(),
+            ),
+            (
+                _orderline::InMemColIdx::QtyDelivered,
+                value.qty.delivered.to_string(),
+            ),
             (
                 _orderline::InMemColIdx::PriceUnit,
                 value.price().unit().to_string(),
@@ -341,6 +396,10 @@ impl From<&OrderLineModel> for AppInMemFetchedSingleRow {
                 _orderline::InMemColIdx::PolicyWarranty,
                 value.policy.warranty_until.to_rfc3339(),
             ),
+            (
+                _orderline::InMemColIdx::PolicyVersion,
+                value.policy.version.to_string(),
+            ),
             (_orderline::InMemColIdx::AttrSetSeq, attrset_seq),
             (_orderline::InMemColIdx::ProductId, prod_id),
             (_orderline::InMemColIdx::SellerID, seller_id_s),
@@ -387,6 +446,12 @@ impl From<AppInMemFetchedSingleRow> for OrderLineModel {
                 .get::<usize>(_orderline::InMemColIdx::QtyPaid.into())
                 .unwrap().parse().unwrap(),
             paid_last_update: qty_paid_last_update,
+            cancelled: row
+                .get::<usize>(_orderline::InMemColIdx::QtyCancelled.into())
+                .unwrap().parse().unwrap(),
+            delivered: row
+                .get::<usize>(_orderline::InMemColIdx::QtyDelivered.into())
+                .unwrap().parse().unwrap(),
         };
         if qty.paid_last_update.is_none() {
             assert_eq!(qty.paid, 0);
@@ -404,7 +469,10 @@ impl From<AppInMemFetchedSingleRow> for OrderLineModel {
             DateTime::parse_from_rfc3339(s.as_str()).unwrap()
         };
 
-        let policy = OrderLineAppliedPolicyModel {reserved_until, warranty_until};
+        let version = row
+            .get::<usize>(_orderline::InMemColIdx::PolicyVersion.into())
+            .unwrap().parse().unwrap();
+        let policy = OrderLineAppliedPolicyModel {reserved_until, warranty_until, version};
 
         let attr_lastupdate = {
             let s = row
@@ -594,6 +662,77 @@ impl From<AppInMemFetchedSingleRow> for ShippingOptionModel {
     }
 }
 
+impl From<ShipmentModel> for AppInMemFetchedSingleRow {
+    fn from(value: ShipmentModel) -> Self {
+        let mut row = (0.._shipment::InMemColIdx::TotNumColumns.into())
+            .map(|_num| String::new())
+            .collect::<Self>();
+        let lines = value
+            .lines
+            .iter()
+            .map(|l| format!("{}-{}-{}", l.product_id, l.attr_set_seq, l.qty))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let received_time = value
+            .received_time
+            .map(|t| t.to_rfc3339())
+            .unwrap_or_default();
+        [
+            (
+                _shipment::InMemColIdx::SellerID,
+                value.seller_id.to_string(),
+            ),
+            (_shipment::InMemColIdx::Carrier, value.carrier.into()),
+            (_shipment::InMemColIdx::TrackingNum, value.tracking_num),
+            (
+                _shipment::InMemColIdx::CreateTime,
+                value.create_time.to_rfc3339(),
+            ),
+            (_shipment::InMemColIdx::ReceivedTime, received_time),
+            (_shipment::InMemColIdx::Lines, lines),
+        ]
+        .into_iter()
+        .map(|(idx, val)| {
+            let idx: usize = idx.into();
+            row[idx] = val;
+        })
+        .count();
+        row
+    }
+}
+impl From<AppInMemFetchedSingleRow> for ShipmentModel {
+    #[rustfmt::skip]
+    fn from(value: AppInMemFetchedSingleRow) -> ShipmentModel {
+        let mut row = value;
+        let mut take = |idx: _shipment::InMemColIdx| -> String {
+            let i: usize = idx.into();
+            std::mem::take(&mut row[i])
+        };
+        let seller_id = take(_shipment::InMemColIdx::SellerID).parse().unwrap();
+        let carrier = ShippingMethod::from(take(_shipment::InMemColIdx::Carrier));
+        let tracking_num = take(_shipment::InMemColIdx::TrackingNum);
+        let create_time = DateTime::parse_from_rfc3339(
+            take(_shipment::InMemColIdx::CreateTime).as_str()
+        ).unwrap();
+        let received_time = DateTime::parse_from_rfc3339(
+            take(_shipment::InMemColIdx::ReceivedTime).as_str()
+        ).ok();
+        let lines = take(_shipment::InMemColIdx::Lines)
+            .split(' ')
+            .map(|raw| {
+                let mut elms = raw.split('-');
+                let product_id = elms.next().unwrap().parse().unwrap();
+                let attr_set_seq = elms.next().unwrap().parse().unwrap();
+                let qty = elms.next().unwrap().parse().unwrap();
+                ShipmentLineModel { product_id, attr_set_seq, qty }
+            })
+            .collect();
+        ShipmentModel {
+            seller_id, carrier, tracking_num, create_time, received_time, lines,
+        }
+    }
+}
+
 impl From<AppInMemFetchedSingleRow> for InnerTopLvlWrapper {
     fn from(value: AppInMemFetchedSingleRow) -> Self {
         let usr_id = value
@@ -839,6 +978,63 @@ impl AbsOrderRepo for OrderInMemRepo {
         })
     } // end of fn update_lines_payment
 
+    async fn cancel_unpaid_lines(
+        &self,
+        oid: &str,
+        pids: Vec<OrderLineIdentity>,
+        usr_cb: AppOrderRepoCancelLinesUserFunc,
+        stk_cb: AppStockRepoReturnUserFunc,
+    ) -> DefaultResult<AppOrderRepoCancelLinesReturn, AppError> {
+        let table_name = _orderline::TABLE_LABEL;
+        // order line table is locked together with stock level table, so the
+        // cancellation and the stock return are saved atomically
+        let (mut models, (mut stock_mset, rsv_set), g_lock) = {
+            let keys = pids
+                .iter()
+                .map(|p| {
+                    _orderline::inmem_pkey(oid, p.store_id(), p.product_id(), p.attrs_seq_num())
+                })
+                .collect();
+            let stk_ids = pids
+                .iter()
+                .map(|p| ProductStockIdentity2 {
+                    store_id: p.store_id(),
+                    product_id: p.product_id(),
+                })
+                .collect();
+            let info = HashMap::from([(table_name.to_string(), keys)]);
+            let (stock_mset, rsv_set, mut rawdata, lock) = StockLvlInMemRepo::fetch_with_lock(
+                &self.datastore,
+                oid.to_string(),
+                stk_ids,
+                None,
+                info,
+            )
+            .await?;
+            let rawdata = rawdata.remove(table_name).unwrap();
+            let ms = rawdata
+                .into_values()
+                .map(AppInMemFetchedSingleRow::into)
+                .collect::<Vec<OrderLineModel>>();
+            (ms, (stock_mset, rsv_set), lock)
+        };
+        let time_now = LocalTime::now().fixed_offset();
+        let errors = usr_cb(&mut models, pids, time_now);
+        if !errors.is_empty() {
+            return Ok(Err(errors)); // lock is released once dropped
+        }
+        let data = OrderLineModel::cancelled_stock_return(oid, &models);
+        let stk_errors = stk_cb(&mut stock_mset, data);
+        let rows = _orderline::to_inmem_tbl(oid, &models);
+        let mut info = HashMap::from([(table_name.to_string(), rows)]);
+        if stk_errors.is_empty() {
+            let (label, rows) = StockLvlInMemRepo::to_tablerows(stock_mset, rsv_set);
+            info.insert(label, rows);
+        }
+        let _num = self.datastore.save_release(info, g_lock)?;
+        Ok(Ok(stk_errors))
+    } // end of fn cancel_unpaid_lines
+
     async fn fetch_lines_by_rsvtime(
         &self,
         time_start: DateTime<FixedOffset>,
@@ -847,14 +1043,10 @@ impl AbsOrderRepo for OrderInMemRepo {
     ) -> DefaultResult<(), AppError> {
         // fetch lines by range of reserved time
         let table_name = _orderline::TABLE_LABEL;
-        let op = _pkey_partial_label::InMemDStoreFilterTimeRangeOp {
-            col_idx: _orderline::InMemColIdx::PolicyReserved.into(),
-            t0: time_start,
-            t1: time_end,
-        };
+        let range = _pkey_partial_label::time_range(time_start, time_end);
         let keys_flattened = self
             .datastore
-            .filter_keys(table_name.to_string(), &op)
+            .range_keys(table_name.to_string(), _orderline::IDX_RSV_TIME, range)
             .await?;
         let key_grps = _orderline::pk_group_by_oid(keys_flattened);
         for (oid, keys) in key_grps.into_iter() {
@@ -904,18 +1096,43 @@ impl AbsOrderRepo for OrderInMemRepo {
         end: DateTime<FixedOffset>,
     ) -> DefaultResult<Vec<String>, AppError> {
         let table_name = _order_toplvl_meta::TABLE_LABEL;
-        let op = _pkey_partial_label::InMemDStoreFilterTimeRangeOp {
-            col_idx: _order_toplvl_meta::InMemColIdx::CreateTime.into(),
-            t0: start,
-            t1: end,
-        };
+        let range = _pkey_partial_label::time_range(start, end);
         let keys = self
             .datastore
-            .filter_keys(table_name.to_string(), &op)
+            .range_keys(
+                table_name.to_string(),
+                _order_toplvl_meta::IDX_CREATE_TIME,
+                range,
+            )
             .await?;
         Ok(keys)
     }
 
+    async fn fetch_ids_by_owner_created_time(
+        &self,
+        usr_id: u32,
+        start: DateTime<FixedOffset>,
+        end: DateTime<FixedOffset>,
+        num_skip: usize,
+        limit: usize,
+    ) -> DefaultResult<AppOrderRepoOwnedIdsReturn, AppError> {
+        let tbl_label = _order_toplvl_meta::TABLE_LABEL;
+        let keys = self.fetch_ids_by_created_time(start, end).await?;
+        let info = HashMap::from([(tbl_label.to_string(), keys)]);
+        let mut data = self.datastore.fetch(info).await?;
+        let rows = data.remove(tbl_label).unwrap_or_default();
+        let mut out = rows
+            .into_iter()
+            .map(|(oid, row)| (oid, InnerTopLvlWrapper::from(row)))
+            .filter(|(_oid, toplvl_m)| toplvl_m.0 == usr_id)
+            .map(|(oid, toplvl_m)| (oid, toplvl_m.1))
+            .collect::<Vec<_>>();
+        out.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+        let total = out.len();
+        let out = out.into_iter().skip(num_skip).take(limit).collect();
+        Ok((total, out))
+    }
+
     async fn owner_id(&self, order_id: &str) -> DefaultResult<u32, AppError> {
         let inner = self.fetch_toplvl_meta(order_id).await?;
         Ok(inner.0)
@@ -961,6 +1178,133 @@ impl AbsOrderRepo for OrderInMemRepo {
         *guard = t;
         Ok(())
     }
+
+    async fn fetch_shipments(&self, oid: &str) -> DefaultResult<Vec<ShipmentModel>, AppError> {
+        let op = _pkey_partial_label::InMemDStoreFiltKeyOID { oid, label: None };
+        let tbl_label = _shipment::TABLE_LABEL;
+        let keys = self
+            .datastore
+            .filter_keys(tbl_label.to_string(), &op)
+            .await?;
+        let info = HashMap::from([(tbl_label.to_string(), keys)]);
+        let mut data = self.datastore.fetch(info).await?;
+        let ms = data
+            .remove(tbl_label)
+            .unwrap()
+            .into_values()
+            .map(ShipmentModel::from)
+            .collect();
+        Ok(ms)
+    }
+
+    async fn create_shipment(
+        &self,
+        oid: &str,
+        shipment: ShipmentModel,
+        usr_cb: AppOrderRepoShipmentCreateUserFunc,
+    ) -> DefaultResult<DefaultResult<(), ShipmentErrorDto>, AppError> {
+        let (tbl_shipment, tbl_oline) = (_shipment::TABLE_LABEL, _orderline::TABLE_LABEL);
+        let seller_id = shipment.seller_id;
+        let label = seller_id.to_string();
+        let op = _pkey_partial_label::InMemDStoreFiltKeyOID {
+            oid,
+            label: Some(label.as_str()),
+        };
+        let saved_keys = self
+            .datastore
+            .filter_keys(tbl_shipment.to_string(), &op)
+            .await?;
+        let line_keys = shipment
+            .lines
+            .iter()
+            .map(|l| _orderline::inmem_pkey(oid, seller_id, l.product_id, l.attr_set_seq))
+            .collect();
+        let info = HashMap::from([
+            (tbl_shipment.to_string(), saved_keys),
+            (tbl_oline.to_string(), line_keys),
+        ]);
+        let (mut rawdata, g_lock) = self.datastore.fetch_acquire(info).await?;
+        let saved = rawdata
+            .remove(tbl_shipment)
+            .unwrap()
+            .into_values()
+            .map(ShipmentModel::from)
+            .collect::<Vec<_>>();
+        let olines = rawdata
+            .remove(tbl_oline)
+            .unwrap()
+            .into_values()
+            .map(OrderLineModel::from)
+            .collect::<Vec<_>>();
+        if let Err(e) = usr_cb(&shipment, &olines, &saved) {
+            return Ok(Err(e));
+        } // lock is released once dropped, if nothing is saved
+        let rows = _shipment::to_inmem_tbl(oid, shipment);
+        let info = HashMap::from([(tbl_shipment.to_string(), rows)]);
+        let _num = self.datastore.save_release(info, g_lock)?;
+        Ok(Ok(()))
+    } // end of fn create_shipment
+
+    async fn confirm_shipment_receipt(
+        &self,
+        oid: &str,
+        seller_id: u32,
+        tracking_num: &str,
+        usr_cb: AppOrderRepoShipmentReceiptUserFunc,
+    ) -> DefaultResult<DefaultResult<(), ShipmentReceiptErrorReason>, AppError> {
+        let (tbl_shipment, tbl_oline) = (_shipment::TABLE_LABEL, _orderline::TABLE_LABEL);
+        let skey = _shipment::inmem_pkey(oid, seller_id, tracking_num);
+        // lines in a shipment never change, it is safe to look them up before
+        // acquiring the lock
+        let line_keys = {
+            let info = HashMap::from([(tbl_shipment.to_string(), vec![skey.clone()])]);
+            let mut rawdata = self.datastore.fetch(info).await?;
+            let result = rawdata.remove(tbl_shipment).unwrap().into_values().next();
+            if let Some(row) = result {
+                ShipmentModel::from(row)
+                    .lines
+                    .iter()
+                    .map(|l| _orderline::inmem_pkey(oid, seller_id, l.product_id, l.attr_set_seq))
+                    .collect::<Vec<_>>()
+            } else {
+                return Ok(Err(ShipmentReceiptErrorReason::NotExist));
+            }
+        };
+        let info = HashMap::from([
+            (tbl_shipment.to_string(), vec![skey]),
+            (tbl_oline.to_string(), line_keys),
+        ]);
+        let (mut rawdata, g_lock) = self.datastore.fetch_acquire(info).await?;
+        let mut shipment = rawdata
+            .remove(tbl_shipment)
+            .unwrap()
+            .into_values()
+            .map(ShipmentModel::from)
+            .next()
+            .unwrap();
+        let mut olines = rawdata
+            .remove(tbl_oline)
+            .unwrap()
+            .into_values()
+            .map(OrderLineModel::from)
+            .collect::<Vec<_>>();
+        let time_now = LocalTime::now().fixed_offset();
+        if let Err(e) = usr_cb(&mut shipment, &mut olines, time_now) {
+            return Ok(Err(e));
+        }
+        let info = HashMap::from([
+            (
+                tbl_oline.to_string(),
+                _orderline::to_inmem_tbl(oid, &olines),
+            ),
+            (
+                tbl_shipment.to_string(),
+                _shipment::to_inmem_tbl(oid, shipment),
+            ),
+        ]);
+        let _num = self.datastore.save_release(info, g_lock)?;
+        Ok(Ok(()))
+    } // end of fn confirm_shipment_receipt
 } // end of impl AbsOrderRepo
 
 impl OrderInMemRepo {
@@ -974,6 +1318,21 @@ impl OrderInMemRepo {
         m.create_table(_orderline::TABLE_LABEL).await?;
         m.create_table(_seller_currencies::TABLE_LABEL).await?;
         m.create_table(_order_toplvl_meta::TABLE_LABEL).await?;
+        m.create_index(
+            _orderline::TABLE_LABEL,
+            _orderline::IDX_RSV_TIME,
+            _orderline::InMemColIdx::PolicyReserved.into(),
+            AppInMemIdxKeyType::DateTime,
+        )
+        .await?;
+        m.create_index(
+            _order_toplvl_meta::TABLE_LABEL,
+            _order_toplvl_meta::IDX_CREATE_TIME,
+            _order_toplvl_meta::InMemColIdx::CreateTime.into(),
+            AppInMemIdxKeyType::DateTime,
+        )
+        .await?;
+        m.create_table(_shipment::TABLE_LABEL).await?;
         let stock_repo = StockLvlInMemRepo::build(m.clone(), timenow).await?;
         let job_time = DateTime::parse_from_rfc3339("2019-03-13T12:59:54+08:00").unwrap();
         let obj = Self {
@@ -983,6 +1342,14 @@ impl OrderInMemRepo {
         };
         Ok(obj)
     }
+    // tables written by `gen_lowlvl_tablerows()`
+    pub(super) fn lowlvl_table_labels() -> [&'static str; 3] {
+        [
+            _orderline::TABLE_LABEL,
+            _seller_currencies::TABLE_LABEL,
+            _order_toplvl_meta::TABLE_LABEL,
+        ]
+    }
     pub(super) fn gen_lowlvl_tablerows(
         lineset: &OrderLineModelSet,
     ) -> Vec<(String, AppInMemFetchedSingleTable)> {
@@ -4,10 +4,12 @@ use std::collections::HashMap;
 use std::convert::Into;
 use std::sync::Arc;
 
+use chrono::DateTime;
+
 use ecommerce_common::error::AppErrorCode;
 
 use super::super::AbstProductPolicyRepo;
-use crate::datastore::AbstInMemoryDStore;
+use crate::datastore::{AbsDStoreFilterKeyOp, AbstInMemoryDStore};
 use crate::error::AppError;
 use crate::model::{ProductPolicyModel, ProductPolicyModelSet};
 
@@ -18,6 +20,7 @@ enum InMemColIdx {
     Warranty,
     MaxNumRsv,
     MinNumRsv,
+    EffectiveFrom,
     TotNumColumns,
 }
 
@@ -28,11 +31,25 @@ impl From<InMemColIdx> for usize {
             InMemColIdx::Warranty => 1,
             InMemColIdx::MaxNumRsv => 2,
             InMemColIdx::MinNumRsv => 3,
-            InMemColIdx::TotNumColumns => 4,
+            InMemColIdx::EffectiveFrom => 4,
+            InMemColIdx::TotNumColumns => 5,
         }
     }
 }
 
+// the key of each row is composed of store ID, product ID and version number
+struct InMemFilterKeyOp<'a> {
+    pids: &'a [(u32, u64)],
+}
+impl AbsDStoreFilterKeyOp for InMemFilterKeyOp<'_> {
+    fn filter(&self, k: &String, _v: &Vec<String>) -> bool {
+        let mut tokens = k.split('-');
+        let store_id = tokens.next().unwrap().parse::<u32>().unwrap();
+        let product_id = tokens.next().unwrap().parse::<u64>().unwrap();
+        self.pids.contains(&(store_id, product_id))
+    }
+}
+
 pub struct ProductPolicyInMemRepo {
     datastore: Arc<Box<dyn AbstInMemoryDStore>>,
 }
@@ -44,21 +61,26 @@ impl ProductPolicyInMemRepo {
     }
 }
 
-#[async_trait]
-impl AbstProductPolicyRepo for ProductPolicyInMemRepo {
-    async fn fetch(&self, ids: Vec<u64>) -> Result<ProductPolicyModelSet, AppError> {
-        let info = {
-            let v = ids.iter().map(|prod_id| prod_id.to_string()).collect();
-            let items = [(TABLE_LABEL.to_string(), v)];
-            HashMap::from(items)
-        };
+impl ProductPolicyInMemRepo {
+    async fn fetch_all_versions(
+        &self,
+        pids: &[(u32, u64)],
+    ) -> Result<Vec<ProductPolicyModel>, AppError> {
+        let op = InMemFilterKeyOp { pids };
+        let keys = self
+            .datastore
+            .filter_keys(TABLE_LABEL.to_string(), &op)
+            .await?;
+        let info = HashMap::from([(TABLE_LABEL.to_string(), keys)]);
         let result_raw = self.datastore.fetch(info).await?;
-        let filtered = if let Some(d) = result_raw.get(TABLE_LABEL) {
+        let out = if let Some(d) = result_raw.get(TABLE_LABEL) {
             // raw strings to model instances
             d.iter()
                 .map(|(key, row)| {
                     let id_elms = key.split('-').collect::<Vec<&str>>();
-                    let product_id = id_elms[0].parse().unwrap();
+                    let store_id = id_elms[0].parse().unwrap();
+                    let product_id = id_elms[1].parse().unwrap();
+                    let version = id_elms[2].parse().unwrap();
                     let auto_cancel_secs = row
                         .get::<usize>(InMemColIdx::AutoCancel.into())
                         .unwrap()
@@ -79,8 +101,15 @@ impl AbstProductPolicyRepo for ProductPolicyInMemRepo {
                         .unwrap()
                         .parse()
                         .unwrap();
+                    let effective_from = {
+                        let s = row.get::<usize>(InMemColIdx::EffectiveFrom.into());
+                        DateTime::parse_from_rfc3339(s.unwrap()).unwrap()
+                    };
                     ProductPolicyModel {
+                        store_id,
                         product_id,
+                        version,
+                        effective_from,
                         auto_cancel_secs,
                         warranty_hours,
                         max_num_rsv,
@@ -92,9 +121,43 @@ impl AbstProductPolicyRepo for ProductPolicyInMemRepo {
         } else {
             Vec::new()
         };
-        Ok(ProductPolicyModelSet { policies: filtered })
+        Ok(out)
+    } // end of fn fetch_all_versions
+} // end of impl ProductPolicyInMemRepo
+
+#[async_trait]
+impl AbstProductPolicyRepo for ProductPolicyInMemRepo {
+    async fn fetch(&self, ids: Vec<(u32, u64)>) -> Result<ProductPolicyModelSet, AppError> {
+        let all_versions = self.fetch_all_versions(&ids).await?;
+        // only keep the latest version of each product
+        let mut latest: HashMap<(u32, u64), ProductPolicyModel> = HashMap::new();
+        all_versions
+            .into_iter()
+            .map(|m| {
+                let k = (m.store_id, m.product_id);
+                let replace = latest
+                    .get(&k)
+                    .map(|v| v.version < m.version)
+                    .unwrap_or(true);
+                if replace {
+                    latest.insert(k, m);
+                }
+            })
+            .count();
+        let policies = latest.into_values().collect();
+        Ok(ProductPolicyModelSet { policies })
     } // end of fn fetch
 
+    async fn fetch_history(
+        &self,
+        store_id: u32,
+        product_id: u64,
+    ) -> Result<ProductPolicyModelSet, AppError> {
+        let mut policies = self.fetch_all_versions(&[(store_id, product_id)]).await?;
+        policies.sort_by_key(|m| m.version);
+        Ok(ProductPolicyModelSet { policies })
+    }
+
     async fn save(&self, ppset: ProductPolicyModelSet) -> Result<(), AppError> {
         if ppset.policies.is_empty() {
             return Err(AppError {
@@ -102,36 +165,52 @@ impl AbstProductPolicyRepo for ProductPolicyInMemRepo {
                 detail: Some("save ProductPolicyModel".to_string()),
             });
         }
-        let data = {
-            let mut h = HashMap::new();
-            let table_data = {
-                let kv_pairs = ppset.policies.iter().map(|m| {
-                    let pkey = format!("{}", m.product_id);
-                    // manually allocate space in advance, instead of `Vec::with_capacity`
-                    let mut row = (0..InMemColIdx::TotNumColumns.into())
-                        .map(|_n| String::new())
-                        .collect::<Vec<String>>();
-                    let _ = [
-                        // so the order of columns can be arbitrary
-                        (InMemColIdx::MinNumRsv, m.min_num_rsv.to_string()),
-                        (InMemColIdx::MaxNumRsv, m.max_num_rsv.to_string()),
-                        (InMemColIdx::Warranty, m.warranty_hours.to_string()),
-                        (InMemColIdx::AutoCancel, m.auto_cancel_secs.to_string()),
-                    ]
-                    .into_iter()
-                    .map(|(idx, val)| {
-                        let idx: usize = idx.into();
-                        row[idx] = val;
-                    })
-                    .collect::<Vec<()>>();
-                    (pkey, row)
-                });
-                HashMap::from_iter(kv_pairs)
-            };
-            h.insert(TABLE_LABEL.to_string(), table_data);
-            h
+        // append-only, existing versions are never modified
+        let table_data = {
+            let kv_pairs = ppset.policies.iter().filter(|m| m.is_create).map(|m| {
+                let pkey = format!("{}-{}-{}", m.store_id, m.product_id, m.version);
+                // manually allocate space in advance, instead of `Vec::with_capacity`
+                let mut row = (0..InMemColIdx::TotNumColumns.into())
+                    .map(|_n| String::new())
+                    .collect::<Vec<String>>();
+                let _ = [
+                    // so the order of columns can be arbitrary
+                    (InMemColIdx::MinNumRsv, m.min_num_rsv.to_string()),
+                    (InMemColIdx::MaxNumRsv, m.max_num_rsv.to_string()),
+                    (InMemColIdx::Warranty, m.warranty_hours.to_string()),
+                    (InMemColIdx::AutoCancel, m.auto_cancel_secs.to_string()),
+                    (InMemColIdx::EffectiveFrom, m.effective_from.to_rfc3339()),
+                ]
+                .into_iter()
+                .map(|(idx, val)| {
+                    let idx: usize = idx.into();
+                    row[idx] = val;
+                })
+                .collect::<Vec<()>>();
+                (pkey, row)
+            });
+            HashMap::from_iter(kv_pairs)
         };
-        let _num_saved = self.datastore.save(data).await?;
+        if table_data.is_empty() {
+            return Ok(());
+        }
+        // compare-and-swap, none of the new versions should exist, which means
+        // the version read by the caller is still the latest one
+        let keys = table_data.keys().cloned().collect::<Vec<_>>();
+        let info = HashMap::from([(TABLE_LABEL.to_string(), keys)]);
+        let (fetched, lock) = self.datastore.fetch_acquire(info).await?;
+        let conflicted = fetched
+            .get(TABLE_LABEL)
+            .map(|d| d.keys().cloned().collect::<Vec<_>>())
+            .unwrap_or_default();
+        if !conflicted.is_empty() {
+            return Err(AppError {
+                code: AppErrorCode::DataConflict,
+                detail: Some(format!("product-policy-version: {:?}", conflicted)),
+            });
+        } // lock is released once dropped, if nothing is saved
+        let data = HashMap::from([(TABLE_LABEL.to_string(), table_data)]);
+        let _num_saved = self.datastore.save_release(data, lock)?;
         Ok(())
     } // end of fn save
 } // end of impl AbstProductPolicyRepo
@@ -91,7 +91,7 @@ mod _stockm {
 
 // list of tuple with order-id and number of reserved for each order
 type FetchedRsv = Vec<(String, u32)>;
-struct FetchedRsvSet(HashMap<String, FetchedRsv>);
+pub(super) struct FetchedRsvSet(HashMap<String, FetchedRsv>);
 struct FetchArg(AppInMemFetchedSingleTable, Option<String>);
 struct SaveArg(StockLevelModelSet, FetchedRsvSet);
 
@@ -293,6 +293,18 @@ impl AbsOrderStockRepo for StockLvlInMemRepo {
         Self::try_into_modelset(None, resultset)
     } // end of fn fetch
 
+    async fn fetch_avail(
+        &self,
+        pids: Vec<ProductStockIdentity2>,
+    ) -> DefaultResult<StockLevelModelSet, AppError> {
+        let tbl_label = _stockm::TABLE_LABEL.to_string();
+        let op = _stockm::InMemDStoreFiltKeyOp::new(pids, Some(self.curr_time));
+        let stock_ids = self.datastore.filter_keys(tbl_label.clone(), &op).await?;
+        let info = HashMap::from([(tbl_label, stock_ids)]);
+        let resultset = self.datastore.fetch(info).await?;
+        Self::try_into_modelset(None, resultset)
+    }
+
     async fn save(&self, slset: StockLevelModelSet) -> DefaultResult<(), AppError> {
         let rsv_set = {
             let ids = slset
@@ -330,10 +342,21 @@ impl AbsOrderStockRepo for StockLvlInMemRepo {
                 product_id: d.id().product_id(),
             })
             .collect();
-        let (mut stock_mset, rsv_set, d_lock) = self
-            .fetch_with_lock(order_req.id().to_string(), pids, Some(self.curr_time))
-            .await
-            .map_err(Err)?;
+        // order tables are locked together with stock level table, so the
+        // reservation and the new order are saved atomically
+        let extra_tables = OrderInMemRepo::lowlvl_table_labels()
+            .into_iter()
+            .map(|label| (label.to_string(), Vec::new()))
+            .collect();
+        let (mut stock_mset, rsv_set, _, d_lock) = Self::fetch_with_lock(
+            &self.datastore,
+            order_req.id().to_string(),
+            pids,
+            Some(self.curr_time),
+            extra_tables,
+        )
+        .await
+        .map_err(Err)?;
         usr_cb(&mut stock_mset, order_req)?;
         let data = {
             let mut seq = OrderInMemRepo::gen_lowlvl_tablerows(order_req);
@@ -362,18 +385,32 @@ impl AbsOrderStockRepo for StockLvlInMemRepo {
             })
             .collect();
         // omit expiry check in the key filter
-        let (mut mset, rsv_set, d_lock) = self
-            .fetch_with_lock(data.order_id.clone(), pids, None)
-            .await?;
+        let (mut mset, rsv_set, _, d_lock) = Self::fetch_with_lock(
+            &self.datastore,
+            data.order_id.clone(),
+            pids,
+            None,
+            HashMap::new(),
+        )
+        .await?;
         let caller_errors = cb(&mut mset, data);
         if caller_errors.is_empty() {
-            let rows = AppInMemFetchedSingleTable::from(SaveArg(mset, rsv_set));
-            let table = (_stockm::TABLE_LABEL.to_string(), rows);
+            let table = Self::to_tablerows(mset, rsv_set);
             let data = HashMap::from([table]);
             let _num_saved = self.datastore.save_release(data, d_lock)?;
         }
         Ok(caller_errors)
     }
+
+    async fn release_pending_rsv(
+        &self,
+        _time_before: DateTime<FixedOffset>,
+        _cb: AppStockRepoReturnUserFunc,
+    ) -> DefaultResult<usize, AppError> {
+        // stock reservation and order lines are always saved together, so are
+        // cancellation of order lines and return of their stock
+        Ok(0)
+    }
 } // end of impl StockLvlInMemRepo
 
 impl StockLvlInMemRepo {
@@ -389,23 +426,46 @@ impl StockLvlInMemRepo {
         Ok(out)
     }
 
-    async fn fetch_with_lock(
-        &self,
+    // tables in `extra` are locked together with stock level table for subsequent
+    // write, rows with the given keys are fetched and returned separately
+    pub(super) async fn fetch_with_lock(
+        datastore: &Arc<Box<dyn AbstInMemoryDStore>>,
         order_id: String,
         pids: Vec<ProductStockIdentity2>,
         curr_time: Option<DateTime<FixedOffset>>,
-    ) -> DefaultResult<(StockLevelModelSet, FetchedRsvSet, AppInMemDstoreLock), AppError> {
+        extra: HashMap<String, Vec<String>>,
+    ) -> DefaultResult<
+        (
+            StockLevelModelSet,
+            FetchedRsvSet,
+            AppInMemFetchedData,
+            AppInMemDstoreLock<'_>,
+        ),
+        AppError,
+    > {
         let tbl_label = _stockm::TABLE_LABEL.to_string();
         let op = _stockm::InMemDStoreFiltKeyOp::new(pids, curr_time);
-        let stock_ids = self.datastore.filter_keys(tbl_label.clone(), &op).await?;
-        let info = HashMap::from([(tbl_label, stock_ids)]);
-        let (tableset, lock) = self.datastore.fetch_acquire(info).await?;
+        let stock_ids = datastore.filter_keys(tbl_label.clone(), &op).await?;
+        let mut info = extra;
+        info.insert(tbl_label.clone(), stock_ids);
+        let (mut tableset, lock) = datastore.fetch_acquire(info).await?;
+        let stock_tableset = tableset
+            .remove_entry(&tbl_label)
+            .into_iter()
+            .collect::<AppInMemFetchedData>();
         let rsv_set = {
-            let rows = tableset.values().next().unwrap();
+            let rows = stock_tableset.values().next().unwrap();
             FetchedRsvSet::from(rows)
         };
-        let ms = Self::try_into_modelset(Some(order_id), tableset)?;
-        Ok((ms, rsv_set, lock))
+        let ms = Self::try_into_modelset(Some(order_id), stock_tableset)?;
+        Ok((ms, rsv_set, tableset, lock))
+    }
+    pub(super) fn to_tablerows(
+        mset: StockLevelModelSet,
+        rsv_set: FetchedRsvSet,
+    ) -> (String, AppInMemFetchedSingleTable) {
+        let rows = AppInMemFetchedSingleTable::from(SaveArg(mset, rsv_set));
+        (_stockm::TABLE_LABEL.to_string(), rows)
     }
     fn try_into_modelset(
         order_id: Option<String>,
@@ -0,0 +1,113 @@
+use std::boxed::Box;
+use std::collections::HashMap;
+use std::result::Result;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use ecommerce_common::api::dto::CountryCode;
+use ecommerce_common::error::AppErrorCode;
+
+use crate::api::dto::ShippingMethod;
+use crate::datastore::{AbstInMemoryDStore, AppInMemFetchedSingleTable};
+use crate::error::AppError;
+use crate::model::{ShippingFeeModel, StoreShipMethodModel, StoreShippingModel};
+use crate::repository::AbsStoreShippingRepo;
+
+const TABLE_LABEL: &str = "store_shipping";
+
+// each row contains all methods supported by a store, the only column is
+// serialized as `<method>:<country>=<amount>,<country>=<amount> <method>:....`
+struct UpdateArgs(StoreShippingModel);
+
+impl From<UpdateArgs> for AppInMemFetchedSingleTable {
+    fn from(value: UpdateArgs) -> Self {
+        let StoreShippingModel { store_id, methods } = value.0;
+        let serial = methods
+            .into_iter()
+            .map(|m| {
+                let fees = m
+                    .fees
+                    .into_iter()
+                    .map(|f| format!("{}={}", String::from(f.country), f.amount))
+                    .collect::<Vec<_>>()
+                    .join(",");
+                format!("{}:{}", String::from(m.method), fees)
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        HashMap::from([(store_id.to_string(), vec![serial])])
+    }
+}
+
+fn decode_method(raw: &str) -> Option<StoreShipMethodModel> {
+    let (method, fees) = raw.split_once(':')?;
+    let method = ShippingMethod::from(method.to_string());
+    if matches!(method, ShippingMethod::Unknown) {
+        return None;
+    }
+    let fees = fees
+        .split(',')
+        .map(|f| {
+            let (country, amount) = f.split_once('=')?;
+            let country = CountryCode::from(country.to_string());
+            let amount = amount.parse::<u32>().ok()?;
+            Some(ShippingFeeModel { country, amount })
+        })
+        .collect::<Option<Vec<_>>>()?;
+    Some(StoreShipMethodModel { method, fees })
+}
+
+impl TryFrom<(String, Vec<String>)> for StoreShippingModel {
+    type Error = AppError;
+    fn try_from(value: (String, Vec<String>)) -> Result<Self, Self::Error> {
+        let (key, row) = value;
+        let corrupted = || AppError {
+            code: AppErrorCode::DataCorruption,
+            detail: Some(format!("store-shipping, key:{key}")),
+        };
+        let store_id = key.parse::<u32>().map_err(|_e| corrupted())?;
+        let serial = row.first().ok_or(corrupted())?;
+        let methods = serial
+            .split(' ')
+            .filter(|s| !s.is_empty())
+            .map(decode_method)
+            .collect::<Option<Vec<_>>>()
+            .ok_or(corrupted())?;
+        Ok(Self { store_id, methods })
+    }
+}
+
+pub struct StoreShippingInMemRepo {
+    dstore: Arc<Box<dyn AbstInMemoryDStore>>,
+}
+
+#[async_trait]
+impl AbsStoreShippingRepo for StoreShippingInMemRepo {
+    async fn fetch(&self, store_ids: Vec<u32>) -> Result<Vec<StoreShippingModel>, AppError> {
+        let keys = store_ids.into_iter().map(|v| v.to_string()).collect();
+        let info = HashMap::from([(TABLE_LABEL.to_string(), keys)]);
+        let mut resultset = self.dstore.fetch(info).await?;
+        let rows = resultset.remove(TABLE_LABEL).ok_or(AppError {
+            code: AppErrorCode::DataTableNotExist,
+            detail: Some(TABLE_LABEL.to_string()),
+        })?;
+        rows.into_iter()
+            .map(StoreShippingModel::try_from)
+            .collect::<Result<Vec<_>, AppError>>()
+    }
+
+    async fn save(&self, obj: StoreShippingModel) -> Result<(), AppError> {
+        let rows = AppInMemFetchedSingleTable::from(UpdateArgs(obj));
+        let data = HashMap::from([(TABLE_LABEL.to_string(), rows)]);
+        let _num_saved = self.dstore.save(data).await?;
+        Ok(())
+    }
+} // end of impl StoreShippingInMemRepo
+
+impl StoreShippingInMemRepo {
+    pub async fn new(dstore: Arc<Box<dyn AbstInMemoryDStore>>) -> Result<Self, AppError> {
+        dstore.create_table(TABLE_LABEL).await?;
+        Ok(Self { dstore })
+    }
+}
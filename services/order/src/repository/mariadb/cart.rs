@@ -6,25 +6,28 @@ use sqlx::database::Database as AbstractDatabase;
 use sqlx::mysql::{MySqlArguments, MySqlRow};
 use sqlx::{Acquire, Arguments, Executor, IntoArguments, MySql, Row, Statement};
 
-use ecommerce_common::error::AppErrorCode;
 use ecommerce_common::model::BaseProductIdentity;
 
 use crate::datastore::AppMariaDbStore;
 use crate::error::AppError;
-use crate::model::{CartLineModel, CartModel};
+use crate::model::{CartCollaboratorModel, CartLineModel, CartModel, CartShareModel};
 use crate::repository::AbsCartRepo;
 
-use super::run_query_once;
+use super::{run_query_once, MariaDbPools};
 
 struct InsertUpdateTopLvlArg<'a>(&'a CartModel);
 struct InsertLineArg(u32, u8, Vec<CartLineModel>);
 struct UpdateLineArg(u32, u8, Vec<CartLineModel>);
 struct DiscardLineArg(u32, u8);
+struct RemoveLineArg(u32, u8, Vec<CartLineModel>);
 struct DiscardTopLvlArg(u32, u8);
+struct DiscardShareArg(u32, u8);
+struct InsertShareArg(CartShareModel);
 
 struct FetchTotNumLinesArg(u32, u8);
 struct FetchTopLvlArg(u32, u8);
 struct FetchLinesArg(u32, u8, Option<Vec<BaseProductIdentity>>);
+struct FetchShareArg(u32, u8);
 
 impl<'a> From<InsertUpdateTopLvlArg<'a>> for (String, MySqlArguments) {
     fn from(value: InsertUpdateTopLvlArg<'a>) -> (String, MySqlArguments) {
@@ -42,12 +45,12 @@ impl<'a> From<InsertUpdateTopLvlArg<'a>> for (String, MySqlArguments) {
 impl InsertLineArg {
     fn sql_pattern(num_batch: usize) -> String {
         let col_seq = (0..num_batch)
-            .map(|_| "(?,?,?,?,?)")
+            .map(|_| "(?,?,?,?,?,?)")
             .collect::<Vec<_>>()
             .join(",");
         format!(
             "INSERT INTO `cart_line_detail`(`usr_id`,`seq`,`store_id`,\
-            `product_id`,`quantity`) VALUES {col_seq}"
+            `product_id`,`attr_set`,`quantity`) VALUES {col_seq}"
         )
     }
 }
@@ -58,6 +61,7 @@ impl<'q> IntoArguments<'q, MySql> for InsertLineArg {
         lines
             .into_iter()
             .map(|line| {
+                let attr_set = line.serialize_attrs().unwrap();
                 let (id_, quantity) = (line.id_, line.qty_req);
                 let BaseProductIdentity {
                     store_id,
@@ -67,6 +71,7 @@ impl<'q> IntoArguments<'q, MySql> for InsertLineArg {
                 args.add(seq_num).unwrap();
                 args.add(store_id).unwrap();
                 args.add(product_id).unwrap();
+                args.add(attr_set).unwrap();
                 args.add(quantity).unwrap();
             })
             .count();
@@ -85,11 +90,11 @@ impl From<InsertLineArg> for (String, MySqlArguments) {
 impl UpdateLineArg {
     fn sql_pattern(num_batch: usize) -> String {
         let case_op = (0..num_batch)
-            .map(|_| "WHEN (`store_id`=? AND `product_id`=?) THEN ? ")
+            .map(|_| "WHEN (`store_id`=? AND `product_id`=? AND `attr_set`=?) THEN ? ")
             .collect::<Vec<_>>()
             .join("");
         let where_op = (0..num_batch)
-            .map(|_| "(`store_id`=? AND `product_id`=?)")
+            .map(|_| "(`store_id`=? AND `product_id`=? AND `attr_set`=?)")
             .collect::<Vec<_>>()
             .join("OR");
         // `usr_id`,`seq`,`store_id`,`product_id`
@@ -109,6 +114,7 @@ impl<'a> IntoArguments<'a, MySql> for UpdateLineArg {
                 let (seller, p_id, qty) = (line.id_.store_id, line.id_.product_id, line.qty_req);
                 args.add(seller).unwrap();
                 args.add(p_id).unwrap();
+                args.add(line.serialize_attrs().unwrap()).unwrap();
                 args.add(qty).unwrap();
             })
             .count();
@@ -120,6 +126,7 @@ impl<'a> IntoArguments<'a, MySql> for UpdateLineArg {
                 let (seller, p_id) = (line.id_.store_id, line.id_.product_id);
                 args.add(seller).unwrap();
                 args.add(p_id).unwrap();
+                args.add(line.serialize_attrs().unwrap()).unwrap();
             })
             .count();
         args
@@ -156,6 +163,98 @@ impl From<DiscardLineArg> for (String, MySqlArguments) {
     }
 }
 
+impl RemoveLineArg {
+    fn sql_pattern(num_batch: usize) -> String {
+        let where_op = (0..num_batch)
+            .map(|_| "(`store_id`=? AND `product_id`=? AND `attr_set`=?)")
+            .collect::<Vec<_>>()
+            .join("OR");
+        format!("DELETE FROM `cart_line_detail` WHERE `usr_id`=? AND `seq`=? AND ({where_op})")
+    }
+}
+impl<'a> IntoArguments<'a, MySql> for RemoveLineArg {
+    fn into_arguments(self) -> <MySql as AbstractDatabase>::Arguments<'a> {
+        let mut args = MySqlArguments::default();
+        let (usr_id, seq, lines) = (self.0, self.1, self.2);
+        args.add(usr_id).unwrap();
+        args.add(seq).unwrap();
+        lines
+            .into_iter()
+            .map(|line| {
+                let (seller, p_id) = (line.id_.store_id, line.id_.product_id);
+                args.add(seller).unwrap();
+                args.add(p_id).unwrap();
+                args.add(line.serialize_attrs().unwrap()).unwrap();
+            })
+            .count();
+        args
+    }
+}
+impl From<RemoveLineArg> for (String, MySqlArguments) {
+    fn from(value: RemoveLineArg) -> (String, MySqlArguments) {
+        (
+            RemoveLineArg::sql_pattern(value.2.len()),
+            value.into_arguments(),
+        )
+    }
+}
+
+impl From<DiscardShareArg> for (String, MySqlArguments) {
+    fn from(value: DiscardShareArg) -> (String, MySqlArguments) {
+        let (usr_id, seq_num) = (value.0, value.1);
+        let sql_patt = "DELETE FROM `cart_share` WHERE `usr_id`=? AND `seq`=?";
+        let mut args = MySqlArguments::default();
+        args.add(usr_id).unwrap();
+        args.add(seq_num).unwrap();
+        (sql_patt.to_string(), args)
+    }
+}
+
+impl InsertShareArg {
+    fn sql_pattern(num_batch: usize) -> String {
+        let col_seq = (0..num_batch)
+            .map(|_| "(?,?,?,?)")
+            .collect::<Vec<_>>()
+            .join(",");
+        format!("INSERT INTO `cart_share`(`usr_id`,`seq`,`collab_id`,`access`) VALUES {col_seq}")
+    }
+}
+impl<'q> IntoArguments<'q, MySql> for InsertShareArg {
+    fn into_arguments(self) -> <MySql as AbstractDatabase>::Arguments<'q> {
+        let mut args = MySqlArguments::default();
+        let (usr_id, seq_num, collaborators) = (self.0.owner, self.0.seq_num, self.0.collaborators);
+        collaborators
+            .into_iter()
+            .map(|c| {
+                args.add(usr_id).unwrap();
+                args.add(seq_num).unwrap();
+                args.add(c.usr_id).unwrap();
+                args.add(c.access_label()).unwrap();
+            })
+            .count();
+        args
+    }
+}
+impl From<InsertShareArg> for (String, MySqlArguments) {
+    fn from(value: InsertShareArg) -> (String, MySqlArguments) {
+        (
+            InsertShareArg::sql_pattern(value.0.collaborators.len()),
+            value.into_arguments(),
+        )
+    }
+}
+
+impl From<FetchShareArg> for (String, MySqlArguments) {
+    fn from(value: FetchShareArg) -> (String, MySqlArguments) {
+        let (usr_id, seq_num) = (value.0, value.1);
+        let sql_patt = "SELECT `collab_id`,`access` FROM `cart_share` WHERE `usr_id`=? AND `seq`=?";
+        let mut args = MySqlArguments::default();
+        args.add(usr_id).unwrap();
+        args.add(seq_num).unwrap();
+        (sql_patt.to_string(), args)
+    }
+}
+
 impl From<FetchTotNumLinesArg> for (String, MySqlArguments) {
     fn from(value: FetchTotNumLinesArg) -> (String, MySqlArguments) {
         let (usr_id, seq_num) = (value.0, value.1);
@@ -181,7 +280,7 @@ impl From<FetchTopLvlArg> for (String, MySqlArguments) {
 
 impl FetchLinesArg {
     fn sql_pattern(num_batch: usize) -> String {
-        let mut sql_patt = "SELECT `store_id`,`product_id`,`quantity`\
+        let mut sql_patt = "SELECT `store_id`,`product_id`,`quantity`,`attr_set` \
                         FROM `cart_line_detail` WHERE `usr_id`=? AND `seq`=?"
             .to_string();
         if num_batch > 0 {
@@ -248,24 +347,40 @@ impl TryFrom<MySqlRow> for CartLineModel {
         let store_id = row.try_get::<u32, usize>(0)?;
         let product_id = row.try_get::<u64, usize>(1)?;
         let qty_req = row.try_get::<u32, usize>(2)?;
+        let chosen_attr = {
+            let raw = row.try_get::<&str, usize>(3)?;
+            CartLineModel::deserialize_attrs(raw)?
+        };
         Ok(Self {
             id_: BaseProductIdentity {
                 store_id,
                 product_id,
             },
+            chosen_attr,
             qty_req,
         })
     }
 }
 
+impl TryFrom<MySqlRow> for CartCollaboratorModel {
+    type Error = AppError;
+    fn try_from(row: MySqlRow) -> DefaultResult<Self, Self::Error> {
+        let usr_id = row.try_get::<u32, usize>(0)?;
+        let label = row.try_get::<&str, usize>(1)?;
+        CartCollaboratorModel::try_from_label(usr_id, label)
+    }
+}
+
 pub(crate) struct CartMariaDbRepo {
-    _db: Arc<AppMariaDbStore>,
+    // carts are always accessed in primary, users expect to see their latest
+    // modification immediately
+    _pools: MariaDbPools,
 }
 
 #[async_trait]
 impl AbsCartRepo for CartMariaDbRepo {
     async fn update(&self, obj: CartModel) -> DefaultResult<usize, AppError> {
-        let mut conn = self._db.acquire().await?;
+        let mut conn = self._pools.primary().acquire().await?;
         let mut tx = conn.begin().await?;
         let (sql_patt, args) = InsertUpdateTopLvlArg(&obj).into();
         // Note the mysql running the raw sql `INSERT ON DUPLICATE KEY UPDATE` will return
@@ -287,19 +402,34 @@ impl AbsCartRepo for CartMariaDbRepo {
     } // end of fn update
 
     async fn discard(&self, owner: u32, seq: u8) -> DefaultResult<(), AppError> {
-        let mut conn = self._db.acquire().await?;
+        let mut conn = self._pools.primary().acquire().await?;
         let mut tx = conn.begin().await?;
         let (sql_patt, args) = DiscardLineArg(owner, seq).into();
         let _rs = run_query_once(&mut tx, sql_patt, args, None).await?;
+        let (sql_patt, args) = DiscardShareArg(owner, seq).into();
+        let _rs = run_query_once(&mut tx, sql_patt, args, None).await?;
+        // the cart might never be saved, which is considered as discarded
         let (sql_patt, args) = DiscardTopLvlArg(owner, seq).into();
-        let _rs = run_query_once(&mut tx, sql_patt, args, Some(1)).await?;
+        let _rs = run_query_once(&mut tx, sql_patt, args, None).await?;
         tx.commit().await?;
         Ok(())
     }
 
+    async fn remove_lines(&self, obj: CartModel) -> DefaultResult<usize, AppError> {
+        if obj.saved_lines.is_empty() {
+            return Ok(0);
+        }
+        let mut conn = self._pools.primary().acquire().await?;
+        let mut tx = conn.begin().await?;
+        let (sql_patt, args) = RemoveLineArg(obj.owner, obj.seq_num, obj.saved_lines).into();
+        let rs = run_query_once(&mut tx, sql_patt, args, None).await?;
+        tx.commit().await?;
+        Ok(rs.rows_affected() as usize)
+    }
+
     async fn num_lines_saved(&self, owner: u32, seq: u8) -> DefaultResult<usize, AppError> {
         let (sql_patt, args) = FetchTotNumLinesArg(owner, seq).into();
-        let mut conn = self._db.acquire().await?;
+        let mut conn = self._pools.primary().acquire().await?;
         let stmt = conn.prepare(sql_patt.as_str()).await?;
         let query = stmt.query_with(args);
         let exec = &mut *conn;
@@ -330,19 +460,44 @@ impl AbsCartRepo for CartMariaDbRepo {
             .await?;
         Ok(out)
     } // end of fn fetch_lines_by_pid
+
+    async fn update_share(&self, obj: CartShareModel) -> DefaultResult<usize, AppError> {
+        let mut conn = self._pools.primary().acquire().await?;
+        let mut tx = conn.begin().await?;
+        let (sql_patt, args) = DiscardShareArg(obj.owner, obj.seq_num).into();
+        let _rs = run_query_once(&mut tx, sql_patt, args, None).await?;
+        let num_inserting = obj.collaborators.len();
+        if num_inserting > 0 {
+            let (sql_patt, args) = InsertShareArg(obj).into();
+            let _rs = run_query_once(&mut tx, sql_patt, args, Some(num_inserting)).await?;
+        }
+        tx.commit().await?;
+        Ok(num_inserting)
+    }
+
+    async fn fetch_share(&self, owner: u32, seq: u8) -> DefaultResult<CartShareModel, AppError> {
+        let (sql_patt, args) = FetchShareArg(owner, seq).into();
+        let mut conn = self._pools.primary().acquire().await?;
+        let stmt = conn.prepare(sql_patt.as_str()).await?;
+        let query = stmt.query_with(args);
+        let exec = &mut *conn;
+        let rows = exec.fetch_all(query).await?;
+        let collaborators = rows
+            .into_iter()
+            .map(CartCollaboratorModel::try_from)
+            .collect::<DefaultResult<Vec<_>, AppError>>()?;
+        Ok(CartShareModel {
+            owner,
+            seq_num: seq,
+            collaborators,
+        })
+    }
 } // end of impl CartMariaDbRepo
 
 impl CartMariaDbRepo {
     pub async fn new(dbs: Vec<Arc<AppMariaDbStore>>) -> DefaultResult<Self, AppError> {
-        if dbs.is_empty() {
-            Err(AppError {
-                code: AppErrorCode::MissingDataStore,
-                detail: Some("mariadb".to_string()),
-            })
-        } else {
-            let _db = dbs.first().unwrap().clone();
-            Ok(Self { _db })
-        }
+        let _pools = MariaDbPools::try_build(&dbs)?;
+        Ok(Self { _pools })
     }
     async fn fetch_common(
         &self,
@@ -351,7 +506,7 @@ impl CartMariaDbRepo {
         rawsql_toplvl: (String, MySqlArguments),
         rawsql_line: (String, MySqlArguments),
     ) -> DefaultResult<CartModel, AppError> {
-        let mut conn = self._db.acquire().await?;
+        let mut conn = self._pools.primary().acquire().await?;
         let result = {
             let stmt = conn.prepare(rawsql_toplvl.0.as_str()).await?;
             let query = stmt.query_with(rawsql_toplvl.1);
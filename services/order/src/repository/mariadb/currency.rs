@@ -11,7 +11,7 @@ use sqlx::{Acquire, Arguments, Executor, IntoArguments, MySql, Row, Statement};
 use ecommerce_common::api::dto::CurrencyDto;
 use ecommerce_common::error::AppErrorCode;
 
-use super::run_query_once;
+use super::{run_query_once, MariaDbPools};
 use crate::datastore::AppMariaDbStore;
 use crate::error::AppError;
 use crate::model::{CurrencyModel, CurrencyModelSet};
@@ -112,14 +112,14 @@ impl TryFrom<MySqlRow> for CurrencyModel {
 }
 
 pub(crate) struct CurrencyMariaDbRepo {
-    _db: Arc<AppMariaDbStore>,
+    _pools: MariaDbPools,
 }
 
 #[async_trait]
 impl AbsCurrencyRepo for CurrencyMariaDbRepo {
     async fn fetch(&self, chosen: Vec<CurrencyDto>) -> Result<CurrencyModelSet, AppError> {
         let (sql_patt, args) = FetchArgs(chosen).into();
-        let mut conn = self._db.acquire().await?;
+        let mut conn = self._pools.acquire_read().await?;
         let stmt = conn.prepare(sql_patt.as_str()).await?;
         let query = stmt.query_with(args);
         let exec = &mut *conn;
@@ -163,7 +163,7 @@ impl AbsCurrencyRepo for CurrencyMariaDbRepo {
         }
         ms.check_rate_range()?;
         let (sql_patt, args) = UpdateArgs(ms).into();
-        let mut conn = self._db.acquire().await?;
+        let mut conn = self._pools.primary().acquire().await?;
         let mut tx = conn.begin().await?;
         let _rs = run_query_once(&mut tx, sql_patt, args, Some(expect_num_updated)).await?;
         tx.commit().await?;
@@ -173,10 +173,7 @@ impl AbsCurrencyRepo for CurrencyMariaDbRepo {
 
 impl CurrencyMariaDbRepo {
     pub fn try_build(dstores: &[Arc<AppMariaDbStore>]) -> Result<Self, AppError> {
-        let _db = dstores.first().cloned().ok_or(AppError {
-            code: AppErrorCode::MissingDataStore,
-            detail: Some("mariadb".to_string()),
-        })?;
-        Ok(Self { _db })
+        let _pools = MariaDbPools::try_build(dstores)?;
+        Ok(Self { _pools })
     }
 } // end of impl CurrencyMariaDbRepo
@@ -5,79 +5,25 @@ pub(super) mod order;
 pub(super) mod product_policy;
 pub(super) mod product_price;
 pub(super) mod stock;
+pub(super) mod store_shipping;
 
-use sqlx::error::Error;
-use sqlx::mysql::{MySqlArguments, MySqlQueryResult, MySqlRow};
+use deadpool::managed::{Manager, Object};
+use sqlx::error::Error as SqlxError;
+use sqlx::mysql::{MySqlArguments, MySqlConnection, MySqlQueryResult, MySqlRow};
 use sqlx::{Executor, MySql, Row, Statement, Transaction};
-use std::io::ErrorKind;
 use std::ops::DerefMut;
 use std::result::Result as DefaultResult;
+use std::sync::Arc;
 use std::u8;
 
+use crate::datastore::AppMariaDbStore;
 use crate::error::AppError;
+use crate::repository::DbPoolLayout;
 use ecommerce_common::adapter::repository::OidBytes;
 use ecommerce_common::error::AppErrorCode;
 
 const DATETIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S.%6f";
 
-impl From<Error> for AppError {
-    fn from(value: Error) -> Self {
-        let (code, detail) = match value {
-            Error::Configuration(e) => (
-                AppErrorCode::InvalidInput,
-                e.to_string() + " invalid-db-config",
-            ),
-            Error::Io(e) => (
-                AppErrorCode::IOerror(e.kind()),
-                e.to_string() + " io-err-mariadb",
-            ),
-            Error::Database(e) => (AppErrorCode::RemoteDbServerFailure, e.to_string()),
-            Error::Protocol(errmsg) => (AppErrorCode::IOerror(ErrorKind::InvalidData), errmsg),
-            Error::Decode(e) => (AppErrorCode::DataCorruption, e.to_string()),
-            Error::ColumnDecode { index, source } => (
-                AppErrorCode::DataCorruption,
-                source.to_string() + ", when decoding column at idx " + index.as_str(),
-            ),
-            Error::Tls(e) => (
-                AppErrorCode::IOerror(ErrorKind::NotConnected),
-                e.to_string(),
-            ),
-            Error::TypeNotFound { type_name } => {
-                (AppErrorCode::InvalidInput, type_name + " wrong-col-typ")
-            }
-            Error::ColumnNotFound(col_name) => (
-                AppErrorCode::IOerror(ErrorKind::NotFound),
-                col_name + "no-col",
-            ),
-            Error::RowNotFound => (
-                AppErrorCode::IOerror(ErrorKind::NotFound),
-                "no-row".to_string(),
-            ),
-            Error::ColumnIndexOutOfBounds { index, len } => (
-                AppErrorCode::InvalidInput,
-                format!("req-idx:{}, limit:{}", index, len),
-            ),
-            Error::PoolTimedOut => (
-                AppErrorCode::DatabaseServerBusy,
-                "no-conn-avail".to_string(),
-            ),
-            Error::PoolClosed => (AppErrorCode::Unknown, "pool-closed".to_string()),
-            Error::WorkerCrashed => (
-                AppErrorCode::Unknown,
-                "low-level-db-worker-crashed".to_string(),
-            ),
-            _others => (
-                AppErrorCode::Unknown,
-                "internal-implementation-issue".to_string(),
-            ),
-        };
-        Self {
-            code,
-            detail: Some(detail),
-        }
-    } // end of fn from
-} // end of impl AppError
-
 fn to_app_oid(row: &MySqlRow, idx: usize) -> DefaultResult<String, AppError> {
     let raw = row.try_get::<Vec<u8>, usize>(idx)?;
     let out = OidBytes::to_app_oid(raw)?;
@@ -113,3 +59,100 @@ async fn run_query_once(
         Ok(resultset)
     }
 }
+
+/// Database pools grouped by their roles, see `DbPoolLayout` for how orders
+/// are distributed among the primaries.
+///
+/// Note replicas might lag behind their primary, only the read-only operations which
+/// tolerate slightly stale data should acquire connections from replicas, e.g. listing
+/// or reporting across orders, product properties, stock levels for display. Reads of
+/// a single order always go to its primary, because callers usually read the order
+/// right after it is created or updated.
+pub(super) struct MariaDbPools {
+    primaries: Vec<Arc<AppMariaDbStore>>,
+    // replicas following each primary, in the same order as `primaries`
+    replicas: Vec<Vec<Arc<AppMariaDbStore>>>,
+    layout: DbPoolLayout,
+}
+
+impl MariaDbPools {
+    pub(super) fn try_build(dbs: &[Arc<AppMariaDbStore>]) -> DefaultResult<Self, AppError> {
+        let servers = dbs
+            .iter()
+            .map(|d| (d.alias.as_str(), &d.role))
+            .collect::<Vec<_>>();
+        let layout = DbPoolLayout::try_build(&servers).map_err(|mut e| {
+            e.detail = e.detail.map(|d| format!("mariadb, {d}"));
+            e
+        })?;
+        let primaries = layout
+            .primaries
+            .iter()
+            .map(|idx| dbs[*idx].clone())
+            .collect();
+        let replicas = layout
+            .replicas
+            .iter()
+            .map(|r| r.iter().map(|idx| dbs[*idx].clone()).collect())
+            .collect();
+        Ok(Self {
+            primaries,
+            replicas,
+            layout,
+        })
+    }
+
+    pub(super) fn primary(&self) -> &Arc<AppMariaDbStore> {
+        &self.primaries[0]
+    }
+    pub(super) fn primary_at(&self, idx: usize) -> &Arc<AppMariaDbStore> {
+        &self.primaries[idx]
+    }
+
+    /// index to the primary which holds the given order
+    pub(super) fn order_shard(&self, oid: &OidBytes) -> usize {
+        self.layout.order_shard(oid)
+    }
+    pub(super) fn order_shards(&self) -> &[usize] {
+        self.layout.order_shards.as_slice()
+    }
+    pub(super) fn order_primary(&self, oid: &OidBytes) -> &Arc<AppMariaDbStore> {
+        self.primary_at(self.order_shard(oid))
+    }
+
+    pub(super) async fn acquire_read(
+        &self,
+    ) -> DefaultResult<Object<impl Manager<Type = MySqlConnection, Error = SqlxError>>, AppError>
+    {
+        self.acquire_at(0, true).await
+    }
+
+    /// acquire a connection from the primary at the given index, or from one of
+    /// its replicas if `from_replica` is set, which falls back to the primary when
+    /// none of the replicas is available
+    pub(super) async fn acquire_at(
+        &self,
+        idx: usize,
+        from_replica: bool,
+    ) -> DefaultResult<Object<impl Manager<Type = MySqlConnection, Error = SqlxError>>, AppError>
+    {
+        let replicas = if from_replica {
+            self.replicas[idx].as_slice()
+        } else {
+            &[]
+        };
+        let num_replicas = replicas.len();
+        let start = if num_replicas > 1 {
+            rand::random_range(0..num_replicas)
+        } else {
+            0
+        };
+        for i in 0..num_replicas {
+            let chosen = &replicas[(start + i) % num_replicas];
+            if let Ok(conn) = chosen.acquire().await {
+                return Ok(conn);
+            } // the error has been logged in the pool
+        }
+        self.primaries[idx].acquire().await
+    }
+} // end of impl MariaDbPools
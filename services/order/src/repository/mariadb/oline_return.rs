@@ -18,7 +18,7 @@ use crate::error::AppError;
 use crate::model::{OrderLineIdentity, OrderLinePriceModel, OrderReturnModel};
 use crate::repository::AbsOrderReturnRepo;
 
-use super::{run_query_once, to_app_oid};
+use super::{run_query_once, to_app_oid, MariaDbPools};
 
 struct InsertReqArg(OidBytes, u16, Vec<OrderReturnModel>);
 struct FetchByIdArg(OidBytes, Vec<OrderLineIdentity>);
@@ -234,7 +234,7 @@ impl TryInto<Vec<(String, OrderReturnModel)>> for ReturnOidMap {
 }
 
 pub(crate) struct OrderReturnMariaDbRepo {
-    _db: Arc<AppMariaDbStore>,
+    _pools: MariaDbPools,
 }
 
 #[async_trait]
@@ -248,8 +248,11 @@ impl AbsOrderReturnRepo for OrderReturnMariaDbRepo {
             Ok(vec![])
         } else {
             let oid_b = OidBytes::try_from(oid)?;
+            let shard = self._pools.order_shard(&oid_b);
             let (sql_patt, args) = FetchByIdArg(oid_b, pids).into();
-            self.fetch_by_oid_common(sql_patt, args).await
+            // always read from primary, the result is used to validate new return
+            // requests which should not exceed quantity of the order lines
+            self.fetch_by_oid_common(shard, false, sql_patt, args).await
         }
     }
     async fn fetch_by_created_time(
@@ -257,12 +260,15 @@ impl AbsOrderReturnRepo for OrderReturnMariaDbRepo {
         start: DateTime<FixedOffset>,
         end: DateTime<FixedOffset>,
     ) -> DefaultResult<Vec<(String, OrderReturnModel)>, AppError> {
-        let mut conn = self._db.acquire().await?;
-        let (sql_patt, args) = FetchByTimeArg(start, end).into();
-        let stmt = conn.prepare(sql_patt.as_str()).await?;
-        let query = stmt.query_with(args);
-        let exec = &mut *conn;
-        let rows = exec.fetch_all(query).await?;
+        let mut rows = Vec::new();
+        for idx in self._pools.order_shards() {
+            let mut conn = self._pools.acquire_at(*idx, true).await?;
+            let (sql_patt, args) = FetchByTimeArg(start, end).into();
+            let stmt = conn.prepare(sql_patt.as_str()).await?;
+            let query = stmt.query_with(args);
+            let exec = &mut *conn;
+            rows.extend(exec.fetch_all(query).await?);
+        }
         ReturnOidMap::new(rows).try_into()
     }
     async fn fetch_by_oid_ctime(
@@ -272,8 +278,9 @@ impl AbsOrderReturnRepo for OrderReturnMariaDbRepo {
         end: DateTime<FixedOffset>,
     ) -> DefaultResult<Vec<OrderReturnModel>, AppError> {
         let oid_b = OidBytes::try_from(oid)?;
+        let shard = self._pools.order_shard(&oid_b);
         let (sql_patt, args) = FetchByIdAndTimeArg(oid_b, start, end).into();
-        self.fetch_by_oid_common(sql_patt, args).await
+        self.fetch_by_oid_common(shard, true, sql_patt, args).await
     }
     async fn create(
         &self,
@@ -282,7 +289,7 @@ impl AbsOrderReturnRepo for OrderReturnMariaDbRepo {
     ) -> DefaultResult<usize, AppError> {
         let oid_b = OidBytes::try_from(oid)?;
         let num_batch = reqs.iter().map(|r| r.qty.len()).sum();
-        let mut conn = self._db.acquire().await?;
+        let mut conn = self._pools.order_primary(&oid_b).acquire().await?;
         let mut tx = conn.begin().await?;
         let num_returns = Self::get_num_reqs(&mut tx, &oid_b).await?;
         let (sql_patt, args) = InsertReqArg(oid_b, num_returns, reqs).into();
@@ -294,15 +301,8 @@ impl AbsOrderReturnRepo for OrderReturnMariaDbRepo {
 
 impl OrderReturnMariaDbRepo {
     pub(crate) async fn new(dbs: Vec<Arc<AppMariaDbStore>>) -> DefaultResult<Self, AppError> {
-        if dbs.is_empty() {
-            Err(AppError {
-                code: AppErrorCode::MissingDataStore,
-                detail: Some("mariadb".to_string()),
-            })
-        } else {
-            let _db = dbs.first().unwrap().clone();
-            Ok(Self { _db })
-        }
+        let _pools = MariaDbPools::try_build(&dbs)?;
+        Ok(Self { _pools })
     }
     async fn get_num_reqs(
         tx: &mut Transaction<'_, MySql>,
@@ -332,10 +332,12 @@ impl OrderReturnMariaDbRepo {
     }
     async fn fetch_by_oid_common(
         &self,
+        shard: usize,
+        from_replica: bool,
         sql_patt: String,
         args: MySqlArguments,
     ) -> DefaultResult<Vec<OrderReturnModel>, AppError> {
-        let mut conn = self._db.acquire().await?;
+        let mut conn = self._pools.acquire_at(shard, from_replica).await?;
         let stmt = conn.prepare(sql_patt.as_str()).await?;
         let query = stmt.query_with(args);
         let exec = conn.as_mut();
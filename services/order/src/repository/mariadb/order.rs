@@ -20,20 +20,25 @@ use ecommerce_common::error::AppErrorCode;
 use ecommerce_common::model::order::{BillingModel, ContactModel, PhyAddrModel};
 
 use crate::api::dto::ShippingMethod;
+use crate::api::rpc::dto::{ShipmentErrorDto, StockLevelReturnDto, StockReturnErrorDto};
+use crate::api::web::dto::ShipmentReceiptErrorReason;
 use crate::constant::hard_limit;
 use crate::datastore::AppMariaDbStore;
 use crate::error::AppError;
 use crate::model::{
     CurrencyModel, OrderCurrencyModel, OrderLineAppliedPolicyModel, OrderLineIdentity,
     OrderLineModel, OrderLineModelSet, OrderLinePriceModel, OrderLineQuantityModel,
-    ProdAttriPriceModel, ShippingModel, ShippingOptionModel,
+    ProdAttriPriceModel, ShipmentLineModel, ShipmentModel, ShippingModel, ShippingOptionModel,
 };
 use crate::repository::{
-    AbsOrderRepo, AbsOrderStockRepo, AppOrderFetchRangeCallback, AppOrderRepoUpdateLinesUserFunc,
+    AbsOrderRepo, AbsOrderStockRepo, AppOrderFetchRangeCallback, AppOrderRepoCancelLinesReturn,
+    AppOrderRepoCancelLinesUserFunc, AppOrderRepoOwnedIdsReturn,
+    AppOrderRepoShipmentCreateUserFunc, AppOrderRepoShipmentReceiptUserFunc,
+    AppOrderRepoUpdateLinesUserFunc, AppStockRepoReturnUserFunc,
 };
 
 use super::stock::StockMariaDbRepo;
-use super::{run_query_once, to_app_oid};
+use super::{run_query_once, to_app_oid, MariaDbPools};
 
 struct InsertTopMetaArg<'a, 'b, 'c>(
     &'a OidBytes,
@@ -48,8 +53,13 @@ struct InsertContactEmail<'a, 'b>(&'a str, &'b OidBytes, Vec<String>);
 struct InsertContactPhone<'a, 'b>(&'a str, &'b OidBytes, Vec<PhoneNumberDto>);
 struct InsertPhyAddr<'a, 'b>(&'a str, &'b OidBytes, PhyAddrModel);
 struct InsertShipOption<'a>(&'a OidBytes, Vec<ShippingOptionModel>);
+struct InsertShipmentArg<'a, 'b>(&'a OidBytes, &'b ShipmentModel);
+struct InsertShipmentLineArg<'a, 'b>(&'a OidBytes, &'b ShipmentModel);
 
 struct UpdateOLinePayArg<'a>(&'a OidBytes, Vec<OrderLineModel>);
+struct UpdateOLineCancelArg<'a>(&'a OidBytes, &'a [OrderLineModel]);
+struct UpdateOLineDeliverArg<'a>(&'a OidBytes, &'a [OrderLineModel]);
+struct UpdateShipmentReceiptArg<'a, 'b>(&'a OidBytes, &'b ShipmentModel);
 
 struct FetchAllLinesArg(OidBytes);
 struct FetchLineByIdArg<'a>(&'a OidBytes, Vec<OrderLineIdentity>);
@@ -63,6 +73,8 @@ struct PhoneRow(MySqlRow);
 struct ContactMetaRow(MySqlRow);
 struct PhyAddrrRow(MySqlRow);
 struct ShipOptionRow(MySqlRow);
+struct ShipmentRow(MySqlRow);
+struct ShipmentLineRow(MySqlRow);
 
 impl<'a, 'b, 'c> From<InsertTopMetaArg<'a, 'b, 'c>> for (String, MySqlArguments) {
     fn from(value: InsertTopMetaArg<'a, 'b, 'c>) -> (String, MySqlArguments) {
@@ -120,9 +132,9 @@ impl<'a, 'b> InsertOLineArg<'a, 'b> {
     fn sql_pattern(num_batch: usize) -> String {
         let col_seq = "`o_id`,`seq`,`store_id`,`product_id`,`price_unit`,`price_total`,\
                        `qty_rsved`,`rsved_until`,`warranty_until`,`attr_lastupdate`,\
-                       `attr_price`,`attr_seq`";
+                       `attr_price`,`attr_seq`,`policy_ver`";
         let items = (0..num_batch)
-            .map(|_| "(?,?,?,?,?,?,?,?,?,?,?,?)")
+            .map(|_| "(?,?,?,?,?,?,?,?,?,?,?,?,?)")
             .collect::<Vec<_>>();
         format!(
             "INSERT INTO `order_line_detail`({}) VALUES {}",
@@ -155,6 +167,7 @@ impl<'a, 'b, 'q> IntoArguments<'q, MySql> for InsertOLineArg<'a, 'b> {
                 args.add(attr_lupdate).unwrap();
                 args.add(attr_pricemap).unwrap();
                 args.add(o.id().attrs_seq_num()).unwrap();
+                args.add(o.policy.version).unwrap();
             })
             .count();
         args
@@ -310,6 +323,64 @@ impl<'a> From<InsertShipOption<'a>> for (String, MySqlArguments) {
     }
 }
 
+impl<'a, 'b> From<InsertShipmentArg<'a, 'b>> for (String, MySqlArguments) {
+    fn from(value: InsertShipmentArg<'a, 'b>) -> (String, MySqlArguments) {
+        let (oid, shipment) = (value.0, value.1);
+        let sql_patt = "INSERT INTO `order_shipment`(`o_id`,`seller_id`,`tracking_num`,\
+                        `carrier`,`create_time`) VALUES (?,?,?,?,?)";
+        let carrier: String = shipment.carrier.clone().into();
+        let mut args = MySqlArguments::default();
+        args.add(oid.as_column()).unwrap();
+        args.add(shipment.seller_id).unwrap();
+        args.add(shipment.tracking_num.as_str()).unwrap();
+        args.add(carrier).unwrap();
+        args.add(shipment.create_time.naive_utc()).unwrap();
+        (sql_patt.to_string(), args)
+    }
+}
+impl<'a, 'b> InsertShipmentLineArg<'a, 'b> {
+    fn sql_pattern(num_batch: usize) -> String {
+        let items = (0..num_batch)
+            .map(|_num| "(?,?,?,?,?,?)")
+            .collect::<Vec<_>>();
+        format!(
+            "INSERT INTO `order_shipment_line`(`o_id`,`seller_id`,`tracking_num`,\
+             `product_id`,`attr_seq`,`qty`) VALUES {}",
+            items.join(",")
+        )
+    }
+}
+impl<'a, 'b, 'q> IntoArguments<'q, MySql> for InsertShipmentLineArg<'a, 'b> {
+    fn into_arguments(self) -> <MySql as AbstractDatabase>::Arguments<'q> {
+        let (oid, shipment) = (self.0, self.1);
+        let oid = oid.as_column();
+        let mut args = MySqlArguments::default();
+        shipment
+            .lines
+            .iter()
+            .map(|line| {
+                args.add(&oid).unwrap();
+                args.add(shipment.seller_id).unwrap();
+                args.add(shipment.tracking_num.clone()).unwrap();
+                args.add(line.product_id).unwrap();
+                args.add(line.attr_set_seq).unwrap();
+                args.add(line.qty).unwrap();
+            })
+            .count();
+        args
+    }
+}
+impl<'a, 'b> From<InsertShipmentLineArg<'a, 'b>> for (String, MySqlArguments) {
+    fn from(value: InsertShipmentLineArg<'a, 'b>) -> (String, MySqlArguments) {
+        let num_batch = value.1.lines.len();
+        assert!(num_batch > 0);
+        (
+            InsertShipmentLineArg::sql_pattern(num_batch),
+            value.into_arguments(),
+        )
+    }
+}
+
 impl<'a> UpdateOLinePayArg<'a> {
     fn sql_pattern(num_batch: usize) -> String {
         let condition = "(`store_id`=? AND `product_id`=? AND `attr_seq`=?)";
@@ -325,8 +396,10 @@ impl<'a> UpdateOLinePayArg<'a> {
             format!("`qty_paid` = CASE {case_ops} ELSE `qty_paid` END"),
             format!("`qty_paid_last_update` = CASE {case_ops} ELSE `qty_paid_last_update` END"),
         ];
+        // lines cancelled concurrently are excluded, which leads to mismatch on
+        // number of affected rows, reported as cancelled lines by the caller
         format!(
-            "UPDATE `order_line_detail` SET {}, {} WHERE `o_id`=? AND ({})",
+            "UPDATE `order_line_detail` SET {}, {} WHERE `o_id`=? AND `qty_cancelled`=0 AND ({})",
             portions[0], portions[1], where_ops
         )
     }
@@ -377,9 +450,130 @@ impl<'a> From<UpdateOLinePayArg<'a>> for (String, MySqlArguments) {
     }
 }
 
+impl<'a> UpdateOLineCancelArg<'a> {
+    fn sql_pattern(num_batch: usize) -> String {
+        let condition = "(`store_id`=? AND `product_id`=? AND `attr_seq`=?)";
+        let case_ops = (0..num_batch)
+            .flat_map(|_| ["WHEN", condition, "THEN", "?"])
+            .collect::<Vec<_>>()
+            .join(" ");
+        let where_ops = (0..num_batch)
+            .map(|_| condition)
+            .collect::<Vec<_>>()
+            .join("OR");
+        format!(
+            "UPDATE `order_line_detail` SET `qty_cancelled` = CASE {case_ops} ELSE \
+             `qty_cancelled` END WHERE `o_id`=? AND `qty_cancelled`=0 AND ({where_ops})"
+        )
+    }
+}
+impl<'a, 'q> IntoArguments<'q, MySql> for UpdateOLineCancelArg<'a> {
+    fn into_arguments(self) -> <MySql as AbstractDatabase>::Arguments<'q> {
+        let (oid, lines) = (self.0, self.1);
+        let mut args = MySqlArguments::default();
+        lines
+            .iter()
+            .map(|line| {
+                args.add(line.id().store_id()).unwrap();
+                args.add(line.id().product_id()).unwrap();
+                args.add(line.id().attrs_seq_num()).unwrap();
+                args.add(line.qty.cancelled).unwrap();
+            })
+            .count();
+        args.add(oid.as_column()).unwrap();
+        lines
+            .iter()
+            .map(|line| {
+                args.add(line.id().store_id()).unwrap();
+                args.add(line.id().product_id()).unwrap();
+                args.add(line.id().attrs_seq_num()).unwrap();
+            })
+            .count();
+        args
+    }
+}
+impl<'a> From<UpdateOLineCancelArg<'a>> for (String, MySqlArguments) {
+    fn from(value: UpdateOLineCancelArg<'a>) -> (String, MySqlArguments) {
+        let num_batch = value.1.len();
+        assert!(num_batch > 0);
+        (
+            UpdateOLineCancelArg::sql_pattern(num_batch),
+            value.into_arguments(),
+        )
+    }
+}
+
+impl<'a> UpdateOLineDeliverArg<'a> {
+    fn sql_pattern(num_batch: usize) -> String {
+        let condition = "(`store_id`=? AND `product_id`=? AND `attr_seq`=?)";
+        let case_ops = (0..num_batch)
+            .flat_map(|_| ["WHEN", condition, "THEN", "?"])
+            .collect::<Vec<_>>()
+            .join(" ");
+        let where_ops = (0..num_batch)
+            .map(|_| condition)
+            .collect::<Vec<_>>()
+            .join("OR");
+        format!(
+            "UPDATE `order_line_detail` SET `qty_delivered` = CASE {case_ops} ELSE \
+             `qty_delivered` END WHERE `o_id`=? AND ({where_ops})"
+        )
+    }
+}
+impl<'a, 'q> IntoArguments<'q, MySql> for UpdateOLineDeliverArg<'a> {
+    fn into_arguments(self) -> <MySql as AbstractDatabase>::Arguments<'q> {
+        let (oid, lines) = (self.0, self.1);
+        let mut args = MySqlArguments::default();
+        lines
+            .iter()
+            .map(|line| {
+                args.add(line.id().store_id()).unwrap();
+                args.add(line.id().product_id()).unwrap();
+                args.add(line.id().attrs_seq_num()).unwrap();
+                args.add(line.qty.delivered).unwrap();
+            })
+            .count();
+        args.add(oid.as_column()).unwrap();
+        lines
+            .iter()
+            .map(|line| {
+                args.add(line.id().store_id()).unwrap();
+                args.add(line.id().product_id()).unwrap();
+                args.add(line.id().attrs_seq_num()).unwrap();
+            })
+            .count();
+        args
+    }
+}
+impl<'a> From<UpdateOLineDeliverArg<'a>> for (String, MySqlArguments) {
+    fn from(value: UpdateOLineDeliverArg<'a>) -> (String, MySqlArguments) {
+        let num_batch = value.1.len();
+        assert!(num_batch > 0);
+        (
+            UpdateOLineDeliverArg::sql_pattern(num_batch),
+            value.into_arguments(),
+        )
+    }
+}
+
+impl<'a, 'b> From<UpdateShipmentReceiptArg<'a, 'b>> for (String, MySqlArguments) {
+    fn from(value: UpdateShipmentReceiptArg<'a, 'b>) -> (String, MySqlArguments) {
+        let (oid, shipment) = (value.0, value.1);
+        let sql_patt = "UPDATE `order_shipment` SET `received_time`=? WHERE `o_id`=? \
+                        AND `seller_id`=? AND `tracking_num`=? AND `received_time` IS NULL";
+        let mut args = MySqlArguments::default();
+        args.add(shipment.received_time.map(|t| t.naive_utc()))
+            .unwrap();
+        args.add(oid.as_column()).unwrap();
+        args.add(shipment.seller_id).unwrap();
+        args.add(shipment.tracking_num.as_str()).unwrap();
+        (sql_patt.to_string(), args)
+    }
+}
+
 const OLINE_SELECT_PREFIX: &str = "SELECT `store_id`,`product_id`,`attr_seq`,`price_unit`,\
    `price_total`,`qty_rsved`,`qty_paid`,`qty_paid_last_update`,`rsved_until`,\
-    `warranty_until`,`attr_lastupdate`,`attr_price` FROM `order_line_detail`";
+    `warranty_until`,`attr_lastupdate`,`attr_price`,`qty_cancelled`,`policy_ver`,`qty_delivered` FROM `order_line_detail`";
 
 impl From<FetchAllLinesArg> for (String, MySqlArguments) {
     fn from(value: FetchAllLinesArg) -> (String, MySqlArguments) {
@@ -391,12 +585,15 @@ impl From<FetchAllLinesArg> for (String, MySqlArguments) {
     }
 }
 impl<'a> FetchLineByIdArg<'a> {
+    // the lines are always loaded within a transaction which updates them later,
+    // lock the rows so concurrent payment, cancellation or shipment requests
+    // on the same lines are serialised instead of working on stale quantities
     fn sql_pattern(num_batch: usize) -> String {
         let items = (0..num_batch)
             .map(|_| "(`store_id`=? AND `product_id`=? AND `attr_seq`=?)")
             .collect::<Vec<_>>();
         format!(
-            "{OLINE_SELECT_PREFIX} WHERE `o_id`=? AND ({})",
+            "{OLINE_SELECT_PREFIX} WHERE `o_id`=? AND ({}) FOR UPDATE",
             items.join("OR")
         )
     }
@@ -511,10 +708,13 @@ impl TryFrom<OLineRow> for OrderLineModel {
             })?;
             ProdAttriPriceModel::deserialize_map(serial)?
         };
+        let cancelled = row.try_get::<u32, usize>(12)?;
+        let version = row.try_get::<u32, usize>(13)?;
+        let delivered = row.try_get::<u32, usize>(14)?;
         let id_ = OrderLineIdentity::from((store_id, product_id, attr_seq));
         let price = OrderLinePriceModel::from((unit, total));
-        let qty = OrderLineQuantityModel {reserved, paid, paid_last_update};
-        let policy = OrderLineAppliedPolicyModel {warranty_until, reserved_until};
+        let qty = OrderLineQuantityModel {reserved, paid, paid_last_update, cancelled, delivered};
+        let policy = OrderLineAppliedPolicyModel {warranty_until, reserved_until, version};
         let attr_chg = ProdAttriPriceModel::from((attr_lupdate, attrprice));
         Ok(OrderLineModel::from((id_, price, policy, qty, attr_chg)))
     }
@@ -590,8 +790,47 @@ impl TryInto<ShippingOptionModel> for ShipOptionRow {
     }
 }
 
+#[rustfmt::skip]
+impl TryFrom<ShipmentRow> for ShipmentModel {
+    type Error = AppError;
+    fn try_from(value: ShipmentRow) -> DefaultResult<Self, Self::Error> {
+        let row = value.0;
+        let seller_id = row.try_get::<u32, usize>(0)?;
+        let tracking_num = row.try_get::<String, usize>(1)?;
+        let carrier = {
+            let raw = row.try_get::<&[u8], usize>(2)?;
+            let raw = std::str::from_utf8(raw).map_err(|e| AppError {
+                code: AppErrorCode::DataCorruption,
+                detail: Some(e.to_string()),
+            })?;
+            ShippingMethod::from(raw.to_string())
+        };
+        let create_time = row.try_get::<NaiveDateTime, usize>(3)?.and_utc().into();
+        let received_time = row.try_get::<Option<NaiveDateTime>, usize>(4)?
+            .map(|t| t.and_utc().into());
+        Ok(Self {
+            seller_id, carrier, tracking_num, create_time, received_time,
+            lines: Vec::new(),
+        })
+    }
+}
+impl TryFrom<ShipmentLineRow> for (u32, String, ShipmentLineModel) {
+    type Error = AppError;
+    fn try_from(value: ShipmentLineRow) -> DefaultResult<Self, Self::Error> {
+        let row = value.0;
+        let seller_id = row.try_get::<u32, usize>(0)?;
+        let tracking_num = row.try_get::<String, usize>(1)?;
+        let line = ShipmentLineModel {
+            product_id: row.try_get::<u64, usize>(2)?,
+            attr_set_seq: row.try_get::<u16, usize>(3)?,
+            qty: row.try_get::<u32, usize>(4)?,
+        };
+        Ok((seller_id, tracking_num, line))
+    }
+}
+
 pub(crate) struct OrderMariaDbRepo {
-    _db: Arc<AppMariaDbStore>,
+    _pools: Arc<MariaDbPools>,
     _stock: Arc<Box<dyn AbsOrderStockRepo>>,
 }
 
@@ -609,7 +848,7 @@ impl AbsOrderRepo for OrderMariaDbRepo {
     ) -> DefaultResult<(), AppError> {
         // TODO, consider update case
         let oid_b = OidBytes::try_from(oid)?;
-        let mut conn = self._db.acquire().await?;
+        let mut conn = self._pools.order_primary(&oid_b).acquire().await?;
         let mut tx = conn.begin().await?;
         let (bl_contact, bl_phyaddr) = (bl.contact, bl.address);
         let (sh_contact, sh_phyaddr, sh_opt) = (sh.contact, sh.address, sh.option);
@@ -627,7 +866,7 @@ impl AbsOrderRepo for OrderMariaDbRepo {
     }
     async fn fetch_all_lines(&self, oid: String) -> DefaultResult<Vec<OrderLineModel>, AppError> {
         let oid_b = OidBytes::try_from(oid.as_str())?;
-        let mut conn = self._db.acquire().await?;
+        let mut conn = self._pools.order_primary(&oid_b).acquire().await?;
         let (sql_patt, args) = FetchAllLinesArg(oid_b).into();
         let stmt = conn.prepare(sql_patt.as_str()).await?;
         let query = stmt.query_with(args);
@@ -643,7 +882,7 @@ impl AbsOrderRepo for OrderMariaDbRepo {
     }
     async fn fetch_billing(&self, oid: String) -> DefaultResult<BillingModel, AppError> {
         let oid_b = OidBytes::try_from(oid.as_str())?;
-        let mut conn = self._db.acquire().await?;
+        let mut conn = self._pools.order_primary(&oid_b).acquire().await?;
         let emails = Self::_fetch_mails(conn.as_mut(), "bill", &oid_b).await?;
         let phones = Self::_fetch_phones(conn.as_mut(), "bill", &oid_b).await?;
         let mut contact = Self::_fetch_contact_meta(conn.as_mut(), "bill", &oid_b).await?;
@@ -654,7 +893,7 @@ impl AbsOrderRepo for OrderMariaDbRepo {
     }
     async fn fetch_shipping(&self, oid: String) -> DefaultResult<ShippingModel, AppError> {
         let oid_b = OidBytes::try_from(oid.as_str())?;
-        let mut conn = self._db.acquire().await?;
+        let mut conn = self._pools.order_primary(&oid_b).acquire().await?;
         let emails = Self::_fetch_mails(conn.as_mut(), "ship", &oid_b).await?;
         let phones = Self::_fetch_phones(conn.as_mut(), "ship", &oid_b).await?;
         let mut contact = Self::_fetch_contact_meta(conn.as_mut(), "ship", &oid_b).await?;
@@ -683,15 +922,35 @@ impl AbsOrderRepo for OrderMariaDbRepo {
                 OrderLineIdentity::from(args)
             })
             .collect::<Vec<_>>();
-        let mut conn = self._db.acquire().await?;
+        let mut conn = self._pools.order_primary(&oid_b).acquire().await?;
         let mut tx = conn.begin().await?;
-        let mut saved_lines = Self::_fetch_lines_by_pid(&mut tx, &oid_b, pids).await?;
-        let errors = cb(&mut saved_lines, data);
+        let mut saved_lines = Self::_fetch_lines_by_pid(&mut tx, &oid_b, pids.clone()).await?;
+        let mut errors = cb(&mut saved_lines, data);
         if errors.is_empty() {
             let num_affected = saved_lines.len();
             let (sql_patt, args) = UpdateOLinePayArg(&oid_b, saved_lines).into();
-            let _rs = run_query_once(&mut tx, sql_patt, args, Some(num_affected)).await?;
-            tx.commit().await?;
+            let rs = run_query_once(&mut tx, sql_patt, args, None).await?;
+            if rs.rows_affected() as usize == num_affected {
+                tx.commit().await?;
+            } else {
+                // some lines were cancelled concurrently and excluded from the
+                // update, discard the payment and report the cancelled lines
+                tx.rollback().await?;
+                let mut tx = conn.begin().await?;
+                let latest = Self::_fetch_lines_by_pid(&mut tx, &oid_b, pids).await?;
+                errors = OrderLineModel::pay_cancelled_errors(&latest);
+                if errors.is_empty() {
+                    let detail = format!(
+                        "num_affected, actual:{}, expect:{}",
+                        rs.rows_affected(),
+                        num_affected
+                    );
+                    return Err(AppError {
+                        code: AppErrorCode::DataCorruption,
+                        detail: Some(detail),
+                    });
+                }
+            }
         }
         Ok(OrderPaymentUpdateErrorDto {
             oid,
@@ -699,6 +958,39 @@ impl AbsOrderRepo for OrderMariaDbRepo {
             lines: errors,
         })
     }
+    async fn cancel_unpaid_lines(
+        &self,
+        oid: &str,
+        pids: Vec<OrderLineIdentity>,
+        cb: AppOrderRepoCancelLinesUserFunc,
+        stk_cb: AppStockRepoReturnUserFunc,
+    ) -> DefaultResult<AppOrderRepoCancelLinesReturn, AppError> {
+        let oid_b = OidBytes::try_from(oid)?;
+        let shard = self._pools.order_shard(&oid_b);
+        let mut conn = self._pools.primary_at(shard).acquire().await?;
+        let mut tx = conn.begin().await?;
+        let mut saved_lines = Self::_fetch_lines_by_pid(&mut tx, &oid_b, pids.clone()).await?;
+        let time_now = Local::now().fixed_offset();
+        let errors = cb(&mut saved_lines, pids, time_now);
+        if !errors.is_empty() {
+            return Ok(Err(errors));
+        }
+        let num_affected = saved_lines.len();
+        let (sql_patt, args) = UpdateOLineCancelArg(&oid_b, &saved_lines).into();
+        let _rs = run_query_once(&mut tx, sql_patt, args, Some(num_affected)).await?;
+        let data = OrderLineModel::cancelled_stock_return(oid, &saved_lines);
+        let stk_errors = if shard == 0 {
+            // stock levels are kept in the same database, return the reserved
+            // stock within the same transaction
+            let errors = StockMariaDbRepo::return_in_tx(&mut tx, stk_cb, data).await?;
+            tx.commit().await?;
+            errors
+        } else {
+            self._cancel_lines_in_shard(tx, &oid_b, &saved_lines, stk_cb, data)
+                .await?
+        };
+        Ok(Ok(stk_errors))
+    }
     async fn fetch_lines_by_rsvtime(
         &self,
         time_start: DateTime<FixedOffset>,
@@ -707,45 +999,51 @@ impl AbsOrderRepo for OrderMariaDbRepo {
     ) -> DefaultResult<(), AppError> {
         // current approach will lead to full-table scan and requires 2 conncetions,
         // TODO, improve query time when the table grows to large amount of data
-        let mut conn0 = self._db.acquire().await?;
-        let mut conn1 = self._db.acquire().await?;
         let (time_start, time_end) = (time_start.naive_utc(), time_end.naive_utc());
-        let sql_patt = "SELECT `a`.`o_id`,`a`.`usr_id`,`a`.`created_time`, `a`.`buyer_currency`, \
-                        `a`.`buyer_ex_rate` FROM `order_toplvl_meta` AS `a` INNER JOIN \
-                        `order_line_detail` AS `b` ON `a`.`o_id` = `b`.`o_id` WHERE \
-                        `b`.`rsved_until` > ? AND `b`.`rsved_until` < ? GROUP BY `a`.`o_id`";
-        let stmt = conn0.prepare(sql_patt).await?;
-        let mut stream = {
-            let query = stmt.query().bind(time_start).bind(time_end);
-            let exec = &mut *conn0;
-            exec.fetch(query)
-        };
-        while let Some(result) = stream.next().await {
-            let row = result?;
-            let oid_raw = row.try_get::<Vec<u8>, usize>(0)?;
-            let sellers_currency =
-                Self::_fetch_seller_exrates(conn1.as_mut(), oid_raw.clone()).await?;
-            let mut ol_set: OrderLineModelSet = TopLvlMetaRow(row, sellers_currency).try_into()?;
-            let sql_patt = format!(
-                "{OLINE_SELECT_PREFIX} WHERE `o_id`=? AND \
-                    (? < `rsved_until` AND `rsved_until` < ?)"
-            );
-            let stmt = conn1.prepare(sql_patt.as_str()).await?;
-            let query = stmt.query().bind(oid_raw).bind(time_start).bind(time_end);
-            let exec = &mut *conn1;
-            let rows = exec.fetch_all(query).await?;
-            let results = rows
-                .into_iter()
-                .map(|row| OLineRow(row).try_into())
-                .collect::<Vec<DefaultResult<OrderLineModel, AppError>>>();
-            let newlines = if let Some(Err(e)) = results.iter().find(|r| r.is_err()) {
-                return Err(e.to_owned());
-            } else {
-                results.into_iter().map(|r| r.unwrap()).collect::<Vec<_>>()
+        // the reserved lines are cancelled by the caller, always scan on the primaries
+        // in order not to miss any payment update which has not been replicated yet
+        for idx in self._pools.order_shards() {
+            let mut conn0 = self._pools.acquire_at(*idx, false).await?;
+            let mut conn1 = self._pools.acquire_at(*idx, false).await?;
+            let sql_patt =
+                "SELECT `a`.`o_id`,`a`.`usr_id`,`a`.`created_time`, `a`.`buyer_currency`, \
+                            `a`.`buyer_ex_rate` FROM `order_toplvl_meta` AS `a` INNER JOIN \
+                            `order_line_detail` AS `b` ON `a`.`o_id` = `b`.`o_id` WHERE \
+                            `b`.`rsved_until` > ? AND `b`.`rsved_until` < ? GROUP BY `a`.`o_id`";
+            let stmt = conn0.prepare(sql_patt).await?;
+            let mut stream = {
+                let query = stmt.query().bind(time_start).bind(time_end);
+                let exec = &mut *conn0;
+                exec.fetch(query)
             };
-            ol_set.append_lines(newlines);
-            usr_cb(self, ol_set).await?;
-        } // end of loop
+            while let Some(result) = stream.next().await {
+                let row = result?;
+                let oid_raw = row.try_get::<Vec<u8>, usize>(0)?;
+                let sellers_currency =
+                    Self::_fetch_seller_exrates(conn1.as_mut(), oid_raw.clone()).await?;
+                let mut ol_set: OrderLineModelSet =
+                    TopLvlMetaRow(row, sellers_currency).try_into()?;
+                let sql_patt = format!(
+                    "{OLINE_SELECT_PREFIX} WHERE `o_id`=? AND \
+                        (? < `rsved_until` AND `rsved_until` < ?)"
+                );
+                let stmt = conn1.prepare(sql_patt.as_str()).await?;
+                let query = stmt.query().bind(oid_raw).bind(time_start).bind(time_end);
+                let exec = &mut *conn1;
+                let rows = exec.fetch_all(query).await?;
+                let results = rows
+                    .into_iter()
+                    .map(|row| OLineRow(row).try_into())
+                    .collect::<Vec<DefaultResult<OrderLineModel, AppError>>>();
+                let newlines = if let Some(Err(e)) = results.iter().find(|r| r.is_err()) {
+                    return Err(e.to_owned());
+                } else {
+                    results.into_iter().map(|r| r.unwrap()).collect::<Vec<_>>()
+                };
+                ol_set.append_lines(newlines);
+                usr_cb(self, ol_set).await?;
+            } // end of loop
+        } // end of loop over order shards
         Ok(())
     } // end of fn fetch_lines_by_rsvtime
 
@@ -755,7 +1053,7 @@ impl AbsOrderRepo for OrderMariaDbRepo {
         pids: Vec<OrderLineIdentity>,
     ) -> DefaultResult<Vec<OrderLineModel>, AppError> {
         let oid_b = OidBytes::try_from(oid)?;
-        let mut conn = self._db.acquire().await?;
+        let mut conn = self._pools.order_primary(&oid_b).acquire().await?;
         let mut tx = conn.begin().await?;
         Self::_fetch_lines_by_pid(&mut tx, &oid_b, pids).await
     }
@@ -767,14 +1065,17 @@ impl AbsOrderRepo for OrderMariaDbRepo {
         end: DateTime<FixedOffset>,
     ) -> DefaultResult<Vec<String>, AppError> {
         // TODO, to enhance performance, build extra index for the column `create-time`
-        let mut conn = self._db.acquire().await?;
         let sql_patt = "SELECT `o_id` FROM `order_toplvl_meta` WHERE \
                         `created_time` >= ? AND `created_time` <= ?";
         let (start, end) = (start.naive_utc(), end.naive_utc());
-        let stmt = conn.prepare(sql_patt).await?;
-        let query = stmt.query().bind(start).bind(end);
-        let exec = conn.as_mut();
-        let rows = exec.fetch_all(query).await?;
+        let mut rows = Vec::new();
+        for idx in self._pools.order_shards() {
+            let mut conn = self._pools.acquire_at(*idx, true).await?;
+            let stmt = conn.prepare(sql_patt).await?;
+            let query = stmt.query().bind(start).bind(end);
+            let exec = conn.as_mut();
+            rows.extend(exec.fetch_all(query).await?);
+        }
         let results = rows
             .into_iter()
             .map(|row| to_app_oid(&row, 0))
@@ -786,23 +1087,72 @@ impl AbsOrderRepo for OrderMariaDbRepo {
         };
         Ok(o_meta)
     }
+    async fn fetch_ids_by_owner_created_time(
+        &self,
+        usr_id: u32,
+        start: DateTime<FixedOffset>,
+        end: DateTime<FixedOffset>,
+        num_skip: usize,
+        limit: usize,
+    ) -> DefaultResult<AppOrderRepoOwnedIdsReturn, AppError> {
+        // covered by the index `(usr_id, created_time)`, orders of a user may
+        // spread across shards, each shard returns enough orders to fill the
+        // requested page, the merged result is sliced afterwards
+        let sql_count = "SELECT COUNT(*) FROM `order_toplvl_meta` WHERE \
+                         `usr_id` = ? AND `created_time` >= ? AND `created_time` <= ?";
+        let sql_patt = "SELECT `o_id`, `created_time` FROM `order_toplvl_meta` WHERE \
+                        `usr_id` = ? AND `created_time` >= ? AND `created_time` <= ? \
+                        ORDER BY `created_time` ASC, `o_id` ASC LIMIT ?";
+        let (start, end) = (start.naive_utc(), end.naive_utc());
+        let num_fetch = num_skip.saturating_add(limit) as u64;
+        let (mut total, mut out) = (0usize, Vec::new());
+        for idx in self._pools.order_shards() {
+            let mut conn = self._pools.acquire_at(*idx, true).await?;
+            let stmt = conn.prepare(sql_count).await?;
+            let query = stmt.query().bind(usr_id).bind(start).bind(end);
+            let exec = conn.as_mut();
+            let row = exec.fetch_one(query).await?;
+            let num_owned = row.try_get::<i64, usize>(0)?;
+            total += num_owned as usize;
+            if num_owned == 0 {
+                continue;
+            }
+            let stmt = conn.prepare(sql_patt).await?;
+            let query = stmt
+                .query()
+                .bind(usr_id)
+                .bind(start)
+                .bind(end)
+                .bind(num_fetch);
+            let exec = conn.as_mut();
+            for row in exec.fetch_all(query).await? {
+                let oid = to_app_oid(&row, 0)?;
+                let ctime: DateTime<FixedOffset> =
+                    row.try_get::<NaiveDateTime, usize>(1)?.and_utc().into();
+                out.push((oid, ctime));
+            }
+        }
+        out.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+        let out = out.into_iter().skip(num_skip).take(limit).collect();
+        Ok((total, out))
+    }
     async fn owner_id(&self, oid: &str) -> DefaultResult<u32, AppError> {
-        let OidBytes(oid_b) = OidBytes::try_from(oid)?;
+        let oid_b = OidBytes::try_from(oid)?;
         let sql_patt = "SELECT `usr_id` FROM `order_toplvl_meta` WHERE `o_id`=?";
-        let mut conn = self._db.acquire().await?;
+        let mut conn = self._pools.order_primary(&oid_b).acquire().await?;
         let stmt = conn.prepare(sql_patt).await?;
-        let query = stmt.query().bind(oid_b.to_vec());
+        let query = stmt.query().bind(oid_b.as_column());
         let exec = conn.as_mut();
         let row = exec.fetch_one(query).await?;
         let owner_id = row.try_get::<u32, usize>(0)?;
         Ok(owner_id)
     }
     async fn created_time(&self, oid: &str) -> DefaultResult<DateTime<FixedOffset>, AppError> {
-        let OidBytes(oid_b) = OidBytes::try_from(oid)?;
+        let oid_b = OidBytes::try_from(oid)?;
         let sql_patt = "SELECT `created_time` FROM `order_toplvl_meta` WHERE `o_id`=?";
-        let mut conn = self._db.acquire().await?;
+        let mut conn = self._pools.order_primary(&oid_b).acquire().await?;
         let stmt = conn.prepare(sql_patt).await?;
-        let query = stmt.query().bind(oid_b.to_vec());
+        let query = stmt.query().bind(oid_b.as_column());
         let exec = conn.as_mut();
         let row = exec.fetch_one(query).await?;
         let ctime = row.try_get::<NaiveDateTime, usize>(0)?.and_utc().into();
@@ -813,7 +1163,7 @@ impl AbsOrderRepo for OrderMariaDbRepo {
         let oid_b = OidBytes::try_from(oid)?;
         let sql_patt = "SELECT `buyer_currency`, `buyer_ex_rate` FROM `order_toplvl_meta` \
                         WHERE `o_id`=?";
-        let mut conn = self._db.acquire().await?;
+        let mut conn = self._pools.order_primary(&oid_b).acquire().await?;
         let buyer = {
             let stmt = conn.prepare(sql_patt).await?;
             let query = stmt.query().bind(oid_b.as_column());
@@ -827,7 +1177,7 @@ impl AbsOrderRepo for OrderMariaDbRepo {
 
     async fn cancel_unpaid_last_time(&self) -> DefaultResult<DateTime<FixedOffset>, AppError> {
         let sql_patt = "SELECT `last_update` FROM `schedule_job`";
-        let mut conn = self._db.acquire().await?;
+        let mut conn = self._pools.primary().acquire().await?;
         let stmt = conn.prepare(sql_patt).await?;
         let query = stmt.query();
         let exec = conn.as_mut();
@@ -837,7 +1187,7 @@ impl AbsOrderRepo for OrderMariaDbRepo {
         Ok(t)
     }
     async fn cancel_unpaid_time_update(&self) -> DefaultResult<(), AppError> {
-        let mut conn = self._db.acquire().await?;
+        let mut conn = self._pools.primary().acquire().await?;
         let sql_patt = "UPDATE `schedule_job` SET `last_update`=?";
         let t = Local::now().naive_utc();
         let stmt = conn.prepare(sql_patt).await?;
@@ -847,6 +1197,82 @@ impl AbsOrderRepo for OrderMariaDbRepo {
         let _num_affected = resultset.rows_affected();
         Ok(())
     }
+
+    async fn fetch_shipments(&self, oid: &str) -> DefaultResult<Vec<ShipmentModel>, AppError> {
+        let oid_b = OidBytes::try_from(oid)?;
+        let mut conn = self._pools.order_primary(&oid_b).acquire().await?;
+        Self::_fetch_shipments(conn.as_mut(), &oid_b).await
+    }
+
+    async fn create_shipment(
+        &self,
+        oid: &str,
+        shipment: ShipmentModel,
+        cb: AppOrderRepoShipmentCreateUserFunc,
+    ) -> DefaultResult<DefaultResult<(), ShipmentErrorDto>, AppError> {
+        let oid_b = OidBytes::try_from(oid)?;
+        let pids = shipment
+            .lines
+            .iter()
+            .map(|l| OrderLineIdentity::from((shipment.seller_id, l.product_id, l.attr_set_seq)))
+            .collect::<Vec<_>>();
+        let mut conn = self._pools.order_primary(&oid_b).acquire().await?;
+        let mut tx = conn.begin().await?;
+        let saved_lines = Self::_fetch_lines_by_pid(&mut tx, &oid_b, pids).await?;
+        let saved_shipments = Self::_fetch_shipments(&mut tx, &oid_b)
+            .await?
+            .into_iter()
+            .filter(|s| s.seller_id == shipment.seller_id)
+            .collect::<Vec<_>>();
+        if let Err(e) = cb(&shipment, &saved_lines, &saved_shipments) {
+            return Ok(Err(e));
+        }
+        let num_lines = shipment.lines.len();
+        let (sql_patt, args) = InsertShipmentArg(&oid_b, &shipment).into();
+        let _rs = run_query_once(&mut tx, sql_patt, args, Some(1)).await?;
+        let (sql_patt, args) = InsertShipmentLineArg(&oid_b, &shipment).into();
+        let _rs = run_query_once(&mut tx, sql_patt, args, Some(num_lines)).await?;
+        tx.commit().await?;
+        Ok(Ok(()))
+    }
+
+    async fn confirm_shipment_receipt(
+        &self,
+        oid: &str,
+        seller_id: u32,
+        tracking_num: &str,
+        cb: AppOrderRepoShipmentReceiptUserFunc,
+    ) -> DefaultResult<DefaultResult<(), ShipmentReceiptErrorReason>, AppError> {
+        let oid_b = OidBytes::try_from(oid)?;
+        let mut conn = self._pools.order_primary(&oid_b).acquire().await?;
+        let mut tx = conn.begin().await?;
+        let result = Self::_fetch_shipments(&mut tx, &oid_b)
+            .await?
+            .into_iter()
+            .find(|s| s.seller_id == seller_id && s.tracking_num == tracking_num);
+        let mut shipment = if let Some(v) = result {
+            v
+        } else {
+            return Ok(Err(ShipmentReceiptErrorReason::NotExist));
+        };
+        let pids = shipment
+            .lines
+            .iter()
+            .map(|l| OrderLineIdentity::from((seller_id, l.product_id, l.attr_set_seq)))
+            .collect::<Vec<_>>();
+        let mut saved_lines = Self::_fetch_lines_by_pid(&mut tx, &oid_b, pids).await?;
+        let time_now = Local::now().fixed_offset();
+        if let Err(e) = cb(&mut shipment, &mut saved_lines, time_now) {
+            return Ok(Err(e));
+        }
+        let num_affected = saved_lines.len();
+        let (sql_patt, args) = UpdateOLineDeliverArg(&oid_b, &saved_lines).into();
+        let _rs = run_query_once(&mut tx, sql_patt, args, Some(num_affected)).await?;
+        let (sql_patt, args) = UpdateShipmentReceiptArg(&oid_b, &shipment).into();
+        let _rs = run_query_once(&mut tx, sql_patt, args, Some(1)).await?;
+        tx.commit().await?;
+        Ok(Ok(()))
+    }
 } // end of trait AbsOrderRepo
 
 impl OrderMariaDbRepo {
@@ -854,21 +1280,12 @@ impl OrderMariaDbRepo {
         dbs: Vec<Arc<AppMariaDbStore>>,
         timenow: DateTime<FixedOffset>,
     ) -> DefaultResult<Self, AppError> {
-        if dbs.is_empty() {
-            Err(AppError {
-                code: AppErrorCode::MissingDataStore,
-                detail: Some("mariadb".to_string()),
-            })
-        } else {
-            let _db = dbs.first().unwrap().clone();
-            let stockrepo = StockMariaDbRepo::new(timenow, _db.clone());
-            Ok(Self {
-                _db,
-                _stock: Arc::new(Box::new(stockrepo)),
-            })
-        }
-        // TODO, consider to balance loads of order request to different database servers
-        // , currently this repo selects only the first db pool
+        let _pools = Arc::new(MariaDbPools::try_build(&dbs)?);
+        let stockrepo = StockMariaDbRepo::new(timenow, _pools.clone());
+        Ok(Self {
+            _pools,
+            _stock: Arc::new(Box::new(stockrepo)),
+        })
     }
     pub(super) async fn create_lines(
         tx: &mut Transaction<'_, MySql>,
@@ -921,6 +1338,43 @@ impl OrderMariaDbRepo {
         Ok(())
     } // end of fn create_lines
 
+    async fn _cancel_lines_in_shard(
+        &self,
+        tx: Transaction<'_, MySql>,
+        oid_b: &OidBytes,
+        cancelled_lines: &[OrderLineModel],
+        stk_cb: AppStockRepoReturnUserFunc,
+        data: StockLevelReturnDto,
+    ) -> DefaultResult<Vec<StockReturnErrorDto>, AppError> {
+        // the order is saved in different database server from the stock levels,
+        // which cannot be done in single transaction, the pending return is
+        // committed before the cancellation, then removed along with the stock
+        // return. If this application crashes in between, the pending return is
+        // completed later by `release_pending_rsv()` if the cancellation has been
+        // committed, or discarded otherwise.
+        let ctime = Local::now().naive_utc();
+        let mut conn = self._pools.primary().acquire().await?;
+        let mut stk_tx = conn.begin().await?;
+        StockMariaDbRepo::save_pending_return(&mut stk_tx, oid_b, cancelled_lines, ctime).await?;
+        stk_tx.commit().await?;
+        tx.commit().await?;
+
+        let pids = cancelled_lines
+            .iter()
+            .map(|m| m.id().clone())
+            .collect::<Vec<_>>();
+        let mut stk_tx = conn.begin().await?;
+        let num_discarded =
+            StockMariaDbRepo::discard_pending_return(&mut stk_tx, oid_b, &pids).await?;
+        let errors = if num_discarded == 0 {
+            Vec::new() // already completed by `release_pending_rsv()`
+        } else {
+            StockMariaDbRepo::return_in_tx(&mut stk_tx, stk_cb, data).await?
+        };
+        stk_tx.commit().await?;
+        Ok(errors)
+    } // end of fn _cancel_lines_in_shard
+
     async fn _save_contact(
         tx: &mut Transaction<'_, MySql>,
         oid: &OidBytes,
@@ -979,7 +1433,7 @@ impl OrderMariaDbRepo {
         Ok(())
     }
 
-    async fn _fetch_lines_by_pid(
+    pub(super) async fn _fetch_lines_by_pid(
         tx: &mut Transaction<'_, MySql>,
         oid: &OidBytes,
         pids: Vec<OrderLineIdentity>,
@@ -1101,6 +1555,43 @@ impl OrderMariaDbRepo {
         }
     }
 
+    async fn _fetch_shipments(
+        conn: &mut MySqlConnection,
+        oid_b: &OidBytes,
+    ) -> DefaultResult<Vec<ShipmentModel>, AppError> {
+        let sql_patt = "SELECT `seller_id`,`tracking_num`,`carrier`,`create_time`,\
+                        `received_time` FROM `order_shipment` WHERE `o_id`=?";
+        let stmt = conn.prepare(sql_patt).await?;
+        let query = stmt.query().bind(oid_b.as_column());
+        let rows = conn.fetch_all(query).await?;
+        let mut shipments = rows
+            .into_iter()
+            .map(|row| ShipmentModel::try_from(ShipmentRow(row)))
+            .collect::<DefaultResult<Vec<_>, AppError>>()?;
+
+        let sql_patt = "SELECT `seller_id`,`tracking_num`,`product_id`,`attr_seq`,`qty` \
+                        FROM `order_shipment_line` WHERE `o_id`=?";
+        let stmt = conn.prepare(sql_patt).await?;
+        let query = stmt.query().bind(oid_b.as_column());
+        let rows = conn.fetch_all(query).await?;
+        for row in rows {
+            let (seller_id, tracking_num, line) = ShipmentLineRow(row).try_into()?;
+            let result = shipments
+                .iter_mut()
+                .find(|s| s.seller_id == seller_id && s.tracking_num == tracking_num);
+            if let Some(s) = result {
+                s.lines.push(line);
+            } else {
+                let d = format!("shipment-line-orphan, {seller_id}, {tracking_num}");
+                return Err(AppError {
+                    code: AppErrorCode::DataCorruption,
+                    detail: Some(d),
+                });
+            }
+        }
+        Ok(shipments)
+    }
+
     async fn _fetch_seller_exrates(
         conn: &mut MySqlConnection,
         oid_raw: Vec<u8>,
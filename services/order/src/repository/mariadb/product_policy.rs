@@ -4,6 +4,7 @@ use std::sync::Arc;
 use std::vec::Vec;
 
 use async_trait::async_trait;
+use chrono::NaiveDateTime;
 use sqlx::mysql::{MySqlArguments, MySqlRow};
 use sqlx::{Acquire, Arguments, Executor, MySql, Row, Statement, Transaction};
 
@@ -14,23 +15,16 @@ use crate::error::AppError;
 use crate::model::{ProductPolicyModel, ProductPolicyModelSet};
 use crate::repository::AbstProductPolicyRepo;
 
+use super::MariaDbPools;
+
 pub(crate) struct ProductPolicyMariaDbRepo {
-    db: Arc<AppMariaDbStore>,
+    pools: MariaDbPools,
 }
 
 impl ProductPolicyMariaDbRepo {
     pub async fn new(dbs: &Vec<Arc<AppMariaDbStore>>) -> DefaultResult<Self, AppError> {
-        if dbs.is_empty() {
-            let e = AppError {
-                code: AppErrorCode::MissingDataStore,
-                detail: Some("mariadb".to_string()),
-            };
-            Err(e)
-        } else {
-            let db = dbs.first().unwrap().clone();
-            Ok(Self { db })
-        } // TODO, currently this repo always grabs the first db pool,
-          // will need to figure out how to balance loading when the app data grows
+        let pools = MariaDbPools::try_build(dbs)?;
+        Ok(Self { pools })
     }
 
     fn prep_stmt_patt_read(sql_pattern_blocks: (&str, &str, &str), mut num_items: usize) -> String {
@@ -52,7 +46,6 @@ impl ProductPolicyMariaDbRepo {
         &self,
         tx: &mut Transaction<'_, MySql>,
         mut policies: Vec<ProductPolicyModel>,
-        args_constructor: fn(Vec<ProductPolicyModel>) -> (String, MySqlArguments),
     ) -> DefaultResult<(), AppError> {
         let limit = 14_usize;
         let params = [];
@@ -61,11 +54,16 @@ impl ProductPolicyMariaDbRepo {
             let expect_num_affected = num_batch;
             let policies_processing = policies.split_off(policies.len() - num_batch);
             assert!(!policies_processing.is_empty());
-            let (sql_patt, args) = args_constructor(policies_processing);
+            let (sql_patt, args) = Self::construct_insert_args(policies_processing);
             let stmt = tx.prepare_with(sql_patt.as_str(), &params).await?;
             let exec = &mut **tx;
             let query = stmt.query_with(args);
-            let resultset = query.execute(exec).await?;
+            // the primary key includes version number, the insertion fails if
+            // other client has saved the same version concurrently
+            let resultset = query
+                .execute(exec)
+                .await
+                .map_err(AppError::from_sqlx_unique_conflict)?;
             let num_affected = resultset.rows_affected() as usize;
             if num_affected != expect_num_affected {
                 let detail = format!(
@@ -83,120 +81,59 @@ impl ProductPolicyMariaDbRepo {
 
     fn construct_insert_args(items: Vec<ProductPolicyModel>) -> (String, MySqlArguments) {
         const SQL_PATTERN_BLOCKS: (&str, &str, &str) = (
-            "INSERT INTO `product_policy`(`product_id`,`auto_cancel_secs`,\
-             `warranty_hours`,`max_num_rsv`,`min_num_rsv`) VALUES ",
-            "(?,?,?,?,?)",
+            "INSERT INTO `product_policy`(`store_id`,`product_id`,`version`,`effective_from`,\
+             `auto_cancel_secs`,`warranty_hours`,`max_num_rsv`,`min_num_rsv`) VALUES ",
+            "(?,?,?,?,?,?,?,?)",
             ",",
         );
         let mut args = MySqlArguments::default();
         let num_batch = items
             .into_iter()
             .map(|item| {
-                let (prod_id, auto_cancel, warranty, max_rsv, min_rsv) = (
-                    item.product_id,
-                    item.auto_cancel_secs,
-                    item.warranty_hours,
-                    item.max_num_rsv,
-                    item.min_num_rsv,
-                );
-                args.add(prod_id).unwrap();
-                args.add(auto_cancel).unwrap();
-                args.add(warranty).unwrap();
-                args.add(max_rsv).unwrap();
-                args.add(min_rsv).unwrap();
+                args.add(item.store_id).unwrap();
+                args.add(item.product_id).unwrap();
+                args.add(item.version).unwrap();
+                args.add(item.effective_from.naive_utc()).unwrap();
+                args.add(item.auto_cancel_secs).unwrap();
+                args.add(item.warranty_hours).unwrap();
+                args.add(item.max_num_rsv).unwrap();
+                args.add(item.min_num_rsv).unwrap();
             })
             .count();
         let sql_patt = Self::prep_stmt_patt_read(SQL_PATTERN_BLOCKS, num_batch);
         (sql_patt, args)
     } // end of fn construct_insert_args
-
-    fn construct_update_args(items: Vec<ProductPolicyModel>) -> (String, MySqlArguments) {
-        let mut args = MySqlArguments::default();
-        let mut num_batch = items
-            .iter()
-            .map(|item| {
-                let (prod_id, auto_cancel) = (item.product_id, item.auto_cancel_secs);
-                args.add(prod_id).unwrap();
-                args.add(auto_cancel).unwrap();
-            })
-            .count();
-        items
-            .iter()
-            .map(|item| {
-                let (prod_id, warranty) = (item.product_id, item.warranty_hours);
-                args.add(prod_id).unwrap();
-                args.add(warranty).unwrap();
-            })
-            .count();
-        items
-            .iter()
-            .map(|item| {
-                let (prod_id, max_rsv) = (item.product_id, item.max_num_rsv);
-                args.add(prod_id).unwrap();
-                args.add(max_rsv).unwrap();
-            })
-            .count();
-        items
-            .iter()
-            .map(|item| {
-                let (prod_id, min_rsv) = (item.product_id, item.min_num_rsv);
-                args.add(prod_id).unwrap();
-                args.add(min_rsv).unwrap();
-            })
-            .count();
-        items
-            .iter()
-            .map(|item| {
-                let prod_id = item.product_id;
-                args.add(prod_id).unwrap();
-            })
-            .count();
-        let sql_patt = {
-            let case_ops = (0..num_batch)
-                .map(|_| "WHEN (`product_id`=?) THEN ? ")
-                .collect::<Vec<_>>()
-                .join("");
-            let mut out = format!(
-                "UPDATE `product_policy` SET \
-                `auto_cancel_secs` = CASE {} ELSE `auto_cancel_secs` END,\
-                `warranty_hours` = CASE {} ELSE `warranty_hours` END, \
-                `max_num_rsv` = CASE {} ELSE `max_num_rsv` END, \
-                `min_num_rsv` = CASE {} ELSE `min_num_rsv` END \
-                WHERE ",
-                case_ops, case_ops, case_ops, case_ops
-            );
-            out += "(`product_id`=?)";
-            num_batch -= 1;
-            (0..num_batch)
-                .map(|_| {
-                    out += "OR (`product_id`=?)";
-                })
-                .count();
-            out
-        };
-        (sql_patt, args)
-    } // end of fn construct_update_args
 } // end of impl ProductPolicyMariaDbRepo
 
 #[async_trait]
 impl AbstProductPolicyRepo for ProductPolicyMariaDbRepo {
-    async fn fetch(&self, ids: Vec<u64>) -> DefaultResult<ProductPolicyModelSet, AppError> {
+    async fn fetch(&self, ids: Vec<(u32, u64)>) -> DefaultResult<ProductPolicyModelSet, AppError> {
+        // only the latest version of each product policy is loaded
         const SQL_PATTERN_BLOCKS: (&str, &str, &str) = (
-            "SELECT `product_id`,`auto_cancel_secs`,`warranty_hours`,`max_num_rsv`,`min_num_rsv` FROM `product_policy` WHERE ",
-            "(`product_id`=?)", "OR"
+            "SELECT `a`.`store_id`,`a`.`product_id`,`a`.`version`,`a`.`effective_from`,\
+             `a`.`auto_cancel_secs`,`a`.`warranty_hours`,`a`.`max_num_rsv`,`a`.`min_num_rsv` \
+             FROM `product_policy` AS `a` INNER JOIN (SELECT `store_id`,`product_id`,\
+             MAX(`version`) AS `version` FROM `product_policy` WHERE ",
+            "(`store_id`=? AND `product_id`=?)",
+            "OR",
         );
+        const SQL_SUFFIX: &str = " GROUP BY `store_id`,`product_id`) AS `b` ON \
+             `a`.`store_id`=`b`.`store_id` AND `a`.`product_id`=`b`.`product_id` \
+             AND `a`.`version`=`b`.`version`";
         let (limit, mut num_iter) = (16_usize, 0usize);
         let mut _ids = ids;
         // Mysql/mariadb doesn't need to specify type parameters
         let params = [];
-        let mut conn = self.db.acquire().await?;
+        // stale versions loaded from lagging replica would only cause version
+        // conflict in subsequent save, since the policies are append-only
+        let mut conn = self.pools.acquire_read().await?;
         let num_batch = min(_ids.len(), limit);
-        let mut sql = Self::prep_stmt_patt_read(SQL_PATTERN_BLOCKS, num_batch);
+        let mut sql = Self::prep_stmt_patt_read(SQL_PATTERN_BLOCKS, num_batch) + SQL_SUFFIX;
         let mut policies = vec![];
         while !_ids.is_empty() {
             let num_batch = min(_ids.len(), limit);
             sql = if num_batch == _ids.len() && num_iter > 0 {
-                Self::prep_stmt_patt_read(SQL_PATTERN_BLOCKS, num_batch)
+                Self::prep_stmt_patt_read(SQL_PATTERN_BLOCKS, num_batch) + SQL_SUFFIX
             } else {
                 sql
             };
@@ -207,7 +144,8 @@ impl AbstProductPolicyRepo for ProductPolicyMariaDbRepo {
             let mut args = MySqlArguments::default();
             let _ = (0..num_batch)
                 .map(|_| {
-                    let prod_id = _ids.remove(0);
+                    let (store_id, prod_id) = _ids.remove(0);
+                    args.add(store_id).unwrap();
                     args.add(prod_id).unwrap();
                 })
                 .count();
@@ -240,25 +178,39 @@ impl AbstProductPolicyRepo for ProductPolicyMariaDbRepo {
         Ok(ProductPolicyModelSet { policies })
     } // end of fn fetch
 
+    async fn fetch_history(
+        &self,
+        store_id: u32,
+        product_id: u64,
+    ) -> DefaultResult<ProductPolicyModelSet, AppError> {
+        let sql_patt = "SELECT `store_id`,`product_id`,`version`,`effective_from`,\
+            `auto_cancel_secs`,`warranty_hours`,`max_num_rsv`,`min_num_rsv` FROM \
+            `product_policy` WHERE `store_id`=? AND `product_id`=? ORDER BY `version` ASC";
+        let mut args = MySqlArguments::default();
+        args.add(store_id).unwrap();
+        args.add(product_id).unwrap();
+        let mut conn = self.pools.acquire_read().await?;
+        let stmt = conn.prepare(sql_patt).await?;
+        let query = stmt.query_with(args);
+        let exec = &mut *conn;
+        let rows = exec.fetch_all(query).await?;
+        let policies = rows
+            .into_iter()
+            .map(ProductPolicyModel::try_from)
+            .collect::<DefaultResult<Vec<_>, AppError>>()?;
+        Ok(ProductPolicyModelSet { policies })
+    } // end of fn fetch_history
+
     async fn save(&self, ppset: ProductPolicyModelSet) -> DefaultResult<(), AppError> {
-        let (mut policies_add, mut policies_modify) = (vec![], vec![]);
-        ppset
+        // append-only, existing versions are never modified
+        let policies_add = ppset
             .policies
             .into_iter()
-            .map(|p| {
-                if p.is_create {
-                    policies_add.push(p);
-                } else {
-                    policies_modify.push(p)
-                }
-            })
-            .count(); // TODO, swtich to feature `drain-filter` when it becomes stable
-        let mut conn = self.db.acquire().await?;
+            .filter(|p| p.is_create)
+            .collect::<Vec<_>>();
+        let mut conn = self.pools.primary().acquire().await?;
         let mut tx = conn.begin().await?;
-        self._save(&mut tx, policies_modify, Self::construct_update_args)
-            .await?;
-        self._save(&mut tx, policies_add, Self::construct_insert_args)
-            .await?;
+        self._save(&mut tx, policies_add).await?;
         tx.commit().await?;
         Ok(())
     } // end of fn save
@@ -269,14 +221,20 @@ impl TryFrom<MySqlRow> for ProductPolicyModel {
     fn try_from(value: MySqlRow) -> DefaultResult<Self, Self::Error> {
         // note, the code here implicitly converts the error type received `sqlx::Error`
         // into the error type `AppError`, on immediately returning the error
-        let product_id = value.try_get::<u64, usize>(0)?;
-        let auto_cancel_secs = value.try_get::<u32, usize>(1)?;
-        let warranty_hours = value.try_get::<u32, usize>(2)?;
-        let max_num_rsv = value.try_get::<u16, usize>(3)?;
-        let min_num_rsv = value.try_get::<u16, usize>(4)?;
+        let store_id = value.try_get::<u32, usize>(0)?;
+        let product_id = value.try_get::<u64, usize>(1)?;
+        let version = value.try_get::<u32, usize>(2)?;
+        let effective_from = value.try_get::<NaiveDateTime, usize>(3)?.and_utc().into();
+        let auto_cancel_secs = value.try_get::<u32, usize>(4)?;
+        let warranty_hours = value.try_get::<u32, usize>(5)?;
+        let max_num_rsv = value.try_get::<u16, usize>(6)?;
+        let min_num_rsv = value.try_get::<u16, usize>(7)?;
         Ok(Self {
             is_create: false,
+            store_id,
             product_id,
+            version,
+            effective_from,
             auto_cancel_secs,
             warranty_hours,
             max_num_rsv,
@@ -19,7 +19,7 @@ use crate::error::AppError;
 use crate::model::{ProdAttriPriceModel, ProductPriceModel, ProductPriceModelSet};
 use crate::repository::AbsProductPriceRepo;
 
-use super::{run_query_once, DATETIME_FORMAT};
+use super::{run_query_once, MariaDbPools, DATETIME_FORMAT};
 
 struct InsertProductArg(u32, Vec<ProductPriceModel>);
 struct UpdateProductArg(u32, Vec<ProductPriceModel>);
@@ -457,22 +457,12 @@ impl TryFrom<MySqlRow> for ProductPriceModelSet {
 } // end of impl try-from for ProductPriceModelSet
 
 pub struct ProductPriceMariaDbRepo {
-    db: Arc<AppMariaDbStore>,
+    pools: MariaDbPools,
 }
 impl ProductPriceMariaDbRepo {
     pub fn new(dbs: &Vec<Arc<AppMariaDbStore>>) -> DefaultResult<Self, AppError> {
-        if dbs.is_empty() {
-            let e = AppError {
-                code: AppErrorCode::MissingDataStore,
-                detail: Some("mariadb".to_string()),
-            };
-            Err(e)
-        } else {
-            // TODO, currently this repo always grabs the first db pool,
-            // will need to figure out how to balance loading when the app data grows
-            let db = dbs.first().unwrap().clone();
-            Ok(Self { db })
-        }
+        let pools = MariaDbPools::try_build(dbs)?;
+        Ok(Self { pools })
     }
 
     async fn _save(
@@ -514,8 +504,9 @@ impl ProductPriceMariaDbRepo {
         &self,
         sql_patt: String,
         args: MySqlArguments,
+        from_replica: bool,
     ) -> DefaultResult<Vec<MySqlRow>, AppError> {
-        let mut conn = self.db.acquire().await?;
+        let mut conn = self.pools.acquire_at(0, from_replica).await?;
         let stmt = conn.prepare(sql_patt.as_str()).await?;
         let query = stmt.query_with(args);
         let exec = conn.as_mut();
@@ -527,7 +518,7 @@ impl ProductPriceMariaDbRepo {
         sql_patt: String,
         args: MySqlArguments,
     ) -> DefaultResult<(), AppError> {
-        let mut conn = self.db.acquire().await?;
+        let mut conn = self.pools.primary().acquire().await?;
         let stmt = conn.prepare(sql_patt.as_str()).await?;
         let query = stmt.query_with(args);
         let exec = conn.as_mut();
@@ -578,8 +569,10 @@ impl AbsProductPriceRepo for ProductPriceMariaDbRepo {
                 detail: Some("missing-product-id".to_string()),
             });
         }
+        // always read from primary, the result is usually merged with the
+        // updating prices then saved back, so it cannot be stale
         let (sql_patt, args) = FetchMetaOneSellerArg(store_id).into();
-        let mut rows = self._fetch_common(sql_patt, args).await?;
+        let mut rows = self._fetch_common(sql_patt, args, false).await?;
         if rows.is_empty() {
             return Err(AppError {
                 code: AppErrorCode::ProductNotExist,
@@ -588,7 +581,7 @@ impl AbsProductPriceRepo for ProductPriceMariaDbRepo {
         }
         let mut o = ProductPriceModelSet::try_from(rows.remove(0))?;
         let (sql_patt, args) = FetchProductOneSellerArg(store_id, ids).into();
-        let rows = self._fetch_common(sql_patt, args).await?;
+        let rows = self._fetch_common(sql_patt, args, false).await?;
         let mut errors = vec![];
         o.items = rows
             .into_iter()
@@ -625,7 +618,7 @@ impl AbsProductPriceRepo for ProductPriceMariaDbRepo {
                 .map(|(store_id, _)| *store_id)
                 .collect::<Vec<_>>();
             let (sql_patt, args) = FetchMetaManySellersArg(sids).into();
-            let rows = self._fetch_common(sql_patt, args).await?;
+            let rows = self._fetch_common(sql_patt, args, true).await?;
             let ppset_iter = rows
                 .into_iter()
                 .filter_map(|row| {
@@ -650,7 +643,7 @@ impl AbsProductPriceRepo for ProductPriceMariaDbRepo {
             })
             .collect::<Vec<_>>();
         let (sql_patt, args) = FetchProductManySellersArg(pids).into();
-        let rows = self._fetch_common(sql_patt, args).await?;
+        let rows = self._fetch_common(sql_patt, args, true).await?;
         let num_fetched = rows.len();
         let decoded = rows
             .into_iter()
@@ -699,7 +692,7 @@ impl AbsProductPriceRepo for ProductPriceMariaDbRepo {
             currency,
         } = mset;
         let (ms_add, ms_modify) = ProductPriceModel::split_by_update_state(items);
-        let mut conn = self.db.acquire().await?;
+        let mut conn = self.pools.primary().acquire().await?;
         let mut tx = conn.begin().await?;
         {
             let (sql_patt, args) = InsertUpdateMetaArg(store_id, currency).into();
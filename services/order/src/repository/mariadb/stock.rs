@@ -16,11 +16,11 @@ use ecommerce_common::error::AppErrorCode;
 
 use crate::api::rpc::dto::{InventoryEditStockLevelDto, StockLevelReturnDto, StockReturnErrorDto};
 use crate::api::web::dto::OrderLineCreateErrorDto;
-use crate::datastore::AppMariaDbStore;
 use crate::error::AppError;
 use crate::model::{
-    OrderLineModel, OrderLineModelSet, ProductStockIdentity, ProductStockModel, StockLevelModelSet,
-    StockQtyRsvModel, StockQuantityModel, StoreStockModel,
+    OrderLineIdentity, OrderLineModel, OrderLineModelSet, ProductStockIdentity,
+    ProductStockIdentity2, ProductStockModel, StockLevelModelSet, StockQtyRsvModel,
+    StockQuantityModel, StoreStockModel,
 };
 use crate::repository::{
     AbsOrderStockRepo, AppStockRepoReserveReturn, AppStockRepoReserveUserFunc,
@@ -28,7 +28,7 @@ use crate::repository::{
 };
 
 use super::order::OrderMariaDbRepo;
-use super::{run_query_once, to_app_oid};
+use super::{run_query_once, to_app_oid, MariaDbPools};
 
 struct InsertQtyArg(Vec<(u32, ProductStockModel)>);
 struct UpdateQtyArg(Vec<(u32, ProductStockModel)>);
@@ -36,6 +36,7 @@ struct ReserveArg(Vec<(u32, ProductStockModel)>);
 struct ReturnArg(Vec<(u32, ProductStockModel)>);
 
 struct FetchQtyArg(Vec<ProductStockIdentity>);
+struct FetchAvailQtyArg(Vec<ProductStockIdentity2>, DateTime<FixedOffset>);
 struct FetchQtyForRsvArg<'a>(&'a [OrderLineModel]); // TODO, add current time for expiry filtering
 struct FetchRsvOrderArg<'a>(OidBytes, &'a Vec<InventoryEditStockLevelDto>);
 
@@ -346,6 +347,40 @@ impl From<FetchQtyArg> for (String, MySqlArguments) {
     }
 }
 
+impl FetchAvailQtyArg {
+    fn sql_pattern(num_batch: usize) -> String {
+        let condition = "(`store_id`=? AND `product_id`=?)";
+        let pid_cmps = (0..num_batch).map(|_| condition).collect::<Vec<_>>();
+        let col_seq = "`store_id`,`product_id`,`expiry`,`qty_total`,`qty_cancelled`,`qty_tot_rsv`";
+        format!(
+            "SELECT {col_seq} FROM `stock_level_inventory` WHERE `expiry` > ? AND ({})",
+            pid_cmps.join("OR")
+        )
+    }
+}
+impl<'q> IntoArguments<'q, MySql> for FetchAvailQtyArg {
+    fn into_arguments(self) -> <MySql as AbstractDatabase>::Arguments<'q> {
+        let mut out = MySqlArguments::default();
+        let (pids, time_now) = (self.0, self.1);
+        out.add(time_now.naive_utc()).unwrap();
+        pids.into_iter()
+            .map(|id_| {
+                out.add(id_.store_id).unwrap();
+                out.add(id_.product_id).unwrap();
+            })
+            .count();
+        out
+    }
+}
+impl From<FetchAvailQtyArg> for (String, MySqlArguments) {
+    fn from(value: FetchAvailQtyArg) -> (String, MySqlArguments) {
+        (
+            FetchAvailQtyArg::sql_pattern(value.0.len()),
+            value.into_arguments(),
+        )
+    }
+}
+
 impl<'a> FetchQtyForRsvArg<'a> {
     fn sql_pattern(num_batch: usize) -> String {
         let condition = "(`store_id`=? AND `product_id`=?)";
@@ -532,7 +567,7 @@ impl TryInto<StockLevelModelSet> for StkRsvDetailRows {
 
 pub(super) struct StockMariaDbRepo {
     _time_now: DateTime<FixedOffset>,
-    _db: Arc<AppMariaDbStore>,
+    _pools: Arc<MariaDbPools>,
 }
 
 #[async_trait]
@@ -542,7 +577,23 @@ impl AbsOrderStockRepo for StockMariaDbRepo {
         pids: Vec<ProductStockIdentity>,
     ) -> DefaultResult<StockLevelModelSet, AppError> {
         let (sql_patt, args) = FetchQtyArg(pids).into();
-        let mut conn = self._db.acquire().await?;
+        let mut conn = self._pools.acquire_read().await?;
+        let stmt = conn.prepare(sql_patt.as_str()).await?;
+        let query = stmt.query_with(args);
+        let exec = conn.as_mut();
+        let rows = query.fetch_all(exec).await?;
+        let msets = StkProdRows(rows).try_into()?;
+        Ok(msets)
+    }
+    async fn fetch_avail(
+        &self,
+        pids: Vec<ProductStockIdentity2>,
+    ) -> DefaultResult<StockLevelModelSet, AppError> {
+        if pids.is_empty() {
+            return Ok(StockLevelModelSet { stores: Vec::new() });
+        }
+        let (sql_patt, args) = FetchAvailQtyArg(pids, self._time_now).into();
+        let mut conn = self._pools.acquire_read().await?;
         let stmt = conn.prepare(sql_patt.as_str()).await?;
         let query = stmt.query_with(args);
         let exec = conn.as_mut();
@@ -572,7 +623,7 @@ impl AbsOrderStockRepo for StockMariaDbRepo {
                     .count();
             })
             .count();
-        let mut conn = self._db.acquire().await?;
+        let mut conn = self._pools.primary().acquire().await?;
         let mut tx = conn.begin().await?;
         Self::_save_base_qty("update", 16, &mut tx, stk_modify).await?;
         Self::_save_base_qty("insert", 32, &mut tx, stk_add).await?;
@@ -603,40 +654,55 @@ impl AbsOrderStockRepo for StockMariaDbRepo {
         cb: AppStockRepoReturnUserFunc,
         data: StockLevelReturnDto,
     ) -> DefaultResult<Vec<StockReturnErrorDto>, AppError> {
-        let mut objconn = self._db.acquire().await?;
+        let mut objconn = self._pools.primary().acquire().await?;
         let conn = objconn.as_mut();
         let mut tx = conn.begin().await?;
-        let mut mset = {
-            let oid_b = OidBytes::try_from(data.order_id.as_str())?;
-            let (sql_patt, args) = FetchRsvOrderArg(oid_b, &data.items).into(); // omit expiry check
-            let stmt = tx.prepare(sql_patt.as_str()).await?;
-            let query = stmt.query_with(args);
-            let exec = &mut *tx;
-            let rows = exec.fetch_all(query).await?;
-            StkRsvDetailRows(rows).try_into()?
-        };
-        let errors = cb(&mut mset, data);
+        let errors = Self::return_in_tx(&mut tx, cb, data).await?;
         if errors.is_empty() {
-            let stk = mset
-                .stores
-                .into_iter()
-                .flat_map(|s| {
-                    let store_id = s.store_id;
-                    s.products.into_iter().map(move |p| (store_id, p))
-                })
-                .collect();
-            Self::_save_base_qty("return", 20, &mut tx, stk).await?;
             tx.commit().await?;
         }
         Ok(errors)
     } // end of fn try_return
+
+    async fn release_pending_rsv(
+        &self,
+        time_before: DateTime<FixedOffset>,
+        cb: AppStockRepoReturnUserFunc,
+    ) -> DefaultResult<usize, AppError> {
+        let oids = {
+            let sql_patt = "SELECT `o_id` FROM `stock_rsv_pending` WHERE `create_time` < ?";
+            let mut conn = self._pools.primary().acquire().await?;
+            let stmt = conn.prepare(sql_patt).await?;
+            let query = stmt.query().bind(time_before.naive_utc());
+            let exec = conn.as_mut();
+            let rows = exec.fetch_all(query).await?;
+            rows.iter()
+                .map(|row| to_app_oid(row, 0))
+                .collect::<DefaultResult<Vec<_>, AppError>>()?
+        };
+        let mut num_released = 0;
+        for oid in oids {
+            let oid_b = OidBytes::try_from(oid.as_str())?;
+            let saved = self._order_exists(&oid_b).await?;
+            let mut conn = self._pools.primary().acquire().await?;
+            let mut tx = conn.begin().await?;
+            if !saved {
+                Self::_release_rsv(&mut tx, &oid_b).await?;
+                num_released += 1;
+            }
+            Self::_discard_pending_rsv(&mut tx, &oid_b).await?;
+            tx.commit().await?;
+        }
+        num_released += self._complete_pending_return(time_before, cb).await?;
+        Ok(num_released)
+    } // end of fn release_pending_rsv
 } // end of impl AbsOrderStockRepo for StockMariaDbRepo
 
 impl StockMariaDbRepo {
-    pub(crate) fn new(time_now: DateTime<FixedOffset>, _db: Arc<AppMariaDbStore>) -> Self {
+    pub(super) fn new(time_now: DateTime<FixedOffset>, _pools: Arc<MariaDbPools>) -> Self {
         Self {
             _time_now: time_now,
-            _db,
+            _pools,
         }
     }
 
@@ -671,7 +737,7 @@ impl StockMariaDbRepo {
         usr_cb: AppStockRepoReserveUserFunc,
         order_req: &OrderLineModelSet,
     ) -> DefaultResult<Vec<OrderLineCreateErrorDto>, AppError> {
-        let mut conn = self._db.acquire().await?;
+        let mut conn = self._pools.primary().acquire().await?;
         let mut tx = conn.begin().await?;
         let mut mset = {
             let (sql_patt, args) = FetchQtyForRsvArg(order_req.lines()).into();
@@ -699,9 +765,269 @@ impl StockMariaDbRepo {
                 })
                 .collect();
             Self::_save_base_qty("reserve", 20, &mut tx, stk).await?;
-            OrderMariaDbRepo::create_lines(&mut tx, order_req, 22).await?;
-            tx.commit().await?;
+            let oid_b = OidBytes::try_from(order_req.id().as_str())?;
+            let shard = self._pools.order_shard(&oid_b);
+            if shard == 0 {
+                OrderMariaDbRepo::create_lines(&mut tx, order_req, 22).await?;
+                tx.commit().await?;
+            } else {
+                let ctime = order_req.create_time().naive_utc();
+                Self::_save_pending_rsv(&mut tx, &oid_b, ctime).await?;
+                tx.commit().await?;
+                self._create_lines_in_shard(shard, &oid_b, order_req)
+                    .await?;
+            }
             Ok(vec![])
         }
     } // end of fn _try_reserve
+
+    async fn _create_lines_in_shard(
+        &self,
+        shard: usize,
+        oid_b: &OidBytes,
+        order_req: &OrderLineModelSet,
+    ) -> DefaultResult<(), AppError> {
+        // the order is saved in different database server from the stock levels,
+        // which cannot be done in single transaction, the stock reservation has
+        // been committed with a pending record, which is removed only after the
+        // order lines are committed. If this application crashes in between, the
+        // reservation is released later by `release_pending_rsv()`
+        let result = async {
+            let mut conn = self._pools.primary_at(shard).acquire().await?;
+            let mut tx = conn.begin().await?;
+            OrderMariaDbRepo::create_lines(&mut tx, order_req, 22).await?;
+            tx.commit().await?;
+            Ok::<(), AppError>(())
+        }
+        .await;
+        let mut conn = self._pools.primary().acquire().await?;
+        let mut tx = conn.begin().await?;
+        if result.is_err() {
+            Self::_release_rsv(&mut tx, oid_b).await?;
+        }
+        Self::_discard_pending_rsv(&mut tx, oid_b).await?;
+        tx.commit().await?;
+        result
+    } // end of fn _create_lines_in_shard
+
+    async fn _save_pending_rsv(
+        tx: &mut Transaction<'_, MySql>,
+        oid_b: &OidBytes,
+        ctime: NaiveDateTime,
+    ) -> DefaultResult<(), AppError> {
+        let sql_patt = "INSERT INTO `stock_rsv_pending`(`o_id`,`create_time`) VALUES (?,?)";
+        let mut args = MySqlArguments::default();
+        args.add(oid_b.as_column()).unwrap();
+        args.add(ctime).unwrap();
+        let _rs = run_query_once(tx, sql_patt.to_string(), args, Some(1)).await?;
+        Ok(())
+    }
+    async fn _discard_pending_rsv(
+        tx: &mut Transaction<'_, MySql>,
+        oid_b: &OidBytes,
+    ) -> DefaultResult<(), AppError> {
+        let sql_patt = "DELETE FROM `stock_rsv_pending` WHERE `o_id`=?";
+        let mut args = MySqlArguments::default();
+        args.add(oid_b.as_column()).unwrap();
+        let _rs = run_query_once(tx, sql_patt.to_string(), args, None).await?;
+        Ok(())
+    }
+    async fn _release_rsv(
+        tx: &mut Transaction<'_, MySql>,
+        oid_b: &OidBytes,
+    ) -> DefaultResult<(), AppError> {
+        let sql_patts = [
+            "UPDATE `stock_level_inventory` AS `a` INNER JOIN `stock_rsv_detail` AS `b` \
+             ON `a`.`store_id`=`b`.`store_id` AND `a`.`product_id`=`b`.`product_id` \
+             AND `a`.`expiry`=`b`.`expiry` SET `a`.`qty_tot_rsv` = `a`.`qty_tot_rsv` \
+             - `b`.`qty_reserved` WHERE `b`.`order_id`=?",
+            "DELETE FROM `stock_rsv_detail` WHERE `order_id`=?",
+        ];
+        for sql_patt in sql_patts {
+            let mut args = MySqlArguments::default();
+            args.add(oid_b.as_column()).unwrap();
+            let _rs = run_query_once(tx, sql_patt.to_string(), args, None).await?;
+        }
+        Ok(())
+    }
+    // the returned stock is saved only if the callback reports no error, caller
+    // commits the transaction
+    pub(super) async fn return_in_tx(
+        tx: &mut Transaction<'_, MySql>,
+        cb: AppStockRepoReturnUserFunc,
+        data: StockLevelReturnDto,
+    ) -> DefaultResult<Vec<StockReturnErrorDto>, AppError> {
+        if data.items.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut mset = {
+            let oid_b = OidBytes::try_from(data.order_id.as_str())?;
+            let (sql_patt, args) = FetchRsvOrderArg(oid_b, &data.items).into(); // omit expiry check
+            let stmt = tx.prepare(sql_patt.as_str()).await?;
+            let query = stmt.query_with(args);
+            let exec = &mut **tx;
+            let rows = exec.fetch_all(query).await?;
+            StkRsvDetailRows(rows).try_into()?
+        };
+        let errors = cb(&mut mset, data);
+        if errors.is_empty() {
+            let stk = mset
+                .stores
+                .into_iter()
+                .flat_map(|s| {
+                    let store_id = s.store_id;
+                    s.products.into_iter().map(move |p| (store_id, p))
+                })
+                .collect();
+            Self::_save_base_qty("return", 20, tx, stk).await?;
+        }
+        Ok(errors)
+    }
+
+    // save quantity of the cancelled lines whose stock is going to be returned,
+    // the lines are cancelled in another database server
+    pub(super) async fn save_pending_return(
+        tx: &mut Transaction<'_, MySql>,
+        oid_b: &OidBytes,
+        lines: &[OrderLineModel],
+        ctime: NaiveDateTime,
+    ) -> DefaultResult<(), AppError> {
+        let lines = lines
+            .iter()
+            .filter(|m| m.qty.cancelled > 0)
+            .collect::<Vec<_>>();
+        if lines.is_empty() {
+            return Ok(());
+        }
+        let items = (0..lines.len())
+            .map(|_| "(?,?,?,?,?,?)")
+            .collect::<Vec<_>>()
+            .join(",");
+        let sql_patt = format!(
+            "INSERT INTO `stock_return_pending`(`o_id`,`store_id`,`product_id`,\
+             `attr_seq`,`qty`,`create_time`) VALUES {items}"
+        );
+        let mut args = MySqlArguments::default();
+        lines
+            .iter()
+            .map(|m| {
+                args.add(oid_b.as_column()).unwrap();
+                args.add(m.id().store_id()).unwrap();
+                args.add(m.id().product_id()).unwrap();
+                args.add(m.id().attrs_seq_num()).unwrap();
+                args.add(m.qty.cancelled).unwrap();
+                args.add(ctime).unwrap();
+            })
+            .count();
+        let _rs = run_query_once(tx, sql_patt, args, Some(lines.len())).await?;
+        Ok(())
+    }
+    // return number of the records discarded, zero means the pending return has
+    // been completed by another task
+    pub(super) async fn discard_pending_return(
+        tx: &mut Transaction<'_, MySql>,
+        oid_b: &OidBytes,
+        pids: &[OrderLineIdentity],
+    ) -> DefaultResult<usize, AppError> {
+        if pids.is_empty() {
+            return Ok(0);
+        }
+        let conditions = pids
+            .iter()
+            .map(|_| "(`store_id`=? AND `product_id`=? AND `attr_seq`=?)")
+            .collect::<Vec<_>>()
+            .join("OR");
+        let sql_patt =
+            format!("DELETE FROM `stock_return_pending` WHERE `o_id`=? AND ({conditions})");
+        let mut args = MySqlArguments::default();
+        args.add(oid_b.as_column()).unwrap();
+        pids.iter()
+            .map(|p| {
+                args.add(p.store_id()).unwrap();
+                args.add(p.product_id()).unwrap();
+                args.add(p.attrs_seq_num()).unwrap();
+            })
+            .count();
+        let rs = run_query_once(tx, sql_patt, args, None).await?;
+        Ok(rs.rows_affected() as usize)
+    }
+
+    async fn _complete_pending_return(
+        &self,
+        time_before: DateTime<FixedOffset>,
+        cb: AppStockRepoReturnUserFunc,
+    ) -> DefaultResult<usize, AppError> {
+        let mut pending: HashMap<String, Vec<(OrderLineIdentity, u32)>> = HashMap::new();
+        {
+            let sql_patt = "SELECT `o_id`,`store_id`,`product_id`,`attr_seq`,`qty` FROM \
+                            `stock_return_pending` WHERE `create_time` < ?";
+            let mut conn = self._pools.primary().acquire().await?;
+            let stmt = conn.prepare(sql_patt).await?;
+            let query = stmt.query().bind(time_before.naive_utc());
+            let exec = conn.as_mut();
+            let rows = exec.fetch_all(query).await?;
+            for row in rows.iter() {
+                let oid = to_app_oid(row, 0)?;
+                let store_id = row.try_get::<u32, usize>(1)?;
+                let product_id = row.try_get::<u64, usize>(2)?;
+                let attr_seq = row.try_get::<u16, usize>(3)?;
+                let qty = row.try_get::<u32, usize>(4)?;
+                let pid = OrderLineIdentity::from((store_id, product_id, attr_seq));
+                pending.entry(oid).or_default().push((pid, qty));
+            }
+        }
+        let mut num_returned = 0;
+        for (oid, items) in pending {
+            let oid_b = OidBytes::try_from(oid.as_str())?;
+            let pids = items.iter().map(|(p, _)| p.clone()).collect::<Vec<_>>();
+            let saved_lines = {
+                let mut conn = self._pools.order_primary(&oid_b).acquire().await?;
+                let mut tx = conn.begin().await?;
+                OrderMariaDbRepo::_fetch_lines_by_pid(&mut tx, &oid_b, pids.clone()).await?
+            };
+            // the lines not cancelled indicate the cancellation was never committed,
+            // their pending records are simply discarded
+            let items = items
+                .into_iter()
+                .filter_map(|(pid, qty)| {
+                    saved_lines
+                        .iter()
+                        .find(|m| m.id() == &pid && m.qty.cancelled > 0)
+                        .map(|m| InventoryEditStockLevelDto {
+                            store_id: pid.store_id(),
+                            product_id: pid.product_id(),
+                            qty_add: qty as i32,
+                            expiry: m.policy.reserved_until,
+                        })
+                })
+                .collect::<Vec<_>>();
+            let mut conn = self._pools.primary().acquire().await?;
+            let mut tx = conn.begin().await?;
+            let num_discarded = Self::discard_pending_return(&mut tx, &oid_b, &pids).await?;
+            if num_discarded > 0 && !items.is_empty() {
+                let data = StockLevelReturnDto {
+                    items,
+                    order_id: oid,
+                };
+                let errors = Self::return_in_tx(&mut tx, cb, data).await?;
+                if errors.is_empty() {
+                    num_returned += 1;
+                }
+            }
+            tx.commit().await?;
+        }
+        Ok(num_returned)
+    } // end of fn _complete_pending_return
+
+    async fn _order_exists(&self, oid_b: &OidBytes) -> DefaultResult<bool, AppError> {
+        // read from the primary, the order might have been committed recently
+        let sql_patt = "SELECT COUNT(*) FROM `order_toplvl_meta` WHERE `o_id`=?";
+        let mut conn = self._pools.order_primary(oid_b).acquire().await?;
+        let stmt = conn.prepare(sql_patt).await?;
+        let query = stmt.query().bind(oid_b.as_column());
+        let exec = conn.as_mut();
+        let row = exec.fetch_one(query).await?;
+        let num = row.try_get::<i64, usize>(0)?;
+        Ok(num > 0)
+    }
 } // end of impl StockMariaDbRepo
@@ -15,13 +15,18 @@ use ecommerce_common::error::AppErrorCode;
 use ecommerce_common::model::order::BillingModel;
 use ecommerce_common::model::BaseProductIdentity;
 
-use crate::api::rpc::dto::{ProductPriceDeleteDto, StockLevelReturnDto, StockReturnErrorDto};
-use crate::api::web::dto::OrderLineCreateErrorDto;
+use crate::api::rpc::dto::{
+    ProductPriceDeleteDto, ShipmentErrorDto, StockLevelReturnDto, StockReturnErrorDto,
+};
+use crate::api::web::dto::{
+    OrderLineCancelErrorDto, OrderLineCreateErrorDto, ShipmentReceiptErrorReason,
+};
 use crate::error::AppError;
 use crate::model::{
-    CartModel, CurrencyModelSet, OrderCurrencyModel, OrderLineIdentity, OrderLineModel,
-    OrderLineModelSet, OrderReturnModel, ProductPolicyModelSet, ProductPriceModelSet,
-    ProductStockIdentity, ShippingModel, StockLevelModelSet,
+    CartModel, CartShareModel, CurrencyModelSet, OrderCurrencyModel, OrderLineIdentity,
+    OrderLineModel, OrderLineModelSet, OrderReturnModel, ProductPolicyModelSet,
+    ProductPriceModelSet, ProductStockIdentity, ShipmentModel, ShippingModel, StockLevelModelSet,
+    StoreShippingModel,
 };
 use crate::AppDataStoreContext;
 
@@ -33,6 +38,10 @@ pub use in_mem::oline_return::OrderReturnInMemRepo;
 pub use in_mem::order::OrderInMemRepo;
 pub use in_mem::product_policy::ProductPolicyInMemRepo;
 pub use in_mem::product_price::ProductPriceInMemRepo;
+pub use in_mem::store_shipping::StoreShippingInMemRepo;
+
+mod pool_layout;
+pub use pool_layout::DbPoolLayout;
 
 #[cfg(feature = "mariadb")]
 mod mariadb;
@@ -55,12 +64,50 @@ use mariadb::oline_return::OrderReturnMariaDbRepo;
 #[cfg(feature = "mariadb")]
 use mariadb::cart::CartMariaDbRepo;
 
+#[cfg(feature = "mariadb")]
+use mariadb::store_shipping::StoreShippingMariaDbRepo;
+
+#[cfg(feature = "postgres")]
+mod postgres;
+
+#[cfg(feature = "postgres")]
+use postgres::product_policy::ProductPolicyPostgreSqlRepo;
+
+#[cfg(feature = "postgres")]
+use postgres::product_price::ProductPricePostgreSqlRepo;
+
+#[cfg(feature = "postgres")]
+use postgres::currency::CurrencyPostgreSqlRepo;
+
+#[cfg(feature = "postgres")]
+use postgres::order::OrderPostgreSqlRepo;
+
+#[cfg(feature = "postgres")]
+use postgres::oline_return::OrderReturnPostgreSqlRepo;
+
+#[cfg(feature = "postgres")]
+use postgres::cart::CartPostgreSqlRepo;
+
+#[cfg(feature = "postgres")]
+use postgres::store_shipping::StoreShippingPostgreSqlRepo;
+
 // the repository instance may be used across an await,
 // the future created by app callers has to be able to pass to different threads
 // , it is the reason to add `Send` and `Sync` as super-traits
 #[async_trait]
 pub trait AbstProductPolicyRepo: Sync + Send {
-    async fn fetch(&self, ids: Vec<u64>) -> DefaultResult<ProductPolicyModelSet, AppError>;
+    // fetch latest version of the policies, the first element of the `ids`
+    // tuple should be valid seller ID
+    async fn fetch(&self, ids: Vec<(u32, u64)>) -> DefaultResult<ProductPolicyModelSet, AppError>;
+    // all versions of a product policy, ordered by version number
+    async fn fetch_history(
+        &self,
+        store_id: u32,
+        product_id: u64,
+    ) -> DefaultResult<ProductPolicyModelSet, AppError>;
+    // append-only, only the policies with `is_create` flag are saved, each of
+    // them has to be exactly next version of the latest one saved, otherwise
+    // nothing is saved and error code `DataConflict` is returned.
     async fn save(&self, ppset: ProductPolicyModelSet) -> DefaultResult<(), AppError>;
     // TODO, delete operation
 }
@@ -100,6 +147,16 @@ pub trait AbsCurrencyRepo: Sync + Send {
     async fn save(&self, ms: CurrencyModelSet) -> DefaultResult<(), AppError>;
 }
 
+#[async_trait]
+pub trait AbsStoreShippingRepo: Sync + Send {
+    // stores which have never synchronized shipping methods are omitted
+    // from the result
+    async fn fetch(&self, store_ids: Vec<u32>) -> DefaultResult<Vec<StoreShippingModel>, AppError>;
+
+    // replace all the shipping methods previously saved for the store
+    async fn save(&self, obj: StoreShippingModel) -> DefaultResult<(), AppError>;
+}
+
 #[async_trait]
 pub trait AbsOrderRepo: Sync + Send {
     fn stock(&self) -> Arc<Box<dyn AbsOrderStockRepo>>;
@@ -123,6 +180,20 @@ pub trait AbsOrderRepo: Sync + Send {
         cb: AppOrderRepoUpdateLinesUserFunc,
     ) -> DefaultResult<OrderPaymentUpdateErrorDto, AppError>;
 
+    /// mark unpaid quantity of the specified lines as cancelled, the user callback
+    /// examines the fetched lines, nothing is saved if the callback reports any error.
+    /// Stock reserved for the cancelled quantity is returned through `stk_cb` along
+    /// with the cancellation, so the stock is never lost once the cancellation is
+    /// committed. Errors reported by `stk_cb` do not revert the cancellation, they
+    /// are returned on success.
+    async fn cancel_unpaid_lines(
+        &self,
+        oid: &str,
+        pids: Vec<OrderLineIdentity>,
+        cb: AppOrderRepoCancelLinesUserFunc,
+        stk_cb: AppStockRepoReturnUserFunc,
+    ) -> DefaultResult<AppOrderRepoCancelLinesReturn, AppError>;
+
     async fn fetch_lines_by_rsvtime(
         &self,
         time_start: DateTime<FixedOffset>,
@@ -142,6 +213,19 @@ pub trait AbsOrderRepo: Sync + Send {
         end: DateTime<FixedOffset>,
     ) -> DefaultResult<Vec<String>, AppError>;
 
+    /// IDs and creation time of the orders placed by the given user within
+    /// the time range, sorted by creation time, at most `limit` orders are
+    /// returned after skipping the first `num_skip` orders, along with total
+    /// number of the orders in the time range
+    async fn fetch_ids_by_owner_created_time(
+        &self,
+        usr_id: u32,
+        start: DateTime<FixedOffset>,
+        end: DateTime<FixedOffset>,
+        num_skip: usize,
+        limit: usize,
+    ) -> DefaultResult<AppOrderRepoOwnedIdsReturn, AppError>;
+
     async fn owner_id(&self, order_id: &str) -> DefaultResult<u32, AppError>;
     async fn created_time(&self, order_id: &str) -> DefaultResult<DateTime<FixedOffset>, AppError>;
 
@@ -149,11 +233,51 @@ pub trait AbsOrderRepo: Sync + Send {
 
     async fn cancel_unpaid_last_time(&self) -> DefaultResult<DateTime<FixedOffset>, AppError>;
     async fn cancel_unpaid_time_update(&self) -> DefaultResult<(), AppError>;
+
+    async fn fetch_shipments(&self, oid: &str) -> DefaultResult<Vec<ShipmentModel>, AppError>;
+
+    /// save new shipment of an order, the user callback examines the new shipment
+    /// against the order lines and other shipments of the same seller,
+    /// nothing is saved if the callback reports any error.
+    async fn create_shipment(
+        &self,
+        oid: &str,
+        shipment: ShipmentModel,
+        cb: AppOrderRepoShipmentCreateUserFunc,
+    ) -> DefaultResult<DefaultResult<(), ShipmentErrorDto>, AppError>;
+
+    async fn confirm_shipment_receipt(
+        &self,
+        oid: &str,
+        seller_id: u32,
+        tracking_num: &str,
+        cb: AppOrderRepoShipmentReceiptUserFunc,
+    ) -> DefaultResult<DefaultResult<(), ShipmentReceiptErrorReason>, AppError>;
 } // end of trait AbsOrderRepo
 
 pub type AppOrderRepoUpdateLinesUserFunc =
     fn(&mut Vec<OrderLineModel>, OrderPaymentUpdateDto) -> Vec<OrderLinePayUpdateErrorDto>;
 
+pub type AppOrderRepoCancelLinesUserFunc = fn(
+    &mut [OrderLineModel],
+    Vec<OrderLineIdentity>,
+    DateTime<FixedOffset>,
+) -> Vec<OrderLineCancelErrorDto>;
+
+pub type AppOrderRepoOwnedIdsReturn = (usize, Vec<(String, DateTime<FixedOffset>)>);
+
+pub type AppOrderRepoCancelLinesReturn =
+    DefaultResult<Vec<StockReturnErrorDto>, Vec<OrderLineCancelErrorDto>>;
+
+pub type AppOrderRepoShipmentCreateUserFunc =
+    fn(&ShipmentModel, &[OrderLineModel], &[ShipmentModel]) -> DefaultResult<(), ShipmentErrorDto>;
+
+pub type AppOrderRepoShipmentReceiptUserFunc = fn(
+    &mut ShipmentModel,
+    &mut [OrderLineModel],
+    DateTime<FixedOffset>,
+) -> DefaultResult<(), ShipmentReceiptErrorReason>;
+
 // declare a callback function type which can easily be passed,
 // - I made the return type to be `Future` trait object wrapped in `Pin` type
 //   because `Future` (generated by async block expression) does not implement `Unpin` trait,
@@ -186,6 +310,12 @@ pub trait AbsOrderStockRepo: Sync + Send {
         &self,
         pids: Vec<ProductStockIdentity>,
     ) -> DefaultResult<StockLevelModelSet, AppError>;
+    // load all stock items of the given products which haven't expired yet,
+    // without locking them, the result is for read-only purpose
+    async fn fetch_avail(
+        &self,
+        pids: Vec<BaseProductIdentity>,
+    ) -> DefaultResult<StockLevelModelSet, AppError>;
     async fn save(&self, slset: StockLevelModelSet) -> DefaultResult<(), AppError>;
     async fn try_reserve(
         &self,
@@ -197,6 +327,18 @@ pub trait AbsOrderStockRepo: Sync + Send {
         cb: AppStockRepoReturnUserFunc,
         data: StockLevelReturnDto,
     ) -> DefaultResult<Vec<StockReturnErrorDto>, AppError>;
+    // release stock reserved for orders which were never saved, the reservation
+    // and the order lines may be committed separately in some implementations,
+    // only reservations pending before `time_before` are examined, the function
+    // returns number of the orders whose reservations are released.
+    // The same applies to cancellation of order lines and return of their stock,
+    // the pending return is completed through `cb` if the cancellation has been
+    // committed.
+    async fn release_pending_rsv(
+        &self,
+        time_before: DateTime<FixedOffset>,
+        cb: AppStockRepoReturnUserFunc,
+    ) -> DefaultResult<usize, AppError>;
 }
 
 #[async_trait]
@@ -235,6 +377,9 @@ pub trait AbsCartRepo: Sync + Send {
 
     async fn discard(&self, owner: u32, seq: u8) -> DefaultResult<(), AppError>;
 
+    // remove saved lines of the given cart, the top-level metadata is kept
+    async fn remove_lines(&self, obj: CartModel) -> DefaultResult<usize, AppError>;
+
     async fn num_lines_saved(&self, owner: u32, seq: u8) -> DefaultResult<usize, AppError>;
 
     async fn fetch_cart(&self, owner: u32, seq: u8) -> DefaultResult<CartModel, AppError>;
@@ -245,6 +390,29 @@ pub trait AbsCartRepo: Sync + Send {
         seq: u8,
         pids: Vec<BaseProductIdentity>,
     ) -> DefaultResult<CartModel, AppError>;
+
+    // replace the entire list of users who can access the given cart, the
+    // list is kept when cart lines are updated, discarding the cart deletes it
+    async fn update_share(&self, obj: CartShareModel) -> DefaultResult<usize, AppError>;
+
+    async fn fetch_share(&self, owner: u32, seq: u8) -> DefaultResult<CartShareModel, AppError>;
+}
+
+fn unavailable_repo_error() -> AppError {
+    // SQL database features take precedence over in-memory store once
+    // enabled, the application has to provide at least one database
+    // server in its configuration.
+    if cfg!(any(feature = "mariadb", feature = "postgres")) {
+        AppError {
+            code: AppErrorCode::FeatureDisabled,
+            detail: Some("sql-db".to_string()),
+        }
+    } else {
+        AppError {
+            code: AppErrorCode::MissingDataStore,
+            detail: Some("unknown-type".to_string()),
+        }
+    }
 }
 
 pub async fn app_repo_product_policy(
@@ -253,23 +421,19 @@ pub async fn app_repo_product_policy(
     #[cfg(feature = "mariadb")]
     if let Some(dbs) = ds.sql_dbs.as_ref() {
         let obj = ProductPolicyMariaDbRepo::new(dbs).await?;
-        Ok(Box::new(obj))
-    } else {
-        Err(AppError {
-            code: AppErrorCode::FeatureDisabled,
-            detail: Some("mariadb".to_string()),
-        })
+        return Ok(Box::new(obj));
+    }
+    #[cfg(feature = "postgres")]
+    if let Some(dbs) = ds.pg_dbs.as_ref() {
+        let obj = ProductPolicyPostgreSqlRepo::new(dbs).await?;
+        return Ok(Box::new(obj));
     }
-    #[cfg(not(feature = "mariadb"))]
+    #[cfg(not(any(feature = "mariadb", feature = "postgres")))]
     if let Some(m) = ds.in_mem.as_ref() {
         let obj = ProductPolicyInMemRepo::new(m.clone()).await?;
-        Ok(Box::new(obj))
-    } else {
-        Err(AppError {
-            code: AppErrorCode::MissingDataStore,
-            detail: Some("unknwon-type".to_string()),
-        })
+        return Ok(Box::new(obj));
     }
+    Err(unavailable_repo_error())
 }
 
 pub async fn app_repo_product_price(
@@ -278,23 +442,19 @@ pub async fn app_repo_product_price(
     #[cfg(feature = "mariadb")]
     if let Some(dbs) = ds.sql_dbs.as_ref() {
         let obj = ProductPriceMariaDbRepo::new(dbs)?;
-        Ok(Box::new(obj))
-    } else {
-        Err(AppError {
-            code: AppErrorCode::FeatureDisabled,
-            detail: Some("mariadb".to_string()),
-        })
+        return Ok(Box::new(obj));
+    }
+    #[cfg(feature = "postgres")]
+    if let Some(dbs) = ds.pg_dbs.as_ref() {
+        let obj = ProductPricePostgreSqlRepo::new(dbs)?;
+        return Ok(Box::new(obj));
     }
-    #[cfg(not(feature = "mariadb"))]
+    #[cfg(not(any(feature = "mariadb", feature = "postgres")))]
     if let Some(m) = ds.in_mem.as_ref() {
         let obj = ProductPriceInMemRepo::new(m.clone()).await?;
-        Ok(Box::new(obj))
-    } else {
-        Err(AppError {
-            code: AppErrorCode::MissingDataStore,
-            detail: Some("unknwon-type".to_string()),
-        })
+        return Ok(Box::new(obj));
     }
+    Err(unavailable_repo_error())
 }
 
 pub async fn app_repo_currency(
@@ -303,23 +463,19 @@ pub async fn app_repo_currency(
     #[cfg(feature = "mariadb")]
     if let Some(dbs) = ds.sql_dbs.as_ref() {
         let obj = CurrencyMariaDbRepo::try_build(dbs)?;
-        Ok(Box::new(obj))
-    } else {
-        Err(AppError {
-            code: AppErrorCode::FeatureDisabled,
-            detail: Some("mariadb".to_string()),
-        })
+        return Ok(Box::new(obj));
+    }
+    #[cfg(feature = "postgres")]
+    if let Some(dbs) = ds.pg_dbs.as_ref() {
+        let obj = CurrencyPostgreSqlRepo::try_build(dbs)?;
+        return Ok(Box::new(obj));
     }
-    #[cfg(not(feature = "mariadb"))]
+    #[cfg(not(any(feature = "mariadb", feature = "postgres")))]
     if let Some(m) = ds.in_mem.as_ref() {
         let obj = CurrencyInMemRepo::new(m.clone()).await?;
-        Ok(Box::new(obj))
-    } else {
-        Err(AppError {
-            code: AppErrorCode::MissingDataStore,
-            detail: Some("unknwon-type".to_string()),
-        })
+        return Ok(Box::new(obj));
     }
+    Err(unavailable_repo_error())
 } // end of fn app_repo_currency
 
 pub async fn app_repo_order(
@@ -329,69 +485,80 @@ pub async fn app_repo_order(
     #[cfg(feature = "mariadb")]
     if let Some(dbs) = ds.sql_dbs.as_ref() {
         let obj = OrderMariaDbRepo::new(dbs.clone(), timenow).await?;
-        Ok(Box::new(obj))
-    } else {
-        Err(AppError {
-            code: AppErrorCode::FeatureDisabled,
-            detail: Some("mariadb".to_string()),
-        })
+        return Ok(Box::new(obj));
+    }
+    #[cfg(feature = "postgres")]
+    if let Some(dbs) = ds.pg_dbs.as_ref() {
+        let obj = OrderPostgreSqlRepo::new(dbs.clone(), timenow).await?;
+        return Ok(Box::new(obj));
     }
-    #[cfg(not(feature = "mariadb"))]
-    if let Some(m) = &ds.in_mem {
+    #[cfg(not(any(feature = "mariadb", feature = "postgres")))]
+    if let Some(m) = ds.in_mem.as_ref() {
         let obj = OrderInMemRepo::new(m.clone(), timenow).await?;
-        Ok(Box::new(obj))
-    } else {
-        Err(AppError {
-            code: AppErrorCode::MissingDataStore,
-            detail: Some("unknown-type".to_string()),
-        })
+        return Ok(Box::new(obj));
     }
+    Err(unavailable_repo_error())
 }
+
 pub async fn app_repo_order_return(
     ds: Arc<AppDataStoreContext>,
 ) -> DefaultResult<Box<dyn AbsOrderReturnRepo>, AppError> {
     #[cfg(feature = "mariadb")]
     if let Some(dbs) = ds.sql_dbs.as_ref() {
         let obj = OrderReturnMariaDbRepo::new(dbs.clone()).await?;
-        Ok(Box::new(obj))
-    } else {
-        Err(AppError {
-            code: AppErrorCode::FeatureDisabled,
-            detail: Some("mariadb".to_string()),
-        })
+        return Ok(Box::new(obj));
+    }
+    #[cfg(feature = "postgres")]
+    if let Some(dbs) = ds.pg_dbs.as_ref() {
+        let obj = OrderReturnPostgreSqlRepo::new(dbs.clone()).await?;
+        return Ok(Box::new(obj));
     }
-    #[cfg(not(feature = "mariadb"))]
-    if let Some(m) = &ds.in_mem {
+    #[cfg(not(any(feature = "mariadb", feature = "postgres")))]
+    if let Some(m) = ds.in_mem.as_ref() {
         let obj = OrderReturnInMemRepo::new(m.clone()).await?;
-        Ok(Box::new(obj))
-    } else {
-        Err(AppError {
-            code: AppErrorCode::MissingDataStore,
-            detail: Some("unknown-type".to_string()),
-        })
+        return Ok(Box::new(obj));
     }
+    Err(unavailable_repo_error())
 }
+
 pub async fn app_repo_cart(
     ds: Arc<AppDataStoreContext>,
 ) -> DefaultResult<Box<dyn AbsCartRepo>, AppError> {
     #[cfg(feature = "mariadb")]
     if let Some(dbs) = ds.sql_dbs.as_ref() {
         let obj = CartMariaDbRepo::new(dbs.clone()).await?;
-        Ok(Box::new(obj))
-    } else {
-        Err(AppError {
-            code: AppErrorCode::FeatureDisabled,
-            detail: Some("mariadb".to_string()),
-        })
+        return Ok(Box::new(obj));
+    }
+    #[cfg(feature = "postgres")]
+    if let Some(dbs) = ds.pg_dbs.as_ref() {
+        let obj = CartPostgreSqlRepo::new(dbs.clone()).await?;
+        return Ok(Box::new(obj));
     }
-    #[cfg(not(feature = "mariadb"))]
-    if let Some(m) = &ds.in_mem {
+    #[cfg(not(any(feature = "mariadb", feature = "postgres")))]
+    if let Some(m) = ds.in_mem.as_ref() {
         let obj = CartInMemRepo::new(m.clone()).await?;
-        Ok(Box::new(obj))
-    } else {
-        Err(AppError {
-            code: AppErrorCode::MissingDataStore,
-            detail: Some("unknown-type".to_string()),
-        })
+        return Ok(Box::new(obj));
+    }
+    Err(unavailable_repo_error())
+}
+
+pub async fn app_repo_store_shipping(
+    ds: Arc<AppDataStoreContext>,
+) -> DefaultResult<Box<dyn AbsStoreShippingRepo>, AppError> {
+    #[cfg(feature = "mariadb")]
+    if let Some(dbs) = ds.sql_dbs.as_ref() {
+        let obj = StoreShippingMariaDbRepo::try_build(dbs)?;
+        return Ok(Box::new(obj));
+    }
+    #[cfg(feature = "postgres")]
+    if let Some(dbs) = ds.pg_dbs.as_ref() {
+        let obj = StoreShippingPostgreSqlRepo::try_build(dbs)?;
+        return Ok(Box::new(obj));
+    }
+    #[cfg(not(any(feature = "mariadb", feature = "postgres")))]
+    if let Some(m) = ds.in_mem.as_ref() {
+        let obj = StoreShippingInMemRepo::new(m.clone()).await?;
+        return Ok(Box::new(obj));
     }
+    Err(unavailable_repo_error())
 }
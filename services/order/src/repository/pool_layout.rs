@@ -0,0 +1,78 @@
+use std::result::Result as DefaultResult;
+
+use ecommerce_common::adapter::repository::OidBytes;
+use ecommerce_common::config::AppDbServerRole;
+use ecommerce_common::error::AppErrorCode;
+
+use crate::error::AppError;
+
+/// Database servers grouped by their roles, all the indexes refer to position
+/// of the server in the list given to `try_build()`. The first primary keeps
+/// all the data except orders, which are distributed by hash of order ID among
+/// the primaries with order shard enabled, or kept in the first primary if none
+/// of them enables order shard.
+pub struct DbPoolLayout {
+    pub primaries: Vec<usize>,
+    // replicas following each primary, in the same order as `primaries`
+    pub replicas: Vec<Vec<usize>>,
+    // indexes to `primaries` which hold orders
+    pub order_shards: Vec<usize>,
+}
+
+impl DbPoolLayout {
+    /// each server is described by its alias and role
+    pub fn try_build(servers: &[(&str, &AppDbServerRole)]) -> DefaultResult<Self, AppError> {
+        let primaries = servers
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, role))| matches!(role, AppDbServerRole::Primary { .. }))
+            .map(|(idx, _)| idx)
+            .collect::<Vec<_>>();
+        if primaries.is_empty() {
+            return Err(AppError {
+                code: AppErrorCode::MissingDataStore,
+                detail: Some("primary".to_string()),
+            });
+        }
+        let mut replicas = primaries.iter().map(|_| Vec::new()).collect::<Vec<_>>();
+        for (idx, (alias, role)) in servers.iter().enumerate() {
+            if let AppDbServerRole::Replica { primary } = role {
+                let pos = primaries
+                    .iter()
+                    .position(|p| servers[*p].0 == primary.as_str())
+                    .ok_or_else(|| AppError {
+                        code: AppErrorCode::MissingDataStore,
+                        detail: Some(format!("replica:{alias}, primary:{primary}")),
+                    })?;
+                replicas[pos].push(idx);
+            }
+        }
+        let mut order_shards = primaries
+            .iter()
+            .enumerate()
+            .filter_map(|(pos, idx)| match servers[*idx].1 {
+                AppDbServerRole::Primary { order_shard: true } => Some(pos),
+                _others => None,
+            })
+            .collect::<Vec<_>>();
+        if order_shards.is_empty() {
+            order_shards.push(0);
+        }
+        Ok(Self {
+            primaries,
+            replicas,
+            order_shards,
+        })
+    } // end of fn try_build
+
+    /// index to `primaries` which holds the given order
+    pub fn order_shard(&self, oid: &OidBytes) -> usize {
+        // FNV-1a hash, which is stable across builds, note that existing orders
+        // have to be migrated whenever the number of order shards changes.
+        let hash = oid.0.iter().fold(0xcbf2_9ce4_8422_2325u64, |h, b| {
+            (h ^ (*b as u64)).wrapping_mul(0x0100_0000_01b3)
+        });
+        let chosen = hash % (self.order_shards.len() as u64);
+        self.order_shards[chosen as usize]
+    }
+}
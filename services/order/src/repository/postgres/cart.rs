@@ -0,0 +1,547 @@
+use std::result::Result as DefaultResult;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use sqlx::database::Database as AbstractDatabase;
+use sqlx::postgres::{PgArguments, PgRow};
+use sqlx::{Acquire, Arguments, IntoArguments, Postgres, Row};
+
+use ecommerce_common::error::AppErrorCode;
+use ecommerce_common::model::BaseProductIdentity;
+
+use crate::datastore::AppPostgreSqlStore;
+use crate::error::AppError;
+use crate::model::{CartCollaboratorModel, CartLineModel, CartModel, CartShareModel};
+use crate::repository::AbsCartRepo;
+
+use super::{positional_param, run_query_once, try_get_u32, try_get_u64, try_get_u8};
+
+struct InsertUpdateTopLvlArg<'a>(&'a CartModel);
+struct InsertLineArg(u32, u8, Vec<CartLineModel>);
+struct UpdateLineArg(u32, u8, Vec<CartLineModel>);
+struct DiscardLineArg(u32, u8);
+struct RemoveLineArg(u32, u8, Vec<CartLineModel>);
+struct DiscardTopLvlArg(u32, u8);
+struct DiscardShareArg(u32, u8);
+struct InsertShareArg(CartShareModel);
+
+struct FetchTotNumLinesArg(u32, u8);
+struct FetchTopLvlArg(u32, u8);
+struct FetchLinesArg(u32, u8, Option<Vec<BaseProductIdentity>>);
+struct FetchShareArg(u32, u8);
+
+impl<'a> From<InsertUpdateTopLvlArg<'a>> for (String, PgArguments) {
+    fn from(value: InsertUpdateTopLvlArg<'a>) -> (String, PgArguments) {
+        let sql_patt = "INSERT INTO cart_toplvl_meta(usr_id,seq,title) VALUES ($1,$2,$3) \
+                        ON CONFLICT (usr_id,seq) DO UPDATE SET title=EXCLUDED.title";
+        let mut args = PgArguments::default();
+        args.add(value.0.owner as i64).unwrap();
+        args.add(value.0.seq_num as i16).unwrap();
+        args.add(value.0.title.clone()).unwrap();
+        (sql_patt.to_string(), args)
+    }
+}
+
+impl InsertLineArg {
+    fn sql_pattern(num_batch: usize) -> String {
+        let col_seq = (0..num_batch)
+            .map(|_| "(?,?,?,?,?,?)")
+            .collect::<Vec<_>>()
+            .join(",");
+        let patt = format!(
+            "INSERT INTO cart_line_detail(usr_id,seq,store_id,\
+            product_id,attr_set,quantity) VALUES {col_seq}"
+        );
+        positional_param(patt.as_str())
+    }
+}
+impl<'q> IntoArguments<'q, Postgres> for InsertLineArg {
+    fn into_arguments(self) -> <Postgres as AbstractDatabase>::Arguments<'q> {
+        let mut args = PgArguments::default();
+        let (usr_id, seq_num, lines) = (self.0, self.1, self.2);
+        lines
+            .into_iter()
+            .map(|line| {
+                let attr_set = line.serialize_attrs().unwrap();
+                let (id_, quantity) = (line.id_, line.qty_req);
+                let BaseProductIdentity {
+                    store_id,
+                    product_id,
+                } = id_;
+                args.add(usr_id as i64).unwrap();
+                args.add(seq_num as i16).unwrap();
+                args.add(store_id as i64).unwrap();
+                args.add(product_id as i64).unwrap();
+                args.add(attr_set).unwrap();
+                args.add(quantity as i64).unwrap();
+            })
+            .count();
+        args
+    }
+}
+impl From<InsertLineArg> for (String, PgArguments) {
+    fn from(value: InsertLineArg) -> (String, PgArguments) {
+        (
+            InsertLineArg::sql_pattern(value.2.len()),
+            value.into_arguments(),
+        )
+    }
+}
+
+impl UpdateLineArg {
+    fn sql_pattern(num_batch: usize) -> String {
+        let case_op = (0..num_batch)
+            .map(|_| "WHEN (store_id=? AND product_id=? AND attr_set=?) THEN ? ")
+            .collect::<Vec<_>>()
+            .join("");
+        let where_op = (0..num_batch)
+            .map(|_| "(store_id=? AND product_id=? AND attr_set=?)")
+            .collect::<Vec<_>>()
+            .join(" OR ");
+        // usr_id,seq,store_id,product_id
+        let patt = format!(
+            "UPDATE cart_line_detail SET quantity = CASE {case_op} ELSE quantity END \
+                WHERE usr_id=? AND seq=?  AND ({where_op})"
+        );
+        positional_param(patt.as_str())
+    }
+}
+impl<'a> IntoArguments<'a, Postgres> for UpdateLineArg {
+    fn into_arguments(self) -> <Postgres as AbstractDatabase>::Arguments<'a> {
+        let mut args = PgArguments::default();
+        let (usr_id, seq, lines) = (self.0, self.1, self.2);
+        lines
+            .iter()
+            .map(|line| {
+                let (seller, p_id, qty) = (line.id_.store_id, line.id_.product_id, line.qty_req);
+                args.add(seller as i64).unwrap();
+                args.add(p_id as i64).unwrap();
+                args.add(line.serialize_attrs().unwrap()).unwrap();
+                args.add(qty as i64).unwrap();
+            })
+            .count();
+        args.add(usr_id as i64).unwrap();
+        args.add(seq as i16).unwrap();
+        lines
+            .into_iter()
+            .map(|line| {
+                let (seller, p_id) = (line.id_.store_id, line.id_.product_id);
+                args.add(seller as i64).unwrap();
+                args.add(p_id as i64).unwrap();
+                args.add(line.serialize_attrs().unwrap()).unwrap();
+            })
+            .count();
+        args
+    }
+}
+impl From<UpdateLineArg> for (String, PgArguments) {
+    fn from(value: UpdateLineArg) -> (String, PgArguments) {
+        (
+            UpdateLineArg::sql_pattern(value.2.len()),
+            value.into_arguments(),
+        )
+    }
+}
+
+impl From<DiscardTopLvlArg> for (String, PgArguments) {
+    fn from(value: DiscardTopLvlArg) -> (String, PgArguments) {
+        let (usr_id, seq_num) = (value.0, value.1);
+        let sql_patt = "DELETE FROM cart_toplvl_meta WHERE usr_id=$1 AND seq=$2";
+        let mut args = PgArguments::default();
+        args.add(usr_id as i64).unwrap();
+        args.add(seq_num as i16).unwrap();
+        (sql_patt.to_string(), args)
+    }
+}
+
+impl From<DiscardLineArg> for (String, PgArguments) {
+    fn from(value: DiscardLineArg) -> (String, PgArguments) {
+        let (usr_id, seq_num) = (value.0, value.1);
+        let sql_patt = "DELETE FROM cart_line_detail WHERE usr_id=$1 AND seq=$2";
+        let mut args = PgArguments::default();
+        args.add(usr_id as i64).unwrap();
+        args.add(seq_num as i16).unwrap();
+        (sql_patt.to_string(), args)
+    }
+}
+
+impl RemoveLineArg {
+    fn sql_pattern(num_batch: usize) -> String {
+        let where_op = (0..num_batch)
+            .map(|_| "(store_id=? AND product_id=? AND attr_set=?)")
+            .collect::<Vec<_>>()
+            .join(" OR ");
+        let patt =
+            format!("DELETE FROM cart_line_detail WHERE usr_id=? AND seq=? AND ({where_op})");
+        positional_param(patt.as_str())
+    }
+}
+impl<'a> IntoArguments<'a, Postgres> for RemoveLineArg {
+    fn into_arguments(self) -> <Postgres as AbstractDatabase>::Arguments<'a> {
+        let mut args = PgArguments::default();
+        let (usr_id, seq, lines) = (self.0, self.1, self.2);
+        args.add(usr_id as i64).unwrap();
+        args.add(seq as i16).unwrap();
+        lines
+            .into_iter()
+            .map(|line| {
+                let (seller, p_id) = (line.id_.store_id, line.id_.product_id);
+                args.add(seller as i64).unwrap();
+                args.add(p_id as i64).unwrap();
+                args.add(line.serialize_attrs().unwrap()).unwrap();
+            })
+            .count();
+        args
+    }
+}
+impl From<RemoveLineArg> for (String, PgArguments) {
+    fn from(value: RemoveLineArg) -> (String, PgArguments) {
+        (
+            RemoveLineArg::sql_pattern(value.2.len()),
+            value.into_arguments(),
+        )
+    }
+}
+
+impl From<DiscardShareArg> for (String, PgArguments) {
+    fn from(value: DiscardShareArg) -> (String, PgArguments) {
+        let (usr_id, seq_num) = (value.0, value.1);
+        let sql_patt = "DELETE FROM cart_share WHERE usr_id=$1 AND seq=$2";
+        let mut args = PgArguments::default();
+        args.add(usr_id as i64).unwrap();
+        args.add(seq_num as i16).unwrap();
+        (sql_patt.to_string(), args)
+    }
+}
+
+impl InsertShareArg {
+    fn sql_pattern(num_batch: usize) -> String {
+        let col_seq = (0..num_batch)
+            .map(|_| "(?,?,?,?)")
+            .collect::<Vec<_>>()
+            .join(",");
+        let patt = format!("INSERT INTO cart_share(usr_id,seq,collab_id,access) VALUES {col_seq}");
+        positional_param(patt.as_str())
+    }
+}
+impl<'q> IntoArguments<'q, Postgres> for InsertShareArg {
+    fn into_arguments(self) -> <Postgres as AbstractDatabase>::Arguments<'q> {
+        let mut args = PgArguments::default();
+        let (usr_id, seq_num, collaborators) = (self.0.owner, self.0.seq_num, self.0.collaborators);
+        collaborators
+            .into_iter()
+            .map(|c| {
+                args.add(usr_id as i64).unwrap();
+                args.add(seq_num as i16).unwrap();
+                args.add(c.usr_id as i64).unwrap();
+                args.add(c.access_label()).unwrap();
+            })
+            .count();
+        args
+    }
+}
+impl From<InsertShareArg> for (String, PgArguments) {
+    fn from(value: InsertShareArg) -> (String, PgArguments) {
+        (
+            InsertShareArg::sql_pattern(value.0.collaborators.len()),
+            value.into_arguments(),
+        )
+    }
+}
+
+impl From<FetchShareArg> for (String, PgArguments) {
+    fn from(value: FetchShareArg) -> (String, PgArguments) {
+        let (usr_id, seq_num) = (value.0, value.1);
+        let sql_patt = "SELECT collab_id,access FROM cart_share WHERE usr_id=$1 AND seq=$2";
+        let mut args = PgArguments::default();
+        args.add(usr_id as i64).unwrap();
+        args.add(seq_num as i16).unwrap();
+        (sql_patt.to_string(), args)
+    }
+}
+
+impl From<FetchTotNumLinesArg> for (String, PgArguments) {
+    fn from(value: FetchTotNumLinesArg) -> (String, PgArguments) {
+        let (usr_id, seq_num) = (value.0, value.1);
+        let sql_patt = "SELECT COUNT(*) FROM cart_line_detail WHERE usr_id=$1 AND seq=$2";
+        let mut args = PgArguments::default();
+        args.add(usr_id as i64).unwrap();
+        args.add(seq_num as i16).unwrap();
+        (sql_patt.to_string(), args)
+    }
+}
+
+impl From<FetchTopLvlArg> for (String, PgArguments) {
+    fn from(value: FetchTopLvlArg) -> (String, PgArguments) {
+        let (usr_id, seq_num) = (value.0, value.1);
+        let sql_patt = "SELECT usr_id,seq,title FROM cart_toplvl_meta \
+                        WHERE usr_id=$1 AND seq=$2";
+        let mut args = PgArguments::default();
+        args.add(usr_id as i64).unwrap();
+        args.add(seq_num as i16).unwrap();
+        (sql_patt.to_string(), args)
+    }
+}
+
+impl FetchLinesArg {
+    fn sql_pattern(num_batch: usize) -> String {
+        let mut sql_patt = "SELECT store_id,product_id,quantity,attr_set \
+                        FROM cart_line_detail WHERE usr_id=? AND seq=?"
+            .to_string();
+        if num_batch > 0 {
+            let where_op = (0..num_batch)
+                .map(|_| "(store_id=? AND product_id=?)")
+                .collect::<Vec<_>>()
+                .join(" OR ");
+            let extra = format!(" AND ({where_op})");
+            sql_patt += extra.as_str();
+        }
+        positional_param(sql_patt.as_str())
+    }
+}
+impl<'a> IntoArguments<'a, Postgres> for FetchLinesArg {
+    fn into_arguments(self) -> <Postgres as AbstractDatabase>::Arguments<'a> {
+        let (usr_id, seq_num, opt_pids) = (self.0, self.1, self.2);
+        let mut args = PgArguments::default();
+        args.add(usr_id as i64).unwrap();
+        args.add(seq_num as i16).unwrap();
+        if let Some(pids) = opt_pids {
+            pids.into_iter()
+                .map(|id_| {
+                    let (seller, p_id) = (id_.store_id, id_.product_id);
+                    args.add(seller as i64).unwrap();
+                    args.add(p_id as i64).unwrap();
+                })
+                .count();
+        }
+        args
+    }
+}
+impl From<FetchLinesArg> for (String, PgArguments) {
+    fn from(value: FetchLinesArg) -> (String, PgArguments) {
+        let num_batch = if let Some(v) = value.2.as_ref() {
+            v.len()
+        } else {
+            0usize
+        };
+        (
+            FetchLinesArg::sql_pattern(num_batch),
+            value.into_arguments(),
+        )
+    }
+}
+
+impl TryFrom<PgRow> for CartModel {
+    type Error = AppError;
+    fn try_from(row: PgRow) -> DefaultResult<Self, Self::Error> {
+        let owner = try_get_u32(&row, 0)?;
+        let seq_num = try_get_u8(&row, 1)?;
+        let title = row.try_get::<String, usize>(2)?;
+        Ok(Self {
+            owner,
+            seq_num,
+            title,
+            saved_lines: Vec::new(),
+            new_lines: Vec::new(),
+        })
+    }
+}
+impl TryFrom<PgRow> for CartLineModel {
+    type Error = AppError;
+    fn try_from(row: PgRow) -> DefaultResult<Self, Self::Error> {
+        let store_id = try_get_u32(&row, 0)?;
+        let product_id = try_get_u64(&row, 1)?;
+        let qty_req = try_get_u32(&row, 2)?;
+        let chosen_attr = {
+            let raw = row.try_get::<&str, usize>(3)?;
+            CartLineModel::deserialize_attrs(raw)?
+        };
+        Ok(Self {
+            id_: BaseProductIdentity {
+                store_id,
+                product_id,
+            },
+            chosen_attr,
+            qty_req,
+        })
+    }
+}
+
+impl TryFrom<PgRow> for CartCollaboratorModel {
+    type Error = AppError;
+    fn try_from(row: PgRow) -> DefaultResult<Self, Self::Error> {
+        let usr_id = try_get_u32(&row, 0)?;
+        let label = row.try_get::<&str, usize>(1)?;
+        CartCollaboratorModel::try_from_label(usr_id, label)
+    }
+}
+
+pub(crate) struct CartPostgreSqlRepo {
+    _db: Arc<AppPostgreSqlStore>,
+}
+
+#[async_trait]
+impl AbsCartRepo for CartPostgreSqlRepo {
+    async fn update(&self, obj: CartModel) -> DefaultResult<usize, AppError> {
+        let mut conn = self._db.acquire().await?;
+        let mut tx = conn.begin().await?;
+        let (sql_patt, args) = InsertUpdateTopLvlArg(&obj).into();
+        let _rs = run_query_once(&mut tx, sql_patt, args, Some(1)).await?;
+        let (usr_id, seq_num, saved_lines, new_lines) =
+            (obj.owner, obj.seq_num, obj.saved_lines, obj.new_lines);
+        let (num_updating, num_inserting) = (saved_lines.len(), new_lines.len());
+        if !saved_lines.is_empty() {
+            let (sql_patt, args) = UpdateLineArg(usr_id, seq_num, saved_lines).into();
+            let _rs = run_query_once(&mut tx, sql_patt, args, Some(num_updating)).await?;
+        }
+        if !new_lines.is_empty() {
+            let (sql_patt, args) = InsertLineArg(usr_id, seq_num, new_lines).into();
+            let _rs = run_query_once(&mut tx, sql_patt, args, Some(num_inserting)).await?;
+        }
+        tx.commit().await?;
+        Ok(num_inserting + num_updating)
+    } // end of fn update
+
+    async fn discard(&self, owner: u32, seq: u8) -> DefaultResult<(), AppError> {
+        let mut conn = self._db.acquire().await?;
+        let mut tx = conn.begin().await?;
+        let (sql_patt, args) = DiscardLineArg(owner, seq).into();
+        let _rs = run_query_once(&mut tx, sql_patt, args, None).await?;
+        let (sql_patt, args) = DiscardShareArg(owner, seq).into();
+        let _rs = run_query_once(&mut tx, sql_patt, args, None).await?;
+        // the cart might never be saved, which is considered as discarded
+        let (sql_patt, args) = DiscardTopLvlArg(owner, seq).into();
+        let _rs = run_query_once(&mut tx, sql_patt, args, None).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn remove_lines(&self, obj: CartModel) -> DefaultResult<usize, AppError> {
+        if obj.saved_lines.is_empty() {
+            return Ok(0);
+        }
+        let mut conn = self._db.acquire().await?;
+        let mut tx = conn.begin().await?;
+        let (sql_patt, args) = RemoveLineArg(obj.owner, obj.seq_num, obj.saved_lines).into();
+        let rs = run_query_once(&mut tx, sql_patt, args, None).await?;
+        tx.commit().await?;
+        Ok(rs.rows_affected() as usize)
+    }
+
+    async fn num_lines_saved(&self, owner: u32, seq: u8) -> DefaultResult<usize, AppError> {
+        let (sql_patt, args) = FetchTotNumLinesArg(owner, seq).into();
+        let mut conn = self._db.acquire().await?;
+        let query = sqlx::query_with(sql_patt.as_str(), args);
+        let row = query.fetch_one(conn.as_mut()).await?;
+        let num_lines_saved = row.try_get::<i64, usize>(0)?;
+        Ok(num_lines_saved as usize)
+    }
+
+    async fn fetch_cart(&self, owner: u32, seq: u8) -> DefaultResult<CartModel, AppError> {
+        let rawsql_toplvl = FetchTopLvlArg(owner, seq).into();
+        let rawsql_line = FetchLinesArg(owner, seq, None).into();
+        let out = self
+            .fetch_common(owner, seq, rawsql_toplvl, rawsql_line)
+            .await?;
+        Ok(out)
+    } // end of fn fetch_cart
+
+    async fn fetch_lines_by_pid(
+        &self,
+        owner: u32,
+        seq: u8,
+        pids: Vec<BaseProductIdentity>,
+    ) -> DefaultResult<CartModel, AppError> {
+        let rawsql_toplvl = FetchTopLvlArg(owner, seq).into();
+        let rawsql_line = FetchLinesArg(owner, seq, Some(pids)).into();
+        let out = self
+            .fetch_common(owner, seq, rawsql_toplvl, rawsql_line)
+            .await?;
+        Ok(out)
+    } // end of fn fetch_lines_by_pid
+
+    async fn update_share(&self, obj: CartShareModel) -> DefaultResult<usize, AppError> {
+        let mut conn = self._db.acquire().await?;
+        let mut tx = conn.begin().await?;
+        let (sql_patt, args) = DiscardShareArg(obj.owner, obj.seq_num).into();
+        let _rs = run_query_once(&mut tx, sql_patt, args, None).await?;
+        let num_inserting = obj.collaborators.len();
+        if num_inserting > 0 {
+            let (sql_patt, args) = InsertShareArg(obj).into();
+            let _rs = run_query_once(&mut tx, sql_patt, args, Some(num_inserting)).await?;
+        }
+        tx.commit().await?;
+        Ok(num_inserting)
+    }
+
+    async fn fetch_share(&self, owner: u32, seq: u8) -> DefaultResult<CartShareModel, AppError> {
+        let (sql_patt, args) = FetchShareArg(owner, seq).into();
+        let mut conn = self._db.acquire().await?;
+        let query = sqlx::query_with(sql_patt.as_str(), args);
+        let rows = query.fetch_all(conn.as_mut()).await?;
+        let collaborators = rows
+            .into_iter()
+            .map(CartCollaboratorModel::try_from)
+            .collect::<DefaultResult<Vec<_>, AppError>>()?;
+        Ok(CartShareModel {
+            owner,
+            seq_num: seq,
+            collaborators,
+        })
+    }
+} // end of impl CartPostgreSqlRepo
+
+impl CartPostgreSqlRepo {
+    pub async fn new(dbs: Vec<Arc<AppPostgreSqlStore>>) -> DefaultResult<Self, AppError> {
+        if dbs.is_empty() {
+            Err(AppError {
+                code: AppErrorCode::MissingDataStore,
+                detail: Some("postgres".to_string()),
+            })
+        } else {
+            let _db = dbs.first().unwrap().clone();
+            Ok(Self { _db })
+        }
+    }
+    async fn fetch_common(
+        &self,
+        owner: u32,
+        seq: u8,
+        rawsql_toplvl: (String, PgArguments),
+        rawsql_line: (String, PgArguments),
+    ) -> DefaultResult<CartModel, AppError> {
+        let mut conn = self._db.acquire().await?;
+        let result = {
+            let query = sqlx::query_with(rawsql_toplvl.0.as_str(), rawsql_toplvl.1);
+            query.fetch_optional(conn.as_mut()).await?
+        };
+        if let Some(row) = result {
+            let mut cart = CartModel::try_from(row)?;
+            let query = sqlx::query_with(rawsql_line.0.as_str(), rawsql_line.1);
+            let rows = query.fetch_all(conn.as_mut()).await?;
+            let mut errors = rows
+                .into_iter()
+                .filter_map(|row| match CartLineModel::try_from(row) {
+                    Ok(v) => {
+                        cart.saved_lines.push(v);
+                        None
+                    }
+                    Err(e) => Some(e),
+                })
+                .collect::<Vec<_>>();
+            if errors.is_empty() {
+                Ok(cart)
+            } else {
+                Err(errors.remove(0))
+            }
+        } else {
+            Ok(CartModel {
+                owner,
+                seq_num: seq,
+                title: "Untitled".to_string(),
+                saved_lines: Vec::new(),
+                new_lines: Vec::new(),
+            })
+        }
+    }
+} // end of impl CartPostgreSqlRepo
@@ -0,0 +1,105 @@
+pub(super) mod cart;
+pub(super) mod currency;
+pub(super) mod oline_return;
+pub(super) mod order;
+pub(super) mod product_policy;
+pub(super) mod product_price;
+pub(super) mod stock;
+pub(super) mod store_shipping;
+
+use std::ops::DerefMut;
+use std::result::Result as DefaultResult;
+
+use sqlx::postgres::{PgArguments, PgQueryResult, PgRow};
+use sqlx::{Postgres, Row, Transaction};
+
+use crate::error::AppError;
+use ecommerce_common::adapter::repository::OidBytes;
+use ecommerce_common::error::AppErrorCode;
+
+// PostgreSQL does not support unsigned integer types, each unsigned field in
+// the application models is stored in the next wider signed column type :
+// - `u8`  -> `SMALLINT`
+// - `u16` -> `INTEGER`
+// - `u32` -> `BIGINT`
+// - `u64` -> `BIGINT`, bits are reinterpreted as-is, so large product IDs
+//   may appear negative in database but still round-trip back correctly.
+
+/// convert the placeholders `?` in the given SQL pattern to the positional
+/// parameters `$1, $2, ... $n` required by PostgreSQL
+fn positional_param(sql_patt: &str) -> String {
+    let mut out = String::with_capacity(sql_patt.len() + (sql_patt.len() >> 2));
+    let mut idx = 0usize;
+    sql_patt.chars().for_each(|c| {
+        if c == '?' {
+            idx += 1;
+            out.push('$');
+            out += idx.to_string().as_str();
+        } else {
+            out.push(c);
+        }
+    });
+    out
+}
+
+fn unsigned_decode_error(idx: usize, reason: String) -> AppError {
+    AppError {
+        code: AppErrorCode::DataCorruption,
+        detail: Some(reason + ", when decoding column at idx " + idx.to_string().as_str()),
+    }
+}
+
+fn try_get_u8(row: &PgRow, idx: usize) -> DefaultResult<u8, AppError> {
+    let raw = row.try_get::<i16, usize>(idx)?;
+    u8::try_from(raw).map_err(|e| unsigned_decode_error(idx, e.to_string()))
+}
+fn try_get_u16(row: &PgRow, idx: usize) -> DefaultResult<u16, AppError> {
+    let raw = row.try_get::<i32, usize>(idx)?;
+    u16::try_from(raw).map_err(|e| unsigned_decode_error(idx, e.to_string()))
+}
+fn try_get_u32(row: &PgRow, idx: usize) -> DefaultResult<u32, AppError> {
+    let raw = row.try_get::<i64, usize>(idx)?;
+    u32::try_from(raw).map_err(|e| unsigned_decode_error(idx, e.to_string()))
+}
+fn try_get_u64(row: &PgRow, idx: usize) -> DefaultResult<u64, AppError> {
+    let raw = row.try_get::<i64, usize>(idx)?;
+    Ok(raw as u64)
+}
+
+fn to_app_oid(row: &PgRow, idx: usize) -> DefaultResult<String, AppError> {
+    let raw = row.try_get::<Vec<u8>, usize>(idx)?;
+    let out = OidBytes::to_app_oid(raw)?;
+    Ok(out)
+}
+
+async fn run_query_once(
+    tx: &mut Transaction<'_, Postgres>,
+    sql_patt: String,
+    args: PgArguments,
+    maybe_num_batch: Option<usize>,
+) -> DefaultResult<PgQueryResult, AppError> {
+    // Note, unlike the mariaDB repositories, the statement is not explicitly
+    // prepared without parameter types, `sqlx` declares the statement with
+    // types derived from the given arguments on first use, then caches it
+    // by SQL text in the connection.
+    let query = sqlx::query_with(sql_patt.as_str(), args);
+    let exec = tx.deref_mut();
+    let resultset = query.execute(exec).await?;
+    if let Some(num_batch) = maybe_num_batch {
+        let num_affected = resultset.rows_affected() as usize;
+        if num_affected == num_batch {
+            Ok(resultset)
+        } else {
+            let detail = format!(
+                "num_affected, actual:{}, expect:{}",
+                num_affected, num_batch
+            );
+            Err(AppError {
+                code: AppErrorCode::DataCorruption,
+                detail: Some(detail),
+            })
+        }
+    } else {
+        Ok(resultset)
+    }
+}
@@ -0,0 +1,347 @@
+use std::collections::HashMap;
+use std::result::Result as DefaultResult;
+use std::sync::Arc;
+use std::vec::Vec;
+
+use async_trait::async_trait;
+use chrono::{DateTime, FixedOffset, NaiveDateTime};
+use sqlx::database::Database as AbstractDatabase;
+use sqlx::postgres::{PgArguments, PgRow};
+use sqlx::{Acquire, Arguments, IntoArguments, Postgres, Row, Transaction};
+
+use ecommerce_common::adapter::repository::OidBytes;
+use ecommerce_common::error::AppErrorCode;
+
+use crate::constant::hard_limit;
+use crate::datastore::AppPostgreSqlStore;
+use crate::error::AppError;
+use crate::model::{OrderLineIdentity, OrderLinePriceModel, OrderReturnModel};
+use crate::repository::AbsOrderReturnRepo;
+
+use super::{positional_param, run_query_once, to_app_oid, try_get_u16, try_get_u32, try_get_u64};
+
+struct InsertReqArg(OidBytes, u16, Vec<OrderReturnModel>);
+struct FetchByIdArg(OidBytes, Vec<OrderLineIdentity>);
+struct FetchByTimeArg(DateTime<FixedOffset>, DateTime<FixedOffset>);
+struct FetchByIdAndTimeArg(OidBytes, DateTime<FixedOffset>, DateTime<FixedOffset>);
+
+struct ReturnsPerOrder(Vec<OrderReturnModel>);
+struct ReturnOidMap {
+    rows: Vec<PgRow>,
+    _map: HashMap<String, ReturnsPerOrder>,
+}
+
+impl InsertReqArg {
+    fn sql_pattern(num_batch: usize) -> String {
+        let col_seq = "o_id,seq,store_id,product_id,attr_seq,create_time,\
+            quantity,price_unit,price_total";
+        let items = (0..num_batch)
+            .map(|_| "(?,?,?,?,?,?,?,?,?)")
+            .collect::<Vec<_>>();
+        let patt = format!(
+            "INSERT INTO oline_return_req({col_seq}) VALUES {}",
+            items.join(",")
+        );
+        positional_param(patt.as_str())
+    }
+}
+impl<'q> IntoArguments<'q, Postgres> for InsertReqArg {
+    fn into_arguments(self) -> <Postgres as AbstractDatabase>::Arguments<'q> {
+        let (oid_b, mut seq_start, reqs) = (self.0, self.1, self.2);
+        let oid = oid_b.as_column();
+        let mut args = PgArguments::default();
+        reqs.into_iter()
+            .map(|req| {
+                let qty_map = req.qty;
+                let seller_id = req.id_.store_id();
+                let prod_id = req.id_.product_id();
+                let attr_seq = req.id_.attrs_seq_num();
+                qty_map
+                    .into_iter()
+                    .map(|(ctime, (qty, refund))| {
+                        args.add(oid.clone()).unwrap();
+                        args.add(seq_start as i32).unwrap();
+                        args.add(seller_id as i64).unwrap();
+                        args.add(prod_id as i64).unwrap();
+                        args.add(attr_seq as i32).unwrap();
+                        args.add(ctime.naive_utc()).unwrap();
+                        args.add(qty as i64).unwrap();
+                        args.add(refund.unit() as i64).unwrap();
+                        args.add(refund.total() as i64).unwrap();
+                        seq_start += 1;
+                    })
+                    .count();
+            })
+            .count();
+        args
+    }
+}
+impl From<InsertReqArg> for (String, PgArguments) {
+    fn from(value: InsertReqArg) -> (String, PgArguments) {
+        let num_batch = value.2.iter().map(|r| r.qty.len()).sum();
+        (InsertReqArg::sql_pattern(num_batch), value.into_arguments())
+    }
+}
+
+// TODO, add column attr_seq to order-line return request
+const COLUMN_SEQ_SELECT: &str = "store_id,product_id,attr_seq,create_time,\
+            quantity,price_unit,price_total";
+
+impl FetchByIdArg {
+    fn sql_pattern(num_batch: usize) -> String {
+        let items = (0..num_batch)
+            .map(|_| "(store_id=? AND product_id=? AND attr_seq=?)")
+            .collect::<Vec<_>>();
+        let patt = format!(
+            "SELECT {COLUMN_SEQ_SELECT} FROM oline_return_req WHERE o_id=? AND ({})",
+            items.join(" OR ")
+        );
+        positional_param(patt.as_str())
+    }
+}
+impl<'q> IntoArguments<'q, Postgres> for FetchByIdArg {
+    fn into_arguments(self) -> <Postgres as AbstractDatabase>::Arguments<'q> {
+        let (oid_b, pids) = (self.0, self.1);
+        let mut args = PgArguments::default();
+        args.add(oid_b.as_column()).unwrap();
+        pids.into_iter()
+            .map(|pid| {
+                args.add(pid.store_id() as i64).unwrap();
+                args.add(pid.product_id() as i64).unwrap();
+                args.add(pid.attrs_seq_num() as i32).unwrap();
+            })
+            .count();
+        args
+    }
+}
+impl From<FetchByIdArg> for (String, PgArguments) {
+    fn from(value: FetchByIdArg) -> (String, PgArguments) {
+        let num_batch = value.1.len();
+        (FetchByIdArg::sql_pattern(num_batch), value.into_arguments())
+    }
+}
+
+impl From<FetchByTimeArg> for (String, PgArguments) {
+    fn from(value: FetchByTimeArg) -> (String, PgArguments) {
+        let (start, end) = (value.0, value.1);
+        // TODO, improve query time, since the execution plan will not search in
+        // primary index. Possible approaches:
+        // - secondary index by create-time, since the time range argument in the
+        //   fetch method is used for querying recently added returns, however this will
+        //   slow down the writes, and this application should handle more write
+        //   operations (initiated by customers) than reads (initiates by remote
+        //   payment application), it does not sound like a good option.
+        // - time-series database (TODO)
+        let sql_patt = format!(
+            "SELECT {COLUMN_SEQ_SELECT},o_id FROM oline_return_req \
+             WHERE create_time > $1 AND create_time <= $2"
+        );
+        let mut args = PgArguments::default();
+        args.add(start.naive_utc()).unwrap();
+        args.add(end.naive_utc()).unwrap();
+        (sql_patt, args)
+    }
+}
+
+impl From<FetchByIdAndTimeArg> for (String, PgArguments) {
+    fn from(value: FetchByIdAndTimeArg) -> (String, PgArguments) {
+        let (oid_b, start, end) = (value.0, value.1, value.2);
+        let sql_patt = format!(
+            "SELECT {COLUMN_SEQ_SELECT} FROM oline_return_req \
+             WHERE o_id=$1 AND create_time > $2 AND create_time <= $3"
+        );
+        let mut args = PgArguments::default();
+        args.add(oid_b.as_column()).unwrap();
+        args.add(start.naive_utc()).unwrap();
+        args.add(end.naive_utc()).unwrap();
+        (sql_patt, args)
+    }
+}
+
+impl ReturnsPerOrder {
+    fn new() -> Self {
+        Self(vec![])
+    }
+    fn try_merge(&mut self, row: PgRow) -> DefaultResult<(), AppError> {
+        let store_id = try_get_u32(&row, 0)?;
+        let product_id = try_get_u64(&row, 1)?;
+        let attr_seq = try_get_u16(&row, 2)?;
+        let id_ = OrderLineIdentity::from((store_id, product_id, attr_seq));
+        let result = self.0.iter_mut().find(|ret| ret.id_ == id_);
+        let saved_ret = if let Some(v) = result {
+            v
+        } else {
+            let item = OrderReturnModel {
+                id_,
+                qty: HashMap::new(),
+            };
+            self.0.push(item);
+            self.0.last_mut().unwrap()
+        };
+        let create_time = row.try_get::<NaiveDateTime, usize>(3)?.and_utc().into();
+        let quantity = try_get_u32(&row, 4)?;
+        let unit = try_get_u32(&row, 5)?;
+        let total = try_get_u32(&row, 6)?;
+        let refund = OrderLinePriceModel::from((unit, total));
+        saved_ret.qty.insert(create_time, (quantity, refund));
+        Ok(())
+    }
+}
+impl From<ReturnsPerOrder> for Vec<OrderReturnModel> {
+    fn from(value: ReturnsPerOrder) -> Vec<OrderReturnModel> {
+        value.0
+    }
+}
+
+impl ReturnOidMap {
+    fn new(rows: Vec<PgRow>) -> Self {
+        Self {
+            rows,
+            _map: HashMap::new(),
+        }
+    }
+}
+impl TryInto<Vec<(String, OrderReturnModel)>> for ReturnOidMap {
+    type Error = AppError;
+    fn try_into(self) -> DefaultResult<Vec<(String, OrderReturnModel)>, Self::Error> {
+        let (mut ret_map, rows) = (self._map, self.rows);
+        let has_error = rows
+            .into_iter()
+            .map(|row| {
+                let oid = to_app_oid(&row, 7)?;
+                if !ret_map.contains_key(oid.as_str()) {
+                    ret_map.insert(oid.clone(), ReturnsPerOrder::new());
+                }
+                let entry = ret_map.get_mut(oid.as_str()).unwrap();
+                entry.try_merge(row)?;
+                Ok(())
+            })
+            .find_map(|r| r.err());
+        if let Some(e) = has_error {
+            return Err(e);
+        }
+        let out = ret_map
+            .into_iter()
+            .map(|(oid, inner_rets)| {
+                let o_rets: Vec<OrderReturnModel> = inner_rets.into();
+                (oid, o_rets)
+            })
+            .flat_map(|(oid, o_rets)| o_rets.into_iter().map(move |ret| (oid.clone(), ret)))
+            .collect::<Vec<_>>();
+        Ok(out)
+    }
+}
+
+pub(crate) struct OrderReturnPostgreSqlRepo {
+    _db: Arc<AppPostgreSqlStore>,
+}
+
+#[async_trait]
+impl AbsOrderReturnRepo for OrderReturnPostgreSqlRepo {
+    async fn fetch_by_pid(
+        &self,
+        oid: &str,
+        pids: Vec<OrderLineIdentity>,
+    ) -> DefaultResult<Vec<OrderReturnModel>, AppError> {
+        if pids.is_empty() {
+            Ok(vec![])
+        } else {
+            let oid_b = OidBytes::try_from(oid)?;
+            let (sql_patt, args) = FetchByIdArg(oid_b, pids).into();
+            self.fetch_by_oid_common(sql_patt, args).await
+        }
+    }
+    async fn fetch_by_created_time(
+        &self,
+        start: DateTime<FixedOffset>,
+        end: DateTime<FixedOffset>,
+    ) -> DefaultResult<Vec<(String, OrderReturnModel)>, AppError> {
+        let mut conn = self._db.acquire().await?;
+        let (sql_patt, args) = FetchByTimeArg(start, end).into();
+        let query = sqlx::query_with(sql_patt.as_str(), args);
+        let rows = query.fetch_all(conn.as_mut()).await?;
+        ReturnOidMap::new(rows).try_into()
+    }
+    async fn fetch_by_oid_ctime(
+        &self,
+        oid: &str,
+        start: DateTime<FixedOffset>,
+        end: DateTime<FixedOffset>,
+    ) -> DefaultResult<Vec<OrderReturnModel>, AppError> {
+        let oid_b = OidBytes::try_from(oid)?;
+        let (sql_patt, args) = FetchByIdAndTimeArg(oid_b, start, end).into();
+        self.fetch_by_oid_common(sql_patt, args).await
+    }
+    async fn create(
+        &self,
+        oid: &str,
+        reqs: Vec<OrderReturnModel>,
+    ) -> DefaultResult<usize, AppError> {
+        let oid_b = OidBytes::try_from(oid)?;
+        let num_batch = reqs.iter().map(|r| r.qty.len()).sum();
+        let mut conn = self._db.acquire().await?;
+        let mut tx = conn.begin().await?;
+        let num_returns = Self::get_num_reqs(&mut tx, &oid_b).await?;
+        let (sql_patt, args) = InsertReqArg(oid_b, num_returns, reqs).into();
+        let _rs = run_query_once(&mut tx, sql_patt, args, Some(num_batch)).await?;
+        tx.commit().await?;
+        Ok(num_batch)
+    }
+} // end of impl AbsOrderReturnRepo
+
+impl OrderReturnPostgreSqlRepo {
+    pub(crate) async fn new(dbs: Vec<Arc<AppPostgreSqlStore>>) -> DefaultResult<Self, AppError> {
+        if dbs.is_empty() {
+            Err(AppError {
+                code: AppErrorCode::MissingDataStore,
+                detail: Some("postgres".to_string()),
+            })
+        } else {
+            let _db = dbs.first().unwrap().clone();
+            Ok(Self { _db })
+        }
+    }
+    async fn get_num_reqs(
+        tx: &mut Transaction<'_, Postgres>,
+        oid_b: &OidBytes,
+    ) -> DefaultResult<u16, AppError> {
+        let sql_patt = "SELECT COUNT(seq) FROM oline_return_req WHERE o_id=$1 GROUP BY o_id";
+        let query = sqlx::query(sql_patt).bind(oid_b.as_column());
+        let result = query.fetch_optional(&mut **tx).await?;
+        if let Some(row) = result {
+            let num_returns = row.try_get::<i64, usize>(0)?;
+            let req_limit = hard_limit::MAX_ORDER_LINES_PER_REQUEST.try_into().unwrap();
+            if num_returns < req_limit {
+                Ok(num_returns as u16)
+            } else {
+                let oid = String::from_utf8(oid_b.as_column()).unwrap();
+                let detail = Some(format!("oid:{}, seq:{}", oid, num_returns));
+                Err(AppError {
+                    code: AppErrorCode::ExceedingMaxLimit,
+                    detail,
+                })
+            }
+        } else {
+            Ok(0)
+        }
+    }
+    async fn fetch_by_oid_common(
+        &self,
+        sql_patt: String,
+        args: PgArguments,
+    ) -> DefaultResult<Vec<OrderReturnModel>, AppError> {
+        let mut conn = self._db.acquire().await?;
+        let query = sqlx::query_with(sql_patt.as_str(), args);
+        let rows: Vec<_> = query.fetch_all(conn.as_mut()).await?;
+        let mut rets = ReturnsPerOrder::new();
+        let maybe_error = rows
+            .into_iter()
+            .map(|row| rets.try_merge(row))
+            .find_map(|r| r.err());
+        if let Some(e) = maybe_error {
+            Err(e)
+        } else {
+            Ok(rets.into())
+        }
+    }
+} // end of impl OrderReturnPostgreSqlRepo
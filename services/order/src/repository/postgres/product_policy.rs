@@ -0,0 +1,231 @@
+use std::cmp::min;
+use std::ops::DerefMut;
+use std::result::Result as DefaultResult;
+use std::sync::Arc;
+use std::vec::Vec;
+
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use sqlx::postgres::{PgArguments, PgRow};
+use sqlx::{Acquire, Arguments, Postgres, Row, Transaction};
+
+use ecommerce_common::error::AppErrorCode;
+
+use crate::datastore::AppPostgreSqlStore;
+use crate::error::AppError;
+use crate::model::{ProductPolicyModel, ProductPolicyModelSet};
+use crate::repository::AbstProductPolicyRepo;
+
+use super::{positional_param, try_get_u16, try_get_u32, try_get_u64};
+
+pub(crate) struct ProductPolicyPostgreSqlRepo {
+    db: Arc<AppPostgreSqlStore>,
+}
+
+impl ProductPolicyPostgreSqlRepo {
+    pub async fn new(dbs: &[Arc<AppPostgreSqlStore>]) -> DefaultResult<Self, AppError> {
+        if dbs.is_empty() {
+            let e = AppError {
+                code: AppErrorCode::MissingDataStore,
+                detail: Some("postgres".to_string()),
+            };
+            Err(e)
+        } else {
+            let db = dbs.first().unwrap().clone();
+            Ok(Self { db })
+        }
+    }
+
+    fn prep_stmt_patt_read(sql_pattern_blocks: (&str, &str, &str), mut num_items: usize) -> String {
+        assert!(num_items > 0);
+        let (prefix, item, delimiter) = sql_pattern_blocks;
+        let mut sql = prefix.to_string() + item;
+        num_items -= 1;
+        let num_done = (0..num_items)
+            .map(|_| {
+                sql += delimiter;
+                sql += item;
+            })
+            .count();
+        assert_eq!(num_done, num_items);
+        sql
+    }
+
+    async fn _save(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        mut policies: Vec<ProductPolicyModel>,
+    ) -> DefaultResult<(), AppError> {
+        let limit = 14_usize;
+        while !policies.is_empty() {
+            let num_batch = min(policies.len(), limit);
+            let policies_processing = policies.split_off(policies.len() - num_batch);
+            assert!(!policies_processing.is_empty());
+            let (sql_patt, args) = Self::construct_insert_args(policies_processing);
+            // the primary key includes version number, the insertion fails if
+            // other client has saved the same version concurrently
+            let query = sqlx::query_with(sql_patt.as_str(), args);
+            let exec = tx.deref_mut();
+            let resultset = query
+                .execute(exec)
+                .await
+                .map_err(AppError::from_sqlx_unique_conflict)?;
+            let num_affected = resultset.rows_affected() as usize;
+            if num_affected != num_batch {
+                let detail = format!(
+                    "num_affected, actual:{}, expect:{}",
+                    num_affected, num_batch
+                );
+                return Err(AppError {
+                    code: AppErrorCode::DataCorruption,
+                    detail: Some(detail),
+                });
+            }
+        }
+        Ok(())
+    } // end of fn _save
+
+    fn construct_insert_args(items: Vec<ProductPolicyModel>) -> (String, PgArguments) {
+        const SQL_PATTERN_BLOCKS: (&str, &str, &str) = (
+            "INSERT INTO product_policy(store_id,product_id,version,effective_from,\
+             auto_cancel_secs,warranty_hours,max_num_rsv,min_num_rsv) VALUES ",
+            "(?,?,?,?,?,?,?,?)",
+            ",",
+        );
+        let mut args = PgArguments::default();
+        let num_batch = items
+            .into_iter()
+            .map(|item| {
+                args.add(item.store_id as i64).unwrap();
+                args.add(item.product_id as i64).unwrap();
+                args.add(item.version as i64).unwrap();
+                args.add(item.effective_from.naive_utc()).unwrap();
+                args.add(item.auto_cancel_secs as i64).unwrap();
+                args.add(item.warranty_hours as i64).unwrap();
+                args.add(item.max_num_rsv as i32).unwrap();
+                args.add(item.min_num_rsv as i32).unwrap();
+            })
+            .count();
+        let sql_patt = Self::prep_stmt_patt_read(SQL_PATTERN_BLOCKS, num_batch);
+        (positional_param(sql_patt.as_str()), args)
+    } // end of fn construct_insert_args
+} // end of impl ProductPolicyPostgreSqlRepo
+
+#[async_trait]
+impl AbstProductPolicyRepo for ProductPolicyPostgreSqlRepo {
+    async fn fetch(&self, ids: Vec<(u32, u64)>) -> DefaultResult<ProductPolicyModelSet, AppError> {
+        // only the latest version of each product policy is loaded
+        const SQL_PATTERN_BLOCKS: (&str, &str, &str) = (
+            "SELECT a.store_id,a.product_id,a.version,a.effective_from,\
+             a.auto_cancel_secs,a.warranty_hours,a.max_num_rsv,a.min_num_rsv \
+             FROM product_policy AS a INNER JOIN (SELECT store_id,product_id,\
+             MAX(version) AS version FROM product_policy WHERE ",
+            "(store_id=? AND product_id=?)",
+            " OR ",
+        );
+        const SQL_SUFFIX: &str = " GROUP BY store_id,product_id) AS b ON \
+             a.store_id=b.store_id AND a.product_id=b.product_id \
+             AND a.version=b.version";
+        let limit = 16_usize;
+        let mut _ids = ids;
+        let mut conn = self.db.acquire().await?;
+        let mut policies = vec![];
+        while !_ids.is_empty() {
+            let num_batch = min(_ids.len(), limit);
+            let sql = Self::prep_stmt_patt_read(SQL_PATTERN_BLOCKS, num_batch) + SQL_SUFFIX;
+            let sql = positional_param(sql.as_str());
+            let mut args = PgArguments::default();
+            let _ = _ids
+                .drain(0..num_batch)
+                .map(|(store_id, prod_id)| {
+                    args.add(store_id as i64).unwrap();
+                    args.add(prod_id as i64).unwrap();
+                })
+                .count();
+            let query = sqlx::query_with(sql.as_str(), args);
+            let rows = query.fetch_all(conn.as_mut()).await?;
+            let portions = rows
+                .into_iter()
+                .map(ProductPolicyModel::try_from)
+                .collect::<Vec<_>>();
+            let errors = portions
+                .iter()
+                .filter_map(|r| {
+                    if let Err(e) = r.as_ref() {
+                        Some(e.detail.as_ref().unwrap().to_string())
+                    } else {
+                        None
+                    }
+                })
+                .collect::<Vec<_>>();
+            if !errors.is_empty() {
+                return Err(AppError {
+                    detail: Some(errors.join(",")),
+                    code: AppErrorCode::DataCorruption,
+                });
+            }
+            policies.extend(portions.into_iter().filter_map(|r| r.ok()));
+        } // end of loop
+        Ok(ProductPolicyModelSet { policies })
+    } // end of fn fetch
+
+    async fn fetch_history(
+        &self,
+        store_id: u32,
+        product_id: u64,
+    ) -> DefaultResult<ProductPolicyModelSet, AppError> {
+        let sql_patt = "SELECT store_id,product_id,version,effective_from,\
+            auto_cancel_secs,warranty_hours,max_num_rsv,min_num_rsv FROM \
+            product_policy WHERE store_id=$1 AND product_id=$2 ORDER BY version ASC";
+        let mut conn = self.db.acquire().await?;
+        let rows = sqlx::query(sql_patt)
+            .bind(store_id as i64)
+            .bind(product_id as i64)
+            .fetch_all(conn.as_mut())
+            .await?;
+        let policies = rows
+            .into_iter()
+            .map(ProductPolicyModel::try_from)
+            .collect::<DefaultResult<Vec<_>, AppError>>()?;
+        Ok(ProductPolicyModelSet { policies })
+    } // end of fn fetch_history
+
+    async fn save(&self, ppset: ProductPolicyModelSet) -> DefaultResult<(), AppError> {
+        // append-only, existing versions are never modified
+        let policies_add = ppset
+            .policies
+            .into_iter()
+            .filter(|p| p.is_create)
+            .collect::<Vec<_>>();
+        let mut conn = self.db.acquire().await?;
+        let mut tx = conn.begin().await?;
+        self._save(&mut tx, policies_add).await?;
+        tx.commit().await?;
+        Ok(())
+    } // end of fn save
+} // end of impl ProductPolicyPostgreSqlRepo
+
+impl TryFrom<PgRow> for ProductPolicyModel {
+    type Error = AppError;
+    fn try_from(value: PgRow) -> DefaultResult<Self, Self::Error> {
+        let store_id = try_get_u32(&value, 0)?;
+        let product_id = try_get_u64(&value, 1)?;
+        let version = try_get_u32(&value, 2)?;
+        let effective_from = value.try_get::<NaiveDateTime, usize>(3)?.and_utc().into();
+        let auto_cancel_secs = try_get_u32(&value, 4)?;
+        let warranty_hours = try_get_u32(&value, 5)?;
+        let max_num_rsv = try_get_u16(&value, 6)?;
+        let min_num_rsv = try_get_u16(&value, 7)?;
+        Ok(Self {
+            is_create: false,
+            store_id,
+            product_id,
+            version,
+            effective_from,
+            auto_cancel_secs,
+            warranty_hours,
+            max_num_rsv,
+            min_num_rsv,
+        })
+    } // end of fn try_from
+} // end of impl ProductPolicyModel
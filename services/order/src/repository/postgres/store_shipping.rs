@@ -0,0 +1,180 @@
+use std::result::Result as DefaultResult;
+use std::sync::Arc;
+use std::vec::Vec;
+
+use async_trait::async_trait;
+use sqlx::database::Database as AbstractDatabase;
+use sqlx::postgres::{PgArguments, PgRow};
+use sqlx::{Acquire, Arguments, IntoArguments, Postgres, Row};
+
+use ecommerce_common::api::dto::CountryCode;
+use ecommerce_common::error::AppErrorCode;
+
+use crate::api::dto::ShippingMethod;
+use crate::datastore::AppPostgreSqlStore;
+use crate::error::AppError;
+use crate::model::{ShippingFeeModel, StoreShipMethodModel, StoreShippingModel};
+use crate::repository::AbsStoreShippingRepo;
+
+use super::{positional_param, run_query_once, try_get_u32};
+
+struct DiscardFeeArg(u32);
+struct InsertFeeArg(StoreShippingModel);
+struct FetchFeeArg(Vec<u32>);
+struct FeeRow(PgRow);
+
+impl From<DiscardFeeArg> for (String, PgArguments) {
+    fn from(value: DiscardFeeArg) -> (String, PgArguments) {
+        let sql_patt = "DELETE FROM store_shipping_fee WHERE store_id=$1";
+        let mut args = PgArguments::default();
+        args.add(value.0 as i64).unwrap();
+        (sql_patt.to_string(), args)
+    }
+}
+
+impl InsertFeeArg {
+    fn num_rows(&self) -> usize {
+        self.0.methods.iter().map(|m| m.fees.len()).sum()
+    }
+    fn sql_pattern(num_batch: usize) -> String {
+        let col_seq = (0..num_batch)
+            .map(|_| "(?,?,?,?)")
+            .collect::<Vec<_>>()
+            .join(",");
+        let patt = format!(
+            "INSERT INTO store_shipping_fee(store_id,method,country,\
+             amount) VALUES {col_seq}"
+        );
+        positional_param(patt.as_str())
+    }
+}
+impl<'q> IntoArguments<'q, Postgres> for InsertFeeArg {
+    fn into_arguments(self) -> <Postgres as AbstractDatabase>::Arguments<'q> {
+        let StoreShippingModel { store_id, methods } = self.0;
+        let mut args = PgArguments::default();
+        methods
+            .into_iter()
+            .flat_map(|m| {
+                let method = String::from(m.method);
+                m.fees.into_iter().map(move |f| (method.clone(), f))
+            })
+            .map(|(method, f)| {
+                args.add(store_id as i64).unwrap();
+                args.add(method).unwrap();
+                args.add(String::from(f.country)).unwrap();
+                args.add(f.amount as i64).unwrap();
+            })
+            .count();
+        args
+    }
+}
+impl From<InsertFeeArg> for (String, PgArguments) {
+    fn from(value: InsertFeeArg) -> (String, PgArguments) {
+        (
+            InsertFeeArg::sql_pattern(value.num_rows()),
+            value.into_arguments(),
+        )
+    }
+}
+
+impl From<FetchFeeArg> for (String, PgArguments) {
+    fn from(value: FetchFeeArg) -> (String, PgArguments) {
+        let chosen = (0..value.0.len())
+            .map(|_| "?")
+            .collect::<Vec<_>>()
+            .join(",");
+        let sql_patt = format!(
+            "SELECT store_id,method,country,amount FROM store_shipping_fee \
+             WHERE store_id IN ({chosen})"
+        );
+        let sql_patt = positional_param(sql_patt.as_str());
+        let mut args = PgArguments::default();
+        value
+            .0
+            .into_iter()
+            .map(|store_id| {
+                args.add(store_id as i64).unwrap();
+            })
+            .count();
+        (sql_patt, args)
+    }
+}
+
+impl TryFrom<FeeRow> for (u32, ShippingMethod, ShippingFeeModel) {
+    type Error = AppError;
+    fn try_from(value: FeeRow) -> DefaultResult<Self, Self::Error> {
+        let row = value.0;
+        let store_id = try_get_u32(&row, 0)?;
+        let method = row.try_get::<&str, usize>(1)?.to_string();
+        let country = row.try_get::<&str, usize>(2)?.to_string();
+        let fee = ShippingFeeModel {
+            country: CountryCode::from(country),
+            amount: try_get_u32(&row, 3)?,
+        };
+        Ok((store_id, ShippingMethod::from(method), fee))
+    }
+}
+
+pub(crate) struct StoreShippingPostgreSqlRepo {
+    _db: Arc<AppPostgreSqlStore>,
+}
+
+#[async_trait]
+impl AbsStoreShippingRepo for StoreShippingPostgreSqlRepo {
+    async fn fetch(&self, store_ids: Vec<u32>) -> DefaultResult<Vec<StoreShippingModel>, AppError> {
+        if store_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let (sql_patt, args) = FetchFeeArg(store_ids).into();
+        let mut conn = self._db.acquire().await?;
+        let query = sqlx::query_with(sql_patt.as_str(), args);
+        let rows = query.fetch_all(conn.as_mut()).await?;
+        let mut out: Vec<StoreShippingModel> = Vec::new();
+        for row in rows {
+            let (store_id, method, fee) = FeeRow(row).try_into()?;
+            let store = if let Some(s) = out.iter_mut().find(|s| s.store_id == store_id) {
+                s
+            } else {
+                out.push(StoreShippingModel {
+                    store_id,
+                    methods: Vec::new(),
+                });
+                out.last_mut().unwrap()
+            };
+            if let Some(m) = store.methods.iter_mut().find(|m| m.method == method) {
+                m.fees.push(fee);
+            } else {
+                store.methods.push(StoreShipMethodModel {
+                    method,
+                    fees: vec![fee],
+                });
+            }
+        }
+        Ok(out)
+    } // end of fn fetch
+
+    async fn save(&self, obj: StoreShippingModel) -> DefaultResult<(), AppError> {
+        let mut conn = self._db.acquire().await?;
+        let mut tx = conn.begin().await?;
+        let (sql_patt, args) = DiscardFeeArg(obj.store_id).into();
+        let _rs = run_query_once(&mut tx, sql_patt, args, None).await?;
+        let arg = InsertFeeArg(obj);
+        let num_inserting = arg.num_rows();
+        if num_inserting > 0 {
+            let (sql_patt, args) = arg.into();
+            let _rs = run_query_once(&mut tx, sql_patt, args, Some(num_inserting)).await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+} // end of impl StoreShippingPostgreSqlRepo
+
+impl StoreShippingPostgreSqlRepo {
+    pub fn try_build(dstores: &[Arc<AppPostgreSqlStore>]) -> DefaultResult<Self, AppError> {
+        let _db = dstores.first().cloned().ok_or(AppError {
+            code: AppErrorCode::MissingDataStore,
+            detail: Some("postgres".to_string()),
+        })?;
+        Ok(Self { _db })
+    }
+}
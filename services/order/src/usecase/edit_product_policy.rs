@@ -1,24 +1,27 @@
 use std::collections::hash_map::RandomState;
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashSet};
 use std::future::Future;
 use std::result::Result as DefaultResult;
 use std::sync::Arc;
 use std::vec::Vec;
 
-use chrono::Local;
+use chrono::{DateTime, Local};
 use serde::{Deserialize, Serialize};
 
+use ecommerce_common::api::rpc::dto::{StoreProfileReplicaDto, StoreProfileReplicaReqDto};
 use ecommerce_common::api::web::dto::QuotaResourceErrorDto;
 use ecommerce_common::error::AppErrorCode;
 use ecommerce_common::logging::{app_log_event, AppLogContext, AppLogLevel};
 
 use crate::error::AppError;
 use crate::model::ProductPolicyModelSet;
-use crate::repository::app_repo_product_policy;
+use crate::repository::{app_repo_product_policy, AbstProductPolicyRepo};
 use crate::rpc::{AbstractRpcContext, AppRpcClientReqProperty};
 use crate::{AppAuthPermissionCode, AppAuthQuotaMatCode, AppAuthedClaim, AppDataStoreContext};
 
-use crate::api::web::dto::{ProductPolicyClientErrorDto, ProductPolicyDto};
+use crate::api::web::dto::{
+    ProductPolicyClientErrorDto, ProductPolicyDto, ProductPolicyHistoryRespDto,
+};
 
 use super::{initiate_rpc_request, AppUCrunRPCfn, AppUseKsRPCreply};
 
@@ -49,6 +52,9 @@ pub enum EditProductPolicyResult {
 }
 
 impl EditProductPolicyUseCase {
+    /// the route has to be bound in RPC configuration, with reply queue
+    pub const RPC_ROUTE_STORE_PROFILE: &'static str = "rpc.storefront.get_profile";
+
     fn validate_permission_quota(
         authed_usr: &AppAuthedClaim,
         num_items: usize,
@@ -83,12 +89,45 @@ impl EditProductPolicyUseCase {
             dstore,
             rpc_serialize_msg,
             rpc_deserialize_msg,
+            rpc_serialize_store_msg,
+            rpc_deserialize_store_msg,
         } = self;
         if let Err(ce) = ProductPolicyModelSet::validate(&data) {
             return EditProductPolicyResult::ClientError(ce);
         }
         let usr_prof_id = authed_usr.profile;
         let rpctype = rpc_ctx.label();
+        let store_ids = data.iter().map(|d| d.seller_id).collect::<Vec<_>>();
+        let result = Self::check_store_staff(
+            &store_ids,
+            usr_prof_id,
+            rpc_ctx.clone(),
+            initiate_rpc_request,
+            rpc_serialize_store_msg,
+            rpc_deserialize_store_msg,
+        )
+        .await;
+        if let Err((code, detail)) = result {
+            if code == EditProductPolicyResult::Other(AppErrorCode::RpcRemoteInvalidReply)
+                && rpctype == "dummy"
+            {
+                app_log_event!(log, AppLogLevel::WARNING, "dummy-rpc-applied");
+            } else {
+                app_log_event!(log, AppLogLevel::ERROR, "detail:{:?}", detail);
+                return code;
+            }
+        } else if let Ok(denied_store_ids) = result {
+            if !denied_store_ids.is_empty() {
+                app_log_event!(
+                    log,
+                    AppLogLevel::WARNING,
+                    "usr:{}, denied_store_ids:{:?}",
+                    usr_prof_id,
+                    denied_store_ids
+                );
+                return EditProductPolicyResult::PermissionDeny;
+            }
+        }
         let result = Self::check_product_existence(
             &data,
             usr_prof_id,
@@ -116,10 +155,12 @@ impl EditProductPolicyUseCase {
                     "missing_prod_ids:{:?}",
                     missing_prod_ids
                 );
-                let c_err = missing_prod_ids
-                    .into_iter()
-                    .map(|product_id| ProductPolicyClientErrorDto {
-                        product_id,
+                let c_err = data
+                    .iter()
+                    .filter(|d| missing_prod_ids.contains(&d.product_id))
+                    .map(|d| ProductPolicyClientErrorDto {
+                        seller_id: d.seller_id,
+                        product_id: d.product_id,
                         err_type: format!("{:?}", AppErrorCode::ProductNotExist),
                         warranty_hours: None,
                         auto_cancel_secs: None,
@@ -130,7 +171,8 @@ impl EditProductPolicyUseCase {
             }
         }
         if let Err(e) = Self::_save_to_repo(dstore, data).await {
-            // no need to pass `usr_prof_id`, the product ownership should be verified by previous RPC
+            // no need to pass `usr_prof_id`, the store staff and product existence
+            // have been verified by previous RPC
             app_log_event!(log, AppLogLevel::ERROR, "error:{:?}", e);
             EditProductPolicyResult::Other(e.code)
         } else {
@@ -138,8 +180,59 @@ impl EditProductPolicyUseCase {
         }
     } // end of _execute
 
-    // TODO, check whether the user has permission to edit specific product, this relies
-    // on validation by RPC call to `storefront` service
+    /// returns IDs of the given stores which the user neither supervises nor works
+    /// for as a staff, the store profiles are fetched from `storefront` service,
+    /// the `seller_id` sent by client cannot be trusted.
+    pub async fn check_store_staff(
+        store_ids: &[u32],
+        usr_prof_id: u32,
+        rpc_ctx: Arc<Box<dyn AbstractRpcContext>>,
+        run_rpc_fn: AppUCrunRPCfn<impl Future<Output = AppUseKsRPCreply>>,
+        rpc_serialize_msg: fn(StoreProfileReplicaReqDto) -> DefaultResult<Vec<u8>, AppError>,
+        rpc_deserialize_msg: fn(&Vec<u8>) -> DefaultResult<StoreProfileReplicaDto, AppError>,
+    ) -> DefaultResult<Vec<u32>, (EditProductPolicyResult, String)> {
+        let store_ids = store_ids.iter().copied().collect::<BTreeSet<_>>();
+        let t_now = Local::now().fixed_offset();
+        let mut denied = Vec::new();
+        for store_id in store_ids {
+            let msgbody =
+                rpc_serialize_msg(StoreProfileReplicaReqDto { store_id }).map_err(|e| {
+                    let detail = format!("app-error: {:?}", e);
+                    (EditProductPolicyResult::Other(e.code), detail)
+                })?;
+            let properties = AppRpcClientReqProperty {
+                msgbody,
+                correlation_id: None,
+                start_time: t_now,
+                route: Self::RPC_ROUTE_STORE_PROFILE.to_string(),
+            };
+            let reply = run_rpc_fn(rpc_ctx.clone(), properties).await.map_err(|e| {
+                let detail = format!("rpc-error: {:?}", e);
+                (EditProductPolicyResult::Other(e.code), detail)
+            })?;
+            let store_prof = rpc_deserialize_msg(&reply.body).map_err(|e| {
+                let detail = format!("rpc-reply-decode-error: {:?}", e);
+                (EditProductPolicyResult::Other(e.code), detail)
+            })?;
+            let is_staff = store_prof.supervisor_id == usr_prof_id
+                || store_prof.staff.as_ref().is_some_and(|vs| {
+                    vs.iter().any(|v| {
+                        let t0 = DateTime::parse_from_rfc3339(v.start_after.as_str());
+                        let t1 = DateTime::parse_from_rfc3339(v.end_before.as_str());
+                        match (t0, t1) {
+                            (Ok(t0), Ok(t1)) => {
+                                v.staff_id == usr_prof_id && t0 < t_now && t_now < t1
+                            }
+                            _others => false,
+                        }
+                    })
+                });
+            if !is_staff {
+                denied.push(store_id);
+            }
+        }
+        Ok(denied)
+    } // end of fn check_store_staff
 
     pub async fn check_product_existence(
         data: &[ProductPolicyDto],
@@ -199,9 +292,9 @@ impl EditProductPolicyUseCase {
         data: Vec<ProductPolicyDto>,
     ) -> DefaultResult<(), AppError> {
         let repo = app_repo_product_policy(ds).await?;
-        let ids = data.iter().map(|d| d.product_id).collect();
+        let ids = data.iter().map(|d| (d.seller_id, d.product_id)).collect();
         let previous_saved = repo.fetch(ids).await?;
-        let updated = previous_saved.update(data)?;
+        let updated = previous_saved.update(data, Local::now().fixed_offset())?;
         repo.save(updated).await?;
         Ok(())
     }
@@ -215,4 +308,78 @@ pub struct EditProductPolicyUseCase {
     pub data: Vec<ProductPolicyDto>,
     pub rpc_serialize_msg: fn(ProductInfoReq) -> DefaultResult<Vec<u8>, AppError>,
     pub rpc_deserialize_msg: fn(&Vec<u8>) -> DefaultResult<ProductInfoResp, AppError>,
+    pub rpc_serialize_store_msg: fn(StoreProfileReplicaReqDto) -> DefaultResult<Vec<u8>, AppError>,
+    pub rpc_deserialize_store_msg: fn(&Vec<u8>) -> DefaultResult<StoreProfileReplicaDto, AppError>,
+}
+
+pub enum ProductPolicyHistoryUcOutput {
+    Success(ProductPolicyHistoryRespDto),
+    NotFound,
+    PermissionDeny,
+}
+
+pub struct ProductPolicyHistoryUseCase {
+    pub repo: Box<dyn AbstProductPolicyRepo>,
+    pub log: Arc<AppLogContext>,
+    pub rpc_ctx: Arc<Box<dyn AbstractRpcContext>>,
+    pub authed_usr: AppAuthedClaim,
+    pub rpc_serialize_msg: fn(StoreProfileReplicaReqDto) -> DefaultResult<Vec<u8>, AppError>,
+    pub rpc_deserialize_msg: fn(&Vec<u8>) -> DefaultResult<StoreProfileReplicaDto, AppError>,
+}
+
+impl ProductPolicyHistoryUseCase {
+    pub async fn execute(
+        self,
+        store_id: u32,
+        product_id: u64,
+    ) -> DefaultResult<ProductPolicyHistoryUcOutput, AppError> {
+        // the history exposes the editors of the policies, only the users who are
+        // able to edit the policies of the store can read it
+        let perm_allowed = self
+            .authed_usr
+            .contain_permission(AppAuthPermissionCode::can_create_product_policy);
+        if !perm_allowed {
+            return Ok(ProductPolicyHistoryUcOutput::PermissionDeny);
+        }
+        let usr_prof_id = self.authed_usr.profile;
+        let rpctype = self.rpc_ctx.label();
+        let result = EditProductPolicyUseCase::check_store_staff(
+            &[store_id],
+            usr_prof_id,
+            self.rpc_ctx,
+            initiate_rpc_request,
+            self.rpc_serialize_msg,
+            self.rpc_deserialize_msg,
+        )
+        .await;
+        match result {
+            Ok(denied_store_ids) => {
+                if !denied_store_ids.is_empty() {
+                    return Ok(ProductPolicyHistoryUcOutput::PermissionDeny);
+                }
+            }
+            Err((EditProductPolicyResult::Other(code), detail)) => {
+                if code == AppErrorCode::RpcRemoteInvalidReply && rpctype == "dummy" {
+                    let log = &self.log;
+                    app_log_event!(log, AppLogLevel::WARNING, "dummy-rpc-applied");
+                } else {
+                    let detail = Some(detail);
+                    return Err(AppError { code, detail });
+                }
+            }
+            Err((_others, detail)) => {
+                let code = AppErrorCode::Unknown;
+                let detail = Some(detail);
+                return Err(AppError { code, detail });
+            }
+        }
+        let ms = self.repo.fetch_history(store_id, product_id).await?;
+        let out = if ms.policies.is_empty() {
+            ProductPolicyHistoryUcOutput::NotFound
+        } else {
+            let resp = ms.history_dto(store_id, product_id);
+            ProductPolicyHistoryUcOutput::Success(resp)
+        };
+        Ok(out)
+    }
 }
@@ -0,0 +1,37 @@
+use std::boxed::Box;
+use std::result::Result as DefaultResult;
+use std::sync::Arc;
+
+use ecommerce_common::logging::{app_log_event, AppLogContext, AppLogLevel};
+
+use crate::api::rpc::dto::StoreShippingSyncDto;
+use crate::error::AppError;
+use crate::model::StoreShippingModel;
+use crate::repository::AbsStoreShippingRepo;
+
+pub struct EditStoreShippingUseCase {}
+
+impl EditStoreShippingUseCase {
+    pub async fn execute(
+        repo: Box<dyn AbsStoreShippingRepo>,
+        data: StoreShippingSyncDto,
+        logctx: Arc<AppLogContext>,
+    ) -> DefaultResult<(), AppError> {
+        let (store_id, num_methods) = (data.store_id, data.methods.len());
+        let result = match StoreShippingModel::try_from(data) {
+            Ok(m) => repo.save(m).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = &result {
+            app_log_event!(
+                logctx,
+                AppLogLevel::ERROR,
+                "detail:{}, store:{}, num_methods:{}",
+                e,
+                store_id,
+                num_methods
+            );
+        }
+        result
+    }
+}
@@ -2,16 +2,26 @@ use std::boxed::Box;
 use std::result::Result as DefaultResult;
 use std::sync::Arc;
 
+use ecommerce_common::api::dto::CurrencyDto;
 use ecommerce_common::api::web::dto::QuotaResourceErrorDto;
+use ecommerce_common::error::AppErrorCode;
 use ecommerce_common::logging::{app_log_event, AppLogContext, AppLogLevel};
 use ecommerce_common::model::BaseProductIdentity;
 
-use crate::api::web::dto::CartDto;
+use crate::api::web::dto::{
+    CartAccessDto, CartCheckoutReqDto, CartDto, CartPreviewRespDto, CartShareDto,
+    CartShareErrorDto, OrderCreateReqData, OrderCreateRespOkDto,
+};
 use crate::constant::hard_limit;
 use crate::error::AppError;
-use crate::repository::AbsCartRepo;
+use crate::model::{CartModel, CartShareModel, ProductPolicyModelSet, StockLevelModelSet};
+use crate::repository::{
+    AbsCartRepo, AbsCurrencyRepo, AbsOrderStockRepo, AbsProductPriceRepo, AbstProductPolicyRepo,
+};
 use crate::{AppAuthQuotaMatCode, AppAuthedClaim};
 
+use super::{CreateOrderUsKsErr, CreateOrderUseCase};
+
 pub(crate) struct ModifyCartLineUseCase {
     pub repo: Box<dyn AbsCartRepo>,
     pub log_ctx: Arc<AppLogContext>,
@@ -25,30 +35,106 @@ pub(crate) struct RetrieveCartUseCase {
     pub repo: Box<dyn AbsCartRepo>,
     pub authed_usr: AppAuthedClaim,
 }
+pub(crate) struct PreviewCartUseCase {
+    pub repo_cart: Box<dyn AbsCartRepo>,
+    pub repo_currex: Box<dyn AbsCurrencyRepo>,
+    pub repo_price: Box<dyn AbsProductPriceRepo>,
+    pub repo_policy: Box<dyn AbstProductPolicyRepo>,
+    pub repo_stock: Arc<Box<dyn AbsOrderStockRepo>>,
+    pub authed_usr: AppAuthedClaim,
+}
+pub(crate) struct ShareCartUseCase {
+    pub repo: Box<dyn AbsCartRepo>,
+    pub authed_usr: AppAuthedClaim,
+}
+pub(crate) struct RetrieveCartShareUseCase {
+    pub repo: Box<dyn AbsCartRepo>,
+    pub authed_usr: AppAuthedClaim,
+}
+pub(crate) struct CheckoutCartUseCase {
+    pub repo: Box<dyn AbsCartRepo>,
+    pub log_ctx: Arc<AppLogContext>,
+    // the cart owner is the authenticated user kept in the order use case
+    pub uc_create_order: CreateOrderUseCase,
+}
 
 pub(crate) enum ModifyCartUsKsResult {
     Success,
     NotFound,
+    PermissionDenied,
     QuotaExceed(QuotaResourceErrorDto),
     ServerError(AppError),
 }
 pub(crate) enum DiscardCartUsKsResult {
     Success,
     NotFound,
+    PermissionDenied,
     ServerError(AppError),
 }
 pub(crate) enum RetrieveCartUsKsResult {
     Success(CartDto),
     NotFound,
+    PermissionDenied,
+    ServerError(AppError),
+}
+pub(crate) enum ShareCartUsKsResult {
+    Success,
+    NotFound,
+    InvalidReq(CartShareErrorDto),
+    QuotaExceed(QuotaResourceErrorDto),
+    ServerError(AppError),
+}
+pub(crate) enum RetrieveCartShareUsKsResult {
+    Success(CartShareDto),
+    NotFound,
+    ServerError(AppError),
+}
+pub(crate) enum PreviewCartUsKsResult {
+    Success(CartPreviewRespDto),
+    NotFound,
     ServerError(AppError),
 }
+pub(crate) enum CheckoutCartUsKsResult {
+    Success(OrderCreateRespOkDto),
+    NotFound,
+    CreateOrderError(CreateOrderUsKsErr),
+}
+
+// the authenticated user can access a cart owned by someone else only if
+// the owner granted sufficient access to the user
+async fn cart_access_permitted(
+    repo: &dyn AbsCartRepo,
+    usr_id: u32,
+    owner: u32,
+    seq_num: u8,
+    required: CartAccessDto,
+) -> DefaultResult<bool, AppError> {
+    if usr_id == owner {
+        Ok(true)
+    } else {
+        let share = repo.fetch_share(owner, seq_num).await?;
+        Ok(share.permitted(usr_id, required))
+    }
+}
 
 impl ModifyCartLineUseCase {
-    pub(crate) async fn execute(self, seq_num: u8, data: CartDto) -> ModifyCartUsKsResult {
+    pub(crate) async fn execute(
+        self,
+        owner: u32,
+        seq_num: u8,
+        data: CartDto,
+    ) -> ModifyCartUsKsResult {
         if seq_num >= hard_limit::MAX_NUM_CARTS_PER_USER {
             return ModifyCartUsKsResult::NotFound;
         }
-        match self.validate_update(seq_num, data).await {
+        let usr_id = self.authed_usr.profile;
+        let required = CartAccessDto::Edit;
+        match cart_access_permitted(self.repo.as_ref(), usr_id, owner, seq_num, required).await {
+            Ok(true) => {}
+            Ok(false) => return ModifyCartUsKsResult::PermissionDenied,
+            Err(e) => return ModifyCartUsKsResult::ServerError(e),
+        }
+        match self.validate_update(owner, seq_num, data).await {
             Ok(v) => match v {
                 Some(e) => ModifyCartUsKsResult::QuotaExceed(e),
                 None => ModifyCartUsKsResult::Success,
@@ -59,10 +145,10 @@ impl ModifyCartLineUseCase {
 
     async fn validate_update(
         &self,
+        owner: u32,
         seq_num: u8,
         data: CartDto,
     ) -> DefaultResult<Option<QuotaResourceErrorDto>, AppError> {
-        let owner = self.authed_usr.profile;
         let pids = data
             .lines
             .iter()
@@ -84,6 +170,8 @@ impl ModifyCartLineUseCase {
         );
         let num_saved = self.repo.num_lines_saved(owner, seq_num).await?;
         let total_num_lines = num_saved + obj.new_lines.len();
+        // quota of the user who edits the cart is applied, which is not
+        // always the owner
         let max_limit = self
             .authed_usr
             .quota_limit(AppAuthQuotaMatCode::NumOrderLines);
@@ -101,29 +189,198 @@ impl ModifyCartLineUseCase {
 } // end of impl ModifyCartLineUseCase
 
 impl DiscardCartUseCase {
-    pub(crate) async fn execute(self, seq_num: u8) -> DiscardCartUsKsResult {
-        if seq_num < hard_limit::MAX_NUM_CARTS_PER_USER {
-            let owner = self.authed_usr.profile;
-            match self.repo.discard(owner, seq_num).await {
-                Ok(_v) => DiscardCartUsKsResult::Success,
-                Err(e) => DiscardCartUsKsResult::ServerError(e),
-            }
-        } else {
-            DiscardCartUsKsResult::NotFound
+    pub(crate) async fn execute(self, owner: u32, seq_num: u8) -> DiscardCartUsKsResult {
+        if seq_num >= hard_limit::MAX_NUM_CARTS_PER_USER {
+            return DiscardCartUsKsResult::NotFound;
+        }
+        // collaborators cannot discard the cart even with edit access,
+        // the access granted to them is revoked along with the cart
+        if self.authed_usr.profile != owner {
+            return DiscardCartUsKsResult::PermissionDenied;
+        }
+        match self.repo.discard(owner, seq_num).await {
+            Ok(_v) => DiscardCartUsKsResult::Success,
+            Err(e) => DiscardCartUsKsResult::ServerError(e),
         }
     }
 }
 
 impl RetrieveCartUseCase {
-    pub(crate) async fn execute(self, seq_num: u8) -> RetrieveCartUsKsResult {
-        if seq_num < hard_limit::MAX_NUM_CARTS_PER_USER {
-            let owner = self.authed_usr.profile;
-            match self.repo.fetch_cart(owner, seq_num).await {
+    pub(crate) async fn execute(self, owner: u32, seq_num: u8) -> RetrieveCartUsKsResult {
+        if seq_num >= hard_limit::MAX_NUM_CARTS_PER_USER {
+            return RetrieveCartUsKsResult::NotFound;
+        }
+        let usr_id = self.authed_usr.profile;
+        let required = CartAccessDto::Read;
+        match cart_access_permitted(self.repo.as_ref(), usr_id, owner, seq_num, required).await {
+            Ok(true) => match self.repo.fetch_cart(owner, seq_num).await {
                 Ok(m) => RetrieveCartUsKsResult::Success(m.into()),
                 Err(e) => RetrieveCartUsKsResult::ServerError(e),
-            }
-        } else {
-            RetrieveCartUsKsResult::NotFound
+            },
+            Ok(false) => RetrieveCartUsKsResult::PermissionDenied,
+            Err(e) => RetrieveCartUsKsResult::ServerError(e),
         }
     }
 }
+
+// only the owner can view or change the list of users sharing the cart
+impl ShareCartUseCase {
+    pub(crate) async fn execute(self, seq_num: u8, data: CartShareDto) -> ShareCartUsKsResult {
+        if seq_num >= hard_limit::MAX_NUM_CARTS_PER_USER {
+            return ShareCartUsKsResult::NotFound;
+        }
+        let owner = self.authed_usr.profile;
+        let obj = match CartShareModel::try_from_req(owner, seq_num, data) {
+            Ok(v) => v,
+            Err(e) => return ShareCartUsKsResult::InvalidReq(e),
+        };
+        let max_limit = self
+            .authed_usr
+            .quota_limit(AppAuthQuotaMatCode::NumCartCollaborators);
+        let num_collaborators = obj.collaborators.len();
+        if num_collaborators > (max_limit as usize) {
+            let e = QuotaResourceErrorDto {
+                given: num_collaborators,
+                max_: max_limit,
+            };
+            return ShareCartUsKsResult::QuotaExceed(e);
+        }
+        match self.repo.update_share(obj).await {
+            Ok(_v) => ShareCartUsKsResult::Success,
+            Err(e) => ShareCartUsKsResult::ServerError(e),
+        }
+    }
+}
+
+impl RetrieveCartShareUseCase {
+    pub(crate) async fn execute(self, seq_num: u8) -> RetrieveCartShareUsKsResult {
+        if seq_num >= hard_limit::MAX_NUM_CARTS_PER_USER {
+            return RetrieveCartShareUsKsResult::NotFound;
+        }
+        let owner = self.authed_usr.profile;
+        match self.repo.fetch_share(owner, seq_num).await {
+            Ok(m) => RetrieveCartShareUsKsResult::Success(m.into()),
+            Err(e) => RetrieveCartShareUsKsResult::ServerError(e),
+        }
+    }
+}
+
+impl PreviewCartUseCase {
+    pub(crate) async fn execute(self, seq_num: u8, currency: CurrencyDto) -> PreviewCartUsKsResult {
+        if seq_num >= hard_limit::MAX_NUM_CARTS_PER_USER {
+            return PreviewCartUsKsResult::NotFound;
+        }
+        let owner = self.authed_usr.profile;
+        let result = match self.repo_cart.fetch_cart(owner, seq_num).await {
+            Ok(cart) => self.enrich(cart, currency).await,
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(v) => PreviewCartUsKsResult::Success(v),
+            Err(e) => PreviewCartUsKsResult::ServerError(e),
+        }
+    }
+
+    async fn enrich(
+        &self,
+        cart: CartModel,
+        currency: CurrencyDto,
+    ) -> DefaultResult<CartPreviewRespDto, AppError> {
+        let mut req_ids = cart
+            .saved_lines
+            .iter()
+            .map(|l| (l.id_.store_id, l.id_.product_id))
+            .collect::<Vec<_>>();
+        req_ids.sort();
+        req_ids.dedup();
+        let (ms_policy, ms_price, ms_stock) = if req_ids.is_empty() {
+            let ms_policy = ProductPolicyModelSet {
+                policies: Vec::new(),
+            };
+            let ms_stock = StockLevelModelSet { stores: Vec::new() };
+            (ms_policy, Vec::new(), ms_stock)
+        } else {
+            let pids = req_ids
+                .iter()
+                .map(|(store_id, product_id)| BaseProductIdentity {
+                    store_id: *store_id,
+                    product_id: *product_id,
+                })
+                .collect::<Vec<_>>();
+            let ms_policy = self.repo_policy.fetch(req_ids.clone()).await?;
+            let ms_price = self.repo_price.fetch_many(req_ids).await?;
+            let ms_stock = self.repo_stock.fetch_avail(pids).await?;
+            (ms_policy, ms_price, ms_stock)
+        };
+        let currency_m =
+            CreateOrderUseCase::snapshot_currencies(self.repo_currex.as_ref(), currency, &ms_price)
+                .await
+                .map_err(|mut es| es.remove(0))?;
+        cart.preview(&ms_policy, &ms_price, &ms_stock, &currency_m)
+    }
+} // end of impl PreviewCartUseCase
+
+impl CheckoutCartUseCase {
+    pub(crate) async fn execute(
+        self,
+        seq_num: u8,
+        req: CartCheckoutReqDto,
+    ) -> CheckoutCartUsKsResult {
+        if seq_num >= hard_limit::MAX_NUM_CARTS_PER_USER {
+            return CheckoutCartUsKsResult::NotFound;
+        }
+        let owner = self.uc_create_order.auth_claim.profile;
+        // an unknown cart is loaded as an empty one, or reported as missing data
+        // by some datastores, both should be treated as not found
+        let mut cart = match self.repo.fetch_cart(owner, seq_num).await {
+            Ok(v) => v,
+            Err(e) if e.code == AppErrorCode::DataTableNotExist => {
+                return CheckoutCartUsKsResult::NotFound;
+            }
+            Err(e) => {
+                let e = CreateOrderUsKsErr::Server(vec![e]);
+                return CheckoutCartUsKsResult::CreateOrderError(e);
+            }
+        };
+        let order_lines = cart.checkout_lines();
+        if order_lines.is_empty() {
+            return CheckoutCartUsKsResult::NotFound;
+        }
+        let CartCheckoutReqDto {
+            currency,
+            billing,
+            shipping,
+        } = req;
+        let o_req = OrderCreateReqData {
+            order_lines,
+            currency,
+            billing,
+            shipping,
+        };
+        // the cart is left intact if any of the lines cannot be purchased,
+        // the per-line errors are reported by the order creation
+        let resp = match self.uc_create_order.execute(o_req).await {
+            Ok(v) => v,
+            Err(e) => return CheckoutCartUsKsResult::CreateOrderError(e),
+        };
+        // the order has been created at this point, failing to clean up the
+        // converted lines should not turn the checkout into an error
+        let logctx = &self.log_ctx;
+        match self.repo.remove_lines(cart).await {
+            Ok(num) => app_log_event!(
+                logctx,
+                AppLogLevel::DEBUG,
+                "seq_num:{seq_num}, oid:{}, num-lines-removed:{num}",
+                resp.order_id
+            ),
+            Err(e) => app_log_event!(
+                logctx,
+                AppLogLevel::ERROR,
+                "seq_num:{seq_num}, oid:{}, error:{:?}",
+                resp.order_id,
+                e
+            ),
+        }
+        CheckoutCartUsKsResult::Success(resp)
+    } // end of fn execute
+} // end of impl CheckoutCartUseCase
@@ -5,7 +5,7 @@ use std::pin::Pin;
 use std::result::Result as DefaultResult;
 use std::sync::Arc;
 
-use chrono::{DateTime, FixedOffset, Local as LocalTime};
+use chrono::{DateTime, Duration, FixedOffset, Local as LocalTime};
 use ecommerce_common::api::dto::{CurrencyDto, GenericRangeErrorDto};
 use ecommerce_common::api::rpc::dto::{
     OrderLinePayUpdateErrorDto, OrderLineReplicaRefundDto, OrderPaymentUpdateDto,
@@ -18,12 +18,14 @@ use ecommerce_common::api::web::dto::{
 use crate::api::rpc::dto::{
     InventoryEditStockLevelDto, OrderLineStockReturningDto, OrderReplicaInventoryDto,
     OrderReplicaInventoryReqDto, OrderReplicaStockReservingDto, OrderReplicaStockReturningDto,
-    StockLevelReturnDto, StockReturnErrorDto,
+    ShipmentCreateReqDto, ShipmentErrorDto, StockLevelReturnDto, StockReturnErrorDto,
 };
 use crate::api::web::dto::{
     BillingReqDto, OrderCreateReqData, OrderCreateRespErrorDto, OrderCreateRespOkDto,
+    OrderDetailRespDto, OrderLineCancelErrorDto, OrderLineCancelReqDto,
     OrderLineCreateErrNonExistDto, OrderLineCreateErrorDto, OrderLineCreateErrorReason,
-    OrderLineReturnErrorDto, OrderLineReturnReqDto, OrderLineRsvReqDto, ShippingErrorDto,
+    OrderLineReturnErrorDto, OrderLineReturnReqDto, OrderLineRsvReqDto, OrderListRespDto,
+    OrderSummaryDto, ShipmentDto, ShipmentReceiptErrorDto, ShipmentReceiptReqDto, ShippingErrorDto,
     ShippingReqDto,
 };
 
@@ -31,16 +33,16 @@ use ecommerce_common::error::AppErrorCode;
 use ecommerce_common::logging::{app_log_event, AppLogContext, AppLogLevel};
 use ecommerce_common::model::order::BillingModel;
 
-use crate::constant::app_meta;
+use crate::constant::{app_meta, hard_limit};
 use crate::error::AppError;
 use crate::model::{
     OlineDupError, OrderCurrencyModel, OrderLineIdentity, OrderLineModel, OrderLineModelSet,
-    OrderReturnModel, ProductPolicyModelSet, ProductPriceModelSet, ShippingModel,
+    OrderReturnModel, ProductPolicyModelSet, ProductPriceModelSet, ShipmentModel, ShippingModel,
     StockLevelModelSet,
 };
 use crate::repository::{
-    AbsCurrencyRepo, AbsOrderRepo, AbsOrderReturnRepo, AbsProductPriceRepo, AbstProductPolicyRepo,
-    AppStockRepoReserveReturn,
+    AbsCurrencyRepo, AbsOrderRepo, AbsOrderReturnRepo, AbsProductPriceRepo, AbsStoreShippingRepo,
+    AbstProductPolicyRepo, AppStockRepoReserveReturn,
 };
 use crate::{AppAuthPermissionCode, AppAuthQuotaMatCode, AppAuthedClaim, AppSharedState};
 
@@ -58,6 +60,7 @@ pub struct CreateOrderUseCase {
     pub repo_order: Box<dyn AbsOrderRepo>,
     pub repo_price: Box<dyn AbsProductPriceRepo>,
     pub repo_policy: Box<dyn AbstProductPolicyRepo>,
+    pub repo_shipping: Box<dyn AbsStoreShippingRepo>,
     pub auth_claim: AppAuthedClaim,
 }
 
@@ -86,6 +89,30 @@ pub struct ReturnLinesReqUseCase {
     pub or_repo: Box<dyn AbsOrderReturnRepo>,
     pub logctx: Arc<AppLogContext>,
 }
+pub struct CancelUnpaidLinesUseCase {
+    pub authed_claim: AppAuthedClaim,
+    pub o_repo: Box<dyn AbsOrderRepo>,
+    pub logctx: Arc<AppLogContext>,
+}
+pub struct OrderRetrieveUseCase {
+    pub authed_claim: AppAuthedClaim,
+    pub o_repo: Box<dyn AbsOrderRepo>,
+}
+pub struct OrderListUseCase {
+    pub authed_claim: AppAuthedClaim,
+    pub o_repo: Box<dyn AbsOrderRepo>,
+}
+pub struct OrderShipmentCreateUseCase {
+    pub repo: Box<dyn AbsOrderRepo>,
+}
+pub struct OrderShipmentListUseCase {
+    pub authed_claim: AppAuthedClaim,
+    pub o_repo: Box<dyn AbsOrderRepo>,
+}
+pub struct OrderShipmentReceiptUseCase {
+    pub authed_claim: AppAuthedClaim,
+    pub o_repo: Box<dyn AbsOrderRepo>,
+}
 
 impl CreateOrderUseCase {
     pub async fn execute(
@@ -107,7 +134,7 @@ impl CreateOrderUseCase {
             bl_d.contact.phones.len(),
             ol_d.len(),
         )?;
-        let (o_bl, o_sh) = Self::validate_metadata(sh_d, bl_d)?;
+        let (o_bl, o_sh) = self.validate_metadata(sh_d, bl_d).await?;
         let (ms_policy, ms_price) = self.load_product_properties(&ol_d).await?;
         let o_currency =
             Self::snapshot_currencies(self.repo_currex.as_ref(), currency_buyer, &ms_price)
@@ -213,12 +240,32 @@ impl CreateOrderUseCase {
         }
     } // end of fn validate_quota
 
-    fn validate_metadata(
+    async fn validate_metadata(
+        &self,
         sh_d: ShippingReqDto,
         bl_d: BillingReqDto,
     ) -> DefaultResult<(BillingModel, ShippingModel), CreateOrderUsKsErr> {
         let results = (BillingModel::try_from(bl_d), ShippingModel::try_from(sh_d));
         if let (Ok(billing), Ok(shipping)) = results {
+            // sellers have to support the delivery services chosen by the buyer
+            let store_ids = shipping.option.iter().map(|o| o.seller_id).collect();
+            let saved = self.repo_shipping.fetch(store_ids).await.map_err(|e| {
+                let logctx_p = self.glb_state.log_context();
+                app_log_event!(logctx_p, AppLogLevel::ERROR, "repo-fail-shipping: {e}");
+                CreateOrderUsKsErr::Server(vec![e])
+            })?;
+            shipping.validate_options(&saved).map_err(|es| {
+                let e = ShippingErrorDto {
+                    contact: None,
+                    address: None,
+                    option: Some(es),
+                };
+                let err_obj = OrderCreateRespErrorDto {
+                    shipping: Some(e),
+                    ..Default::default()
+                };
+                CreateOrderUsKsErr::ReqContent(Box::new(err_obj))
+            })?;
             Ok((billing, shipping))
         } else {
             let mut err_obj = OrderCreateRespErrorDto::default();
@@ -236,14 +283,13 @@ impl CreateOrderUseCase {
         &self,
         data: &[OrderLineRsvReqDto],
     ) -> DefaultResult<(ProductPolicyModelSet, Vec<ProductPriceModelSet>), CreateOrderUsKsErr> {
-        let req_ids_policy = data.iter().map(|d| d.product_id).collect::<Vec<u64>>();
-        let req_ids_price = data
+        let req_ids = data
             .iter()
             .map(|d| (d.seller_id, d.product_id))
             .collect::<Vec<(u32, u64)>>();
         // TODO, limit number of distinct product items to load for each order
-        let rs_policy = self.repo_policy.fetch(req_ids_policy.clone()).await;
-        let rs_price = self.repo_price.fetch_many(req_ids_price.clone()).await;
+        let rs_policy = self.repo_policy.fetch(req_ids.clone()).await;
+        let rs_price = self.repo_price.fetch_many(req_ids).await;
         if rs_policy.is_ok() && rs_price.is_ok() {
             let (ms_policy, ms_price) = (rs_policy.unwrap(), rs_price.unwrap());
             Ok((ms_policy, ms_price))
@@ -307,7 +353,7 @@ impl CreateOrderUseCase {
                 let result1 = ms_policy
                     .policies
                     .iter()
-                    .find(|m| m.product_id == d.product_id);
+                    .find(|m| m.store_id == d.seller_id && m.product_id == d.product_id);
                 let result2 = ms_price.iter().find_map(|ms| ms.find_product(&d));
                 let (plc_nonexist, price_nonexist) = (result1.is_none(), result2.is_none());
                 if let (Some(plc), Some(price)) = (result1, result2) {
@@ -619,6 +665,17 @@ impl OrderDiscardUnpaidItemsUseCase {
     pub async fn execute(self) -> DefaultResult<(), AppError> {
         let time_start = self.repo.cancel_unpaid_last_time().await?;
         let time_end = LocalTime::now().fixed_offset();
+        // reservations still pending since last run belong to orders which
+        // failed to save, or to cancelled lines whose stock was not returned,
+        // this does not prevent discarding unpaid items below
+        let st_repo = self.repo.stock();
+        let result = st_repo
+            .release_pending_rsv(time_start, Self::read_stocklvl_cb)
+            .await;
+        if let Err(e) = result {
+            let lctx = &self.logctx;
+            app_log_event!(lctx, AppLogLevel::ERROR, "error: {:?}", e);
+        }
         let result = self
             .repo
             .fetch_lines_by_rsvtime(time_start, time_end, Self::read_oline_set_cb)
@@ -703,3 +760,252 @@ impl ReturnLinesReqUseCase {
         }
     }
 } // end of impl ReturnLinesReqUseCase
+
+pub enum CancelUnpaidLinesUcOutput {
+    Success,
+    InvalidOwner,
+    InvalidRequest(Vec<OrderLineCancelErrorDto>),
+}
+
+impl CancelUnpaidLinesUseCase {
+    pub async fn execute(
+        self,
+        oid: String,
+        data: Vec<OrderLineCancelReqDto>,
+    ) -> DefaultResult<CancelUnpaidLinesUcOutput, AppError> {
+        let o_usr_id = self.o_repo.owner_id(oid.as_str()).await?;
+        if o_usr_id != self.authed_claim.profile {
+            return Ok(CancelUnpaidLinesUcOutput::InvalidOwner);
+        }
+        let pids = if data.is_empty() {
+            // cancel all the unpaid lines whose reservation is still valid
+            let time_now = LocalTime::now().fixed_offset();
+            self.o_repo
+                .fetch_all_lines(oid.clone())
+                .await?
+                .into_iter()
+                .filter(|m| {
+                    m.qty.has_unpaid() && m.qty.cancelled == 0 && m.policy.reserved_until > time_now
+                })
+                .map(|m| m.id().clone())
+                .collect::<Vec<_>>()
+        } else {
+            data.into_iter()
+                .map(|d| OrderLineIdentity::from((d.seller_id, d.product_id, d.attr_set_seq)))
+                .collect::<Vec<_>>()
+        };
+        if pids.is_empty() {
+            return Ok(CancelUnpaidLinesUcOutput::Success);
+        }
+        let result = self
+            .o_repo
+            .cancel_unpaid_lines(
+                oid.as_str(),
+                pids,
+                OrderLineModel::cancel_unpaid,
+                OrderDiscardUnpaidItemsUseCase::read_stocklvl_cb,
+            )
+            .await?;
+        let errors = match result {
+            Ok(v) => v,
+            Err(errors) => return Ok(CancelUnpaidLinesUcOutput::InvalidRequest(errors)),
+        };
+        if !errors.is_empty() {
+            let logctx = &self.logctx;
+            app_log_event!(
+                logctx,
+                AppLogLevel::ERROR,
+                "oid:{}, stock-return-errors:{:?}",
+                oid,
+                errors
+            );
+        }
+        Ok(CancelUnpaidLinesUcOutput::Success)
+    } // end of fn execute
+} // end of impl CancelUnpaidLinesUseCase
+
+pub enum OrderRetrieveUcOutput {
+    Success(Box<OrderDetailRespDto>),
+    NotFound,
+    InvalidOwner,
+}
+
+impl OrderRetrieveUseCase {
+    pub async fn execute(self, oid: String) -> DefaultResult<OrderRetrieveUcOutput, AppError> {
+        let o_usr_id = match self.o_repo.owner_id(oid.as_str()).await {
+            Ok(v) => v,
+            Err(e) => {
+                return if Self::is_order_missing(&e) {
+                    Ok(OrderRetrieveUcOutput::NotFound)
+                } else {
+                    Err(e)
+                };
+            }
+        };
+        if o_usr_id != self.authed_claim.profile {
+            return Ok(OrderRetrieveUcOutput::InvalidOwner);
+        }
+        let ctime = self.o_repo.created_time(oid.as_str()).await?;
+        let currency_m = self.o_repo.currency_exrates(oid.as_str()).await?;
+        let olines = self.o_repo.fetch_all_lines(oid.clone()).await?;
+        let billing = self.o_repo.fetch_billing(oid.clone()).await?;
+        let shipping = self.o_repo.fetch_shipping(oid.clone()).await?;
+        let resp = OrderLineModelSet::detail_dto(
+            oid, o_usr_id, ctime, olines, currency_m, billing, shipping,
+        )?;
+        Ok(OrderRetrieveUcOutput::Success(Box::new(resp)))
+    }
+
+    fn is_order_missing(e: &AppError) -> bool {
+        // in-memory repository reports nonexistent order as invalid input, while
+        // SQL database reports it as not-found I/O error
+        matches!(
+            e.code,
+            AppErrorCode::InvalidInput | AppErrorCode::IOerror(std::io::ErrorKind::NotFound)
+        )
+    }
+} // end of impl OrderRetrieveUseCase
+
+pub enum OrderListUcOutput {
+    Success(OrderListRespDto),
+    InvalidRequest,
+}
+
+impl OrderListUseCase {
+    pub async fn execute(
+        self,
+        time_start: DateTime<FixedOffset>,
+        time_end: DateTime<FixedOffset>,
+        page: u32,
+        page_size: u16,
+    ) -> DefaultResult<OrderListUcOutput, AppError> {
+        let max_range = Duration::days(hard_limit::MAX_DAYS_LIST_ORDERS);
+        if time_start >= time_end
+            || (time_end - time_start) > max_range
+            || page == 0
+            || page_size == 0
+            || page_size > hard_limit::MAX_ORDERS_PER_PAGE
+        {
+            return Ok(OrderListUcOutput::InvalidRequest);
+        }
+        let usr_id = self.authed_claim.profile;
+        let num_skip = (page as usize - 1).saturating_mul(page_size as usize);
+        let (total, owned) = self
+            .o_repo
+            .fetch_ids_by_owner_created_time(
+                usr_id,
+                time_start,
+                time_end,
+                num_skip,
+                page_size as usize,
+            )
+            .await?;
+        let orders = owned
+            .into_iter()
+            .map(|(order_id, ctime)| OrderSummaryDto {
+                order_id,
+                time: ctime.to_rfc3339(),
+            })
+            .collect::<Vec<_>>();
+        let resp = OrderListRespDto {
+            total,
+            page,
+            orders,
+        };
+        Ok(OrderListUcOutput::Success(resp))
+    }
+} // end of impl OrderListUseCase
+
+impl OrderShipmentCreateUseCase {
+    pub async fn execute(
+        self,
+        data: ShipmentCreateReqDto,
+    ) -> DefaultResult<DefaultResult<(), ShipmentErrorDto>, AppError> {
+        let oid = data.order_id.clone();
+        let time_now = LocalTime::now().fixed_offset();
+        let shipment = match ShipmentModel::try_from_req(data, time_now) {
+            Ok(v) => v,
+            Err(e) => return Ok(Err(e)),
+        };
+        self.repo
+            .create_shipment(oid.as_str(), shipment, ShipmentModel::validate)
+            .await
+    }
+} // end of impl OrderShipmentCreateUseCase
+
+pub enum OrderShipmentListUcOutput {
+    Success(Vec<ShipmentDto>),
+    NotFound,
+    InvalidOwner,
+}
+
+impl OrderShipmentListUseCase {
+    pub async fn execute(self, oid: String) -> DefaultResult<OrderShipmentListUcOutput, AppError> {
+        let o_usr_id = match self.o_repo.owner_id(oid.as_str()).await {
+            Ok(v) => v,
+            Err(e) => {
+                return if OrderRetrieveUseCase::is_order_missing(&e) {
+                    Ok(OrderShipmentListUcOutput::NotFound)
+                } else {
+                    Err(e)
+                };
+            }
+        };
+        if o_usr_id != self.authed_claim.profile {
+            return Ok(OrderShipmentListUcOutput::InvalidOwner);
+        }
+        let mut ms = self.o_repo.fetch_shipments(oid.as_str()).await?;
+        ms.sort_by_key(|m| m.create_time);
+        let resp = ms.into_iter().map(ShipmentDto::from).collect();
+        Ok(OrderShipmentListUcOutput::Success(resp))
+    }
+} // end of impl OrderShipmentListUseCase
+
+pub enum OrderShipmentReceiptUcOutput {
+    Success,
+    NotFound,
+    InvalidOwner,
+    InvalidRequest(ShipmentReceiptErrorDto),
+}
+
+impl OrderShipmentReceiptUseCase {
+    pub async fn execute(
+        self,
+        oid: String,
+        data: ShipmentReceiptReqDto,
+    ) -> DefaultResult<OrderShipmentReceiptUcOutput, AppError> {
+        let o_usr_id = match self.o_repo.owner_id(oid.as_str()).await {
+            Ok(v) => v,
+            Err(e) => {
+                return if OrderRetrieveUseCase::is_order_missing(&e) {
+                    Ok(OrderShipmentReceiptUcOutput::NotFound)
+                } else {
+                    Err(e)
+                };
+            }
+        };
+        if o_usr_id != self.authed_claim.profile {
+            return Ok(OrderShipmentReceiptUcOutput::InvalidOwner);
+        }
+        let ShipmentReceiptReqDto {
+            seller_id,
+            tracking_num,
+        } = data;
+        let result = self
+            .o_repo
+            .confirm_shipment_receipt(
+                oid.as_str(),
+                seller_id,
+                tracking_num.as_str(),
+                ShipmentModel::confirm_receipt,
+            )
+            .await?;
+        let out = match result {
+            Ok(()) => OrderShipmentReceiptUcOutput::Success,
+            Err(reason) => {
+                OrderShipmentReceiptUcOutput::InvalidRequest(ShipmentReceiptErrorDto { reason })
+            }
+        };
+        Ok(out)
+    }
+} // end of impl OrderShipmentReceiptUseCase
@@ -1,6 +1,7 @@
 mod currency;
 mod edit_product_policy;
 mod edit_product_price;
+mod edit_store_shipping;
 mod manage_cart;
 mod manage_order;
 mod stock_level;
@@ -12,16 +13,23 @@ use std::sync::Arc;
 pub use currency::CurrencyRateRefreshUseCase;
 pub use edit_product_policy::{
     EditProductPolicyResult, EditProductPolicyUseCase, ProductInfoReq, ProductInfoResp,
+    ProductPolicyHistoryUcOutput, ProductPolicyHistoryUseCase,
 };
 pub use edit_product_price::EditProductPriceUseCase;
+pub use edit_store_shipping::EditStoreShippingUseCase;
 pub(crate) use manage_cart::{
-    DiscardCartUsKsResult, DiscardCartUseCase, ModifyCartLineUseCase, ModifyCartUsKsResult,
-    RetrieveCartUsKsResult, RetrieveCartUseCase,
+    CheckoutCartUsKsResult, CheckoutCartUseCase, DiscardCartUsKsResult, DiscardCartUseCase,
+    ModifyCartLineUseCase, ModifyCartUsKsResult, PreviewCartUsKsResult, PreviewCartUseCase,
+    RetrieveCartShareUsKsResult, RetrieveCartShareUseCase, RetrieveCartUsKsResult,
+    RetrieveCartUseCase, ShareCartUsKsResult, ShareCartUseCase,
 };
 pub use manage_order::{
-    CreateOrderUsKsErr, CreateOrderUseCase, OrderDiscardUnpaidItemsUseCase,
-    OrderPaymentUpdateUseCase, OrderReplicaInventoryUseCase, OrderReplicaPaymentUseCase,
-    OrderReplicaRefundUseCase, ReturnLinesReqUcOutput, ReturnLinesReqUseCase,
+    CancelUnpaidLinesUcOutput, CancelUnpaidLinesUseCase, CreateOrderUsKsErr, CreateOrderUseCase,
+    OrderDiscardUnpaidItemsUseCase, OrderListUcOutput, OrderListUseCase, OrderPaymentUpdateUseCase,
+    OrderReplicaInventoryUseCase, OrderReplicaPaymentUseCase, OrderReplicaRefundUseCase,
+    OrderRetrieveUcOutput, OrderRetrieveUseCase, OrderShipmentCreateUseCase,
+    OrderShipmentListUcOutput, OrderShipmentListUseCase, OrderShipmentReceiptUcOutput,
+    OrderShipmentReceiptUseCase, ReturnLinesReqUcOutput, ReturnLinesReqUseCase,
 };
 pub use stock_level::StockLevelUseCase;
 
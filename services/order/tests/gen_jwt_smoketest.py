@@ -26,6 +26,7 @@ def main():
             (APP_CODE, 2, 3),  # max limit AppAuthQuotaMatCode::NumEmails
             (APP_CODE, 3, 10),  # max limit AppAuthQuotaMatCode::NumOrderLines
             (APP_CODE, 4, 11),  # max limit AppAuthQuotaMatCode::NumProductPolicies
+            (APP_CODE, 5, 4),  # max limit AppAuthQuotaMatCode::NumCartCollaborators
         ],
         usr_id=int(mock_app_user_id),
     )
@@ -2,7 +2,7 @@ use std::result::Result as DefaultResult;
 use std::sync::Arc;
 
 use axum_core::body::Body as AxumBody;
-use chrono::{DateTime, Duration, FixedOffset, Local};
+use chrono::{DateTime, Duration, FixedOffset, Local, SecondsFormat, Utc};
 use http::{Request, StatusCode};
 use http_body_util::BodyExt;
 use hyper::body::Bytes as HyperBytes;
@@ -10,9 +10,12 @@ use serde_json::{Map as JsnMap, Value as JsnVal};
 
 use ecommerce_common::api::web::dto::{ContactErrorReason, PhoneNumNationErrorReason};
 
+use order::api::dto::{ShippingMethod, ShippingOptionDto};
 use order::api::rpc;
 use order::api::web::dto::{
-    OrderCreateReqData, OrderCreateRespErrorDto, OrderCreateRespOkDto, OrderEditReqData,
+    OrderCreateReqData, OrderCreateRespErrorDto, OrderCreateRespOkDto, OrderDetailRespDto,
+    OrderEditReqData, OrderListRespDto, ProductPolicyHistoryRespDto, ShipOptionMethodErrorReason,
+    ShipOptionSellerErrorReason,
 };
 use order::constant::{app_meta, hard_limit};
 use order::error::AppError;
@@ -96,6 +99,40 @@ async fn itest_setup_product_policy(
     }
 } // end of fn itest_setup_product_policy
 
+async fn itest_product_policy_history(
+    cfg: Arc<AppConfig>,
+    srv: Arc<Mutex<WebServiceRoute>>,
+    seller_id: u32,
+    product_id: u64,
+    mut authed_claim: AppAuthedClaim,
+    expect_status: StatusCode,
+) -> Option<ProductPolicyHistoryRespDto> {
+    authed_claim.perms.push(AppAuthClaimPermission {
+        app_code: app_meta::RESOURCE_QUOTA_AP_CODE,
+        codename: AppAuthPermissionCode::can_create_product_policy,
+    });
+    let uri = format!(
+        "/{}/policy/products/{}/{}/history",
+        cfg.api_server.listen.api_version, seller_id, product_id
+    );
+    let mut req = Request::builder()
+        .uri(uri)
+        .method("GET")
+        .header("accept", "application/json")
+        .body(AxumBody::empty())
+        .unwrap();
+    let _ = req.extensions_mut().insert(authed_claim);
+    let mut response = TestWebServer::consume(&srv, req).await;
+    assert_eq!(response.status(), expect_status);
+    if expect_status == StatusCode::OK {
+        let result =
+            TestWebServer::to_custom_type::<ProductPolicyHistoryRespDto>(response.body_mut()).await;
+        Some(result.unwrap())
+    } else {
+        None
+    }
+} // end of fn itest_product_policy_history
+
 async fn itest_setup_currency_exrate(shrstate: AppSharedState) {
     let mock_rpc_topic = "rpc.order.currency_exrate_refresh";
     let req = {
@@ -248,6 +285,55 @@ async fn itest_return_olines_request(
     resp_body.into_data().unwrap().to_vec()
 } // end of fn itest_return_olines_request
 
+async fn itest_retrieve_order(
+    cfg: Arc<AppConfig>,
+    srv: Arc<Mutex<WebServiceRoute>>,
+    oid: &str,
+    authed_claim: AppAuthedClaim,
+    expect_status: StatusCode,
+) -> Option<OrderDetailRespDto> {
+    let uri = format!("/{}/order/{}", cfg.api_server.listen.api_version, oid);
+    let mut req = Request::builder()
+        .uri(uri)
+        .method("GET")
+        .header("accept", "application/json")
+        .body(AxumBody::empty())
+        .unwrap();
+    let _ = req.extensions_mut().insert(authed_claim);
+    let mut response = TestWebServer::consume(&srv, req).await;
+    assert_eq!(response.status(), expect_status);
+    if expect_status == StatusCode::OK {
+        let result = TestWebServer::to_custom_type::<OrderDetailRespDto>(response.body_mut()).await;
+        Some(result.unwrap())
+    } else {
+        None
+    }
+} // end of fn itest_retrieve_order
+
+async fn itest_list_orders(
+    cfg: Arc<AppConfig>,
+    srv: Arc<Mutex<WebServiceRoute>>,
+    time_range: [DateTime<Utc>; 2],
+    authed_claim: AppAuthedClaim,
+) -> OrderListRespDto {
+    let [t0, t1] = time_range.map(|t| t.to_rfc3339_opts(SecondsFormat::Secs, true));
+    let uri = format!(
+        "/{}/orders?start={}&end={}&page=1&size=10",
+        cfg.api_server.listen.api_version, t0, t1
+    );
+    let mut req = Request::builder()
+        .uri(uri)
+        .method("GET")
+        .header("accept", "application/json")
+        .body(AxumBody::empty())
+        .unwrap();
+    let _ = req.extensions_mut().insert(authed_claim);
+    let mut response = TestWebServer::consume(&srv, req).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let result = TestWebServer::to_custom_type::<OrderListRespDto>(response.body_mut()).await;
+    result.unwrap()
+} // end of fn itest_list_orders
+
 #[tokio::test]
 async fn new_order_then_return() -> DefaultResult<(), AppError> {
     const FPATH_EDIT_PRODUCTPOLICY_OK: &str =
@@ -280,6 +366,16 @@ async fn new_order_then_return() -> DefaultResult<(), AppError> {
         assert_eq!(items.len(), 3);
     }
     itest_setup_currency_exrate(shr_state.clone()).await;
+    itest_setup_store_shipping(shr_state.clone(), 27037, &[("UPS", &[("TW", 160)])]).await;
+    itest_setup_store_shipping(
+        shr_state.clone(),
+        18830,
+        &[
+            ("FedEx", &[("TW", 120), ("US", 980)]),
+            ("UPS", &[("TH", 350)]),
+        ],
+    )
+    .await;
     let oid = place_new_order_ok(
         top_lvl_cfg.clone(),
         srv.clone(),
@@ -287,6 +383,143 @@ async fn new_order_then_return() -> DefaultResult<(), AppError> {
         itest_clone_authed_claim(&authed_claim),
     )
     .await?;
+    {
+        let detail = itest_retrieve_order(
+            top_lvl_cfg.clone(),
+            srv.clone(),
+            oid.as_str(),
+            itest_clone_authed_claim(&authed_claim),
+            StatusCode::OK,
+        )
+        .await
+        .unwrap();
+        assert_eq!(detail.order_id, oid);
+        assert_eq!(detail.usr_id, mock_authed_usr);
+        assert_eq!(detail.lines.len(), 3);
+        assert!(detail.lines.iter().all(|l| l.quantity.paid == 0));
+        assert_eq!(detail.shipping.contact.first_name.as_str(), "Jon");
+        let other_usr = setup_mock_authed_claim(mock_authed_usr + 1);
+        let _ = itest_retrieve_order(
+            top_lvl_cfg.clone(),
+            srv.clone(),
+            oid.as_str(),
+            other_usr,
+            StatusCode::FORBIDDEN,
+        )
+        .await;
+        let now = Utc::now();
+        let time_range = [now - Duration::minutes(1), now + Duration::minutes(1)];
+        let actual = itest_list_orders(
+            top_lvl_cfg.clone(),
+            srv.clone(),
+            time_range,
+            itest_clone_authed_claim(&authed_claim),
+        )
+        .await;
+        assert_eq!(actual.total, 1);
+        assert_eq!(actual.orders[0].order_id, oid);
+    }
+    // items which have not been delivered yet cannot be returned
+    let _ = itest_return_olines_request(
+        top_lvl_cfg.clone(),
+        srv.clone(),
+        FPATH_RETURN_OLINE_REQ_OK,
+        oid.as_str(),
+        itest_clone_authed_claim(&authed_claim),
+        StatusCode::BAD_REQUEST,
+    )
+    .await;
+    let mock_seller = 18830;
+    itest_update_payment_status(
+        shr_state.clone(),
+        oid.clone(),
+        Local::now().fixed_offset(),
+        vec![(mock_seller, 270118, 0, 3), (mock_seller, 270119, 0, 2)],
+    )
+    .await;
+    {
+        let resp = itest_record_shipment(
+            shr_state.clone(),
+            oid.as_str(),
+            mock_seller,
+            "1Z0018830",
+            vec![(270118, 0, 4), (270119, 0, 1)],
+        )
+        .await;
+        let errors = resp.get("lines").unwrap().as_array().unwrap();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].get("product_id").unwrap().as_u64(), Some(270118));
+        assert_eq!(
+            errors[0].get("reason").unwrap().as_str(),
+            Some("QtyLimitExceed")
+        );
+        let resp = itest_record_shipment(
+            shr_state.clone(),
+            oid.as_str(),
+            mock_seller,
+            "1Z0018830",
+            vec![(270118, 0, 2), (270119, 0, 1)],
+        )
+        .await;
+        assert!(resp.is_null());
+    }
+    {
+        let resp = itest_list_shipments(
+            top_lvl_cfg.clone(),
+            srv.clone(),
+            oid.as_str(),
+            itest_clone_authed_claim(&authed_claim),
+        )
+        .await;
+        let shipments = resp.as_array().unwrap();
+        assert_eq!(shipments.len(), 1);
+        let tracking_num = shipments[0].get("tracking_num").unwrap().as_str();
+        assert_eq!(tracking_num, Some("1Z0018830"));
+        assert!(shipments[0].get("received_time").unwrap().is_null());
+        assert_eq!(
+            shipments[0].get("lines").unwrap().as_array().unwrap().len(),
+            2
+        );
+    }
+    let shipment = (mock_seller, "1Z0018830");
+    let other_usr = setup_mock_authed_claim(mock_authed_usr + 1);
+    let args = [
+        (other_usr, StatusCode::FORBIDDEN),
+        (itest_clone_authed_claim(&authed_claim), StatusCode::OK),
+        (
+            itest_clone_authed_claim(&authed_claim),
+            StatusCode::BAD_REQUEST,
+        ),
+    ];
+    for (claim, expect_status) in args {
+        itest_confirm_shipment_receipt(
+            top_lvl_cfg.clone(),
+            srv.clone(),
+            oid.as_str(),
+            shipment,
+            claim,
+            expect_status,
+        )
+        .await;
+    }
+    {
+        let detail = itest_retrieve_order(
+            top_lvl_cfg.clone(),
+            srv.clone(),
+            oid.as_str(),
+            itest_clone_authed_claim(&authed_claim),
+            StatusCode::OK,
+        )
+        .await
+        .unwrap();
+        let mut delivered = detail
+            .lines
+            .iter()
+            .map(|l| (l.product_id, l.quantity.delivered))
+            .collect::<Vec<_>>();
+        delivered.sort();
+        assert_eq!(delivered, vec![(270118, 2), (270119, 1), (1270118, 0)]);
+    }
     let _ = itest_return_olines_request(
         top_lvl_cfg.clone(),
         srv.clone(),
@@ -319,6 +552,35 @@ async fn itest_setup_product_price<'a>(shrstate: AppSharedState, body_fpath: &'a
     let _resp_json = _itest_process_rpc_response(raw_body).unwrap();
 }
 
+async fn itest_setup_store_shipping(
+    shrstate: AppSharedState,
+    store_id: u32,
+    methods: &[(&str, &[(&str, u32)])],
+) {
+    let mock_rpc_topic = "rpc.order.sync_store_shipping";
+    let methods = methods
+        .iter()
+        .map(|(method, fees)| {
+            let fees = fees
+                .iter()
+                .map(|(country, amount)| serde_json::json!({"country": country, "amount": amount}))
+                .collect::<Vec<_>>();
+            serde_json::json!({"method": method, "fees": fees})
+        })
+        .collect::<Vec<_>>();
+    let kwargs = serde_json::json!({"store_id": store_id, "methods": methods});
+    let req = AppRpcClientReqProperty {
+        start_time: Local::now().fixed_offset(),
+        msgbody: serde_json::json!([[], kwargs, {}]).to_string().into_bytes(),
+        route: mock_rpc_topic.to_string(),
+        correlation_id: Some("py-celery-task-id-xx1235".to_string()),
+    };
+    let result = rpc::route_to_handler(req, shrstate).await;
+    assert!(result.is_ok());
+    let raw_body = result.unwrap();
+    let _resp_json = _itest_process_rpc_response(raw_body).unwrap();
+}
+
 #[tokio::test]
 async fn update_product_price_ok() -> DefaultResult<(), AppError> {
     let shrstate = test_setup_shr_state()?;
@@ -437,6 +699,19 @@ async fn update_stock_level_ok() -> DefaultResult<(), AppError> {
     itest_setup_product_price(shrstate.clone(), FPATH_EDIT_PRODUCTPRICE_OK[1]).await;
     itest_setup_product_price(shrstate.clone(), FPATH_EDIT_PRODUCTPRICE_OK[2]).await;
     itest_setup_currency_exrate(shrstate.clone()).await;
+    itest_setup_store_shipping(
+        shrstate.clone(),
+        1006,
+        &[("FedEx", &[("TW", 90)]), ("BlackCatExpress", &[("TW", 45)])],
+    )
+    .await;
+    itest_setup_store_shipping(shrstate.clone(), 1007, &[("FedEx", &[("TW", 90)])]).await;
+    itest_setup_store_shipping(
+        shrstate.clone(),
+        1009,
+        &[("UPS", &[("TW", 110)]), ("BlackCatExpress", &[("TW", 45)])],
+    )
+    .await;
     let _oid = place_new_order_ok(
         top_lvl_cfg.clone(),
         srv.clone(),
@@ -542,6 +817,72 @@ async fn place_new_order_contact_error() -> DefaultResult<(), AppError> {
     Ok(())
 } // end of place_new_order_contact_error
 
+#[tokio::test]
+async fn place_new_order_shipping_unsupported() -> DefaultResult<(), AppError> {
+    const FPATH_NEW_ORDER: &str = "/tests/integration/examples/order_new_ok_1.json";
+    let shr_state = test_setup_shr_state()?;
+    let srv = TestWebServer::setup(shr_state.clone());
+    let top_lvl_cfg = shr_state.config();
+    let mock_authed_usr = 232;
+    let mut authed_claim = setup_mock_authed_claim(mock_authed_usr);
+    authed_claim.quota = [
+        (AppAuthQuotaMatCode::NumEmails, 17),
+        (AppAuthQuotaMatCode::NumPhones, 18),
+        (AppAuthQuotaMatCode::NumOrderLines, 19),
+    ]
+    .into_iter()
+    .map(|(mat_code, maxnum)| AppAuthClaimQuota {
+        mat_code,
+        maxnum,
+        app_code: app_meta::RESOURCE_QUOTA_AP_CODE,
+    })
+    .collect::<Vec<_>>();
+    // the seller 27038 delivers items to Taiwan only by UPS, the seller
+    // 18831 has not synchronized any shipping method yet
+    itest_setup_store_shipping(shr_state.clone(), 27038, &[("UPS", &[("TW", 160)])]).await;
+    let listener = &top_lvl_cfg.api_server.listen;
+    let reqbody = {
+        let mut rb = deserialize_json_template::<OrderCreateReqData>(
+            &top_lvl_cfg.basepath,
+            FPATH_NEW_ORDER,
+        )?;
+        rb.shipping.option = [(27038, ShippingMethod::FedEx), (18831, ShippingMethod::UPS)]
+            .into_iter()
+            .map(|(seller_id, method)| ShippingOptionDto { seller_id, method })
+            .collect();
+        let rb = serde_json::to_string(&rb).unwrap();
+        AxumBody::from(rb)
+    };
+    let uri = format!("/{}/order", listener.api_version);
+    let mut req = Request::builder()
+        .uri(uri)
+        .method("POST")
+        .header("content-type", "application/json")
+        .header("accept", "application/json")
+        .body(reqbody)
+        .unwrap();
+    let _ = req.extensions_mut().insert(authed_claim);
+
+    let mut response = TestWebServer::consume(&srv, req).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let actual =
+        TestWebServer::to_custom_type::<OrderCreateRespErrorDto>(response.body_mut()).await?;
+    let opt_errs = actual.shipping.unwrap().option.unwrap();
+    assert_eq!(opt_errs.len(), 2);
+    let e0 = opt_errs[0].as_ref().unwrap();
+    assert!(e0.seller_id.is_none());
+    assert!(matches!(
+        e0.method.as_ref().unwrap(),
+        ShipOptionMethodErrorReason::NotSupport
+    ));
+    let e1 = opt_errs[1].as_ref().unwrap();
+    assert!(matches!(
+        e1.seller_id.as_ref().unwrap(),
+        ShipOptionSellerErrorReason::NotSupport
+    ));
+    Ok(())
+} // end of fn place_new_order_shipping_unsupported
+
 #[tokio::test]
 async fn place_new_order_quota_violation() -> DefaultResult<(), AppError> {
     const FPATH_NEW_ORDER: &str = "/tests/integration/examples/order_new_ok_5.json";
@@ -669,6 +1010,42 @@ async fn add_product_policy_ok() -> DefaultResult<(), AppError> {
         )
         .await;
     }
+    // every change is kept as new version, the latest versions come from the
+    // 2 request files above respectively
+    let resp = itest_product_policy_history(
+        top_lvl_cfg.clone(),
+        srv.clone(),
+        1008,
+        1015,
+        setup_mock_authed_claim(mock_authed_usr),
+        StatusCode::OK,
+    )
+    .await
+    .unwrap();
+    assert_eq!(resp.seller_id, 1008);
+    assert_eq!(resp.product_id, 1015);
+    assert!(resp.versions.len() >= 2);
+    let (prev, last) = {
+        let mut iter = resp.versions.iter().rev();
+        let last = iter.next().unwrap();
+        (iter.next().unwrap(), last)
+    };
+    assert_eq!(prev.version + 1, last.version);
+    assert_eq!((prev.auto_cancel_secs, prev.warranty_hours), (3600, 84417));
+    assert_eq!((last.auto_cancel_secs, last.warranty_hours), (2400, 44178));
+    let t0 = DateTime::parse_from_rfc3339(prev.effective_from.as_str()).unwrap();
+    let t1 = DateTime::parse_from_rfc3339(last.effective_from.as_str()).unwrap();
+    assert!(t0 <= t1);
+    // the same product in other store front has no policy
+    let _ = itest_product_policy_history(
+        top_lvl_cfg.clone(),
+        srv.clone(),
+        1009,
+        1015,
+        setup_mock_authed_claim(mock_authed_usr),
+        StatusCode::NOT_FOUND,
+    )
+    .await;
     Ok(())
 } // end of fn add_product_policy_ok
 
@@ -975,6 +1352,88 @@ async fn itest_update_payment_status(
     }
 } // end of fn itest_update_payment_status
 
+async fn itest_record_shipment(
+    shrstate: AppSharedState,
+    oid: &str,
+    seller_id: u32,
+    tracking_num: &str,
+    lines: Vec<(u64, u16, u32)>,
+) -> JsnVal {
+    let mock_rpc_topic = "rpc.order.order_shipment_create";
+    let lines = lines
+        .into_iter()
+        .map(|(product_id, attr_set_seq, qty)| {
+            serde_json::json!({
+                "product_id": product_id, "attr_set_seq": attr_set_seq, "qty": qty
+            })
+        })
+        .collect::<Vec<_>>();
+    let msgbody = serde_json::json!({
+        "order_id": oid, "seller_id": seller_id, "carrier": "UPS",
+        "tracking_num": tracking_num, "lines": lines,
+    })
+    .to_string()
+    .into_bytes();
+    let req = AppRpcClientReqProperty {
+        start_time: Local::now().fixed_offset(),
+        msgbody,
+        route: mock_rpc_topic.to_string(),
+        correlation_id: None,
+    };
+    let result = rpc::route_to_handler(req, shrstate).await;
+    assert!(result.is_ok());
+    let result = _itest_process_rpc_response(result.unwrap());
+    assert!(result.is_ok());
+    result.unwrap()
+} // end of fn itest_record_shipment
+
+async fn itest_list_shipments(
+    cfg: Arc<AppConfig>,
+    srv: Arc<Mutex<WebServiceRoute>>,
+    oid: &str,
+    authed_claim: AppAuthedClaim,
+) -> JsnVal {
+    let uri = format!(
+        "/{}/order/{}/shipments",
+        cfg.api_server.listen.api_version, oid
+    );
+    let mut req = Request::builder()
+        .uri(uri)
+        .method("GET")
+        .header("accept", "application/json")
+        .body(AxumBody::empty())
+        .unwrap();
+    let _ = req.extensions_mut().insert(authed_claim);
+    let mut response = TestWebServer::consume(&srv, req).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let result = TestWebServer::to_custom_type::<JsnVal>(response.body_mut()).await;
+    result.unwrap()
+}
+
+async fn itest_confirm_shipment_receipt(
+    cfg: Arc<AppConfig>,
+    srv: Arc<Mutex<WebServiceRoute>>,
+    oid: &str,
+    shipment: (u32, &str),
+    authed_claim: AppAuthedClaim,
+    expect_status: StatusCode,
+) {
+    let uri = format!(
+        "/{}/order/{}/shipments",
+        cfg.api_server.listen.api_version, oid
+    );
+    let req_body = serde_json::json!({"seller_id": shipment.0, "tracking_num": shipment.1});
+    let mut req = Request::builder()
+        .uri(uri)
+        .method("PATCH")
+        .header("content-type", "application/json")
+        .body(AxumBody::from(req_body.to_string()))
+        .unwrap();
+    let _ = req.extensions_mut().insert(authed_claim);
+    let response = TestWebServer::consume(&srv, req).await;
+    assert_eq!(response.status(), expect_status);
+}
+
 #[tokio::test]
 async fn replica_update_order_payment() -> DefaultResult<(), AppError> {
     const FPATH_EDIT_PRODUCTPOLICY: &str =
@@ -988,6 +1447,9 @@ async fn replica_update_order_payment() -> DefaultResult<(), AppError> {
     let time_now = Local::now().fixed_offset();
     let (mock_authed_usr, mock_seller) = (188, 543);
     let authed_claim = setup_mock_authed_claim(mock_authed_usr);
+    let ship_methods: [(&str, &[(&str, u32)]); 2] =
+        [("FedEx", &[("TW", 85)]), ("BlackCatExpress", &[("TW", 40)])];
+    itest_setup_store_shipping(shrstate.clone(), mock_seller, &ship_methods).await;
     let oid = itest_setup_create_order(
         shrstate.clone(),
         srv,
@@ -1035,25 +1497,168 @@ async fn replica_update_order_payment() -> DefaultResult<(), AppError> {
     Ok(())
 } // end of fn replica_update_order_payment
 
-async fn itest_setup_get_order_refund(
-    shrstate: AppSharedState,
-    oid: String,
-    t_start: DateTime<FixedOffset>,
-    t_end: DateTime<FixedOffset>,
-) -> JsnVal {
-    const FPATH_REP_REFUND_TEMPLATE: &str =
-        "/tests/integration/examples/replica_refund_template.json";
-    let mock_rpc_topic = "rpc.order.order_returned_replica_refund";
-    let cfg = shrstate.config().clone();
-    let req = {
-        let result = deserialize_json_template::<JsnVal>(&cfg.basepath, FPATH_REP_REFUND_TEMPLATE);
-        let mut req_body_template = result.unwrap();
-        let obj = req_body_template.as_object_mut().unwrap();
-        obj.insert("order_id".to_string(), JsnVal::String(oid));
-        obj.insert("start".to_string(), JsnVal::String(t_start.to_rfc3339()));
-        obj.insert("end".to_string(), JsnVal::String(t_end.to_rfc3339()));
-        let msgbody = req_body_template.to_string().into_bytes();
-        AppRpcClientReqProperty {
+async fn itest_cancel_unpaid_lines(
+    cfg: Arc<AppConfig>,
+    srv: Arc<Mutex<WebServiceRoute>>,
+    oid: &str,
+    lines: Vec<(u32, u64, u16)>,
+    authed_claim: AppAuthedClaim,
+    expect_status: StatusCode,
+) -> Vec<u8> {
+    let uri = format!(
+        "/{}/order/{}/cancel",
+        cfg.api_server.listen.api_version, oid
+    );
+    let req_body = {
+        let items = lines
+            .into_iter()
+            .map(|(seller_id, product_id, attr_set_seq)| {
+                serde_json::json!({
+                    "seller_id": seller_id, "product_id": product_id,
+                    "attr_set_seq": attr_set_seq,
+                })
+            })
+            .collect::<Vec<_>>();
+        AxumBody::from(JsnVal::Array(items).to_string())
+    };
+    let mut req = Request::builder()
+        .uri(uri)
+        .method("PATCH")
+        .header("content-type", "application/json")
+        .body(req_body)
+        .unwrap();
+    let _ = req.extensions_mut().insert(authed_claim);
+    let mut response = TestWebServer::consume(&srv, req).await;
+    assert_eq!(response.status(), expect_status);
+    let resp_body = response.body_mut().collect().await.unwrap();
+    resp_body.to_bytes().to_vec()
+} // end of fn itest_cancel_unpaid_lines
+
+#[tokio::test]
+async fn cancel_unpaid_order_lines() -> DefaultResult<(), AppError> {
+    const FPATH_EDIT_PRODUCTPOLICY: &str =
+        "/tests/integration/examples/policy_product_edit_ok_5.json";
+    const FPATH_EDIT_PRODUCTPRICE: &str =
+        "/tests/integration/examples/product_price_celery_ok_8.json";
+    const FPATH_EDIT_STOCK_LVL: &str = "/tests/integration/examples/stock_level_edit_ok_6.json";
+    const FPATH_NEW_ORDER: &str = "/tests/integration/examples/order_new_ok_4.json";
+    let shrstate = test_setup_shr_state()?;
+    let srv = TestWebServer::setup(shrstate.clone());
+    let top_lvl_cfg = shrstate.config();
+    let time_now = Local::now().fixed_offset();
+    let (mock_authed_usr, mock_seller) = (193, 543);
+    let authed_claim = setup_mock_authed_claim(mock_authed_usr);
+    let ship_methods: [(&str, &[(&str, u32)]); 2] =
+        [("FedEx", &[("TW", 85)]), ("BlackCatExpress", &[("TW", 40)])];
+    itest_setup_store_shipping(shrstate.clone(), mock_seller, &ship_methods).await;
+    let oid = itest_setup_create_order(
+        shrstate.clone(),
+        srv.clone(),
+        time_now,
+        &authed_claim,
+        FPATH_EDIT_PRODUCTPOLICY,
+        FPATH_EDIT_PRODUCTPRICE,
+        FPATH_EDIT_STOCK_LVL,
+        FPATH_NEW_ORDER,
+    )
+    .await;
+    itest_update_payment_status(
+        shrstate.clone(),
+        oid.clone(),
+        time_now + Duration::seconds(5),
+        vec![(mock_seller, 20094, 1, 2)],
+    )
+    .await;
+    let _ = itest_cancel_unpaid_lines(
+        top_lvl_cfg.clone(),
+        srv.clone(),
+        oid.as_str(),
+        vec![(mock_seller, 20092, 0)],
+        setup_mock_authed_claim(mock_authed_usr + 1),
+        StatusCode::FORBIDDEN,
+    )
+    .await;
+    let _ = itest_cancel_unpaid_lines(
+        top_lvl_cfg.clone(),
+        srv.clone(),
+        oid.as_str(),
+        vec![(mock_seller, 20092, 0)],
+        itest_clone_authed_claim(&authed_claim),
+        StatusCode::OK,
+    )
+    .await;
+    {
+        let raw = itest_cancel_unpaid_lines(
+            top_lvl_cfg.clone(),
+            srv.clone(),
+            oid.as_str(),
+            vec![(mock_seller, 20092, 0), (mock_seller, 20094, 1)],
+            itest_clone_authed_claim(&authed_claim),
+            StatusCode::BAD_REQUEST,
+        )
+        .await;
+        let errors = serde_json::from_slice::<JsnVal>(&raw).unwrap();
+        let errors = errors.as_array().unwrap();
+        assert_eq!(errors.len(), 2);
+        let reasons = errors
+            .iter()
+            .map(|e| e.get("reason").unwrap().as_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(reasons, vec!["DuplicateCancel", "AlreadyPaid"]);
+    }
+    // cancel the rest of unpaid lines
+    let _ = itest_cancel_unpaid_lines(
+        top_lvl_cfg.clone(),
+        srv.clone(),
+        oid.as_str(),
+        vec![],
+        itest_clone_authed_claim(&authed_claim),
+        StatusCode::OK,
+    )
+    .await;
+    let detail = itest_retrieve_order(
+        top_lvl_cfg.clone(),
+        srv,
+        oid.as_str(),
+        authed_claim,
+        StatusCode::OK,
+    )
+    .await
+    .unwrap();
+    detail
+        .lines
+        .iter()
+        .map(|line| {
+            let expect = match (line.product_id, line.attr_set_seq) {
+                (20092, 0) => 13,
+                (20094, 0) => 19,
+                _others => 0,
+            };
+            assert_eq!(line.quantity.cancelled, expect);
+        })
+        .count();
+    Ok(())
+} // end of fn cancel_unpaid_order_lines
+
+async fn itest_setup_get_order_refund(
+    shrstate: AppSharedState,
+    oid: String,
+    t_start: DateTime<FixedOffset>,
+    t_end: DateTime<FixedOffset>,
+) -> JsnVal {
+    const FPATH_REP_REFUND_TEMPLATE: &str =
+        "/tests/integration/examples/replica_refund_template.json";
+    let mock_rpc_topic = "rpc.order.order_returned_replica_refund";
+    let cfg = shrstate.config().clone();
+    let req = {
+        let result = deserialize_json_template::<JsnVal>(&cfg.basepath, FPATH_REP_REFUND_TEMPLATE);
+        let mut req_body_template = result.unwrap();
+        let obj = req_body_template.as_object_mut().unwrap();
+        obj.insert("order_id".to_string(), JsnVal::String(oid));
+        obj.insert("start".to_string(), JsnVal::String(t_start.to_rfc3339()));
+        obj.insert("end".to_string(), JsnVal::String(t_end.to_rfc3339()));
+        let msgbody = req_body_template.to_string().into_bytes();
+        AppRpcClientReqProperty {
             start_time: Local::now().fixed_offset(),
             msgbody,
             route: mock_rpc_topic.to_string(),
@@ -1121,6 +1726,9 @@ async fn replica_order_refund() -> DefaultResult<(), AppError> {
     let time_now = Local::now().fixed_offset();
     let (mock_authed_usr, mock_seller) = (188, 543);
     let authed_claim = setup_mock_authed_claim(mock_authed_usr);
+    let ship_methods: [(&str, &[(&str, u32)]); 2] =
+        [("FedEx", &[("TW", 85)]), ("BlackCatExpress", &[("TW", 40)])];
+    itest_setup_store_shipping(shrstate.clone(), mock_seller, &ship_methods).await;
     let oid = itest_setup_create_order(
         shrstate.clone(),
         srv.clone(),
@@ -1143,6 +1751,24 @@ async fn replica_order_refund() -> DefaultResult<(), AppError> {
         ],
     )
     .await;
+    let resp = itest_record_shipment(
+        shrstate.clone(),
+        oid.as_str(),
+        mock_seller,
+        "BC0000543",
+        vec![(20095, 0, 5), (20096, 0, 2), (20097, 0, 4)],
+    )
+    .await;
+    assert!(resp.is_null());
+    itest_confirm_shipment_receipt(
+        shrstate.config().clone(),
+        srv.clone(),
+        oid.as_str(),
+        (mock_seller, "BC0000543"),
+        itest_clone_authed_claim(&authed_claim),
+        StatusCode::OK,
+    )
+    .await;
     sleep(std::time::Duration::from_secs(2)).await;
     let _resp_body = itest_return_olines_request(
         shrstate.config().clone(),
@@ -1291,6 +1917,7 @@ async fn replica_order_rsv_inventory() -> DefaultResult<(), AppError> {
     let time_now = Local::now().fixed_offset();
     let (mock_authed_usr, mock_seller) = (192, 545);
     let authed_claim = setup_mock_authed_claim(mock_authed_usr);
+    itest_setup_store_shipping(shrstate.clone(), mock_seller, &[("UPS", &[("TW", 85)])]).await;
     let oid = itest_setup_create_order(
         shrstate.clone(),
         srv.clone(),
@@ -1322,6 +1949,31 @@ async fn replica_order_rsv_inventory() -> DefaultResult<(), AppError> {
             ],
         );
     }
+    itest_update_payment_status(
+        shrstate.clone(),
+        oid.clone(),
+        Local::now().fixed_offset(),
+        vec![(mock_seller, 20101, 0, 4), (mock_seller, 20100, 0, 6)],
+    )
+    .await;
+    let resp = itest_record_shipment(
+        shrstate.clone(),
+        oid.as_str(),
+        mock_seller,
+        "BC0000545",
+        vec![(20101, 0, 4), (20100, 0, 6)],
+    )
+    .await;
+    assert!(resp.is_null());
+    itest_confirm_shipment_receipt(
+        shrstate.config().clone(),
+        srv.clone(),
+        oid.as_str(),
+        (mock_seller, "BC0000545"),
+        itest_clone_authed_claim(&authed_claim),
+        StatusCode::OK,
+    )
+    .await;
     sleep(std::time::Duration::from_secs(2u64)).await;
     let _resp_body = itest_return_olines_request(
         shrstate.config().clone(),
@@ -1477,6 +2129,11 @@ fn _cart_line_dto_sort_helper(x: &JsnVal, y: &JsnVal) -> std::cmp::Ordering {
         let prod_id_y = map_y.get("product_id").unwrap().as_u64().unwrap();
         c = prod_id_x.cmp(&prod_id_y);
     }
+    if c == std::cmp::Ordering::Equal {
+        let attr_x = map_x.get("applied_attr").unwrap().to_string();
+        let attr_y = map_y.get("applied_attr").unwrap().to_string();
+        c = attr_x.cmp(&attr_y);
+    }
     c
 }
 
@@ -1546,3 +2203,443 @@ async fn modify_retrieve_cart_ok() -> DefaultResult<(), AppError> {
     ).await;
     Ok(())
 } // end of fn modify_retrieve_cart_ok
+
+#[rustfmt::skip]
+#[tokio::test]
+async fn modify_cart_attr_variants_ok() -> DefaultResult<(), AppError> {
+    const FPATH_MODIFY_CART: [&str; 2] = [
+        "/tests/integration/examples/cartline_update_4.json",
+        "/tests/integration/examples/cartline_update_5.json",
+    ];
+    const FPATH_EXPECT_FETCHED_CART: [&str; 2] = [
+        "/tests/integration/examples/cart_expect_fetched_3.json",
+        "/tests/integration/examples/cart_expect_fetched_4.json",
+    ];
+    let shrstate = test_setup_shr_state()?;
+    let srv = TestWebServer::setup(shrstate.clone());
+    let mock_authed_usr = 122;
+    let authed_claim = setup_mock_authed_claim(mock_authed_usr);
+    let _resp_body = itest_cart_discard_request(
+        shrstate.config().clone(), srv.clone(),
+        &authed_claim, 1, StatusCode::NO_CONTENT,
+    )
+    .await;
+    for (path_modify, path_expect) in FPATH_MODIFY_CART.into_iter().zip(FPATH_EXPECT_FETCHED_CART) {
+        let _resp_body = itest_cart_modify_request(
+            shrstate.config().clone(), srv.clone(),
+            path_modify, &authed_claim,
+            1, StatusCode::OK,
+        )
+        .await;
+        itest_cart_retrieve_request(
+            shrstate.config().clone(), srv.clone(),
+            &authed_claim, 1, StatusCode::OK,
+            Some(path_expect),
+        )
+        .await;
+    }
+    Ok(())
+} // end of fn modify_cart_attr_variants_ok
+
+async fn itest_cart_checkout_request(
+    cfg: Arc<AppConfig>,
+    srv: Arc<Mutex<WebServiceRoute>>,
+    req_fpath: &'static str,
+    authed_claim: &AppAuthedClaim,
+    seq_num: u8,
+    expect_status: StatusCode,
+) -> Option<JsnVal> {
+    let uri = format!(
+        "/{}/cart/{}/checkout",
+        cfg.api_server.listen.api_version, seq_num
+    );
+    let reqbody = {
+        let result = deserialize_json_template::<JsnVal>(&cfg.basepath, req_fpath);
+        let rb = serde_json::to_string(&result.unwrap()).unwrap();
+        AxumBody::from(rb)
+    };
+    let mut authed_claim_cpy = itest_clone_authed_claim(authed_claim);
+    authed_claim_cpy.quota = [
+        (AppAuthQuotaMatCode::NumEmails, 51),
+        (AppAuthQuotaMatCode::NumPhones, 52),
+        (AppAuthQuotaMatCode::NumOrderLines, 53),
+    ]
+    .into_iter()
+    .map(|(mat_code, maxnum)| AppAuthClaimQuota {
+        mat_code,
+        maxnum,
+        app_code: app_meta::RESOURCE_QUOTA_AP_CODE,
+    })
+    .collect::<Vec<_>>();
+    let mut req = Request::builder()
+        .uri(uri)
+        .method("POST")
+        .header("content-type", "application/json")
+        .header("accept", "application/json")
+        .body(reqbody)
+        .unwrap();
+    let _ = req.extensions_mut().insert(authed_claim_cpy);
+    let mut response = TestWebServer::consume(&srv, req).await;
+    assert_eq!(response.status(), expect_status);
+    TestWebServer::to_custom_type::<JsnVal>(response.body_mut())
+        .await
+        .ok()
+} // end of fn itest_cart_checkout_request
+
+#[rustfmt::skip]
+#[tokio::test]
+async fn checkout_cart_ok() -> DefaultResult<(), AppError> {
+    const FPATH_EDIT_PRODUCTPOLICY_OK: &str = "/tests/integration/examples/policy_product_edit_ok_2.json";
+    const FPATH_EDIT_PRODUCTPRICE_OK: &str = "/tests/integration/examples/product_price_celery_ok_4.json";
+    const FPATH_EDIT_STOCK_LVL_OK: &str = "/tests/integration/examples/stock_level_edit_ok_3.json";
+    const FPATH_MODIFY_CART: [&str; 2] = [
+        "/tests/integration/examples/cartline_update_6.json",
+        "/tests/integration/examples/cartline_update_7.json",
+    ];
+    const FPATH_EXPECT_FETCHED_CART: &str = "/tests/integration/examples/cart_expect_fetched_5.json";
+    const FPATH_CHECKOUT_CART: &str = "/tests/integration/examples/cart_checkout_ok_1.json";
+    let shrstate = test_setup_shr_state()?;
+    let srv = TestWebServer::setup(shrstate.clone());
+    let top_lvl_cfg = shrstate.config();
+    let mock_authed_usr = 187;
+    let authed_claim = setup_mock_authed_claim(mock_authed_usr);
+    let _ = itest_setup_product_policy(
+        top_lvl_cfg.clone(), srv.clone(), FPATH_EDIT_PRODUCTPOLICY_OK,
+        itest_clone_authed_claim(&authed_claim), StatusCode::OK,
+    ).await;
+    itest_setup_product_price(shrstate.clone(), FPATH_EDIT_PRODUCTPRICE_OK).await;
+    {
+        let expiry = Local::now().fixed_offset() + Duration::minutes(1);
+        let _resp_body = itest_setup_stock_level(shrstate.clone(), expiry, FPATH_EDIT_STOCK_LVL_OK).await;
+    }
+    itest_setup_currency_exrate(shrstate.clone()).await;
+    itest_setup_store_shipping(shrstate.clone(), 27037, &[("UPS", &[("TW", 160)])]).await;
+    itest_setup_store_shipping(
+        shrstate.clone(), 18830,
+        &[("FedEx", &[("TW", 120), ("US", 980)]), ("UPS", &[("TH", 350)])],
+    ).await;
+    let _ = itest_cart_discard_request(
+        top_lvl_cfg.clone(), srv.clone(), &authed_claim, 0, StatusCode::NO_CONTENT,
+    ).await;
+    let _ = itest_cart_checkout_request(
+        top_lvl_cfg.clone(), srv.clone(), FPATH_CHECKOUT_CART,
+        &authed_claim, 0, StatusCode::NOT_FOUND,
+    ).await;
+    // the cart has never been created
+    let _ = itest_cart_checkout_request(
+        top_lvl_cfg.clone(), srv.clone(), FPATH_CHECKOUT_CART,
+        &authed_claim, 4, StatusCode::NOT_FOUND,
+    ).await;
+    let _ = itest_cart_modify_request(
+        top_lvl_cfg.clone(), srv.clone(), FPATH_MODIFY_CART[0],
+        &authed_claim, 0, StatusCode::OK,
+    ).await;
+    // one of the products is not purchasable, the cart is left unchanged
+    let resp_body = itest_cart_checkout_request(
+        top_lvl_cfg.clone(), srv.clone(), FPATH_CHECKOUT_CART,
+        &authed_claim, 0, StatusCode::BAD_REQUEST,
+    ).await.unwrap();
+    let err_lines = resp_body.get("order_lines").unwrap().as_array().unwrap();
+    assert_eq!(err_lines.len(), 1);
+    let product_id = err_lines[0].get("product_id").unwrap().as_u64().unwrap();
+    assert_eq!(product_id, 9270118);
+    let reason = err_lines[0].get("reason").unwrap().as_str().unwrap();
+    assert_eq!(reason, "NotExist");
+    itest_cart_retrieve_request(
+        top_lvl_cfg.clone(), srv.clone(), &authed_claim,
+        0, StatusCode::OK, Some(FPATH_MODIFY_CART[0]),
+    ).await;
+    // lines with zero quantity are skipped on checkout
+    let _ = itest_cart_modify_request(
+        top_lvl_cfg.clone(), srv.clone(), FPATH_MODIFY_CART[1],
+        &authed_claim, 0, StatusCode::OK,
+    ).await;
+    let resp_body = itest_cart_checkout_request(
+        top_lvl_cfg.clone(), srv.clone(), FPATH_CHECKOUT_CART,
+        &authed_claim, 0, StatusCode::CREATED,
+    ).await.unwrap();
+    let oid = resp_body.get("order_id").unwrap().as_str().unwrap();
+    let detail = itest_retrieve_order(
+        top_lvl_cfg.clone(), srv.clone(), oid,
+        itest_clone_authed_claim(&authed_claim), StatusCode::OK,
+    ).await.unwrap();
+    assert_eq!(detail.usr_id, mock_authed_usr);
+    assert_eq!(detail.lines.len(), 2);
+    [(270118u64, 2u32), (270119, 3)].into_iter()
+        .map(|(product_id, expect_qty)| {
+            let line = detail.lines.iter()
+                .find(|l| l.product_id == product_id)
+                .unwrap();
+            assert_eq!(line.quantity.reserved, expect_qty);
+        })
+        .count();
+    itest_cart_retrieve_request(
+        top_lvl_cfg.clone(), srv.clone(), &authed_claim,
+        0, StatusCode::OK, Some(FPATH_EXPECT_FETCHED_CART),
+    ).await;
+    let _ = itest_cart_checkout_request(
+        top_lvl_cfg.clone(), srv.clone(), FPATH_CHECKOUT_CART,
+        &authed_claim, 0, StatusCode::NOT_FOUND,
+    ).await;
+    Ok(())
+} // end of fn checkout_cart_ok
+
+async fn itest_cart_preview_request(
+    cfg: Arc<AppConfig>,
+    srv: Arc<Mutex<WebServiceRoute>>,
+    authed_claim: &AppAuthedClaim,
+    seq_num: u8,
+    currency: &str,
+    expect_status: StatusCode,
+) -> Option<JsnVal> {
+    let uri = format!(
+        "/{}/cart/{}/preview?currency={}",
+        cfg.api_server.listen.api_version, seq_num, currency
+    );
+    let authed_claim_cpy = itest_clone_authed_claim(authed_claim);
+    let mut req = Request::builder()
+        .uri(uri)
+        .method("GET")
+        .header("accept", "application/json")
+        .body(AxumBody::empty())
+        .unwrap();
+    let _ = req.extensions_mut().insert(authed_claim_cpy);
+    let mut response = TestWebServer::consume(&srv, req).await;
+    assert_eq!(response.status(), expect_status);
+    TestWebServer::to_custom_type::<JsnVal>(response.body_mut())
+        .await
+        .ok()
+} // end of fn itest_cart_preview_request
+
+#[rustfmt::skip]
+#[tokio::test]
+async fn preview_cart_ok() -> DefaultResult<(), AppError> {
+    const FPATH_EDIT_PRODUCTPOLICY_OK: &str = "/tests/integration/examples/policy_product_edit_ok_2.json";
+    const FPATH_EDIT_PRODUCTPRICE_OK: &str = "/tests/integration/examples/product_price_celery_ok_4.json";
+    const FPATH_EDIT_STOCK_LVL_OK: &str = "/tests/integration/examples/stock_level_edit_ok_3.json";
+    const FPATH_MODIFY_CART: &str = "/tests/integration/examples/cartline_update_8.json";
+    let shrstate = test_setup_shr_state()?;
+    let srv = TestWebServer::setup(shrstate.clone());
+    let top_lvl_cfg = shrstate.config();
+    let authed_claim = setup_mock_authed_claim(191);
+    let _ = itest_setup_product_policy(
+        top_lvl_cfg.clone(), srv.clone(), FPATH_EDIT_PRODUCTPOLICY_OK,
+        itest_clone_authed_claim(&authed_claim), StatusCode::OK,
+    ).await;
+    itest_setup_product_price(shrstate.clone(), FPATH_EDIT_PRODUCTPRICE_OK).await;
+    {
+        let expiry = Local::now().fixed_offset() + Duration::minutes(1);
+        let _resp_body = itest_setup_stock_level(shrstate.clone(), expiry, FPATH_EDIT_STOCK_LVL_OK).await;
+    }
+    itest_setup_currency_exrate(shrstate.clone()).await;
+    let _ = itest_cart_discard_request(
+        top_lvl_cfg.clone(), srv.clone(), &authed_claim, 1, StatusCode::NO_CONTENT,
+    ).await;
+    let resp_body = itest_cart_preview_request(
+        top_lvl_cfg.clone(), srv.clone(), &authed_claim, 1, "THB", StatusCode::OK,
+    ).await.unwrap();
+    assert!(resp_body.get("lines").unwrap().as_array().unwrap().is_empty());
+    let _ = itest_cart_preview_request(
+        top_lvl_cfg.clone(), srv.clone(), &authed_claim, 250, "THB", StatusCode::NOT_FOUND,
+    ).await;
+    let _ = itest_cart_modify_request(
+        top_lvl_cfg.clone(), srv.clone(), FPATH_MODIFY_CART,
+        &authed_claim, 1, StatusCode::OK,
+    ).await;
+    let resp_body = itest_cart_preview_request(
+        top_lvl_cfg.clone(), srv.clone(), &authed_claim, 1, "THB", StatusCode::OK,
+    ).await.unwrap();
+    assert_eq!(resp_body.get("title").unwrap().as_str().unwrap(), "study room");
+    assert_eq!(resp_body.get("currency").unwrap().as_str().unwrap(), "THB");
+    let lines = resp_body.get("lines").unwrap().as_array().unwrap();
+    assert_eq!(lines.len(), 3);
+    let find_line = |product_id: u64| {
+        lines.iter()
+            .find(|l| l.get("product_id").unwrap().as_u64().unwrap() == product_id)
+            .unwrap()
+    };
+    // purchasable as it is
+    let line = find_line(270118);
+    assert!(line.get("amount").unwrap().is_object());
+    assert!(line.get("nonexist").unwrap().is_null());
+    assert!(line.get("shortage").unwrap().is_null());
+    assert!(line.get("rsv_limit").unwrap().is_null());
+    // stock level is shared with other test cases, the exact number of
+    // available items varies, but it is far less than the request
+    let line = find_line(270119);
+    assert!(line.get("amount").unwrap().is_object());
+    let shortage = line.get("shortage").unwrap().as_u64().unwrap();
+    assert!(shortage > 0 && shortage < 2000);
+    // no such product in all the sources
+    let line = find_line(9270118);
+    assert!(line.get("amount").unwrap().is_null());
+    let nonexist = line.get("nonexist").unwrap();
+    assert!(nonexist.get("product_policy").unwrap().as_bool().unwrap());
+    assert!(nonexist.get("product_price").unwrap().as_bool().unwrap());
+    assert!(nonexist.get("stock_seller").unwrap().as_bool().unwrap());
+    Ok(())
+} // end of fn preview_cart_ok
+
+async fn itest_cart_share_common_request(
+    cfg: Arc<AppConfig>,
+    srv: Arc<Mutex<WebServiceRoute>>,
+    method: &str,
+    uri_path: String,
+    req_fpath: Option<&'static str>,
+    authed_claim: &AppAuthedClaim,
+    expect_status: StatusCode,
+) -> Option<JsnVal> {
+    let uri = format!("/{}{}", cfg.api_server.listen.api_version, uri_path);
+    let reqbody = if let Some(fpath) = req_fpath {
+        let result = deserialize_json_template::<JsnVal>(&cfg.basepath, fpath);
+        let rb = serde_json::to_string(&result.unwrap()).unwrap();
+        AxumBody::from(rb)
+    } else {
+        AxumBody::empty()
+    };
+    let mut authed_claim_cpy = itest_clone_authed_claim(authed_claim);
+    authed_claim_cpy.quota = [
+        (AppAuthQuotaMatCode::NumOrderLines, 14),
+        (AppAuthQuotaMatCode::NumCartCollaborators, 2),
+    ]
+    .into_iter()
+    .map(|(mat_code, maxnum)| AppAuthClaimQuota {
+        mat_code,
+        maxnum,
+        app_code: app_meta::RESOURCE_QUOTA_AP_CODE,
+    })
+    .collect::<Vec<_>>();
+    let mut req = Request::builder()
+        .uri(uri)
+        .method(method)
+        .header("content-type", "application/json")
+        .header("accept", "application/json")
+        .body(reqbody)
+        .unwrap();
+    let _ = req.extensions_mut().insert(authed_claim_cpy);
+    let mut response = TestWebServer::consume(&srv, req).await;
+    assert_eq!(response.status(), expect_status);
+    TestWebServer::to_custom_type::<JsnVal>(response.body_mut())
+        .await
+        .ok()
+} // end of fn itest_cart_share_common_request
+
+#[rustfmt::skip]
+#[tokio::test]
+async fn share_cart_ok() -> DefaultResult<(), AppError> {
+    const FPATH_MODIFY_CART: [&str; 2] = [
+        "/tests/integration/examples/cartline_update_9.json",
+        "/tests/integration/examples/cartline_update_10.json",
+    ];
+    const FPATH_SHARE_CART_OK: &str = "/tests/integration/examples/cart_share_ok_1.json";
+    const FPATH_SHARE_CART_INVALID: [&str; 2] = [
+        "/tests/integration/examples/cart_share_invalid_1.json",
+        "/tests/integration/examples/cart_share_invalid_2.json",
+    ];
+    let shrstate = test_setup_shr_state()?;
+    let srv = TestWebServer::setup(shrstate.clone());
+    let top_lvl_cfg = shrstate.config();
+    let (owner, reader, editor, stranger) = (192u32, 193u32, 194u32, 195u32);
+    let [claim_owner, claim_reader, claim_editor, claim_stranger] =
+        [owner, reader, editor, stranger].map(setup_mock_authed_claim);
+    let seq_num = 2u8;
+    let uri_cart_owned = format!("/cart/{seq_num}");
+    let uri_cart_shared = format!("/cart/{seq_num}?owner={owner}");
+    let uri_share = format!("/cart/{seq_num}/share");
+
+    let _ = itest_cart_discard_request(
+        top_lvl_cfg.clone(), srv.clone(), &claim_owner, seq_num, StatusCode::NO_CONTENT,
+    ).await;
+    let _ = itest_cart_modify_request(
+        top_lvl_cfg.clone(), srv.clone(), FPATH_MODIFY_CART[0],
+        &claim_owner, seq_num, StatusCode::OK,
+    ).await;
+    // not shared yet
+    let _ = itest_cart_share_common_request(
+        top_lvl_cfg.clone(), srv.clone(), "GET", uri_cart_shared.clone(),
+        None, &claim_reader, StatusCode::FORBIDDEN,
+    ).await;
+    // the owner itself and duplicate users are rejected
+    let resp_body = itest_cart_share_common_request(
+        top_lvl_cfg.clone(), srv.clone(), "PUT", uri_share.clone(),
+        Some(FPATH_SHARE_CART_INVALID[0]), &claim_owner, StatusCode::BAD_REQUEST,
+    ).await.unwrap();
+    let usr_ids = resp_body.get("usr_ids").unwrap().as_array().unwrap();
+    let usr_ids = usr_ids.iter().map(|v| v.as_u64().unwrap()).collect::<Vec<_>>();
+    assert_eq!(usr_ids, vec![owner as u64, reader as u64]);
+    let resp_body = itest_cart_share_common_request(
+        top_lvl_cfg.clone(), srv.clone(), "PUT", uri_share.clone(),
+        Some(FPATH_SHARE_CART_INVALID[1]), &claim_owner, StatusCode::FORBIDDEN,
+    ).await.unwrap();
+    assert_eq!(resp_body.get("given").unwrap().as_u64().unwrap(), 3);
+    assert_eq!(resp_body.get("max_").unwrap().as_u64().unwrap(), 2);
+    let _ = itest_cart_share_common_request(
+        top_lvl_cfg.clone(), srv.clone(), "PUT", uri_share.clone(),
+        Some(FPATH_SHARE_CART_OK), &claim_owner, StatusCode::OK,
+    ).await;
+    let resp_body = itest_cart_share_common_request(
+        top_lvl_cfg.clone(), srv.clone(), "GET", uri_share.clone(),
+        None, &claim_owner, StatusCode::OK,
+    ).await.unwrap();
+    let collaborators = resp_body.get("collaborators").unwrap().as_array().unwrap();
+    assert_eq!(collaborators.len(), 2);
+
+    // read-only access
+    let resp_body = itest_cart_share_common_request(
+        top_lvl_cfg.clone(), srv.clone(), "GET", uri_cart_shared.clone(),
+        None, &claim_reader, StatusCode::OK,
+    ).await.unwrap();
+    assert_eq!(resp_body.get("title").unwrap().as_str().unwrap(), "party snacks");
+    assert_eq!(resp_body.get("lines").unwrap().as_array().unwrap().len(), 1);
+    let _ = itest_cart_share_common_request(
+        top_lvl_cfg.clone(), srv.clone(), "PATCH", uri_cart_shared.clone(),
+        Some(FPATH_MODIFY_CART[1]), &claim_reader, StatusCode::FORBIDDEN,
+    ).await;
+    let _ = itest_cart_share_common_request(
+        top_lvl_cfg.clone(), srv.clone(), "DELETE", uri_cart_shared.clone(),
+        None, &claim_reader, StatusCode::FORBIDDEN,
+    ).await;
+    // the reader accesses its own cart without the owner parameter
+    let resp_body = itest_cart_share_common_request(
+        top_lvl_cfg.clone(), srv.clone(), "GET", uri_cart_owned.clone(),
+        None, &claim_reader, StatusCode::OK,
+    ).await.unwrap();
+    assert!(resp_body.get("lines").unwrap().as_array().unwrap().is_empty());
+    // users not in the list
+    let _ = itest_cart_share_common_request(
+        top_lvl_cfg.clone(), srv.clone(), "GET", uri_cart_shared.clone(),
+        None, &claim_stranger, StatusCode::FORBIDDEN,
+    ).await;
+
+    // edit access
+    let _ = itest_cart_share_common_request(
+        top_lvl_cfg.clone(), srv.clone(), "PATCH", uri_cart_shared.clone(),
+        Some(FPATH_MODIFY_CART[1]), &claim_editor, StatusCode::OK,
+    ).await;
+    itest_cart_retrieve_request(
+        top_lvl_cfg.clone(), srv.clone(), &claim_owner,
+        seq_num, StatusCode::OK, Some(FPATH_MODIFY_CART[1]),
+    ).await;
+    // only the owner can discard the cart
+    let _ = itest_cart_share_common_request(
+        top_lvl_cfg.clone(), srv.clone(), "DELETE", uri_cart_shared.clone(),
+        None, &claim_editor, StatusCode::FORBIDDEN,
+    ).await;
+    let _ = itest_cart_share_common_request(
+        top_lvl_cfg.clone(), srv.clone(), "DELETE", uri_cart_owned.clone(),
+        None, &claim_owner, StatusCode::NO_CONTENT,
+    ).await;
+    let resp_body = itest_cart_share_common_request(
+        top_lvl_cfg.clone(), srv.clone(), "GET", uri_cart_owned.clone(),
+        None, &claim_owner, StatusCode::OK,
+    ).await.unwrap();
+    assert!(resp_body.get("lines").unwrap().as_array().unwrap().is_empty());
+    // discarding the cart also revokes the access
+    for claim in [&claim_reader, &claim_editor] {
+        let _ = itest_cart_share_common_request(
+            top_lvl_cfg.clone(), srv.clone(), "GET", uri_cart_shared.clone(),
+            None, claim, StatusCode::FORBIDDEN,
+        ).await;
+    }
+    Ok(())
+} // end of fn share_cart_ok
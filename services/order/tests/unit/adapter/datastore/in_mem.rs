@@ -1,13 +1,16 @@
 use std::collections::hash_map::RandomState;
 use std::collections::{HashMap, HashSet};
+use std::ops::Bound;
+use std::path::PathBuf;
+use std::sync::Arc;
 
 use ecommerce_common::error::AppErrorCode;
 
 use order::datastore::{
     AbsDStoreFilterKeyOp, AbstInMemoryDStore, AppInMemDeleteInfo, AppInMemFetchKeys,
-    AppInMemUpdateData, AppInMemoryDStore,
+    AppInMemIdxKey, AppInMemIdxKeyType, AppInMemUpdateData, AppInMemoryDStore,
 };
-use order::AppInMemoryDbCfg;
+use order::{AppInMemFsyncPolicy, AppInMemPersistCfg, AppInMemoryDbCfg};
 
 const UT_NUM_TABLES: usize = 3;
 const UT_TABLE_LABEL_A: &'static str = "app-table-12";
@@ -21,8 +24,9 @@ async fn save_ok_1() {
     let cfg = AppInMemoryDbCfg {
         alias: "Sheipa".to_string(),
         max_items: 10,
+        persistence: None,
     };
-    let dstore = AppInMemoryDStore::new(&cfg);
+    let dstore = AppInMemoryDStore::new(&cfg).unwrap();
     for label in UT_TABLE_LABELS.clone().into_iter() {
         let result = dstore.create_table(label).await;
         assert!(result.is_ok());
@@ -129,8 +133,9 @@ async fn save_ok_2() {
     let cfg = AppInMemoryDbCfg {
         alias: "Sheipa".to_string(),
         max_items: 10,
+        persistence: None,
     };
-    let dstore = AppInMemoryDStore::new(&cfg);
+    let dstore = AppInMemoryDStore::new(&cfg).unwrap();
     assert_eq!(dstore.create_table(UT_TABLE_LABEL_A).await.is_ok(), true);
     let new_data: AppInMemUpdateData = {
         let mut out = HashMap::new();
@@ -219,8 +224,9 @@ async fn fetch_acquire_save_release_ok() {
     let cfg = AppInMemoryDbCfg {
         alias: "Sheipa".to_string(),
         max_items: 10,
+        persistence: None,
     };
-    let dstore = AppInMemoryDStore::new(&cfg);
+    let dstore = AppInMemoryDStore::new(&cfg).unwrap();
     assert_eq!(dstore.create_table(UT_TABLE_LABEL_A).await.is_ok(), true);
     let new_data: AppInMemUpdateData = {
         let mut out = HashMap::new();
@@ -305,14 +311,145 @@ async fn fetch_acquire_save_release_ok() {
     }
 } // end of fetch_acquire_save_release_ok
 
+#[tokio::test]
+async fn save_release_table_not_locked() {
+    let cfg = AppInMemoryDbCfg {
+        alias: "Sheipa".to_string(),
+        max_items: 10,
+        persistence: None,
+    };
+    let dstore = AppInMemoryDStore::new(&cfg).unwrap();
+    for label in UT_TABLE_LABELS.into_iter() {
+        assert!(dstore.create_table(label).await.is_ok());
+    }
+    // table B is locked for write only, nothing fetched from it
+    let fetching_keys: AppInMemFetchKeys = HashMap::from([
+        (UT_TABLE_LABEL_A.to_string(), vec!["G802".to_string()]),
+        (UT_TABLE_LABEL_B.to_string(), Vec::new()),
+    ]);
+    let result = dstore.fetch_acquire(fetching_keys.clone()).await;
+    let (fetched, lock) = result.unwrap();
+    assert!(fetched.get(UT_TABLE_LABEL_B).unwrap().is_empty());
+    let row = vec!["yoshi".to_string(), "0.28".to_string()];
+    let new_data: AppInMemUpdateData = HashMap::from([
+        (
+            UT_TABLE_LABEL_B.to_string(),
+            HashMap::from([("Te9h".to_string(), row.clone())]),
+        ),
+        (
+            UT_TABLE_LABEL_C.to_string(),
+            HashMap::from([("Oy7u".to_string(), row.clone())]),
+        ),
+    ]);
+    let result = dstore.save_release(new_data, lock);
+    assert!(result.is_err());
+    if let Err(e) = result {
+        assert_eq!(e.code, AppErrorCode::AcquireLockFailure);
+    }
+    let (_fetched, lock) = dstore.fetch_acquire(fetching_keys).await.unwrap();
+    let new_data: AppInMemUpdateData = HashMap::from([(
+        UT_TABLE_LABEL_B.to_string(),
+        HashMap::from([("Te9h".to_string(), row)]),
+    )]);
+    let result = dstore.save_release(new_data, lock);
+    assert_eq!(result.unwrap(), 1);
+} // end of save_release_table_not_locked
+
+#[tokio::test]
+async fn lock_table_independent() {
+    let cfg = AppInMemoryDbCfg {
+        alias: "Sheipa".to_string(),
+        max_items: 10,
+        persistence: None,
+    };
+    let dstore = AppInMemoryDStore::new(&cfg).unwrap();
+    for label in UT_TABLE_LABELS.into_iter() {
+        assert!(dstore.create_table(label).await.is_ok());
+    }
+    let keys_a: AppInMemFetchKeys = HashMap::from([(UT_TABLE_LABEL_A.to_string(), Vec::new())]);
+    let keys_b: AppInMemFetchKeys = HashMap::from([(UT_TABLE_LABEL_B.to_string(), Vec::new())]);
+    let (_fetched, lock) = dstore.fetch_acquire(keys_a.clone()).await.unwrap();
+    let wait = std::time::Duration::from_millis(100);
+    // other tables are still accessible while table A is locked
+    let result = tokio::time::timeout(wait, dstore.fetch(keys_b)).await;
+    assert!(result.is_ok());
+    let result = tokio::time::timeout(wait, dstore.fetch(keys_a.clone())).await;
+    assert!(result.is_err());
+    drop(lock);
+    let result = tokio::time::timeout(wait, dstore.fetch(keys_a)).await;
+    assert!(result.is_ok());
+} // end of lock_table_independent
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn fetch_acquire_concurrent_rmw() {
+    let cfg = AppInMemoryDbCfg {
+        alias: "Sheipa".to_string(),
+        max_items: 10,
+        persistence: None,
+    };
+    let dstore = Arc::new(AppInMemoryDStore::new(&cfg).unwrap());
+    for label in UT_TABLE_LABELS.into_iter() {
+        assert!(dstore.create_table(label).await.is_ok());
+    }
+    let counter_id = "counter".to_string();
+    let init_data: AppInMemUpdateData = HashMap::from([(
+        UT_TABLE_LABEL_C.to_string(),
+        HashMap::from([(counter_id.clone(), vec!["0".to_string()])]),
+    )]);
+    assert!(dstore.save(init_data).await.is_ok());
+    let num_tasks = 16usize;
+    let num_rounds = 25usize;
+    let handles = (0..num_tasks)
+        .map(|idx| {
+            let dstore = dstore.clone();
+            let counter_id = counter_id.clone();
+            // tables are specified in different order in each task
+            let mut labels = [UT_TABLE_LABEL_C, UT_TABLE_LABEL_A];
+            if idx % 2 == 0 {
+                labels.reverse();
+            }
+            tokio::spawn(async move {
+                for _ in 0..num_rounds {
+                    let keys: AppInMemFetchKeys = labels
+                        .iter()
+                        .map(|l| (l.to_string(), vec![counter_id.clone()]))
+                        .collect();
+                    let (mut fetched, lock) = dstore.fetch_acquire(keys).await.unwrap();
+                    let mut table = fetched.remove(UT_TABLE_LABEL_C).unwrap();
+                    let row = table.get_mut(counter_id.as_str()).unwrap();
+                    let value = row[0].parse::<usize>().unwrap();
+                    tokio::task::yield_now().await;
+                    row[0] = (value + 1).to_string();
+                    let data = HashMap::from([(UT_TABLE_LABEL_C.to_string(), table)]);
+                    assert!(dstore.save_release(data, lock).is_ok());
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    for h in handles {
+        assert!(h.await.is_ok());
+    }
+    let keys: AppInMemFetchKeys =
+        HashMap::from([(UT_TABLE_LABEL_C.to_string(), vec![counter_id.clone()])]);
+    let fetched = dstore.fetch(keys).await.unwrap();
+    let row = fetched
+        .get(UT_TABLE_LABEL_C)
+        .unwrap()
+        .get(counter_id.as_str())
+        .unwrap();
+    let expect = (num_tasks * num_rounds).to_string();
+    assert_eq!(row[0], expect);
+} // end of fetch_acquire_concurrent_rmw
+
 #[tokio::test]
 async fn delete_ok() {
     let chosen_key = "Palau";
     let cfg = AppInMemoryDbCfg {
         alias: "Sheipa".to_string(),
         max_items: 10,
+        persistence: None,
     };
-    let dstore = AppInMemoryDStore::new(&cfg);
+    let dstore = AppInMemoryDStore::new(&cfg).unwrap();
     assert_eq!(dstore.create_table(UT_TABLE_LABEL_A).await.is_ok(), true);
     let new_data: AppInMemUpdateData = {
         let mut out = HashMap::new();
@@ -380,8 +517,9 @@ async fn access_nonexist_table() {
     let cfg = AppInMemoryDbCfg {
         alias: "Sheipa".to_string(),
         max_items: 10,
+        persistence: None,
     };
-    let dstore = AppInMemoryDStore::new(&cfg);
+    let dstore = AppInMemoryDStore::new(&cfg).unwrap();
     let new_data: AppInMemUpdateData = {
         let mut out = HashMap::new();
         let t1 = {
@@ -407,8 +545,9 @@ async fn exceed_limit_error() {
     let cfg = AppInMemoryDbCfg {
         alias: "Sheipa".to_string(),
         max_items: 5,
+        persistence: None,
     };
-    let dstore = AppInMemoryDStore::new(&cfg);
+    let dstore = AppInMemoryDStore::new(&cfg).unwrap();
     assert_eq!(dstore.create_table(UT_TABLE_LABEL_A).await.is_ok(), true);
     let new_data: AppInMemUpdateData = {
         let mut out = HashMap::new();
@@ -483,8 +622,9 @@ async fn filter_key_ok() {
     let cfg = AppInMemoryDbCfg {
         alias: "Alishan".to_string(),
         max_items: 8,
+        persistence: None,
     };
-    let dstore = AppInMemoryDStore::new(&cfg);
+    let dstore = AppInMemoryDStore::new(&cfg).unwrap();
     assert_eq!(dstore.create_table(UT_TABLE_LABEL_A).await.is_ok(), true);
     let search_id = "hemu";
     let init_data: [Vec<String>; 4] = [
@@ -535,3 +675,227 @@ async fn filter_key_ok() {
     assert_eq!(actual_keys, expect_keys);
     assert_eq!(actual_keys.contains("gopher-neihts"), false);
 } // end of filter_key_ok
+
+fn ut_index_rows(rawdata: &[(&str, &str, &str)]) -> AppInMemUpdateData {
+    let rows = rawdata
+        .iter()
+        .map(|(id, owner, time)| (id.to_string(), vec![owner.to_string(), time.to_string()]))
+        .collect::<HashMap<String, Vec<String>>>();
+    HashMap::from([(UT_TABLE_LABEL_A.to_string(), rows)])
+}
+
+fn ut_time_key(t: &str) -> AppInMemIdxKey {
+    let t = chrono::DateTime::parse_from_rfc3339(t).unwrap();
+    AppInMemIdxKey::DateTime(t.to_utc())
+}
+
+#[tokio::test]
+async fn index_range_ok() {
+    let cfg = AppInMemoryDbCfg {
+        alias: "Alishan".to_string(),
+        max_items: 8,
+        persistence: None,
+    };
+    let dstore = AppInMemoryDStore::new(&cfg).unwrap();
+    assert!(dstore.create_table(UT_TABLE_LABEL_A).await.is_ok());
+    #[rustfmt::skip]
+    let rawdata = [
+        ("sa0", "127", "2023-05-01T10:05:00+08:00"),
+        ("sa1", "45", "2023-05-01T03:00:00+00:00"),
+        ("sa2", "1380", "2023-05-01T01:59:00-02:00"),
+        ("sa3", "9", "not-a-time"),
+    ];
+    let result = dstore.save(ut_index_rows(&rawdata)).await;
+    assert_eq!(result.unwrap(), 4);
+    // index built from existing rows
+    let result = dstore
+        .create_index(UT_TABLE_LABEL_A, "by-time", 1, AppInMemIdxKeyType::DateTime)
+        .await;
+    assert!(result.is_ok());
+    let result = dstore
+        .create_index(
+            UT_TABLE_LABEL_A,
+            "by-owner",
+            0,
+            AppInMemIdxKeyType::UInteger,
+        )
+        .await;
+    assert!(result.is_ok());
+    let result = dstore
+        .create_index(
+            UT_TABLE_LABEL_A,
+            "by-owner",
+            1,
+            AppInMemIdxKeyType::DateTime,
+        )
+        .await;
+    assert_eq!(result.unwrap_err().code, AppErrorCode::InvalidInput);
+
+    // time with different timezone offsets should be ordered correctly
+    let range = (
+        Bound::Included(ut_time_key("2023-05-01T02:00:00+00:00")),
+        Bound::Excluded(ut_time_key("2023-05-01T03:59:00+00:00")),
+    );
+    let tbl_label = UT_TABLE_LABEL_A.to_string();
+    let actual = dstore.range_keys(tbl_label.clone(), "by-time", range).await;
+    assert_eq!(actual.unwrap(), vec!["sa0".to_string(), "sa1".to_string()]);
+    let range = (
+        Bound::Excluded(AppInMemIdxKey::UInteger(9)),
+        Bound::Included(AppInMemIdxKey::UInteger(127)),
+    );
+    let actual = dstore
+        .range_keys(tbl_label.clone(), "by-owner", range.clone())
+        .await;
+    assert_eq!(actual.unwrap(), vec!["sa1".to_string(), "sa0".to_string()]);
+
+    // index maintained on update and delete
+    #[rustfmt::skip]
+    let rawdata = [
+        ("sa1", "2001", "2023-05-01T03:00:00+00:00"),
+        ("sa4", "100", "2023-05-01T02:30:00+00:00"),
+    ];
+    let result = dstore.save(ut_index_rows(&rawdata)).await;
+    assert_eq!(result.unwrap(), 2);
+    let info: AppInMemDeleteInfo = HashMap::from([(tbl_label.clone(), vec!["sa0".to_string()])]);
+    let result = dstore.delete(info).await;
+    assert_eq!(result.unwrap(), 1);
+    let actual = dstore
+        .range_keys(tbl_label.clone(), "by-owner", range)
+        .await;
+    assert_eq!(actual.unwrap(), vec!["sa4".to_string()]);
+    let range = (Bound::Unbounded, Bound::Unbounded);
+    let actual = dstore.range_keys(tbl_label.clone(), "by-time", range).await;
+    let expect = ["sa4", "sa1", "sa2"].map(String::from).to_vec();
+    assert_eq!(actual.unwrap(), expect);
+
+    // empty range
+    let t = ut_time_key("2023-05-01T03:00:00+00:00");
+    let range = (Bound::Excluded(t.clone()), Bound::Excluded(t));
+    let actual = dstore.range_keys(tbl_label.clone(), "by-time", range).await;
+    assert!(actual.unwrap().is_empty());
+    let range = (Bound::Unbounded, Bound::Unbounded);
+    let result = dstore.range_keys(tbl_label, "nonexist", range).await;
+    assert_eq!(result.unwrap_err().code, AppErrorCode::InvalidInput);
+} // end of index_range_ok
+
+fn ut_persist_cfg(
+    alias: &str,
+    fsync: AppInMemFsyncPolicy,
+    snapshot_interval: u32,
+) -> (AppInMemoryDbCfg, PathBuf) {
+    let dir = std::env::temp_dir().join(format!(
+        "order-utest-inmem-{}-{}",
+        alias,
+        std::process::id()
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    let persistence = AppInMemPersistCfg {
+        path: dir.to_str().unwrap().to_string(),
+        fsync,
+        snapshot_interval,
+    };
+    let cfg = AppInMemoryDbCfg {
+        alias: alias.to_string(),
+        max_items: 10,
+        persistence: Some(persistence),
+    };
+    (cfg, dir)
+}
+
+fn ut_persist_rows(ids: [&str; 3]) -> AppInMemUpdateData {
+    let rows = ids
+        .into_iter()
+        .enumerate()
+        .map(|(idx, id)| {
+            let row = vec![format!("item-{idx}"), format!("{}", idx * 3)];
+            (id.to_string(), row)
+        })
+        .collect::<HashMap<String, Vec<String>>>();
+    HashMap::from([(UT_TABLE_LABEL_B.to_string(), rows)])
+}
+
+async fn ut_persist_fetch_all(dstore: &AppInMemoryDStore, ids: [&str; 3]) -> HashSet<String> {
+    let keys: AppInMemFetchKeys = HashMap::from([(
+        UT_TABLE_LABEL_B.to_string(),
+        ids.into_iter().map(String::from).collect(),
+    )]);
+    let result = dstore.fetch(keys).await;
+    assert!(result.is_ok());
+    let mut fetched = result.unwrap();
+    let table = fetched.remove(UT_TABLE_LABEL_B).unwrap();
+    table.into_keys().collect()
+}
+
+#[tokio::test]
+async fn persist_replay_log_ok() {
+    let (cfg, dir) = ut_persist_cfg("replay", AppInMemFsyncPolicy::Always, 100);
+    let ids = ["Dh8u", "Nk3r", "w9Pq"];
+    {
+        let dstore = AppInMemoryDStore::try_build(&cfg, "").unwrap();
+        assert!(dstore.create_table(UT_TABLE_LABEL_B).await.is_ok());
+        let result = dstore.save(ut_persist_rows(ids)).await;
+        assert_eq!(result.unwrap(), 3);
+        let info: AppInMemDeleteInfo =
+            HashMap::from([(UT_TABLE_LABEL_B.to_string(), vec![ids[1].to_string()])]);
+        let result = dstore.delete(info).await;
+        assert_eq!(result.unwrap(), 1);
+    } // drop the store, as if the service is restarted
+    assert!(dir.join("wal.jsonl").exists());
+    assert!(!dir.join("snapshot.json").exists());
+    let dstore = AppInMemoryDStore::try_build(&cfg, "").unwrap();
+    let actual = ut_persist_fetch_all(&dstore, ids).await;
+    let expect = HashSet::from([ids[0].to_string(), ids[2].to_string()]);
+    assert_eq!(actual, expect);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn persist_snapshot_truncate_log() {
+    let (cfg, dir) = ut_persist_cfg("snapshot", AppInMemFsyncPolicy::Never, 2);
+    let ids = ["iu3R", "Ge0w", "mQ5z"];
+    {
+        let dstore = AppInMemoryDStore::try_build(&cfg, "").unwrap();
+        assert!(dstore.create_table(UT_TABLE_LABEL_B).await.is_ok());
+        let result = dstore.save(ut_persist_rows(ids)).await;
+        assert_eq!(result.unwrap(), 3);
+        // snapshot taken on the 2nd operation, the log should be empty
+        let wal_sz = std::fs::metadata(dir.join("wal.jsonl")).unwrap().len();
+        assert_eq!(wal_sz, 0);
+        assert!(dir.join("snapshot.json").exists());
+        let info: AppInMemDeleteInfo =
+            HashMap::from([(UT_TABLE_LABEL_B.to_string(), vec![ids[0].to_string()])]);
+        let result = dstore.delete(info).await;
+        assert_eq!(result.unwrap(), 1);
+    }
+    let dstore = AppInMemoryDStore::try_build(&cfg, "").unwrap();
+    let actual = ut_persist_fetch_all(&dstore, ids).await;
+    let expect = HashSet::from([ids[1].to_string(), ids[2].to_string()]);
+    assert_eq!(actual, expect);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn persist_corrupted_log_error() {
+    let (cfg, dir) = ut_persist_cfg("corrupted", AppInMemFsyncPolicy::Periodic(50), 100);
+    std::fs::create_dir_all(&dir).unwrap();
+    let content = "{\"op\":\"CreateTable\",\"label\":\"abc\"}\n\
+                   {\"op\":\"Sav\n\
+                   {\"op\":\"CreateTable\",\"label\":\"xyz\"}\n";
+    std::fs::write(dir.join("wal.jsonl"), content).unwrap();
+    let result = AppInMemoryDStore::try_build(&cfg, "");
+    assert!(result.is_err());
+    if let Err(e) = result {
+        assert_eq!(e.code, AppErrorCode::DataCorruption);
+    }
+    let result = AppInMemoryDStore::new(&cfg);
+    assert!(result.is_err());
+    if let Err(e) = result {
+        assert_eq!(e.code, AppErrorCode::DataCorruption);
+    }
+    // incomplete record at the end of log is discarded
+    let content = "{\"op\":\"CreateTable\",\"label\":\"abc\"}\n{\"op\":\"Sav";
+    std::fs::write(dir.join("wal.jsonl"), content).unwrap();
+    let result = AppInMemoryDStore::try_build(&cfg, "");
+    assert!(result.is_ok());
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,53 @@
+use order::api::web::dto::{CartAccessDto, CartCollaboratorDto, CartShareDto};
+use order::model::CartShareModel;
+
+fn ut_gen_share_req(data: &[(u32, CartAccessDto)]) -> CartShareDto {
+    let collaborators = data
+        .iter()
+        .map(|(usr_id, access)| CartCollaboratorDto {
+            usr_id: *usr_id,
+            access: *access,
+        })
+        .collect();
+    CartShareDto { collaborators }
+}
+
+#[test]
+fn share_req_ok() {
+    let (owner, seq_num) = (5566u32, 1u8);
+    let req = ut_gen_share_req(&[(5567, CartAccessDto::Edit), (5568, CartAccessDto::Read)]);
+    let result = CartShareModel::try_from_req(owner, seq_num, req);
+    assert!(result.is_ok());
+    if let Ok(obj) = result {
+        assert_eq!(obj.collaborators.len(), 2);
+        [
+            (owner, CartAccessDto::Edit, true),
+            (5567, CartAccessDto::Edit, true),
+            (5567, CartAccessDto::Read, true),
+            (5568, CartAccessDto::Edit, false),
+            (5568, CartAccessDto::Read, true),
+            (5569, CartAccessDto::Read, false),
+        ]
+        .into_iter()
+        .map(|(usr_id, required, expect)| {
+            assert_eq!(obj.permitted(usr_id, required), expect);
+        })
+        .count();
+    }
+}
+
+#[test]
+fn share_req_invalid_user() {
+    let (owner, seq_num) = (5566u32, 1u8);
+    let req = ut_gen_share_req(&[
+        (5567, CartAccessDto::Edit),
+        (owner, CartAccessDto::Read),
+        (5568, CartAccessDto::Read),
+        (5567, CartAccessDto::Read),
+    ]);
+    let result = CartShareModel::try_from_req(owner, seq_num, req);
+    assert!(result.is_err());
+    if let Err(e) = result {
+        assert_eq!(e.usr_ids, vec![5566, 5567]);
+    }
+}
@@ -1,3 +1,4 @@
+mod cart;
 mod oorder;
 mod product_policy;
 mod product_price;
@@ -7,7 +8,10 @@ use order::model::{ProductPolicyModel, ProductStockModel, StockLevelModelSet};
 
 pub(crate) fn ut_clone_productpolicy(src: &ProductPolicyModel) -> ProductPolicyModel {
     ProductPolicyModel {
+        store_id: src.store_id,
         product_id: src.product_id,
+        version: src.version,
+        effective_from: src.effective_from,
         auto_cancel_secs: src.auto_cancel_secs,
         warranty_hours: src.warranty_hours,
         is_create: src.is_create,
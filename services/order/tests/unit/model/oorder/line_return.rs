@@ -31,9 +31,10 @@ fn ut_saved_orderline_setup(dt_now: DateTime<FixedOffset>, store_id: u32) -> Vec
                 OrderLinePriceModel::from((unit, total)),
                 OrderLineAppliedPolicyModel {
                     reserved_until, warranty_until,
+                    version: 0,
                 },
                 OrderLineQuantityModel {
-                    reserved, paid, paid_last_update,
+                    reserved, paid, paid_last_update, cancelled: 0, delivered: paid,
                 },
                 ProdAttriPriceModel::from((attr_lastupdate, None)),
             );
@@ -216,6 +217,41 @@ fn filter_request_invalid_qty() {
     }
 }
 
+#[test]
+fn filter_request_undelivered() {
+    let seller_id = 145;
+    let dt_now = Local::now().fixed_offset();
+    let mut o_lines = ut_saved_orderline_setup(dt_now, seller_id);
+    // buyer has not received some of paid items yet
+    o_lines
+        .iter_mut()
+        .filter(|m| m.id().product_id() == 890)
+        .map(|m| {
+            m.qty.delivered = 2;
+        })
+        .count();
+    let o_returns = ut_saved_oline_return_setup(dt_now, seller_id);
+    let data = [(890, 3), (257, 2)]
+        .into_iter()
+        .map(|(product_id, quantity)| OrderLineReturnReqDto {
+            seller_id,
+            product_id,
+            quantity,
+            attr_set_seq: 0,
+        })
+        .collect::<Vec<_>>();
+    let result = OrderReturnModel::filter_requests(data, o_lines, o_returns);
+    assert!(result.is_err());
+    if let Err(es) = result {
+        assert_eq!(es.len(), 1);
+        assert_eq!(es[0].product_id, 890);
+        assert!(matches!(
+            es[0].reason,
+            OrderLineReturnErrorReason::QtyLimitExceed
+        ));
+    }
+}
+
 #[test]
 fn filter_request_err_duplicate() {
     let seller_id = 145;
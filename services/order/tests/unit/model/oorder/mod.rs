@@ -2,4 +2,5 @@ mod contact;
 mod currency;
 mod line_return;
 mod order_line;
+mod shipment;
 mod shipping;
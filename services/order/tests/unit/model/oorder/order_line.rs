@@ -8,8 +8,8 @@ use ecommerce_common::error::AppErrorCode;
 
 use order::api::dto::ProdAttrValueDto;
 use order::api::web::dto::{
-    OlineProductAttrDto, OrderCreateRespOkDto, OrderLineCreateErrorDto, OrderLineCreateErrorReason,
-    OrderLineRsvReqDto,
+    OlineProductAttrDto, OrderCreateRespOkDto, OrderLineCancelErrorReason, OrderLineCreateErrorDto,
+    OrderLineCreateErrorReason, OrderLineRsvReqDto,
 };
 use order::model::{
     OrderLineAppliedPolicyModel, OrderLineIdentity, OrderLineModel, OrderLineModelSet,
@@ -34,10 +34,11 @@ pub(super) fn ut_setup_order_lines(
             let id_ = OrderLineIdentity::from(d.0);
             let price= OrderLinePriceModel::from(d.1);
             let qty = OrderLineQuantityModel {
-                reserved: d.2, paid: d.3, paid_last_update: d.4,
+                reserved: d.2, paid: d.3, paid_last_update: d.4, cancelled: 0, delivered: 0,
             };
             let policy = OrderLineAppliedPolicyModel {
                 reserved_until: d.5, warranty_until: d.6,
+                version: 0,
             };
             let attr_lastupdate = d.5 - Duration::days(15);
             let attrs_charge = ProdAttriPriceModel::from((attr_lastupdate, d.7));
@@ -50,7 +51,10 @@ pub(super) fn ut_setup_order_lines(
 fn convert_from_req_dto_without_rsv_limit_ok() {
     let (seller_id, product_id) = (19, 146);
     let policym = ProductPolicyModel {
+        store_id: seller_id,
         product_id,
+        version: 2,
+        effective_from: LocalTime::now().fixed_offset(),
         is_create: false,
         auto_cancel_secs: 69,
         warranty_hours: 23,
@@ -78,13 +82,17 @@ fn convert_from_req_dto_without_rsv_limit_ok() {
     let timenow = LocalTime::now().fixed_offset();
     let expect_reserved_time = timenow + Duration::seconds(69i64);
     assert!(m.policy.reserved_until <= expect_reserved_time);
+    assert_eq!(m.policy.version, 2);
 }
 
 #[test]
 fn convert_from_req_dto_with_rsv_limit_ok() {
     let (seller_id, product_id) = (19, 146);
     let policym = ProductPolicyModel {
+        store_id: seller_id,
         product_id,
+        version: 0,
+        effective_from: LocalTime::now().fixed_offset(),
         is_create: false,
         auto_cancel_secs: 69,
         warranty_hours: 23,
@@ -115,7 +123,10 @@ fn convert_from_req_dto_with_rsv_limit_ok() {
 fn convert_from_req_dto_violate_rsv_limit() {
     let (seller_id, product_id) = (19, 146);
     let policym = ProductPolicyModel {
+        store_id: seller_id,
         product_id,
+        version: 0,
+        effective_from: LocalTime::now().fixed_offset(),
         is_create: false,
         auto_cancel_secs: 180,
         warranty_hours: 48,
@@ -146,7 +157,10 @@ fn convert_from_req_dto_violate_rsv_limit() {
 fn convert_from_req_with_attributes_ok() {
     let (seller_id, product_id) = (19, 146);
     let policym = ProductPolicyModel {
+        store_id: seller_id,
         product_id,
+        version: 0,
+        effective_from: LocalTime::now().fixed_offset(),
         is_create: false,
         auto_cancel_secs: 69,
         warranty_hours: 23,
@@ -201,7 +215,10 @@ fn convert_from_req_with_attributes_ok() {
 fn convert_from_req_with_attributes_error() {
     let (seller_id, product_id) = (19, 146);
     let policym = ProductPolicyModel {
+        store_id: seller_id,
         product_id,
+        version: 0,
+        effective_from: LocalTime::now().fixed_offset(),
         is_create: false,
         auto_cancel_secs: 69,
         warranty_hours: 23,
@@ -265,7 +282,10 @@ fn convert_from_req_with_attributes_error() {
 fn convert_from_req_dto_product_id_mismatch() {
     let (seller_id, product_id) = (19, 146);
     let policym = ProductPolicyModel {
+        store_id: seller_id,
         product_id,
+        version: 0,
+        effective_from: LocalTime::now().fixed_offset(),
         is_create: false,
         auto_cancel_secs: 180,
         warranty_hours: 48,
@@ -292,6 +312,41 @@ fn convert_from_req_dto_product_id_mismatch() {
     }
 }
 
+#[test]
+fn convert_from_req_dto_store_id_mismatch() {
+    let (seller_id, product_id) = (19, 146);
+    let policym = ProductPolicyModel {
+        store_id: seller_id + 1,
+        product_id,
+        version: 0,
+        effective_from: LocalTime::now().fixed_offset(),
+        is_create: false,
+        auto_cancel_secs: 180,
+        warranty_hours: 48,
+        max_num_rsv: 10,
+        min_num_rsv: 0,
+    };
+    let pricem = {
+        let start_after = DateTime::parse_from_rfc3339("2022-10-28T10:16:54+05:00").unwrap();
+        let end_before = DateTime::parse_from_rfc3339("2022-10-31T06:11:50+02:00").unwrap();
+        let attr_lastupdate = DateTime::parse_from_rfc3339("2022-10-03T07:56:04+03:30").unwrap();
+        let ts = [start_after, end_before, attr_lastupdate];
+        ProductPriceModel::from((product_id, 60, ts, None))
+    };
+    let data = OrderLineRsvReqDto {
+        seller_id,
+        product_id,
+        quantity: 2,
+        applied_attr: None,
+    };
+    let result = OrderLineModel::try_from(data, &policym, &pricem);
+    assert!(result.is_err());
+    if let Err(e) = result {
+        assert_eq!(e.code, AppErrorCode::DataCorruption);
+        assert_eq!(e.detail.as_deref(), Some("product-policy, store"));
+    }
+}
+
 #[test]
 fn gen_order_id_seq() {
     use std::collections::hash_map::RandomState;
@@ -498,6 +553,91 @@ fn update_payments_old_record_omitted() {
     );
 } // end of fn update_payments_old_record_omitted
 
+#[rustfmt::skip]
+#[test]
+fn update_payments_cancelled_line() {
+    let dt_now = LocalTime::now().fixed_offset();
+    let reserved_until = dt_now + Duration::hours(1);
+    let warranty_until = dt_now + Duration::days(1);
+    let seller_id = 123;
+    let mocked_data = vec![
+        ((seller_id, 812u64, 0u16), (7u32, 70u32), 10u32, 0u32, None, reserved_until, warranty_until, None),
+        ((seller_id, 890, 0), (10, 90), 9, 0, None, reserved_until, warranty_until, None),
+    ];
+    let mut models = ut_setup_order_lines(mocked_data);
+    models[1].qty.cancelled = 9;
+    let d_lines = [(seller_id, 890u64, 0u16, 9u32), (seller_id, 812, 0, 10)]
+        .into_iter()
+        .map(|d| OrderLinePaidUpdateDto {
+            seller_id: d.0, product_id:  d.1, qty: d.3,
+            attr_set_seq: d.2,
+        })
+        .collect::<Vec<_>>();
+    let errors = OrderLineModel::update_payments(&mut models, d_lines, dt_now);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].product_id, 890);
+    assert!(matches!(errors[0].reason, OrderLinePayUpdateErrorReason::Cancelled));
+    assert_eq!(models[0].qty.paid, 10);
+    assert_eq!(models[1].qty.paid, 0); // not modified
+} // end of fn update_payments_cancelled_line
+
+#[rustfmt::skip]
+#[test]
+fn cancel_unpaid_ok() {
+    let dt_now = LocalTime::now().fixed_offset();
+    let reserved_until = dt_now + Duration::hours(1);
+    let warranty_until = dt_now + Duration::days(1);
+    let seller_id = 123;
+    let mocked_data = vec![
+        ((seller_id, 812u64, 0u16), (7u32, 70u32), 10u32, 3u32, Some(dt_now), reserved_until, warranty_until, None),
+        ((seller_id, 890, 0), (10, 90), 9, 0, None, reserved_until, warranty_until, None),
+        ((seller_id, 894, 0), (10, 40), 4, 0, None, reserved_until, warranty_until, None),
+    ];
+    let mut models = ut_setup_order_lines(mocked_data);
+    let pids = vec![
+        OrderLineIdentity::from((seller_id, 812, 0)),
+        OrderLineIdentity::from((seller_id, 890, 0)),
+    ];
+    let errors = OrderLineModel::cancel_unpaid(&mut models, pids, dt_now);
+    assert!(errors.is_empty());
+    assert_eq!(models[0].qty.cancelled, 7);
+    assert_eq!(models[0].qty.paid, 3);
+    assert!(!models[0].qty.has_unpaid());
+    assert_eq!(models[1].qty.cancelled, 9);
+    assert_eq!(models[2].qty.cancelled, 0);
+    assert!(models[2].qty.has_unpaid());
+} // end of fn cancel_unpaid_ok
+
+#[rustfmt::skip]
+#[test]
+fn cancel_unpaid_error() {
+    let dt_now = LocalTime::now().fixed_offset();
+    let reserved_until = dt_now + Duration::hours(1);
+    let warranty_until = dt_now + Duration::days(1);
+    let seller_id = 123;
+    let mocked_data = vec![
+        ((seller_id, 812u64, 0u16), (7u32, 70u32), 10u32, 10u32, Some(dt_now), reserved_until, warranty_until, None),
+        ((seller_id, 890, 0), (10, 90), 9, 0, None, reserved_until, warranty_until, None),
+        ((seller_id, 894, 0), (10, 40), 4, 0, None, dt_now - Duration::minutes(1), warranty_until, None),
+        ((seller_id, 897, 0), (10, 30), 3, 0, None, reserved_until, warranty_until, None),
+    ];
+    let mut models = ut_setup_order_lines(mocked_data);
+    models[1].qty.cancelled = 9;
+    let pids = [(seller_id, 812u64, 0u16), (seller_id, 890, 0), (seller_id, 894, 0),
+                (seller_id, 897, 0), (seller_id, 899, 0)]
+        .into_iter().map(OrderLineIdentity::from).collect::<Vec<_>>();
+    let errors = OrderLineModel::cancel_unpaid(&mut models, pids, dt_now);
+    assert_eq!(errors.len(), 4);
+    let actual = errors.iter().map(|e| e.product_id).collect::<Vec<_>>();
+    assert_eq!(actual, vec![812, 890, 894, 899]);
+    assert!(matches!(errors[0].reason, OrderLineCancelErrorReason::AlreadyPaid));
+    assert!(matches!(errors[1].reason, OrderLineCancelErrorReason::DuplicateCancel));
+    assert!(matches!(errors[2].reason, OrderLineCancelErrorReason::ReservationExpired));
+    assert!(matches!(errors[3].reason, OrderLineCancelErrorReason::NotExist));
+    // nothing is modified if any error occurred
+    assert_eq!(models[3].qty.cancelled, 0);
+} // end of fn cancel_unpaid_error
+
 #[rustfmt::skip]
 fn convert_to_olset_common() -> OrderLineModelSet {
     let create_time = LocalTime::now().fixed_offset();
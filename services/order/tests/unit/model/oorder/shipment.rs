@@ -0,0 +1,220 @@
+use chrono::{DateTime, Duration, FixedOffset, Local};
+
+use order::api::dto::{ShipmentLineDto, ShippingMethod};
+use order::api::rpc::dto::{ShipmentCreateReqDto, ShipmentErrorReason, ShipmentLineErrorReason};
+use order::api::web::dto::ShipmentReceiptErrorReason;
+use order::model::{
+    OrderLineAppliedPolicyModel, OrderLineIdentity, OrderLineModel, OrderLinePriceModel,
+    OrderLineQuantityModel, ProdAttriPriceModel, ShipmentLineModel, ShipmentModel,
+};
+
+#[rustfmt::skip]
+fn ut_saved_orderline_setup(dt_now: DateTime<FixedOffset>, store_id: u32) -> Vec<OrderLineModel> {
+    let paid_last_update = dt_now - Duration::days(1);
+    let reserved_until = dt_now + Duration::hours(2);
+    let warranty_until = dt_now + Duration::days(8);
+    let attr_lastupdate = dt_now - Duration::days(10);
+    [
+        ((3026, 0), (7, 70), 10, 0, None),
+        ((3027, 0), (11, 99), 9, 7, Some(paid_last_update)),
+        ((3027, 1), (13, 39), 3, 3, Some(paid_last_update)),
+    ]
+    .into_iter()
+    .map(|((product_id, attr_seq), (unit, total), reserved, paid, paid_last_update)| {
+        let args = (
+            OrderLineIdentity::from((store_id, product_id, attr_seq)),
+            OrderLinePriceModel::from((unit, total)),
+            OrderLineAppliedPolicyModel {reserved_until, warranty_until, version: 0},
+            OrderLineQuantityModel {
+                reserved, paid, paid_last_update, cancelled: 0, delivered: 0,
+            },
+            ProdAttriPriceModel::from((attr_lastupdate, None)),
+        );
+        OrderLineModel::from(args)
+    })
+    .collect::<Vec<_>>()
+}
+
+fn ut_shipment_req(
+    seller_id: u32,
+    tracking_num: &str,
+    lines: &[(u64, u16, u32)],
+) -> ShipmentCreateReqDto {
+    ShipmentCreateReqDto {
+        order_id: "0e2a7b31".to_string(),
+        seller_id,
+        carrier: ShippingMethod::FedEx,
+        tracking_num: tracking_num.to_string(),
+        lines: lines
+            .iter()
+            .map(|(product_id, attr_set_seq, qty)| ShipmentLineDto {
+                product_id: *product_id,
+                attr_set_seq: *attr_set_seq,
+                qty: *qty,
+            })
+            .collect(),
+    }
+}
+
+#[test]
+fn convert_from_req_ok() {
+    let dt_now = Local::now().fixed_offset();
+    let req = ut_shipment_req(107, "TW0032a87", &[(3027, 0, 4), (3027, 1, 2)]);
+    let result = ShipmentModel::try_from_req(req, dt_now);
+    assert!(result.is_ok());
+    if let Ok(m) = result {
+        assert_eq!(m.seller_id, 107);
+        assert_eq!(m.tracking_num.as_str(), "TW0032a87");
+        assert!(m.received_time.is_none());
+        assert_eq!(m.lines.len(), 2);
+    }
+}
+
+#[test]
+fn convert_from_req_error() {
+    let dt_now = Local::now().fixed_offset();
+    let mut req = ut_shipment_req(107, "TW0032a87", &[(3027, 0, 4)]);
+    req.carrier = ShippingMethod::Unknown;
+    let result = ShipmentModel::try_from_req(req, dt_now);
+    assert!(result.is_err());
+    if let Err(e) = result {
+        assert!(matches!(
+            e.reason,
+            Some(ShipmentErrorReason::InvalidCarrier)
+        ));
+    }
+    let req = ut_shipment_req(107, "TW-0032", &[(3027, 0, 4)]);
+    let result = ShipmentModel::try_from_req(req, dt_now);
+    assert!(result.is_err());
+    if let Err(e) = result {
+        assert!(matches!(
+            e.reason,
+            Some(ShipmentErrorReason::InvalidTrackingNum)
+        ));
+    }
+    let req = ut_shipment_req(107, "TW0032a87", &[]);
+    let result = ShipmentModel::try_from_req(req, dt_now);
+    assert!(result.is_err());
+    if let Err(e) = result {
+        assert!(matches!(e.reason, Some(ShipmentErrorReason::EmptyLines)));
+    }
+    let req = ut_shipment_req(
+        107,
+        "TW0032a87",
+        &[(3027, 0, 4), (3027, 1, 0), (3027, 0, 1)],
+    );
+    let result = ShipmentModel::try_from_req(req, dt_now);
+    assert!(result.is_err());
+    if let Err(e) = result {
+        assert!(e.reason.is_none());
+        assert_eq!(e.lines.len(), 2);
+        assert_eq!(e.lines[0].attr_set_seq, 1);
+        assert!(matches!(
+            e.lines[0].reason,
+            ShipmentLineErrorReason::InvalidQuantity
+        ));
+        assert_eq!(e.lines[1].attr_set_seq, 0);
+        assert!(matches!(
+            e.lines[1].reason,
+            ShipmentLineErrorReason::DuplicateLine
+        ));
+    }
+}
+
+#[test]
+fn validate_against_saved_ok() {
+    let (seller_id, dt_now) = (107, Local::now().fixed_offset());
+    let o_lines = ut_saved_orderline_setup(dt_now, seller_id);
+    let saved = {
+        let req = ut_shipment_req(seller_id, "TW0032a87", &[(3027, 0, 4)]);
+        vec![ShipmentModel::try_from_req(req, dt_now).unwrap()]
+    };
+    let req = ut_shipment_req(seller_id, "TW0032a88", &[(3027, 0, 3), (3027, 1, 3)]);
+    let m = ShipmentModel::try_from_req(req, dt_now).unwrap();
+    let result = m.validate(&o_lines, &saved);
+    assert!(result.is_ok());
+}
+
+#[test]
+fn validate_against_saved_error() {
+    let (seller_id, dt_now) = (107, Local::now().fixed_offset());
+    let o_lines = ut_saved_orderline_setup(dt_now, seller_id);
+    let saved = {
+        let req = ut_shipment_req(seller_id, "TW0032a87", &[(3027, 0, 4)]);
+        vec![ShipmentModel::try_from_req(req, dt_now).unwrap()]
+    };
+    let req = ut_shipment_req(seller_id, "TW0032a87", &[(3027, 1, 1)]);
+    let m = ShipmentModel::try_from_req(req, dt_now).unwrap();
+    let result = m.validate(&o_lines, &saved);
+    assert!(result.is_err());
+    if let Err(e) = result {
+        assert!(matches!(
+            e.reason,
+            Some(ShipmentErrorReason::DuplicateTrackingNum)
+        ));
+    }
+    // unpaid line cannot be shipped, and the number of shipped items should
+    // not exceed the paid quantity
+    let req = ut_shipment_req(
+        seller_id,
+        "TW0032a88",
+        &[(3026, 0, 1), (3027, 0, 4), (3028, 0, 1)],
+    );
+    let m = ShipmentModel::try_from_req(req, dt_now).unwrap();
+    let result = m.validate(&o_lines, &saved);
+    assert!(result.is_err());
+    if let Err(e) = result {
+        assert!(e.reason.is_none());
+        assert_eq!(e.lines.len(), 3);
+        e.lines
+            .iter()
+            .map(|d| match d.product_id {
+                3026 | 3027 => assert!(matches!(d.reason, ShipmentLineErrorReason::QtyLimitExceed)),
+                3028 => assert!(matches!(d.reason, ShipmentLineErrorReason::NotExist)),
+                _others => panic!("unexpected product"),
+            })
+            .count();
+    }
+}
+
+#[test]
+fn confirm_receipt_ok() {
+    let (seller_id, dt_now) = (107, Local::now().fixed_offset());
+    let mut o_lines = ut_saved_orderline_setup(dt_now, seller_id);
+    let mut m = ShipmentModel {
+        seller_id,
+        carrier: ShippingMethod::UPS,
+        tracking_num: "UPS1234".to_string(),
+        create_time: dt_now - Duration::days(2),
+        received_time: None,
+        lines: vec![
+            ShipmentLineModel {
+                product_id: 3027,
+                attr_set_seq: 0,
+                qty: 5,
+            },
+            ShipmentLineModel {
+                product_id: 3027,
+                attr_set_seq: 1,
+                qty: 2,
+            },
+        ],
+    };
+    let result = m.confirm_receipt(&mut o_lines, dt_now);
+    assert!(result.is_ok());
+    assert_eq!(m.received_time, Some(dt_now));
+    let delivered = o_lines
+        .iter()
+        .map(|l| (l.id().product_id(), l.id().attrs_seq_num(), l.qty.delivered))
+        .collect::<Vec<_>>();
+    assert_eq!(delivered, vec![(3026, 0, 0), (3027, 0, 5), (3027, 1, 2)]);
+    let result = m.confirm_receipt(&mut o_lines, dt_now);
+    assert!(result.is_err());
+    if let Err(reason) = result {
+        assert!(matches!(
+            reason,
+            ShipmentReceiptErrorReason::AlreadyReceived
+        ));
+    }
+    assert_eq!(o_lines[1].qty.delivered, 5);
+}
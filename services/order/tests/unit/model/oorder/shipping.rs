@@ -1,9 +1,12 @@
 use ecommerce_common::api::dto::{ContactDto, CountryCode, PhoneNumberDto, PhyAddrDto};
 use ecommerce_common::api::web::dto::{PhyAddrDistinctErrorReason, PhyAddrRegionErrorReason};
+use ecommerce_common::error::AppErrorCode;
 use ecommerce_common::model::order::PhyAddrModel;
 
 use order::api::dto::{ShippingDto, ShippingMethod, ShippingOptionDto};
-use order::model::ShippingModel;
+use order::api::rpc::dto::{StoreShippingFeeDto, StoreShippingMethodDto, StoreShippingSyncDto};
+use order::api::web::dto::{ShipOptionMethodErrorReason, ShipOptionSellerErrorReason};
+use order::model::{ShippingModel, StoreShippingModel};
 
 #[test]
 fn addr_convert_dto_ok() {
@@ -122,3 +125,151 @@ fn shipping_opt_convert_dto_error() {
         }
     }
 } // end of shipping_opt_convert_dto_error
+
+fn ut_setup_store_shipping(
+    store_id: u32,
+    data: Vec<(ShippingMethod, Vec<(CountryCode, u32)>)>,
+) -> StoreShippingSyncDto {
+    let methods = data
+        .into_iter()
+        .map(|(method, fees)| StoreShippingMethodDto {
+            method,
+            fees: fees
+                .into_iter()
+                .map(|(country, amount)| StoreShippingFeeDto { country, amount })
+                .collect(),
+        })
+        .collect();
+    StoreShippingSyncDto { store_id, methods }
+}
+
+#[test]
+fn store_shipping_convert_dto_ok() {
+    let data = ut_setup_store_shipping(
+        1009,
+        vec![
+            (
+                ShippingMethod::UPS,
+                vec![(CountryCode::TW, 120), (CountryCode::US, 980)],
+            ),
+            (ShippingMethod::FedEx, vec![(CountryCode::TH, 230)]),
+        ],
+    );
+    let result = StoreShippingModel::try_from(data);
+    assert!(result.is_ok());
+    if let Ok(m) = result {
+        assert_eq!(m.store_id, 1009);
+        assert_eq!(m.methods.len(), 2);
+        assert!(m.support(&ShippingMethod::UPS, Some(&CountryCode::US)));
+        assert!(m.support(&ShippingMethod::FedEx, None));
+        assert!(!m.support(&ShippingMethod::FedEx, Some(&CountryCode::TW)));
+        assert!(!m.support(&ShippingMethod::BlackCatExpress, None));
+    }
+}
+
+#[test]
+fn store_shipping_convert_dto_error() {
+    let invalid_data = [
+        vec![
+            (ShippingMethod::UPS, vec![(CountryCode::TW, 120)]),
+            (ShippingMethod::UPS, vec![(CountryCode::US, 980)]),
+        ],
+        vec![(ShippingMethod::Unknown, vec![(CountryCode::TW, 120)])],
+        vec![(ShippingMethod::FedEx, vec![])],
+        vec![(
+            ShippingMethod::FedEx,
+            vec![(CountryCode::ID, 35), (CountryCode::ID, 36)],
+        )],
+        vec![(ShippingMethod::FedEx, vec![(CountryCode::Unknown, 35)])],
+    ];
+    invalid_data
+        .into_iter()
+        .map(|d| {
+            let data = ut_setup_store_shipping(1009, d);
+            let result = StoreShippingModel::try_from(data);
+            assert!(result.is_err());
+            if let Err(e) = result {
+                assert_eq!(e.code, AppErrorCode::InvalidInput);
+            }
+        })
+        .count();
+}
+
+#[test]
+fn shipping_validate_options() {
+    let saved = [
+        ut_setup_store_shipping(
+            1009,
+            vec![
+                (ShippingMethod::UPS, vec![(CountryCode::TW, 120)]),
+                (
+                    ShippingMethod::FedEx,
+                    vec![(CountryCode::TW, 99), (CountryCode::TH, 230)],
+                ),
+            ],
+        ),
+        ut_setup_store_shipping(
+            1010,
+            vec![(ShippingMethod::BlackCatExpress, vec![(CountryCode::TH, 41)])],
+        ),
+    ]
+    .into_iter()
+    .map(|d| StoreShippingModel::try_from(d).unwrap())
+    .collect::<Vec<_>>();
+    let mut data = ShippingDto {
+        contact: ContactDto {
+            first_name: "Stu".to_string(),
+            last_name: "Allabom".to_string(),
+            emails: vec!["myacc@domain.org".to_string()],
+            phones: vec![PhoneNumberDto {
+                nation: 886,
+                number: "0019283".to_string(),
+            }],
+        },
+        address: None,
+        option: [
+            (1009, ShippingMethod::FedEx),
+            (1010, ShippingMethod::BlackCatExpress),
+        ]
+        .into_iter()
+        .map(|(seller_id, method)| ShippingOptionDto { seller_id, method })
+        .collect(),
+    };
+    let m = ShippingModel::try_from(data).ok().unwrap();
+    let result = m.validate_options(&saved);
+    assert!(result.is_ok());
+    // the seller 1010 does not deliver items to Taiwan, the seller 1011 never
+    // synchronized shipping methods
+    data = ShippingDto::from(m);
+    data.address = Some(PhyAddrDto {
+        country: CountryCode::TW,
+        region: "Yilan".to_string(),
+        city: "WaiAo".to_string(),
+        distinct: "shore-seaweed bay".to_string(),
+        street_name: None,
+        detail: "321-5".to_string(),
+    });
+    data.option.push(ShippingOptionDto {
+        seller_id: 1011,
+        method: ShippingMethod::UPS,
+    });
+    let m = ShippingModel::try_from(data).ok().unwrap();
+    let result = m.validate_options(&saved);
+    assert!(result.is_err());
+    if let Err(errs) = result {
+        assert_eq!(errs.len(), 3);
+        assert!(errs[0].is_none());
+        let e1 = errs[1].as_ref().unwrap();
+        assert!(e1.seller_id.is_none());
+        assert!(matches!(
+            e1.method,
+            Some(ShipOptionMethodErrorReason::NotSupport)
+        ));
+        let e2 = errs[2].as_ref().unwrap();
+        assert!(matches!(
+            e2.seller_id,
+            Some(ShipOptionSellerErrorReason::NotSupport)
+        ));
+        assert!(e2.method.is_none());
+    }
+} // end of fn shipping_validate_options
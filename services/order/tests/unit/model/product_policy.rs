@@ -1,3 +1,5 @@
+use chrono::{DateTime, Local};
+
 use super::ut_clone_productpolicy;
 use order::api::web::dto::ProductPolicyDto;
 use order::model::{ProductPolicyModel, ProductPolicyModelSet};
@@ -10,6 +12,7 @@ fn validate_newdata_ok() {
     ]
     .into_iter()
     .map(|d| ProductPolicyDto {
+        seller_id: 19,
         product_id: d.0,
         min_num_rsv: d.1,
         warranty_hours: d.2,
@@ -31,6 +34,7 @@ fn validate_newdata_error_limit() {
     ]
     .into_iter()
     .map(|d| ProductPolicyDto {
+        seller_id: 19,
         product_id: d.0,
         min_num_rsv: d.1,
         warranty_hours: d.2,
@@ -64,24 +68,33 @@ fn validate_newdata_error_limit() {
 
 #[test]
 fn update_instance_ok() {
+    let t_saved = DateTime::parse_from_rfc3339("2023-07-30T10:04:51+08:00").unwrap();
+    let t_now = Local::now().fixed_offset();
     let init_data = [
-        (20903u64, 731u32, 271u32, false, 0u16, 0u16),
-        (144, 380, 30098, false, 8, 0),
-        (144, 3597, 478, false, 0, 0),
-        (123, 3600, 480, true, 26, 15),
+        (
+            19u32, 20903u64, 0u32, 731u32, 271u32, false, 0u16, 0u16, t_saved,
+        ),
+        (19, 144, 0, 380, 30098, false, 8, 0, t_saved),
+        (19, 144, 1, 3597, 478, true, 0, 0, t_now),
+        (19, 123, 0, 3600, 480, true, 26, 15, t_now),
+        (18, 144, 0, 3590, 470, true, 0, 0, t_now),
     ]
     .into_iter()
     .map(|d| ProductPolicyModel {
-        product_id: d.0,
-        auto_cancel_secs: d.1,
-        warranty_hours: d.2,
-        is_create: d.3,
-        max_num_rsv: d.4,
-        min_num_rsv: d.5,
+        store_id: d.0,
+        product_id: d.1,
+        version: d.2,
+        auto_cancel_secs: d.3,
+        warranty_hours: d.4,
+        is_create: d.5,
+        max_num_rsv: d.6,
+        min_num_rsv: d.7,
+        effective_from: d.8,
     })
     .collect::<Vec<_>>();
     let newdata = vec![
         ProductPolicyDto {
+            seller_id: 19,
             product_id: 123,
             warranty_hours: 480,
             auto_cancel_secs: 3600,
@@ -89,12 +102,31 @@ fn update_instance_ok() {
             min_num_rsv: Some(15),
         },
         ProductPolicyDto {
+            seller_id: 19,
             product_id: 144,
             warranty_hours: 478,
             auto_cancel_secs: 3597,
             max_num_rsv: None,
             min_num_rsv: None,
         },
+        // same product in another store, should be treated as new policy
+        ProductPolicyDto {
+            seller_id: 18,
+            product_id: 144,
+            warranty_hours: 470,
+            auto_cancel_secs: 3590,
+            max_num_rsv: None,
+            min_num_rsv: None,
+        },
+        // identical to the latest version, no new version added
+        ProductPolicyDto {
+            seller_id: 19,
+            product_id: 20903,
+            warranty_hours: 271,
+            auto_cancel_secs: 731,
+            max_num_rsv: None,
+            min_num_rsv: None,
+        },
     ];
     let ms = ProductPolicyModelSet {
         policies: vec![
@@ -102,28 +134,31 @@ fn update_instance_ok() {
             ut_clone_productpolicy(&init_data[1]),
         ],
     }; // assume these instances were stored somewhere
-    let result = ms.update(newdata);
+    let result = ms.update(newdata, t_now);
     assert_eq!(result.is_ok(), true);
     let updated = result.unwrap();
     {
-        assert_eq!(updated.policies.len(), 3);
-        let actual = updated
-            .policies
-            .iter()
-            .find(|m| m.product_id == 20903)
-            .unwrap();
-        assert_eq!(actual, &init_data[0]);
-        let actual = updated
-            .policies
-            .iter()
-            .find(|m| m.product_id == 144)
-            .unwrap();
-        assert_eq!(actual, &init_data[2]);
-        let actual = updated
-            .policies
-            .iter()
-            .find(|m| m.product_id == 123)
-            .unwrap();
-        assert_eq!(actual, &init_data[3]);
+        assert_eq!(updated.policies.len(), 5);
+        [
+            ((19u32, 20903u64, 0u32), &init_data[0]),
+            ((19, 144, 0), &init_data[1]),
+            ((19, 144, 1), &init_data[2]),
+            ((19, 123, 0), &init_data[3]),
+            ((18, 144, 0), &init_data[4]),
+        ]
+        .into_iter()
+        .map(|((store_id, product_id, version), expect)| {
+            let actual = updated
+                .policies
+                .iter()
+                .find(|m| {
+                    m.store_id == store_id && m.product_id == product_id && m.version == version
+                })
+                .unwrap();
+            assert_eq!(actual, expect);
+            assert_eq!(actual.is_create, expect.is_create);
+            assert_eq!(actual.effective_from, expect.effective_from);
+        })
+        .count();
     }
 } // end of update_instance_ok
@@ -232,6 +232,29 @@ fn present_instance_ok() {
     }
 } // end of present_instance_ok
 
+#[test]
+fn sum_avail_across_expiry() {
+    let saved_products = ut_mock_saved_product();
+    let mut products_1013 = saved_products[7..11].to_vec();
+    products_1013.push(saved_products[0].clone());
+    let mset = StockLevelModelSet {
+        stores: vec![
+            StoreStockModel {
+                store_id: 1013,
+                products: products_1013,
+            },
+            StoreStockModel {
+                store_id: 1014,
+                products: saved_products[4..7].to_vec(),
+            },
+        ],
+    };
+    assert_eq!(mset.num_avail(1013, 9002), Some(28));
+    assert_eq!(mset.num_avail(1014, 9006), Some(33));
+    assert_eq!(mset.num_avail(1014, 9002), None);
+    assert_eq!(mset.num_avail(1015, 9006), None);
+}
+
 fn ut_setup_order_currency(seller_ids: Vec<u32>) -> OrderCurrencyModel {
     let buyer = CurrencyModel {
         name: CurrencyDto::USD,
@@ -295,11 +318,14 @@ fn reserve_ok_1() {
         let policy = OrderLineAppliedPolicyModel {
             reserved_until: mock_warranty.clone(),
             warranty_until: mock_warranty.clone(),
+            version: 0,
         };
         let qty = OrderLineQuantityModel {
             reserved,
             paid: 0,
             paid_last_update: None,
+            cancelled: 0,
+            delivered: 0,
         };
         let attrs_charge = ProdAttriPriceModel::from((attr_lastupdate, None));
         OrderLineModel::from((id_, price, policy, qty, attrs_charge))
@@ -392,11 +418,14 @@ fn reserve_ok_2() {
             OrderLineAppliedPolicyModel {
                 reserved_until: mock_warranty.clone(),
                 warranty_until: mock_warranty.clone(),
+                version: 0,
             },
             OrderLineQuantityModel {
                 reserved,
                 paid: 0,
                 paid_last_update: None,
+                cancelled: 0,
+                delivered: 0,
             },
             ProdAttriPriceModel::from((attr_lastupdate, None)),
         );
@@ -477,11 +506,14 @@ fn reserve_shortage() {
             OrderLineAppliedPolicyModel {
                 reserved_until: mock_warranty.clone(),
                 warranty_until: mock_warranty.clone(),
+                version: 0,
             },
             OrderLineQuantityModel {
                 reserved,
                 paid: 0,
                 paid_last_update: None,
+                cancelled: 0,
+                delivered: 0,
             },
             ProdAttriPriceModel::from((attr_lastupdate, None)),
         ))
@@ -540,11 +572,14 @@ fn reserve_seller_nonexist() {
             OrderLineAppliedPolicyModel {
                 reserved_until: mock_warranty.clone(),
                 warranty_until: mock_warranty,
+                version: 0,
             },
             OrderLineQuantityModel {
                 reserved,
                 paid: 0,
                 paid_last_update: None,
+                cancelled: 0,
+                delivered: 0,
             },
             ProdAttriPriceModel::from((attr_lastupdate, None)),
         ))
@@ -0,0 +1,255 @@
+use std::boxed::Box;
+
+use ecommerce_common::model::BaseProductIdentity;
+
+use order::api::dto::ProdAttrValueDto;
+use order::api::web::dto::{CartAccessDto, CartDto, CartLineDto, OlineProductAttrDto};
+use order::datastore::AppInMemoryDStore;
+use order::model::{CartCollaboratorModel, CartLineModel, CartModel, CartShareModel};
+use order::repository::{AbsCartRepo, CartInMemRepo};
+
+use super::in_mem_ds_ctx_setup;
+
+type UtestCartLineData<'a> = (u32, u64, &'a [(&'a str, &'a str)], u32);
+
+fn ut_gen_attrs(data: &[(&str, &str)]) -> Vec<OlineProductAttrDto> {
+    data.iter()
+        .map(|(label, val)| OlineProductAttrDto {
+            label_id: label.to_string(),
+            value: ProdAttrValueDto::Str(val.to_string()),
+        })
+        .collect()
+}
+
+fn ut_gen_line_dto(data: UtestCartLineData) -> CartLineDto {
+    let applied_attr = if data.2.is_empty() {
+        None
+    } else {
+        Some(ut_gen_attrs(data.2))
+    };
+    CartLineDto {
+        seller_id: data.0,
+        product_id: data.1,
+        applied_attr,
+        quantity: data.3,
+    }
+}
+
+fn ut_verify_lines(actual: &[CartLineModel], expect: &[UtestCartLineData]) {
+    assert_eq!(actual.len(), expect.len());
+    expect
+        .iter()
+        .map(|(store_id, product_id, attrs, qty)| {
+            let given_attrs = ut_gen_attrs(attrs);
+            let found = actual
+                .iter()
+                .find(|m| {
+                    m.id_.store_id == *store_id
+                        && m.id_.product_id == *product_id
+                        && m.match_attributes(&given_attrs)
+                })
+                .unwrap();
+            assert_eq!(found.qty_req, *qty);
+        })
+        .count();
+}
+
+pub(crate) async fn save_fetch_attr_variants_common(repo: Box<dyn AbsCartRepo>) {
+    let (owner, seq_num) = (131u32, 2u8);
+    let attrs_red: &[(&str, &str)] = &[("color", "red"), ("size", "L")];
+    let attrs_red_rev: &[(&str, &str)] = &[("size", "L"), ("color", "red")];
+    let attrs_blue: &[(&str, &str)] = &[("color", "blue"), ("size", "L")];
+    let attrs_green: &[(&str, &str)] = &[("color", "green")];
+    let no_attr: &[(&str, &str)] = &[];
+    let new_lines = [
+        (3u32, 108u64, attrs_red, 2u32),
+        (3, 108, attrs_blue, 3),
+        (3, 108, no_attr, 1),
+        (3, 110, no_attr, 6),
+    ]
+    .into_iter()
+    .map(|d| CartLineModel::from(ut_gen_line_dto(d)))
+    .collect::<Vec<_>>();
+    let obj = CartModel {
+        owner,
+        seq_num,
+        title: "apparel".to_string(),
+        saved_lines: Vec::new(),
+        new_lines,
+    };
+    let result = repo.update(obj).await;
+    assert!(result.is_ok());
+    let result = repo.fetch_cart(owner, seq_num).await;
+    let actual = result.unwrap();
+    ut_verify_lines(
+        &actual.saved_lines,
+        &[
+            (3, 108, attrs_red, 2),
+            (3, 108, attrs_blue, 3),
+            (3, 108, no_attr, 1),
+            (3, 110, no_attr, 6),
+        ],
+    );
+    // all variants of the product are loaded, then identified by attribute set,
+    // the order of the given attributes does not matter
+    let pids = vec![BaseProductIdentity {
+        store_id: 3,
+        product_id: 108,
+    }];
+    let result = repo.fetch_lines_by_pid(owner, seq_num, pids).await;
+    let mut obj = result.unwrap();
+    assert_eq!(obj.saved_lines.len(), 3);
+    let data = CartDto {
+        title: "apparel".to_string(),
+        lines: [
+            (3u32, 108u64, attrs_red_rev, 5u32),
+            (3, 108, attrs_green, 4),
+        ]
+        .into_iter()
+        .map(ut_gen_line_dto)
+        .collect(),
+    };
+    obj.update(data);
+    assert_eq!(obj.saved_lines.len(), 3);
+    assert_eq!(obj.new_lines.len(), 1);
+    let result = repo.update(obj).await;
+    assert!(result.is_ok());
+    let actual = repo.fetch_cart(owner, seq_num).await.unwrap();
+    ut_verify_lines(
+        &actual.saved_lines,
+        &[
+            (3, 108, attrs_red, 5),
+            (3, 108, attrs_blue, 3),
+            (3, 108, attrs_green, 4),
+            (3, 108, no_attr, 1),
+            (3, 110, no_attr, 6),
+        ],
+    );
+    let num_saved = repo.num_lines_saved(owner, seq_num).await.unwrap();
+    assert_eq!(num_saved, 5);
+    // chosen attributes are carried over to the order-line request
+    let line = actual
+        .saved_lines
+        .into_iter()
+        .find(|m| m.id_.product_id == 108 && m.chosen_attr.len() == 2)
+        .unwrap();
+    let dto = CartLineDto::from(line);
+    assert_eq!(dto.applied_attr.unwrap().len(), 2);
+} // end of fn save_fetch_attr_variants_common
+
+#[tokio::test]
+async fn save_fetch_attr_variants() {
+    let ds = in_mem_ds_ctx_setup::<AppInMemoryDStore>(30);
+    let in_mem_ds = ds.in_mem.as_ref().unwrap().clone();
+    let repo = CartInMemRepo::new(in_mem_ds).await.unwrap();
+    save_fetch_attr_variants_common(Box::new(repo)).await;
+}
+
+pub(crate) async fn remove_lines_common(repo: Box<dyn AbsCartRepo>) {
+    let (owner, seq_num) = (132u32, 1u8);
+    let attrs_red: &[(&str, &str)] = &[("color", "red")];
+    let no_attr: &[(&str, &str)] = &[];
+    let new_lines = [
+        (3u32, 108u64, attrs_red, 2u32),
+        (3, 108, no_attr, 1),
+        (4, 108, no_attr, 5),
+    ]
+    .into_iter()
+    .map(|d| CartLineModel::from(ut_gen_line_dto(d)))
+    .collect::<Vec<_>>();
+    let obj = CartModel {
+        owner,
+        seq_num,
+        title: "groceries".to_string(),
+        saved_lines: Vec::new(),
+        new_lines,
+    };
+    let result = repo.update(obj).await;
+    assert!(result.is_ok());
+    let mut obj = repo.fetch_cart(owner, seq_num).await.unwrap();
+    obj.saved_lines.retain(|m| m.id_.store_id == 3);
+    let result = repo.remove_lines(obj).await;
+    assert_eq!(result.unwrap(), 2);
+    let actual = repo.fetch_cart(owner, seq_num).await.unwrap();
+    assert_eq!(actual.title.as_str(), "groceries");
+    ut_verify_lines(&actual.saved_lines, &[(4, 108, no_attr, 5)]);
+} // end of fn remove_lines_common
+
+#[tokio::test]
+async fn remove_lines_ok() {
+    let ds = in_mem_ds_ctx_setup::<AppInMemoryDStore>(30);
+    let in_mem_ds = ds.in_mem.as_ref().unwrap().clone();
+    let repo = CartInMemRepo::new(in_mem_ds).await.unwrap();
+    remove_lines_common(Box::new(repo)).await;
+}
+
+pub(crate) async fn update_fetch_share_common(repo: Box<dyn AbsCartRepo>) {
+    let (owner, seq_num) = (133u32, 3u8);
+    let collaborators = [(1301u32, CartAccessDto::Read), (1302, CartAccessDto::Edit)]
+        .into_iter()
+        .map(|(usr_id, access)| CartCollaboratorModel { usr_id, access })
+        .collect::<Vec<_>>();
+    let obj = CartShareModel {
+        owner,
+        seq_num,
+        collaborators,
+    };
+    let result = repo.update_share(obj).await;
+    assert_eq!(result.unwrap(), 2);
+    let actual = repo.fetch_share(owner, seq_num).await.unwrap();
+    assert_eq!(actual.collaborators.len(), 2);
+    assert!(actual.permitted(1301, CartAccessDto::Read));
+    assert!(!actual.permitted(1301, CartAccessDto::Edit));
+    assert!(actual.permitted(1302, CartAccessDto::Edit));
+    assert!(!actual.permitted(1303, CartAccessDto::Read));
+    // other carts of the same owner are not shared
+    let actual = repo.fetch_share(owner, seq_num + 1).await.unwrap();
+    assert!(actual.collaborators.is_empty());
+    // the entire list is replaced
+    let obj = CartShareModel {
+        owner,
+        seq_num,
+        collaborators: vec![CartCollaboratorModel {
+            usr_id: 1303,
+            access: CartAccessDto::Read,
+        }],
+    };
+    let result = repo.update_share(obj).await;
+    assert_eq!(result.unwrap(), 1);
+    let actual = repo.fetch_share(owner, seq_num).await.unwrap();
+    assert_eq!(actual.collaborators.len(), 1);
+    assert!(!actual.permitted(1302, CartAccessDto::Read));
+    assert!(actual.permitted(1303, CartAccessDto::Read));
+    let obj = CartShareModel {
+        owner,
+        seq_num,
+        collaborators: Vec::new(),
+    };
+    let result = repo.update_share(obj).await;
+    assert_eq!(result.unwrap(), 0);
+    let actual = repo.fetch_share(owner, seq_num).await.unwrap();
+    assert!(actual.collaborators.is_empty());
+    // discarding the cart revokes the access
+    let obj = CartShareModel {
+        owner,
+        seq_num,
+        collaborators: vec![CartCollaboratorModel {
+            usr_id: 1302,
+            access: CartAccessDto::Edit,
+        }],
+    };
+    let result = repo.update_share(obj).await;
+    assert_eq!(result.unwrap(), 1);
+    let result = repo.discard(owner, seq_num).await;
+    assert!(result.is_ok());
+    let actual = repo.fetch_share(owner, seq_num).await.unwrap();
+    assert!(actual.collaborators.is_empty());
+} // end of fn update_fetch_share_common
+
+#[tokio::test]
+async fn update_fetch_share_ok() {
+    let ds = in_mem_ds_ctx_setup::<AppInMemoryDStore>(30);
+    let in_mem_ds = ds.in_mem.as_ref().unwrap().clone();
+    let repo = CartInMemRepo::new(in_mem_ds).await.unwrap();
+    update_fetch_share_common(Box::new(repo)).await;
+}
@@ -1,7 +1,9 @@
+pub(super) mod cart;
 pub(super) mod currency;
 pub(super) mod oorder;
 pub(super) mod product_policy;
 mod product_price;
+pub(super) mod store_shipping;
 
 use async_trait::async_trait;
 use std::boxed::Box;
@@ -11,7 +13,8 @@ use ecommerce_common::error::AppErrorCode;
 
 use order::datastore::{
     AbsDStoreFilterKeyOp, AbstInMemoryDStore, AppInMemDeleteInfo, AppInMemDstoreLock,
-    AppInMemFetchKeys, AppInMemFetchedData, AppInMemUpdateData,
+    AppInMemFetchKeys, AppInMemFetchedData, AppInMemIdxKeyType, AppInMemIdxRange,
+    AppInMemUpdateData,
 };
 use order::error::AppError;
 use order::{AppDataStoreContext, AppInMemoryDbCfg};
@@ -22,12 +25,14 @@ fn in_mem_ds_ctx_setup<T: AbstInMemoryDStore + 'static>(
     let d = AppInMemoryDbCfg {
         alias: format!("utest"),
         max_items,
+        persistence: None,
     };
-    let obj = T::new(&d);
+    let obj = T::new(&d).unwrap();
     let obj: Box<dyn AbstInMemoryDStore> = Box::new(obj);
     let inmem_ds = Arc::new(obj);
     Arc::new(AppDataStoreContext {
         sql_dbs: None,
+        pg_dbs: None,
         in_mem: Some(inmem_ds),
     })
 }
@@ -35,11 +40,11 @@ struct MockInMemDeadDataStore {}
 
 #[async_trait]
 impl AbstInMemoryDStore for MockInMemDeadDataStore {
-    fn new(_cfg: &AppInMemoryDbCfg) -> Self
+    fn new(_cfg: &AppInMemoryDbCfg) -> Result<Self, AppError>
     where
         Self: Sized,
     {
-        Self {}
+        Ok(Self {})
     }
     async fn fetch(&self, _info: AppInMemFetchKeys) -> Result<AppInMemFetchedData, AppError> {
         Err(AppError {
@@ -91,4 +96,24 @@ impl AbstInMemoryDStore for MockInMemDeadDataStore {
             detail: Some(format!("utest")),
         })
     }
+    async fn create_index(
+        &self,
+        _tbl_label: &str,
+        _idx_label: &str,
+        _col_idx: usize,
+        _key_type: AppInMemIdxKeyType,
+    ) -> Result<(), AppError> {
+        Ok(())
+    }
+    async fn range_keys(
+        &self,
+        _tbl_label: String,
+        _idx_label: &str,
+        _range: AppInMemIdxRange,
+    ) -> Result<Vec<String>, AppError> {
+        Err(AppError {
+            code: AppErrorCode::NotImplemented,
+            detail: Some("utest".to_string()),
+        })
+    }
 }
@@ -150,11 +150,13 @@ async fn ut_verify_fetch_all_olines(
         assert_eq!(lines[0].id().product_id(), 190);
         assert_eq!(lines[0].price().unit(), 10);
         assert_eq!(lines[0].price().total(), 39);
+        assert_eq!(lines[0].policy.version, 1);
         assert_eq!(lines[2].qty.reserved, 6);
         assert_eq!(lines[2].id().store_id(), mock_seller_ids[1]);
         assert_eq!(lines[2].id().product_id(), 1190);
         assert_eq!(lines[2].price().unit(), 40);
         assert_eq!(lines[2].price().total(), 225);
+        assert_eq!(lines[2].policy.version, 2);
     }
     let result = o_repo.fetch_all_lines(mock_oid[1].clone()).await;
     assert!(result.is_ok());
@@ -255,6 +257,40 @@ async fn ut_verify_fetch_ids_by_ctime(
     }
 }
 
+async fn ut_verify_fetch_ids_by_owner_ctime(
+    data: [(u32, &str, &str, Vec<String>); 3],
+    o_repo: &OrderInMemRepo,
+) {
+    for (usr_id, start, end, expect_ids) in data {
+        let t_start = DateTime::parse_from_rfc3339(start).unwrap();
+        let t_end = DateTime::parse_from_rfc3339(end).unwrap();
+        let result = o_repo
+            .fetch_ids_by_owner_created_time(usr_id, t_start, t_end, 0, 10)
+            .await;
+        assert!(result.is_ok());
+        if let Ok((total, fetched)) = result {
+            assert_eq!(total, expect_ids.len());
+            assert!(fetched.windows(2).all(|w| w[0].1 <= w[1].1));
+            let actual = fetched.into_iter().map(|(oid, _)| oid).collect::<Vec<_>>();
+            assert_eq!(actual, expect_ids);
+        }
+        // only the requested page is returned
+        let result = o_repo
+            .fetch_ids_by_owner_created_time(usr_id, t_start, t_end, 1, 1)
+            .await;
+        let (total, fetched) = result.unwrap();
+        assert_eq!(total, expect_ids.len());
+        let actual = fetched.into_iter().map(|(oid, _)| oid).collect::<Vec<_>>();
+        let expect = expect_ids
+            .iter()
+            .skip(1)
+            .take(1)
+            .cloned()
+            .collect::<Vec<_>>();
+        assert_eq!(actual, expect);
+    }
+}
+
 async fn ut_verify_fetch_billing(mock_oid: [String; 3], o_repo: &OrderInMemRepo) {
     let result = o_repo.fetch_billing(mock_oid[0].clone()).await;
     assert!(result.is_ok());
@@ -451,6 +487,30 @@ async fn in_mem_create_ok() {
         &o_repo,
     )
     .await;
+    ut_verify_fetch_ids_by_owner_ctime(
+        [
+            (
+                mock_buyer_ids[0],
+                "2022-11-07T03:58:17.001-01:00",
+                "2022-11-09T22:13:18.409+04:00",
+                vec![mock_oid[0].clone(), mock_oid[2].clone()],
+            ),
+            (
+                mock_buyer_ids[1],
+                "2022-11-07T03:58:17.001-01:00",
+                "2022-11-09T22:13:18.409+04:00",
+                vec![mock_oid[1].clone()],
+            ),
+            (
+                mock_buyer_ids[1],
+                "2022-11-07T03:59:06.503-01:00",
+                "2022-11-07T04:00:08.990-01:00",
+                vec![],
+            ),
+        ],
+        &o_repo,
+    )
+    .await;
     ut_verify_seller_currencies(
         mock_oid.clone(),
         mock_buyer_meta,
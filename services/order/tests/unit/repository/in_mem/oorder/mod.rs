@@ -259,10 +259,13 @@ fn ut_setup_orderlines(seller_ids: &[u32; 2]) -> Vec<OrderLineModel> {
             reserved: d.4,
             paid: 0,
             paid_last_update: None,
+            cancelled: 0,
+            delivered: 0,
         };
         let policy = OrderLineAppliedPolicyModel {
             reserved_until,
             warranty_until,
+            version: (d.1 % 3) as u32,
         };
         let attrprice = d.5.map(|v| HashMap::from([("duglas-567".to_string(), v)]));
         let attrs_charge = ProdAttriPriceModel::from((attr_lastupdate, attrprice));
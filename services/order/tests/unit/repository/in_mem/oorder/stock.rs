@@ -7,6 +7,7 @@ use rust_decimal::Decimal;
 
 use ecommerce_common::api::dto::CurrencyDto;
 use ecommerce_common::error::AppErrorCode;
+use ecommerce_common::model::BaseProductIdentity;
 
 use order::api::rpc::dto::{
     InventoryEditStockLevelDto, StockLevelReturnDto, StockReturnErrorDto, StockReturnErrorReason,
@@ -250,6 +251,42 @@ async fn same_product_diff_expiry() {
     }
 } // end of fn same_product_diff_expiry
 
+#[tokio::test]
+async fn fetch_avail_ok() {
+    let mock_curr_time = DateTime::parse_from_rfc3339("2023-11-01T09:15:00+08:00").unwrap();
+    let repo = in_mem_repo_ds_setup::<AppInMemoryDStore>(10, Some(mock_curr_time)).await;
+    let stockrepo = repo.stock();
+    let all_products = ut_init_data_product();
+    let slset = {
+        let mut store = UT_INIT_DATA_STORE[0].clone();
+        [0usize, 2, 8, 9]
+            .into_iter()
+            .map(|idx| store.products.push(all_products[idx].clone()))
+            .count();
+        StockLevelModelSet {
+            stores: vec![store],
+        }
+    };
+    let result = stockrepo.save(slset).await;
+    assert!(result.is_ok());
+    let pids = [(1001u32, 9004u64), (1001, 9002), (1002, 9004)]
+        .into_iter()
+        .map(|(store_id, product_id)| BaseProductIdentity {
+            store_id,
+            product_id,
+        })
+        .collect::<Vec<_>>();
+    let result = stockrepo.fetch_avail(pids).await;
+    assert!(result.is_ok());
+    let actual = result.unwrap();
+    assert_eq!(actual.stores.len(), 1);
+    // the items expired before current time are excluded
+    assert_eq!(actual.stores[0].products.len(), 2);
+    assert_eq!(actual.num_avail(1001, 9004), Some(34));
+    assert_eq!(actual.num_avail(1001, 9002), None);
+    assert_eq!(actual.num_avail(1002, 9004), None);
+} // end of fn fetch_avail_ok
+
 #[tokio::test]
 async fn save_dstore_error() {
     let repo = in_mem_repo_ds_setup::<MockInMemDeadDataStore>(4, None).await;
@@ -397,10 +434,13 @@ pub(crate) async fn ut_reserve_init_setup(
             reserved: num_req,
             paid: 0,
             paid_last_update: None,
+            cancelled: 0,
+            delivered: 0,
         };
         let policy = OrderLineAppliedPolicyModel {
             reserved_until: mock_warranty.clone(),
             warranty_until: mock_warranty,
+            version: 0,
         };
         let price = OrderLinePriceModel::from((4, 4 * num_req));
         let attr_lastupdate = mock_warranty - Duration::days(14);
@@ -483,10 +523,13 @@ async fn try_reserve_ok() {
                 reserved,
                 paid: 0,
                 paid_last_update: None,
+                cancelled: 0,
+                delivered: 0,
             };
             let policy = OrderLineAppliedPolicyModel {
                 reserved_until: mock_warranty,
                 warranty_until: mock_warranty,
+                version: 0,
             };
             let price = OrderLinePriceModel::from((unit, total));
             let attrs_charge = ProdAttriPriceModel::from((attr_lastupdate, None));
@@ -604,11 +647,14 @@ async fn try_reserve_shortage() {
             OrderLineAppliedPolicyModel {
                 reserved_until: mock_warranty,
                 warranty_until: mock_warranty,
+                version: 0,
             },
             OrderLineQuantityModel {
                 reserved,
                 paid: 0,
                 paid_last_update: None,
+                cancelled: 0,
+                delivered: 0,
             },
             ProdAttriPriceModel::from((attr_lastupdate, None)),
         ))
@@ -686,10 +732,13 @@ async fn try_reserve_user_cb_err() {
             reserved: 9,
             paid: 0,
             paid_last_update: None,
+            cancelled: 0,
+            delivered: 0,
         };
         let policy = OrderLineAppliedPolicyModel {
             reserved_until: mock_warranty,
             warranty_until: mock_warranty,
+            version: 0,
         };
         let price = OrderLinePriceModel::from((20, 179));
         let attrs_charge = ProdAttriPriceModel::from((attr_lastupdate, None));
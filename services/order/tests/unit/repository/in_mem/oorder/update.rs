@@ -3,7 +3,7 @@ use std::future::Future;
 use std::pin::Pin;
 use std::result::Result as DefaultResult;
 
-use chrono::{DateTime, Duration as ChronoDuration};
+use chrono::{DateTime, Duration as ChronoDuration, FixedOffset};
 use rust_decimal::Decimal;
 use tokio::time::{sleep, Duration as TokioDuration};
 
@@ -14,9 +14,14 @@ use ecommerce_common::api::rpc::dto::{
 };
 use ecommerce_common::error::AppErrorCode;
 
+use order::api::rpc::dto::{StockLevelReturnDto, StockReturnErrorDto, StockReturnErrorReason};
+use order::api::web::dto::OrderLineCancelErrorDto;
 use order::datastore::AppInMemoryDStore;
 use order::error::AppError;
-use order::model::{CurrencyModel, OrderCurrencyModel, OrderLineModel, OrderLineModelSet};
+use order::model::{
+    CurrencyModel, OrderCurrencyModel, OrderLineIdentity, OrderLineModel, OrderLineModelSet,
+    ProductStockIdentity, StockLevelModelSet,
+};
 use order::repository::{AbsOrderRepo, OrderInMemRepo};
 
 use super::create::{ut_setup_save_stock, ut_setup_stock_rsv_cb};
@@ -406,3 +411,99 @@ async fn in_mem_scheduled_job_time_ok() {
     assert!(time3 > time2);
     assert_eq!(time3, time4);
 }
+
+fn ut_cancel_lines_usr_cb(
+    models: &mut [OrderLineModel],
+    pids: Vec<OrderLineIdentity>,
+    _time_now: DateTime<FixedOffset>,
+) -> Vec<OrderLineCancelErrorDto> {
+    assert_eq!(models.len(), pids.len());
+    models.iter_mut().for_each(|m| {
+        m.qty.cancelled = m.qty.num_unpaid();
+    });
+    vec![]
+}
+
+fn ut_return_stock_err_cb(
+    _ms: &mut StockLevelModelSet,
+    data: StockLevelReturnDto,
+) -> Vec<StockReturnErrorDto> {
+    data.items
+        .into_iter()
+        .map(|d| StockReturnErrorDto {
+            seller_id: d.store_id,
+            product_id: d.product_id,
+            reason: StockReturnErrorReason::NotExist,
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn in_mem_cancel_unpaid_lines_return_stock() {
+    let mock_seller_ids = [19u32, 43];
+    let oid = OrderLineModel::generate_order_id(7);
+    let mock_repo_time = DateTime::parse_from_rfc3339("2023-12-24T14:30:41+02:00").unwrap();
+    let o_repo = in_mem_repo_ds_setup::<AppInMemoryDStore>(60, Some(mock_repo_time)).await;
+    let lines = ut_setup_orderlines(&mock_seller_ids);
+    ut_setup_save_stock(o_repo.stock(), mock_repo_time, &lines).await;
+    ut_setup_saved_order(&o_repo, oid.as_str(), 124, lines, mock_seller_ids).await;
+    let stock_expiry = (mock_repo_time + ChronoDuration::minutes(2)).to_utc();
+    let read_booked = |store_id: u32, product_id: u64| {
+        let pid = ProductStockIdentity {
+            store_id,
+            product_id,
+            expiry: stock_expiry,
+        };
+        let stockrepo = o_repo.stock();
+        async move {
+            let ms = stockrepo.fetch(vec![pid]).await.unwrap();
+            ms.stores[0].products[0].quantity.booked
+        }
+    };
+    assert_eq!(read_booked(mock_seller_ids[0], 190).await, 4);
+    assert_eq!(read_booked(mock_seller_ids[1], 192).await, 18);
+
+    let pids = vec![
+        OrderLineIdentity::from((mock_seller_ids[0], 190, 0)),
+        OrderLineIdentity::from((mock_seller_ids[1], 192, 0)),
+    ];
+    let result = o_repo
+        .cancel_unpaid_lines(
+            oid.as_str(),
+            pids.clone(),
+            ut_cancel_lines_usr_cb,
+            StockLevelModelSet::return_across_expiry,
+        )
+        .await;
+    let Ok(Ok(stk_errors)) = result else {
+        panic!("cancel-lines-failure");
+    };
+    assert!(stk_errors.is_empty());
+    // stock reserved for the cancelled lines is returned in the same update
+    assert_eq!(read_booked(mock_seller_ids[0], 190).await, 0);
+    assert_eq!(read_booked(mock_seller_ids[1], 192).await, 0);
+    let saved = o_repo.fetch_lines_by_pid(oid.as_str(), pids).await.unwrap();
+    assert_eq!(saved.len(), 2);
+    saved.iter().for_each(|m| {
+        assert_eq!(m.qty.cancelled, m.qty.reserved);
+    });
+
+    // the cancellation is kept even if the stock cannot be returned
+    let pids = vec![OrderLineIdentity::from((mock_seller_ids[0], 193, 0))];
+    let result = o_repo
+        .cancel_unpaid_lines(
+            oid.as_str(),
+            pids.clone(),
+            ut_cancel_lines_usr_cb,
+            ut_return_stock_err_cb,
+        )
+        .await;
+    let Ok(Ok(stk_errors)) = result else {
+        panic!("cancel-lines-failure");
+    };
+    assert_eq!(stk_errors.len(), 1);
+    assert_eq!(stk_errors[0].product_id, 193);
+    assert_eq!(read_booked(mock_seller_ids[0], 193).await, 32);
+    let saved = o_repo.fetch_lines_by_pid(oid.as_str(), pids).await.unwrap();
+    assert_eq!(saved[0].qty.cancelled, 32);
+} // end of fn in_mem_cancel_unpaid_lines_return_stock
@@ -1,5 +1,7 @@
 use std::boxed::Box;
 
+use chrono::{DateTime, FixedOffset, NaiveDate};
+
 use ecommerce_common::error::AppErrorCode;
 
 use order::datastore::{AbstInMemoryDStore, AppInMemoryDStore};
@@ -9,9 +11,29 @@ use order::repository::{AbstProductPolicyRepo, ProductPolicyInMemRepo};
 use super::{in_mem_ds_ctx_setup, MockInMemDeadDataStore};
 use crate::model::ut_clone_productpolicy;
 
-const UTEST_INIT_DATA: [ProductPolicyModel; 7] = [
+const UT_EFFECT_TIME: [DateTime<FixedOffset>; 2] = [
+    DateTime::from_naive_utc_and_offset(
+        NaiveDate::from_ymd_opt(2023, 9, 12)
+            .unwrap()
+            .and_hms_opt(3, 15, 0)
+            .unwrap(),
+        FixedOffset::east_opt(0).unwrap(),
+    ),
+    DateTime::from_naive_utc_and_offset(
+        NaiveDate::from_ymd_opt(2023, 11, 5)
+            .unwrap()
+            .and_hms_opt(19, 40, 2)
+            .unwrap(),
+        FixedOffset::east_opt(0).unwrap(),
+    ),
+];
+
+const UTEST_INIT_DATA: [ProductPolicyModel; 8] = [
     ProductPolicyModel {
+        store_id: 1001,
         product_id: 1556,
+        version: 0,
+        effective_from: UT_EFFECT_TIME[0],
         min_num_rsv: 0,
         auto_cancel_secs: 309,
         warranty_hours: 7400,
@@ -19,7 +41,10 @@ const UTEST_INIT_DATA: [ProductPolicyModel; 7] = [
         max_num_rsv: 2,
     },
     ProductPolicyModel {
+        store_id: 1001,
         product_id: 9273,
+        version: 0,
+        effective_from: UT_EFFECT_TIME[0],
         min_num_rsv: 3,
         auto_cancel_secs: 900,
         warranty_hours: 7209,
@@ -27,7 +52,10 @@ const UTEST_INIT_DATA: [ProductPolicyModel; 7] = [
         max_num_rsv: 6,
     },
     ProductPolicyModel {
+        store_id: 1001,
         product_id: 40051,
+        version: 0,
+        effective_from: UT_EFFECT_TIME[0],
         min_num_rsv: 0,
         auto_cancel_secs: 707,
         warranty_hours: 1295,
@@ -35,7 +63,10 @@ const UTEST_INIT_DATA: [ProductPolicyModel; 7] = [
         max_num_rsv: 0,
     },
     ProductPolicyModel {
+        store_id: 1001,
         product_id: 1620,
+        version: 0,
+        effective_from: UT_EFFECT_TIME[0],
         min_num_rsv: 3,
         auto_cancel_secs: 1645,
         warranty_hours: 1918,
@@ -43,7 +74,10 @@ const UTEST_INIT_DATA: [ProductPolicyModel; 7] = [
         max_num_rsv: 20,
     },
     ProductPolicyModel {
+        store_id: 1001,
         product_id: 14005,
+        version: 0,
+        effective_from: UT_EFFECT_TIME[0],
         min_num_rsv: 0,
         auto_cancel_secs: 77,
         warranty_hours: 5129,
@@ -51,7 +85,10 @@ const UTEST_INIT_DATA: [ProductPolicyModel; 7] = [
         max_num_rsv: 91,
     },
     ProductPolicyModel {
+        store_id: 1001,
         product_id: 1622,
+        version: 0,
+        effective_from: UT_EFFECT_TIME[0],
         min_num_rsv: 15,
         auto_cancel_secs: 6451,
         warranty_hours: 9181,
@@ -59,13 +96,27 @@ const UTEST_INIT_DATA: [ProductPolicyModel; 7] = [
         max_num_rsv: 57,
     },
     ProductPolicyModel {
+        store_id: 1001,
         product_id: 1622,
+        version: 1,
+        effective_from: UT_EFFECT_TIME[1],
         min_num_rsv: 6,
         auto_cancel_secs: 1178,
         warranty_hours: 11086,
-        is_create: false,
+        is_create: true,
         max_num_rsv: 60,
     },
+    ProductPolicyModel {
+        store_id: 1002,
+        product_id: 1556,
+        version: 0,
+        effective_from: UT_EFFECT_TIME[0],
+        min_num_rsv: 1,
+        auto_cancel_secs: 590,
+        warranty_hours: 3000,
+        is_create: true,
+        max_num_rsv: 4,
+    },
 ]; // end of UTEST_INIT_DATA
 
 async fn in_mem_repo_ds_setup<T: AbstInMemoryDStore + 'static>(
@@ -90,7 +141,7 @@ pub(crate) async fn save_fetch_ok_common(repo: Box<dyn AbstProductPolicyRepo>) {
     };
     let result = repo.save(ppset).await;
     assert_eq!(result.is_ok(), true);
-    let chosen_ids = vec![14005, 1556, 40051];
+    let chosen_ids = vec![(1001, 14005), (1001, 1556), (1001, 40051), (1002, 1556)];
     let result = repo.fetch(chosen_ids).await;
     {
         assert_eq!(result.is_ok(), true);
@@ -113,7 +164,7 @@ pub(crate) async fn save_fetch_ok_common(repo: Box<dyn AbstProductPolicyRepo>) {
     };
     let result = repo.save(ppset).await;
     assert_eq!(result.is_ok(), true);
-    let chosen_ids = vec![1622, 1620, 9273];
+    let chosen_ids = vec![(1001, 1622), (1001, 1620), (1001, 9273)];
     let result = repo.fetch(chosen_ids).await;
     let modelset = result.unwrap();
     [
@@ -158,7 +209,7 @@ async fn save_fetch_ok_2() {
     let result = repo.save(ppset).await;
     assert_eq!(result.is_ok(), true);
 
-    let result = repo.fetch(vec![1622u64]).await;
+    let result = repo.fetch(vec![(1001u32, 1622u64)]).await;
     {
         assert_eq!(result.is_ok(), true);
         let modelset = result.unwrap();
@@ -169,9 +220,126 @@ async fn save_fetch_ok_2() {
             .unwrap();
         assert_eq!(fetched, &UTEST_INIT_DATA[6]);
         assert_ne!(fetched, &UTEST_INIT_DATA[5]);
+        assert_eq!(fetched.effective_from, UT_EFFECT_TIME[1]);
     }
+    let result = repo.fetch_history(1001, 1622).await;
+    {
+        assert!(result.is_ok());
+        let modelset = result.unwrap();
+        assert_eq!(modelset.policies.len(), 2);
+        assert_eq!(&modelset.policies[0], &UTEST_INIT_DATA[5]);
+        assert_eq!(&modelset.policies[1], &UTEST_INIT_DATA[6]);
+        assert_eq!(modelset.policies[0].effective_from, UT_EFFECT_TIME[0]);
+    }
+    let result = repo.fetch_history(1002, 1622).await;
+    assert!(result.unwrap().policies.is_empty());
 } // end of fn save_fetch_ok_2
 
+#[tokio::test]
+async fn save_skip_existing_version() {
+    let repo = in_mem_repo_ds_setup::<AppInMemoryDStore>(20).await;
+    let ppset = {
+        let item = ut_clone_productpolicy(&UTEST_INIT_DATA[5]);
+        ProductPolicyModelSet {
+            policies: vec![item],
+        }
+    };
+    let result = repo.save(ppset).await;
+    assert!(result.is_ok());
+    // previously saved version is loaded without the create flag, it should never
+    // be written again, even if the caller modified it by mistake
+    let ppset = {
+        let mut item = ut_clone_productpolicy(&UTEST_INIT_DATA[5]);
+        item.is_create = false;
+        item.warranty_hours += 1;
+        ProductPolicyModelSet {
+            policies: vec![item],
+        }
+    };
+    let result = repo.save(ppset).await;
+    assert!(result.is_ok());
+    let result = repo.fetch_history(1001, 1622).await;
+    let modelset = result.unwrap();
+    assert_eq!(modelset.policies.len(), 1);
+    assert_eq!(&modelset.policies[0], &UTEST_INIT_DATA[5]);
+    assert_eq!(
+        modelset.policies[0].warranty_hours,
+        UTEST_INIT_DATA[5].warranty_hours
+    );
+} // end of fn save_skip_existing_version
+
+// two clients load the same latest version, then save their own changes
+pub(crate) async fn save_version_conflict_common(repo: Box<dyn AbstProductPolicyRepo>) {
+    let mut item = ut_clone_productpolicy(&UTEST_INIT_DATA[7]);
+    item.product_id = 1557;
+    let result = repo
+        .save(ProductPolicyModelSet {
+            policies: vec![item],
+        })
+        .await;
+    assert!(result.is_ok());
+    let next_versions = [3021u32, 3022]
+        .into_iter()
+        .map(|warranty_hours| {
+            let mut item = ut_clone_productpolicy(&UTEST_INIT_DATA[7]);
+            item.product_id = 1557;
+            item.version = 1;
+            item.warranty_hours = warranty_hours;
+            item.effective_from = UT_EFFECT_TIME[1];
+            item
+        })
+        .collect::<Vec<_>>();
+    let mut results = Vec::new();
+    for item in next_versions {
+        let ppset = ProductPolicyModelSet {
+            policies: vec![item],
+        };
+        results.push(repo.save(ppset).await);
+    }
+    assert!(results[0].is_ok());
+    let error = results.remove(1).err().unwrap();
+    assert_eq!(error.code, AppErrorCode::DataConflict);
+    let modelset = repo.fetch_history(1002, 1557).await.unwrap();
+    assert_eq!(modelset.policies.len(), 2);
+    assert_eq!(modelset.policies[1].version, 1);
+    assert_eq!(modelset.policies[1].warranty_hours, 3021);
+} // end of fn save_version_conflict_common
+
+#[tokio::test]
+async fn save_version_conflict() {
+    let repo = in_mem_repo_ds_setup::<AppInMemoryDStore>(20).await;
+    save_version_conflict_common(repo).await;
+}
+
+#[tokio::test]
+async fn save_fetch_per_store() {
+    let repo = in_mem_repo_ds_setup::<AppInMemoryDStore>(20).await;
+    let ppset = {
+        let items = [&UTEST_INIT_DATA[0], &UTEST_INIT_DATA[7]]
+            .into_iter()
+            .map(ut_clone_productpolicy)
+            .collect();
+        ProductPolicyModelSet { policies: items }
+    };
+    let result = repo.save(ppset).await;
+    assert!(result.is_ok());
+    let result = repo.fetch(vec![(1002, 1556), (1003, 1556)]).await;
+    let modelset = result.unwrap();
+    assert_eq!(modelset.policies.len(), 1);
+    assert_eq!(&modelset.policies[0], &UTEST_INIT_DATA[7]);
+    let result = repo.fetch(vec![(1001, 1556), (1002, 1556)]).await;
+    let modelset = result.unwrap();
+    assert_eq!(modelset.policies.len(), 2);
+    [1001u32, 1002]
+        .into_iter()
+        .zip([&UTEST_INIT_DATA[0], &UTEST_INIT_DATA[7]])
+        .map(|(store_id, expect)| {
+            let actual = modelset.policies.iter().find(|m| m.store_id == store_id);
+            assert_eq!(actual.unwrap(), expect);
+        })
+        .count();
+} // end of fn save_fetch_per_store
+
 #[tokio::test]
 async fn save_empty_input() {
     let repo = in_mem_repo_ds_setup::<AppInMemoryDStore>(9).await;
@@ -196,16 +364,17 @@ async fn save_dstore_error() {
     let result = repo.save(ppset).await;
     assert_eq!(result.is_err(), true);
     let error = result.err().unwrap();
-    assert_eq!(error.code, AppErrorCode::DataTableNotExist);
+    assert_eq!(error.code, AppErrorCode::AcquireLockFailure);
     assert_eq!(error.detail, Some("utest".to_string()));
 }
 
 #[tokio::test]
 async fn fetch_dstore_error() {
     let repo = in_mem_repo_ds_setup::<MockInMemDeadDataStore>(10).await;
-    let result = repo.fetch(vec![1622u64]).await;
+    let result = repo.fetch(vec![(1001u32, 1622u64)]).await;
     assert_eq!(result.is_err(), true);
     let error = result.err().unwrap();
-    assert_eq!(error.code, AppErrorCode::AcquireLockFailure);
+    // versions of each policy are looked up by key filter before fetching rows
+    assert_eq!(error.code, AppErrorCode::NotImplemented);
     assert_eq!(error.detail, Some("utest".to_string()));
 }
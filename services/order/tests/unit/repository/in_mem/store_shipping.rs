@@ -0,0 +1,110 @@
+use ecommerce_common::api::dto::CountryCode;
+
+use order::api::dto::ShippingMethod;
+use order::datastore::AppInMemoryDStore;
+use order::model::{ShippingFeeModel, StoreShipMethodModel, StoreShippingModel};
+use order::repository::{AbsStoreShippingRepo, StoreShippingInMemRepo};
+
+use crate::repository::in_mem::in_mem_ds_ctx_setup;
+
+async fn in_mem_repo_ds_setup(max_items: u32) -> StoreShippingInMemRepo {
+    let ds_ctx = in_mem_ds_ctx_setup::<AppInMemoryDStore>(max_items);
+    let inmem = ds_ctx.in_mem.as_ref().unwrap().clone();
+    let result = StoreShippingInMemRepo::new(inmem).await;
+    assert!(result.is_ok());
+    result.unwrap()
+}
+
+pub(crate) fn ut_setup_store_shipping(
+    store_id: u32,
+    data: Vec<(ShippingMethod, Vec<(CountryCode, u32)>)>,
+) -> StoreShippingModel {
+    let methods = data
+        .into_iter()
+        .map(|(method, fees)| StoreShipMethodModel {
+            method,
+            fees: fees
+                .into_iter()
+                .map(|(country, amount)| ShippingFeeModel { country, amount })
+                .collect(),
+        })
+        .collect();
+    StoreShippingModel { store_id, methods }
+}
+
+pub(crate) fn ut_verify_store_shipping(
+    actual: &[StoreShippingModel],
+    store_id: u32,
+    expect: Vec<(ShippingMethod, Vec<(CountryCode, u32)>)>,
+) {
+    let result = actual.iter().find(|m| m.store_id == store_id);
+    assert!(result.is_some());
+    let saved = result.unwrap();
+    assert_eq!(saved.methods.len(), expect.len());
+    expect
+        .into_iter()
+        .map(|(method, fees)| {
+            let result = saved.methods.iter().find(|m| m.method == method);
+            assert!(result.is_some());
+            let saved_m = result.unwrap();
+            assert_eq!(saved_m.fees.len(), fees.len());
+            fees.into_iter()
+                .map(|(country, amount)| {
+                    let found = saved_m
+                        .fees
+                        .iter()
+                        .any(|f| f.country == country && f.amount == amount);
+                    assert!(found);
+                })
+                .count();
+        })
+        .count();
+}
+
+#[tokio::test]
+async fn save_fetch_ok() {
+    let repo = in_mem_repo_ds_setup(20).await;
+    let mocked = [
+        (
+            1093,
+            vec![
+                (
+                    ShippingMethod::UPS,
+                    vec![(CountryCode::TW, 120), (CountryCode::US, 980)],
+                ),
+                (ShippingMethod::FedEx, vec![(CountryCode::TH, 230)]),
+            ],
+        ),
+        (
+            1094,
+            vec![(ShippingMethod::BlackCatExpress, vec![(CountryCode::TW, 65)])],
+        ),
+    ];
+    for (store_id, data) in mocked.clone() {
+        let obj = ut_setup_store_shipping(store_id, data);
+        let result = repo.save(obj).await;
+        assert!(result.is_ok());
+    }
+    let result = repo.fetch(vec![1093, 1094, 1095]).await;
+    assert!(result.is_ok());
+    let saved = result.unwrap();
+    assert_eq!(saved.len(), 2);
+    for (store_id, data) in mocked {
+        ut_verify_store_shipping(&saved, store_id, data);
+    }
+    // replace all methods of the store
+    let data = vec![(ShippingMethod::FedEx, vec![(CountryCode::ID, 77)])];
+    let obj = ut_setup_store_shipping(1093, data.clone());
+    let result = repo.save(obj).await;
+    assert!(result.is_ok());
+    let saved = repo.fetch(vec![1093]).await.unwrap();
+    assert_eq!(saved.len(), 1);
+    ut_verify_store_shipping(&saved, 1093, data);
+    // the store no longer delivers any item
+    let obj = ut_setup_store_shipping(1094, vec![]);
+    let result = repo.save(obj).await;
+    assert!(result.is_ok());
+    let saved = repo.fetch(vec![1094]).await.unwrap();
+    assert_eq!(saved.len(), 1);
+    assert!(saved[0].methods.is_empty());
+} // end of fn save_fetch_ok
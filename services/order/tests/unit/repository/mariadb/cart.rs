@@ -3,6 +3,9 @@ use ecommerce_common::model::BaseProductIdentity;
 use order::model::{CartLineModel, CartModel};
 use order::repository::app_repo_cart;
 
+use super::super::in_mem::cart::{
+    remove_lines_common, save_fetch_attr_variants_common, update_fetch_share_common,
+};
 use super::dstore_ctx_setup;
 
 fn ut_gen_line_model(value: (u32, u64, u32)) -> CartLineModel {
@@ -11,6 +14,7 @@ fn ut_gen_line_model(value: (u32, u64, u32)) -> CartLineModel {
             store_id: value.0,
             product_id: value.1,
         },
+        chosen_attr: Vec::new(),
         qty_req: value.2,
     }
 }
@@ -208,3 +212,26 @@ async fn discard_cart_ok() {
         ut_verify_cart_model(actual, item);
     }
 } // end of fn discard_cart_ok
+
+#[tokio::test]
+async fn save_fetch_attr_variants() {
+    let ds = dstore_ctx_setup();
+    let repo = app_repo_cart(ds).await.unwrap();
+    let _ = repo.discard(131, 2).await;
+    save_fetch_attr_variants_common(repo).await;
+}
+
+#[tokio::test]
+async fn remove_lines_ok() {
+    let ds = dstore_ctx_setup();
+    let repo = app_repo_cart(ds).await.unwrap();
+    let _ = repo.discard(132, 1).await;
+    remove_lines_common(repo).await;
+}
+
+#[tokio::test]
+async fn update_fetch_share_ok() {
+    let ds = dstore_ctx_setup();
+    let repo = app_repo_cart(ds).await.unwrap();
+    update_fetch_share_common(repo).await;
+}
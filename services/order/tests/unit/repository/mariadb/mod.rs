@@ -3,6 +3,7 @@ mod currency;
 mod oorder;
 mod product_policy;
 mod product_price;
+mod store_shipping;
 
 use std::env;
 use std::sync::Arc;
@@ -19,10 +20,19 @@ fn dstore_ctx_setup() -> Arc<AppDataStoreContext> {
         let path = sys_basepath.clone() + "/common/data/secrets.json";
         UserSpaceConfidentiality::build(path)
     };
-    let app_state = ut_setup_share_state("config_ok.json", Box::new(cfdntl));
+    let is_postgres = module_path!().ends_with("::postgres");
+    let cfg_fname = if is_postgres {
+        "config_ok_postgres.json"
+    } else {
+        "config_ok.json"
+    };
+    let app_state = ut_setup_share_state(cfg_fname, Box::new(cfdntl));
     let dstore = app_state.datastore();
-    assert!(dstore.sql_dbs.is_some());
-    let db_stores = dstore.sql_dbs.as_ref().unwrap();
-    assert!(!db_stores.is_empty());
+    let num_stores = if is_postgres {
+        dstore.pg_dbs.as_ref().map(|v| v.len())
+    } else {
+        dstore.sql_dbs.as_ref().map(|v| v.len())
+    };
+    assert!(num_stores.unwrap_or(0) > 0);
     dstore
 }
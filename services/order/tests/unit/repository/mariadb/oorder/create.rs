@@ -273,6 +273,19 @@ async fn fetch_toplvl_meta_ok() {
         assert!(oids.contains(&"0e927d00".to_string()));
         assert!(oids.contains(&"0e927d78".to_string()));
     }
+    let result = o_repo.fetch_ids_by_owner_created_time(146, time_start, time_end, 0, 5).await;
+    let (total, owned) = result.unwrap();
+    assert_eq!(total, 1);
+    let oids = owned.into_iter().map(|(oid, _)| oid).collect::<Vec<_>>();
+    assert_eq!(oids, vec!["0e927d78".to_string()]);
+    let result = o_repo.fetch_ids_by_owner_created_time(146, time_start, time_end, 1, 5).await;
+    let (total, owned) = result.unwrap();
+    assert_eq!(total, 1);
+    assert!(owned.is_empty());
+    let result = o_repo.fetch_ids_by_owner_created_time(166, time_start, time_end, 0, 5).await;
+    let (total, owned) = result.unwrap();
+    assert_eq!(total, 0);
+    assert!(owned.is_empty());
     let result = o_repo.owner_id("0e927d8a").await;
     assert_eq!(result.unwrap(), 166);
     let result = o_repo.owner_id("0e927d78").await;
@@ -16,6 +16,7 @@ use order::repository::AbsOrderStockRepo;
 
 mod create;
 mod line_return;
+mod shipment;
 mod stock;
 mod update;
 
@@ -80,11 +81,14 @@ fn ut_oline_init_setup(
                 reserved: d.1,
                 paid: 0,
                 paid_last_update: None,
+                cancelled: 0,
+                delivered: 0,
             };
             let price = OrderLinePriceModel::from((d.2, d.2 * d.1));
             let policy = OrderLineAppliedPolicyModel {
                 reserved_until: d.4 + Duration::minutes(2),
                 warranty_until: d.4 + Duration::minutes(4),
+                version: 0,
             };
             let att_lastupdate = d.4 - Duration::minutes(35);
             let attr_price = d.3.map(|v| HashMap::from([(v.0.to_string(), v.1)]));
@@ -0,0 +1,106 @@
+use chrono::{Duration, Local};
+
+use ecommerce_common::api::rpc::dto::{OrderLinePaidUpdateDto, OrderPaymentUpdateDto};
+use order::api::dto::ShippingMethod;
+use order::api::rpc::dto::ShipmentLineErrorReason;
+use order::model::{ShipmentLineModel, ShipmentModel};
+use order::repository::app_repo_order;
+
+use super::super::dstore_ctx_setup;
+use super::update::{mock_reserve_usr_cb_0, ut_update_payment_repo_cb};
+use super::{ut_default_order_currency, ut_oline_init_setup, ut_setup_stock_product};
+
+fn ut_shipment(seller_id: u32, tracking_num: &str, lines: Vec<(u64, u32)>) -> ShipmentModel {
+    let lines = lines
+        .into_iter()
+        .map(|(product_id, qty)| ShipmentLineModel {
+            product_id,
+            attr_set_seq: 0,
+            qty,
+        })
+        .collect::<Vec<_>>();
+    ShipmentModel {
+        seller_id,
+        carrier: ShippingMethod::UPS,
+        tracking_num: tracking_num.to_string(),
+        create_time: Local::now().fixed_offset(),
+        received_time: None,
+        lines,
+    }
+}
+
+#[cfg(any(feature = "mariadb", feature = "postgres"))]
+#[tokio::test]
+async fn create_shipment_concurrent_not_exceed_paid() {
+    let ds = dstore_ctx_setup();
+    let o_repo = app_repo_order(ds).await.unwrap();
+    let (mock_oid, mock_seller_id) = ("0e9270b35e01", 1077u32);
+    let create_time = Local::now().fixed_offset();
+    ut_setup_stock_product(o_repo.stock(), mock_seller_id, 9301, 20).await;
+    ut_setup_stock_product(o_repo.stock(), mock_seller_id, 9302, 20).await;
+    {
+        let lines = vec![
+            ((mock_seller_id, 9301), 8, 120, None, create_time),
+            ((mock_seller_id, 9302), 6, 75, None, create_time),
+        ];
+        let currency = ut_default_order_currency(vec![mock_seller_id]);
+        let ol_set = ut_oline_init_setup(mock_oid, 125, create_time, currency, lines);
+        let result = o_repo
+            .stock()
+            .try_reserve(mock_reserve_usr_cb_0, &ol_set)
+            .await;
+        assert!(result.is_ok());
+    }
+    let data = OrderPaymentUpdateDto {
+        oid: mock_oid.to_string(),
+        charge_time: (create_time + Duration::seconds(5)).to_rfc3339(),
+        lines: [(9301u64, 8u32), (9302, 6)]
+            .into_iter()
+            .map(|(product_id, qty)| OrderLinePaidUpdateDto {
+                seller_id: mock_seller_id,
+                product_id,
+                attr_set_seq: 0,
+                qty,
+            })
+            .collect(),
+    };
+    let usr_err = o_repo
+        .update_lines_payment(data, ut_update_payment_repo_cb)
+        .await
+        .unwrap();
+    assert!(usr_err.lines.is_empty());
+
+    // each shipment alone fits the paid quantity, both together exceed it
+    let shipments = [
+        ut_shipment(mock_seller_id, "1Z0A11", vec![(9301, 5), (9302, 4)]),
+        ut_shipment(mock_seller_id, "1Z0A12", vec![(9301, 5), (9302, 2)]),
+    ];
+    let [s0, s1] = shipments;
+    let (r0, r1) = tokio::join!(
+        o_repo.create_shipment(mock_oid, s0, ShipmentModel::validate),
+        o_repo.create_shipment(mock_oid, s1, ShipmentModel::validate),
+    );
+    let results = [r0.unwrap(), r1.unwrap()];
+    let num_ok = results.iter().filter(|r| r.is_ok()).count();
+    assert_eq!(num_ok, 1);
+    let Some(Err(e)) = results.iter().find(|r| r.is_err()) else {
+        panic!("missing-rejected-shipment");
+    };
+    assert!(e.reason.is_none());
+    assert_eq!(e.lines.len(), 1);
+    assert_eq!(e.lines[0].product_id, 9301);
+    assert!(matches!(
+        e.lines[0].reason,
+        ShipmentLineErrorReason::QtyLimitExceed
+    ));
+
+    let saved = o_repo.fetch_shipments(mock_oid).await.unwrap();
+    assert_eq!(saved.len(), 1);
+    let num_shipped = saved[0]
+        .lines
+        .iter()
+        .find(|l| l.product_id == 9301)
+        .map(|l| l.qty)
+        .unwrap();
+    assert_eq!(num_shipped, 5);
+} // end of fn create_shipment_concurrent_not_exceed_paid
@@ -2,15 +2,18 @@ use chrono::{DateTime, Duration, Local, SubsecRound};
 use tokio::time::{sleep, Duration as TokioDuration};
 
 use ecommerce_common::api::rpc::dto::{
-    OrderLinePaidUpdateDto, OrderLinePayUpdateErrorDto, OrderPaymentUpdateDto,
+    OrderLinePaidUpdateDto, OrderLinePayUpdateErrorDto, OrderLinePayUpdateErrorReason,
+    OrderPaymentUpdateDto,
+};
+use order::model::{
+    OrderLineIdentity, OrderLineModel, OrderLineModelSet, ProductStockIdentity, StockLevelModelSet,
 };
-use order::model::{OrderLineIdentity, OrderLineModel, OrderLineModelSet, StockLevelModelSet};
 use order::repository::{app_repo_order, AppStockRepoReserveReturn};
 
 use super::super::dstore_ctx_setup;
 use super::{ut_default_order_currency, ut_oline_init_setup, ut_setup_stock_product};
 
-fn mock_reserve_usr_cb_0(
+pub(super) fn mock_reserve_usr_cb_0(
     ms: &mut StockLevelModelSet,
     req: &OrderLineModelSet,
 ) -> AppStockRepoReserveReturn {
@@ -19,7 +22,7 @@ fn mock_reserve_usr_cb_0(
     Ok(())
 }
 
-fn ut_update_payment_repo_cb(
+pub(super) fn ut_update_payment_repo_cb(
     saved_lines: &mut Vec<OrderLineModel>,
     data: OrderPaymentUpdateDto,
 ) -> Vec<OrderLinePayUpdateErrorDto> {
@@ -32,7 +35,7 @@ fn ut_update_payment_repo_cb(
     OrderLineModel::update_payments(saved_lines, lines, ctime)
 }
 
-#[cfg(feature = "mariadb")]
+#[cfg(any(feature = "mariadb", feature = "postgres"))]
 #[tokio::test]
 async fn update_payment_ok() {
     let ds = dstore_ctx_setup();
@@ -127,7 +130,87 @@ async fn update_payment_ok() {
     }
 } // end of fn update_payment_ok
 
-#[cfg(feature = "mariadb")]
+#[cfg(any(feature = "mariadb", feature = "postgres"))]
+#[tokio::test]
+async fn pay_after_cancel_unpaid_lines() {
+    let ds = dstore_ctx_setup();
+    let o_repo = app_repo_order(ds).await.unwrap();
+    let (mock_oid, mock_seller_id) = ("0e9270b35e02", 1078u32);
+    let create_time = Local::now().fixed_offset();
+    ut_setup_stock_product(o_repo.stock(), mock_seller_id, 9311, 20).await;
+    ut_setup_stock_product(o_repo.stock(), mock_seller_id, 9312, 20).await;
+    {
+        let lines = vec![
+            ((mock_seller_id, 9311), 7, 120, None, create_time),
+            ((mock_seller_id, 9312), 5, 75, None, create_time),
+        ];
+        let currency = ut_default_order_currency(vec![mock_seller_id]);
+        let ol_set = ut_oline_init_setup(mock_oid, 126, create_time, currency, lines);
+        let result = o_repo
+            .stock()
+            .try_reserve(mock_reserve_usr_cb_0, &ol_set)
+            .await;
+        assert!(result.is_ok());
+    }
+    let read_booked = |product_id: u64| {
+        let pid = ProductStockIdentity {
+            store_id: mock_seller_id,
+            product_id,
+            expiry: DateTime::parse_from_rfc3339("2023-11-07T08:12:05.008+02:00")
+                .unwrap()
+                .into(),
+        };
+        let stockrepo = o_repo.stock();
+        async move {
+            let ms = stockrepo.fetch(vec![pid]).await.unwrap();
+            ms.stores[0].products[0].quantity.booked
+        }
+    };
+    assert_eq!(read_booked(9311).await, 7);
+
+    let pids = vec![OrderLineIdentity::from((mock_seller_id, 9311, 0))];
+    let result = o_repo
+        .cancel_unpaid_lines(
+            mock_oid,
+            pids,
+            OrderLineModel::cancel_unpaid,
+            StockLevelModelSet::return_across_expiry,
+        )
+        .await;
+    let Ok(Ok(stk_errors)) = result else {
+        panic!("cancel-lines-failure");
+    };
+    assert!(stk_errors.is_empty());
+    // stock of the cancelled line is returned together with the cancellation
+    assert_eq!(read_booked(9311).await, 0);
+    assert_eq!(read_booked(9312).await, 5);
+
+    let data = OrderPaymentUpdateDto {
+        oid: mock_oid.to_string(),
+        charge_time: (create_time + Duration::seconds(5)).to_rfc3339(),
+        lines: [(9311u64, 7u32), (9312, 5)]
+            .into_iter()
+            .map(|(product_id, qty)| OrderLinePaidUpdateDto {
+                seller_id: mock_seller_id,
+                product_id,
+                attr_set_seq: 0,
+                qty,
+            })
+            .collect(),
+    };
+    let usr_err = o_repo
+        .update_lines_payment(data, ut_update_payment_repo_cb)
+        .await
+        .unwrap();
+    assert_eq!(usr_err.lines.len(), 1);
+    assert_eq!(usr_err.lines[0].product_id, 9311);
+    assert!(matches!(
+        usr_err.lines[0].reason,
+        OrderLinePayUpdateErrorReason::Cancelled
+    ));
+} // end of fn pay_after_cancel_unpaid_lines
+
+#[cfg(any(feature = "mariadb", feature = "postgres"))]
 #[tokio::test]
 async fn cancel_unpaid_job_time_ok() {
     let ds = dstore_ctx_setup();
@@ -1,7 +1,7 @@
 use order::repository::app_repo_product_policy;
 
-use super::super::in_mem::product_policy::save_fetch_ok_common;
-use crate::repository::mariadb::dstore_ctx_setup;
+use super::super::in_mem::product_policy::{save_fetch_ok_common, save_version_conflict_common};
+use super::dstore_ctx_setup;
 
 #[tokio::test]
 async fn save_fetch_ok() {
@@ -9,3 +9,10 @@ async fn save_fetch_ok() {
     let repo = app_repo_product_policy(ds).await.unwrap();
     save_fetch_ok_common(repo).await;
 }
+
+#[tokio::test]
+async fn save_version_conflict() {
+    let ds = dstore_ctx_setup();
+    let repo = app_repo_product_policy(ds).await.unwrap();
+    save_version_conflict_common(repo).await;
+}
@@ -215,7 +215,11 @@ async fn save_insert_dup() {
         assert!(result.is_err());
         if let Err(e) = result {
             println!("[unit-test] error : {:?}", e);
-            let dup_err_code = "1062";
+            let dup_err_code = if module_path!().contains("::postgres::") {
+                "duplicate key"
+            } else {
+                "1062"
+            };
             assert_eq!(e.code, AppErrorCode::RemoteDbServerFailure);
             is_dup_err = e.detail.as_ref().unwrap().contains(dup_err_code);
             if is_dup_err {
@@ -0,0 +1,55 @@
+use ecommerce_common::api::dto::CountryCode;
+
+use order::api::dto::ShippingMethod;
+use order::repository::app_repo_store_shipping;
+
+use super::super::in_mem::store_shipping::{ut_setup_store_shipping, ut_verify_store_shipping};
+use super::dstore_ctx_setup;
+
+#[tokio::test]
+async fn save_fetch_ok() {
+    let ds = dstore_ctx_setup();
+    let repo = app_repo_store_shipping(ds).await.unwrap();
+    let mocked = [
+        (
+            2093,
+            vec![
+                (
+                    ShippingMethod::UPS,
+                    vec![(CountryCode::TW, 120), (CountryCode::US, 980)],
+                ),
+                (ShippingMethod::FedEx, vec![(CountryCode::TH, 230)]),
+            ],
+        ),
+        (
+            2094,
+            vec![(ShippingMethod::BlackCatExpress, vec![(CountryCode::TW, 65)])],
+        ),
+    ];
+    for (store_id, data) in mocked.clone() {
+        let obj = ut_setup_store_shipping(store_id, data);
+        let result = repo.save(obj).await;
+        assert!(result.is_ok());
+    }
+    let result = repo.fetch(vec![2093, 2094, 2095]).await;
+    assert!(result.is_ok());
+    let saved = result.unwrap();
+    assert_eq!(saved.len(), 2);
+    for (store_id, data) in mocked {
+        ut_verify_store_shipping(&saved, store_id, data);
+    }
+    // replace all methods of the store
+    let data = vec![(ShippingMethod::FedEx, vec![(CountryCode::ID, 77)])];
+    let obj = ut_setup_store_shipping(2093, data.clone());
+    let result = repo.save(obj).await;
+    assert!(result.is_ok());
+    let saved = repo.fetch(vec![2093]).await.unwrap();
+    assert_eq!(saved.len(), 1);
+    ut_verify_store_shipping(&saved, 2093, data);
+    // rows of the store are all removed
+    let obj = ut_setup_store_shipping(2094, vec![]);
+    let result = repo.save(obj).await;
+    assert!(result.is_ok());
+    let saved = repo.fetch(vec![2094]).await.unwrap();
+    assert!(saved.is_empty());
+} // end of fn save_fetch_ok
@@ -1,3 +1,10 @@
 mod in_mem;
 #[cfg(feature = "mariadb")]
+#[allow(clippy::duplicate_mod)]
 mod mariadb;
+mod pool_layout;
+// the same test suites are run against PostgreSQL
+#[cfg(feature = "postgres")]
+#[allow(clippy::duplicate_mod)]
+#[path = "mariadb/mod.rs"]
+mod postgres;
@@ -0,0 +1,135 @@
+use ecommerce_common::adapter::repository::OidBytes;
+use ecommerce_common::config::AppDbServerRole;
+use ecommerce_common::error::AppErrorCode;
+use order::repository::DbPoolLayout;
+
+fn ut_primary(order_shard: bool) -> AppDbServerRole {
+    AppDbServerRole::Primary { order_shard }
+}
+fn ut_replica(primary: &str) -> AppDbServerRole {
+    AppDbServerRole::Replica {
+        primary: primary.to_string(),
+    }
+}
+
+#[test]
+fn group_by_role_ok() {
+    let roles = [
+        ut_replica("db-2"),
+        ut_primary(false),
+        ut_primary(true),
+        ut_replica("db-1"),
+        ut_replica("db-2"),
+        ut_primary(true),
+    ];
+    let aliases = ["db-2r", "db-1", "db-2", "db-1r", "db-2r2", "db-3"];
+    let servers = aliases.into_iter().zip(roles.iter()).collect::<Vec<_>>();
+    let result = DbPoolLayout::try_build(&servers);
+    assert!(result.is_ok());
+    let layout = result.unwrap();
+    assert_eq!(layout.primaries, vec![1, 2, 5]);
+    assert_eq!(layout.replicas, vec![vec![3], vec![0, 4], vec![]]);
+    assert_eq!(layout.order_shards, vec![1, 2]);
+}
+
+#[test]
+fn no_order_shard_enabled() {
+    let roles = [ut_primary(false), ut_primary(false), ut_replica("db-1")];
+    let servers = vec![
+        ("db-1", &roles[0]),
+        ("db-2", &roles[1]),
+        ("db-1r", &roles[2]),
+    ];
+    let layout = DbPoolLayout::try_build(&servers).unwrap();
+    assert_eq!(layout.order_shards, vec![0]);
+    let oids = [
+        "0e2a01",
+        "9876543210abcdef",
+        "ff00ff00ff00ff00ff00ff00ff00ff00",
+    ];
+    oids.into_iter()
+        .map(|oid| {
+            let oid_b = OidBytes::try_from(oid).unwrap();
+            assert_eq!(layout.order_shard(&oid_b), 0);
+        })
+        .count();
+}
+
+#[test]
+fn replica_to_unknown_primary() {
+    let roles = [ut_primary(true), ut_replica("db-9")];
+    let servers = vec![("db-1", &roles[0]), ("db-1r", &roles[1])];
+    let result = DbPoolLayout::try_build(&servers);
+    assert!(result.is_err());
+    if let Err(e) = result {
+        assert_eq!(e.code, AppErrorCode::MissingDataStore);
+        let detail = e.detail.unwrap();
+        assert!(detail.contains("db-1r"));
+        assert!(detail.contains("db-9"));
+    }
+}
+
+#[test]
+fn replica_cannot_follow_replica() {
+    let roles = [ut_primary(true), ut_replica("db-1"), ut_replica("db-1r")];
+    let servers = vec![
+        ("db-1", &roles[0]),
+        ("db-1r", &roles[1]),
+        ("db-1rr", &roles[2]),
+    ];
+    let result = DbPoolLayout::try_build(&servers);
+    assert!(result.is_err());
+}
+
+#[test]
+fn missing_primary() {
+    let roles = [ut_replica("db-1")];
+    let servers = vec![("db-1r", &roles[0])];
+    let result = DbPoolLayout::try_build(&servers);
+    assert!(result.is_err());
+    if let Err(e) = result {
+        assert_eq!(e.code, AppErrorCode::MissingDataStore);
+    }
+}
+
+#[test]
+fn order_shard_stable_hash() {
+    let roles = [ut_primary(false), ut_primary(true), ut_primary(true)];
+    let servers = vec![
+        ("db-1", &roles[0]),
+        ("db-2", &roles[1]),
+        ("db-3", &roles[2]),
+    ];
+    let layout = DbPoolLayout::try_build(&servers).unwrap();
+    // expected shards are derived from FNV-1a hash of the 16-byte order ID,
+    // which must not change between builds or restarts of the application
+    let expect = [
+        ("0e2a01", 1usize),
+        ("0e2a02", 2),
+        ("9876543210abcdef", 1),
+        ("ff00ff00ff00ff00ff00ff00ff00ff00", 2),
+    ];
+    expect
+        .into_iter()
+        .map(|(oid, shard)| {
+            let oid_b = OidBytes::try_from(oid).unwrap();
+            let actual = layout.order_shard(&oid_b);
+            assert_eq!(actual, shard);
+            // the same order always goes to the same primary
+            let oid_b = OidBytes::try_from(oid).unwrap();
+            assert_eq!(layout.order_shard(&oid_b), actual);
+        })
+        .count();
+    // orders are spread over all the shards, never to the primary without it
+    let mut counts = [0usize; 3];
+    (0u16..256)
+        .map(|n| {
+            let oid = format!("{:04x}", n);
+            let oid_b = OidBytes::try_from(oid.as_str()).unwrap();
+            counts[layout.order_shard(&oid_b)] += 1;
+        })
+        .count();
+    assert_eq!(counts[0], 0);
+    assert!(counts[1] > 64);
+    assert!(counts[2] > 64);
+}
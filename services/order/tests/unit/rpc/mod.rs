@@ -1,2 +1,42 @@
 #[cfg(feature = "amqprs")]
 mod amqp;
+
+use std::env;
+
+use ecommerce_common::config::AppRpcCfg;
+use ecommerce_common::constant::env_vars::SERVICE_BASEPATH;
+
+use order::constant::hard_limit;
+use order::usecase::EditProductPolicyUseCase;
+use order::{AppCfgHardLimit, AppConfig};
+
+// outbound routes called by this application should be bound in the
+// configuration used in deployment, each with reply queue
+#[test]
+fn dev_settings_bind_outbound_routes() {
+    let service_basepath = env::var(SERVICE_BASEPATH).unwrap();
+    let fullpath = service_basepath + "/settings/development.json";
+    let limit = AppCfgHardLimit {
+        nitems_per_inmem_table: hard_limit::MAX_ITEMS_STORED_PER_MODEL,
+        num_db_conns: hard_limit::MAX_DB_CONNECTIONS,
+        seconds_db_idle: hard_limit::MAX_SECONDS_DB_IDLE,
+    };
+    let cfg = AppConfig::parse_from_file(fullpath, limit).unwrap();
+    let amqp_cfg = match &cfg.rpc {
+        AppRpcCfg::AMQP(c) => c,
+        _others => panic!("unexpected rpc handler type"),
+    };
+    let routes = [
+        EditProductPolicyUseCase::RPC_ROUTE_STORE_PROFILE,
+        "rpc.product.get_product",
+    ];
+    for route in routes {
+        let bind_cfg = amqp_cfg
+            .bindings
+            .iter()
+            .find(|b| b.routing_key.as_str() == route);
+        assert!(bind_cfg.is_some(), "binding not found, {route}");
+        let reply_cfg = bind_cfg.unwrap().reply.as_ref();
+        assert!(reply_cfg.is_some(), "reply queue not found, {route}");
+    }
+}
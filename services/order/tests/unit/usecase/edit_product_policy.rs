@@ -4,16 +4,24 @@ use std::boxed::Box;
 use std::result::Result as DefaultResult;
 use std::sync::Arc;
 
+use chrono::{DateTime, Duration, Local};
+
+use ecommerce_common::api::rpc::dto::{StoreProfileReplicaDto, StoreProfileReplicaReqDto};
 use ecommerce_common::error::AppErrorCode;
 
+use super::MockProductPolicyRepo;
+use crate::{ut_setup_share_state, MockConfidential};
 use order::api::web::dto::ProductPolicyDto;
+use order::constant::app_meta;
 use order::error::AppError;
+use order::model::{ProductPolicyModel, ProductPolicyModelSet};
 use order::usecase::{
     AppUseKsRPCreply, EditProductPolicyResult, EditProductPolicyUseCase, ProductInfoReq,
-    ProductInfoResp,
+    ProductInfoResp, ProductPolicyHistoryUcOutput, ProductPolicyHistoryUseCase,
 };
 use order::{
-    AbsRpcClientCtx, AbsRpcServerCtx, AbstractRpcClient, AbstractRpcContext, AppRpcCfg,
+    AbsRpcClientCtx, AbsRpcServerCtx, AbstractRpcClient, AbstractRpcContext,
+    AppAuthClaimPermission, AppAuthPermissionCode, AppAuthedClaim, AppRpcCfg,
     AppRpcClientReqProperty, AppRpcReply, AppRpcRouteHdlrFn, AppSharedState,
 };
 
@@ -67,13 +75,59 @@ impl UTestDummyRpcContext {
     }
 }
 
+struct UTestStoreRpcContext {}
+struct UTestStoreRpcClient {
+    reply: Vec<u8>,
+}
+
+#[async_trait]
+impl AbsRpcClientCtx for UTestStoreRpcContext {
+    async fn acquire(&self, _num_retry: u8) -> DefaultResult<Box<dyn AbstractRpcClient>, AppError> {
+        Ok(Box::new(UTestStoreRpcClient { reply: Vec::new() }))
+    }
+}
+#[async_trait]
+impl AbsRpcServerCtx for UTestStoreRpcContext {
+    async fn server_start(
+        &self,
+        _shr_state: AppSharedState,
+        _route_hdlr: AppRpcRouteHdlrFn,
+    ) -> DefaultResult<(), AppError> {
+        Err(AppError {
+            code: AppErrorCode::NotImplemented,
+            detail: None,
+        })
+    }
+}
+impl AbstractRpcContext for UTestStoreRpcContext {
+    fn label(&self) -> &'static str {
+        "unit-test"
+    }
+}
+
+#[async_trait]
+impl AbstractRpcClient for UTestStoreRpcClient {
+    async fn send_request(
+        mut self: Box<Self>,
+        props: AppRpcClientReqProperty,
+    ) -> DefaultResult<Box<dyn AbstractRpcClient>, AppError> {
+        self.reply = ut_store_profile_reply(&props.msgbody);
+        Ok(self)
+    }
+    async fn receive_response(&mut self) -> DefaultResult<AppRpcReply, AppError> {
+        Ok(AppRpcReply {
+            body: self.reply.clone(),
+        })
+    }
+}
+
 fn setup_data() -> Vec<ProductPolicyDto> {
     let raw = r#"
         [
-            {"product_id":22, "auto_cancel_secs":600, "warranty_hours":1800 },
-            {"product_id":168, "auto_cancel_secs":610, "warranty_hours":1700 },
-            {"product_id":79, "auto_cancel_secs":630, "warranty_hours":1600 },
-            {"product_id":19, "auto_cancel_secs":660, "warranty_hours":1500 }
+            {"seller_id":77, "product_id":22, "auto_cancel_secs":600, "warranty_hours":1800 },
+            {"seller_id":77, "product_id":168, "auto_cancel_secs":610, "warranty_hours":1700 },
+            {"seller_id":77, "product_id":79, "auto_cancel_secs":630, "warranty_hours":1600 },
+            {"seller_id":77, "product_id":19, "auto_cancel_secs":660, "warranty_hours":1500 }
         ]
     "#;
     deserialize_json(raw).unwrap()
@@ -215,3 +269,234 @@ async fn check_product_existence_found_nonexist_item() {
     let missing_product_ids = result.unwrap();
     assert_eq!(missing_product_ids, vec![168]);
 }
+
+fn mock_rpc_serialize_store_msg(
+    src: StoreProfileReplicaReqDto,
+) -> DefaultResult<Vec<u8>, AppError> {
+    let src = serde_json::to_vec(&src).unwrap();
+    Ok(src)
+}
+fn mock_rpc_deserialize_store_msg(
+    src: &Vec<u8>,
+) -> DefaultResult<StoreProfileReplicaDto, AppError> {
+    serde_json::from_slice::<StoreProfileReplicaDto>(src).map_err(|_e| AppError {
+        code: AppErrorCode::RpcRemoteInvalidReply,
+        detail: Some("unit-test".to_string()),
+    })
+}
+
+fn ut_store_profile_reply(msgbody: &[u8]) -> Vec<u8> {
+    let req = serde_json::from_slice::<serde_json::Value>(msgbody).unwrap();
+    let store_id = req.get("store_id").unwrap().as_u64().unwrap();
+    let t_now = Local::now().fixed_offset();
+    let (t_past, t_future) = (t_now - Duration::days(30), t_now + Duration::days(30));
+    // the unit-test user supervises store 77, works for store 78, used to work
+    // for store 79, and has nothing to do with store 80
+    let (supervisor_id, staff_id, end_before) = match store_id {
+        77 => (UTEST_USR_PROF_ID, 1005, t_future),
+        78 => (1006, UTEST_USR_PROF_ID, t_future),
+        79 => (1006, UTEST_USR_PROF_ID, t_now - Duration::days(1)),
+        _others => (1006, 1005, t_future),
+    };
+    let body = serde_json::json!({
+        "label": "unit-test-store", "active": true, "supervisor_id": supervisor_id,
+        "emails": null, "phones": null, "location": null,
+        "staff": [{
+            "staff_id": staff_id, "start_after": t_past.to_rfc3339(),
+            "end_before": end_before.to_rfc3339(),
+        }],
+    });
+    serde_json::to_vec(&body).unwrap()
+}
+
+async fn mock_run_rpc_store_profile(
+    _ctx: Arc<Box<dyn AbstractRpcContext>>,
+    prop: AppRpcClientReqProperty,
+) -> AppUseKsRPCreply {
+    assert_eq!(prop.route.as_str(), "rpc.storefront.get_profile");
+    let res = AppRpcReply {
+        body: ut_store_profile_reply(&prop.msgbody),
+    };
+    Ok(res)
+}
+
+#[tokio::test]
+async fn check_store_staff_ok() {
+    let mut data = setup_data();
+    data[1].seller_id = 78;
+    let rpc_ctx = UTestDummyRpcContext::test_build();
+    let store_ids = data.iter().map(|d| d.seller_id).collect::<Vec<_>>();
+    let result = EditProductPolicyUseCase::check_store_staff(
+        &store_ids,
+        UTEST_USR_PROF_ID,
+        rpc_ctx,
+        mock_run_rpc_store_profile,
+        mock_rpc_serialize_store_msg,
+        mock_rpc_deserialize_store_msg,
+    )
+    .await;
+    assert!(result.is_ok());
+    let denied_store_ids = result.unwrap();
+    assert!(denied_store_ids.is_empty());
+}
+
+#[tokio::test]
+async fn check_store_staff_foreign_seller() {
+    let mut data = setup_data();
+    data[1].seller_id = 80;
+    data[2].seller_id = 79;
+    data[3].seller_id = 78;
+    let rpc_ctx = UTestDummyRpcContext::test_build();
+    let store_ids = data.iter().map(|d| d.seller_id).collect::<Vec<_>>();
+    let result = EditProductPolicyUseCase::check_store_staff(
+        &store_ids,
+        UTEST_USR_PROF_ID,
+        rpc_ctx,
+        mock_run_rpc_store_profile,
+        mock_rpc_serialize_store_msg,
+        mock_rpc_deserialize_store_msg,
+    )
+    .await;
+    assert!(result.is_ok());
+    let denied_store_ids = result.unwrap();
+    assert_eq!(denied_store_ids, vec![79, 80]);
+}
+
+#[tokio::test]
+async fn check_store_staff_rpc_error() {
+    let rpc_ctx = UTestDummyRpcContext::test_build();
+    let actual = EditProductPolicyUseCase::check_store_staff(
+        &[77],
+        UTEST_USR_PROF_ID,
+        rpc_ctx,
+        mock_run_rpc_remote_down,
+        mock_rpc_serialize_store_msg,
+        mock_rpc_deserialize_store_msg,
+    )
+    .await;
+    assert!(actual.is_err());
+    let (result, _msg) = actual.err().unwrap();
+    assert_eq!(
+        result,
+        EditProductPolicyResult::Other(AppErrorCode::RpcRemoteUnavail)
+    );
+}
+
+fn ut_setup_history_usecase(
+    repo: MockProductPolicyRepo,
+    perm_granted: bool,
+) -> ProductPolicyHistoryUseCase {
+    let shr_state = ut_setup_share_state("config_ok_no_sqldb.json", Box::new(MockConfidential {}));
+    let t_now = Local::now().timestamp();
+    let perms = if perm_granted {
+        vec![AppAuthClaimPermission {
+            app_code: app_meta::RESOURCE_QUOTA_AP_CODE,
+            codename: AppAuthPermissionCode::can_create_product_policy,
+        }]
+    } else {
+        vec![]
+    };
+    let authed_usr = AppAuthedClaim {
+        profile: UTEST_USR_PROF_ID,
+        iat: t_now - 5,
+        exp: t_now + 60,
+        perms,
+        quota: vec![],
+        aud: vec![app_meta::LABAL.to_string()],
+    };
+    let rpc_ctx: Box<dyn AbstractRpcContext> = Box::new(UTestStoreRpcContext {});
+    ProductPolicyHistoryUseCase {
+        repo: Box::new(repo),
+        log: shr_state.log_context().clone(),
+        rpc_ctx: Arc::new(rpc_ctx),
+        authed_usr,
+        rpc_serialize_msg: mock_rpc_serialize_store_msg,
+        rpc_deserialize_msg: mock_rpc_deserialize_store_msg,
+    }
+}
+
+#[tokio::test]
+async fn history_ok() {
+    let policies = [
+        (1u32, 900u32, 1500u32, "2023-05-11T09:10:00+08:00"),
+        (0, 600, 1800, "2023-02-23T18:37:00+08:00"),
+        (2, 930, 1750, "2023-08-04T07:55:00+08:00"),
+    ]
+    .into_iter()
+    .map(|d| ProductPolicyModel {
+        store_id: 77,
+        product_id: 168,
+        version: d.0,
+        effective_from: DateTime::parse_from_rfc3339(d.3).unwrap(),
+        auto_cancel_secs: d.1,
+        warranty_hours: d.2,
+        max_num_rsv: 0,
+        min_num_rsv: 0,
+        is_create: false,
+    })
+    .collect::<Vec<_>>();
+    let mock_repo = MockProductPolicyRepo::build(Ok(ProductPolicyModelSet { policies }));
+    let uc = ut_setup_history_usecase(mock_repo, true);
+    let result = uc.execute(77, 168).await;
+    assert!(result.is_ok());
+    if let Ok(ProductPolicyHistoryUcOutput::Success(resp)) = result {
+        assert_eq!(resp.seller_id, 77);
+        assert_eq!(resp.product_id, 168);
+        assert_eq!(resp.versions.len(), 3);
+        [(0u32, 600u32), (1, 900), (2, 930)]
+            .into_iter()
+            .zip(resp.versions.iter())
+            .map(|((expect_ver, expect_cancel), actual)| {
+                assert_eq!(actual.version, expect_ver);
+                assert_eq!(actual.auto_cancel_secs, expect_cancel);
+            })
+            .count();
+        let t0 = DateTime::parse_from_rfc3339(resp.versions[0].effective_from.as_str()).unwrap();
+        let t2 = DateTime::parse_from_rfc3339(resp.versions[2].effective_from.as_str()).unwrap();
+        assert!(t0 < t2);
+    } else {
+        panic!("unexpected use-case output");
+    }
+} // end of fn history_ok
+
+#[tokio::test]
+async fn history_not_found() {
+    let mock_repo = MockProductPolicyRepo::build(Ok(ProductPolicyModelSet { policies: vec![] }));
+    let uc = ut_setup_history_usecase(mock_repo, true);
+    let result = uc.execute(77, 169).await;
+    assert!(matches!(result, Ok(ProductPolicyHistoryUcOutput::NotFound)));
+}
+
+#[tokio::test]
+async fn history_repo_error() {
+    let mock_repo = MockProductPolicyRepo::build(Err(AppError {
+        code: AppErrorCode::DataCorruption,
+        detail: None,
+    }));
+    let uc = ut_setup_history_usecase(mock_repo, true);
+    let result = uc.execute(77, 170).await;
+    assert!(result.is_err());
+    if let Err(e) = result {
+        assert_eq!(e.code, AppErrorCode::DataCorruption);
+    }
+}
+
+#[tokio::test]
+async fn history_permission_denied() {
+    // the user is not staff of the store
+    let mock_repo = MockProductPolicyRepo::build(Ok(ProductPolicyModelSet { policies: vec![] }));
+    let uc = ut_setup_history_usecase(mock_repo, true);
+    let result = uc.execute(80, 168).await;
+    assert!(matches!(
+        result,
+        Ok(ProductPolicyHistoryUcOutput::PermissionDeny)
+    ));
+    // the user is staff of the store, without permission to edit policies
+    let mock_repo = MockProductPolicyRepo::build(Ok(ProductPolicyModelSet { policies: vec![] }));
+    let uc = ut_setup_history_usecase(mock_repo, false);
+    let result = uc.execute(78, 168).await;
+    assert!(matches!(
+        result,
+        Ok(ProductPolicyHistoryUcOutput::PermissionDeny)
+    ));
+}
@@ -15,7 +15,10 @@ use order::api::dto::ProdAttrValueDto;
 use order::api::rpc::dto::{
     OrderReplicaInventoryDto, OrderReplicaInventoryReqDto, StockReturnErrorDto,
 };
-use order::api::web::dto::{OlineProductAttrDto, OrderLineReturnReqDto, OrderLineRsvReqDto};
+use order::api::web::dto::{
+    OlineProductAttrDto, OrderLineCancelErrorReason, OrderLineCancelReqDto, OrderLineReturnReqDto,
+    OrderLineRsvReqDto,
+};
 use order::constant::app_meta;
 use order::error::AppError;
 use order::model::{
@@ -26,9 +29,10 @@ use order::model::{
 };
 use order::repository::{AbsOrderRepo, AbsOrderReturnRepo};
 use order::usecase::{
-    CreateOrderUsKsErr, CreateOrderUseCase, OrderDiscardUnpaidItemsUseCase,
-    OrderReplicaInventoryUseCase, OrderReplicaRefundUseCase, ReturnLinesReqUcOutput,
-    ReturnLinesReqUseCase,
+    CancelUnpaidLinesUcOutput, CancelUnpaidLinesUseCase, CreateOrderUsKsErr, CreateOrderUseCase,
+    OrderDiscardUnpaidItemsUseCase, OrderListUcOutput, OrderListUseCase,
+    OrderReplicaInventoryUseCase, OrderReplicaRefundUseCase, OrderRetrieveUcOutput,
+    OrderRetrieveUseCase, ReturnLinesReqUcOutput, ReturnLinesReqUseCase,
 };
 use order::{AppAuthClaimPermission, AppAuthPermissionCode, AppAuthedClaim};
 
@@ -38,19 +42,23 @@ use crate::{ut_setup_share_state, MockConfidential};
 fn ut_setup_prod_policies() -> ProductPolicyModelSet {
     let policies = [
         #[cfg_attr(rustfmt, rustfmt_skip)]
-        (1168u64, 0u16, 127u32, 1008u32, false, 0u16),
-        (168, 0, 20000, 1250, false, 0),
-        (174, 0, 30000, 2255, false, 0),
-        (169, 1, 21000, 150, false, 5),
+        (51u32, 1168u64, 0u16, 127u32, 1008u32, false, 0u16, 0u32),
+        (51, 168, 0, 20000, 1250, false, 0, 0),
+        (52, 168, 0, 20000, 900, false, 0, 3),
+        (52, 174, 0, 30000, 2255, false, 0, 0),
+        (51, 169, 1, 21000, 150, false, 5, 1),
     ]
     .into_iter()
     .map(|d| ProductPolicyModel {
-        product_id: d.0,
-        min_num_rsv: d.1,
-        warranty_hours: d.2,
-        auto_cancel_secs: d.3,
-        is_create: d.4,
-        max_num_rsv: d.5,
+        store_id: d.0,
+        product_id: d.1,
+        version: d.7,
+        effective_from: Local::now().fixed_offset(),
+        min_num_rsv: d.2,
+        warranty_hours: d.3,
+        auto_cancel_secs: d.4,
+        is_create: d.5,
+        max_num_rsv: d.6,
     })
     .collect::<Vec<_>>();
     ProductPolicyModelSet { policies }
@@ -158,7 +166,7 @@ fn validate_orderline_ok() {
     assert!(result.is_ok());
     if let Ok(v) = result {
         assert_eq!(v.len(), 3);
-        v.into_iter()
+        v.iter()
             .map(|m| {
                 let id = m.id();
                 let search_key = (id.store_id(), id.product_id());
@@ -171,6 +179,20 @@ fn validate_orderline_ok() {
                 assert!(found);
             })
             .count();
+        // the same product is applied with different policies in different stores
+        let rsv_times = [52u32, 51]
+            .into_iter()
+            .map(|store_id| {
+                v.iter()
+                    .find(|m| m.id().store_id() == store_id && m.id().product_id() == 168)
+                    .map(|m| (m.policy.reserved_until, m.policy.version))
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+        assert!(rsv_times[0].0 < rsv_times[1].0);
+        // order line keeps the policy version it was created with
+        assert_eq!(rsv_times[0].1, 3);
+        assert_eq!(rsv_times[1].1, 0);
     }
 } // end of fn validate_orderline_ok
 
@@ -325,8 +347,10 @@ fn ut_setup_orderlines() -> Vec<OrderLineModel> {
     .map(|d| {
         let id_ = OrderLineIdentity::from((d.0, d.1, 0));
         let price = OrderLinePriceModel::from((d.2, d.3));
-        let qty = OrderLineQuantityModel {reserved: d.4, paid: d.5, paid_last_update};
-        let policy = OrderLineAppliedPolicyModel {reserved_until, warranty_until};
+        let qty = OrderLineQuantityModel {
+            reserved: d.4, paid: d.5, paid_last_update, cancelled: 0, delivered: d.5,
+        };
+        let policy = OrderLineAppliedPolicyModel {reserved_until, warranty_until, version: 0};
         let attrs_charge = ProdAttriPriceModel::from((attr_lastupdate, None));
         OrderLineModel::from((id_, price, policy, qty, attrs_charge))
     })
@@ -357,9 +381,12 @@ fn ut_setup_olines_returns() -> Vec<OrderReturnModel> {
 }
 
 async fn discard_unpaid_items_common(
-    stock_return_results: Vec<DefaultResult<Vec<StockReturnErrorDto>, AppError>>,
+    mut stock_return_results: Vec<DefaultResult<Vec<StockReturnErrorDto>, AppError>>,
     fetched_ol_sets: Vec<OrderLineModelSet>,
 ) -> DefaultResult<(), AppError> {
+    // the first stock repo is loaded for releasing pending reservations, which
+    // does not return any stock item
+    stock_return_results.insert(0, Ok(vec![]));
     let shr_state = ut_setup_share_state("config_ok_no_sqldb.json", Box::new(MockConfidential {}));
     let logctx = shr_state.log_context().clone();
     let not_impl_err = AppError {
@@ -782,3 +809,241 @@ async fn replica_refund_ok() {
         assert_eq!(diff_cnt, 0);
     }
 } // end of fn replica_refund_ok
+
+fn ut_setup_authed_claim(usr_id: u32) -> AppAuthedClaim {
+    AppAuthedClaim {
+        profile: usr_id,
+        iat: 0,
+        exp: 0,
+        aud: Vec::new(),
+        quota: vec![],
+        perms: vec![],
+    }
+}
+
+#[tokio::test]
+async fn retrieve_order_ok() {
+    let fetched_olines = ut_setup_orderlines();
+    let mocked_seller_ids = fetched_olines
+        .iter()
+        .map(|v| v.id().store_id())
+        .collect::<Vec<_>>();
+    let currency_rate = ut_setup_order_currency(mocked_seller_ids);
+    let owner_usr_id = 1710u32;
+    let order_ctime = DateTime::parse_from_rfc3339("2023-11-17T09:23:50+02:00").unwrap();
+    let o_repo = ut_oreturn_setup_repository_1(
+        fetched_olines,
+        vec![],
+        owner_usr_id,
+        Some(order_ctime),
+        Some(currency_rate),
+    );
+    let uc = OrderRetrieveUseCase {
+        authed_claim: ut_setup_authed_claim(owner_usr_id),
+        o_repo,
+    };
+    let result = uc.execute("SomebodyOrderedThis".to_string()).await;
+    assert!(result.is_ok());
+    if let Ok(OrderRetrieveUcOutput::Success(detail)) = result {
+        assert_eq!(detail.order_id.as_str(), "SomebodyOrderedThis");
+        assert_eq!(detail.usr_id, owner_usr_id);
+        assert_eq!(detail.time, order_ctime.to_rfc3339());
+        assert_eq!(detail.lines.len(), 3);
+        assert_eq!(detail.currency.sellers.len(), 3);
+        assert_eq!(detail.billing.contact.first_name.as_str(), "Aplha");
+        assert_eq!(detail.shipping.option.len(), 1);
+        detail
+            .lines
+            .iter()
+            .map(|line| {
+                let expect = match (line.seller_id, line.product_id) {
+                    (108, 190) => (14, 13),
+                    (800, 191) => (15, 15),
+                    (426, 192) => (18, 15),
+                    _others => (0, 0),
+                };
+                let actual = (line.quantity.reserved, line.quantity.paid);
+                assert_eq!(actual, expect);
+                assert!(line.quantity.paid_last_update.is_some());
+            })
+            .count();
+    } else {
+        panic!("unexpected use-case output");
+    }
+} // end of fn retrieve_order_ok
+
+#[tokio::test]
+async fn retrieve_order_invalid_owner() {
+    let o_repo = ut_oreturn_setup_repository_1(ut_setup_orderlines(), vec![], 1710, None, None);
+    let uc = OrderRetrieveUseCase {
+        authed_claim: ut_setup_authed_claim(1711),
+        o_repo,
+    };
+    let result = uc.execute("SomebodyOrderedThis".to_string()).await;
+    assert!(matches!(result, Ok(OrderRetrieveUcOutput::InvalidOwner)));
+}
+
+async fn list_orders_common(
+    fetched_oids_ctime: Vec<String>,
+    page: u32,
+    page_size: u16,
+) -> DefaultResult<OrderListUcOutput, AppError> {
+    let owner_usr_id = 1710u32;
+    let order_ctime = DateTime::parse_from_rfc3339("2023-11-17T09:23:50+02:00").unwrap();
+    let o_repo = ut_oreturn_setup_repository_1(
+        vec![],
+        fetched_oids_ctime,
+        owner_usr_id,
+        Some(order_ctime),
+        None,
+    );
+    let uc = OrderListUseCase {
+        authed_claim: ut_setup_authed_claim(owner_usr_id),
+        o_repo,
+    };
+    let t0 = DateTime::parse_from_rfc3339("2023-11-16T09:23:50+02:00").unwrap();
+    let t1 = DateTime::parse_from_rfc3339("2023-11-18T09:23:50+02:00").unwrap();
+    uc.execute(t0, t1, page, page_size).await
+}
+
+#[tokio::test]
+async fn list_orders_ok() {
+    let oids = ["order739", "order446", "order701"]
+        .into_iter()
+        .map(String::from)
+        .collect::<Vec<_>>();
+    let result = list_orders_common(oids.clone(), 1, 2).await;
+    if let Ok(OrderListUcOutput::Success(resp)) = result {
+        assert_eq!(resp.total, 3);
+        assert_eq!(resp.page, 1);
+        let actual = resp
+            .orders
+            .iter()
+            .map(|o| o.order_id.as_str())
+            .collect::<Vec<_>>();
+        // orders created at the same time are sorted by their IDs
+        assert_eq!(actual, vec!["order446", "order701"]);
+    } else {
+        panic!("unexpected use-case output");
+    }
+    let result = list_orders_common(oids, 2, 2).await;
+    if let Ok(OrderListUcOutput::Success(resp)) = result {
+        assert_eq!(resp.total, 3);
+        assert_eq!(resp.orders.len(), 1);
+        assert_eq!(resp.orders[0].order_id.as_str(), "order739");
+        let ctime = DateTime::parse_from_rfc3339(resp.orders[0].time.as_str()).unwrap();
+        assert_eq!(ctime.to_rfc3339(), "2023-11-17T09:23:50+02:00");
+    } else {
+        panic!("unexpected use-case output");
+    }
+}
+
+#[tokio::test]
+async fn list_orders_invalid_page() {
+    let oids = vec!["order739".to_string()];
+    let result = list_orders_common(oids.clone(), 0, 2).await;
+    assert!(matches!(result, Ok(OrderListUcOutput::InvalidRequest)));
+    let result = list_orders_common(oids, 1, 0).await;
+    assert!(matches!(result, Ok(OrderListUcOutput::InvalidRequest)));
+}
+
+#[tokio::test]
+async fn list_orders_time_range_too_long() {
+    let owner_usr_id = 1710u32;
+    let order_ctime = DateTime::parse_from_rfc3339("2023-11-17T09:23:50+02:00").unwrap();
+    let o_repo = ut_oreturn_setup_repository_1(
+        vec![],
+        vec!["order739".to_string()],
+        owner_usr_id,
+        Some(order_ctime),
+        None,
+    );
+    let uc = OrderListUseCase {
+        authed_claim: ut_setup_authed_claim(owner_usr_id),
+        o_repo,
+    };
+    let t0 = DateTime::parse_from_rfc3339("2021-11-16T09:23:50+02:00").unwrap();
+    let t1 = DateTime::parse_from_rfc3339("2023-11-18T09:23:50+02:00").unwrap();
+    let result = uc.execute(t0, t1, 1, 2).await;
+    assert!(matches!(result, Ok(OrderListUcOutput::InvalidRequest)));
+}
+
+async fn cancel_unpaid_lines_common(
+    authed_usr_id: u32,
+    stock_return_results: Vec<DefaultResult<Vec<StockReturnErrorDto>, AppError>>,
+    req: Vec<OrderLineCancelReqDto>,
+) -> DefaultResult<CancelUnpaidLinesUcOutput, AppError> {
+    let shr_state = ut_setup_share_state("config_ok_no_sqldb.json", Box::new(MockConfidential {}));
+    let not_impl_err = AppError {
+        detail: None,
+        code: AppErrorCode::NotImplemented,
+    };
+    let repo = MockOrderRepo::build(
+        Err(not_impl_err.clone()),
+        Err(not_impl_err),
+        stock_return_results,
+        vec![],
+        ut_setup_orderlines(),
+        vec![],
+        Some(1710),
+        None,
+        None,
+    );
+    let uc = CancelUnpaidLinesUseCase {
+        authed_claim: ut_setup_authed_claim(authed_usr_id),
+        o_repo: Box::new(repo),
+        logctx: shr_state.log_context().clone(),
+    };
+    uc.execute("SomebodyOrderedThis".to_string(), req).await
+}
+
+#[tokio::test]
+async fn cancel_unpaid_lines_ok() {
+    let req = [(108u32, 190u64), (426, 192)]
+        .into_iter()
+        .map(|d| OrderLineCancelReqDto {
+            seller_id: d.0,
+            product_id: d.1,
+            attr_set_seq: 0,
+        })
+        .collect::<Vec<_>>();
+    let result = cancel_unpaid_lines_common(1710, vec![Ok(vec![])], req).await;
+    assert!(matches!(result, Ok(CancelUnpaidLinesUcOutput::Success)));
+}
+
+#[tokio::test]
+async fn cancel_unpaid_lines_invalid_owner() {
+    let result = cancel_unpaid_lines_common(1711, vec![], vec![]).await;
+    assert!(matches!(
+        result,
+        Ok(CancelUnpaidLinesUcOutput::InvalidOwner)
+    ));
+}
+
+#[tokio::test]
+async fn cancel_unpaid_lines_client_error() {
+    let req = [(108u32, 190u64), (800, 191), (426, 8964)]
+        .into_iter()
+        .map(|d| OrderLineCancelReqDto {
+            seller_id: d.0,
+            product_id: d.1,
+            attr_set_seq: 0,
+        })
+        .collect::<Vec<_>>();
+    let result = cancel_unpaid_lines_common(1710, vec![], req).await;
+    if let Ok(CancelUnpaidLinesUcOutput::InvalidRequest(errors)) = result {
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].product_id, 191);
+        assert!(matches!(
+            errors[0].reason,
+            OrderLineCancelErrorReason::AlreadyPaid
+        ));
+        assert_eq!(errors[1].product_id, 8964);
+        assert!(matches!(
+            errors[1].reason,
+            OrderLineCancelErrorReason::NotExist
+        ));
+    } else {
+        panic!("unexpected use-case output");
+    }
+}
@@ -12,7 +12,7 @@ use std::sync::{Arc, Mutex};
 use std::vec;
 
 use async_trait::async_trait;
-use chrono::{DateTime, FixedOffset};
+use chrono::{DateTime, FixedOffset, Local};
 use tokio::sync::Mutex as AsyncMutex;
 
 use ecommerce_common::api::dto::CurrencyDto;
@@ -21,16 +21,20 @@ use ecommerce_common::error::AppErrorCode;
 use ecommerce_common::model::order::{BillingModel, ContactModel};
 
 use order::api::dto::ShippingMethod;
-use order::api::rpc::dto::{StockLevelReturnDto, StockReturnErrorDto};
+use order::api::rpc::dto::{ShipmentErrorDto, StockLevelReturnDto, StockReturnErrorDto};
+use order::api::web::dto::ShipmentReceiptErrorReason;
 use order::error::AppError;
 use order::model::{
     CurrencyModelSet, OrderCurrencyModel, OrderLineIdentity, OrderLineModel, OrderLineModelSet,
-    OrderReturnModel, ProductStockIdentity, ShippingModel, ShippingOptionModel, StockLevelModelSet,
+    OrderReturnModel, ProductPolicyModelSet, ProductStockIdentity, ProductStockIdentity2,
+    ShipmentModel, ShippingModel, ShippingOptionModel, StockLevelModelSet,
 };
 use order::repository::{
-    AbsCurrencyRepo, AbsOrderRepo, AbsOrderReturnRepo, AbsOrderStockRepo,
-    AppOrderFetchRangeCallback, AppOrderRepoUpdateLinesUserFunc, AppStockRepoReserveReturn,
-    AppStockRepoReserveUserFunc, AppStockRepoReturnUserFunc,
+    AbsCurrencyRepo, AbsOrderRepo, AbsOrderReturnRepo, AbsOrderStockRepo, AbstProductPolicyRepo,
+    AppOrderFetchRangeCallback, AppOrderRepoCancelLinesReturn, AppOrderRepoCancelLinesUserFunc,
+    AppOrderRepoOwnedIdsReturn, AppOrderRepoShipmentCreateUserFunc,
+    AppOrderRepoShipmentReceiptUserFunc, AppOrderRepoUpdateLinesUserFunc,
+    AppStockRepoReserveReturn, AppStockRepoReserveUserFunc, AppStockRepoReturnUserFunc,
 };
 use order::usecase::initiate_rpc_request;
 use order::{
@@ -44,6 +48,10 @@ struct MockCurrencyRepo {
     _mocked_rate_mset: AsyncMutex<Option<CurrencyModelSet>>,
 }
 
+struct MockProductPolicyRepo {
+    _mocked_history: AsyncMutex<Option<DefaultResult<ProductPolicyModelSet, AppError>>>,
+}
+
 struct MockStockRepo {
     _mocked_save_r: DefaultResult<(), AppError>,
     _mocked_fetch_r: DefaultResult<StockLevelModelSet, AppError>,
@@ -90,6 +98,38 @@ impl MockCurrencyRepo {
     }
 }
 
+#[async_trait]
+impl AbstProductPolicyRepo for MockProductPolicyRepo {
+    #[rustfmt::skip]
+    async fn fetch(&self, _ids: Vec<(u32, u64)>) -> DefaultResult<ProductPolicyModelSet, AppError> {
+        Err(AppError {code: AppErrorCode::NotImplemented, detail: None})
+    }
+
+    async fn fetch_history(
+        &self,
+        _store_id: u32,
+        _product_id: u64,
+    ) -> DefaultResult<ProductPolicyModelSet, AppError> {
+        let mut guard = self._mocked_history.lock().await;
+        guard.take().unwrap_or(Err(AppError {
+            code: AppErrorCode::InvalidInput,
+            detail: Some("MockProductPolicyRepo::fetch_history".to_string()),
+        }))
+    }
+
+    #[rustfmt::skip]
+    async fn save(&self, _ppset: ProductPolicyModelSet) -> DefaultResult<(), AppError> {
+        Err(AppError {code: AppErrorCode::NotImplemented, detail: None})
+    }
+}
+impl MockProductPolicyRepo {
+    fn build(mock_history: DefaultResult<ProductPolicyModelSet, AppError>) -> Self {
+        Self {
+            _mocked_history: AsyncMutex::new(Some(mock_history)),
+        }
+    }
+}
+
 #[async_trait]
 impl AbsOrderStockRepo for MockStockRepo {
     async fn fetch(
@@ -98,6 +138,12 @@ impl AbsOrderStockRepo for MockStockRepo {
     ) -> DefaultResult<StockLevelModelSet, AppError> {
         self._mocked_fetch_r.clone()
     }
+    async fn fetch_avail(
+        &self,
+        _pids: Vec<ProductStockIdentity2>,
+    ) -> DefaultResult<StockLevelModelSet, AppError> {
+        self._mocked_fetch_r.clone()
+    }
     async fn save(&self, _slset: StockLevelModelSet) -> DefaultResult<(), AppError> {
         self._mocked_save_r.clone()
     }
@@ -129,6 +175,13 @@ impl AbsOrderStockRepo for MockStockRepo {
             returns.remove(0)
         }
     }
+    async fn release_pending_rsv(
+        &self,
+        _time_before: DateTime<FixedOffset>,
+        _cb: AppStockRepoReturnUserFunc,
+    ) -> DefaultResult<usize, AppError> {
+        Ok(0)
+    }
 }
 
 #[async_trait]
@@ -175,9 +228,15 @@ impl AbsOrderRepo for MockOrderRepo {
         }
     }
     async fn fetch_billing(&self, _oid: String) -> DefaultResult<BillingModel, AppError> {
-        Err(AppError {
-            code: AppErrorCode::NotImplemented,
-            detail: None,
+        let contact = ContactModel {
+            first_name: "Aplha".to_string(),
+            last_name: "Ant".to_string(),
+            emails: vec![],
+            phones: vec![],
+        };
+        Ok(BillingModel {
+            contact,
+            address: None,
         })
     }
     async fn fetch_shipping(&self, _oid: String) -> DefaultResult<ShippingModel, AppError> {
@@ -208,6 +267,41 @@ impl AbsOrderRepo for MockOrderRepo {
         Err(AppError {code: AppErrorCode::NotImplemented, detail: None})
     }
 
+    async fn cancel_unpaid_lines(
+        &self,
+        _oid: &str,
+        pids: Vec<OrderLineIdentity>,
+        cb: AppOrderRepoCancelLinesUserFunc,
+        _stk_cb: AppStockRepoReturnUserFunc,
+    ) -> DefaultResult<AppOrderRepoCancelLinesReturn, AppError> {
+        let mut g = self._mocked_olines.lock().await;
+        if g.is_empty() {
+            let detail = "MockOrderRepo::cancel_unpaid_lines".to_string();
+            Err(AppError {
+                code: AppErrorCode::InvalidInput,
+                detail: Some(detail),
+            })
+        } else {
+            let mut models = g.drain(0..).collect::<Vec<OrderLineModel>>();
+            let time_now = Local::now().fixed_offset();
+            let errors = cb(&mut models, pids, time_now);
+            if !errors.is_empty() {
+                return Ok(Err(errors));
+            }
+            let mut g = self._mocked_stock_return.lock().unwrap();
+            let returns = g.get_mut();
+            if returns.is_empty() {
+                let detail = "MockOrderRepo::cancel_unpaid_lines, stock-return".to_string();
+                Err(AppError {
+                    code: AppErrorCode::InvalidInput,
+                    detail: Some(detail),
+                })
+            } else {
+                returns.remove(0).map(Ok)
+            }
+        }
+    }
+
     async fn fetch_lines_by_rsvtime(
         &self,
         _time_start: DateTime<FixedOffset>,
@@ -254,6 +348,36 @@ impl AbsOrderRepo for MockOrderRepo {
             Ok(g.drain(..).collect())
         }
     }
+    async fn fetch_ids_by_owner_created_time(
+        &self,
+        usr_id: u32,
+        _start: DateTime<FixedOffset>,
+        _end: DateTime<FixedOffset>,
+        num_skip: usize,
+        limit: usize,
+    ) -> DefaultResult<AppOrderRepoOwnedIdsReturn, AppError> {
+        let mut g = self._mock_oids_ctime.lock().await;
+        match (self._mock_usr_id, self._mock_ctime) {
+            (Some(owner_id), Some(ctime)) => {
+                let mut out = g
+                    .drain(..)
+                    .filter(|_oid| owner_id == usr_id)
+                    .map(|oid| (oid, ctime))
+                    .collect::<Vec<_>>();
+                out.sort();
+                let total = out.len();
+                let out = out.into_iter().skip(num_skip).take(limit).collect();
+                Ok((total, out))
+            }
+            _others => {
+                let detail = "MockOrderRepo::fetch_ids_by_owner_created_time".to_string();
+                Err(AppError {
+                    code: AppErrorCode::InvalidInput,
+                    detail: Some(detail),
+                })
+            }
+        }
+    }
     async fn owner_id(&self, _order_id: &str) -> DefaultResult<u32, AppError> {
         if let Some(usr_id) = self._mock_usr_id.as_ref() {
             Ok(usr_id.clone())
@@ -295,6 +419,27 @@ impl AbsOrderRepo for MockOrderRepo {
     async fn cancel_unpaid_time_update(&self) -> DefaultResult<(), AppError> {
         Ok(())
     }
+
+    async fn fetch_shipments(&self, _oid: &str) -> DefaultResult<Vec<ShipmentModel>, AppError> {
+        Ok(Vec::new())
+    }
+    async fn create_shipment(
+        &self,
+        _oid: &str,
+        _shipment: ShipmentModel,
+        _cb: AppOrderRepoShipmentCreateUserFunc,
+    ) -> DefaultResult<DefaultResult<(), ShipmentErrorDto>, AppError> {
+        Ok(Ok(()))
+    }
+    async fn confirm_shipment_receipt(
+        &self,
+        _oid: &str,
+        _seller_id: u32,
+        _tracking_num: &str,
+        _cb: AppOrderRepoShipmentReceiptUserFunc,
+    ) -> DefaultResult<DefaultResult<(), ShipmentReceiptErrorReason>, AppError> {
+        Ok(Err(ShipmentReceiptErrorReason::NotExist))
+    }
 } // end of impl MockOrderRepo
 
 impl MockOrderRepo {
@@ -1,22 +1,61 @@
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
 use async_trait::async_trait;
+use chrono::{DateTime, Duration, Local, Utc};
+
+use super::{AbstractOrderSyncLockCache, OrderSyncLockError, OrderSyncLockToken};
 
-use super::{AbstractOrderSyncLockCache, OrderSyncLockError};
+type InnerLockKey = (u32, String);
 
-pub(super) struct InMemOrderSyncLockCache {}
+pub(super) struct InMemOrderSyncLockCache {
+    _expiry: Duration,
+    // lock entries never released by callers are discarded after expiry time
+    _locked: Mutex<HashMap<InnerLockKey, DateTime<Utc>>>,
+}
 
-impl Default for InMemOrderSyncLockCache {
-    fn default() -> Self {
-        Self {}
+impl InMemOrderSyncLockCache {
+    pub(super) fn new(expiry_secs: u16) -> Self {
+        Self {
+            _expiry: Duration::seconds(expiry_secs as i64),
+            _locked: Mutex::new(HashMap::new()),
+        }
     }
 }
 
 #[async_trait]
 impl AbstractOrderSyncLockCache for InMemOrderSyncLockCache {
-    async fn acquire(&self, _usr_id: u32, _oid: &str) -> Result<bool, OrderSyncLockError> {
-        Ok(true) // TODO, finish implementation
+    async fn acquire(
+        &self,
+        usr_id: u32,
+        oid: &str,
+    ) -> Result<Option<OrderSyncLockToken>, OrderSyncLockError> {
+        let t_now = Local::now().to_utc();
+        let mut guard = self._locked.lock().map_err(|_e| OrderSyncLockError)?;
+        guard.retain(|_k, expiry| *expiry > t_now);
+        let key = (usr_id, oid.to_string());
+        match guard.entry(key) {
+            Entry::Occupied(_) => Ok(None),
+            Entry::Vacant(e) => {
+                let t_expiry = t_now + self._expiry;
+                let _ = e.insert(t_expiry);
+                Ok(Some(OrderSyncLockToken(t_expiry)))
+            }
+        }
     }
 
-    async fn release(&self, _usr_id: u32, _oid: &str) -> Result<(), OrderSyncLockError> {
+    async fn release(
+        &self,
+        usr_id: u32,
+        oid: &str,
+        token: OrderSyncLockToken,
+    ) -> Result<(), OrderSyncLockError> {
+        let mut guard = self._locked.lock().map_err(|_e| OrderSyncLockError)?;
+        let key = (usr_id, oid.to_string());
+        if guard.get(&key) == Some(&token.0) {
+            let _ = guard.remove(&key);
+        }
         Ok(())
     }
 }
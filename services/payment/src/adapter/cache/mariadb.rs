@@ -0,0 +1,108 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{Duration, Local, SubsecRound};
+use mysql_async::prelude::Queryable;
+use mysql_async::Params;
+
+use ecommerce_common::adapter::repository::OidBytes;
+use ecommerce_common::logging::{app_log_event, AppLogLevel};
+
+use crate::adapter::datastore::AppDStoreMariaDB;
+
+use super::{AbstractOrderSyncLockCache, OrderSyncLockError, OrderSyncLockToken};
+
+const DATETIME_FMT_P0F: &str = "%Y-%m-%d %H:%M:%S";
+
+/// Lock table shared by all application instances connecting to the same
+/// database, each row represents a lock on an order of a buyer, the lock can
+/// be taken over by others once it expires.
+pub(super) struct MariadbOrderSyncLockCache {
+    _dstore: Arc<AppDStoreMariaDB>,
+    _expiry: Duration,
+}
+
+impl MariadbOrderSyncLockCache {
+    pub(super) fn new(dstore: Arc<AppDStoreMariaDB>, expiry_secs: u16) -> Self {
+        Self {
+            _dstore: dstore,
+            _expiry: Duration::seconds(expiry_secs as i64),
+        }
+    }
+
+    fn _map_log_err(&self, fn_label: &str, detail: String) -> OrderSyncLockError {
+        let logctx = self._dstore.log_context();
+        app_log_event!(logctx, AppLogLevel::ERROR, "{fn_label}, {detail}");
+        OrderSyncLockError
+    }
+
+    fn _to_oid_bytes(&self, fn_label: &str, oid: &str) -> Result<OidBytes, OrderSyncLockError> {
+        OidBytes::try_from(oid)
+            .map_err(|(code, msg)| self._map_log_err(fn_label, format!("{:?}, {msg}", code)))
+    }
+}
+
+#[async_trait]
+impl AbstractOrderSyncLockCache for MariadbOrderSyncLockCache {
+    async fn acquire(
+        &self,
+        usr_id: u32,
+        oid: &str,
+    ) -> Result<Option<OrderSyncLockToken>, OrderSyncLockError> {
+        let fn_label = "acquire";
+        let oid_b = self._to_oid_bytes(fn_label, oid)?;
+        // the expiry time is also the token identifying current holder, it has
+        // to be truncated to the precision of the column `expiry`
+        let t_now = Local::now().to_utc().trunc_subsecs(0);
+        let t_expiry = t_now + self._expiry;
+        // The existing row is overwritten only when it expires, the number of
+        // affected rows is 1 for new row, 2 for updated row, 0 if the lock is
+        // still held by someone else.
+        let stmt = "INSERT INTO `order_sync_lock`(`usr_id`,`o_id`,`expiry`) VALUES (?,?,?) \
+                    ON DUPLICATE KEY UPDATE `expiry`=IF(`expiry` < ?, VALUES(`expiry`), `expiry`)";
+        let args = vec![
+            usr_id.into(),
+            oid_b.as_column().into(),
+            t_expiry.format(DATETIME_FMT_P0F).to_string().into(),
+            t_now.format(DATETIME_FMT_P0F).to_string().into(),
+        ];
+        let mut conn = self
+            ._dstore
+            .acquire()
+            .await
+            .map_err(|e| self._map_log_err(fn_label, format!("{:?}", e)))?;
+        let result = conn
+            .exec_iter(stmt, Params::Positional(args))
+            .await
+            .map_err(|e| self._map_log_err(fn_label, e.to_string()))?;
+        let acquired = result.affected_rows() > 0;
+        Ok(acquired.then_some(OrderSyncLockToken(t_expiry)))
+    }
+
+    async fn release(
+        &self,
+        usr_id: u32,
+        oid: &str,
+        token: OrderSyncLockToken,
+    ) -> Result<(), OrderSyncLockError> {
+        let fn_label = "release";
+        let oid_b = self._to_oid_bytes(fn_label, oid)?;
+        // the row is not deleted if the lock has been taken over by others
+        let stmt = "DELETE FROM `order_sync_lock` WHERE `usr_id`=? AND `o_id`=? AND `expiry`=?";
+        let args = vec![
+            usr_id.into(),
+            oid_b.as_column().into(),
+            token.0.format(DATETIME_FMT_P0F).to_string().into(),
+        ];
+        let mut conn = self
+            ._dstore
+            .acquire()
+            .await
+            .map_err(|e| self._map_log_err(fn_label, format!("{:?}", e)))?;
+        let _result = conn
+            .exec_iter(stmt, Params::Positional(args))
+            .await
+            .map_err(|e| self._map_log_err(fn_label, e.to_string()))?;
+        Ok(())
+    }
+} // end of impl MariadbOrderSyncLockCache
@@ -1,25 +1,65 @@
 mod in_mem;
+mod mariadb;
 
 use std::boxed::Box;
 use std::marker::{Send, Sync};
 use std::result::Result;
+use std::sync::Arc;
 
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+
+use ecommerce_common::config::{AppCacheCfg, AppLockCacheCfg};
+
+use crate::adapter::datastore::AppDataStoreContext;
+use crate::hard_limit::ORDERSYNC_LOCK_EXPIRY_SECS;
 
 use in_mem::InMemOrderSyncLockCache;
+use mariadb::MariadbOrderSyncLockCache;
 
+#[derive(Debug)]
 pub struct OrderSyncLockError;
 
+/// Identify the holder of a lock, which is the expiry time set on acquiring
+/// the lock. A holder whose lock expired cannot release the lock taken over
+/// by others afterwards.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderSyncLockToken(pub DateTime<Utc>);
+
 #[async_trait]
 pub trait AbstractOrderSyncLockCache: Send + Sync {
-    async fn acquire(&self, usr_id: u32, oid: &str) -> Result<bool, OrderSyncLockError>;
+    /// return `None` if the lock is held by others and has not expired yet
+    async fn acquire(
+        &self,
+        usr_id: u32,
+        oid: &str,
+    ) -> Result<Option<OrderSyncLockToken>, OrderSyncLockError>;
 
-    async fn release(&self, usr_id: u32, oid: &str) -> Result<(), OrderSyncLockError>;
+    /// the lock is kept if it has been taken over by others with different token
+    async fn release(
+        &self,
+        usr_id: u32,
+        oid: &str,
+        token: OrderSyncLockToken,
+    ) -> Result<(), OrderSyncLockError>;
 }
 
-// TODO, pass config object that allows users to switch between
-// different caches e.g. Redis in the future
-pub fn app_cache_order_sync_lock() -> Box<dyn AbstractOrderSyncLockCache> {
-    let cch = InMemOrderSyncLockCache::default();
-    Box::new(cch)
+// TODO, support other caches e.g. Redis in the future
+pub fn app_cache_order_sync_lock(
+    cfg: Option<&AppCacheCfg>,
+    dstore: Arc<AppDataStoreContext>,
+) -> Result<Box<dyn AbstractOrderSyncLockCache>, OrderSyncLockError> {
+    let out: Box<dyn AbstractOrderSyncLockCache> = match cfg.map(|c| &c.order_sync_lock) {
+        None => Box::new(InMemOrderSyncLockCache::new(ORDERSYNC_LOCK_EXPIRY_SECS)),
+        Some(AppLockCacheCfg::InMemory { expiry_secs }) => {
+            Box::new(InMemOrderSyncLockCache::new(*expiry_secs))
+        }
+        Some(AppLockCacheCfg::DbServer { alias, expiry_secs }) => {
+            let ds = dstore
+                .mariadb(Some(alias.as_str()))
+                .ok_or(OrderSyncLockError)?;
+            Box::new(MariadbOrderSyncLockCache::new(ds, *expiry_secs))
+        }
+    };
+    Ok(out)
 }
@@ -1,4 +1,5 @@
 mod base_client;
+mod paypal;
 mod stripe;
 
 use std::boxed::Box;
@@ -14,14 +15,16 @@ use ecommerce_common::config::App3rdPartyCfg;
 use ecommerce_common::logging::AppLogContext;
 
 pub use self::base_client::{BaseClientError, BaseClientErrorReason};
+use self::paypal::{AbstPaypalContext, AppProcessorPaypalCtx, MockProcessorPaypalCtx};
 use self::stripe::{AbstStripeContext, AppProcessorStripeCtx, MockProcessorStripeCtx};
 use crate::api::web::dto::{
     CapturePay3partyRespDto, CapturePayRespDto, ChargeCreateRespDto, PaymentMethodErrorReason,
     PaymentMethodReqDto, PaymentMethodRespDto, StoreOnboardReqDto, StoreOnboardRespDto,
 };
 use crate::model::{
-    BuyerPayInState, Charge3partyModel, ChargeBuyerMetaModel, ChargeBuyerModel,
+    BuyerPayInState, Charge3partyModel, ChargeBuyerMetaModel, ChargeBuyerModel, Label3party,
     Merchant3partyModel, Payout3partyModel, PayoutModel, RefundReqResolutionModel,
+    Webhook3partyEventModel,
 };
 
 #[async_trait]
@@ -58,10 +61,21 @@ pub trait AbstractPaymentProcessor: Send + Sync {
         &self,
         resolve_m: RefundReqResolutionModel,
     ) -> Result<RefundReqResolutionModel, AppProcessorError>;
+
+    /// verify signature of the event pushed from the 3rd party, then parse
+    /// the raw payload to event model
+    fn webhook_event(
+        &self,
+        label3pt: Label3party,
+        sig_header: &str,
+        payload: &[u8],
+    ) -> Result<Webhook3partyEventModel, AppProcessorError>;
 } // end of trait AbstractPaymentProcessor
 
 struct AppProcessorContext {
     _stripe: Box<dyn AbstStripeContext>,
+    // optional, only for platforms which enable PayPal in configuration
+    _paypal: Option<Box<dyn AbstPaypalContext>>,
     _logctx: Arc<AppLogContext>,
 }
 
@@ -79,6 +93,7 @@ pub enum AppProcessorErrorReason {
     CorruptedTimeStamp(String, i64), // label and given incorrect timestamp
     AmountOverflow(String),
     ThirdParty(String),
+    InvalidWebhook(String), // corrupted signature or event payload
 }
 
 #[derive(Debug)]
@@ -90,6 +105,7 @@ pub enum AppProcessorFnLabel {
     OnboardMerchant,
     RefreshOnboardStatus,
     Refund,
+    WebhookEvent,
 }
 
 #[derive(Debug)]
@@ -185,6 +201,7 @@ impl AppProcessorContext {
     ) -> Result<Self, AppProcessorError> {
         let mut errors = Vec::new();
         let mut result_stripe = None;
+        let mut result_paypal = None;
         cfgs3pt
             .into_iter()
             .map(|c| match c.as_ref() {
@@ -204,18 +221,36 @@ impl AppProcessorContext {
                         )
                         .map_err(|e| errors.push(e))
                         .ok();
+                    } else if result_paypal.is_none() && name.as_str().to_lowercase() == "paypal" {
+                        result_paypal = AppProcessorPaypalCtx::try_build(
+                            host.as_str(),
+                            *port,
+                            confidentiality_path.as_str(),
+                            cfdntl.clone(),
+                            _logctx.clone(),
+                        )
+                        .map_err(|e| errors.push(e))
+                        .ok();
                     }
                 }
-                App3rdPartyCfg::test { name, data_src: _ } => {
+                App3rdPartyCfg::test { name, data_src } => {
                     if result_stripe.is_none() && name.as_str().to_lowercase() == "stripe" {
                         result_stripe = Some(MockProcessorStripeCtx::build());
+                    } else if result_paypal.is_none() && name.as_str().to_lowercase() == "paypal" {
+                        result_paypal = MockProcessorPaypalCtx::build(data_src.as_str())
+                            .map_err(|e| errors.push(e))
+                            .ok();
                     }
                 }
             })
             .count();
         if errors.is_empty() {
             if let Some(_stripe) = result_stripe {
-                Ok(Self { _logctx, _stripe })
+                Ok(Self {
+                    _logctx,
+                    _stripe,
+                    _paypal: result_paypal,
+                })
             } else {
                 Err(AppProcessorError {
                     reason: AppProcessorErrorReason::InvalidConfig,
@@ -229,6 +264,14 @@ impl AppProcessorContext {
             })
         }
     } // end of fn new
+
+    fn paypal(&self) -> Result<&dyn AbstPaypalContext, AppProcessorErrorReason> {
+        self._paypal
+            .as_deref()
+            .ok_or(AppProcessorErrorReason::InvalidMethod(
+                "paypal-disabled".to_string(),
+            ))
+    }
 } // end of impl AppProcessorContext
 
 #[async_trait]
@@ -240,6 +283,10 @@ impl AbstractPaymentProcessor for AppProcessorContext {
     ) -> Result<(AppProcessorPayInResult, Charge3partyModel), AppProcessorError> {
         let result = match req_mthd {
             PaymentMethodReqDto::Stripe(c) => self._stripe.pay_in_start(&c, charge_m).await,
+            PaymentMethodReqDto::Paypal(c) => match self.paypal() {
+                Ok(p) => p.pay_in_start(&c, charge_m).await,
+                Err(e) => Err(e),
+            },
         };
         result.map_err(|reason| AppProcessorError {
             reason,
@@ -257,6 +304,10 @@ impl AbstractPaymentProcessor for AppProcessorContext {
                 .pay_in_progress(c)
                 .await
                 .map(Charge3partyModel::Stripe),
+            Charge3partyModel::Paypal(c) => match self.paypal() {
+                Ok(p) => p.pay_in_progress(c).await.map(Charge3partyModel::Paypal),
+                Err(e) => Err(e),
+            },
             Charge3partyModel::Unknown => Err(AppProcessorErrorReason::InvalidMethod(
                 "unknown".to_string(),
             )),
@@ -274,6 +325,10 @@ impl AbstractPaymentProcessor for AppProcessorContext {
     ) -> Result<AppProcessorMerchantResult, AppProcessorError> {
         let result = match req_3pt {
             StoreOnboardReqDto::Stripe(req) => self._stripe.onboard_merchant(profile, req).await,
+            StoreOnboardReqDto::Paypal(req) => match self.paypal() {
+                Ok(p) => p.onboard_merchant(profile, req).await,
+                Err(e) => Err(e),
+            },
         };
         result.map_err(|reason| AppProcessorError {
             reason,
@@ -290,6 +345,12 @@ impl AbstractPaymentProcessor for AppProcessorContext {
             (Merchant3partyModel::Stripe(ms), StoreOnboardReqDto::Stripe(ds)) => {
                 self._stripe.refresh_onboard_status(ms, ds).await
             }
+            (Merchant3partyModel::Paypal(mp), StoreOnboardReqDto::Paypal(dp)) => {
+                match self.paypal() {
+                    Ok(p) => p.refresh_onboard_status(mp, dp).await,
+                    Err(e) => Err(e),
+                }
+            }
             _others => {
                 let msg = "given-model-dto-mismatch".to_string();
                 Err(AppProcessorErrorReason::InvalidMethod(msg))
@@ -312,6 +373,10 @@ impl AbstractPaymentProcessor for AppProcessorContext {
                 .pay_out(&p_inner, s)
                 .await
                 .map(Payout3partyModel::Stripe),
+            Payout3partyModel::Paypal(s) => match self.paypal() {
+                Ok(p) => p.pay_out(&p_inner, s).await.map(Payout3partyModel::Paypal),
+                Err(e) => Err(e),
+            },
         };
         result
             .map_err(|reason| AppProcessorError {
@@ -340,6 +405,10 @@ impl AbstractPaymentProcessor for AppProcessorContext {
             Charge3partyModel::Stripe(s0) =>
                 self._stripe.refund(&r_inner, s0).await
                     .map(Charge3partyModel::Stripe),
+            Charge3partyModel::Paypal(s0) => match self.paypal() {
+                Ok(p) => p.refund(&r_inner, s0).await.map(Charge3partyModel::Paypal),
+                Err(e) => Err(e),
+            },
             Charge3partyModel::Unknown =>
                 Err(AppProcessorErrorReason::InvalidMethod("unknown".to_string())),
         };
@@ -348,9 +417,29 @@ impl AbstractPaymentProcessor for AppProcessorContext {
                 reason, fn_label: AppProcessorFnLabel::Refund,
             })
     }
+
+    fn webhook_event(
+        &self,
+        label3pt: Label3party,
+        sig_header: &str,
+        payload: &[u8],
+    ) -> Result<Webhook3partyEventModel, AppProcessorError> {
+        let result = match label3pt {
+            Label3party::Stripe => self
+                ._stripe
+                .webhook_event(sig_header, payload)
+                .map(Webhook3partyEventModel::Stripe),
+            // the order status is polled actively from PayPal
+            Label3party::Paypal => Err(AppProcessorErrorReason::NotImplemented),
+        };
+        result.map_err(|reason| AppProcessorError {
+            reason,
+            fn_label: AppProcessorFnLabel::WebhookEvent,
+        })
+    }
 } // end of impl AppProcessorContext
 
-pub(crate) fn app_processor_context(
+pub fn app_processor_context(
     cfg_3pt: &Option<Vec<Arc<App3rdPartyCfg>>>,
     cfdntl: Arc<Box<dyn AbstractConfidentiality>>,
     logctx: Arc<AppLogContext>,
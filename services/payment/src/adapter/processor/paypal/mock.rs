@@ -0,0 +1,188 @@
+use std::boxed::Box;
+use std::fs::File;
+use std::result::Result;
+
+use async_trait::async_trait;
+use chrono::{Duration, Local};
+use http_body_util::Full;
+use hyper::body::Bytes;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::Value as JsnVal;
+
+use ecommerce_common::api::rpc::dto::StoreProfileReplicaDto;
+
+use crate::api::web::dto::{
+    PaymentMethodRespDto, PaypalOrderReqDto, PaypalOrderRespDto, StoreOnboardPaypalReqDto,
+};
+use crate::model::{
+    BuyerPayInState, Charge3partyModel, Charge3partyPaypalModel, ChargeBuyerModel,
+    Merchant3partyPaypalModel, Payout3partyPaypalModel, PayoutInnerModel, PaypalActionLinkModel,
+    PaypalOrderStatusModel, RefundReqRslvInnerModel,
+};
+
+use super::super::{AppProcessorErrorReason, AppProcessorMerchantResult, AppProcessorPayInResult};
+use super::client::AppPaypalClient;
+use super::resources::{CreateOrder, CreateRefund, Order, RefundResult};
+use super::{pay_in_accepted, AbstPaypalContext};
+
+/// raw response bodies of PayPal endpoints, replayed by the mock context
+/// instead of sending requests to PayPal. Any endpoint without recorded
+/// body falls back to default successful result
+#[derive(Deserialize, Default)]
+struct MockPaypalResponses {
+    create_order: Option<JsnVal>,
+    refresh_order: Option<JsnVal>,
+    capture_order: Option<JsnVal>,
+    refund: Option<JsnVal>,
+}
+
+// TODO, conditional compilation for test
+pub(crate) struct MockProcessorPaypalCtx {
+    _resps: MockPaypalResponses,
+}
+
+impl MockProcessorPaypalCtx {
+    /// the data source is optional, all endpoints return default result if
+    /// the file does not exist
+    pub(crate) fn build(
+        data_src: &str,
+    ) -> Result<Box<dyn AbstPaypalContext>, AppProcessorErrorReason> {
+        let _resps = match File::open(data_src) {
+            Ok(f) => serde_json::from_reader::<File, MockPaypalResponses>(f)
+                .map_err(|_e| AppProcessorErrorReason::InvalidConfig)?,
+            Err(_e) => MockPaypalResponses::default(),
+        };
+        Ok(Box::new(Self { _resps }))
+    }
+
+    /// parse recorded body exactly the same way as the response from PayPal
+    fn replay<D>(raw: &JsnVal) -> Result<D, AppProcessorErrorReason>
+    where
+        D: DeserializeOwned + Send + 'static,
+    {
+        let raw = raw.to_string().into_bytes();
+        AppPaypalClient::<Full<Bytes>>::deserialise_body::<D>(raw, 200)
+            .map_err(AppProcessorErrorReason::from)
+    }
+} // end of impl MockProcessorPaypalCtx
+
+#[async_trait]
+impl AbstPaypalContext for MockProcessorPaypalCtx {
+    async fn pay_in_start(
+        &self,
+        req: &PaypalOrderReqDto,
+        charge_buyer: &ChargeBuyerModel,
+    ) -> Result<(AppProcessorPayInResult, Charge3partyModel), AppProcessorErrorReason> {
+        let reference_id = charge_buyer.meta.token().to_string();
+        let _body = CreateOrder::try_from((req, charge_buyer, reference_id.clone()))?;
+        if let Some(raw) = self._resps.create_order.as_ref() {
+            let order = Self::replay::<Order>(raw)?;
+            return Ok(pay_in_accepted(order, charge_buyer, reference_id));
+        }
+        let order_id = "mock-paypal-order-id".to_string();
+        let mthd_detail = PaypalOrderRespDto {
+            id: order_id.clone(),
+            approve_url: Some(format!("{}?token={}", req.return_url, order_id)),
+        };
+        let ctime = *charge_buyer.meta.create_time();
+        let result = AppProcessorPayInResult {
+            charge_id: charge_buyer.meta.token().0.to_vec(),
+            method: PaymentMethodRespDto::Paypal(mthd_detail),
+            state: BuyerPayInState::ProcessorAccepted(ctime),
+            completed: false,
+        };
+        let paypal_m = Charge3partyPaypalModel {
+            order_id,
+            status: PaypalOrderStatusModel::Created,
+            capture_id: None,
+            reference_id,
+            expiry: ctime + Duration::seconds(35),
+        };
+        let mthd_m = Charge3partyModel::Paypal(paypal_m);
+        Ok((result, mthd_m))
+    }
+
+    async fn pay_in_progress(
+        &self,
+        old: &Charge3partyPaypalModel,
+    ) -> Result<Charge3partyPaypalModel, AppProcessorErrorReason> {
+        if let Some(raw) = self._resps.refresh_order.as_ref() {
+            let order = Self::replay::<Order>(raw)?;
+            let mut new_m =
+                Charge3partyPaypalModel::from((order, old.expiry, old.reference_id.clone()));
+            if let (true, Some(raw)) = (new_m.capture_required(), &self._resps.capture_order) {
+                let order = Self::replay::<Order>(raw)?;
+                new_m =
+                    Charge3partyPaypalModel::from((order, old.expiry, old.reference_id.clone()));
+            }
+            return Ok(new_m);
+        }
+        // assume buyer approved the order and the payment is captured
+        let new_m = Charge3partyPaypalModel {
+            order_id: old.order_id.clone(),
+            status: PaypalOrderStatusModel::Completed,
+            capture_id: Some("mock-paypal-capture-id".to_string()),
+            reference_id: old.reference_id.clone(),
+            expiry: old.expiry,
+        };
+        Ok(new_m)
+    }
+
+    async fn onboard_merchant(
+        &self,
+        store_profile: StoreProfileReplicaDto,
+        req: StoreOnboardPaypalReqDto,
+    ) -> Result<AppProcessorMerchantResult, AppProcessorErrorReason> {
+        let t_now = Local::now().to_utc();
+        let action_link = PaypalActionLinkModel {
+            url: req.return_url,
+            expiry: t_now + Duration::minutes(10),
+        };
+        let m = Merchant3partyPaypalModel {
+            tracking_id: format!("{}-mock", store_profile.supervisor_id),
+            merchant_id: None,
+            email: Some("hayley@wo0dberry.org".to_string()),
+            payments_receivable: false,
+            primary_email_confirmed: false,
+            created: t_now,
+            action_link: Some(action_link),
+        };
+        Ok(AppProcessorMerchantResult::from(m))
+    }
+
+    async fn refresh_onboard_status(
+        &self,
+        mut old_m3pty: Merchant3partyPaypalModel,
+        _req3pt: StoreOnboardPaypalReqDto,
+    ) -> Result<AppProcessorMerchantResult, AppProcessorErrorReason> {
+        // assume the onboarding process has completed
+        old_m3pty.merchant_id = Some("MOCKPAYPALMERCHANT".to_string());
+        old_m3pty.payments_receivable = true;
+        old_m3pty.primary_email_confirmed = true;
+        old_m3pty.action_link = None;
+        Ok(AppProcessorMerchantResult::from(old_m3pty))
+    }
+
+    async fn pay_out(
+        &self,
+        p_inner: &PayoutInnerModel,
+        mut p3pty: Payout3partyPaypalModel,
+    ) -> Result<Payout3partyPaypalModel, AppProcessorErrorReason> {
+        p3pty.set_batch_id("mock-paypal-payout-batch-id".to_string());
+        p3pty.set_amount(p_inner.amount_base());
+        Ok(p3pty)
+    }
+
+    async fn refund(
+        &self,
+        rslv_inner: &RefundReqRslvInnerModel,
+        detail3pty: Charge3partyPaypalModel,
+    ) -> Result<Charge3partyPaypalModel, AppProcessorErrorReason> {
+        let _body = CreateRefund::try_from((rslv_inner, &detail3pty))?;
+        if let Some(raw) = self._resps.refund.as_ref() {
+            Self::replay::<RefundResult>(raw)?.validate()?;
+        }
+        Ok(detail3pty)
+    }
+} // end of impl MockProcessorPaypalCtx
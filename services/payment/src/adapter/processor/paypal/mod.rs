@@ -0,0 +1,471 @@
+mod client;
+mod mock;
+mod resources;
+
+use std::boxed::Box;
+use std::result::Result;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use base64::engine::general_purpose::STANDARD as B64_STANDARD;
+use base64::Engine;
+use chrono::{DateTime, Duration, Local, Utc};
+use http_body_util::{Empty, Full};
+use hyper::body::Bytes;
+use hyper::header::{HeaderName, HeaderValue};
+use hyper::Method;
+use serde::Deserialize;
+use tokio_native_tls::{native_tls, TlsConnector as TlsConnectorWrapper};
+
+use ecommerce_common::api::rpc::dto::StoreProfileReplicaDto;
+use ecommerce_common::confidentiality::AbstractConfidentiality;
+use ecommerce_common::logging::{app_log_event, AppLogContext, AppLogLevel};
+
+use self::client::AppPaypalClient;
+pub(super) use self::mock::MockProcessorPaypalCtx;
+use self::resources::{
+    AccessToken, CaptureOrder, CreateAccessToken, CreateOrder, CreatePartnerReferral,
+    CreatePayoutBatch, CreateRefund, MerchantIntegration, MerchantIntegrationRef, Order,
+    PartnerReferral, PayoutBatch, RefundResult,
+};
+use super::{
+    AppProcessorErrorReason, AppProcessorMerchantResult, AppProcessorPayInResult, BaseClientError,
+    BaseClientErrorReason,
+};
+use crate::api::web::dto::{
+    PaymentMethodRespDto, PaypalOrderReqDto, PaypalOrderRespDto, StoreOnboardPaypalReqDto,
+    StoreOnboardRespDto,
+};
+use crate::model::{
+    BuyerPayInState, Charge3partyModel, Charge3partyPaypalModel, ChargeBuyerModel,
+    Merchant3partyModel, Merchant3partyPaypalModel, Payout3partyPaypalModel, PayoutInnerModel,
+    PaypalActionLinkModel, RefundReqRslvInnerModel,
+};
+
+const HEADER_NAME_REQUEST_ID: &str = "PayPal-Request-Id";
+// PayPal keeps created order for 3 hours until buyer approves it
+const ORDER_APPROVAL_MIN_SECONDS: i64 = 10800;
+const ACTION_LINK_EXPIRY_MIN_DAYS: i64 = 2;
+// renew access token slightly before it expires
+const ACCESS_TOKEN_EXPIRY_MARGIN_SECONDS: i64 = 60;
+
+#[async_trait]
+pub(super) trait AbstPaypalContext: Send + Sync {
+    async fn pay_in_start(
+        &self,
+        req: &PaypalOrderReqDto,
+        meta: &ChargeBuyerModel,
+    ) -> Result<(AppProcessorPayInResult, Charge3partyModel), AppProcessorErrorReason>;
+
+    async fn pay_in_progress(
+        &self,
+        detail3pty: &Charge3partyPaypalModel,
+    ) -> Result<Charge3partyPaypalModel, AppProcessorErrorReason>;
+
+    async fn onboard_merchant(
+        &self,
+        store_profile: StoreProfileReplicaDto,
+        req: StoreOnboardPaypalReqDto,
+    ) -> Result<AppProcessorMerchantResult, AppProcessorErrorReason>;
+
+    async fn refresh_onboard_status(
+        &self,
+        m3pty: Merchant3partyPaypalModel,
+        req3pt: StoreOnboardPaypalReqDto,
+    ) -> Result<AppProcessorMerchantResult, AppProcessorErrorReason>;
+
+    async fn pay_out(
+        &self,
+        p_inner: &PayoutInnerModel,
+        p3pty: Payout3partyPaypalModel,
+    ) -> Result<Payout3partyPaypalModel, AppProcessorErrorReason>;
+
+    async fn refund(
+        &self,
+        rslv_inner: &RefundReqRslvInnerModel,
+        detail3pty: Charge3partyPaypalModel,
+    ) -> Result<Charge3partyPaypalModel, AppProcessorErrorReason>;
+} // end of trait AbstPaypalContext
+
+#[derive(Deserialize)]
+struct PaypalCredential {
+    client_id: String,
+    client_secret: String,
+    // merchant ID of this platform, applied in partner referral
+    partner_id: String,
+}
+
+pub(super) struct AppProcessorPaypalCtx {
+    host: String,
+    port: u16,
+    secure_connector: TlsConnectorWrapper,
+    credential: PaypalCredential,
+    // cached access token and its expiry time
+    access_token: Mutex<Option<(String, DateTime<Utc>)>>,
+    logctx: Arc<AppLogContext>,
+}
+
+impl AppProcessorPaypalCtx {
+    pub(super) fn try_build(
+        host: &str,
+        port: u16,
+        confidential_path: &str,
+        cfdntl: Arc<Box<dyn AbstractConfidentiality>>,
+        logctx: Arc<AppLogContext>,
+    ) -> Result<Box<dyn AbstPaypalContext>, AppProcessorErrorReason> {
+        let serial = cfdntl
+            .try_get_payload(confidential_path)
+            .map_err(|_e| AppProcessorErrorReason::MissingCredential)?;
+        let credential = serde_json::from_str::<PaypalCredential>(serial.as_str())
+            .map_err(|_e| AppProcessorErrorReason::CredentialCorrupted)?;
+        let secure_connector = {
+            let mut builder = native_tls::TlsConnector::builder();
+            builder.min_protocol_version(Some(native_tls::Protocol::Tlsv12));
+            let c = builder
+                .build()
+                .map_err(|e| BaseClientError { reason: e.into() })
+                .map_err(AppProcessorErrorReason::from)?;
+            c.into()
+        };
+        let m = Self {
+            host: host.to_string(),
+            port,
+            secure_connector,
+            credential,
+            access_token: Mutex::new(None),
+            logctx,
+        };
+        Ok(Box::new(m))
+    } // end of fn try-build
+
+    async fn refresh_access_token(&self) -> Result<String, AppProcessorErrorReason> {
+        let t_now = Local::now().to_utc();
+        if let Some((token, expiry)) = self.access_token.lock().unwrap().as_ref() {
+            if *expiry > t_now {
+                return Ok(token.clone());
+            }
+        }
+        let auth_value = {
+            let c = &self.credential;
+            let raw = format!("{}:{}", c.client_id, c.client_secret);
+            format!("Basic {}", B64_STANDARD.encode(raw))
+        };
+        let mut _client = AppPaypalClient::<Full<Bytes>>::try_build(
+            self.logctx.clone(),
+            &self.secure_connector,
+            self.host.clone(),
+            self.port,
+            auth_value,
+        )
+        .await
+        .map_err(AppProcessorErrorReason::from)?;
+        let body_obj = CreateAccessToken {
+            grant_type: "client_credentials",
+        };
+        let resp = _client
+            .execute_form::<AccessToken, CreateAccessToken>("/v1/oauth2/token", &body_obj)
+            .await
+            .map_err(|e| self.map_log_err("access-token", e))?;
+        let lifetime = resp.expires_in - ACCESS_TOKEN_EXPIRY_MARGIN_SECONDS;
+        let expiry = t_now + Duration::seconds(lifetime.max(0));
+        let mut guard = self.access_token.lock().unwrap();
+        *guard = Some((resp.access_token.clone(), expiry));
+        Ok(resp.access_token)
+    } // end of fn refresh_access_token
+
+    async fn init_conn_fullbyte(
+        &self,
+    ) -> Result<AppPaypalClient<Full<Bytes>>, AppProcessorErrorReason> {
+        let token = self.refresh_access_token().await?;
+        AppPaypalClient::<Full<Bytes>>::try_build(
+            self.logctx.clone(),
+            &self.secure_connector,
+            self.host.clone(),
+            self.port,
+            format!("Bearer {token}"),
+        )
+        .await
+        .map_err(AppProcessorErrorReason::from)
+    }
+
+    async fn init_conn_empty(
+        &self,
+    ) -> Result<AppPaypalClient<Empty<Bytes>>, AppProcessorErrorReason> {
+        let token = self.refresh_access_token().await?;
+        AppPaypalClient::<Empty<Bytes>>::try_build(
+            self.logctx.clone(),
+            &self.secure_connector,
+            self.host.clone(),
+            self.port,
+            format!("Bearer {token}"),
+        )
+        .await
+        .map_err(AppProcessorErrorReason::from)
+    }
+
+    fn map_log_err(&self, label: &str, e: BaseClientError) -> AppProcessorErrorReason {
+        let logger = &self.logctx;
+        app_log_event!(logger, AppLogLevel::ERROR, "{label}: {:?}", &e);
+        AppProcessorErrorReason::from(e)
+    }
+
+    fn request_id_header(value: &str) -> Vec<(HeaderName, HeaderValue)> {
+        vec![(
+            // header-name from-static does not allow uppercase word
+            HeaderName::from_bytes(HEADER_NAME_REQUEST_ID.as_bytes()).unwrap(),
+            HeaderValue::from_str(value).unwrap(),
+        )]
+    }
+
+    async fn create_partner_referral(
+        &self,
+        body_obj: CreatePartnerReferral,
+    ) -> Result<PaypalActionLinkModel, AppProcessorErrorReason> {
+        let mut _client = self.init_conn_fullbyte().await?;
+        let referral = _client
+            .execute_json::<PartnerReferral, CreatePartnerReferral>(
+                "/v2/customer/partner-referrals",
+                Method::POST,
+                &body_obj,
+                Vec::new(),
+            )
+            .await
+            .map_err(|e| self.map_log_err("partner-referral", e))?;
+        let (url, expiry) = referral.action_link(ACTION_LINK_EXPIRY_MIN_DAYS).ok_or(
+            AppProcessorErrorReason::ThirdParty("missing-action-link".to_string()),
+        )?;
+        Ok(PaypalActionLinkModel { url, expiry })
+    }
+
+    /// return `None` if the merchant has not granted permissions to this
+    /// platform yet
+    async fn lookup_merchant_integration(
+        &self,
+        tracking_id: &str,
+    ) -> Result<Option<MerchantIntegration>, AppProcessorErrorReason> {
+        let partner_id = self.credential.partner_id.as_str();
+        let mut _client = self.init_conn_empty().await?;
+        let resource_path = format!(
+            "/v1/customer/partners/{partner_id}/merchant-integrations?tracking_id={tracking_id}"
+        );
+        let result = _client
+            .execute::<MerchantIntegrationRef>(resource_path.as_str(), Method::GET)
+            .await;
+        let merchant_id = match result {
+            Ok(v) => v.merchant_id,
+            Err(BaseClientError {
+                reason: BaseClientErrorReason::DeserialiseFailure(_, 404),
+            }) => return Ok(None),
+            Err(e) => return Err(self.map_log_err("merchant-lookup", e)),
+        };
+        let mut _client = self.init_conn_empty().await?;
+        let resource_path =
+            format!("/v1/customer/partners/{partner_id}/merchant-integrations/{merchant_id}");
+        let integration = _client
+            .execute::<MerchantIntegration>(resource_path.as_str(), Method::GET)
+            .await
+            .map_err(|e| self.map_log_err("merchant-read", e))?;
+        if integration.tracking_id.as_str() == tracking_id {
+            Ok(Some(integration))
+        } else {
+            let msg = format!("tracking-id-mismatch, expect:{tracking_id}");
+            Err(AppProcessorErrorReason::ThirdParty(msg))
+        }
+    } // end of fn lookup_merchant_integration
+} // end of impl AppProcessorPaypalCtx
+
+#[async_trait]
+impl AbstPaypalContext for AppProcessorPaypalCtx {
+    async fn pay_in_start(
+        &self,
+        req: &PaypalOrderReqDto,
+        charge_buyer: &ChargeBuyerModel,
+    ) -> Result<(AppProcessorPayInResult, Charge3partyModel), AppProcessorErrorReason> {
+        let charge_token_serial = charge_buyer.meta.token().to_string();
+        let body_obj = CreateOrder::try_from((req, charge_buyer, charge_token_serial.clone()))?;
+        let mut _client = self.init_conn_fullbyte().await?;
+        let hdrs = Self::request_id_header(charge_token_serial.as_str());
+        let order = _client
+            .execute_json::<Order, CreateOrder>(
+                "/v2/checkout/orders",
+                Method::POST,
+                &body_obj,
+                hdrs,
+            )
+            .await
+            .map_err(|e| self.map_log_err("new-order", e))?;
+        Ok(pay_in_accepted(order, charge_buyer, charge_token_serial))
+    } // end of fn pay_in_start
+
+    async fn pay_in_progress(
+        &self,
+        old: &Charge3partyPaypalModel,
+    ) -> Result<Charge3partyPaypalModel, AppProcessorErrorReason> {
+        let mut _client = self.init_conn_empty().await?;
+        let resource_path = format!("/v2/checkout/orders/{}", old.order_id);
+        let order = _client
+            .execute::<Order>(resource_path.as_str(), Method::GET)
+            .await
+            .map_err(|e| self.map_log_err("refresh-order", e))?;
+        let mut new_m =
+            Charge3partyPaypalModel::from((order, old.expiry, old.reference_id.clone()));
+        if new_m.capture_required() {
+            // buyer approved the order, capture the payment immediately
+            let mut _client = self.init_conn_fullbyte().await?;
+            let resource_path = format!("/v2/checkout/orders/{}/capture", old.order_id);
+            let req_id = format!("{}-capture", old.reference_id);
+            let hdrs = Self::request_id_header(req_id.as_str());
+            let order = _client
+                .execute_json::<Order, CaptureOrder>(
+                    resource_path.as_str(),
+                    Method::POST,
+                    &CaptureOrder {},
+                    hdrs,
+                )
+                .await
+                .map_err(|e| self.map_log_err("capture-order", e))?;
+            new_m = Charge3partyPaypalModel::from((order, old.expiry, old.reference_id.clone()));
+        }
+        Ok(new_m)
+    } // end of fn pay_in_progress
+
+    async fn onboard_merchant(
+        &self,
+        store_profile: StoreProfileReplicaDto,
+        req: StoreOnboardPaypalReqDto,
+    ) -> Result<AppProcessorMerchantResult, AppProcessorErrorReason> {
+        let body_obj = CreatePartnerReferral::try_from((store_profile, req))?;
+        let tracking_id = body_obj.tracking_id.clone();
+        let email = Some(body_obj.email.clone());
+        let action_link = self.create_partner_referral(body_obj).await?;
+        let m = Merchant3partyPaypalModel {
+            tracking_id,
+            merchant_id: None,
+            email,
+            payments_receivable: false,
+            primary_email_confirmed: false,
+            created: Local::now().to_utc(),
+            action_link: Some(action_link),
+        };
+        Ok(AppProcessorMerchantResult::from(m))
+    } // end of fn onboard_merchant
+
+    async fn refresh_onboard_status(
+        &self,
+        mut m3pty: Merchant3partyPaypalModel,
+        req: StoreOnboardPaypalReqDto,
+    ) -> Result<AppProcessorMerchantResult, AppProcessorErrorReason> {
+        if let Some(v) = self
+            .lookup_merchant_integration(m3pty.tracking_id.as_str())
+            .await?
+        {
+            m3pty.merchant_id = Some(v.merchant_id);
+            m3pty.payments_receivable = v.payments_receivable;
+            m3pty.primary_email_confirmed = v.primary_email_confirmed;
+            if v.primary_email.is_some() {
+                m3pty.email = v.primary_email;
+            }
+        }
+        if m3pty.can_perform_payout() {
+            m3pty.action_link = None;
+        } else if m3pty.renew_link_required() {
+            let email = m3pty.email.clone().unwrap_or_default();
+            let arg = (m3pty.tracking_id.clone(), email, req);
+            let body_obj = CreatePartnerReferral::from(arg);
+            let action_link = self.create_partner_referral(body_obj).await?;
+            m3pty.action_link = Some(action_link);
+        }
+        Ok(AppProcessorMerchantResult::from(m3pty))
+    } // end of fn refresh_onboard_status
+
+    async fn pay_out(
+        &self,
+        p_inner: &PayoutInnerModel,
+        mut p3pty: Payout3partyPaypalModel,
+    ) -> Result<Payout3partyPaypalModel, AppProcessorErrorReason> {
+        let req_body = CreatePayoutBatch::try_from((p_inner, &p3pty))?;
+        let mut _client = self.init_conn_fullbyte().await?;
+        let batch = _client
+            .execute_json::<PayoutBatch, CreatePayoutBatch>(
+                "/v1/payments/payouts",
+                Method::POST,
+                &req_body,
+                Vec::new(),
+            )
+            .await
+            .map_err(|e| self.map_log_err("pay-out", e))?;
+        batch.validate()?;
+        p3pty.set_amount(req_body.amount_decimal()?);
+        p3pty.set_batch_id(batch.batch_header.payout_batch_id);
+        Ok(p3pty)
+    } // end of fn pay_out
+
+    async fn refund(
+        &self,
+        rslv_inner: &RefundReqRslvInnerModel,
+        detail3pty: Charge3partyPaypalModel,
+    ) -> Result<Charge3partyPaypalModel, AppProcessorErrorReason> {
+        let req_body = CreateRefund::try_from((rslv_inner, &detail3pty))?;
+        let request_id = rslv_inner
+            .processor_request_id(detail3pty.reference_id.as_str())
+            .map_err(|_e| AppProcessorErrorReason::MissingMerchant)?;
+        // capture ID presence has been checked when building the request body
+        let capture_id = detail3pty.capture_id.as_deref().unwrap_or_default();
+        let resource_path = format!("/v2/payments/captures/{capture_id}/refund");
+        let mut _client = self.init_conn_fullbyte().await?;
+        let hdrs = Self::request_id_header(request_id.as_str());
+        let rfd_obj = _client
+            .execute_json::<RefundResult, CreateRefund>(
+                resource_path.as_str(),
+                Method::POST,
+                &req_body,
+                hdrs,
+            )
+            .await
+            .map_err(|e| self.map_log_err("refund", e))?;
+        rfd_obj.validate()?;
+        Ok(detail3pty)
+    } // end of fn refund
+} // end of impl AppProcessorPaypalCtx
+
+/// convert the order newly created in PayPal to pay-in result
+fn pay_in_accepted(
+    order: Order,
+    charge_buyer: &ChargeBuyerModel,
+    reference_id: String,
+) -> (AppProcessorPayInResult, Charge3partyModel) {
+    let time_now = Utc::now();
+    let result = AppProcessorPayInResult {
+        charge_id: charge_buyer.meta.token().0.to_vec(),
+        method: PaymentMethodRespDto::Paypal(PaypalOrderRespDto {
+            id: order.id.clone(),
+            approve_url: order.approve_link(),
+        }),
+        state: BuyerPayInState::ProcessorAccepted(time_now),
+        completed: false,
+    };
+    let time_end = time_now + Duration::seconds(ORDER_APPROVAL_MIN_SECONDS);
+    let mthd_3pty = Charge3partyPaypalModel::from((order, time_end, reference_id));
+    (result, Charge3partyModel::Paypal(mthd_3pty))
+}
+
+impl<'a> From<&'a Merchant3partyPaypalModel> for StoreOnboardRespDto {
+    fn from(value: &'a Merchant3partyPaypalModel) -> Self {
+        let alink = value.action_link.as_ref();
+        Self::Paypal {
+            merchant_id: value.merchant_id.clone(),
+            payments_receivable: value.payments_receivable,
+            email_confirmed: value.primary_email_confirmed,
+            url: alink.map(|v| v.url.clone()),
+            expiry: alink.map(|v| v.expiry),
+        }
+    }
+}
+
+impl From<Merchant3partyPaypalModel> for AppProcessorMerchantResult {
+    fn from(value: Merchant3partyPaypalModel) -> Self {
+        let d = StoreOnboardRespDto::from(&value);
+        let m = Merchant3partyModel::Paypal(value);
+        Self { dto: d, model: m }
+    }
+}
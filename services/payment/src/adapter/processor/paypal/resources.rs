@@ -0,0 +1,481 @@
+use std::result::Result;
+
+use chrono::{DateTime, Duration, Local, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use ecommerce_common::api::dto::CurrencyDto;
+use ecommerce_common::api::rpc::dto::StoreProfileReplicaDto;
+
+use super::super::AppProcessorErrorReason;
+use crate::api::web::dto::{PaypalOrderReqDto, StoreOnboardPaypalReqDto};
+use crate::model::{
+    Charge3partyPaypalModel, ChargeBuyerModel, ChargeLineBuyerModel, Payout3partyPaypalModel,
+    PayoutInnerModel, PaypalOrderStatusModel, RefundReqRslvInnerModel,
+};
+
+#[derive(Serialize)]
+pub(super) struct CreateAccessToken {
+    pub grant_type: &'static str,
+}
+
+#[derive(Deserialize)]
+pub(super) struct AccessToken {
+    pub access_token: String,
+    pub expires_in: i64, // in seconds
+}
+
+#[derive(Serialize, Deserialize)]
+pub(super) struct Money {
+    pub currency_code: CurrencyDto,
+    pub value: String,
+}
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Serialize)]
+pub(super) enum OrderIntent {
+    CAPTURE,
+}
+
+#[derive(Serialize)]
+pub(super) struct AmountBreakdown {
+    pub item_total: Money,
+}
+#[derive(Serialize)]
+pub(super) struct AmountWithBreakdown {
+    pub currency_code: CurrencyDto,
+    pub value: String,
+    pub breakdown: AmountBreakdown,
+}
+
+#[derive(Serialize)]
+pub(super) struct OrderItem {
+    pub name: String,
+    pub sku: String,
+    pub quantity: String,
+    pub unit_amount: Money,
+}
+
+#[derive(Serialize)]
+pub(super) struct PurchaseUnitRequest {
+    pub reference_id: String,
+    pub custom_id: String,
+    pub amount: AmountWithBreakdown,
+    pub items: Vec<OrderItem>,
+}
+
+#[derive(Serialize)]
+pub(super) struct OrderApplicationContext {
+    pub return_url: String,
+    pub cancel_url: String,
+    pub shipping_preference: &'static str,
+    pub user_action: &'static str,
+}
+
+#[derive(Serialize)]
+pub(super) struct CreateOrder {
+    pub intent: OrderIntent,
+    pub purchase_units: Vec<PurchaseUnitRequest>,
+    pub application_context: OrderApplicationContext,
+}
+
+#[derive(Serialize)]
+pub(super) struct CaptureOrder {}
+
+#[derive(Deserialize)]
+pub(super) struct LinkDescription {
+    pub href: String,
+    pub rel: String,
+}
+
+#[derive(Deserialize)]
+pub(super) struct Capture {
+    pub id: String,
+    pub status: String,
+}
+#[derive(Deserialize)]
+pub(super) struct PaymentCollection {
+    #[serde(default)]
+    pub captures: Vec<Capture>,
+}
+#[derive(Deserialize)]
+pub(super) struct PurchaseUnit {
+    pub payments: Option<PaymentCollection>,
+}
+
+#[derive(Deserialize)]
+pub(super) struct Order {
+    pub id: String,
+    pub status: PaypalOrderStatusModel,
+    #[serde(default)]
+    pub links: Vec<LinkDescription>,
+    #[serde(default)]
+    pub purchase_units: Vec<PurchaseUnit>,
+}
+
+#[derive(Serialize)]
+pub(super) struct ThirdPartyDetails {
+    pub features: [&'static str; 2],
+}
+#[derive(Serialize)]
+pub(super) struct RestApiIntegration {
+    pub integration_method: &'static str,
+    pub integration_type: &'static str,
+    pub third_party_details: ThirdPartyDetails,
+}
+#[derive(Serialize)]
+pub(super) struct ApiIntegrationPreference {
+    pub rest_api_integration: RestApiIntegration,
+}
+#[derive(Serialize)]
+pub(super) struct ReferralOperation {
+    pub operation: &'static str,
+    pub api_integration_preference: ApiIntegrationPreference,
+}
+#[derive(Serialize)]
+pub(super) struct LegalConsent {
+    #[serde(rename = "type")]
+    pub type_: &'static str,
+    pub granted: bool,
+}
+#[derive(Serialize)]
+pub(super) struct PartnerConfigOverride {
+    pub return_url: String,
+}
+
+#[derive(Serialize)]
+pub(super) struct CreatePartnerReferral {
+    pub tracking_id: String,
+    pub email: String,
+    pub operations: Vec<ReferralOperation>,
+    pub products: [&'static str; 1],
+    pub legal_consents: Vec<LegalConsent>,
+    pub partner_config_override: PartnerConfigOverride,
+}
+
+#[derive(Deserialize)]
+pub(super) struct PartnerReferral {
+    pub links: Vec<LinkDescription>,
+}
+
+#[derive(Deserialize)]
+pub(super) struct MerchantIntegrationRef {
+    pub merchant_id: String,
+}
+
+#[derive(Deserialize)]
+pub(super) struct MerchantIntegration {
+    pub merchant_id: String,
+    pub tracking_id: String,
+    pub payments_receivable: bool,
+    pub primary_email_confirmed: bool,
+    pub primary_email: Option<String>,
+}
+
+#[derive(Serialize)]
+pub(super) struct SenderBatchHeader {
+    pub sender_batch_id: String,
+    pub recipient_type: &'static str,
+}
+#[derive(Serialize)]
+pub(super) struct PayoutItem {
+    pub receiver: String,
+    pub amount: Money,
+    pub sender_item_id: String,
+}
+#[derive(Serialize)]
+pub(super) struct CreatePayoutBatch {
+    pub sender_batch_header: SenderBatchHeader,
+    pub items: Vec<PayoutItem>,
+}
+
+#[derive(Deserialize)]
+pub(super) struct PayoutBatchHeader {
+    pub payout_batch_id: String,
+    pub batch_status: String,
+}
+#[derive(Deserialize)]
+pub(super) struct PayoutBatch {
+    pub batch_header: PayoutBatchHeader,
+}
+
+#[derive(Serialize)]
+pub(super) struct CreateRefund {
+    pub amount: Money,
+}
+
+#[derive(Deserialize)]
+pub(super) struct RefundResult {
+    pub id: String,
+    pub status: String,
+}
+
+impl From<(CurrencyDto, &ChargeLineBuyerModel)> for OrderItem {
+    fn from(value: (CurrencyDto, &ChargeLineBuyerModel)) -> Self {
+        let (currency_code, cline) = value;
+        let (store_id, product_id, attr_seq) = cline.id();
+        let amt = cline.amount_orig();
+        let unit_amount = Money {
+            value: Charge3partyPaypalModel::amount_represent(amt.unit, currency_code.clone()),
+            currency_code,
+        };
+        Self {
+            // TODO, load product name
+            name: format!("{store_id}-{product_id}-{attr_seq}"),
+            sku: format!("{product_id}-{attr_seq}"),
+            quantity: amt.qty.to_string(),
+            unit_amount,
+        }
+    }
+}
+
+impl<'a, 'b> TryFrom<(&'a PaypalOrderReqDto, &'b ChargeBuyerModel, String)> for CreateOrder {
+    type Error = AppProcessorErrorReason;
+    fn try_from(
+        value: (&'a PaypalOrderReqDto, &'b ChargeBuyerModel, String),
+    ) -> Result<Self, Self::Error> {
+        let (req, charge_buyer, reference_id) = value;
+        let currency =
+            charge_buyer
+                .get_buyer_currency()
+                .ok_or(AppProcessorErrorReason::MissingCurrency(
+                    charge_buyer.meta.owner(),
+                ))?;
+        let label = currency.label;
+        let total = charge_buyer
+            .lines
+            .iter()
+            .try_fold(Decimal::ZERO, |acc, v| {
+                acc.checked_add(v.amount_orig().total)
+            })
+            .ok_or(AppProcessorErrorReason::AmountOverflow(
+                "order-total".to_string(),
+            ))?;
+        let total = Charge3partyPaypalModel::amount_represent(total, label.clone());
+        let items = charge_buyer
+            .lines
+            .iter()
+            .map(|v| OrderItem::from((label.clone(), v)))
+            .collect::<Vec<_>>();
+        let amount = AmountWithBreakdown {
+            currency_code: label.clone(),
+            value: total.clone(),
+            breakdown: AmountBreakdown {
+                item_total: Money {
+                    currency_code: label,
+                    value: total,
+                },
+            },
+        };
+        let purchase_unit = PurchaseUnitRequest {
+            custom_id: format!("{}-{}", charge_buyer.meta.owner(), charge_buyer.meta.oid()),
+            reference_id,
+            amount,
+            items,
+        };
+        Ok(Self {
+            intent: OrderIntent::CAPTURE,
+            purchase_units: vec![purchase_unit],
+            application_context: OrderApplicationContext {
+                return_url: req.return_url.clone(),
+                cancel_url: req.cancel_url.clone(),
+                shipping_preference: "NO_SHIPPING",
+                user_action: "PAY_NOW",
+            },
+        })
+    } // end of fn try-from
+} // end of impl CreateOrder
+
+impl Order {
+    pub(super) fn approve_link(&self) -> Option<String> {
+        self.links
+            .iter()
+            .find(|v| matches!(v.rel.as_str(), "approve" | "payer-action"))
+            .map(|v| v.href.clone())
+    }
+    fn capture_id(&self) -> Option<String> {
+        self.purchase_units
+            .iter()
+            .filter_map(|u| u.payments.as_ref())
+            .flat_map(|p| p.captures.iter())
+            .find(|c| c.status.as_str() == "COMPLETED")
+            .map(|c| c.id.clone())
+    }
+}
+
+impl From<(Order, DateTime<Utc>, String)> for Charge3partyPaypalModel {
+    fn from(value: (Order, DateTime<Utc>, String)) -> Self {
+        let (order, expiry, reference_id) = value;
+        let capture_id = order.capture_id();
+        Self {
+            order_id: order.id,
+            status: order.status,
+            capture_id,
+            reference_id,
+            expiry,
+        }
+    }
+}
+
+impl TryFrom<(StoreProfileReplicaDto, StoreOnboardPaypalReqDto)> for CreatePartnerReferral {
+    type Error = AppProcessorErrorReason;
+    fn try_from(
+        value: (StoreProfileReplicaDto, StoreOnboardPaypalReqDto),
+    ) -> Result<Self, Self::Error> {
+        let (profile, req) = value;
+        let mut err_detail = Vec::new();
+        if !profile.active {
+            err_detail.push("not-active".to_string());
+        }
+        if profile.label.is_empty() {
+            err_detail.push("label-empty".to_string());
+        }
+        let email = profile
+            .emails
+            .and_then(|mut v| {
+                if v.is_empty() {
+                    None
+                } else {
+                    Some(v.remove(0))
+                }
+            })
+            .map(|v| v.addr);
+        if email.is_none() {
+            err_detail.push("missing-email".to_string());
+        }
+        if !err_detail.is_empty() {
+            return Err(AppProcessorErrorReason::InvalidStoreProfileDto(err_detail));
+        }
+        // tracking ID is required to look up the merchant before it grants
+        // permissions to this platform
+        let t_now = Local::now().to_utc();
+        let tracking_id = format!("{}-{}", profile.supervisor_id, t_now.timestamp_millis());
+        Ok(Self::from((tracking_id, email.unwrap(), req)))
+    } // end of fn try-from
+} // end of impl CreatePartnerReferral
+
+impl From<(String, String, StoreOnboardPaypalReqDto)> for CreatePartnerReferral {
+    fn from(value: (String, String, StoreOnboardPaypalReqDto)) -> Self {
+        let (tracking_id, email, req) = value;
+        let api_integration_preference = ApiIntegrationPreference {
+            rest_api_integration: RestApiIntegration {
+                integration_method: "PAYPAL",
+                integration_type: "THIRD_PARTY",
+                third_party_details: ThirdPartyDetails {
+                    features: ["PAYMENT", "REFUND"],
+                },
+            },
+        };
+        Self {
+            tracking_id,
+            email,
+            operations: vec![ReferralOperation {
+                operation: "API_INTEGRATION",
+                api_integration_preference,
+            }],
+            products: ["EXPRESS_CHECKOUT"],
+            legal_consents: vec![LegalConsent {
+                type_: "SHARE_DATA_CONSENT",
+                granted: true,
+            }],
+            partner_config_override: PartnerConfigOverride {
+                return_url: req.return_url,
+            },
+        }
+    }
+} // end of impl CreatePartnerReferral
+
+impl PartnerReferral {
+    pub(super) fn action_link(&self, lifetime_days: i64) -> Option<(String, DateTime<Utc>)> {
+        let t_exp = Local::now().to_utc() + Duration::days(lifetime_days);
+        self.links
+            .iter()
+            .find(|v| v.rel.as_str() == "action_url")
+            .map(|v| (v.href.clone(), t_exp))
+    }
+}
+
+impl<'a, 'b> TryFrom<(&'a PayoutInnerModel, &'b Payout3partyPaypalModel)> for CreatePayoutBatch {
+    type Error = AppProcessorErrorReason;
+    fn try_from(
+        value: (&'a PayoutInnerModel, &'b Payout3partyPaypalModel),
+    ) -> Result<Self, Self::Error> {
+        let (pm, p3pt) = value;
+        let amt_orig = pm.amount_base();
+        let value = Payout3partyPaypalModel::amount_represent(amt_orig, CurrencyDto::USD);
+        // PayPal rejects any batch whose sender batch ID was used in last 30 days,
+        // the sequence number distinguishes multiple payouts of the same charge
+        let sender_batch_id = format!("{}-{}-{}", p3pt.reference_id(), pm.merchant_id(), pm.seq());
+        Ok(Self {
+            sender_batch_header: SenderBatchHeader {
+                sender_batch_id: sender_batch_id.clone(),
+                recipient_type: "PAYPAL_ID",
+            },
+            items: vec![PayoutItem {
+                receiver: p3pt.payee_id().to_string(),
+                amount: Money {
+                    currency_code: CurrencyDto::USD,
+                    value,
+                },
+                sender_item_id: sender_batch_id,
+            }],
+        })
+    }
+} // end of impl CreatePayoutBatch
+
+impl CreatePayoutBatch {
+    pub(super) fn amount_decimal(&self) -> Result<Decimal, AppProcessorErrorReason> {
+        let v = self.items[0].amount.value.as_str();
+        v.parse::<Decimal>()
+            .map_err(|e| AppProcessorErrorReason::AmountOverflow(e.to_string()))
+    }
+}
+
+impl PayoutBatch {
+    pub(super) fn validate(&self) -> Result<(), AppProcessorErrorReason> {
+        let h = &self.batch_header;
+        let result = if h.payout_batch_id.is_empty() {
+            Err("missing-batch-id".to_string())
+        } else if matches!(h.batch_status.as_str(), "DENIED" | "CANCELED") {
+            Err(format!("batch-status:{}", h.batch_status))
+        } else {
+            Ok(())
+        };
+        result.map_err(AppProcessorErrorReason::ThirdParty)
+    }
+}
+
+impl<'a, 'b> TryFrom<(&'a RefundReqRslvInnerModel, &'b Charge3partyPaypalModel)> for CreateRefund {
+    type Error = AppProcessorErrorReason;
+    fn try_from(
+        value: (&'a RefundReqRslvInnerModel, &'b Charge3partyPaypalModel),
+    ) -> Result<Self, Self::Error> {
+        let (rslv_inner, chrg_3pty) = value;
+        if chrg_3pty.capture_id.is_none() {
+            let msg = "missing-capture-id".to_string();
+            return Err(AppProcessorErrorReason::ThirdParty(msg));
+        }
+        let currency_label = rslv_inner.currency()[0].label.clone();
+        let amt_orig = rslv_inner.total_amount_curr_round();
+        let value = Charge3partyPaypalModel::amount_represent(amt_orig, currency_label.clone());
+        Ok(Self {
+            amount: Money {
+                currency_code: currency_label,
+                value,
+            },
+        })
+    }
+} // end of impl CreateRefund
+
+impl RefundResult {
+    pub(super) fn validate(&self) -> Result<(), AppProcessorErrorReason> {
+        let result = if self.id.is_empty() {
+            Err("missing-refund-id".to_string())
+        } else if !matches!(self.status.as_str(), "COMPLETED" | "PENDING") {
+            Err(format!("refund-status:{}", self.status))
+        } else {
+            Ok(())
+        };
+        result.map_err(AppProcessorErrorReason::ThirdParty)
+    }
+}
@@ -16,11 +16,14 @@ use crate::model::{
     Merchant3partyModel, Merchant3partyStripeModel, Payout3partyStripeModel, PayoutInnerModel,
     RefundReqRslvInnerModel, StripeAccountCapabilityModel, StripeAccountCapableState,
     StripeAccountLinkModel, StripeAccountSettingModel, StripeCheckoutPaymentStatusModel,
-    StripeSessionStatusModel,
+    StripeSessionStatusModel, StripeWebhookEventModel,
 };
 
 use super::super::{AppProcessorErrorReason, AppProcessorMerchantResult, AppProcessorPayInResult};
-use super::AbstStripeContext;
+use super::{webhook, AbstStripeContext};
+
+// endpoint secret applied only in testing environment
+const MOCK_WEBHOOK_SECRET: &str = "whsec_mock_endpoint_secret";
 
 // TODO, conditional compilation for test
 pub(crate) struct MockProcessorStripeCtx;
@@ -64,6 +67,7 @@ impl AbstStripeContext for MockProcessorStripeCtx {
             session_state: StripeSessionStatusModel::open,
             payment_state: StripeCheckoutPaymentStatusModel::unpaid,
             expiry: ctime + Duration::seconds(35),
+            amount_refunded: 0,
         }; // TODO, configuable parameter expiry time
         let mthd_m = Charge3partyModel::Stripe(stripe_m);
         Ok((result, mthd_m))
@@ -80,6 +84,7 @@ impl AbstStripeContext for MockProcessorStripeCtx {
             session_state: StripeSessionStatusModel::complete,
             payment_state: StripeCheckoutPaymentStatusModel::paid,
             expiry: old.expiry,
+            amount_refunded: old.amount_refunded,
         };
         Ok(new_m)
     }
@@ -167,4 +172,14 @@ impl AbstStripeContext for MockProcessorStripeCtx {
     ) -> Result<Charge3partyStripeModel, AppProcessorErrorReason> {
         Ok(detail3pty)
     }
+
+    fn webhook_event(
+        &self,
+        sig_header: &str,
+        payload: &[u8],
+    ) -> Result<StripeWebhookEventModel, AppProcessorErrorReason> {
+        let t_now = Local::now().to_utc();
+        webhook::verify_signature(MOCK_WEBHOOK_SECRET, sig_header, payload, t_now)?;
+        webhook::parse_event(payload)
+    }
 } // end of impl MockProcessorStripeCtx
@@ -1,13 +1,14 @@
 mod client;
 mod mock;
 mod resources;
+mod webhook;
 
 use std::boxed::Box;
 use std::result::Result;
 use std::sync::Arc;
 
 use async_trait::async_trait;
-use chrono::{DateTime, Duration, DurationRound, Local, TimeDelta, Utc};
+use chrono::{DateTime, Duration, Local, Utc};
 use http_body_util::{Empty, Full};
 use hyper::body::Bytes;
 use hyper::header::{HeaderName, HeaderValue};
@@ -37,12 +38,15 @@ use crate::model::{
     BuyerPayInState, Charge3partyModel, Charge3partyStripeModel, ChargeBuyerModel,
     Merchant3partyModel, Merchant3partyStripeModel, Payout3partyStripeModel, PayoutInnerModel,
     RefundReqRslvInnerModel, StripeAccountLinkModel, StripeAccountSettingModel,
+    StripeWebhookEventModel,
 };
 
 const HEADER_NAME_IDEMPOTENCY: &str = "Idempotency-Key";
 const CHECKOUT_SESSION_MIN_SECONDS: i64 = 1800;
 const ACCOUNT_LINK_EXPIRY_MIN_DAYS: i64 = 2;
-const REFUND_INTERVAL_MIN_SECONDS: i64 = 75;
+// the endpoint secret for webhook is stored next to the API key in
+// confidential storage, with this suffix appended to the same path
+const WEBHOOK_SECRET_PATH_SUFFIX: &str = "Webhook";
 
 #[derive(serde::Serialize)]
 struct InnerEmptyBody;
@@ -83,6 +87,12 @@ pub(super) trait AbstStripeContext: Send + Sync {
         rslv_inner: &RefundReqRslvInnerModel,
         detail3pty: Charge3partyStripeModel,
     ) -> Result<Charge3partyStripeModel, AppProcessorErrorReason>;
+
+    fn webhook_event(
+        &self,
+        sig_header: &str,
+        payload: &[u8],
+    ) -> Result<StripeWebhookEventModel, AppProcessorErrorReason>;
 } // end of trait AbstStripeContext
 
 pub(super) struct AppProcessorStripeCtx {
@@ -90,6 +100,7 @@ pub(super) struct AppProcessorStripeCtx {
     port: u16,
     secure_connector: TlsConnectorWrapper,
     api_key: String,
+    webhook_secret: Option<String>,
     logctx: Arc<AppLogContext>,
 }
 
@@ -106,6 +117,19 @@ impl AppProcessorStripeCtx {
             .map_err(|_e| AppProcessorErrorReason::MissingCredential)?;
         let api_key = serde_json::from_str::<String>(serial.as_str())
             .map_err(|_e| AppProcessorErrorReason::CredentialCorrupted)?;
+        // webhook is optional, the application can still work by actively
+        // polling the state of charges and connected accounts
+        let webhook_secret = {
+            let path = format!("{confidential_path}{WEBHOOK_SECRET_PATH_SUFFIX}");
+            match cfdntl.try_get_payload(path.as_str()) {
+                Ok(serial) => {
+                    let v = serde_json::from_str::<String>(serial.as_str())
+                        .map_err(|_e| AppProcessorErrorReason::CredentialCorrupted)?;
+                    Some(v)
+                }
+                Err(_e) => None,
+            }
+        };
         let secure_connector = {
             let mut builder = native_tls::TlsConnector::builder();
             builder.min_protocol_version(Some(native_tls::Protocol::Tlsv12));
@@ -120,6 +144,7 @@ impl AppProcessorStripeCtx {
             port,
             secure_connector,
             api_key,
+            webhook_secret,
             logctx,
         };
         Ok(Box::new(m))
@@ -144,6 +169,12 @@ impl AppProcessorStripeCtx {
         app_log_event!(logger, AppLogLevel::ERROR, "{label}: {:?}", &e);
         AppProcessorErrorReason::from(e)
     }
+
+    fn map_log_webhook_err(&self, e: AppProcessorErrorReason) -> AppProcessorErrorReason {
+        let logger = &self.logctx;
+        app_log_event!(logger, AppLogLevel::WARNING, "webhook: {:?}", &e);
+        e
+    }
 } // end of impl AppProcessorStripeCtx
 
 #[async_trait]
@@ -263,7 +294,10 @@ impl AbstStripeContext for AppProcessorStripeCtx {
             .await
             .map_err(|e| self.map_log_err("refresh-sess", e))?;
         let arg = (new_session, old.expiry, old.transfer_group.clone());
-        Ok(Charge3partyStripeModel::from(arg))
+        let mut new_m = Charge3partyStripeModel::from(arg);
+        // checkout session does not report refund
+        new_m.amount_refunded = old.amount_refunded;
+        Ok(new_m)
     }
 
     async fn onboard_merchant(
@@ -346,7 +380,12 @@ impl AbstStripeContext for AppProcessorStripeCtx {
     ) -> Result<Payout3partyStripeModel, AppProcessorErrorReason> {
         let mut _client = self.init_conn_fullbyte().await?;
         let req_body = CreateTransfer::try_from((p_inner, &p3pty))?;
-        let idempotency_key = format!("{}-{}", p3pty.transfer_group(), p_inner.merchant_id());
+        let idempotency_key = format!(
+            "{}-{}-{}",
+            p3pty.transfer_group(),
+            p_inner.merchant_id(),
+            p_inner.seq()
+        );
         let hdrs = vec![(
             HeaderName::from_bytes(HEADER_NAME_IDEMPOTENCY.as_bytes()).unwrap(),
             HeaderValue::from_str(idempotency_key.as_str()).unwrap(),
@@ -394,22 +433,9 @@ impl AbstStripeContext for AppProcessorStripeCtx {
         rslv_inner: &RefundReqRslvInnerModel,
         detail3pty: Charge3partyStripeModel,
     ) -> Result<Charge3partyStripeModel, AppProcessorErrorReason> {
-        let merchant_id = rslv_inner
-            .merchant_id()
+        let idempotency_key = rslv_inner
+            .processor_request_id(detail3pty.transfer_group.as_str())
             .map_err(|_e| AppProcessorErrorReason::MissingMerchant)?;
-        let idempotency_key = {
-            let t_now = Local::now().to_utc();
-            let digits = TimeDelta::seconds(REFUND_INTERVAL_MIN_SECONDS);
-            let req_time = t_now.duration_trunc(digits).map_err(|e| {
-                AppProcessorErrorReason::CorruptedTimeStamp(e.to_string(), t_now.timestamp())
-            })?;
-            format!(
-                "{}-{}-{}",
-                detail3pty.transfer_group,
-                merchant_id,
-                req_time.to_rfc3339()
-            )
-        }; // TODO, improve the idempotency key layout
         let mut _client = self.init_conn_fullbyte().await?;
         let req_body = CreateRefund::try_from((rslv_inner, &detail3pty))?;
         let hdrs = vec![(
@@ -425,6 +451,21 @@ impl AbstStripeContext for AppProcessorStripeCtx {
         // payment application ?
         Ok(detail3pty)
     } // end of fn refund
+
+    fn webhook_event(
+        &self,
+        sig_header: &str,
+        payload: &[u8],
+    ) -> Result<StripeWebhookEventModel, AppProcessorErrorReason> {
+        let secret = self
+            .webhook_secret
+            .as_ref()
+            .ok_or(AppProcessorErrorReason::MissingCredential)?;
+        let t_now = Local::now().to_utc();
+        webhook::verify_signature(secret.as_str(), sig_header, payload, t_now)
+            .map_err(|e| self.map_log_webhook_err(e))?;
+        webhook::parse_event(payload).map_err(|e| self.map_log_webhook_err(e))
+    }
 } // end of impl AppProcessorStripeCtx
 
 impl From<(CheckoutSession, DateTime<Utc>, String)> for Charge3partyStripeModel {
@@ -434,9 +475,10 @@ impl From<(CheckoutSession, DateTime<Utc>, String)> for Charge3partyStripeModel
             checkout_session_id: session.id,
             session_state: session.status,
             payment_state: session.payment_status,
-            payment_intent_id: session.payment_intent,
+            payment_intent_id: session.payment_intent.unwrap_or_default(),
             transfer_group,
             expiry: DateTime::from_timestamp(session.expires_at, 0).unwrap_or(time_end),
+            amount_refunded: 0,
         }
     }
 }
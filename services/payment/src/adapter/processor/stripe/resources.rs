@@ -21,7 +21,9 @@ pub(super) struct CheckoutSession {
     pub url: Option<String>,
     pub status: StripeSessionStatusModel,
     pub payment_status: StripeCheckoutPaymentStatusModel,
-    pub payment_intent: String,
+    // could be null if the session expires before customer attempts to pay
+    pub payment_intent: Option<String>,
+    pub client_reference_id: Option<String>,
     pub expires_at: i64,
     // TODO, record more fields for payout at later time
 }
@@ -221,6 +223,30 @@ pub(super) struct Transfer {
     pub destination: String,
     pub amount: i64,
     pub transfer_group: String,
+    #[serde(default)]
+    pub reversed: bool,
+}
+
+#[derive(Deserialize)]
+pub(super) struct Charge {
+    pub payment_intent: Option<String>,
+    pub transfer_group: Option<String>,
+    pub amount_refunded: i64,
+}
+
+#[derive(Deserialize)]
+pub(super) struct WebhookEventData {
+    // the resource object varies with the event type
+    pub object: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+pub(super) struct WebhookEvent {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub created: i64, // UNIX timestamp
+    pub data: WebhookEventData,
 }
 
 #[allow(non_camel_case_types)]
@@ -0,0 +1,137 @@
+use std::result::Result;
+
+use chrono::{DateTime, Utc};
+use ring::hmac;
+
+use ecommerce_common::util::hex_to_octet;
+
+use super::resources::{Charge, CheckoutSession, ConnectAccount, Transfer, WebhookEvent};
+use super::AppProcessorErrorReason;
+use crate::model::{Merchant3partyStripeModel, StripeWebhookEventDetail, StripeWebhookEventModel};
+
+// maximum difference between the timestamp in signature header and current
+// time, which is the same as default value in official Stripe SDKs
+const SIGNATURE_TOLERANCE_SECONDS: i64 = 300;
+
+/// verify the header `Stripe-Signature` which looks like
+/// `t=<timestamp>,v1=<signature>,v1=<signature>,v0=<signature>`, each of
+/// the `v1` signature is HMAC-SHA256 of the string `<timestamp>.<payload>`
+/// with the endpoint secret, only the `v1` scheme is accepted.
+pub(super) fn verify_signature(
+    secret: &str,
+    sig_header: &str,
+    payload: &[u8],
+    t_now: DateTime<Utc>,
+) -> Result<(), AppProcessorErrorReason> {
+    let mut timestamp = None;
+    let mut signatures = Vec::new();
+    for (k, v) in sig_header.split(',').filter_map(|kv| kv.split_once('=')) {
+        match k.trim() {
+            "t" => {
+                timestamp = v.trim().parse::<i64>().ok();
+            }
+            "v1" => signatures.push(v.trim()),
+            _others => {}
+        }
+    }
+    let timestamp = timestamp.ok_or(AppProcessorErrorReason::InvalidWebhook(
+        "sig-missing-timestamp".to_string(),
+    ))?;
+    if (t_now.timestamp() - timestamp).abs() > SIGNATURE_TOLERANCE_SECONDS {
+        let msg = format!("sig-timestamp-expired:{timestamp}");
+        return Err(AppProcessorErrorReason::InvalidWebhook(msg));
+    }
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    let mut signed = format!("{timestamp}.").into_bytes();
+    signed.extend_from_slice(payload);
+    let matched = signatures
+        .into_iter()
+        .filter_map(|s| hex_to_octet(s).ok())
+        .any(|tag| hmac::verify(&key, &signed, &tag).is_ok());
+    if matched {
+        Ok(())
+    } else {
+        Err(AppProcessorErrorReason::InvalidWebhook(
+            "sig-mismatch".to_string(),
+        ))
+    }
+} // end of fn verify_signature
+
+pub(super) fn parse_event(
+    payload: &[u8],
+) -> Result<StripeWebhookEventModel, AppProcessorErrorReason> {
+    let evt = serde_json::from_slice::<WebhookEvent>(payload)
+        .map_err(|e| AppProcessorErrorReason::InvalidWebhook(e.to_string()))?;
+    StripeWebhookEventModel::try_from(evt)
+}
+
+fn deserialize_object<T: serde::de::DeserializeOwned>(
+    evt_type: &str,
+    obj: serde_json::Value,
+) -> Result<T, AppProcessorErrorReason> {
+    serde_json::from_value::<T>(obj).map_err(|e| {
+        let msg = format!("type:{evt_type}, detail:{e}");
+        AppProcessorErrorReason::InvalidWebhook(msg)
+    })
+}
+
+impl TryFrom<WebhookEvent> for StripeWebhookEventModel {
+    type Error = AppProcessorErrorReason;
+    fn try_from(value: WebhookEvent) -> Result<Self, Self::Error> {
+        let WebhookEvent {
+            id,
+            type_,
+            created: created_ts,
+            data,
+        } = value;
+        let created = DateTime::from_timestamp(created_ts, 0).ok_or(
+            AppProcessorErrorReason::CorruptedTimeStamp(
+                "stripe.event.created".to_string(),
+                created_ts,
+            ),
+        )?;
+        let obj = data.object;
+        let detail = match type_.as_str() {
+            "checkout.session.completed" | "checkout.session.expired" => {
+                let s = deserialize_object::<CheckoutSession>(type_.as_str(), obj)?;
+                StripeWebhookEventDetail::CheckoutSession {
+                    session_id: s.id,
+                    client_reference_id: s.client_reference_id,
+                    session_state: s.status,
+                    payment_state: s.payment_status,
+                }
+            }
+            "charge.refunded" => {
+                let c = deserialize_object::<Charge>(type_.as_str(), obj)?;
+                StripeWebhookEventDetail::ChargeRefunded {
+                    payment_intent_id: c.payment_intent,
+                    transfer_group: c.transfer_group,
+                    amount_refunded: c.amount_refunded,
+                }
+            }
+            "account.updated" => {
+                let a = deserialize_object::<ConnectAccount>(type_.as_str(), obj)?;
+                let m = Merchant3partyStripeModel::try_from((a, None))?;
+                StripeWebhookEventDetail::AccountUpdated(m)
+            }
+            t if t.starts_with("transfer.") => {
+                let tx = deserialize_object::<Transfer>(t, obj)?;
+                let amount = tx.amount_decimal();
+                StripeWebhookEventDetail::Transfer {
+                    reversed: tx.reversed || t == "transfer.reversed",
+                    transfer_id: tx.id,
+                    acct_id: tx.destination,
+                    transfer_group: tx.transfer_group,
+                    amount,
+                }
+            }
+            _others => StripeWebhookEventDetail::Unsupported,
+        };
+        Ok(Self {
+            id,
+            type_,
+            created,
+            detail,
+        })
+    } // end of fn try-from
+} // end of impl StripeWebhookEventModel
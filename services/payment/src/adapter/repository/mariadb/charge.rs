@@ -17,21 +17,26 @@ use ecommerce_common::model::order::BillingModel;
 use crate::adapter::datastore::{AppDStoreMariaDB, AppDataStoreContext};
 use crate::model::{
     ChargeBuyerMetaModel, ChargeBuyerModel, ChargeLineBuyerModel, ChargeRefundMap, Label3party,
-    OrderCurrencySnapshot, OrderLineModel, OrderLineModelSet, PayoutAmountModel, PayoutModel,
+    OrderCurrencySnapshot, OrderLineModel, OrderLineModelSet, PayoutAmountModel, PayoutLineModel,
+    PayoutModel,
 };
 
-use super::super::{AbstractChargeRepo, AppRepoError, AppRepoErrorDetail, AppRepoErrorFnLabel};
+use super::super::{
+    AbstractChargeRepo, AppChargeUnsyncedCursor, AppRepoError, AppRepoErrorDetail,
+    AppRepoErrorFnLabel,
+};
 use super::charge_converter::{
-    ChargeIdRowType, ChargeLineRowType, ChargeMetaRowType, FetchChargeIDsArgs, FetchChargeLineArgs,
-    FetchChargeMetaArgs, InsertChargeArgs, UpdateChargeLineRefundArgs, UpdateChargeMetaArgs,
+    ChargeIdRowType, ChargeLineRowType, ChargeMetaRowType, ChargeUnsyncedRowType,
+    FetchChargeIDsArgs, FetchChargeLineArgs, FetchChargeMetaArgs, FetchChargeUnsyncedArgs,
+    InsertChargeArgs, UpdateChargeLineRefundArgs, UpdateChargeMetaArgs,
 };
 use super::order_replica::{
     FetchCurrencySnapshotArgs, FetchUnpaidOlineArgs, InsertOrderReplicaArgs, OrderCurrencyRowType,
     OrderlineRowType,
 };
 use super::payout::{
-    FetchPayout3partyArgs, FetchPayoutMetaArgs, InsertPayout3partyArgs, InsertPayoutMetaArgs,
-    PayoutMetaRowType,
+    FetchPayout3partyArgs, FetchPayoutLinesArgs, FetchPayoutMetaArgs, InsertPayout3partyArgs,
+    InsertPayoutLinesArgs, InsertPayoutMetaArgs, PayoutLineRowType, PayoutMetaRowType,
 };
 use super::raw_column_to_datetime;
 
@@ -383,6 +388,34 @@ impl AbstractChargeRepo for MariadbChargeRepo {
         }
     } // end of fn update_charge_progress
 
+    async fn fetch_charges_unsynced(
+        &self,
+        t_start: DateTime<Utc>,
+        t_end: DateTime<Utc>,
+        cursor: Option<AppChargeUnsyncedCursor>,
+        max_items: u16,
+    ) -> Result<Vec<ChargeBuyerMetaModel>, AppRepoError> {
+        let (stmt, params) =
+            FetchChargeUnsyncedArgs::from((t_start, t_end, cursor, max_items)).into_parts();
+        let mut conn = self._dstore.acquire().await.map_err(|e| {
+            let code = AppErrorCode::DatabaseServerBusy;
+            let detail = AppRepoErrorDetail::DataStore(e);
+            self._map_log_err_common((code, detail), AppRepoErrorFnLabel::FetchChargesUnsynced)
+        })?;
+        let rows = stmt
+            .with(params)
+            .fetch::<ChargeUnsyncedRowType, &mut Conn>(&mut conn)
+            .await
+            .map_err(|e| {
+                let code = AppErrorCode::RemoteDbServerFailure;
+                let detail = AppRepoErrorDetail::DatabaseQuery(e.to_string());
+                self._map_log_err_common((code, detail), AppRepoErrorFnLabel::FetchChargesUnsynced)
+            })?;
+        FetchChargeUnsyncedArgs::convert_rows(rows).map_err(|reason| {
+            self._map_log_err_common(reason, AppRepoErrorFnLabel::FetchChargesUnsynced)
+        })
+    } // end of fn fetch_charges_unsynced
+
     async fn update_lines_refund(&self, cl_map: ChargeRefundMap) -> Result<(), AppRepoError> {
         let mut conn = self._dstore.acquire().await.map_err(|e| {
             let code = AppErrorCode::DatabaseServerBusy;
@@ -469,12 +502,12 @@ impl AbstractChargeRepo for MariadbChargeRepo {
         }))
     } // end of fn fetch_charge_by_merchant
 
-    async fn fetch_payout(
+    async fn fetch_payouts(
         &self,
         store_id: u32,
         buyer_usr_id: u32,
         charged_ctime: DateTime<Utc>,
-    ) -> Result<Option<PayoutModel>, AppRepoError> {
+    ) -> Result<Vec<PayoutModel>, AppRepoError> {
         let mut conn = self._dstore.acquire().await.map_err(|e| {
             self._map_log_err_common(
                 (
@@ -488,22 +521,23 @@ impl AbstractChargeRepo for MariadbChargeRepo {
             let arg = (buyer_usr_id, charged_ctime, store_id);
             FetchPayoutMetaArgs::from(arg).into_parts()
         };
-        let maybe_row = stmt
+        let rows_meta = stmt
             .with(params)
-            .first::<PayoutMetaRowType, &mut Conn>(&mut conn)
+            .fetch::<PayoutMetaRowType, &mut Conn>(&mut conn)
             .await
             .map_err(|e| {
                 let code = AppErrorCode::RemoteDbServerFailure;
                 let detail = AppRepoErrorDetail::DatabaseQuery(e.to_string());
                 self._map_log_err_common((code, detail), AppRepoErrorFnLabel::FetchPayout)
             })?;
-        let row_meta = if let Some(v) = maybe_row {
+        let first_meta = if let Some(v) = rows_meta.first() {
             v
         } else {
-            return Ok(None);
+            return Ok(Vec::new());
         };
-        let p3pty_m = {
-            let label3pt = Label3party::try_from(row_meta.4.as_str()).map_err(|s| {
+        // all payouts of the same charge are processed by the same 3rd party
+        let mut p3pty_ms = {
+            let label3pt = Label3party::try_from(first_meta.5.as_str()).map_err(|s| {
                 let code = AppErrorCode::DataCorruption;
                 let detail = AppRepoErrorDetail::PayMethodUnsupport(s.to_string());
                 self._map_log_err_common((code, detail), AppRepoErrorFnLabel::FetchPayout)
@@ -515,9 +549,34 @@ impl AbstractChargeRepo for MariadbChargeRepo {
                 .map_err(|reason| {
                     self._map_log_err_common(reason, AppRepoErrorFnLabel::FetchPayout)
                 })?
+                .into_iter()
+                .collect::<HashMap<_, _>>()
+        };
+        let mut lines_map = {
+            let (stmt, params) = {
+                let arg = (buyer_usr_id, charged_ctime, store_id);
+                FetchPayoutLinesArgs::from(arg).into_parts()
+            };
+            let rows = stmt
+                .with(params)
+                .fetch::<PayoutLineRowType, &mut Conn>(&mut conn)
+                .await
+                .map_err(|e| {
+                    let code = AppErrorCode::RemoteDbServerFailure;
+                    let detail = AppRepoErrorDetail::DatabaseQuery(e.to_string());
+                    self._map_log_err_common((code, detail), AppRepoErrorFnLabel::FetchPayout)
+                })?;
+            let mut out: HashMap<u16, Vec<PayoutLineModel>> = HashMap::new();
+            rows.into_iter()
+                .map(|(seq, product_id, attr_seq, qty, amount_buyer)| {
+                    let line = PayoutLineModel::from((product_id, attr_seq, qty, amount_buyer));
+                    out.entry(seq).or_default().push(line);
+                })
+                .count();
+            out
         };
 
-        let oid_ref = OidBytes::to_app_oid(row_meta.2.clone()).map_err(|(code, msg)| {
+        let oid_ref = OidBytes::to_app_oid(first_meta.3.clone()).map_err(|(code, msg)| {
             let detail = AppRepoErrorDetail::OrderIDparse(msg);
             self._map_log_err_common((code, detail), AppRepoErrorFnLabel::FetchPayout)
         })?;
@@ -563,40 +622,69 @@ impl AbstractChargeRepo for MariadbChargeRepo {
                 )
             })?;
 
-        let arg = (row_meta.3, currency_seller, currency_buyer);
-        let amount_m = PayoutAmountModel::try_from(arg).map_err(|e| {
-            let msg = format!("payout-model: {:?}", e);
-            let detail = AppRepoErrorDetail::DataRowParse(msg);
-            self._map_log_err_common(
-                (AppErrorCode::DataCorruption, detail),
-                AppRepoErrorFnLabel::FetchPayout,
-            )
-        })?;
-
-        let capture_create_time = raw_column_to_datetime(row_meta.0, 0)
-            .map_err(|reason| self._map_log_err_common(reason, AppRepoErrorFnLabel::FetchPayout))?;
-
-        let arg = (
-            store_id,
-            capture_create_time,
-            buyer_usr_id,
-            charged_ctime,
-            oid_ref,
-            row_meta.1,
-            amount_m,
-            p3pty_m,
-        );
-        Ok(Some(PayoutModel::from(arg)))
-    } // end of fn fetch_payout
+        let mut out = Vec::new();
+        for row_meta in rows_meta {
+            let seq = row_meta.0;
+            let arg = (
+                row_meta.4,
+                row_meta.6,
+                currency_seller.clone(),
+                currency_buyer.clone(),
+            );
+            let amount_m = PayoutAmountModel::try_from(arg).map_err(|e| {
+                let msg = format!("payout-model: {:?}", e);
+                let detail = AppRepoErrorDetail::DataRowParse(msg);
+                self._map_log_err_common(
+                    (AppErrorCode::DataCorruption, detail),
+                    AppRepoErrorFnLabel::FetchPayout,
+                )
+            })?;
+            let p3pty_m = p3pty_ms
+                .remove(&seq)
+                .ok_or(AppRepoErrorDetail::DatabaseQuery(format!(
+                    "missing-3party, seq:{seq}"
+                )))
+                .map_err(|detail| {
+                    self._map_log_err_common(
+                        (AppErrorCode::DataCorruption, detail),
+                        AppRepoErrorFnLabel::FetchPayout,
+                    )
+                })?;
+            let lines = lines_map.remove(&seq).unwrap_or_default();
+            let capture_create_time = raw_column_to_datetime(row_meta.1, 0).map_err(|reason| {
+                self._map_log_err_common(reason, AppRepoErrorFnLabel::FetchPayout)
+            })?;
+            let arg = (
+                store_id,
+                capture_create_time,
+                buyer_usr_id,
+                charged_ctime,
+                oid_ref.clone(),
+                row_meta.2,
+                seq,
+                amount_m,
+                lines,
+                p3pty_m,
+            );
+            out.push(PayoutModel::from(arg));
+        }
+        Ok(out)
+    } // end of fn fetch_payouts
 
     async fn create_payout(&self, payout_m: PayoutModel) -> Result<(), AppRepoError> {
-        let (p_inner, p3pty) = payout_m.into_parts();
+        let (mut p_inner, p3pty) = payout_m.into_parts();
         let label3pt = Label3party::from(&p3pty);
         let (buyer_usr_id, charged_ctime) = p_inner.referenced_charge();
         let merchant_id = p_inner.merchant_id();
+        let seq = p_inner.seq();
 
+        let (stmt_lines, params_lines) = {
+            let lines = p_inner.take_lines();
+            let arg = (buyer_usr_id, charged_ctime, merchant_id, seq, lines);
+            InsertPayoutLinesArgs::from(arg).into_parts()
+        };
         let (stmt_3pt, params_3pt) = {
-            let arg = (buyer_usr_id, charged_ctime, merchant_id, p3pty);
+            let arg = (buyer_usr_id, charged_ctime, merchant_id, seq, p3pty);
             InsertPayout3partyArgs::try_from(arg)
                 .map_err(|reason| {
                     self._map_log_err_common(reason, AppRepoErrorFnLabel::CreatePayout)
@@ -638,6 +726,13 @@ impl AbstractChargeRepo for MariadbChargeRepo {
                 ));
             }
         } // end of for loop
+        if !params_lines.is_empty() {
+            tx.exec_batch(stmt_lines, params_lines).await.map_err(|e| {
+                let code = AppErrorCode::RemoteDbServerFailure;
+                let detail = AppRepoErrorDetail::DatabaseExec(e.to_string());
+                self._map_log_err_common((code, detail), AppRepoErrorFnLabel::CreatePayout)
+            })?;
+        }
 
         tx.commit().await.map_err(|e| {
             let code = AppErrorCode::RemoteDbServerFailure;
@@ -8,11 +8,14 @@ use ecommerce_common::adapter::repository::OidBytes;
 use ecommerce_common::error::AppErrorCode;
 use ecommerce_common::model::BaseProductIdentity;
 
-use super::super::{AppRepoError, AppRepoErrorDetail, AppRepoErrorFnLabel};
+use super::super::{
+    AppChargeUnsyncedCursor, AppRepoError, AppRepoErrorDetail, AppRepoErrorFnLabel,
+};
 use super::{inner_into_parts, raw_column_to_datetime, DATETIME_FMT_P0F, DATETIME_FMT_P3F};
 use crate::model::{
-    BuyerPayInState, Charge3partyModel, Charge3partyStripeModel, ChargeBuyerMetaModel,
-    ChargeBuyerModel, ChargeLineBuyerModel, ChargeRefundMap, PayLineAmountModel,
+    BuyerPayInState, Charge3partyModel, Charge3partyPaypalModel, Charge3partyStripeModel,
+    ChargeBuyerMetaModel, ChargeBuyerModel, ChargeLineBuyerModel, ChargeRefundMap,
+    PayLineAmountModel,
 };
 
 struct InsertChargeTopLvlArgs(String, Params);
@@ -40,6 +43,7 @@ pub(super) struct UpdateChargeMetaArgs(String, Params);
 pub(super) struct FetchChargeLineArgs(String, Params);
 pub(super) struct UpdateChargeLineRefundArgs(String, Vec<Params>);
 pub(super) struct FetchChargeIDsArgs(String, Params);
+pub(super) struct FetchChargeUnsyncedArgs(String, Params);
 
 pub(super) type ChargeMetaRowType = (
     Vec<u8>,
@@ -58,6 +62,12 @@ pub(super) type ChargeLineRowType = (
 
 pub(super) type ChargeIdRowType = (u32, mysql_async::Value);
 
+#[rustfmt::skip]
+pub(super) type ChargeUnsyncedRowType = (
+    u32, mysql_async::Value, Vec<u8>, String, Option<mysql_async::Value>,
+    Option<mysql_async::Value>, Option<mysql_async::Value>, String, String,
+);
+
 impl TryFrom<BuyerPayInState> for InsertChargeStatusArgs {
     type Error = AppRepoError;
     fn try_from(value: BuyerPayInState) -> Result<Self, Self::Error> {
@@ -128,6 +138,15 @@ impl TryFrom<Charge3partyModel> for UpdateCharge3partyArgs {
                     ))
                     .map(|detail| Self {label, detail})
             }
+            Charge3partyModel::Paypal(m) => {
+                let label = "Paypal".to_string();
+                serde_json::to_string(&m)
+                    .map_err(|e| (
+                        AppErrorCode::DataCorruption,
+                        AppRepoErrorDetail::PayDetail(label.clone(), e.to_string()),
+                    ))
+                    .map(|detail| Self {label, detail})
+            }
             Charge3partyModel::Unknown =>
                 Err((
                     AppErrorCode::InvalidInput,
@@ -304,6 +323,9 @@ impl TryFrom<(String, String)> for Charge3partyModel {
             "Stripe" => serde_json::from_str::<Charge3partyStripeModel>(detail.as_str())
                 .map(Charge3partyModel::Stripe)
                 .map_err(|e| e.to_string()),
+            "Paypal" => serde_json::from_str::<Charge3partyPaypalModel>(detail.as_str())
+                .map(Charge3partyModel::Paypal)
+                .map_err(|e| e.to_string()),
             _others => Err(format!("unknown-3pty-method: {}", label)),
         };
         result.map_err(|msg| {
@@ -500,3 +522,66 @@ impl FetchChargeIDsArgs {
         }
     } // end of fn convert_rows
 } // end of impl FetchChargeIDsArgs
+
+type FetchChargeUnsyncedCvtArgs = (
+    DateTime<Utc>,
+    DateTime<Utc>,
+    Option<AppChargeUnsyncedCursor>,
+    u16,
+);
+
+impl From<FetchChargeUnsyncedCvtArgs> for FetchChargeUnsyncedArgs {
+    fn from(value: FetchChargeUnsyncedCvtArgs) -> Self {
+        let (t_start, t_end, cursor, max_items) = value;
+        let mut args = vec![
+            t_start.format(DATETIME_FMT_P3F).to_string().into(),
+            t_end.format(DATETIME_FMT_P3F).to_string().into(),
+        ];
+        // keyset pagination, charges which are never confirmed or failed to
+        // sync would stay in the range, they should not block newer charges
+        let cond_cursor = if let Some((t_completed, usr_id, ctime)) = cursor {
+            args.extend([
+                t_completed.format(DATETIME_FMT_P3F).to_string().into(),
+                usr_id.into(),
+                ctime.format(DATETIME_FMT_P0F).to_string().into(),
+            ]);
+            "AND (`processor_completed_time`,`usr_id`,`create_time`) > (?,?,?) "
+        } else {
+            ""
+        };
+        args.push(max_items.into());
+        let stmt = format!(
+            "SELECT `usr_id`,`create_time`,`order_id`,`state`,`processor_accepted_time`,\
+            `processor_completed_time`,`orderapp_synced_time`,`pay_method`,`detail_3rdparty` \
+            FROM `charge_buyer_toplvl` WHERE `state`='ProcessorCompleted' AND \
+            `processor_completed_time` BETWEEN ? AND ? {cond_cursor}ORDER BY \
+            `processor_completed_time` ASC, `usr_id` ASC, `create_time` ASC LIMIT ?"
+        );
+        Self(stmt, Params::Positional(args))
+    }
+}
+
+inner_into_parts!(FetchChargeUnsyncedArgs);
+
+impl FetchChargeUnsyncedArgs {
+    #[rustfmt::skip]
+    pub(super) fn convert_rows(
+        rows: Vec<ChargeUnsyncedRowType>,
+    ) -> Result<Vec<ChargeBuyerMetaModel>, (AppErrorCode, AppRepoErrorDetail)> {
+        rows.into_iter()
+            .map(|row| {
+                let (
+                    usr_id, ctime_raw, oid_raw, buyin_state, accepted_time_3pty,
+                    completed_time_3pty, orderapp_synced_time, mthd_3pty_label,
+                    detail_3pty_serial,
+                ) = row;
+                let create_time = raw_column_to_datetime(ctime_raw, 0)?;
+                let meta_row = (
+                    oid_raw, buyin_state, accepted_time_3pty, completed_time_3pty,
+                    orderapp_synced_time, mthd_3pty_label, detail_3pty_serial,
+                );
+                ChargeBuyerMetaModel::try_from((usr_id, create_time, meta_row))
+            })
+            .collect()
+    }
+} // end of impl FetchChargeUnsyncedArgs
@@ -4,13 +4,16 @@ use std::sync::Arc;
 use async_trait::async_trait;
 use mysql_async::prelude::{Query, Queryable, WithParams};
 use mysql_async::{Conn, IsolationLevel, Params, TxOpts};
+use rust_decimal::Decimal;
 
+use ecommerce_common::api::dto::CurrencyDto;
 use ecommerce_common::error::AppErrorCode;
 use ecommerce_common::logging::{app_log_event, AppLogLevel};
 
 use crate::adapter::datastore::{AppDStoreMariaDB, AppDataStoreContext};
 use crate::model::{
-    Label3party, Merchant3partyModel, Merchant3partyStripeModel, MerchantProfileModel,
+    Label3party, Merchant3partyModel, Merchant3partyPaypalModel, Merchant3partyStripeModel,
+    MerchantProfileModel, PlatformFeeRuleModel, PlatformFeeScheduleModel,
 };
 
 use super::super::{AbstractMerchantRepo, AppRepoError, AppRepoErrorDetail, AppRepoErrorFnLabel};
@@ -21,6 +24,8 @@ struct Insert3partyArgs(String, Params);
 struct Update3partyArgs(String, Params);
 struct FetchProfileArgs(String, Params);
 struct Fetch3partyArgs(String, Params, String);
+struct FetchIdBy3partyArgs(String, Params);
+struct FetchPlatformFeeArgs(String, Params);
 
 type MercProfRowType = (
     String,             // `name`
@@ -29,6 +34,15 @@ type MercProfRowType = (
     mysql_async::Value, // `last_update`
 );
 type Merc3ptyRowType = (Vec<u8>,);
+#[rustfmt::skip]
+type PlatformFeeRowType = (
+    u32,                        // `store_id`, zero means default rule for all merchants
+    String,                     // `currency`
+    Decimal,                    // `fee_flat`
+    Decimal,                    // `fee_percent`
+    mysql_async::Value,         // `start_after`
+    Option<mysql_async::Value>, // `end_before`
+);
 
 impl From<MerchantProfileModel> for InsertUpdateProfileArgs {
     fn from(value: MerchantProfileModel) -> Self {
@@ -74,6 +88,16 @@ impl TryInto<(String, String)> for Merchant3partyModel {
                 })?;
                 Ok((label, d))
             }
+            Self::Paypal(pm) => {
+                let label = "Paypal".to_string();
+                let d = serde_json::to_string(&pm).map_err(|e| {
+                    (
+                        AppErrorCode::DataCorruption,
+                        AppRepoErrorDetail::PayDetail(label.clone(), e.to_string()),
+                    )
+                })?;
+                Ok((label, d))
+            }
             Self::Unknown => Err((
                 AppErrorCode::InvalidInput,
                 AppRepoErrorDetail::PayMethodUnsupport("unknown".to_string()),
@@ -127,6 +151,64 @@ impl From<(u32, Label3party)> for Fetch3partyArgs {
     }
 }
 
+impl<'a> From<(Label3party, &'a str)> for FetchIdBy3partyArgs {
+    fn from(value: (Label3party, &'a str)) -> Self {
+        // the identifier issued by 3rd party is always saved as top-level
+        // field in the serialized detail, the field name varies among processors
+        let (l3pt, id3pty) = value;
+        let stmt = match &l3pt {
+            Label3party::Stripe => {
+                "SELECT `sid` FROM `merchant_3party` WHERE `method`=? AND \
+                 JSON_VALUE(`detail`, '$.id')=?"
+            }
+            Label3party::Paypal => {
+                "SELECT `sid` FROM `merchant_3party` WHERE `method`=? AND \
+                 JSON_VALUE(`detail`, '$.merchant_id')=?"
+            }
+        };
+        let arg = vec![l3pt.to_string().into(), id3pty.into()];
+        let params = Params::Positional(arg);
+        Self(stmt.to_string(), params)
+    }
+}
+
+impl From<u32> for FetchPlatformFeeArgs {
+    fn from(value: u32) -> Self {
+        let stmt = "SELECT `store_id`,`currency`,`fee_flat`,`fee_percent`,`start_after`,\
+                    `end_before` FROM `platform_fee_schedule` WHERE `store_id` IN (0,?)";
+        let arg = vec![value.into()];
+        let params = Params::Positional(arg);
+        Self(stmt.to_string(), params)
+    }
+}
+
+impl TryFrom<(u32, Vec<PlatformFeeRowType>)> for PlatformFeeScheduleModel {
+    type Error = (AppErrorCode, AppRepoErrorDetail);
+    #[rustfmt::skip]
+    fn try_from(value: (u32, Vec<PlatformFeeRowType>)) -> Result<Self, Self::Error> {
+        let (store_id, rows) = value;
+        let mut rules = Vec::new();
+        for row in rows {
+            let (sid, currency_raw, flat, percentage, t0_raw, t1_raw) = row;
+            let merchant_id = if sid == 0 { None } else { Some(sid) };
+            let currency = CurrencyDto::from(&currency_raw);
+            let start_after = raw_column_to_datetime(t0_raw, 0)?;
+            let end_before = if let Some(v) = t1_raw {
+                Some(raw_column_to_datetime(v, 0)?)
+            } else {
+                None
+            };
+            let arg = (merchant_id, currency, flat, percentage, start_after, end_before);
+            let rule = PlatformFeeRuleModel::try_from(arg).map_err(|e| {
+                let detail = AppRepoErrorDetail::DataRowParse(format!("{:?}", e));
+                (AppErrorCode::DataCorruption, detail)
+            })?;
+            rules.push(rule);
+        }
+        Ok(Self::from((store_id, rules)))
+    }
+} // end of impl PlatformFeeScheduleModel
+
 impl TryFrom<(u32, MercProfRowType)> for MerchantProfileModel {
     type Error = (AppErrorCode, AppRepoErrorDetail);
     #[rustfmt::skip]
@@ -163,19 +245,29 @@ impl TryFrom<(Label3party, Merc3ptyRowType)> for Merchant3partyModel {
     type Error = (AppErrorCode, AppRepoErrorDetail);
     fn try_from(value: (Label3party, Merc3ptyRowType)) -> Result<Self, Self::Error> {
         let (label, (detail_raw,)) = value;
-        let out = match label {
-            Label3party::Stripe => {
-                let s = serde_json::from_slice::<Merchant3partyStripeModel>(&detail_raw).map_err(
-                    |e| {
-                        (
-                            AppErrorCode::DataCorruption,
-                            AppRepoErrorDetail::DataRowParse(e.to_string()),
-                        )
-                    },
-                )?;
-                Self::Stripe(s)
-            }
-        };
+        let out =
+            match label {
+                Label3party::Stripe => {
+                    let s = serde_json::from_slice::<Merchant3partyStripeModel>(&detail_raw)
+                        .map_err(|e| {
+                            (
+                                AppErrorCode::DataCorruption,
+                                AppRepoErrorDetail::DataRowParse(e.to_string()),
+                            )
+                        })?;
+                    Self::Stripe(s)
+                }
+                Label3party::Paypal => {
+                    let p = serde_json::from_slice::<Merchant3partyPaypalModel>(&detail_raw)
+                        .map_err(|e| {
+                            (
+                                AppErrorCode::DataCorruption,
+                                AppRepoErrorDetail::DataRowParse(e.to_string()),
+                            )
+                        })?;
+                    Self::Paypal(p)
+                }
+            };
         Ok(out)
     }
 } // end of impl MerchantProfileModel
@@ -418,4 +510,58 @@ impl AbstractMerchantRepo for MariadbMerchantRepo {
             Err(e)
         }
     } // end of fn update_3party
+
+    async fn fetch_id_by_3party(
+        &self,
+        label3pty: Label3party,
+        id3pty: &str,
+    ) -> Result<Option<u32>, AppRepoError> {
+        let FetchIdBy3partyArgs(stmt, params) = FetchIdBy3partyArgs::from((label3pty, id3pty));
+        let mut conn = self._dstore.acquire().await.map_err(|e| {
+            self._map_log_err(
+                AppErrorCode::DatabaseServerBusy,
+                AppRepoErrorDetail::DataStore(e),
+                AppRepoErrorFnLabel::FetchMerchantBy3party,
+            )
+        })?;
+        let maybe_row = stmt
+            .with(params)
+            .first::<(u32,), &mut Conn>(&mut conn)
+            .await
+            .map_err(|e| {
+                self._map_log_err(
+                    AppErrorCode::RemoteDbServerFailure,
+                    AppRepoErrorDetail::DatabaseQuery(e.to_string()),
+                    AppRepoErrorFnLabel::FetchMerchantBy3party,
+                )
+            })?;
+        Ok(maybe_row.map(|(store_id,)| store_id))
+    } // end of fn fetch_id_by_3party
+
+    async fn fetch_platform_fee(
+        &self,
+        store_id: u32,
+    ) -> Result<PlatformFeeScheduleModel, AppRepoError> {
+        let FetchPlatformFeeArgs(stmt, params) = FetchPlatformFeeArgs::from(store_id);
+        let mut conn = self._dstore.acquire().await.map_err(|e| {
+            self._map_log_err(
+                AppErrorCode::DatabaseServerBusy,
+                AppRepoErrorDetail::DataStore(e),
+                AppRepoErrorFnLabel::FetchPlatformFee,
+            )
+        })?;
+        let rows = stmt
+            .with(params)
+            .fetch::<PlatformFeeRowType, &mut Conn>(&mut conn)
+            .await
+            .map_err(|e| {
+                self._map_log_err(
+                    AppErrorCode::RemoteDbServerFailure,
+                    AppRepoErrorDetail::DatabaseQuery(e.to_string()),
+                    AppRepoErrorFnLabel::FetchPlatformFee,
+                )
+            })?;
+        PlatformFeeScheduleModel::try_from((store_id, rows))
+            .map_err(|e| self._map_log_err(e.0, e.1, AppRepoErrorFnLabel::FetchPlatformFee))
+    } // end of fn fetch_platform_fee
 } // end of impl MariadbMerchantRepo
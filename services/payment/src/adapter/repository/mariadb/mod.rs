@@ -5,6 +5,7 @@ mod order_replica;
 mod payout;
 pub(super) mod refund;
 pub(super) mod reporting;
+pub(super) mod webhook;
 
 use std::result::Result;
 
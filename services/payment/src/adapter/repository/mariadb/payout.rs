@@ -11,95 +11,146 @@ use ecommerce_common::error::AppErrorCode;
 
 use super::super::AppRepoErrorDetail;
 use super::{inner_into_parts, DATETIME_FMT_P0F};
-use crate::model::{Label3party, Payout3partyModel, Payout3partyStripeModel, PayoutInnerModel};
+use crate::model::{
+    Label3party, Payout3partyModel, Payout3partyPaypalModel, Payout3partyStripeModel,
+    PayoutInnerModel, PayoutLineModel,
+};
 
 pub(super) struct InsertPayoutMetaArgs(String, Params);
 pub(super) struct InsertPayout3partyArgs(String, Params);
+pub(super) struct InsertPayoutLinesArgs(String, Vec<Params>);
 pub(super) struct FetchPayoutMetaArgs(String, Params);
 pub(super) struct FetchPayout3partyArgs(String, Params, Label3party);
+pub(super) struct FetchPayoutLinesArgs(String, Params);
 
 pub(super) type PayoutMetaRowType = (
+    u16,                // `seq`
     mysql_async::Value, // `create_time`
     u32,                // `storestaff-usr-id`
     Vec<u8>,            // `order-id`
     Decimal,            // `amount-buyer`
     String,             // `3party-label`
+    Decimal,            // `fee-buyer`
 );
 
 type Payout3ptyStripeRowType = (
+    u16,     // `seq`
     String,  // `tx-grp`
     String,  // `acct-id`
     String,  // `transfer-id`
     Decimal, // `amount-base`
 );
 
+type Payout3ptyPaypalRowType = (
+    u16,     // `seq`
+    String,  // `ref-id`
+    String,  // `payee-id`
+    String,  // `batch-id`
+    Decimal, // `amount-base`
+);
+
+pub(super) type PayoutLineRowType = (
+    u16,     // `seq`
+    u64,     // `product-id`
+    u16,     // `attr-seq`
+    u32,     // `qty`
+    Decimal, // `amount-buyer`
+);
+
 #[rustfmt::skip]
 impl TryFrom<(PayoutInnerModel, Label3party)> for InsertPayoutMetaArgs {
     type Error = (AppErrorCode, AppRepoErrorDetail);
     
     fn try_from(value: (PayoutInnerModel, Label3party)) -> Result<Self, Self::Error> {
         let (p_inner, label3pt) = value;
-        let stmt = "INSERT INTO `payout_meta`(`buyer_usr_id`,`charged_time`,`store_id`,`create_time`,\
-                    `storestaff_usr_id`,`order_id`,`amount_buyer`,`label3party`)\
-                    VALUES (?,?,?,?, ?,?,?,?)";
+        let stmt = "INSERT INTO `payout_meta`(`buyer_usr_id`,`charged_time`,`store_id`,`seq`,\
+                    `create_time`,`storestaff_usr_id`,`order_id`,`amount_buyer`,`label3party`,\
+                    `fee_buyer`) VALUES (?,?,?,?,?, ?,?,?,?,?)";
         
         // note the currency snoapshot for specific order should be saved in another module
         // `order-replica`, no need to persist them at here
         let amt_buyer = p_inner.amount_buyer();
+        let fee_buyer = p_inner.platform_fee();
         let (
-            merchant_id, capture_time, buyer_id, charge_ctime,
-            storestaff_id, _amount_m, order_id
+            merchant_id, capture_time, buyer_id, charge_ctime, seq,
+            storestaff_id, _amount_m, _lines, order_id
         ) = p_inner.into_parts();
         let oid_b = OidBytes::try_from(order_id.as_str())
             .map_err(|(code, msg)| (code, AppRepoErrorDetail::OrderIDparse(msg)))?;
         
         let args = vec![
             buyer_id.into(), charge_ctime.format(DATETIME_FMT_P0F).to_string().into(),
-            merchant_id.into(), capture_time.format(DATETIME_FMT_P0F).to_string().into(),
+            merchant_id.into(), seq.into(),
+            capture_time.format(DATETIME_FMT_P0F).to_string().into(),
             storestaff_id.into(), oid_b.as_column().into(), amt_buyer.into(),
-            label3pt.to_string().into(),
+            label3pt.to_string().into(), fee_buyer.into(),
         ];
         let params = Params::Positional(args);
         Ok(Self(stmt.to_string(), params))
     }
-} // end of impl InsertPayout3partyArgs
+} // end of impl InsertPayoutMetaArgs
 
-type Payout3partyCvtFromArg = (u32, DateTime<Utc>, u32, Payout3partyModel);
+type Payout3partyCvtFromArg = (u32, DateTime<Utc>, u32, u16, Payout3partyModel);
 
 impl TryFrom<Payout3partyCvtFromArg> for InsertPayout3partyArgs {
     type Error = (AppErrorCode, AppRepoErrorDetail);
 
     fn try_from(value: Payout3partyCvtFromArg) -> Result<Self, Self::Error> {
-        let (buyer_usr_id, charged_ctime, merchant_id, p3pty) = value;
+        let (buyer_usr_id, charged_ctime, merchant_id, seq, p3pty) = value;
+        let key = (buyer_usr_id, charged_ctime, merchant_id, seq);
         match p3pty {
-            Payout3partyModel::Stripe(s) => {
-                Self::try_from_stripe(buyer_usr_id, charged_ctime, merchant_id, s).map_err(|msg| {
-                    (
-                        AppErrorCode::InvalidInput,
-                        AppRepoErrorDetail::PayDetail(Label3party::Stripe.to_string(), msg),
-                    )
-                })
-            }
+            Payout3partyModel::Stripe(s) => Self::try_from_stripe(key, s).map_err(|msg| {
+                (
+                    AppErrorCode::InvalidInput,
+                    AppRepoErrorDetail::PayDetail(Label3party::Stripe.to_string(), msg),
+                )
+            }),
+            Payout3partyModel::Paypal(p) => Self::try_from_paypal(key, p).map_err(|msg| {
+                (
+                    AppErrorCode::InvalidInput,
+                    AppRepoErrorDetail::PayDetail(Label3party::Paypal.to_string(), msg),
+                )
+            }),
         }
     }
 }
 impl InsertPayout3partyArgs {
     #[rustfmt::skip]
     fn try_from_stripe(
-        buyer_usr_id: u32,
-        charged_ctime: DateTime<Utc>,
-        merchant_id: u32,
+        key: (u32, DateTime<Utc>, u32, u16),
         value: Payout3partyStripeModel
     ) -> Result<Self, String> {
+        let (buyer_usr_id, charged_ctime, merchant_id, seq) = key;
         let amt_bs = value.amount().ok_or("missing-amount".to_string())?;
         let transfer_id = value.transfer_id().ok_or("missing-transfer-id".to_string())?;
         let tx_grp = value.transfer_group();
         let acct_id = value.connect_account();
         let stmt = "INSERT INTO `payout_3party_stripe`(`buyer_usr_id`,`charged_time`,`store_id`,\
-                    `tx_grp`,`acct_id`,`transfer_id`,`amount`) VALUES (?,?,?,?,?,?,?)";
+                    `seq`,`tx_grp`,`acct_id`,`transfer_id`,`amount`) VALUES (?,?,?,?,?,?,?,?)";
+        let args = vec![
+            buyer_usr_id.into(), charged_ctime.format(DATETIME_FMT_P0F).to_string().into(),
+            merchant_id.into(), seq.into(), tx_grp.into(), acct_id.into(), transfer_id.into(),
+            amt_bs.into(),
+        ];
+        let params = Params::Positional(args);
+        Ok(Self(stmt.to_string(), params))
+    }
+
+    #[rustfmt::skip]
+    fn try_from_paypal(
+        key: (u32, DateTime<Utc>, u32, u16),
+        value: Payout3partyPaypalModel
+    ) -> Result<Self, String> {
+        let (buyer_usr_id, charged_ctime, merchant_id, seq) = key;
+        let amt_bs = value.amount().ok_or("missing-amount".to_string())?;
+        let batch_id = value.batch_id().ok_or("missing-batch-id".to_string())?;
+        let ref_id = value.reference_id();
+        let payee_id = value.payee_id();
+        let stmt = "INSERT INTO `payout_3party_paypal`(`buyer_usr_id`,`charged_time`,`store_id`,\
+                    `seq`,`ref_id`,`payee_id`,`batch_id`,`amount`) VALUES (?,?,?,?,?,?,?,?)";
         let args = vec![
             buyer_usr_id.into(), charged_ctime.format(DATETIME_FMT_P0F).to_string().into(),
-            merchant_id.into(), tx_grp.into(), acct_id.into(), transfer_id.into(),
+            merchant_id.into(), seq.into(), ref_id.into(), payee_id.into(), batch_id.into(),
             amt_bs.into(),
         ];
         let params = Params::Positional(args);
@@ -107,16 +158,48 @@ impl InsertPayout3partyArgs {
     }
 } // end of impl InsertPayout3partyArgs
 
+type PayoutLinesCvtFromArg = (u32, DateTime<Utc>, u32, u16, Vec<PayoutLineModel>);
+
+#[rustfmt::skip]
+impl From<PayoutLinesCvtFromArg> for InsertPayoutLinesArgs {
+    fn from(value: PayoutLinesCvtFromArg) -> Self {
+        let (buyer_usr_id, charged_ctime, merchant_id, seq, lines) = value;
+        let charged_ctime = charged_ctime.format(DATETIME_FMT_P0F).to_string();
+        let params = lines.into_iter()
+            .map(|line| {
+                let (product_id, attr_set_seq, qty, amount_buyer) = line.into_parts();
+                let arg = vec![
+                    buyer_usr_id.into(), charged_ctime.as_str().into(),
+                    merchant_id.into(), seq.into(), product_id.into(),
+                    attr_set_seq.into(), qty.into(), amount_buyer.into(),
+                ];
+                Params::Positional(arg)
+            })
+            .collect();
+        let stmt = "INSERT INTO `payout_line`(`buyer_usr_id`,`charged_time`,`store_id`,`seq`,\
+                    `product_id`,`attr_seq`,`qty`,`amount_buyer`) VALUES (?,?,?,?,?,?,?,?)";
+        Self(stmt.to_string(), params)
+    }
+} // end of impl InsertPayoutLinesArgs
+
+impl InsertPayoutLinesArgs {
+    pub(super) fn into_parts(self) -> (String, Vec<Params>) {
+        let Self(stmt, params) = self;
+        (stmt, params)
+    }
+}
+
 inner_into_parts!(InsertPayoutMetaArgs);
 inner_into_parts!(InsertPayout3partyArgs);
 inner_into_parts!(FetchPayoutMetaArgs);
+inner_into_parts!(FetchPayoutLinesArgs);
 
 impl From<(u32, DateTime<Utc>, u32)> for FetchPayoutMetaArgs {
     fn from(value: (u32, DateTime<Utc>, u32)) -> Self {
         let (buyer_id, charged_time, store_id) = value;
-        let stmt = "SELECT `create_time`,`storestaff_usr_id`,`order_id`,`amount_buyer`,\
-                   `label3party` FROM `payout_meta` WHERE `buyer_usr_id`=? AND `charged_time`=? \
-                   AND `store_id`=?";
+        let stmt = "SELECT `seq`,`create_time`,`storestaff_usr_id`,`order_id`,`amount_buyer`,\
+                   `label3party`,`fee_buyer` FROM `payout_meta` WHERE `buyer_usr_id`=? AND `charged_time`=? \
+                   AND `store_id`=? ORDER BY `seq` ASC";
         let arg = vec![
             buyer_id.into(),
             charged_time.format(DATETIME_FMT_P0F).to_string().into(),
@@ -132,7 +215,11 @@ impl From<(u32, DateTime<Utc>, u32, Label3party)> for FetchPayout3partyArgs {
         let (buyer_id, charged_time, store_id, label3pt) = value;
         let stmt = match &label3pt {
             Label3party::Stripe => {
-                "SELECT `tx_grp`,`acct_id`,`transfer_id`,`amount` FROM `payout_3party_stripe`\
+                "SELECT `seq`,`tx_grp`,`acct_id`,`transfer_id`,`amount` FROM `payout_3party_stripe`\
+                WHERE `buyer_usr_id`=? AND `charged_time`=? AND `store_id`=?"
+            }
+            Label3party::Paypal => {
+                "SELECT `seq`,`ref_id`,`payee_id`,`batch_id`,`amount` FROM `payout_3party_paypal`\
                 WHERE `buyer_usr_id`=? AND `charged_time`=? AND `store_id`=?"
             }
         };
@@ -147,36 +234,66 @@ impl From<(u32, DateTime<Utc>, u32, Label3party)> for FetchPayout3partyArgs {
 }
 
 impl FetchPayout3partyArgs {
+    /// return 3rd-party detail of all payouts to the merchant in the charge,
+    /// each of which comes with its sequence number
     pub(super) async fn fetch(
         self,
         conn: &mut Conn,
-    ) -> Result<Payout3partyModel, (AppErrorCode, AppRepoErrorDetail)> {
+    ) -> Result<Vec<(u16, Payout3partyModel)>, (AppErrorCode, AppRepoErrorDetail)> {
         let Self(stmt, params, label) = self;
-        match label {
+        let out = match label {
             Label3party::Stripe => {
-                let row = Self::lowlvl_fetch::<Payout3ptyStripeRowType>(stmt, params, conn).await?;
-                let arg = (row.0, row.1, Some(row.2), Some(row.3));
-                let s = Payout3partyStripeModel::from(arg);
-                Ok(Payout3partyModel::Stripe(s))
+                Self::lowlvl_fetch::<Payout3ptyStripeRowType>(stmt, params, conn)
+                    .await?
+                    .into_iter()
+                    .map(|row| {
+                        let arg = (row.1, row.2, Some(row.3), Some(row.4));
+                        let s = Payout3partyStripeModel::from(arg);
+                        (row.0, Payout3partyModel::Stripe(s))
+                    })
+                    .collect()
             }
-        }
+            Label3party::Paypal => {
+                Self::lowlvl_fetch::<Payout3ptyPaypalRowType>(stmt, params, conn)
+                    .await?
+                    .into_iter()
+                    .map(|row| {
+                        let arg = (row.1, row.2, Some(row.3), Some(row.4));
+                        let p = Payout3partyPaypalModel::from(arg);
+                        (row.0, Payout3partyModel::Paypal(p))
+                    })
+                    .collect()
+            }
+        };
+        Ok(out)
     }
     async fn lowlvl_fetch<T: FromRow + Send + 'static>(
         stmt: String,
         params: Params,
         conn: &mut Conn,
-    ) -> Result<T, (AppErrorCode, AppRepoErrorDetail)> {
+    ) -> Result<Vec<T>, (AppErrorCode, AppRepoErrorDetail)> {
         stmt.with(params)
-            .first::<T, &mut Conn>(conn)
+            .fetch::<T, &mut Conn>(conn)
             .await
             .map_err(|e| {
                 let code = AppErrorCode::RemoteDbServerFailure;
                 let detail = AppRepoErrorDetail::DatabaseQuery(e.to_string());
                 (code, detail)
-            })?
-            .ok_or((
-                AppErrorCode::DataCorruption,
-                AppRepoErrorDetail::DatabaseQuery("missing-3party".to_string()),
-            ))
+            })
     }
 } // end of impl FetchPayout3partyArgs
+
+impl From<(u32, DateTime<Utc>, u32)> for FetchPayoutLinesArgs {
+    fn from(value: (u32, DateTime<Utc>, u32)) -> Self {
+        let (buyer_id, charged_time, store_id) = value;
+        let stmt = "SELECT `seq`,`product_id`,`attr_seq`,`qty`,`amount_buyer` FROM `payout_line` \
+                   WHERE `buyer_usr_id`=? AND `charged_time`=? AND `store_id`=?";
+        let arg = vec![
+            buyer_id.into(),
+            charged_time.format(DATETIME_FMT_P0F).to_string().into(),
+            store_id.into(),
+        ];
+        let params = Params::Positional(arg);
+        Self(stmt.to_string(), params)
+    }
+}
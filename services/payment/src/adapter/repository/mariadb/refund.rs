@@ -3,9 +3,9 @@ use std::collections::HashMap;
 use std::sync::Arc;
 
 use async_trait::async_trait;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Local, Utc};
 use mysql_async::prelude::{Query, Queryable, WithParams};
-use mysql_async::{Conn, IsolationLevel, Params, TxOpts, Value as MySqlVal};
+use mysql_async::{Conn, Error as MysqlError, IsolationLevel, Params, TxOpts, Value as MySqlVal};
 use rust_decimal::Decimal;
 
 use ecommerce_common::adapter::repository::OidBytes;
@@ -16,20 +16,25 @@ use ecommerce_common::model::BaseProductIdentity;
 use crate::adapter::datastore::{AppDStoreMariaDB, AppDataStoreContext};
 use crate::adapter::processor::AbstractPaymentProcessor;
 use crate::api::web::dto::{
-    RefundCompletionOlineReqDto, RefundCompletionReqDto, RefundRejectReasonDto,
+    RefundCompletionOlineReqDto, RefundCompletionReqDto, RefundCompletionRespDto,
+    RefundRejectReasonDto,
 };
 use crate::model::{
-    ChargeBuyerModel, OLineRefundModel, OrderRefundModel, PayLineAmountModel,
-    RefundLineQtyRejectModel, RefundModelError,
+    ChargeBuyerModel, ChargeRefundMap, OLineRefundModel, OrderRefundModel, PayLineAmountModel,
+    RefundIdempotencyModel, RefundLineQtyRejectModel, RefundModelError,
+    RefundResolutionHistoryModel,
 };
 
 use super::super::{
-    AbstractRefundRepo, AppRefundRslvReqCallback, AppRefundRslvReqOkReturn, AppRepoError,
-    AppRepoErrorDetail, AppRepoErrorFnLabel,
+    AbstractRefundRepo, AppRefundIdempotentReturn, AppRefundRslvReqCallback,
+    AppRefundRslvReqOkReturn, AppRepoError, AppRepoErrorDetail, AppRepoErrorFnLabel,
 };
+use super::charge_converter::UpdateChargeLineRefundArgs;
 use super::{inner_into_parts, raw_column_to_datetime, DATETIME_FMT_P0F, DATETIME_FMT_P3F};
 
 const JOB_SCHE_LABEL: &str = "refund-req-sync";
+// see error code reference in mariadb doc
+const ER_DUP_ENTRY: u16 = 1062;
 
 type Req4RslvRowType = (
     u64,      // `product_id`
@@ -45,10 +50,35 @@ type Req4RslvRowType = (
     u32,      // `qty_aprv`
 );
 
+type ReqByOrderRowType = (
+    u32,      // `store_id`
+    u64,      // `product_id`
+    u16,      // `attr_seq`
+    MySqlVal, // `create_time`
+    Decimal,  // `amt_req_unit`
+    Decimal,  // `amt_req_total`
+    u32,      // `qty_req`
+    u32,      // `qty_rej_fraud`
+    u32,      // `qty_rej_damage`
+    Decimal,  // `amt_aprv_unit`
+    Decimal,  // `amt_aprv_total`
+    u32,      // `qty_aprv`
+);
+
 struct UpdateLastTimeSyncArgs(String, Params);
 struct InsertRequestArgs(String, Vec<Params>);
 struct FetchReqForRslvArgs(String, Params);
+struct FetchReqByOrderArgs(String, Params);
 struct UpdateResolvedReqArgs(String, Vec<Params>);
+struct InsertResolutionHistoryArgs(String, Vec<Params>);
+struct FetchIdempotentArgs(String, Params);
+struct InsertIdempotentArgs(String, Params);
+
+type IdempotentRowType = (
+    String, // `order_id`
+    String, // `req_digest`
+    String, // `resp_body`
+);
 
 impl From<DateTime<Utc>> for UpdateLastTimeSyncArgs {
     fn from(value: DateTime<Utc>) -> Self {
@@ -63,6 +93,54 @@ impl From<DateTime<Utc>> for UpdateLastTimeSyncArgs {
 }
 
 inner_into_parts!(UpdateLastTimeSyncArgs);
+inner_into_parts!(FetchIdempotentArgs);
+inner_into_parts!(InsertIdempotentArgs);
+
+impl<'a> From<(&'a OidBytes, Vec<RefundResolutionHistoryModel>)> for InsertResolutionHistoryArgs {
+    #[rustfmt::skip]
+    fn from(value: (&'a OidBytes, Vec<RefundResolutionHistoryModel>)) -> Self {
+        let (oid_b, history) = value;
+        let oid = oid_b.as_column();
+        let stmt = "INSERT INTO `refund_rslv_history`(`o_id`,`store_id`,`product_id`,`attr_seq`,\
+                    `time_req`,`time_rslv`,`buyer_usr_id`,`charged_time`,`qty_aprv`,\
+                    `amt_aprv_unit`,`amt_aprv_total`,`qty_rej_fraud`,`qty_rej_damage`) VALUES \
+                    (?,?,?,?,?,?,?,?,?,?,?,?,?)";
+        let params = history.into_iter().map(|m| {
+            let (pid, attr_seq, time_req) = m.id();
+            let (buyer_id, charged_ctime) = m.charge_id();
+            let amt_aprv = m.approved();
+            let rejected = m.rejected().inner_map();
+            let num_rej_fraud = rejected.get(&RefundRejectReasonDto::Fraudulent)
+                .unwrap_or(&0u32).to_owned();
+            let num_rej_damage = rejected.get(&RefundRejectReasonDto::Damaged)
+                .unwrap_or(&0u32).to_owned();
+            let arg = vec![
+                oid.clone().into(),
+                pid.store_id.into(),
+                pid.product_id.into(),
+                attr_seq.into(),
+                time_req.format(DATETIME_FMT_P0F).to_string().into(),
+                m.time_resolved().format(DATETIME_FMT_P3F).to_string().into(),
+                buyer_id.into(),
+                charged_ctime.format(DATETIME_FMT_P0F).to_string().into(),
+                amt_aprv.qty.into(),
+                amt_aprv.unit.into(),
+                amt_aprv.total.into(),
+                num_rej_fraud.into(),
+                num_rej_damage.into(),
+            ];
+            Params::Positional(arg)
+        }).collect::<Vec<_>>();
+        Self(stmt.to_string(), params)
+    }
+} // end of impl InsertResolutionHistoryArgs
+
+impl InsertResolutionHistoryArgs {
+    fn into_parts(self) -> (String, Vec<Params>) {
+        let Self(stmt, params) = self;
+        (stmt, params)
+    }
+}
 
 impl TryFrom<Vec<OrderRefundModel>> for InsertRequestArgs {
     type Error = Vec<(AppErrorCode, AppRepoErrorDetail)>;
@@ -157,6 +235,48 @@ impl<'a, 'b> From<(&'a OidBytes, u32, &'b [RefundCompletionOlineReqDto])> for Fe
 
 inner_into_parts!(FetchReqForRslvArgs);
 
+impl<'a> From<(u32, &'a str)> for FetchIdempotentArgs {
+    fn from(value: (u32, &'a str)) -> Self {
+        let (merchant_id, key) = value;
+        let stmt = "SELECT `order_id`,`req_digest`,`resp_body` FROM `refund_idempotency` \
+                    WHERE `store_id`=? AND `idem_key`=?"
+            .to_string();
+        let arg = vec![merchant_id.into(), key.into()];
+        Self(stmt, Params::Positional(arg))
+    }
+}
+
+impl<'a> From<(&'a RefundIdempotencyModel, String, DateTime<Utc>)> for InsertIdempotentArgs {
+    fn from(value: (&'a RefundIdempotencyModel, String, DateTime<Utc>)) -> Self {
+        let (m, resp_body, time_resolved) = value;
+        let stmt = "INSERT INTO `refund_idempotency`(`store_id`,`idem_key`,`order_id`,\
+                    `req_digest`,`resp_body`,`time_rslv`) VALUES (?,?,?,?,?,?)"
+            .to_string();
+        let arg = vec![
+            m.merchant_id().into(),
+            m.key().into(),
+            m.order_id().into(),
+            m.req_digest().into(),
+            resp_body.into(),
+            time_resolved.format(DATETIME_FMT_P3F).to_string().into(),
+        ];
+        Self(stmt, Params::Positional(arg))
+    }
+}
+
+impl<'a> From<&'a OidBytes> for FetchReqByOrderArgs {
+    fn from(value: &'a OidBytes) -> Self {
+        let stmt = "SELECT `store_id`,`product_id`,`attr_seq`,`create_time`,`amt_req_unit`,\
+                    `amt_req_total`,`qty_req`,`qty_rej_fraud`,`qty_rej_damage`,`amt_aprv_unit`,\
+                    `amt_aprv_total`,`qty_aprv` FROM `oline_refund_req` WHERE `o_id`=?"
+            .to_string();
+        let args = vec![value.as_column().into()];
+        Self(stmt, Params::Positional(args))
+    }
+}
+
+inner_into_parts!(FetchReqByOrderArgs);
+
 impl FetchReqForRslvArgs {
     fn generate_prep_statement(num_batches: usize) -> String {
         assert_ne!(num_batches, 0);
@@ -246,6 +366,32 @@ impl TryFrom<(u32, Req4RslvRowType)> for OLineRefundModel {
     } // end of fn try-from
 } // end of impl OLineRefundModel
 
+impl<'a> TryFrom<(&'a OidBytes, Vec<ReqByOrderRowType>)> for OrderRefundModel {
+    type Error = (AppErrorCode, AppRepoErrorDetail);
+    #[rustfmt::skip]
+    fn try_from(value: (&'a OidBytes, Vec<ReqByOrderRowType>)) -> Result<Self, Self::Error> {
+        let (oid_b, rows) = value;
+        let id = OidBytes::to_app_oid(oid_b.as_column())
+            .map_err(|(code, msg)| (code, AppRepoErrorDetail::OrderIDparse(msg)))?;
+        let lines = rows.into_iter()
+            .map(|row| {
+                let (
+                    store_id, product_id, attr_seq, time_issued, amt_req_unit,
+                    amt_req_total, qty_req, qty_rej_fraud, qty_rej_damage,
+                    amt_aprv_unit, amt_aprv_total, qty_aprv,
+                ) = row;
+                let inner = (
+                    product_id, attr_seq, time_issued, amt_req_unit, amt_req_total,
+                    qty_req, qty_rej_fraud, qty_rej_damage, amt_aprv_unit,
+                    amt_aprv_total, qty_aprv,
+                );
+                OLineRefundModel::try_from((store_id, inner))
+            })
+            .collect::<Result<Vec<_>, Self::Error>>()?;
+        Ok(Self::from((id, lines)))
+    }
+}
+
 impl<'a> TryFrom<(&'a OidBytes, u32, Vec<Req4RslvRowType>)> for OrderRefundModel {
     type Error = (AppErrorCode, AppRepoErrorDetail);
     fn try_from(value: (&'a OidBytes, u32, Vec<Req4RslvRowType>)) -> Result<Self, Self::Error> {
@@ -437,14 +583,81 @@ impl AbstractRefundRepo for MariaDbRefundRepo {
         })
     } // end of fn save_request
 
+    async fn fetch_request_by_order(&self, oid: &str) -> Result<OrderRefundModel, AppRepoError> {
+        let oid_b = OidBytes::try_from(oid).map_err(|(code, msg)| {
+            self._map_log_err_common(
+                code,
+                AppRepoErrorDetail::OrderIDparse(msg),
+                AppRepoErrorFnLabel::RefundFetchReqByOrder,
+            )
+        })?;
+        let mut conn = self._dstore.acquire().await.map_err(|e| {
+            self._map_log_err_common(
+                AppErrorCode::DatabaseServerBusy,
+                AppRepoErrorDetail::DataStore(e),
+                AppRepoErrorFnLabel::RefundFetchReqByOrder,
+            )
+        })?;
+        let (stmt, params) = FetchReqByOrderArgs::from(&oid_b).into_parts();
+        let rows = stmt
+            .with(params)
+            .fetch::<ReqByOrderRowType, &mut Conn>(&mut conn)
+            .await
+            .map_err(|e| {
+                self._map_log_err_common(
+                    AppErrorCode::RemoteDbServerFailure,
+                    AppRepoErrorDetail::DatabaseQuery(e.to_string()),
+                    AppRepoErrorFnLabel::RefundFetchReqByOrder,
+                )
+            })?;
+        OrderRefundModel::try_from((&oid_b, rows)).map_err(|(code, detail)| {
+            self._map_log_err_common(code, detail, AppRepoErrorFnLabel::RefundFetchReqByOrder)
+        })
+    } // end of fn fetch_request_by_order
+
+    async fn fetch_idempotent_result(
+        &self,
+        merchant_id: u32,
+        key: &str,
+    ) -> Result<AppRefundIdempotentReturn, AppRepoError> {
+        let mut conn = self._dstore.acquire().await.map_err(|e| {
+            self._map_log_err_common(
+                AppErrorCode::DatabaseServerBusy,
+                AppRepoErrorDetail::DataStore(e),
+                AppRepoErrorFnLabel::RefundFetchIdempotent,
+            )
+        })?;
+        let (stmt, params) = FetchIdempotentArgs::from((merchant_id, key)).into_parts();
+        let result = stmt
+            .with(params)
+            .first::<IdempotentRowType, &mut Conn>(&mut conn)
+            .await
+            .map_err(|e| {
+                self._map_log_err_common(
+                    AppErrorCode::RemoteDbServerFailure,
+                    AppRepoErrorDetail::DatabaseQuery(e.to_string()),
+                    AppRepoErrorFnLabel::RefundFetchIdempotent,
+                )
+            })?;
+        if let Some((oid, req_digest, resp_body)) = result {
+            // the body is returned as it is, so the merchant gets exactly the
+            // same response as the first time the key was used
+            let m = RefundIdempotencyModel::from((key.to_string(), merchant_id, oid, req_digest));
+            Ok(Some((m, resp_body)))
+        } else {
+            Ok(None)
+        }
+    } // end of fn fetch_idempotent_result
+
     async fn resolve_request(
         &self,
         merchant_id: u32,
         cmplt_req: RefundCompletionReqDto,
+        idempotency: Option<RefundIdempotencyModel>,
         charge_ms: Vec<ChargeBuyerModel>,
         processor: Arc<Box<dyn AbstractPaymentProcessor>>,
         usr_cb: AppRefundRslvReqCallback,
-    ) -> Result<AppRefundRslvReqOkReturn, AppRepoError> {
+    ) -> Result<(AppRefundRslvReqOkReturn, String), AppRepoError> {
         if cmplt_req.lines.is_empty() {
             let me = RefundModelError::EmptyResolutionRequest(merchant_id);
             return Err(AppRepoError {
@@ -515,6 +728,70 @@ impl AbstractRefundRepo for MariaDbRefundRepo {
             )
         })?;
 
+        // keep track of each round of resolution for merchant reporting
+        let time_resolved = Local::now().to_utc();
+        let resolved = cb_res
+            .iter()
+            .filter_map(|r| r.as_ref().ok())
+            .collect::<Vec<_>>();
+        let history = resolved
+            .iter()
+            .flat_map(|m| m.to_history(time_resolved))
+            .collect::<Vec<_>>();
+        let resp_body = {
+            let resp = RefundCompletionRespDto::from(resolved.as_slice());
+            serde_json::to_string(&resp).map_err(|e| {
+                self._map_log_err_common(
+                    AppErrorCode::DataCorruption,
+                    AppRepoErrorDetail::DataRowParse(e.to_string()),
+                    AppRepoErrorFnLabel::ResolveRefundReq,
+                )
+            })?
+        };
+        // refunded amount of charge lines has to be committed along with the
+        // resolved request lines and idempotency key, otherwise a retry with
+        // the same key would replay the response without updating the charges
+        if !resolved.is_empty() {
+            let cl_map = ChargeRefundMap::build(resolved.iter().copied());
+            let (stmt, params) = UpdateChargeLineRefundArgs::from(cl_map).into_parts();
+            tx.exec_batch(stmt, params).await.map_err(|e| {
+                self._map_log_err_common(
+                    AppErrorCode::RemoteDbServerFailure,
+                    AppRepoErrorDetail::DatabaseExec(e.to_string()),
+                    AppRepoErrorFnLabel::ResolveRefundReq,
+                )
+            })?;
+        }
+        // the key is kept only when anything is resolved in this round, so the
+        // merchant can retry with the same key if all the processors failed
+        if let (Some(idem_m), false) = (idempotency.as_ref(), history.is_empty()) {
+            let arg = (idem_m, resp_body.clone(), time_resolved);
+            let (stmt, params) = InsertIdempotentArgs::from(arg).into_parts();
+            tx.exec_drop(stmt, params).await.map_err(|e| {
+                let (code, detail) = match &e {
+                    MysqlError::Server(se) if se.code == ER_DUP_ENTRY => (
+                        AppErrorCode::InvalidInput,
+                        AppRepoErrorDetail::IdempotencyKeyInUse(idem_m.key().to_string()),
+                    ),
+                    _others => (
+                        AppErrorCode::RemoteDbServerFailure,
+                        AppRepoErrorDetail::DatabaseExec(e.to_string()),
+                    ),
+                };
+                self._map_log_err_common(code, detail, AppRepoErrorFnLabel::ResolveRefundReq)
+            })?;
+        }
+        if !history.is_empty() {
+            let (stmt, params) = InsertResolutionHistoryArgs::from((&oid_b, history)).into_parts();
+            tx.exec_batch(stmt, params).await.map_err(|e| {
+                self._map_log_err_common(
+                    AppErrorCode::RemoteDbServerFailure,
+                    AppRepoErrorDetail::DatabaseExec(e.to_string()),
+                    AppRepoErrorFnLabel::ResolveRefundReq,
+                )
+            })?;
+        }
+
         tx.commit().await.map_err(|e| {
             self._map_log_err_common(
                 AppErrorCode::RemoteDbServerFailure,
@@ -522,6 +799,6 @@ impl AbstractRefundRepo for MariaDbRefundRepo {
                 AppRepoErrorFnLabel::ResolveRefundReq,
             )
         })?;
-        Ok(cb_res)
+        Ok((cb_res, resp_body))
     } // end of fn resolve_request
 } // end of impl MariaDbRefundRepo
@@ -4,6 +4,7 @@ use std::sync::Arc;
 
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
+use futures_util::StreamExt;
 use mysql_async::prelude::{Query, WithParams};
 use mysql_async::{Conn, Params, Value as MySqlVal};
 use rust_decimal::Decimal;
@@ -12,15 +13,19 @@ use ecommerce_common::adapter::repository::OidBytes;
 use ecommerce_common::api::dto::CurrencyDto;
 use ecommerce_common::error::AppErrorCode;
 use ecommerce_common::logging::{app_log_event, AppLogLevel};
+use ecommerce_common::model::BaseProductIdentity;
 
-use super::{inner_into_parts, raw_column_to_datetime, DATETIME_FMT_P0F};
+use super::{inner_into_parts, raw_column_to_datetime, DATETIME_FMT_P0F, DATETIME_FMT_P3F};
 use crate::adapter::datastore::{AppDStoreMariaDB, AppDataStoreContext};
 use crate::adapter::repository::{
     AbstractReportingRepo, AppRepoError, AppRepoErrorDetail, AppRepoErrorFnLabel,
 };
-use crate::api::web::dto::ReportTimeRangeDto;
+use crate::api::web::dto::{RefundRejectReasonDto, ReportTimeRangeDto};
 use crate::model::{
-    ChargeBuyerMetaModel, ChargeBuyerModel, ChargeLineBuyerModel, OrderCurrencySnapshot,
+    ChargeBuyerMetaModel, ChargeBuyerModel, ChargeLineBuyerModel, Label3party,
+    OrderCurrencySnapshot, PayLineAmountModel, Payout3partyModel, Payout3partyPaypalModel,
+    Payout3partyStripeModel, PayoutAmountModel, PayoutModel, RefundLineQtyRejectModel,
+    RefundResolutionHistoryModel,
 };
 
 #[rustfmt::skip]
@@ -48,29 +53,94 @@ type ChargeMetaRowType = (
 #[rustfmt::skip]
 type OrderCurrencyRowType = (Vec<u8>,u32, String, Decimal);
 
-type InnerChargeLineMap = HashMap<(u32, DateTime<Utc>), Vec<ChargeLineBuyerModel>>;
+type RefundHistoryRowType = (
+    Vec<u8>,  // `o_id`
+    u32,      // `buyer_usr_id`
+    MySqlVal, // `charged_time`
+    u64,      // `product_id`
+    u16,      // `attr_seq`
+    MySqlVal, // `time_req`
+    MySqlVal, // `time_rslv`
+    Decimal,  // `amt_aprv_unit`
+    Decimal,  // `amt_aprv_total`
+    u32,      // `qty_aprv`
+    u32,      // `qty_rej_fraud`
+    u32,      // `qty_rej_damage`
+);
+
+type PayoutMetaRowType = (
+    u32,      // `buyer_usr_id`
+    MySqlVal, // `charged_time`
+    u16,      // `seq`
+    MySqlVal, // `create_time`
+    u32,      // `storestaff_usr_id`
+    Vec<u8>,  // `order_id`
+    Decimal,  // `amount_buyer`
+    String,   // `label3party`
+    Decimal,  // `fee_buyer`
+);
+
+// key of payout, and detail of 3rd party, the 3rd-party detail consists of
+// 3 strings which are identifiers specific to the processor, and the amount
+// transferred in base currency
+#[rustfmt::skip]
+type Payout3partyRowType = (
+    u32, MySqlVal, u16, String, String, String, Decimal,
+);
+
+// payout key (buyer ID, charged time, sequence) to its 3rd-party detail
+type InnerPayout3partyMap = HashMap<(u32, DateTime<Utc>, u16), Payout3partyModel>;
+
+type InnerChargeId = (u32, DateTime<Utc>); // buyer-usr-id, create-time
+type InnerChargeLineMap = HashMap<InnerChargeId, Vec<ChargeLineBuyerModel>>;
 type InnerOrderCurrencyMap = HashMap<String, HashMap<u32, OrderCurrencySnapshot>>;
 
 struct FetchChargeLineArgs(String, Params);
 struct FetchChargeMetaArgs(String, Params);
 struct FetchCurrencySnapshotArgs(String, Params);
+struct FetchRefundHistoryArgs(String, Params);
+struct FetchPayoutMetaArgs(String, Params);
+struct FetchPayout3partyArgs(String, Params);
+
+type FetchChargeLineCvtArgs<'a> = (
+    u32,
+    &'a ReportTimeRangeDto,
+    Option<(u32, DateTime<Utc>)>,
+    u16,
+);
 
 #[rustfmt::skip]
-impl<'a> From<(u32, &'a ReportTimeRangeDto)> for FetchChargeLineArgs {
-    fn from(value: (u32, &'a ReportTimeRangeDto)) -> Self {
-        let (store_id, t_range) = value;
-        let stmt = "SELECT `buyer_id`,`create_time`,`product_id`,`attr_seq`,`amt_orig_unit`,\
-                    `amt_orig_total`,`qty_orig`,`amt_rfnd_unit`,`amt_rfnd_total`,`qty_rfnd`,\
-                    `qty_rej` FROM `charge_line` WHERE `store_id`=?  AND `create_time` >= ? \
-                    AND `create_time` <= ?"
-            .to_string();
-        let args = vec![
+impl<'a> From<FetchChargeLineCvtArgs<'a>> for FetchChargeLineArgs {
+    fn from(value: FetchChargeLineCvtArgs<'a>) -> Self {
+        let (store_id, t_range, cursor, limit) = value;
+        let mut args = vec![
             store_id.into(),
             t_range.start_after.format(DATETIME_FMT_P0F).to_string().into(),
             t_range.end_before.format(DATETIME_FMT_P0F).to_string().into(),
         ];
-        let params = Params::Positional(args);
-        Self(stmt, params)
+        let cond_cursor = if let Some((buyer_id, ctime)) = cursor {
+            let ctime = ctime.format(DATETIME_FMT_P0F).to_string();
+            args.extend([ctime.clone().into(), ctime.into(), buyer_id.into()]);
+            "AND (`create_time` > ? OR (`create_time` = ? AND `buyer_id` > ?))"
+        } else {
+            ""
+        };
+        let cols = "`buyer_id`,`create_time`,`product_id`,`attr_seq`,`amt_orig_unit`,\
+             `amt_orig_total`,`qty_orig`,`amt_rfnd_unit`,`amt_rfnd_total`,`qty_rfnd`,`qty_rej`";
+        let cond = format!(
+            "`store_id`=? AND `create_time` >= ? AND `create_time` <= ? {cond_cursor}"
+        );
+        // the limit applies to number of charges, not number of lines,
+        // so the charges are grouped in a derived table first
+        args.push(limit.into());
+        args.push(store_id.into());
+        let stmt = format!(
+            "SELECT {cols} FROM `charge_line` INNER JOIN (SELECT `buyer_id`,`create_time` \
+             FROM `charge_line` WHERE {cond} GROUP BY `buyer_id`,`create_time` ORDER BY \
+             `create_time` ASC, `buyer_id` ASC LIMIT ?) AS `c` USING (`buyer_id`,`create_time`) \
+             WHERE `store_id`=? ORDER BY `create_time` ASC, `buyer_id` ASC"
+        );
+        Self(stmt, Params::Positional(args))
     }
 }
 
@@ -137,6 +207,114 @@ impl FetchCurrencySnapshotArgs {
 
 inner_into_parts!(FetchCurrencySnapshotArgs);
 
+#[rustfmt::skip]
+impl<'a> From<(u32, &'a ReportTimeRangeDto)> for FetchRefundHistoryArgs {
+    fn from(value: (u32, &'a ReportTimeRangeDto)) -> Self {
+        let (store_id, t_range) = value;
+        let stmt = "SELECT `o_id`,`buyer_usr_id`,`charged_time`,`product_id`,`attr_seq`,\
+                    `time_req`,`time_rslv`,`amt_aprv_unit`,`amt_aprv_total`,`qty_aprv`,\
+                    `qty_rej_fraud`,`qty_rej_damage` FROM `refund_rslv_history` WHERE \
+                    `store_id`=? AND `time_rslv` >= ? AND `time_rslv` <= ?"
+            .to_string();
+        let args = vec![
+            store_id.into(),
+            t_range.start_after.format(DATETIME_FMT_P3F).to_string().into(),
+            t_range.end_before.format(DATETIME_FMT_P3F).to_string().into(),
+        ];
+        Self(stmt, Params::Positional(args))
+    }
+}
+
+/// payouts are loaded either by the time they were transferred, or by the
+/// charges they were captured from
+enum PayoutFilter<'a> {
+    TimeRange(&'a ReportTimeRangeDto),
+    Charges(&'a [InnerChargeId]),
+}
+
+impl PayoutFilter<'_> {
+    /// SQL condition on the table `payout_meta` with the given alias prefix,
+    /// followed by the positional arguments
+    fn sql_cond(&self, prefix: &str) -> (String, Vec<MySqlVal>) {
+        match self {
+            Self::TimeRange(t_range) => {
+                let cond = format!("{prefix}`create_time` >= ? AND {prefix}`create_time` <= ?");
+                let args = vec![
+                    t_range
+                        .start_after
+                        .format(DATETIME_FMT_P0F)
+                        .to_string()
+                        .into(),
+                    t_range
+                        .end_before
+                        .format(DATETIME_FMT_P0F)
+                        .to_string()
+                        .into(),
+                ];
+                (cond, args)
+            }
+            Self::Charges(charge_ids) => {
+                assert!(!charge_ids.is_empty());
+                let cond = charge_ids
+                    .iter()
+                    .map(|_| format!("({prefix}`buyer_usr_id`=? AND {prefix}`charged_time`=?)"))
+                    .collect::<Vec<_>>()
+                    .join("OR");
+                let args = charge_ids
+                    .iter()
+                    .flat_map(|(buyer_id, ctime)| {
+                        vec![
+                            (*buyer_id).into(),
+                            ctime.format(DATETIME_FMT_P0F).to_string().into(),
+                        ]
+                    })
+                    .collect::<Vec<_>>();
+                (format!("({cond})"), args)
+            }
+        }
+    }
+}
+
+impl<'a> From<(u32, &'a PayoutFilter<'a>)> for FetchPayoutMetaArgs {
+    fn from(value: (u32, &'a PayoutFilter<'a>)) -> Self {
+        let (store_id, filter) = value;
+        let (cond, args) = filter.sql_cond("");
+        let stmt = format!(
+            "SELECT `buyer_usr_id`,`charged_time`,`seq`,`create_time`,`storestaff_usr_id`,\
+             `order_id`,`amount_buyer`,`label3party`,`fee_buyer` FROM `payout_meta` \
+             WHERE `store_id`=? AND {cond}"
+        );
+        let mut params = vec![store_id.into()];
+        params.extend(args);
+        Self(stmt, Params::Positional(params))
+    }
+}
+
+#[rustfmt::skip]
+impl<'a> From<(u32, &'a PayoutFilter<'a>, Label3party)> for FetchPayout3partyArgs {
+    fn from(value: (u32, &'a PayoutFilter<'a>, Label3party)) -> Self {
+        let (store_id, filter, label3pt) = value;
+        let (table, cols) = match label3pt {
+            Label3party::Stripe => ("payout_3party_stripe", "`a`.`tx_grp`,`a`.`acct_id`,`a`.`transfer_id`"),
+            Label3party::Paypal => ("payout_3party_paypal", "`a`.`ref_id`,`a`.`payee_id`,`a`.`batch_id`"),
+        };
+        let (cond, args) = filter.sql_cond("`b`.");
+        let stmt = format!(
+            "SELECT `a`.`buyer_usr_id`,`a`.`charged_time`,`a`.`seq`,{cols},`a`.`amount` FROM \
+             `{table}` AS `a` INNER JOIN `payout_meta` AS `b` ON `a`.`buyer_usr_id`=`b`.`buyer_usr_id` \
+             AND `a`.`charged_time`=`b`.`charged_time` AND `a`.`store_id`=`b`.`store_id` AND \
+             `a`.`seq`=`b`.`seq` WHERE `b`.`store_id`=? AND {cond}"
+        );
+        let mut params = vec![store_id.into()];
+        params.extend(args);
+        Self(stmt, Params::Positional(params))
+    }
+}
+
+inner_into_parts!(FetchRefundHistoryArgs);
+inner_into_parts!(FetchPayoutMetaArgs);
+inner_into_parts!(FetchPayout3partyArgs);
+
 pub struct MariadbReportingRepo {
     dstore_pri: Arc<AppDStoreMariaDB>,
     dstore_rep: Arc<AppDStoreMariaDB>,
@@ -161,52 +339,57 @@ impl MariadbReportingRepo {
     }
 
     #[rustfmt::skip]
-    fn parse_charge_lines(
+    fn parse_charge_line(
         store_id: u32,
-        rows: Vec<ChargeLineRowType>,
-    ) -> Result<InnerChargeLineMap, (AppErrorCode, AppRepoErrorDetail)> {
-        let mut errors = Vec::new();
-        let kv_pairs = rows.into_iter()
-            .filter_map(|row| {
-                let (
-                    buyer_usr_id, ctime_raw, product_id, attr_set_seq,
-                    amt_orig_unit, amt_orig_total, qty_orig,
-                    amt_rfnd_unit, amt_rfnd_total, qty_rfnd, num_rejected,
-                ) = row;
-                let d = (
-                    store_id, product_id, attr_set_seq,
-                    amt_orig_unit, amt_orig_total, qty_orig,
-                    amt_rfnd_unit, amt_rfnd_total, qty_rfnd, num_rejected,
-                );
-                let result0 = raw_column_to_datetime(ctime_raw, 0);
-                let result1 = ChargeLineBuyerModel::try_from(d);
-                match (result0, result1) {
-                    (Ok(ctime), Ok(cline)) => Some(((buyer_usr_id, ctime), cline)),
-                    (Ok(_), Err(detail)) => {
-                        let code = AppErrorCode::DataCorruption;
-                        errors.push((code, detail));
-                        None
-                    }
-                    (Err(reason), _) => {
-                        errors.push(reason);
-                        None
-                    }
-                }
-            })
-            .collect::<Vec<_>>();
-        if !errors.is_empty() {
-            return Err(errors.remove(0));
+        row: ChargeLineRowType,
+    ) -> Result<(InnerChargeId, ChargeLineBuyerModel), (AppErrorCode, AppRepoErrorDetail)> {
+        let (
+            buyer_usr_id, ctime_raw, product_id, attr_set_seq,
+            amt_orig_unit, amt_orig_total, qty_orig,
+            amt_rfnd_unit, amt_rfnd_total, qty_rfnd, num_rejected,
+        ) = row;
+        let d = (
+            store_id, product_id, attr_set_seq,
+            amt_orig_unit, amt_orig_total, qty_orig,
+            amt_rfnd_unit, amt_rfnd_total, qty_rfnd, num_rejected,
+        );
+        let ctime = raw_column_to_datetime(ctime_raw, 0)?;
+        let cline = ChargeLineBuyerModel::try_from(d)
+            .map_err(|detail| (AppErrorCode::DataCorruption, detail))?;
+        Ok(((buyer_usr_id, ctime), cline))
+    }
+
+    /// read charge lines row by row from the stream, group them by charge
+    async fn stream_charge_lines(
+        &self,
+        conn: &mut Conn,
+        store_id: u32,
+        stmt: String,
+        params: Params,
+    ) -> Result<InnerChargeLineMap, AppRepoError> {
+        let mut line_stream = stmt
+            .with(params)
+            .stream::<ChargeLineRowType, &mut Conn>(conn)
+            .await
+            .map_err(|e| {
+                let code = AppErrorCode::RemoteDbServerFailure;
+                let detail = AppRepoErrorDetail::DatabaseQuery(e.to_string());
+                self.map_log_err((code, detail), AppRepoErrorFnLabel::ReportChargeByMerchant)
+            })?;
+        let mut out: InnerChargeLineMap = HashMap::new();
+        while let Some(result) = line_stream.next().await {
+            let row = result.map_err(|e| {
+                let code = AppErrorCode::RemoteDbServerFailure;
+                let detail = AppRepoErrorDetail::DatabaseQuery(e.to_string());
+                self.map_log_err((code, detail), AppRepoErrorFnLabel::ReportChargeByMerchant)
+            })?;
+            let (k, cline) = Self::parse_charge_line(store_id, row).map_err(|reason| {
+                self.map_log_err(reason, AppRepoErrorFnLabel::ReportChargeByMerchant)
+            })?;
+            out.entry(k).or_default().push(cline);
         }
-        let mut out = HashMap::new();
-        kv_pairs
-            .into_iter()
-            .map(|(k, v)| {
-                let entry = out.entry(k).or_insert(Vec::new());
-                entry.push(v);
-            })
-            .count();
         Ok(out)
-    } // end of parse_charge_lines
+    } // end of fn stream_charge_lines
 
     #[rustfmt::skip]
     fn parse_charge_meta(
@@ -252,6 +435,12 @@ impl MariadbReportingRepo {
             let buyer_id = row.0;
             (oid, buyer_id)
         });
+        Self::gen_sql_currency_snapshot_common(store_id, iter)
+    }
+    fn gen_sql_currency_snapshot_common<'a>(
+        store_id: u32,
+        iter: impl Iterator<Item = (&'a Vec<u8>, u32)>,
+    ) -> (String, Params) {
         let hset = HashSet::<(&Vec<u8>, u32)>::from_iter(iter);
         let oid_buyer_pairs = hset.into_iter().collect::<Vec<_>>();
         FetchCurrencySnapshotArgs::from((store_id, oid_buyer_pairs)).into_parts()
@@ -296,7 +485,7 @@ impl MariadbReportingRepo {
         currency_snapshots: InnerOrderCurrencyMap,
     ) -> Result<Vec<ChargeBuyerModel>, AppRepoErrorDetail> {
         let mut errors = Vec::new();
-        let charge_ms = c_metas
+        let mut charge_ms = c_metas
             .into_iter()
             .filter_map(|meta| {
                 let k = (meta.owner(), *meta.create_time());
@@ -326,12 +515,229 @@ impl MariadbReportingRepo {
             .collect::<Vec<_>>();
         assert!(cline_map.is_empty()); // TODO, error handling
         if errors.is_empty() {
+            charge_ms.sort_by_key(|c| (*c.meta.create_time(), c.meta.owner()));
             Ok(charge_ms)
         } else {
             Err(errors.remove(0))
         }
     }
 
+    /// look up currency snapshots of buyer and merchant in the given order,
+    /// the snapshots are returned in the order : buyer, merchant
+    fn get_currency_pair(
+        snapshots: &InnerOrderCurrencyMap,
+        oid_raw: &[u8],
+        buyer_id: u32,
+        store_id: u32,
+    ) -> Result<(String, [OrderCurrencySnapshot; 2]), (AppErrorCode, AppRepoErrorDetail)> {
+        let oid = OidBytes::to_app_oid(oid_raw.to_vec())
+            .map_err(|(code, msg)| (code, AppRepoErrorDetail::OrderIDparse(msg)))?;
+        let found = snapshots
+            .get(&oid)
+            .and_then(|m| m.get(&buyer_id).zip(m.get(&store_id)));
+        if let Some((c_buyer, c_merc)) = found {
+            let pair = [c_buyer.clone(), c_merc.clone()];
+            Ok((oid, pair))
+        } else {
+            let msg = format!("missing-currency-snapshot, oid: {oid}");
+            let detail = AppRepoErrorDetail::DataRowParse(msg);
+            Err((AppErrorCode::DataCorruption, detail))
+        }
+    }
+
+    #[rustfmt::skip]
+    fn parse_refund_history(
+        store_id: u32,
+        row: RefundHistoryRowType,
+        snapshots: &InnerOrderCurrencyMap,
+    ) -> Result<RefundResolutionHistoryModel, (AppErrorCode, AppRepoErrorDetail)> {
+        let (
+            oid_raw, buyer_usr_id, charged_time, product_id, attr_seq,
+            time_req, time_rslv, amt_aprv_unit, amt_aprv_total, qty_aprv,
+            qty_rej_fraud, qty_rej_damage,
+        ) = row;
+        let (_oid, currency_pair) =
+            Self::get_currency_pair(snapshots, &oid_raw, buyer_usr_id, store_id)?;
+        let charged_ctime = raw_column_to_datetime(charged_time, 0)?;
+        let time_req = raw_column_to_datetime(time_req, 0)?;
+        let time_rslv = raw_column_to_datetime(time_rslv, 3)?;
+        let pid = BaseProductIdentity { store_id, product_id };
+        let approved = PayLineAmountModel {
+            unit: amt_aprv_unit, total: amt_aprv_total, qty: qty_aprv,
+        };
+        let rejected = {
+            let rejmap = HashMap::from([
+                (RefundRejectReasonDto::Fraudulent, qty_rej_fraud),
+                (RefundRejectReasonDto::Damaged, qty_rej_damage),
+            ]);
+            RefundLineQtyRejectModel::from(&rejmap)
+        };
+        let arg = (
+            pid, attr_seq, time_req, time_rslv, (buyer_usr_id, charged_ctime),
+            approved, rejected, currency_pair,
+        );
+        Ok(RefundResolutionHistoryModel::from(arg))
+    } // end of fn parse_refund_history
+
+    fn parse_payout_3party(
+        label3pt: Label3party,
+        rows: Vec<Payout3partyRowType>,
+    ) -> Result<InnerPayout3partyMap, (AppErrorCode, AppRepoErrorDetail)> {
+        let mut out = HashMap::new();
+        for row in rows {
+            let (buyer_id, charged_time, seq, id0, id1, id2, amount) = row;
+            let charged_ctime = raw_column_to_datetime(charged_time, 0)?;
+            let arg = (id0, id1, Some(id2), Some(amount));
+            let m = match label3pt {
+                Label3party::Stripe => {
+                    Payout3partyModel::Stripe(Payout3partyStripeModel::from(arg))
+                }
+                Label3party::Paypal => {
+                    Payout3partyModel::Paypal(Payout3partyPaypalModel::from(arg))
+                }
+            };
+            let _ = out.insert((buyer_id, charged_ctime, seq), m);
+        }
+        Ok(out)
+    }
+
+    #[rustfmt::skip]
+    fn parse_payout(
+        store_id: u32,
+        row: PayoutMetaRowType,
+        snapshots: &InnerOrderCurrencyMap,
+        p3pty_map: &mut InnerPayout3partyMap,
+    ) -> Result<PayoutModel, (AppErrorCode, AppRepoErrorDetail)> {
+        let (
+            buyer_usr_id, charged_time, seq, create_time, storestaff_id,
+            oid_raw, amount_buyer, _label3pt, fee_buyer,
+        ) = row;
+        let (oid, [currency_buyer, currency_merc]) =
+            Self::get_currency_pair(snapshots, &oid_raw, buyer_usr_id, store_id)?;
+        let charged_ctime = raw_column_to_datetime(charged_time, 0)?;
+        let capture_time = raw_column_to_datetime(create_time, 0)?;
+        let p3pty = p3pty_map.remove(&(buyer_usr_id, charged_ctime, seq))
+            .ok_or(format!("missing-3party, seq:{seq}"))
+            .map_err(|msg| (AppErrorCode::DataCorruption, AppRepoErrorDetail::DataRowParse(msg)))?;
+        let arg = (amount_buyer, fee_buyer, currency_merc, currency_buyer);
+        let amount = PayoutAmountModel::try_from(arg)
+            .map_err(|e| {
+                let msg = format!("payout-model: {:?}", e);
+                (AppErrorCode::DataCorruption, AppRepoErrorDetail::DataRowParse(msg))
+            })?;
+        let arg = (
+            store_id, capture_time, buyer_usr_id, charged_ctime, oid,
+            storestaff_id, seq, amount, Vec::new(), p3pty,
+        );
+        Ok(PayoutModel::from(arg))
+    } // end of fn parse_payout
+
+    async fn fetch_currency_snapshots<'a>(
+        conn: &mut Conn,
+        store_id: u32,
+        iter: impl Iterator<Item = (&'a Vec<u8>, u32)>,
+    ) -> Result<InnerOrderCurrencyMap, (AppErrorCode, AppRepoErrorDetail)> {
+        let (stmt, params) = Self::gen_sql_currency_snapshot_common(store_id, iter);
+        let rows = stmt
+            .with(params)
+            .fetch::<OrderCurrencyRowType, &mut Conn>(conn)
+            .await
+            .map_err(|e| {
+                let code = AppErrorCode::RemoteDbServerFailure;
+                let detail = AppRepoErrorDetail::DatabaseQuery(e.to_string());
+                (code, detail)
+            })?;
+        Self::parse_currency_snapshots(rows)
+    }
+
+    async fn _fetch_refunds_by_merchant(
+        &self,
+        store_id: u32,
+        t_range: ReportTimeRangeDto,
+    ) -> Result<Vec<RefundResolutionHistoryModel>, (AppErrorCode, AppRepoErrorDetail)> {
+        let mut conn = self.dstore_pri.acquire().await.map_err(|e| {
+            (
+                AppErrorCode::DatabaseServerBusy,
+                AppRepoErrorDetail::DataStore(e),
+            )
+        })?;
+        let (stmt, params) = FetchRefundHistoryArgs::from((store_id, &t_range)).into_parts();
+        let rows = stmt
+            .with(params)
+            .fetch::<RefundHistoryRowType, &mut Conn>(&mut conn)
+            .await
+            .map_err(|e| {
+                let code = AppErrorCode::RemoteDbServerFailure;
+                (code, AppRepoErrorDetail::DatabaseQuery(e.to_string()))
+            })?;
+        if rows.is_empty() {
+            return Ok(Vec::new());
+        }
+        let iter = rows.iter().map(|row| (&row.0, row.1));
+        let snapshots = Self::fetch_currency_snapshots(&mut conn, store_id, iter).await?;
+        rows.into_iter()
+            .map(|row| Self::parse_refund_history(store_id, row, &snapshots))
+            .collect()
+    }
+
+    async fn _fetch_payouts(
+        &self,
+        store_id: u32,
+        filter: PayoutFilter<'_>,
+    ) -> Result<Vec<PayoutModel>, (AppErrorCode, AppRepoErrorDetail)> {
+        let mut conn = self.dstore_pri.acquire().await.map_err(|e| {
+            (
+                AppErrorCode::DatabaseServerBusy,
+                AppRepoErrorDetail::DataStore(e),
+            )
+        })?;
+        let (stmt, params) = FetchPayoutMetaArgs::from((store_id, &filter)).into_parts();
+        let rows = stmt
+            .with(params)
+            .fetch::<PayoutMetaRowType, &mut Conn>(&mut conn)
+            .await
+            .map_err(|e| {
+                let code = AppErrorCode::RemoteDbServerFailure;
+                (code, AppRepoErrorDetail::DatabaseQuery(e.to_string()))
+            })?;
+        if rows.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut labels = Vec::new();
+        for row in rows.iter() {
+            let label3pt = Label3party::try_from(row.7.as_str()).map_err(|s| {
+                let detail = AppRepoErrorDetail::PayMethodUnsupport(s.to_string());
+                (AppErrorCode::DataCorruption, detail)
+            })?;
+            if !labels
+                .iter()
+                .any(|v: &Label3party| v.to_string() == label3pt.to_string())
+            {
+                labels.push(label3pt);
+            }
+        }
+        let mut p3pty_map = HashMap::new();
+        for label3pt in labels {
+            let (stmt, params) =
+                FetchPayout3partyArgs::from((store_id, &filter, label3pt)).into_parts();
+            let rows_3pt = stmt
+                .with(params)
+                .fetch::<Payout3partyRowType, &mut Conn>(&mut conn)
+                .await
+                .map_err(|e| {
+                    let code = AppErrorCode::RemoteDbServerFailure;
+                    (code, AppRepoErrorDetail::DatabaseQuery(e.to_string()))
+                })?;
+            let parsed = Self::parse_payout_3party(label3pt, rows_3pt)?;
+            p3pty_map.extend(parsed);
+        }
+        let iter = rows.iter().map(|row| (&row.5, row.0));
+        let snapshots = Self::fetch_currency_snapshots(&mut conn, store_id, iter).await?;
+        rows.into_iter()
+            .map(|row| Self::parse_payout(store_id, row, &snapshots, &mut p3pty_map))
+            .collect()
+    }
+
     #[rustfmt::skip]
     fn map_log_err(
         &self,
@@ -352,6 +758,8 @@ impl AbstractReportingRepo for MariadbReportingRepo {
         &self,
         store_id: u32,
         t_range: ReportTimeRangeDto,
+        cursor: Option<(u32, DateTime<Utc>)>,
+        limit: u16,
     ) -> Result<Vec<ChargeBuyerModel>, AppRepoError> {
         let mut conn_rep = self.dstore_rep.acquire().await.map_err(|e| {
             let code = AppErrorCode::DatabaseServerBusy;
@@ -359,26 +767,12 @@ impl AbstractReportingRepo for MariadbReportingRepo {
             let fn_label = AppRepoErrorFnLabel::ReportChargeByMerchant;
             self.map_log_err((code, detail), fn_label)
         })?;
-        let (stmt, params) = FetchChargeLineArgs::from((store_id, &t_range)).into_parts();
-
-        // TODO, consider to stream the fetch operation row by row, in case
-        // the number of lines to fetch is very large
-        let rows = stmt
-            .with(params)
-            .fetch::<ChargeLineRowType, &mut Conn>(&mut conn_rep)
-            .await
-            .map_err(|e| {
-                let code = AppErrorCode::RemoteDbServerFailure;
-                let detail = AppRepoErrorDetail::DatabaseQuery(e.to_string());
-                let fn_label = AppRepoErrorFnLabel::ReportChargeByMerchant;
-                self.map_log_err((code, detail), fn_label)
-            })?;
+        let (stmt, params) =
+            FetchChargeLineArgs::from((store_id, &t_range, cursor, limit)).into_parts();
+        let cline_map = self
+            .stream_charge_lines(&mut conn_rep, store_id, stmt, params)
+            .await?;
         drop(conn_rep);
-
-        let cline_map = Self::parse_charge_lines(store_id, rows).map_err(|reason| {
-            let fn_label = AppRepoErrorFnLabel::ReportChargeByMerchant;
-            self.map_log_err(reason, fn_label)
-        })?;
         if cline_map.is_empty() {
             return Ok(Vec::new());
         }
@@ -431,4 +825,37 @@ impl AbstractReportingRepo for MariadbReportingRepo {
             self.map_log_err((code, detail), fn_label)
         })
     } // end of fn fetch_charges_by_merchant
+
+    async fn fetch_refunds_by_merchant(
+        &self,
+        store_id: u32,
+        t_range: ReportTimeRangeDto,
+    ) -> Result<Vec<RefundResolutionHistoryModel>, AppRepoError> {
+        self._fetch_refunds_by_merchant(store_id, t_range)
+            .await
+            .map_err(|reason| self.map_log_err(reason, AppRepoErrorFnLabel::ReportRefundByMerchant))
+    }
+
+    async fn fetch_payouts_by_merchant(
+        &self,
+        store_id: u32,
+        t_range: ReportTimeRangeDto,
+    ) -> Result<Vec<PayoutModel>, AppRepoError> {
+        self._fetch_payouts(store_id, PayoutFilter::TimeRange(&t_range))
+            .await
+            .map_err(|reason| self.map_log_err(reason, AppRepoErrorFnLabel::ReportPayoutByMerchant))
+    }
+
+    async fn fetch_payouts_by_charges(
+        &self,
+        store_id: u32,
+        charge_ids: Vec<(u32, DateTime<Utc>)>,
+    ) -> Result<Vec<PayoutModel>, AppRepoError> {
+        if charge_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        self._fetch_payouts(store_id, PayoutFilter::Charges(&charge_ids))
+            .await
+            .map_err(|reason| self.map_log_err(reason, AppRepoErrorFnLabel::ReportPayoutByCharges))
+    }
 } // end of impl MariadbReportingRepo
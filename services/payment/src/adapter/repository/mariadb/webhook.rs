@@ -0,0 +1,145 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Local, Utc};
+use mysql_async::prelude::Queryable;
+use mysql_async::{Error as MysqlError, Params};
+
+use ecommerce_common::error::AppErrorCode;
+use ecommerce_common::logging::{app_log_event, AppLogLevel};
+
+use crate::adapter::datastore::{AppDStoreMariaDB, AppDataStoreContext};
+use crate::model::Label3party;
+
+use super::super::{
+    AbstractWebhookEventRepo, AppRepoError, AppRepoErrorDetail, AppRepoErrorFnLabel,
+};
+use super::{inner_into_parts, DATETIME_FMT_P0F};
+
+// see error code reference in mariadb doc
+const ER_DUP_ENTRY: u16 = 1062;
+
+struct DeleteEventArgs(String, Params);
+struct InsertEventArgs(String, Params);
+
+impl<'a> From<(Label3party, &'a str)> for DeleteEventArgs {
+    fn from(value: (Label3party, &'a str)) -> Self {
+        let (label3pty, evt_id) = value;
+        let stmt = "DELETE FROM `processor_webhook_event` WHERE \
+                    `label3party`=? AND `evt_id`=?";
+        let arg = vec![label3pty.to_string().into(), evt_id.into()];
+        Self(stmt.to_string(), Params::Positional(arg))
+    }
+}
+
+impl<'a, 'b> From<(Label3party, &'a str, &'b str, DateTime<Utc>)> for InsertEventArgs {
+    fn from(value: (Label3party, &'a str, &'b str, DateTime<Utc>)) -> Self {
+        let (label3pty, evt_id, evt_type, ctime) = value;
+        // the same event might be delivered concurrently by the 3rd party,
+        // the unique key lets the first one win
+        let stmt = "INSERT INTO `processor_webhook_event`(`label3party`,`evt_id`,\
+                    `evt_type`,`create_time`,`processed_time`) VALUES (?,?,?,?,?)";
+        let t_now = Local::now().to_utc();
+        let arg = vec![
+            label3pty.to_string().into(),
+            evt_id.into(),
+            evt_type.into(),
+            ctime.format(DATETIME_FMT_P0F).to_string().into(),
+            t_now.format(DATETIME_FMT_P0F).to_string().into(),
+        ];
+        Self(stmt.to_string(), Params::Positional(arg))
+    }
+}
+
+inner_into_parts!(DeleteEventArgs);
+inner_into_parts!(InsertEventArgs);
+
+pub(crate) struct MariadbWebhookEventRepo {
+    _dstore: Arc<AppDStoreMariaDB>,
+}
+
+impl MariadbWebhookEventRepo {
+    pub(crate) fn new(ds: Arc<AppDataStoreContext>) -> Result<Self, AppRepoError> {
+        ds.mariadb(Some("db-write-primary"))
+            .map(|found| Self { _dstore: found })
+            .ok_or(AppRepoError {
+                fn_label: AppRepoErrorFnLabel::InitWebhookEventRepo,
+                code: AppErrorCode::MissingDataStore,
+                detail: AppRepoErrorDetail::Unknown,
+            })
+    }
+
+    #[rustfmt::skip]
+    fn _map_log_err_common(
+        &self,
+        code: AppErrorCode,
+        detail: AppRepoErrorDetail,
+        fn_label: AppRepoErrorFnLabel,
+    ) -> AppRepoError {
+        let e = AppRepoError {fn_label, code, detail};
+        let logctx = self._dstore.log_context();
+        app_log_event!(logctx, AppLogLevel::ERROR, "{:?}", e);
+        e
+    }
+} // end of impl MariadbWebhookEventRepo
+
+#[async_trait]
+impl AbstractWebhookEventRepo for MariadbWebhookEventRepo {
+    async fn save(
+        &self,
+        label3pty: Label3party,
+        evt_id: &str,
+        evt_type: &str,
+        create_time: DateTime<Utc>,
+    ) -> Result<bool, AppRepoError> {
+        let arg = (label3pty, evt_id, evt_type, create_time);
+        let (stmt, params) = InsertEventArgs::from(arg).into_parts();
+        let mut conn = self._dstore.acquire().await.map_err(|e| {
+            self._map_log_err_common(
+                AppErrorCode::DatabaseServerBusy,
+                AppRepoErrorDetail::DataStore(e),
+                AppRepoErrorFnLabel::WebhookEventSave,
+            )
+        })?;
+        let result = match conn.exec_iter(stmt, params).await {
+            Ok(r) => r,
+            Err(MysqlError::Server(se)) if se.code == ER_DUP_ENTRY => return Ok(false),
+            Err(e) => {
+                return Err(self._map_log_err_common(
+                    AppErrorCode::RemoteDbServerFailure,
+                    AppRepoErrorDetail::DatabaseExec(e.to_string()),
+                    AppRepoErrorFnLabel::WebhookEventSave,
+                ))
+            }
+        };
+        let num_affected = result.affected_rows();
+        if num_affected == 1 {
+            Ok(true)
+        } else {
+            let msg = format!("rows-affected: {num_affected}");
+            Err(self._map_log_err_common(
+                AppErrorCode::DataCorruption,
+                AppRepoErrorDetail::DatabaseExec(msg),
+                AppRepoErrorFnLabel::WebhookEventSave,
+            ))
+        }
+    }
+
+    async fn delete(&self, label3pty: Label3party, evt_id: &str) -> Result<(), AppRepoError> {
+        let (stmt, params) = DeleteEventArgs::from((label3pty, evt_id)).into_parts();
+        let mut conn = self._dstore.acquire().await.map_err(|e| {
+            self._map_log_err_common(
+                AppErrorCode::DatabaseServerBusy,
+                AppRepoErrorDetail::DataStore(e),
+                AppRepoErrorFnLabel::WebhookEventDelete,
+            )
+        })?;
+        conn.exec_drop(stmt, params).await.map_err(|e| {
+            self._map_log_err_common(
+                AppErrorCode::RemoteDbServerFailure,
+                AppRepoErrorDetail::DatabaseExec(e.to_string()),
+                AppRepoErrorFnLabel::WebhookEventDelete,
+            )
+        })
+    }
+} // end of impl MariadbWebhookEventRepo
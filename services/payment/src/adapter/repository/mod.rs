@@ -16,13 +16,15 @@ use crate::api::web::dto::{RefundCompletionReqDto, ReportTimeRangeDto};
 use crate::model::{
     BuyerPayInState, ChargeBuyerMetaModel, ChargeBuyerModel, ChargeLineBuyerModel, ChargeRefundMap,
     Label3party, Merchant3partyModel, MerchantProfileModel, OrderLineModelSet, OrderRefundModel,
-    PayoutModel, RefundModelError, RefundReqResolutionModel,
+    PayoutModel, PlatformFeeScheduleModel, RefundIdempotencyModel, RefundModelError,
+    RefundReqResolutionModel, RefundResolutionHistoryModel,
 };
 
 use self::mariadb::charge::MariadbChargeRepo;
 use self::mariadb::merchant::MariadbMerchantRepo;
 use self::mariadb::refund::MariaDbRefundRepo;
 use self::mariadb::reporting::MariadbReportingRepo;
+use self::mariadb::webhook::MariadbWebhookEventRepo;
 use super::datastore::{AppDStoreError, AppDataStoreContext};
 
 #[derive(Debug)]
@@ -36,10 +38,13 @@ pub enum AppRepoErrorFnLabel {
     FetchChargeIds,
     FetchChargeMeta,
     FetchChargeLines,
+    FetchChargesUnsynced,
     FetchMerchant,
     FetchMerchantProf,
+    FetchMerchantBy3party,
     FetchChargeByMerchant,
     FetchPayout,
+    FetchPlatformFee,
     UpdateChargeProgress,
     UpdateChargeLinesRefund,
     UpdateMerchant3party,
@@ -49,8 +54,16 @@ pub enum AppRepoErrorFnLabel {
     RefundGetTimeSynced,
     RefundUpdateTimeSynced,
     RefundSaveReq,
+    RefundFetchReqByOrder,
+    RefundFetchIdempotent,
     ResolveRefundReq,
     ReportChargeByMerchant,
+    ReportRefundByMerchant,
+    ReportPayoutByMerchant,
+    ReportPayoutByCharges,
+    InitWebhookEventRepo,
+    WebhookEventSave,
+    WebhookEventDelete,
 }
 
 #[derive(Debug)]
@@ -68,6 +81,7 @@ pub enum AppRepoErrorDetail {
     DataRowParse(String),
     CurrencyPrecision(u32, String, String, u32, u32),
     RefundResolution(Vec<RefundModelError>),
+    IdempotencyKeyInUse(String),
     ConstructChargeFailure(String),
     Unknown,
 }
@@ -117,6 +131,18 @@ pub trait AbstractChargeRepo: Sync + Send {
 
     async fn update_charge_progress(&self, meta: ChargeBuyerMetaModel) -> Result<(), AppRepoError>;
 
+    /// load charges which have been completed in 3rd-party processors within
+    /// the given time range, but not synced with order-processing service yet.
+    /// The charges are sorted by completion time, callers page through them
+    /// by passing position of the last charge loaded in previous page
+    async fn fetch_charges_unsynced(
+        &self,
+        t_start: DateTime<Utc>,
+        t_end: DateTime<Utc>,
+        cursor: Option<AppChargeUnsyncedCursor>,
+        max_items: u16,
+    ) -> Result<Vec<ChargeBuyerMetaModel>, AppRepoError>;
+
     async fn fetch_charge_by_merchant(
         &self,
         buyer_id: u32,
@@ -126,15 +152,17 @@ pub trait AbstractChargeRepo: Sync + Send {
 
     async fn update_lines_refund(&self, cl_map: ChargeRefundMap) -> Result<(), AppRepoError>;
 
-    /// the method `fetch_payout()` returns payout summary of a specific payment made by client
-    /// , which includes total amount that has been transferred to merchant's bank account.
-    async fn fetch_payout(
+    /// the method `fetch_payouts()` returns payout history of a specific payment made by
+    /// client, each item represents an amount that has been transferred to merchant's bank
+    /// account, ordered by the sequence number of the payout.
+    async fn fetch_payouts(
         &self,
         store_id: u32,
         buyer_id: u32,
         create_time: DateTime<Utc>,
-    ) -> Result<Option<PayoutModel>, AppRepoError>;
+    ) -> Result<Vec<PayoutModel>, AppRepoError>;
 
+    /// append a new payout with the captured lines to the history
     async fn create_payout(&self, payout_m: PayoutModel) -> Result<(), AppRepoError>;
 } // end of trait AbstractChargeRepo
 
@@ -162,6 +190,21 @@ pub trait AbstractMerchantRepo: Sync + Send {
         &self,
         store_id: u32,
     ) -> Result<Option<MerchantProfileModel>, AppRepoError>;
+
+    /// look up store ID by the identifier of merchant account which is
+    /// issued by the 3rd-party processor
+    async fn fetch_id_by_3party(
+        &self,
+        label3pty: Label3party,
+        id3pty: &str,
+    ) -> Result<Option<u32>, AppRepoError>;
+
+    /// load the platform fee rules applicable to the given merchant, including
+    /// the default rules for all merchants
+    async fn fetch_platform_fee(
+        &self,
+        store_id: u32,
+    ) -> Result<PlatformFeeScheduleModel, AppRepoError>;
 } // end of trait AbstractMerchantRepo
 
 #[async_trait]
@@ -172,16 +215,41 @@ pub trait AbstractRefundRepo: Sync + Send {
 
     async fn save_request(&self, req: Vec<OrderRefundModel>) -> Result<(), AppRepoError>;
 
+    /// load all refund requests of an order, regardless of the merchants
+    /// involved, the returned model contains no line if nothing requested
+    async fn fetch_request_by_order(&self, oid: &str) -> Result<OrderRefundModel, AppRepoError>;
+
+    /// load the key and the serialised response body of a completion request
+    /// previously resolved by the merchant, return `None` if the key has never
+    /// been used
+    async fn fetch_idempotent_result(
+        &self,
+        merchant_id: u32,
+        key: &str,
+    ) -> Result<AppRefundIdempotentReturn, AppRepoError>;
+
+    /// return result of each processor, and the serialised response body for
+    /// the items resolved in this round. The refunded amount of corresponding
+    /// charge lines, and the optional idempotency key along with the same
+    /// response body, are saved within the same transaction, the key
+    /// already used by other completion request of the same merchant leads to
+    /// `AppRepoErrorDetail::IdempotencyKeyInUse`
     async fn resolve_request(
         &self,
         merchant_id: u32,
         cmplt_req: RefundCompletionReqDto,
+        idempotency: Option<RefundIdempotencyModel>,
         charge_ms: Vec<ChargeBuyerModel>,
         processor: Arc<Box<dyn AbstractPaymentProcessor>>,
         cb: AppRefundRslvReqCallback,
-    ) -> Result<AppRefundRslvReqOkReturn, AppRepoError>;
+    ) -> Result<(AppRefundRslvReqOkReturn, String), AppRepoError>;
 }
 
+/// time completed in 3rd-party processor, buyer ID, and creation time of a charge
+pub type AppChargeUnsyncedCursor = (DateTime<Utc>, u32, DateTime<Utc>);
+
+pub type AppRefundIdempotentReturn = Option<(RefundIdempotencyModel, String)>;
+
 pub type AppRefundRslvReqOkReturn = Vec<Result<RefundReqResolutionModel, AppProcessorError>>;
 
 pub type AppRefundRslvReqCbReturn = Result<AppRefundRslvReqOkReturn, AppRepoErrorDetail>;
@@ -222,12 +290,56 @@ pub type AppRefundRslvReqCallback =
 
 #[async_trait]
 pub trait AbstractReportingRepo: Send + Sync {
-    // TODO, pagination
+    /// fetch charges in the time range, sorted by create time and buyer ID,
+    /// the optional `cursor` is the ID (buyer-id, create-time) of the last
+    /// charge in previous page, at most `limit` charges after the cursor are
+    /// loaded
     async fn fetch_charges_by_merchant(
         &self,
         store_id: u32,
         t_range: ReportTimeRangeDto,
+        cursor: Option<(u32, DateTime<Utc>)>,
+        limit: u16,
     ) -> Result<Vec<ChargeBuyerModel>, AppRepoError>;
+
+    /// fetch all the refund lines resolved by the merchant in the time range
+    async fn fetch_refunds_by_merchant(
+        &self,
+        store_id: u32,
+        t_range: ReportTimeRangeDto,
+    ) -> Result<Vec<RefundResolutionHistoryModel>, AppRepoError>;
+
+    /// fetch all the payouts transferred to the merchant in the time range,
+    /// note the captured lines of each payout are not loaded
+    async fn fetch_payouts_by_merchant(
+        &self,
+        store_id: u32,
+        t_range: ReportTimeRangeDto,
+    ) -> Result<Vec<PayoutModel>, AppRepoError>;
+
+    /// fetch all the payouts captured from the given charges, regardless of
+    /// the time they were transferred, note the captured lines are not loaded
+    async fn fetch_payouts_by_charges(
+        &self,
+        store_id: u32,
+        charge_ids: Vec<(u32, DateTime<Utc>)>,
+    ) -> Result<Vec<PayoutModel>, AppRepoError>;
+}
+
+#[async_trait]
+pub trait AbstractWebhookEventRepo: Send + Sync {
+    /// return `false` if the event has been recorded before
+    async fn save(
+        &self,
+        label3pty: Label3party,
+        evt_id: &str,
+        evt_type: &str,
+        create_time: DateTime<Utc>,
+    ) -> Result<bool, AppRepoError>;
+
+    /// remove the record, so the event can be processed again when the
+    /// 3rd party delivers it next time
+    async fn delete(&self, label3pty: Label3party, evt_id: &str) -> Result<(), AppRepoError>;
 }
 
 pub async fn app_repo_charge(
@@ -258,3 +370,10 @@ pub async fn app_repo_reporting(
 
     Ok(Box::new(repo))
 }
+
+pub async fn app_repo_webhook_event(
+    dstore: Arc<AppDataStoreContext>,
+) -> Result<Box<dyn AbstractWebhookEventRepo>, AppRepoError> {
+    let repo = MariadbWebhookEventRepo::new(dstore)?;
+    Ok(Box::new(repo))
+}
@@ -16,14 +16,14 @@ use ecommerce_common::logging::AppLogContext;
 use amqp::AppAmqpRpcContext;
 use mock::AppMockRpcContext;
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub enum AppRpcErrorFnLabel {
     InitCtx,
     AcquireClientConn,
     ClientSendReq,
     ClientRecvResp,
 }
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub enum AppRpcErrorReason {
     NotSupport,
     InvalidCredential,
@@ -41,7 +41,7 @@ pub enum AppRpcErrorReason {
 // this clone trait is applied only for current workaround in lazy-init of  rpc connection pool.
 // after upgradinf std library to v1.80, replace `OnceLock` with easier-to-implement `LazyLock`
 // this clone trait will be no longer needed.
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct AppRpcCtxError {
     pub fn_label: AppRpcErrorFnLabel,
     pub reason: AppRpcErrorReason,
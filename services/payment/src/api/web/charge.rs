@@ -47,6 +47,7 @@ pub(super) async fn create_charge(
         processors: shr_state.processor_context(),
         ordersync_lockset: shr_state.ordersync_lockset(),
         rpc_ctx: shr_state.rpc_context(),
+        logctx: logctx.clone(),
     };
     let req_body = req_body.into_inner();
     let resp = match uc.execute(authed_claim, req_body).await {
@@ -184,6 +185,7 @@ pub(super) async fn capture_authorized_charge(
     shr_state: WebData<AppSharedState>,
 ) -> ActixResult<HttpResponse> {
     let charge_id = path_segms.into_inner().0;
+    let req_body = req_body.into_inner();
     let store_id = req_body.store_id;
     let logctx = shr_state.log_context();
     app_log_event!(logctx, AppLogLevel::DEBUG, "{charge_id}, {store_id}");
@@ -196,7 +198,7 @@ pub(super) async fn capture_authorized_charge(
         repo_c,
         repo_m,
     };
-    let result = uc.execute(charge_id, store_id).await;
+    let result = uc.execute(charge_id, req_body).await;
 
     let (http_status, body_raw) = match result {
         Ok(v) => {
@@ -230,6 +232,10 @@ pub(super) async fn capture_authorized_charge(
                     app_log_event!(logctx, AppLogLevel::ERROR, "{msg}");
                     StatusCode::INTERNAL_SERVER_ERROR
                 }
+                ChargeCaptureUcError::InvalidCapture(e) => {
+                    app_log_event!(logctx, AppLogLevel::INFO, "{:?}", e);
+                    StatusCode::BAD_REQUEST
+                }
                 ChargeCaptureUcError::CorruptedModel(e) => {
                     app_log_event!(logctx, AppLogLevel::ERROR, "{:?}", e);
                     StatusCode::INTERNAL_SERVER_ERROR
@@ -22,10 +22,17 @@ pub struct StripeCheckoutSessionReqDto {
     pub ui_mode: StripeCheckoutUImodeDto,
 }
 
+#[derive(Deserialize)]
+pub struct PaypalOrderReqDto {
+    pub return_url: String, // redirect buyer back after approving the order
+    pub cancel_url: String,
+}
+
 #[derive(Deserialize)]
 #[serde(tag = "label")]
 pub enum PaymentMethodReqDto {
     Stripe(StripeCheckoutSessionReqDto),
+    Paypal(PaypalOrderReqDto),
 }
 #[derive(Deserialize)]
 pub struct ChargeAmountOlineDto {
@@ -66,9 +73,15 @@ pub struct StripeCheckoutSessionRespDto {
     pub client_session: Option<String>, // for Stripe.js embedded checkout
 }
 #[derive(Serialize)]
+pub struct PaypalOrderRespDto {
+    pub id: String,
+    pub approve_url: Option<String>, // redirect to PayPal-hosted approval page
+}
+#[derive(Serialize)]
 #[serde(tag = "label")]
 pub enum PaymentMethodRespDto {
     Stripe(StripeCheckoutSessionRespDto),
+    Paypal(PaypalOrderRespDto),
     // TODO, integrate with Wise (TransferWise) wallet
 }
 #[derive(Serialize)]
@@ -149,9 +162,14 @@ pub struct StoreOnboardStripeReqDto {
     pub refresh_url: String,
 }
 #[derive(Deserialize)]
+pub struct StoreOnboardPaypalReqDto {
+    pub return_url: String,
+}
+#[derive(Deserialize)]
 #[serde(tag = "processor")]
 pub enum StoreOnboardReqDto {
     Stripe(StoreOnboardStripeReqDto),
+    Paypal(StoreOnboardPaypalReqDto),
 }
 
 #[derive(Serialize)]
@@ -163,12 +181,29 @@ pub enum StoreOnboardRespDto {
         url: Option<String>,
         expiry: Option<DateTime<Utc>>,
     },
+    Paypal {
+        merchant_id: Option<String>,
+        payments_receivable: bool,
+        email_confirmed: bool,
+        url: Option<String>,
+        expiry: Option<DateTime<Utc>>,
+    },
     Unknown,
 }
 
+#[derive(Deserialize)]
+pub struct CapturePayLineReqDto {
+    pub product_id: u64,
+    pub attr_set_seq: u16,
+    pub quantity: u32,
+}
+
 #[derive(Deserialize)]
 pub struct CapturePayReqDto {
     pub store_id: u32,
+    // merchant specifies the shipped items to capture, all the items which
+    // can still be captured will be included if omitted.
+    pub lines: Option<Vec<CapturePayLineReqDto>>,
 }
 
 #[derive(Serialize)]
@@ -179,6 +214,10 @@ pub enum CapturePay3partyRespDto {
         amount: String,
         currency: CurrencyDto,
     },
+    Paypal {
+        amount: String,
+        currency: CurrencyDto,
+    },
 }
 
 #[derive(Serialize)]
@@ -199,6 +238,13 @@ impl StoreOnboardRespDto {
                 url,
                 expiry: _,
             } => disabled_reason.is_none() && url.is_none(),
+            Self::Paypal {
+                merchant_id,
+                payments_receivable,
+                email_confirmed,
+                url: _,
+                expiry: _,
+            } => merchant_id.is_some() && *payments_receivable && *email_confirmed,
             Self::Unknown => false,
         }
     }
@@ -259,12 +305,12 @@ impl RefundCompletionOlineReqDto {
     }
 }
 
-#[derive(Serialize)]
+#[derive(Deserialize, Serialize)]
 pub struct RefundCompletionRespDto {
     pub lines: Vec<RefundCompletionOlineRespDto>,
 }
 
-#[derive(Serialize)]
+#[derive(Deserialize, Serialize)]
 pub struct RefundCompletionOlineRespDto {
     pub product_id: u64,
     pub attr_set_seq: u16,
@@ -273,6 +319,33 @@ pub struct RefundCompletionOlineRespDto {
     pub approval: RefundLineApprovalDto,
 }
 
+#[derive(Serialize)]
+pub struct RefundStatusReqRespDto {
+    // the time when customer issued the refund request
+    pub time_issued: DateTime<Utc>,
+    pub qty_requested: u32,
+    pub amount_requested: PayAmountDto,
+    pub approval: RefundLineApprovalDto,
+    pub reject: RefundLineRejectDto,
+}
+
+#[derive(Serialize)]
+pub struct RefundStatusOlineRespDto {
+    pub seller_id: u32,
+    pub product_id: u64,
+    pub attr_set_seq: u16,
+    pub requests: Vec<RefundStatusReqRespDto>,
+    // amount which has been refunded to the buyer through payment processors,
+    // summed up from all the charges of the order line, in buyer's currency
+    pub refunded: RefundLineApprovalDto,
+}
+
+#[derive(Serialize)]
+pub struct RefundStatusRespDto {
+    pub order_id: String,
+    pub lines: Vec<RefundStatusOlineRespDto>,
+}
+
 struct ExpectTimeRangeFormat(String);
 
 impl ExpectTimeRangeFormat {
@@ -320,17 +393,139 @@ impl ReportTimeRangeDto {
     }
 }
 
+#[derive(Deserialize, Clone, Debug, Default, PartialEq)]
+pub enum ReportChargeBreakdownDto {
+    #[default]
+    Product,
+    DayProduct,
+}
+
+#[derive(Deserialize, Clone, Debug, Default, PartialEq)]
+pub enum ReportFormatDto {
+    #[default]
+    Json,
+    Csv,
+}
+
+/// query parameters for splitting a charge report into pages, the cursor
+/// comes from `next_cursor` in previous page, `limit` indicates max number
+/// of charges to load in a page, default page size applies if omitted
+#[derive(Deserialize, Debug, Default)]
+pub struct ReportChargePageReqDto {
+    pub cursor: Option<String>,
+    pub limit: Option<u16>,
+    pub breakdown: Option<ReportChargeBreakdownDto>,
+    pub format: Option<ReportFormatDto>,
+}
+
 #[derive(Serialize)]
 pub struct ReportChargeLineRespDto {
+    // date of the charges in UTC, present only in per-day breakdown
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub date: Option<String>,
     pub product_id: u64,
     pub currency: CurrencyDto,
     pub amount: String,
     pub qty: u32,
 }
 
+/// total platform fee charged in the time period, in merchant's currency
+#[derive(Serialize)]
+pub struct ReportChargeFeeRespDto {
+    pub currency: CurrencyDto,
+    pub amount: String,
+}
+
 #[derive(Serialize)]
 pub struct ReportChargeRespDto {
     pub merchant_id: u32,
     pub time_range: ReportTimeRangeDto,
     pub lines: Vec<ReportChargeLineRespDto>,
+    pub fee_lines: Vec<ReportChargeFeeRespDto>,
+    // absent if there is no more charge to load in the time range
+    pub next_cursor: Option<String>,
+}
+
+impl ReportChargeLineRespDto {
+    fn to_csv_row(&self) -> String {
+        let date = self.date.as_deref().unwrap_or("");
+        let currency = self.currency.to_string();
+        format!(
+            "charge,{date},{},{currency},{},{}",
+            self.product_id, self.amount, self.qty
+        )
+    }
+}
+
+impl ReportChargeFeeRespDto {
+    // platform fee is not broken down by date or product
+    fn to_csv_row(&self) -> String {
+        let currency = self.currency.to_string();
+        format!("fee,,,{currency},{},", self.amount)
+    }
+}
+
+impl ReportChargeRespDto {
+    const CSV_HEADER: &'static str = "line_type,date,product_id,currency,amount,qty";
+
+    /// export report lines in CSV format, followed by the platform fee lines,
+    /// the first column tells the type of each row, which ends with CRLF (RFC 4180)
+    pub fn to_csv(&self) -> String {
+        let iter_chrg = self.lines.iter().map(ReportChargeLineRespDto::to_csv_row);
+        let iter_fee = self
+            .fee_lines
+            .iter()
+            .map(ReportChargeFeeRespDto::to_csv_row);
+        [Self::CSV_HEADER.to_string()]
+            .into_iter()
+            .chain(iter_chrg)
+            .chain(iter_fee)
+            .map(|row| row + "\r\n")
+            .collect::<String>()
+    }
+}
+
+#[derive(Serialize)]
+pub struct ReportRefundLineRespDto {
+    pub product_id: u64,
+    pub currency: CurrencyDto,
+    // total amount approved to refund, in merchant's currency
+    pub amount: String,
+    pub qty_approved: u32,
+    pub qty_rejected: RefundLineRejectDto,
+}
+
+#[derive(Serialize)]
+pub struct ReportRefundRespDto {
+    pub merchant_id: u32,
+    pub time_range: ReportTimeRangeDto,
+    pub lines: Vec<ReportRefundLineRespDto>,
+}
+
+#[derive(Serialize)]
+pub struct ReportPayoutLineRespDto {
+    pub currency: CurrencyDto,
+    pub num_payouts: u32,
+    // all the amounts below are in merchant's currency, the captured amount
+    // includes platform fee, which is deducted before transfer
+    pub amount_captured: String,
+    pub fee: String,
+    pub amount_transferred: String,
+}
+
+#[derive(Serialize)]
+pub struct ReportSettlementRespDto {
+    pub currency: CurrencyDto,
+    pub transferred: String,
+    pub refunded: String,
+    // transferred amount subtracted by refunded amount, could be negative
+    pub net: String,
+}
+
+#[derive(Serialize)]
+pub struct ReportPayoutRespDto {
+    pub merchant_id: u32,
+    pub time_range: ReportTimeRangeDto,
+    pub lines: Vec<ReportPayoutLineRespDto>,
+    pub settlement: Vec<ReportSettlementRespDto>,
 }
@@ -3,6 +3,7 @@ pub mod dto;
 mod onboard;
 mod refund;
 mod reporting;
+mod webhook;
 
 use actix_http::Method;
 use actix_web::body::BoxBody;
@@ -14,21 +15,33 @@ use std::collections::HashMap;
 
 use charge::{capture_authorized_charge, create_charge, refresh_charge_status};
 use onboard::{onboard_store, track_onboarding_status};
-use refund::mechant_complete_refund;
-use reporting::report_charge_lines;
+use refund::{buyer_refund_status, mechant_complete_refund};
+use reporting::{report_charge_lines, report_payouts, report_refund_lines};
+use webhook::stripe_webhook;
 
 pub struct AppRouteTable {
     pub version: String,
     pub entries: HashMap<String, Route>,
+    // endpoints which authenticate callers without JWT, e.g. webhooks
+    // for external processors
+    pub public_entries: HashMap<String, Route>,
 } // note, figure out how do multiple versions of API endpoints co-exist
 
 impl AppRouteTable {
     pub fn get(ver_req: &str) -> Self {
-        let (version, entries) = match ver_req {
-            "0.1.0" | "0.1.1" => (format!("v{ver_req}"), Self::v0_1_0_entries()),
-            _others => (String::new(), HashMap::new()),
+        let (version, entries, public_entries) = match ver_req {
+            "0.1.0" | "0.1.1" => (
+                format!("v{ver_req}"),
+                Self::v0_1_0_entries(),
+                Self::v0_1_0_public_entries(),
+            ),
+            _others => (String::new(), HashMap::new(), HashMap::new()),
         };
-        Self { version, entries }
+        Self {
+            version,
+            entries,
+            public_entries,
+        }
     }
     fn v0_1_0_entries() -> HashMap<String, Route> {
         let data = [
@@ -62,13 +75,32 @@ impl AppRouteTable {
                     .method(Method::PATCH)
                     .to(mechant_complete_refund),
             ),
+            (
+                "refund_status".to_string(),
+                Route::new().method(Method::GET).to(buyer_refund_status),
+            ),
             (
                 "report_charge_lines".to_string(),
                 Route::new().method(Method::GET).to(report_charge_lines),
             ),
+            (
+                "report_refund_lines".to_string(),
+                Route::new().method(Method::GET).to(report_refund_lines),
+            ),
+            (
+                "report_payouts".to_string(),
+                Route::new().method(Method::GET).to(report_payouts),
+            ),
         ];
         HashMap::from(data)
     }
+    fn v0_1_0_public_entries() -> HashMap<String, Route> {
+        let data = [(
+            "stripe_webhook".to_string(),
+            Route::new().method(Method::POST).to(stripe_webhook),
+        )];
+        HashMap::from(data)
+    }
 } // end of impl AppRouteTable
 
 #[derive(Debug)]
@@ -4,14 +4,16 @@ use actix_web::error::Error as ActixError;
 use actix_web::http::header::{ContentType, CONTENT_TYPE};
 use actix_web::http::StatusCode;
 use actix_web::web::{Data as AppData, Json as ExtJson, Path as ExtPath};
-use actix_web::{HttpResponse, HttpResponseBuilder, Result as ActixResult};
+use actix_web::{HttpRequest, HttpResponse, HttpResponseBuilder, Result as ActixResult};
 
 use ecommerce_common::logging::{app_log_event, AppLogContext, AppLogLevel};
 
 use crate::adapter::datastore::AppDataStoreContext;
 use crate::adapter::repository::{app_repo_refund, AbstractRefundRepo, AppRepoErrorDetail};
 use crate::auth::AppAuthedClaim;
-use crate::usecase::{FinalizeRefundUcError, FinalizeRefundUseCase};
+use crate::usecase::{
+    FinalizeRefundUcError, FinalizeRefundUseCase, RefundReqStatusUcError, RefundReqStatusUseCase,
+};
 use crate::AppSharedState;
 
 use super::charge::try_creating_charge_repo;
@@ -19,6 +21,8 @@ use super::dto::RefundCompletionReqDto;
 use super::onboard::try_creating_merchant_repo;
 use super::RepoInitFailure;
 
+const HEADER_NAME_IDEMPOTENCY: &str = "Idempotency-Key";
+
 async fn try_creating_refund_repo(
     dstore: Arc<AppDataStoreContext>,
     logctx: Arc<AppLogContext>,
@@ -30,6 +34,7 @@ async fn try_creating_refund_repo(
 }
 
 pub(super) async fn mechant_complete_refund(
+    req: HttpRequest,
     path_segms: ExtPath<(String, u32)>,
     ExtJson(req_body): ExtJson<RefundCompletionReqDto>,
     auth_claim: AppAuthedClaim,
@@ -37,6 +42,16 @@ pub(super) async fn mechant_complete_refund(
 ) -> ActixResult<HttpResponse> {
     let (oid, store_id) = path_segms.into_inner();
     let logctx = shr_state.log_context();
+    let idempotency_key = match req.headers().get(HEADER_NAME_IDEMPOTENCY) {
+        Some(v) => match v.to_str() {
+            Ok(s) => Some(s.to_string()),
+            Err(_e) => {
+                app_log_event!(logctx, AppLogLevel::WARNING, "idempotency-key-not-ascii");
+                return Ok(HttpResponse::BadRequest().finish());
+            }
+        },
+        None => None,
+    };
 
     let dstore = shr_state.datastore();
     let repo_ch = try_creating_charge_repo(dstore.clone(), logctx.clone()).await?;
@@ -50,13 +65,15 @@ pub(super) async fn mechant_complete_refund(
         repo_rfd,
         processors,
     };
-    let result = uc.execute(oid, store_id, auth_claim, req_body).await;
+    let result = uc
+        .execute(oid, store_id, auth_claim, req_body, idempotency_key)
+        .await;
     let (http_status, body_raw) = match result {
-        Ok((o, es)) => {
+        Ok((body, es)) => {
             if !es.is_empty() {
                 app_log_event!(logctx, AppLogLevel::ERROR, "{:?}", es);
             }
-            (StatusCode::OK, serde_json::to_vec(&o).unwrap())
+            (StatusCode::OK, body.into_bytes())
         }
         Err(e) => {
             let err_status = match e {
@@ -76,6 +93,14 @@ pub(super) async fn mechant_complete_refund(
                     app_log_event!(logctx, AppLogLevel::WARNING, "{auth_usr_id}");
                     StatusCode::FORBIDDEN
                 }
+                FinalizeRefundUcError::InvalidIdempotencyKey(me) => {
+                    app_log_event!(logctx, AppLogLevel::WARNING, "{:?}", me);
+                    StatusCode::BAD_REQUEST
+                }
+                FinalizeRefundUcError::IdempotencyConflict(key) => {
+                    app_log_event!(logctx, AppLogLevel::WARNING, "{key}");
+                    StatusCode::CONFLICT
+                }
                 FinalizeRefundUcError::DataStore(re) => {
                     let client_err = matches!(re.detail, AppRepoErrorDetail::RefundResolution(_));
                     if client_err {
@@ -98,3 +123,52 @@ pub(super) async fn mechant_complete_refund(
     };
     Ok(resp)
 } // end of fn mechant_complete_refund
+
+pub(super) async fn buyer_refund_status(
+    path_segms: ExtPath<(String,)>,
+    auth_claim: AppAuthedClaim,
+    shr_state: AppData<AppSharedState>,
+) -> ActixResult<HttpResponse> {
+    let oid = path_segms.into_inner().0;
+    let logctx = shr_state.log_context();
+
+    let dstore = shr_state.datastore();
+    let repo_ch = try_creating_charge_repo(dstore.clone(), logctx.clone()).await?;
+    let repo_rfd = try_creating_refund_repo(dstore, logctx.clone()).await?;
+
+    let uc = RefundReqStatusUseCase { repo_ch, repo_rfd };
+    let result = uc.execute(oid, auth_claim).await;
+    let (http_status, body_raw) = match result {
+        Ok(o) => (StatusCode::OK, serde_json::to_vec(&o).unwrap()),
+        Err(e) => {
+            let err_status = match e {
+                RefundReqStatusUcError::MissingChargeId(oid) => {
+                    app_log_event!(logctx, AppLogLevel::DEBUG, "{oid}");
+                    StatusCode::NOT_FOUND
+                }
+                RefundReqStatusUcError::OwnerMismatch(auth_usr_id) => {
+                    app_log_event!(logctx, AppLogLevel::WARNING, "{auth_usr_id}");
+                    StatusCode::FORBIDDEN
+                }
+                RefundReqStatusUcError::DataStore(re) => {
+                    let client_err = matches!(re.detail, AppRepoErrorDetail::OrderIDparse(_));
+                    if client_err {
+                        app_log_event!(logctx, AppLogLevel::WARNING, "{:?}", re);
+                        StatusCode::BAD_REQUEST
+                    } else {
+                        app_log_event!(logctx, AppLogLevel::ERROR, "{:?}", re);
+                        StatusCode::INTERNAL_SERVER_ERROR
+                    }
+                }
+            };
+            (err_status, Vec::new())
+        }
+    };
+    let resp = {
+        let mut r = HttpResponseBuilder::new(http_status);
+        let header = (CONTENT_TYPE, ContentType::json());
+        r.append_header(header);
+        r.body(body_raw)
+    };
+    Ok(resp)
+} // end of fn buyer_refund_status
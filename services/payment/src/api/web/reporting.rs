@@ -2,7 +2,7 @@ use std::boxed::Box;
 use std::sync::Arc;
 
 use actix_web::error::Error as ActixError;
-use actix_web::http::header::{ContentType, CONTENT_TYPE};
+use actix_web::http::header::{ContentType, HeaderName, CONTENT_TYPE};
 use actix_web::http::StatusCode;
 use actix_web::web::{Data as AppData, Path as ExtPath, Query as ExtQuery};
 use actix_web::{HttpResponse, HttpResponseBuilder, Result as ActixResult};
@@ -13,9 +13,12 @@ use super::onboard::try_creating_merchant_repo;
 use super::RepoInitFailure;
 use crate::adapter::datastore::AppDataStoreContext;
 use crate::adapter::repository::{app_repo_reporting, AbstractReportingRepo};
-use crate::api::web::dto::ReportTimeRangeDto;
+use crate::api::web::dto::{ReportChargePageReqDto, ReportFormatDto, ReportTimeRangeDto};
 use crate::auth::AppAuthedClaim;
-use crate::usecase::{MerchantReportChargeUcError, MerchantReportChargeUseCase};
+use crate::usecase::{
+    MerchantReportChargeUcError, MerchantReportChargeUseCase, MerchantReportPayoutUseCase,
+    MerchantReportRefundUseCase, MerchantReportSettleUcError,
+};
 use crate::AppSharedState;
 
 async fn try_creating_reporting_repo(
@@ -28,30 +31,51 @@ async fn try_creating_reporting_repo(
     })
 }
 
+// cursor to next page of the report, in case the report is exported as CSV
+const HEADER_NEXT_CURSOR: HeaderName = HeaderName::from_static("x-next-cursor");
+
 pub(super) async fn report_charge_lines(
     path_m: ExtPath<(u32,)>,
     query_m: ExtQuery<ReportTimeRangeDto>,
+    query_pg: ExtQuery<ReportChargePageReqDto>,
     auth_claim: AppAuthedClaim,
     shr_state: AppData<AppSharedState>,
 ) -> ActixResult<HttpResponse> {
     let store_id = path_m.into_inner().0;
     let time_range = query_m.into_inner();
+    let page = query_pg.into_inner();
+    let format = page.format.clone().unwrap_or_default();
 
     let logctx = shr_state.log_context();
-    app_log_event!(logctx, AppLogLevel::DEBUG, "{store_id}, {:?}", &time_range);
+    app_log_event!(
+        logctx,
+        AppLogLevel::DEBUG,
+        "{store_id}, {:?}, {:?}",
+        &time_range,
+        &page
+    );
     let dstore = shr_state.datastore();
 
     let repo_mc = try_creating_merchant_repo(dstore.clone(), logctx.clone()).await?;
     let repo_rpt = try_creating_reporting_repo(dstore, logctx.clone()).await?;
     let uc = MerchantReportChargeUseCase::new(auth_claim, repo_mc, repo_rpt);
-    let result = uc.execute(store_id, time_range).await;
+    let result = uc.execute(store_id, time_range, page).await;
+    let mut next_cursor = None;
     let (http_status, body_raw) = match result {
         Ok(v) => {
-            let body_raw = serde_json::to_vec(&v).unwrap();
+            let body_raw = match format {
+                ReportFormatDto::Json => serde_json::to_vec(&v).unwrap(),
+                ReportFormatDto::Csv => v.to_csv().into_bytes(),
+            };
+            next_cursor = v.next_cursor;
             (StatusCode::OK, body_raw)
         }
         Err(e) => {
             let s = match e {
+                MerchantReportChargeUcError::InvalidCursor(code, detail) => {
+                    app_log_event!(logctx, AppLogLevel::DEBUG, "{:?}, {detail}", code);
+                    StatusCode::BAD_REQUEST
+                }
                 MerchantReportChargeUcError::MissingMerchant(store_id) => {
                     app_log_event!(logctx, AppLogLevel::DEBUG, "{store_id}");
                     StatusCode::BAD_REQUEST
@@ -73,7 +97,87 @@ pub(super) async fn report_charge_lines(
         }
     };
     let mut r = HttpResponseBuilder::new(http_status);
-    let header = (CONTENT_TYPE, ContentType::json());
-    r.append_header(header);
+    match (http_status, format) {
+        (StatusCode::OK, ReportFormatDto::Csv) => {
+            r.append_header((CONTENT_TYPE, "text/csv; charset=utf-8"));
+            if let Some(c) = next_cursor {
+                r.append_header((HEADER_NEXT_CURSOR, c));
+            }
+        }
+        _others => {
+            r.append_header((CONTENT_TYPE, ContentType::json()));
+        }
+    }
     Ok(r.body(body_raw))
 } // end of fn report_charge_lines
+
+fn settle_error_status(logctx: &AppLogContext, e: MerchantReportSettleUcError) -> StatusCode {
+    match e {
+        MerchantReportSettleUcError::MissingMerchant(store_id) => {
+            app_log_event!(logctx, AppLogLevel::DEBUG, "{store_id}");
+            StatusCode::BAD_REQUEST
+        }
+        MerchantReportSettleUcError::PermissionDenied(usr_id) => {
+            app_log_event!(logctx, AppLogLevel::INFO, "{usr_id}");
+            StatusCode::FORBIDDEN
+        }
+        MerchantReportSettleUcError::DataStore(e) => {
+            app_log_event!(logctx, AppLogLevel::ERROR, "{:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+        MerchantReportSettleUcError::TransformFailure(es) => {
+            app_log_event!(logctx, AppLogLevel::ERROR, "{:?}", es);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+pub(super) async fn report_refund_lines(
+    path_m: ExtPath<(u32,)>,
+    query_m: ExtQuery<ReportTimeRangeDto>,
+    auth_claim: AppAuthedClaim,
+    shr_state: AppData<AppSharedState>,
+) -> ActixResult<HttpResponse> {
+    let store_id = path_m.into_inner().0;
+    let time_range = query_m.into_inner();
+    let logctx = shr_state.log_context();
+    app_log_event!(logctx, AppLogLevel::DEBUG, "{store_id}, {:?}", &time_range);
+    let dstore = shr_state.datastore();
+
+    let repo_mc = try_creating_merchant_repo(dstore.clone(), logctx.clone()).await?;
+    let repo_rpt = try_creating_reporting_repo(dstore, logctx.clone()).await?;
+    let uc = MerchantReportRefundUseCase::new(auth_claim, repo_mc, repo_rpt);
+    let (http_status, body_raw) = match uc.execute(store_id, time_range).await {
+        Ok(v) => (StatusCode::OK, serde_json::to_vec(&v).unwrap()),
+        Err(e) => (settle_error_status(logctx.as_ref(), e), Vec::new()),
+    };
+    let resp = HttpResponseBuilder::new(http_status)
+        .append_header(ContentType::json())
+        .body(body_raw);
+    Ok(resp)
+} // end of fn report_refund_lines
+
+pub(super) async fn report_payouts(
+    path_m: ExtPath<(u32,)>,
+    query_m: ExtQuery<ReportTimeRangeDto>,
+    auth_claim: AppAuthedClaim,
+    shr_state: AppData<AppSharedState>,
+) -> ActixResult<HttpResponse> {
+    let store_id = path_m.into_inner().0;
+    let time_range = query_m.into_inner();
+    let logctx = shr_state.log_context();
+    app_log_event!(logctx, AppLogLevel::DEBUG, "{store_id}, {:?}", &time_range);
+    let dstore = shr_state.datastore();
+
+    let repo_mc = try_creating_merchant_repo(dstore.clone(), logctx.clone()).await?;
+    let repo_rpt = try_creating_reporting_repo(dstore, logctx.clone()).await?;
+    let uc = MerchantReportPayoutUseCase::new(auth_claim, repo_mc, repo_rpt);
+    let (http_status, body_raw) = match uc.execute(store_id, time_range).await {
+        Ok(v) => (StatusCode::OK, serde_json::to_vec(&v).unwrap()),
+        Err(e) => (settle_error_status(logctx.as_ref(), e), Vec::new()),
+    };
+    let resp = HttpResponseBuilder::new(http_status)
+        .append_header(ContentType::json())
+        .body(body_raw);
+    Ok(resp)
+} // end of fn report_payouts
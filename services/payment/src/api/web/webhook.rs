@@ -0,0 +1,103 @@
+use std::boxed::Box;
+use std::sync::Arc;
+
+use actix_web::error::Error as ActixError;
+use actix_web::http::header::{ContentType, CONTENT_TYPE};
+use actix_web::http::StatusCode;
+use actix_web::web::{Bytes, Data as AppData};
+use actix_web::{HttpRequest, HttpResponse, HttpResponseBuilder, Result as ActixResult};
+
+use ecommerce_common::logging::{app_log_event, AppLogContext, AppLogLevel};
+
+use crate::adapter::datastore::AppDataStoreContext;
+use crate::adapter::repository::{app_repo_webhook_event, AbstractWebhookEventRepo};
+use crate::model::Label3party;
+use crate::usecase::{ProcessorWebhookUcError, ProcessorWebhookUseCase};
+use crate::AppSharedState;
+
+use super::charge::try_creating_charge_repo;
+use super::onboard::try_creating_merchant_repo;
+use super::RepoInitFailure;
+
+const HEADER_NAME_STRIPE_SIGNATURE: &str = "Stripe-Signature";
+
+async fn try_creating_webhook_repo(
+    dstore: Arc<AppDataStoreContext>,
+    logctx: Arc<AppLogContext>,
+) -> ActixResult<Box<dyn AbstractWebhookEventRepo>> {
+    app_repo_webhook_event(dstore).await.map_err(|e_repo| {
+        app_log_event!(logctx, AppLogLevel::ERROR, "repo-init-error {:?}", e_repo);
+        ActixError::from(RepoInitFailure)
+    })
+}
+
+fn usecase_result_to_httpresp(
+    logctx: Arc<AppLogContext>,
+    result: Result<bool, ProcessorWebhookUcError>,
+) -> HttpResponse {
+    // the 3rd party keeps re-sending the same event until this endpoint
+    // responds with 2xx status, so only refuse the event which will never
+    // be accepted, or the failure which could be recovered later.
+    let http_status = match result {
+        Ok(_newly_processed) => StatusCode::OK,
+        Err(uce) => match uce {
+            ProcessorWebhookUcError::InvalidEvent(e) => {
+                app_log_event!(logctx, AppLogLevel::WARNING, "{:?}", e);
+                StatusCode::BAD_REQUEST
+            }
+            ProcessorWebhookUcError::ExternalProcessor(e) => {
+                app_log_event!(logctx, AppLogLevel::ERROR, "{:?}", e);
+                StatusCode::SERVICE_UNAVAILABLE
+            }
+            ProcessorWebhookUcError::DataStore(e) => {
+                app_log_event!(logctx, AppLogLevel::ERROR, "{:?}", e);
+                StatusCode::SERVICE_UNAVAILABLE
+            }
+            ProcessorWebhookUcError::ChargeIdDecode(code, detail) => {
+                app_log_event!(logctx, AppLogLevel::WARNING, "{:?} {}", code, detail);
+                StatusCode::BAD_REQUEST
+            }
+            ProcessorWebhookUcError::CorruptedMerchant(store_id, detail) => {
+                app_log_event!(logctx, AppLogLevel::ERROR, "{store_id} {detail}");
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        },
+    };
+    let mut r = HttpResponseBuilder::new(http_status);
+    let header = (CONTENT_TYPE, ContentType::json());
+    r.append_header(header);
+    r.body(b"{}".to_vec())
+} // end of fn usecase_result_to_httpresp
+
+pub(super) async fn stripe_webhook(
+    req: HttpRequest,
+    body: Bytes,
+    shr_state: AppData<AppSharedState>,
+) -> ActixResult<HttpResponse> {
+    let logctx = shr_state.log_context();
+    let sig_header = req
+        .headers()
+        .get(HEADER_NAME_STRIPE_SIGNATURE)
+        .and_then(|v| v.to_str().ok());
+    let sig_header = if let Some(v) = sig_header {
+        v
+    } else {
+        app_log_event!(logctx, AppLogLevel::WARNING, "missing-signature");
+        return Ok(HttpResponse::BadRequest().finish());
+    };
+    let dstore = shr_state.datastore();
+    let repo_evt = try_creating_webhook_repo(dstore.clone(), logctx.clone()).await?;
+    let repo_charge = try_creating_charge_repo(dstore.clone(), logctx.clone()).await?;
+    let repo_merchant = try_creating_merchant_repo(dstore, logctx.clone()).await?;
+    let uc = ProcessorWebhookUseCase {
+        processors: shr_state.processor_context(),
+        repo_evt,
+        repo_charge,
+        repo_merchant,
+        rpc_ctx: shr_state.rpc_context(),
+        logctx: logctx.clone(),
+    };
+    let result = uc.execute(Label3party::Stripe, sig_header, &body).await;
+    let resp = usecase_result_to_httpresp(logctx, result);
+    Ok(resp)
+} // end of fn stripe_webhook
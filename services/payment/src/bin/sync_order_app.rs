@@ -0,0 +1,82 @@
+use std::collections::HashMap;
+use std::env;
+use std::result::Result;
+use std::time::Duration;
+
+use payment::adapter::repository::app_repo_charge;
+use tokio::runtime::Builder;
+use tokio::time;
+
+use ecommerce_common::config::{AppCfgHardLimit, AppCfgInitArgs, AppConfig};
+use ecommerce_common::constant::env_vars::EXPECTED_LABELS;
+use ecommerce_common::logging::{app_log_event, AppLogLevel};
+
+use payment::usecase::SyncOrderAppUseCase;
+use payment::{hard_limit, AppSharedState};
+
+#[rustfmt::skip]
+async fn sync_once(shr_state: &AppSharedState) -> Result<(), ()> {
+    let logctx = shr_state.log_context();
+    let repo = app_repo_charge(shr_state.datastore())
+        .await.map_err(|e| {
+            app_log_event!(logctx, AppLogLevel::ERROR, "{:?}", e);
+        })?;
+    let uc = SyncOrderAppUseCase {
+        repo, rpc_ctx: shr_state.rpc_context(), logctx: logctx.clone(),
+    };
+    let (num_synced, num_failed) = uc.execute()
+        .await
+        .map_err(|e| {
+            app_log_event!(logctx, AppLogLevel::ERROR, "{:?}", e);
+        })?;
+    app_log_event!(logctx, AppLogLevel::INFO, "num_synced:{num_synced}, num_failed:{num_failed}");
+    Ok(())
+}
+
+async fn start_sync(shr_state: AppSharedState) -> Result<(), ()> {
+    // keep retrying periodically, failures in one round are logged and
+    // the remaining charges will be picked up in next round
+    let period = Duration::from_secs(hard_limit::ORDERAPP_SYNC_INTERVAL_SECS as u64);
+    loop {
+        let _ = sync_once(&shr_state).await;
+        time::sleep(period).await;
+    }
+}
+
+fn init_config() -> Result<AppConfig, ()> {
+    let iter = env::vars().filter(|(k, _v)| EXPECTED_LABELS.contains(&k.as_str()));
+    let env_var_map = HashMap::from_iter(iter);
+    let limit = AppCfgHardLimit {
+        nitems_per_inmem_table: 0,
+        num_db_conns: 10,
+        seconds_db_idle: hard_limit::MAX_SECONDS_DB_IDLE,
+    };
+    let args = AppCfgInitArgs { env_var_map, limit };
+    AppConfig::new(args).map_err(|e| {
+        println!(
+            "[ERROR] config failure, code:{:?}, detail:{:?}",
+            e.code, e.detail
+        );
+    })
+}
+
+fn main() -> Result<(), ()> {
+    let cfg = init_config()?;
+    let shr_state = AppSharedState::new(cfg).map_err(|e| {
+        println!("[ERROR] shared state init failure, {:?}", e);
+    })?;
+    let cfg = shr_state.config();
+    let logctx = shr_state.log_context();
+    let stack_nbytes = (cfg.api_server.stack_sz_kb as usize) << 10;
+    let runtime = Builder::new_current_thread()
+        .worker_threads(1)
+        .thread_stack_size(stack_nbytes)
+        .thread_name("sync-order-app")
+        .enable_time()
+        .enable_io()
+        .build()
+        .map_err(|e| {
+            app_log_event!(logctx, AppLogLevel::ERROR, "{:?}", e);
+        })?;
+    runtime.block_on(async move { start_sync(shr_state).await })
+} // end of fn main
@@ -3,7 +3,6 @@ use std::env;
 
 use actix_web::rt;
 use actix_web::web::{Data as WebData, JsonConfig};
-use actix_web_httpauth::middleware::HttpAuthentication;
 
 use ecommerce_common::config::{AppCfgHardLimit, AppCfgInitArgs, AppConfig};
 use ecommerce_common::constant::env_vars::EXPECTED_LABELS;
@@ -11,7 +10,7 @@ use ecommerce_common::logging::{app_log_event, AppLogLevel};
 
 use payment::api::web::AppRouteTable;
 use payment::network::{app_web_service, middleware, net_server_listener};
-use payment::{hard_limit, validate_jwt, AppSharedState};
+use payment::{hard_limit, AppSharedState};
 
 fn init_config() -> Result<AppConfig, ()> {
     let iter = env::vars().filter(|(k, _v)| EXPECTED_LABELS.contains(&k.as_str()));
@@ -65,12 +64,11 @@ fn main() -> Result<(), ()> {
         let api_ver = listener_ref.api_version.as_str();
         let route_table = AppRouteTable::get(api_ver);
         let cfgroutes = listener_ref.routes.clone();
-        let (app, num_applied) = app_web_service(route_table, cfgroutes);
+        let (app, num_applied) = app_web_service(route_table, cfgroutes, true);
         if num_applied == 0 {
             app_log_event!(logctx_p, AppLogLevel::ERROR, "no-route-in-app-router");
         } // actix-web doesn't consider to handle errors from this callback
         let reqbodycfg = JsonConfig::default().limit(cfg_ref.api_server.limit_req_body_in_bytes);
-        let auth_middleware = HttpAuthentication::bearer(validate_jwt);
         let cors = {
             let path =
                 cfg_ref.basepath.system.clone() + "/" + cfg_ref.api_server.listen.cors.as_str();
@@ -80,8 +78,7 @@ fn main() -> Result<(), ()> {
             }
             result.unwrap()
         };
-        app.wrap(auth_middleware)
-            .wrap(cors)
+        app.wrap(cors)
             .app_data(WebData::new(_state.auth_keystore()))
             .app_data(WebData::new(_state))
             .app_data(reqbodycfg)
@@ -13,7 +13,9 @@ use ecommerce_common::config::AppConfig;
 use ecommerce_common::error::{AppConfidentialityError, AppErrorCode};
 use ecommerce_common::logging::AppLogContext;
 
-use crate::adapter::cache::{app_cache_order_sync_lock, AbstractOrderSyncLockCache};
+use crate::adapter::cache::{
+    app_cache_order_sync_lock, AbstractOrderSyncLockCache, OrderSyncLockError,
+};
 use crate::adapter::datastore::{AppDStoreError, AppDataStoreContext};
 use crate::adapter::processor::{
     app_processor_context, AbstractPaymentProcessor, AppProcessorError,
@@ -26,12 +28,8 @@ pub use crate::auth::{
 };
 
 pub mod app_meta {
-    use ecommerce_common::api::dto::CurrencyDto;
-
     pub const LABAL: &str = "payment";
     pub const RESOURCE_QUOTA_AP_CODE: u8 = 7;
-    // TODO, make it configurable parameter
-    pub const PLATFORM_FEE_AMOUNT: (u32, CurrencyDto) = (12, CurrencyDto::TWD);
 }
 pub mod hard_limit {
     pub const MAX_DB_CONNECTIONS: u32 = 1800u32;
@@ -40,6 +38,15 @@ pub mod hard_limit {
     pub const CREATE_CHARGE_SECONDS_INTERVAL: u16 = 5u16;
     pub const RPC_WAIT_FOR_REPLY: u16 = 5u16;
     pub const CURRENCY_RATE_PRECISION: u32 = 8;
+    pub const ORDERSYNC_LOCK_EXPIRY_SECS: u16 = 30u16;
+    pub const ORDERSYNC_LOCK_WAIT_SECS: u16 = 3u16;
+    pub const ORDERSYNC_LOCK_POLL_MILLIS: u16 = 100u16;
+    pub const ORDERAPP_SYNC_DELAY_SECS: u16 = 90u16;
+    pub const ORDERAPP_SYNC_MAX_HOURS: u16 = 72u16;
+    pub const ORDERAPP_SYNC_MAX_ITEMS: u16 = 200u16;
+    pub const ORDERAPP_SYNC_INTERVAL_SECS: u16 = 300u16;
+    pub const MAX_REPORT_CHARGES_PAGE: u16 = 500u16;
+    pub const DEFAULT_REPORT_CHARGES_PAGE: u16 = 100u16;
 }
 
 pub struct AppSharedState {
@@ -58,6 +65,7 @@ pub enum ShrStateInitProgress {
     DataStore,
     RpcContext,
     ExternalProcessor,
+    OrderSyncLockCache,
     AuthKeyStore(AuthKeystoreError),
 }
 
@@ -89,6 +97,13 @@ impl From<AppProcessorError> for ShrStateInitError {
         }
     }
 }
+impl From<OrderSyncLockError> for ShrStateInitError {
+    fn from(_value: OrderSyncLockError) -> Self {
+        Self {
+            progress: ShrStateInitProgress::OrderSyncLockCache,
+        }
+    }
+}
 impl From<AuthKeystoreError> for ShrStateInitError {
     fn from(detail: AuthKeystoreError) -> Self {
         Self {
@@ -120,17 +135,24 @@ impl AppSharedState {
             cfdntl.clone(),
             logctx.clone(),
         )?;
-        let dstore =
-            AppDataStoreContext::new(&cfg.api_server.data_store, cfdntl.clone(), logctx.clone())?;
+        let dstore = {
+            let d = AppDataStoreContext::new(
+                &cfg.api_server.data_store,
+                cfdntl.clone(),
+                logctx.clone(),
+            )?;
+            Arc::new(d)
+        };
         let _processors =
             app_processor_context(&cfg.api_server.third_parties, cfdntl, logctx.clone())?;
-        let ordersync_lockset = app_cache_order_sync_lock();
+        let ordersync_lockset =
+            app_cache_order_sync_lock(cfg.api_server.cache.as_ref(), dstore.clone())?;
         let auth_keys = AppAuthKeystore::try_create(&cfg.api_server.auth)?;
         Ok(Self {
             _config: Arc::new(cfg),
             _log_ctx: logctx,
             _ordersync_lockset: Arc::new(ordersync_lockset),
-            _dstore: Arc::new(dstore),
+            _dstore: dstore,
             _rpc_ctx: Arc::new(rpc_ctx),
             _processors: Arc::new(_processors),
             _auth_keys: Arc::new(Box::new(auth_keys)),
@@ -14,19 +14,21 @@ use ecommerce_common::error::AppErrorCode;
 use ecommerce_common::model::BaseProductIdentity;
 
 use super::{
-    Charge3partyStripeModel, OrderCurrencySnapshot, OrderLineModel, OrderLineModelSet,
-    PayLineAmountModel, PayoutAmountModel, PayoutModelError, RefundLineReqResolutionModel,
-    RefundReqResolutionModel,
+    Charge3partyPaypalModel, Charge3partyStripeModel, OrderCurrencySnapshot, OrderLineModel,
+    OrderLineModelSet, PayLineAmountModel, PayoutAmountModel, PayoutLineModel, PayoutModelError,
+    PlatformFeeScheduleModel, RefundLineReqResolutionModel, RefundReqResolutionModel,
+    StripeCheckoutPaymentStatusModel, StripeSessionStatusModel,
 };
 use crate::api::web::dto::{
-    ChargeAmountOlineDto, ChargeOlineErrorDto, ChargeRefreshRespDto, ChargeReqOrderDto,
-    ChargeRespErrorDto, ChargeStatusDto, OrderErrorReason,
+    CapturePayLineReqDto, ChargeAmountOlineDto, ChargeOlineErrorDto, ChargeRefreshRespDto,
+    ChargeReqOrderDto, ChargeRespErrorDto, ChargeStatusDto, OrderErrorReason,
 };
 use crate::hard_limit::{CREATE_CHARGE_SECONDS_INTERVAL, SECONDS_ORDERLINE_DISCARD_MARGIN};
 
 pub enum Charge3partyModel {
     Unknown,
     Stripe(Charge3partyStripeModel),
+    Paypal(Charge3partyPaypalModel),
 }
 
 mod token_inner {
@@ -131,18 +133,21 @@ impl Charge3partyModel {
         match self {
             Self::Unknown => Some(false),
             Self::Stripe(m) => m.pay_in_comfirmed(),
+            Self::Paypal(m) => m.pay_in_comfirmed(),
         }
     }
     fn status_dto(&self) -> ChargeStatusDto {
         match self {
             Self::Unknown => ChargeStatusDto::UnknownPsp,
             Self::Stripe(m) => m.status_dto(),
+            Self::Paypal(m) => m.status_dto(),
         }
     }
     pub(super) fn clone(&self) -> Self {
         match self {
             Self::Unknown => Self::Unknown,
             Self::Stripe(m) => Self::Stripe(m.inner_clone()),
+            Self::Paypal(m) => Self::Paypal(m.inner_clone()),
         }
     }
 } // end of impl Charge3partyModel
@@ -225,6 +230,46 @@ impl ChargeBuyerMetaModel {
     pub fn update_3party(&mut self, value: Charge3partyModel) {
         self._method = value;
     }
+    /// apply latest state of checkout session pushed from Stripe, return
+    /// `false` if the session is not the one recorded in this charge.
+    pub fn sync_stripe_session(
+        &mut self,
+        session_id: &str,
+        session_state: StripeSessionStatusModel,
+        payment_state: StripeCheckoutPaymentStatusModel,
+        time: DateTime<Utc>,
+    ) -> bool {
+        let m3pty = match &mut self._method {
+            Charge3partyModel::Stripe(s) if s.checkout_session_id.as_str() == session_id => s,
+            _others => return false,
+        };
+        m3pty.session_state = session_state;
+        m3pty.payment_state = payment_state;
+        if m3pty.pay_in_comfirmed().is_some() {
+            let new_state = BuyerPayInState::ProcessorCompleted(time);
+            self.update_progress(&new_state);
+        }
+        true
+    }
+    /// apply total amount refunded pushed from Stripe, which only grows in
+    /// Stripe while the events might be delivered out of order. Return `None`
+    /// if the payment intent is not the one recorded in this charge, otherwise
+    /// return whether the refunded amount is modified.
+    pub fn sync_stripe_refund(
+        &mut self,
+        payment_intent_id: &str,
+        amount_refunded: i64,
+    ) -> Option<bool> {
+        let m3pty = match &mut self._method {
+            Charge3partyModel::Stripe(s) if s.payment_intent_id.as_str() == payment_intent_id => s,
+            _others => return None,
+        };
+        let modified = m3pty.amount_refunded < amount_refunded;
+        if modified {
+            m3pty.amount_refunded = amount_refunded;
+        }
+        Some(modified)
+    }
     #[rustfmt::skip]
     pub(crate) fn into_parts(self) -> (u32, DateTime<Utc>, String, BuyerPayInState, Charge3partyModel)
     {
@@ -338,9 +383,82 @@ impl ChargeBuyerModel {
             .sum::<Decimal>()
     }
 
+    /// determine the items to capture for a merchant, the quantity of each
+    /// line cannot exceed the remaining items which are not yet refunded,
+    /// rejected, or captured by previous payouts
+    pub(super) fn capture_lines(
+        &self,
+        seller_id: u32,
+        captured: &HashMap<(u64, u16), (u32, Decimal)>,
+        req_lines: Option<Vec<CapturePayLineReqDto>>,
+    ) -> Result<Vec<PayoutLineModel>, PayoutModelError> {
+        let capturable = self
+            .lines
+            .iter()
+            .filter(|line| line.pid.store_id == seller_id)
+            .map(|line| {
+                let key = (line.pid.product_id, line.attr_set_seq);
+                let remain = line.amount_remain();
+                let (num_captured, amt_captured) =
+                    captured.get(&key).copied().unwrap_or((0, Decimal::ZERO));
+                let qty_avail = remain.qty.saturating_sub(num_captured);
+                let amt_avail = remain.total.saturating_sub(amt_captured);
+                (key, (remain.unit, qty_avail, amt_avail))
+            })
+            .collect::<HashMap<_, _>>();
+        let requested = if let Some(reqs) = req_lines {
+            let mut merged: Vec<((u64, u16), u32)> = Vec::new();
+            for r in reqs {
+                let key = (r.product_id, r.attr_set_seq);
+                if let Some(v) = merged.iter_mut().find(|v| v.0 == key) {
+                    v.1 += r.quantity;
+                } else {
+                    merged.push((key, r.quantity));
+                }
+            }
+            merged
+        } else {
+            let mut all = capturable
+                .iter()
+                .map(|(k, v)| (*k, v.1))
+                .filter(|v| v.1 > 0)
+                .collect::<Vec<_>>();
+            all.sort_by_key(|v| v.0);
+            all
+        };
+        let mut out = Vec::new();
+        for (key, qty) in requested {
+            let (unit, qty_avail, amt_avail) = capturable
+                .get(&key)
+                .copied()
+                .ok_or(PayoutModelError::CaptureLineNotExist(key.0, key.1))?;
+            if qty > qty_avail {
+                return Err(PayoutModelError::CaptureQtyExceedLimit(
+                    key.0, key.1, qty_avail,
+                ));
+            } else if qty > 0 {
+                // the last capture of a line takes the rest of its amount, which
+                // avoids accumulating rounding error over several partial captures
+                let amount = if qty == qty_avail {
+                    amt_avail
+                } else {
+                    unit * Decimal::new(qty as i64, 0)
+                };
+                out.push(PayoutLineModel::from((key.0, key.1, qty, amount)));
+            }
+        }
+        Ok(out)
+    } // end of fn capture_lines
+
+    /// the amount to capture is the sum of the given lines, which cannot
+    /// exceed the remaining balance of the merchant in this charge, the
+    /// platform fee is deducted from the amount transferred to the merchant.
     pub(super) fn capture_amount(
         &self,
         seller_id: u32,
+        captured_amount: Decimal,
+        lines: &[PayoutLineModel],
+        fee_arg: (&PlatformFeeScheduleModel, bool),
     ) -> Result<PayoutAmountModel, PayoutModelError> {
         let currency_seller = self
             .get_seller_currency(seller_id)
@@ -350,12 +468,55 @@ impl ChargeBuyerModel {
             .get_buyer_currency()
             .ok_or("missing-currency-buyer".to_string())
             .map_err(|d| PayoutModelError::AmountEstimate(AppErrorCode::DataCorruption, d))?;
-        let tot_amt_buyer = self.estimate_avail_lines_amount(seller_id);
-        let args = (tot_amt_buyer, currency_seller, currency_buyer);
-        PayoutAmountModel::try_from(args)
+        let remain_buyer = self.estimate_avail_lines_amount(seller_id) - captured_amount;
+        let tot_amt_buyer = lines.iter().map(|v| v.amount_buyer()).sum::<Decimal>();
+        if tot_amt_buyer <= Decimal::ZERO || tot_amt_buyer > remain_buyer {
+            return Err(PayoutModelError::AmountNotEnough(
+                remain_buyer,
+                tot_amt_buyer,
+            ));
+        }
+        let (fee_sched, flat_included) = fee_arg;
+        let fee_buyer = fee_sched
+            .estimate(
+                &currency_buyer.label,
+                *self.meta.create_time(),
+                tot_amt_buyer,
+                flat_included,
+            )
+            .map_err(PayoutModelError::PlatformFee)?;
+        let args = (tot_amt_buyer, fee_buyer, currency_seller, currency_buyer);
+        let out = PayoutAmountModel::try_from(args)?;
+        if out.base() > Decimal::ZERO {
+            Ok(out)
+        } else {
+            Err(PayoutModelError::AmountNotEnough(
+                remain_buyer,
+                tot_amt_buyer,
+            ))
+        }
     }
 } // end of impl ChargeBuyerModel
 
+type ChargeRefundMapCvtArgs = (u32, Vec<(DateTime<Utc>, Vec<ChargeLineBuyerModel>)>);
+
+impl From<ChargeRefundMapCvtArgs> for ChargeRefundMap {
+    /// collect the amount refunded so far from the saved charge lines of
+    /// a buyer, each charge is identified by its creation time
+    fn from(value: ChargeRefundMapCvtArgs) -> Self {
+        let (buyer_id, charges) = value;
+        let iter = charges.into_iter().map(|(ctime, clines)| {
+            let iter = clines.into_iter().map(|cline| {
+                let key = (cline.pid, cline.attr_set_seq);
+                (key, (cline.amount_refunded, cline.num_rejected))
+            });
+            let inner_map = ChargeRefundLineMap(HashMap::from_iter(iter));
+            ((buyer_id, ctime), inner_map)
+        });
+        Self(HashMap::from_iter(iter))
+    }
+}
+
 impl ChargeRefundMap {
     pub(crate) fn into_inner(self) -> HashMap<(u32, DateTime<Utc>), ChargeRefundLineMap> {
         self.0
@@ -366,10 +527,13 @@ impl ChargeRefundMap {
         self.0.get(&key)
     }
 
-    pub fn build(rfd_rslv_ms: &[RefundReqResolutionModel]) -> Self {
+    pub fn build<'a, I>(rfd_rslv_ms: I) -> Self
+    where
+        I: IntoIterator<Item = &'a RefundReqResolutionModel>,
+    {
         let mut out = Self::default();
         rfd_rslv_ms
-            .iter()
+            .into_iter()
             .map(|rslv_m| {
                 let charge_id = rslv_m.charge_id();
                 let inner_map = out.0.entry(charge_id).or_default();
@@ -379,6 +543,23 @@ impl ChargeRefundMap {
         out
     } // end of fn to_chargeline_map
 
+    /// sum up refunded amount of the same order line across all the charges
+    pub fn aggregate_lines(&self) -> ChargeRefundLineMap {
+        let mut out = ChargeRefundLineMap::default();
+        self.0
+            .values()
+            .flat_map(|inner_map| inner_map.0.iter())
+            .map(|(k, v)| {
+                let entry = out.0.entry(k.clone()).or_default();
+                entry.0.unit = v.0.unit;
+                entry.0.total += v.0.total;
+                entry.0.qty += v.0.qty;
+                entry.1 += v.1;
+            })
+            .count();
+        out
+    }
+
     fn merge(
         inner_map: &mut ChargeRefundLineMap,
         rlines: &[RefundLineReqResolutionModel],
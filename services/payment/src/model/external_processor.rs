@@ -28,6 +28,10 @@ pub struct Charge3partyStripeModel {
     pub payment_intent_id: String, // applied in refund workflow
     pub transfer_group: String,    // `transfer-group` field from payment intent object
     pub expiry: DateTime<Utc>,
+    // total amount refunded so far in smallest unit of the charged currency,
+    // reported by Stripe, absent in the charges saved before refund happens
+    #[serde(default)]
+    pub amount_refunded: i64,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -162,6 +166,7 @@ impl Charge3partyStripeModel {
             payment_intent_id: self.payment_intent_id.clone(),
             transfer_group: self.transfer_group.clone(),
             expiry: self.expiry,
+            amount_refunded: self.amount_refunded,
         }
     }
 } // end of impl Charge3partyStripeModel
@@ -180,6 +185,26 @@ impl Merchant3partyStripeModel {
         // , this payment application hasn't supported that yet.
         self.payouts_enabled && self.tos_accepted.is_some() && tx_active
     }
+    /// merge latest state of the connected account pushed from Stripe, the
+    /// saved account link is kept until the merchant is able to receive payout,
+    /// return whether any field is modified
+    pub fn sync_account(&mut self, latest: Self) -> Result<bool, String> {
+        if self.id.as_str() != latest.id.as_str() {
+            return Err(format!("account-id, saved:{}, new:{}", self.id, latest.id));
+        }
+        let serial_orig = serde_json::to_value(&*self).map_err(|e| e.to_string())?;
+        let update_link = if latest.can_perform_payout() {
+            None
+        } else {
+            self.update_link.take()
+        };
+        *self = Self {
+            update_link,
+            ..latest
+        };
+        let serial_new = serde_json::to_value(&*self).map_err(|e| e.to_string())?;
+        Ok(serial_orig != serial_new)
+    }
 }
 
 type PayoutStripeCvtArgs = (String, String, Option<String>, Option<Decimal>);
@@ -214,6 +239,27 @@ impl Payout3partyStripeModel {
             Ok(())
         }
     }
+    /// compare the transfer object reported by Stripe with the saved payout
+    pub fn validate_transfer(
+        &self,
+        transfer_id: &str,
+        acct_id: &str,
+        amount: Decimal,
+    ) -> Result<(), String> {
+        if self.acct_id.as_str() != acct_id {
+            Err(format!("account-id, expect:{}", self.acct_id))
+        } else if self
+            .transfer_id
+            .as_deref()
+            .is_some_and(|v| v != transfer_id)
+        {
+            Err(format!("transfer-id, expect:{:?}", self.transfer_id))
+        } else if self.amount.is_some_and(|v| v != amount) {
+            Err(format!("amount, expect:{:?}, actual:{amount}", self.amount))
+        } else {
+            Ok(())
+        }
+    }
     pub(crate) fn transfer_group(&self) -> &str {
         self.tx_grp.as_str()
     }
@@ -241,3 +287,220 @@ impl Payout3partyStripeModel {
         i64::try_from(mantissa).map_err(|e| format!("amount-too-large: {mantissa}, {:?}", e))
     }
 } // end of impl Payout3partyStripeModel
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum PaypalOrderStatusModel {
+    Created,
+    Saved,
+    Approved,
+    Voided,
+    Completed,
+    PayerActionRequired,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Charge3partyPaypalModel {
+    pub order_id: String,
+    pub status: PaypalOrderStatusModel,
+    pub capture_id: Option<String>, // applied in refund workflow
+    pub reference_id: String,       // `reference_id` field of the purchase unit
+    pub expiry: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PaypalActionLinkModel {
+    pub url: String,
+    pub expiry: DateTime<Utc>,
+}
+#[derive(Serialize, Deserialize)]
+pub struct Merchant3partyPaypalModel {
+    // generated by this application on partner referral, it is the only
+    // way to look up the merchant before the onboarding is complete
+    pub tracking_id: String,
+    // payer ID of the merchant account in PayPal, available once the
+    // merchant grants permissions to this platform
+    pub merchant_id: Option<String>,
+    pub email: Option<String>,
+    pub payments_receivable: bool,
+    pub primary_email_confirmed: bool,
+    pub created: DateTime<Utc>,
+    pub action_link: Option<PaypalActionLinkModel>,
+}
+
+#[derive(Clone)]
+pub struct Payout3partyPaypalModel {
+    ref_id: String,   // `reference_id` field of the purchase unit in the order
+    payee_id: String, // merchant ID in PayPal
+    batch_id: Option<String>,
+    amount: Option<Decimal>,
+}
+
+mod paypal_common {
+    use super::{CurrencyDto, Decimal};
+
+    /// PayPal represents amount as decimal string, the number of digits in
+    /// fraction part has to match the currency, extra digits are truncated
+    pub(super) fn amount_represent(orig: Decimal, currency: CurrencyDto) -> String {
+        let scale = currency.amount_fraction_scale();
+        let mut out = orig.trunc_with_scale(scale);
+        out.rescale(scale);
+        out.to_string()
+    }
+} // end of mod paypal_common
+
+impl PaypalOrderStatusModel {
+    fn status_dto(&self) -> ChargeStatusDto {
+        match self {
+            Self::Created | Self::Saved | Self::PayerActionRequired => {
+                ChargeStatusDto::PspProcessing
+            }
+            // buyer approved the order, this application should capture
+            // the payment next time the charge status is refreshed
+            Self::Approved => ChargeStatusDto::PspProcessing,
+            Self::Completed => ChargeStatusDto::InternalSyncing,
+            Self::Voided => ChargeStatusDto::PspRefused,
+        }
+    }
+}
+
+impl Charge3partyPaypalModel {
+    pub(super) fn status_dto(&self) -> ChargeStatusDto {
+        let t_now = Local::now().to_utc();
+        let pending = !matches!(
+            self.status,
+            PaypalOrderStatusModel::Completed | PaypalOrderStatusModel::Voided
+        );
+        if pending && self.expiry < t_now {
+            ChargeStatusDto::SessionExpired
+        } else {
+            self.status.status_dto()
+        }
+    }
+    pub(super) fn pay_in_comfirmed(&self) -> Option<bool> {
+        match &self.status {
+            PaypalOrderStatusModel::Completed => Some(self.capture_id.is_some()),
+            PaypalOrderStatusModel::Voided => Some(false),
+            _others => None,
+        }
+    }
+    pub(crate) fn capture_required(&self) -> bool {
+        matches!(self.status, PaypalOrderStatusModel::Approved)
+    }
+    pub(crate) fn amount_represent(orig: Decimal, c: CurrencyDto) -> String {
+        paypal_common::amount_represent(orig, c)
+    }
+    pub(super) fn inner_clone(&self) -> Self {
+        Self {
+            order_id: self.order_id.clone(),
+            status: self.status.clone(),
+            capture_id: self.capture_id.clone(),
+            reference_id: self.reference_id.clone(),
+            expiry: self.expiry,
+        }
+    }
+} // end of impl Charge3partyPaypalModel
+
+impl Merchant3partyPaypalModel {
+    pub(crate) fn renew_link_required(&self) -> bool {
+        let t_now = Local::now().to_utc();
+        self.action_link.as_ref().map_or(true, |v| v.expiry < t_now)
+    }
+    pub(crate) fn can_perform_payout(&self) -> bool {
+        self.merchant_id.is_some() && self.payments_receivable && self.primary_email_confirmed
+    }
+}
+
+type PayoutPaypalCvtArgs = (String, String, Option<String>, Option<Decimal>);
+
+impl From<PayoutPaypalCvtArgs> for Payout3partyPaypalModel {
+    #[rustfmt::skip]
+    fn from(value: PayoutPaypalCvtArgs) -> Self {
+        let (ref_id, payee_id, batch_id, amount) = value;
+        Self { ref_id, payee_id, batch_id, amount }
+    }
+}
+
+impl Payout3partyPaypalModel {
+    pub(super) fn try_new(
+        c3p: &Charge3partyPaypalModel,
+        m3p: &Merchant3partyPaypalModel,
+    ) -> Option<Self> {
+        m3p.merchant_id.as_ref().map(|payee_id| Self {
+            ref_id: c3p.reference_id.clone(),
+            payee_id: payee_id.clone(),
+            batch_id: None,
+            amount: None,
+        })
+    }
+    pub(super) fn validate(
+        &self,
+        c3p: &Charge3partyPaypalModel,
+        m3p: &Merchant3partyPaypalModel,
+    ) -> Result<(), String> {
+        if self.ref_id.as_str() != c3p.reference_id.as_str() {
+            Err("reference-id".to_string())
+        } else if m3p.merchant_id.as_deref() != Some(self.payee_id.as_str()) {
+            Err("payee-id".to_string())
+        } else {
+            Ok(())
+        }
+    }
+    pub fn reference_id(&self) -> &str {
+        self.ref_id.as_str()
+    }
+    pub fn payee_id(&self) -> &str {
+        self.payee_id.as_str()
+    }
+    pub fn batch_id(&self) -> Option<&str> {
+        self.batch_id.as_deref()
+    }
+    pub(crate) fn set_batch_id(&mut self, value: String) {
+        self.batch_id = Some(value);
+    }
+    pub fn amount(&self) -> Option<Decimal> {
+        self.amount
+    }
+    pub(crate) fn set_amount(&mut self, value: Decimal) {
+        self.amount = Some(value);
+    }
+    pub(crate) fn amount_represent(orig: Decimal, currency: CurrencyDto) -> String {
+        paypal_common::amount_represent(orig, currency)
+    }
+} // end of impl Payout3partyPaypalModel
+
+/// event pushed from Stripe platform to the webhook endpoint of this
+/// application, only the event types this application subscribes are
+/// parsed in detail.
+pub struct StripeWebhookEventModel {
+    pub id: String,
+    pub type_: String,
+    pub created: DateTime<Utc>,
+    pub detail: StripeWebhookEventDetail,
+}
+
+pub enum StripeWebhookEventDetail {
+    // `checkout.session.completed` or `checkout.session.expired`
+    CheckoutSession {
+        session_id: String,
+        client_reference_id: Option<String>,
+        session_state: StripeSessionStatusModel,
+        payment_state: StripeCheckoutPaymentStatusModel,
+    },
+    ChargeRefunded {
+        payment_intent_id: Option<String>,
+        // serialized charge ID, see `Charge3partyStripeModel::transfer_group`
+        transfer_group: Option<String>,
+        amount_refunded: i64, // smallest unit of the charged currency
+    },
+    AccountUpdated(Merchant3partyStripeModel),
+    // `transfer.created`, `transfer.updated`, or `transfer.reversed`
+    Transfer {
+        reversed: bool,
+        transfer_id: String,
+        acct_id: String,
+        transfer_group: String,
+        amount: Decimal,
+    },
+    Unsupported,
+}
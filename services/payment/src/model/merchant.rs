@@ -4,7 +4,7 @@ use chrono::{DateTime, Local, Utc};
 
 use ecommerce_common::api::rpc::dto::{StoreProfileReplicaDto, StoreStaffRepDto};
 
-use super::external_processor::Merchant3partyStripeModel;
+use super::external_processor::{Merchant3partyPaypalModel, Merchant3partyStripeModel};
 
 #[derive(Debug)]
 pub enum MerchantModelError {
@@ -16,6 +16,7 @@ pub enum MerchantModelError {
 
 pub enum Merchant3partyModel {
     Stripe(Merchant3partyStripeModel),
+    Paypal(Merchant3partyPaypalModel),
     Unknown,
 }
 
@@ -108,6 +109,7 @@ impl Merchant3partyModel {
     pub(super) fn can_perform_payout(&self) -> bool {
         match self {
             Self::Stripe(s) => s.can_perform_payout(),
+            Self::Paypal(p) => p.can_perform_payout(),
             Self::Unknown => false,
         }
     }
@@ -3,8 +3,10 @@ mod external_processor;
 mod merchant;
 mod order_replica;
 mod payout;
+mod platform_fee;
 mod refund;
 mod reporting;
+mod webhook;
 
 use rust_decimal::Decimal;
 use std::str::FromStr;
@@ -18,23 +20,36 @@ pub use self::charge::{
     ChargeLineBuyerModel, ChargeRefundLineMap, ChargeRefundMap, ChargeToken,
 };
 pub use self::external_processor::{
-    Charge3partyStripeModel, Merchant3partyStripeModel, Payout3partyStripeModel,
-    StripeAccountCapabilityModel, StripeAccountCapableState, StripeAccountLinkModel,
-    StripeAccountSettingModel, StripeCheckoutPaymentStatusModel, StripeSessionStatusModel,
+    Charge3partyPaypalModel, Charge3partyStripeModel, Merchant3partyPaypalModel,
+    Merchant3partyStripeModel, Payout3partyPaypalModel, Payout3partyStripeModel,
+    PaypalActionLinkModel, PaypalOrderStatusModel, StripeAccountCapabilityModel,
+    StripeAccountCapableState, StripeAccountLinkModel, StripeAccountSettingModel,
+    StripeCheckoutPaymentStatusModel, StripeSessionStatusModel, StripeWebhookEventDetail,
+    StripeWebhookEventModel,
 };
 pub use self::merchant::{Merchant3partyModel, MerchantModelError, MerchantProfileModel};
 pub use self::order_replica::{
     OrderCurrencySnapshot, OrderLineModel, OrderLineModelSet, OrderModelError,
 };
 pub(crate) use self::payout::PayoutInnerModel;
-pub use self::payout::{Payout3partyModel, PayoutAmountModel, PayoutModel, PayoutModelError};
+pub use self::payout::{
+    Payout3partyModel, PayoutAmountModel, PayoutLineModel, PayoutModel, PayoutModelError,
+};
+pub use self::platform_fee::{
+    PlatformFeeModelError, PlatformFeeRuleModel, PlatformFeeScheduleModel,
+};
 use self::refund::RefundLineReqResolutionModel;
 pub(crate) use self::refund::RefundReqRslvInnerModel;
 pub use self::refund::{
-    OLineRefundModel, OrderRefundModel, RefundErrorParseOline, RefundLineQtyRejectModel,
-    RefundLineResolveAmountModel, RefundModelError, RefundReqResolutionModel,
+    OLineRefundModel, OrderRefundModel, RefundErrorParseOline, RefundIdempotencyModel,
+    RefundLineQtyRejectModel, RefundLineResolveAmountModel, RefundModelError,
+    RefundReqResolutionModel, RefundResolutionHistoryModel,
+};
+pub use self::reporting::{
+    MerchantReportChargeModel, MerchantReportPayoutModel, MerchantReportRefundModel,
+    ReportModelError,
 };
-pub use self::reporting::{MerchantReportChargeModel, ReportModelError};
+pub use self::webhook::Webhook3partyEventModel;
 
 #[derive(Debug)]
 pub enum PayLineAmountError {
@@ -64,6 +79,7 @@ pub struct PayLineAmountModel {
 #[derive(Copy, Clone)]
 pub enum Label3party {
     Stripe,
+    Paypal,
 }
 
 impl TryFrom<(u32, PayAmountDto, CurrencyDto)> for PayLineAmountModel {
@@ -118,6 +134,7 @@ impl<'a> TryFrom<&'a str> for Label3party {
     fn try_from(value: &'a str) -> Result<Self, Self::Error> {
         match value {
             "Stripe" => Ok(Self::Stripe),
+            "Paypal" => Ok(Self::Paypal),
             _others => Err(value),
         }
     }
@@ -127,6 +144,7 @@ impl<'a> From<&'a StoreOnboardReqDto> for Label3party {
     fn from(value: &'a StoreOnboardReqDto) -> Self {
         match value {
             StoreOnboardReqDto::Stripe(_) => Self::Stripe,
+            StoreOnboardReqDto::Paypal(_) => Self::Paypal,
         }
     }
 }
@@ -136,6 +154,7 @@ impl<'a> TryFrom<&'a Charge3partyModel> for Label3party {
     fn try_from(value: &'a Charge3partyModel) -> Result<Self, Self::Error> {
         match value {
             Charge3partyModel::Stripe(_) => Ok(Self::Stripe),
+            Charge3partyModel::Paypal(_) => Ok(Self::Paypal),
             Charge3partyModel::Unknown => Err("unknown".to_string()),
         }
     }
@@ -145,6 +164,7 @@ impl<'a> From<&'a Payout3partyModel> for Label3party {
     fn from(value: &'a Payout3partyModel) -> Self {
         match value {
             Payout3partyModel::Stripe(_) => Self::Stripe,
+            Payout3partyModel::Paypal(_) => Self::Paypal,
         }
     }
 }
@@ -153,6 +173,7 @@ impl ToString for Label3party {
     fn to_string(&self) -> String {
         let s = match self {
             Self::Stripe => "Stripe",
+            Self::Paypal => "Paypal",
         };
         s.to_string()
     }
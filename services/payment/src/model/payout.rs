@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use chrono::{DateTime, Local, Utc};
 use rust_decimal::Decimal;
 
@@ -6,9 +8,10 @@ use ecommerce_common::error::AppErrorCode;
 
 use super::{
     Charge3partyModel, ChargeBuyerMetaModel, ChargeBuyerModel, Merchant3partyModel,
-    MerchantProfileModel, OrderCurrencySnapshot, Payout3partyStripeModel,
+    MerchantProfileModel, OrderCurrencySnapshot, Payout3partyPaypalModel, Payout3partyStripeModel,
+    PlatformFeeModelError, PlatformFeeScheduleModel,
 };
-use crate::api::web::dto::CapturePay3partyRespDto;
+use crate::api::web::dto::{CapturePay3partyRespDto, CapturePayLineReqDto};
 use crate::hard_limit::CURRENCY_RATE_PRECISION;
 
 #[derive(Debug)]
@@ -22,32 +25,54 @@ pub enum PayoutModelError {
     MerchantPermissionDenied(u32),
     Init3partyFailure,
     Invalid3partyParams(String),
+    // product-id and attribute-set sequence of the line which does not exist
+    // in the charge, or does not belong to the merchant
+    CaptureLineNotExist(u64, u16),
+    // product-id, attribute-set sequence, and quantity which is still capturable
+    CaptureQtyExceedLimit(u64, u16, u32),
+    PlatformFee(PlatformFeeModelError),
 }
 
 pub enum Payout3partyModel {
     Stripe(Payout3partyStripeModel),
+    Paypal(Payout3partyPaypalModel),
 }
 
 pub struct PayoutAmountModel {
-    /// total amount to transfer in buyer configured currency
+    /// total amount captured in buyer configured currency, including platform fee
     total_buyer: Decimal,
-    /// total amount to transfer in merchant configured currency
+    /// platform fee in buyer configured currency, deducted from the captured amount
+    fee_buyer: Decimal,
+    /// total amount to transfer in merchant configured currency, excluding platform fee
     total_mc: Decimal,
-    /// total amount to transfer in base currency (USD in this project)
+    /// total amount to transfer in base currency (USD in this project), excluding platform fee
     total_bs: Decimal,
     target_rate: Decimal, // the conversion rate from buyer's currency to seller's
     currency_seller: OrderCurrencySnapshot,
     currency_buyer: OrderCurrencySnapshot,
 }
 
+/// the items of a charge line captured in a single payout
+pub struct PayoutLineModel {
+    product_id: u64,
+    attr_set_seq: u16,
+    qty: u32,
+    /// total amount of the captured items in buyer configured currency
+    amount_buyer: Decimal,
+}
+
 pub(crate) struct PayoutInnerModel {
     merchant_id: u32,
     capture_time: DateTime<Utc>,
     buyer_id: u32, // note the 2 fields `buyer-id` and `charge-ctime` uniquely identify
     // a single charge object.
     charge_ctime: DateTime<Utc>, // the time the charge was created
-    storestaff_id: u32,          // for logging and monitoring purpose
+    // a merchant may capture a charge several times, e.g. shipping the items
+    // in several batches, the sequence number distinguishes these payouts.
+    seq: u16,
+    storestaff_id: u32, // for logging and monitoring purpose
     amount: PayoutAmountModel,
+    lines: Vec<PayoutLineModel>,
     order_id: String,
 }
 pub struct PayoutModel {
@@ -57,8 +82,8 @@ pub struct PayoutModel {
 
 #[rustfmt::skip]
 type PayoutModelCvtArgs2 = (
-    u32, DateTime<Utc>, u32, DateTime<Utc>, String,
-    u32, PayoutAmountModel, Payout3partyModel,
+    u32, DateTime<Utc>, u32, DateTime<Utc>, String, u32,
+    u16, PayoutAmountModel, Vec<PayoutLineModel>, Payout3partyModel,
 );
 
 impl From<PayoutModelCvtArgs2> for PayoutModel {
@@ -66,29 +91,39 @@ impl From<PayoutModelCvtArgs2> for PayoutModel {
     fn from(value: PayoutModelCvtArgs2) -> Self {
         let (
             merchant_id, capture_time, buyer_id, charge_ctime,
-            order_id, storestaff_id, amount, _p3pty
+            order_id, storestaff_id, seq, amount, lines, _p3pty
         ) = value;
         let _inner = PayoutInnerModel {
             merchant_id, capture_time, buyer_id, charge_ctime,
-            storestaff_id, amount, order_id
+            seq, storestaff_id, amount, lines, order_id
         };
         Self { _inner, _p3pty }
     }
 }
 
+/// Note the payout history contains all previous payouts of the same charge
+/// to the same merchant, the lines to capture are optional, if not specified
+/// , all the items which can still be captured will be paid out to the merchant.
 type PayoutModelCvtArgs = (
     ChargeBuyerModel,
     MerchantProfileModel,
     Merchant3partyModel,
     u32,
-    Option<PayoutModel>,
+    Vec<PayoutModel>,
+    Option<Vec<CapturePayLineReqDto>>,
+    PlatformFeeScheduleModel,
 );
 
 impl TryFrom<PayoutModelCvtArgs> for PayoutModel {
     type Error = PayoutModelError;
     fn try_from(value: PayoutModelCvtArgs) -> Result<Self, Self::Error> {
-        let (charge_m, merc_prof, merc_3pt, storestaff_id, opt_old_payout) = value;
-        if let Some(v) = &opt_old_payout {
+        let (charge_m, merc_prof, merc_3pt, storestaff_id, old_payouts, req_lines, fee_sched) =
+            value;
+        if fee_sched.merchant_id() != merc_prof.id {
+            let ids = (merc_prof.id, fee_sched.merchant_id());
+            return Err(PayoutModelError::MerchantInconsistent(ids.0, ids.1));
+        }
+        for v in old_payouts.iter() {
             let id0 = v.merchant_id();
             let id1 = merc_prof.id;
             if id0 != id1 {
@@ -102,19 +137,29 @@ impl TryFrom<PayoutModelCvtArgs> for PayoutModel {
 
         let _p3pty = {
             let arg = (charge_m.meta.method_3party(), &merc_3pt);
-            opt_old_payout
-                .as_ref()
-                .map_or(Payout3partyModel::try_from(arg), |v| {
-                    v._p3pty.try_clone(arg.0, arg.1)
-                })?
+            if let Some(v) = old_payouts.last() {
+                v._p3pty.validate(arg.0, arg.1)?;
+            }
+            Payout3partyModel::try_from(arg)?
         };
-        let amount_tot = charge_m.capture_amount(merc_prof.id)?;
 
-        let amount_new = if let Some(v) = opt_old_payout.as_ref() {
-            amount_tot.try_update(&v._inner.amount)?
-        } else {
-            amount_tot
-        };
+        let captured = Self::captured_lines(&old_payouts);
+        let lines = charge_m.capture_lines(merc_prof.id, &captured, req_lines)?;
+        let captured_amount = old_payouts
+            .iter()
+            .map(|v| v.amount_buyer())
+            .sum::<Decimal>();
+        // flat part of the platform fee is charged only in the first payout
+        let fee_arg = (&fee_sched, old_payouts.is_empty());
+        let amount = charge_m.capture_amount(merc_prof.id, captured_amount, &lines, fee_arg)?;
+        for v in old_payouts.iter() {
+            amount.validate_currency(&v._inner.amount)?;
+        }
+        let seq = old_payouts
+            .iter()
+            .map(|v| v._inner.seq + 1)
+            .max()
+            .unwrap_or(0);
 
         let _inner = PayoutInnerModel {
             merchant_id: merc_prof.id,
@@ -122,7 +167,9 @@ impl TryFrom<PayoutModelCvtArgs> for PayoutModel {
             buyer_id: charge_m.meta.owner(),
             charge_ctime: *charge_m.meta.create_time(),
             order_id: charge_m.meta.oid().clone(),
-            amount: amount_new,
+            seq,
+            amount,
+            lines,
             storestaff_id,
         };
         Ok(Self { _inner, _p3pty })
@@ -143,6 +190,20 @@ impl PayoutModel {
         }
         Ok(())
     }
+    /// accumulated quantity and amount of each charge line captured in given payouts
+    fn captured_lines(payouts: &[PayoutModel]) -> HashMap<(u64, u16), (u32, Decimal)> {
+        let mut out = HashMap::new();
+        payouts
+            .iter()
+            .flat_map(|v| v._inner.lines.iter())
+            .map(|line| {
+                let entry = out.entry(line.id()).or_insert((0u32, Decimal::ZERO));
+                entry.0 += line.qty;
+                entry.1 += line.amount_buyer;
+            })
+            .count();
+        out
+    }
     pub(crate) fn into_parts(self) -> (PayoutInnerModel, Payout3partyModel) {
         let Self { _inner, _p3pty } = self;
         (_inner, _p3pty)
@@ -162,15 +223,51 @@ impl PayoutModel {
     pub fn amount_buyer(&self) -> Decimal {
         self._inner.amount_buyer()
     }
+    pub fn platform_fee(&self) -> Decimal {
+        self._inner.platform_fee()
+    }
+    pub fn seq(&self) -> u16 {
+        self._inner.seq()
+    }
+    pub fn lines(&self) -> &[PayoutLineModel] {
+        &self._inner.lines
+    }
     pub fn thirdparty(&self) -> &Payout3partyModel {
         &self._p3pty
     }
 } // end of impl PayoutModel
 
+type PayoutLineCvtArgs = (u64, u16, u32, Decimal);
+
+impl From<PayoutLineCvtArgs> for PayoutLineModel {
+    #[rustfmt::skip]
+    fn from(value: PayoutLineCvtArgs) -> Self {
+        let (product_id, attr_set_seq, qty, amount_buyer) = value;
+        Self { product_id, attr_set_seq, qty, amount_buyer }
+    }
+}
+
+impl PayoutLineModel {
+    pub fn id(&self) -> (u64, u16) {
+        (self.product_id, self.attr_set_seq)
+    }
+    pub fn qty(&self) -> u32 {
+        self.qty
+    }
+    pub fn amount_buyer(&self) -> Decimal {
+        self.amount_buyer
+    }
+    #[rustfmt::skip]
+    pub(crate) fn into_parts(self) -> PayoutLineCvtArgs {
+        let Self { product_id, attr_set_seq, qty, amount_buyer } = self;
+        (product_id, attr_set_seq, qty, amount_buyer)
+    }
+}
+
 #[rustfmt::skip]
 type PayoutInnerDecomposedArgs = (
-    u32, DateTime<Utc>, u32, DateTime<Utc>,
-    u32, PayoutAmountModel, String,
+    u32, DateTime<Utc>, u32, DateTime<Utc>, u16,
+    u32, PayoutAmountModel, Vec<PayoutLineModel>, String,
 );
 
 impl PayoutInnerModel {
@@ -180,6 +277,12 @@ impl PayoutInnerModel {
     pub(crate) fn referenced_charge(&self) -> (u32, DateTime<Utc>) {
         (self.buyer_id, self.charge_ctime)
     }
+    pub(crate) fn seq(&self) -> u16 {
+        self.seq
+    }
+    pub(crate) fn take_lines(&mut self) -> Vec<PayoutLineModel> {
+        self.lines.split_off(0)
+    }
     pub(crate) fn amount_merchant(&self) -> (Decimal, Decimal, &OrderCurrencySnapshot) {
         self.amount.merchant()
     }
@@ -189,49 +292,58 @@ impl PayoutInnerModel {
     pub(crate) fn amount_buyer(&self) -> Decimal {
         self.amount.buyer()
     }
+    pub(crate) fn platform_fee(&self) -> Decimal {
+        self.amount.fee()
+    }
     #[rustfmt::skip]
     pub(crate) fn into_parts(self) -> PayoutInnerDecomposedArgs {
         let Self {
-            merchant_id, capture_time, buyer_id, charge_ctime,
-            storestaff_id, amount: amount_m, order_id
+            merchant_id, capture_time, buyer_id, charge_ctime, seq,
+            storestaff_id, amount: amount_m, lines, order_id
         } = self;
-        (merchant_id, capture_time, buyer_id, charge_ctime,
-         storestaff_id, amount_m, order_id)
+        (merchant_id, capture_time, buyer_id, charge_ctime, seq,
+         storestaff_id, amount_m, lines, order_id)
     }
 } // end of impl PayoutInnerModel
 
+/// total amount captured and platform fee, both in buyer's currency, followed
+/// by currency snapshots of the seller and the buyer
 #[rustfmt::skip]
-type PayoutAmountCvtArgs = (Decimal, OrderCurrencySnapshot, OrderCurrencySnapshot);
+type PayoutAmountCvtArgs = (Decimal, Decimal, OrderCurrencySnapshot, OrderCurrencySnapshot);
 
 impl TryFrom<PayoutAmountCvtArgs> for PayoutAmountModel {
     type Error = PayoutModelError;
     #[rustfmt::skip]
     fn try_from(value: PayoutAmountCvtArgs) -> Result<Self, Self::Error> {
-        let (tot_amt_buyer, currency_seller, currency_buyer) = value;
+        let (tot_amt_buyer, fee_buyer, currency_seller, currency_buyer) = value;
+        if fee_buyer.is_sign_negative() || fee_buyer > tot_amt_buyer {
+            return Err(PayoutModelError::AmountNotEnough(tot_amt_buyer, fee_buyer));
+        }
+        let net_amt_buyer = tot_amt_buyer - fee_buyer;
         let target_rate =ChargeBuyerModel::calc_target_rate(&currency_seller, &currency_buyer)
             .map_err(|d| PayoutModelError::AmountEstimate(AppErrorCode::DataCorruption, d))?
             .trunc_with_scale(CURRENCY_RATE_PRECISION);
-        let total_bs = tot_amt_buyer
+        let total_bs = net_amt_buyer
             .checked_div(currency_buyer.rate)
             .ok_or(format!("convert-overflow, base, rate:{}, amount:{}",
-                           currency_buyer.rate, tot_amt_buyer))
+                           currency_buyer.rate, net_amt_buyer))
             .map_err(|d| PayoutModelError::AmountEstimate(AppErrorCode::DataCorruption, d))?
             .trunc_with_scale(CurrencyDto::USD.amount_fraction_scale());
-        let total_mc = tot_amt_buyer
+        let total_mc = net_amt_buyer
             .checked_mul(target_rate)
             .ok_or(format!("convert-overflow, merchant, rate:{}, amount:{}",
-                           target_rate, tot_amt_buyer))
+                           target_rate, net_amt_buyer))
             .map_err(|d| PayoutModelError::AmountEstimate(AppErrorCode::DataCorruption, d))?
             .trunc_with_scale(currency_seller.label.amount_fraction_scale());
         Ok(Self {
-            total_buyer: tot_amt_buyer, total_mc, total_bs, target_rate,
+            total_buyer: tot_amt_buyer, fee_buyer, total_mc, total_bs, target_rate,
             currency_seller, currency_buyer
         })
     }
 } // end of impl PayoutAmountModel
 
 impl PayoutAmountModel {
-    fn try_update(mut self, given: &Self) -> Result<Self, PayoutModelError> {
+    fn validate_currency(&self, given: &Self) -> Result<(), PayoutModelError> {
         if self.currency_buyer != given.currency_buyer {
             let arg = (
                 "buyer".to_string(),
@@ -247,38 +359,9 @@ impl PayoutAmountModel {
                 given.currency_seller.clone(),
             );
             return Err(PayoutModelError::CurrencyInconsistent(arg.0, arg.1, arg.2));
-        } // TODO, implement domain logic at here if multi-payout feature is supported
-        let remain_mc =
-            self.total_mc
-                .checked_sub(given.total_mc)
-                .ok_or(PayoutModelError::AmountEstimate(
-                    AppErrorCode::DataCorruption,
-                    format!(
-                        "overflow-merchant, orig:{:?}, given:{:?}",
-                        self.total_mc, given.total_mc
-                    ),
-                ))?;
-        let remain_bs =
-            self.total_bs
-                .checked_sub(given.total_bs)
-                .ok_or(PayoutModelError::AmountEstimate(
-                    AppErrorCode::DataCorruption,
-                    format!(
-                        "overflow-base-curr, orig:{:?}, given:{:?}",
-                        self.total_bs, given.total_bs
-                    ),
-                ))?;
-        if (remain_mc > Decimal::ZERO) && (remain_bs > Decimal::ZERO) {
-            self.total_mc = remain_mc;
-            self.total_bs = remain_bs;
-            Ok(self)
-        } else {
-            Err(PayoutModelError::AmountNotEnough(
-                self.total_mc,
-                given.total_mc,
-            ))
         }
-    } // end of fn try_update
+        Ok(())
+    }
 
     /// return amount in merchant's configured currency
     fn merchant(&self) -> (Decimal, Decimal, &OrderCurrencySnapshot) {
@@ -286,13 +369,17 @@ impl PayoutAmountModel {
     }
 
     /// return amount in base currency (USD in this project)
-    fn base(&self) -> Decimal {
+    pub(super) fn base(&self) -> Decimal {
         self.total_bs
     }
 
     fn buyer(&self) -> Decimal {
         self.total_buyer
     }
+
+    fn fee(&self) -> Decimal {
+        self.fee_buyer
+    }
 } // end of impl PayoutAmountModel
 
 type Payout3ptyCvtArgs<'a, 'b> = (&'a Charge3partyModel, &'b Merchant3partyModel);
@@ -306,6 +393,11 @@ impl<'a, 'b> TryFrom<Payout3ptyCvtArgs<'a, 'b>> for Payout3partyModel {
                 let inner = Payout3partyStripeModel::new(cs, ms);
                 Ok(Self::Stripe(inner))
             }
+            (Charge3partyModel::Paypal(cp), Merchant3partyModel::Paypal(mp)) => {
+                let inner = Payout3partyPaypalModel::try_new(cp, mp)
+                    .ok_or(PayoutModelError::Init3partyFailure)?;
+                Ok(Self::Paypal(inner))
+            }
             _others => Err(PayoutModelError::Init3partyFailure),
         }
     }
@@ -317,21 +409,30 @@ impl<'a> From<&'a Payout3partyModel> for CapturePay3partyRespDto {
                 amount: s.amount().unwrap().to_string(),
                 currency: CurrencyDto::USD,
             },
+            Payout3partyModel::Paypal(p) => Self::Paypal {
+                amount: p.amount().unwrap().to_string(),
+                currency: CurrencyDto::USD,
+            },
         }
     }
 }
 
 impl Payout3partyModel {
-    fn try_clone(
+    /// check whether previous payout was made to the same 3rd-party account
+    /// and for the same charge
+    fn validate(
         &self,
         charge3pty: &Charge3partyModel,
         m3pty: &Merchant3partyModel,
-    ) -> Result<Self, PayoutModelError> {
+    ) -> Result<(), PayoutModelError> {
         match (self, charge3pty, m3pty) {
             (Self::Stripe(ps), Charge3partyModel::Stripe(cs), Merchant3partyModel::Stripe(ms)) => {
                 ps.validate(cs, ms)
-                    .map_err(PayoutModelError::Invalid3partyParams)?;
-                Ok(Self::Stripe(ps.clone()))
+                    .map_err(PayoutModelError::Invalid3partyParams)
+            }
+            (Self::Paypal(pp), Charge3partyModel::Paypal(cp), Merchant3partyModel::Paypal(mp)) => {
+                pp.validate(cp, mp)
+                    .map_err(PayoutModelError::Invalid3partyParams)
             }
             _others => {
                 let d = "mismatch".to_string();
@@ -0,0 +1,150 @@
+use std::result::Result;
+
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+
+use ecommerce_common::api::dto::CurrencyDto;
+
+#[derive(Debug)]
+pub enum PlatformFeeModelError {
+    // merchant-id of the rule, the invalid value of flat amount or percentage
+    InvalidFlat(Option<u32>, Decimal),
+    InvalidPercentage(Option<u32>, Decimal),
+    InvalidTimeRange(Option<u32>, DateTime<Utc>, DateTime<Utc>),
+    UnknownCurrency(Option<u32>),
+    // fields represent the rate, and the original amount
+    Overflow(Decimal, Decimal),
+}
+
+/// Fee charged by this platform for each payment which has been captured by
+/// a merchant, the fee consists of a flat amount and a percentage of the
+/// captured amount, both are in buyer's currency specified in this rule.
+pub struct PlatformFeeRuleModel {
+    // `None` means default rule applied to all merchants which do not have
+    // their own rule for the same currency and time period
+    merchant_id: Option<u32>,
+    currency: CurrencyDto,
+    flat: Decimal,
+    // e.g. the value `2.5` means 2.5% of the captured amount
+    percentage: Decimal,
+    start_after: DateTime<Utc>,
+    end_before: Option<DateTime<Utc>>,
+}
+
+/// collection of fee rules applicable to a merchant
+pub struct PlatformFeeScheduleModel {
+    merchant_id: u32,
+    rules: Vec<PlatformFeeRuleModel>,
+}
+
+#[rustfmt::skip]
+type PlatformFeeRuleCvtArgs = (
+    Option<u32>, CurrencyDto, Decimal, Decimal,
+    DateTime<Utc>, Option<DateTime<Utc>>,
+);
+
+impl TryFrom<PlatformFeeRuleCvtArgs> for PlatformFeeRuleModel {
+    type Error = PlatformFeeModelError;
+    #[rustfmt::skip]
+    fn try_from(value: PlatformFeeRuleCvtArgs) -> Result<Self, Self::Error> {
+        let (merchant_id, currency, flat, percentage, start_after, end_before) = value;
+        if matches!(currency, CurrencyDto::Unknown) {
+            return Err(PlatformFeeModelError::UnknownCurrency(merchant_id));
+        }
+        if flat.is_sign_negative() || flat.scale() > currency.amount_fraction_scale() {
+            return Err(PlatformFeeModelError::InvalidFlat(merchant_id, flat));
+        }
+        if percentage.is_sign_negative() || percentage >= Decimal::ONE_HUNDRED {
+            return Err(PlatformFeeModelError::InvalidPercentage(merchant_id, percentage));
+        }
+        if let Some(t1) = end_before {
+            if t1 <= start_after {
+                return Err(PlatformFeeModelError::InvalidTimeRange(merchant_id, start_after, t1));
+            }
+        }
+        Ok(Self { merchant_id, currency, flat, percentage, start_after, end_before })
+    }
+}
+
+impl PlatformFeeRuleModel {
+    fn applicable(&self, currency: &CurrencyDto, t: DateTime<Utc>) -> bool {
+        let within_period = self.start_after <= t && self.end_before.map_or(true, |t1| t < t1);
+        (&self.currency == currency) && within_period
+    }
+
+    /// the flat amount is charged only once for each charge, the
+    /// percentage is applied to every captured amount
+    fn calculate(
+        &self,
+        amount: Decimal,
+        flat_included: bool,
+    ) -> Result<Decimal, PlatformFeeModelError> {
+        let rate = self.percentage / Decimal::ONE_HUNDRED;
+        let variable = amount
+            .checked_mul(rate)
+            .ok_or(PlatformFeeModelError::Overflow(rate, amount))?
+            .trunc_with_scale(self.currency.amount_fraction_scale());
+        let out = if flat_included {
+            variable + self.flat
+        } else {
+            variable
+        };
+        Ok(out)
+    }
+
+    pub fn merchant_id(&self) -> Option<u32> {
+        self.merchant_id
+    }
+    pub fn currency(&self) -> &CurrencyDto {
+        &self.currency
+    }
+    pub fn flat(&self) -> Decimal {
+        self.flat
+    }
+    pub fn percentage(&self) -> Decimal {
+        self.percentage
+    }
+    pub fn time_range(&self) -> (DateTime<Utc>, Option<DateTime<Utc>>) {
+        (self.start_after, self.end_before)
+    }
+} // end of impl PlatformFeeRuleModel
+
+impl From<(u32, Vec<PlatformFeeRuleModel>)> for PlatformFeeScheduleModel {
+    fn from(value: (u32, Vec<PlatformFeeRuleModel>)) -> Self {
+        let (merchant_id, mut rules) = value;
+        // discard the rules which belong to other merchants
+        rules.retain(|r| r.merchant_id.map_or(true, |v| v == merchant_id));
+        Self { merchant_id, rules }
+    }
+}
+
+impl PlatformFeeScheduleModel {
+    pub fn merchant_id(&self) -> u32 {
+        self.merchant_id
+    }
+
+    /// find the rule applied to the given currency at the given time, the rule
+    /// specific to the merchant takes precedence over the default rule, and
+    /// the most recently started rule takes precedence over older ones.
+    pub fn find(&self, currency: &CurrencyDto, t: DateTime<Utc>) -> Option<&PlatformFeeRuleModel> {
+        self.rules
+            .iter()
+            .filter(|r| r.applicable(currency, t))
+            .max_by_key(|r| (r.merchant_id.is_some(), r.start_after))
+    }
+
+    /// estimate fee in the given currency, return zero if no rule is applicable
+    pub fn estimate(
+        &self,
+        currency: &CurrencyDto,
+        t: DateTime<Utc>,
+        amount: Decimal,
+        flat_included: bool,
+    ) -> Result<Decimal, PlatformFeeModelError> {
+        if let Some(r) = self.find(currency, t) {
+            r.calculate(amount, flat_included)
+        } else {
+            Ok(Decimal::ZERO)
+        }
+    }
+} // end of impl PlatformFeeScheduleModel
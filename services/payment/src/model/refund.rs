@@ -4,18 +4,21 @@ use std::collections::{HashMap, HashSet};
 use std::str::FromStr;
 
 use chrono::{DateTime, SubsecRound, Utc};
+use ring::digest;
 use rust_decimal::Decimal;
 
+use ecommerce_common::api::dto::PayAmountDto;
 use ecommerce_common::api::rpc::dto::OrderLineReplicaRefundDto;
 use ecommerce_common::model::BaseProductIdentity;
 
 use super::{
-    Charge3partyModel, ChargeBuyerModel, ChargeLineBuyerModel, OrderCurrencySnapshot,
-    PayLineAmountError, PayLineAmountModel,
+    Charge3partyModel, ChargeBuyerModel, ChargeLineBuyerModel, ChargeRefundLineMap,
+    OrderCurrencySnapshot, PayLineAmountError, PayLineAmountModel,
 };
 use crate::api::web::dto::{
     RefundCompletionOlineReqDto, RefundCompletionOlineRespDto, RefundCompletionReqDto,
     RefundCompletionRespDto, RefundLineApprovalDto, RefundLineRejectDto, RefundRejectReasonDto,
+    RefundStatusOlineRespDto, RefundStatusReqRespDto, RefundStatusRespDto,
 };
 
 #[derive(Debug)]
@@ -46,6 +49,8 @@ pub enum RefundModelError {
     MissingCurrency(String, u32),
     MissingMerchant,
     EmptyResolutionRequest(u32),
+    InvalidIdempotencyKey(String),
+    UnknownProcessor,
 } // end of enum RefundModelError
 
 // quantities of product items rejected to refund for defined reasons
@@ -83,6 +88,21 @@ pub struct RefundReqResolutionModel {
     chrg3pty: Charge3partyModel,
 }
 
+/// items of an order line resolved in a single round, kept as history for
+/// reporting, note the approved amount is present in buyer's currency
+pub struct RefundResolutionHistoryModel {
+    pid: BaseProductIdentity,
+    attr_set_seq: u16,
+    time_req: DateTime<Utc>,
+    time_resolved: DateTime<Utc>,
+    buyer_usr_id: u32,
+    charged_ctime: DateTime<Utc>,
+    approved: PayLineAmountModel,
+    rejected: RefundLineQtyRejectModel,
+    currency_buyer: OrderCurrencySnapshot,
+    currency_merc: OrderCurrencySnapshot,
+}
+
 pub struct OLineRefundModel {
     pid: BaseProductIdentity,
     attr_set_seq: u16,
@@ -99,6 +119,18 @@ pub struct OrderRefundModel {
     lines: Vec<OLineRefundModel>,
 }
 
+/// client-supplied key which identifies a single completion request made by
+/// a merchant against an order, the result of the request is saved along
+/// with the key, so retrying the same request will not refund twice
+pub struct RefundIdempotencyModel {
+    key: String,
+    merchant_id: u32,
+    oid: String,
+    // digest of the completion request, to detect a key reused with
+    // different content
+    req_digest: String,
+}
+
 #[rustfmt::skip]
 impl RefundModelError {
     fn qty_limit(
@@ -156,7 +188,7 @@ impl RefundLineQtyRejectModel {
     pub fn inner_map(&self) -> &RefundLineRejectDto {
         &self.0
     }
-    fn accumulate(&self, dst: &mut Self) {
+    pub(super) fn accumulate(&self, dst: &mut Self) {
         self.0
             .iter()
             .filter_map(|(k1, v1)| {
@@ -460,6 +492,107 @@ impl OrderRefundModel {
     } // end of fn update
 } // end of impl OrderRefundModel
 
+impl<'a, 'b> TryFrom<(String, u32, &'a str, &'b RefundCompletionReqDto)>
+    for RefundIdempotencyModel
+{
+    type Error = RefundModelError;
+    fn try_from(
+        value: (String, u32, &'a str, &'b RefundCompletionReqDto),
+    ) -> Result<Self, Self::Error> {
+        let (key, merchant_id, oid, cmplt_req) = value;
+        let valid = !key.is_empty()
+            && key.len() <= Self::MAX_KEY_LEN
+            && key
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid {
+            return Err(RefundModelError::InvalidIdempotencyKey(key));
+        }
+        let req_digest = Self::digest_request(cmplt_req);
+        Ok(Self {
+            key,
+            merchant_id,
+            oid: oid.to_string(),
+            req_digest,
+        })
+    }
+} // end of impl RefundIdempotencyModel
+
+impl From<(String, u32, String, String)> for RefundIdempotencyModel {
+    fn from(value: (String, u32, String, String)) -> Self {
+        let (key, merchant_id, oid, req_digest) = value;
+        Self {
+            key,
+            merchant_id,
+            oid,
+            req_digest,
+        }
+    }
+}
+
+impl RefundIdempotencyModel {
+    pub const MAX_KEY_LEN: usize = 64;
+
+    fn digest_request(cmplt_req: &RefundCompletionReqDto) -> String {
+        let mut lines = cmplt_req
+            .lines
+            .iter()
+            .map(|r| {
+                let amt_tot = Decimal::from_str(r.approval.amount_total.as_str())
+                    .map(|d| d.normalize().to_string())
+                    .unwrap_or(r.approval.amount_total.clone());
+                let n_fraud = r
+                    .reject
+                    .get(&RefundRejectReasonDto::Fraudulent)
+                    .unwrap_or(&0);
+                let n_damage = r.reject.get(&RefundRejectReasonDto::Damaged).unwrap_or(&0);
+                format!(
+                    "{}/{}/{}/{}/{}/{}/{}",
+                    r.product_id,
+                    r.attr_set_seq,
+                    r.time_issued.timestamp(),
+                    r.approval.quantity,
+                    amt_tot,
+                    n_fraud,
+                    n_damage,
+                )
+            })
+            .collect::<Vec<_>>();
+        lines.sort();
+        sha256_hex(lines.join("|").as_str())
+    }
+
+    pub fn key(&self) -> &str {
+        self.key.as_str()
+    }
+    pub fn merchant_id(&self) -> u32 {
+        self.merchant_id
+    }
+    pub fn order_id(&self) -> &str {
+        self.oid.as_str()
+    }
+    pub fn req_digest(&self) -> &str {
+        self.req_digest.as_str()
+    }
+    /// whether the given key was sent with identical completion request
+    /// against the same order
+    pub fn same_request(&self, other: &Self) -> bool {
+        self.key == other.key
+            && self.merchant_id == other.merchant_id
+            && self.oid == other.oid
+            && self.req_digest == other.req_digest
+    }
+} // end of impl RefundIdempotencyModel
+
+fn sha256_hex(content: &str) -> String {
+    digest::digest(&digest::SHA256, content.as_bytes())
+        .as_ref()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<Vec<_>>()
+        .join("")
+}
+
 impl RefundLineReqResolutionModel {
     // TODO, FIXME, should return error when completion request contains number of returns
     // more than number of remaining items
@@ -526,12 +659,6 @@ impl RefundLineReqResolutionModel {
     pub(super) fn num_rejected(&self) -> u32 {
         self.qty_reject.total_qty()
     }
-    #[rustfmt::skip]
-    fn into_parts(self) -> (BaseProductIdentity, u16, DateTime<Utc>, RefundLineQtyRejectModel, RefundLineResolveAmountModel)
-    {
-        let Self { pid, attr_set_seq, time_req, qty_reject, amount } = self;
-        (pid, attr_set_seq, time_req, qty_reject, amount)
-    }
 } // end of impl RefundLineReqResolutionModel
 
 impl<'a, 'b> TryFrom<(u32, &'a ChargeBuyerModel, &'b RefundCompletionReqDto)>
@@ -588,6 +715,61 @@ impl RefundReqRslvInnerModel {
             .map(|v| v.amount().curr_round().total)
             .sum::<Decimal>()
     }
+    /// digest of the lines resolved in current round, quantities and amount
+    /// accumulated in previous rounds are also included, so that retrying the
+    /// same round derives the same digest, while every new round of the
+    /// same request line derives a different one.
+    fn round_digest(&self) -> String {
+        let mut lines = self
+            .lines
+            .iter()
+            .map(|v| {
+                let (prev_paid, prev_rejected) = v.amount.accumulated();
+                let curr = v.amount.curr_round();
+                let rej = v.qty_reject.inner_map();
+                let n_fraud = rej.get(&RefundRejectReasonDto::Fraudulent).unwrap_or(&0);
+                let n_damage = rej.get(&RefundRejectReasonDto::Damaged).unwrap_or(&0);
+                format!(
+                    "{}/{}/{}/{}/{}/{}/{}/{}/{}/{}/{}",
+                    v.pid.store_id,
+                    v.pid.product_id,
+                    v.attr_set_seq,
+                    v.time_req.timestamp(),
+                    prev_paid.qty,
+                    prev_paid.total.normalize(),
+                    prev_rejected,
+                    curr.qty,
+                    curr.total.normalize(),
+                    n_fraud,
+                    n_damage,
+                )
+            })
+            .collect::<Vec<_>>();
+        lines.sort();
+        let content = format!(
+            "{}/{}|{}",
+            self.buyer_usr_id,
+            self.charged_ctime.timestamp(),
+            lines.join("|")
+        );
+        sha256_hex(content.as_str())
+    }
+    /// request ID sent to 3rd-party processor for refunding current round,
+    /// which does not depend on the time the refund is requested, so retrying
+    /// the same round always sends the same ID and the processor refunds
+    /// only once.
+    pub(crate) fn processor_request_id(
+        &self,
+        charge_ref: &str,
+    ) -> Result<String, RefundModelError> {
+        let merchant_id = self.merchant_id()?;
+        Ok(format!(
+            "{}-{}-{}",
+            charge_ref,
+            merchant_id,
+            self.round_digest()
+        ))
+    }
     #[rustfmt::skip]
     fn get_status(
         &self, merchant_id: u32, product_id: u64, attr_seq: u16, time_req: DateTime<Utc>,
@@ -611,6 +793,14 @@ impl RefundReqResolutionModel {
     pub fn currency(&self) -> [&OrderCurrencySnapshot; 2] {
         self.inner.currency()
     }
+    pub fn processor_request_id(&self) -> Result<String, RefundModelError> {
+        let charge_ref = match &self.chrg3pty {
+            Charge3partyModel::Stripe(s) => s.transfer_group.as_str(),
+            Charge3partyModel::Paypal(p) => p.reference_id.as_str(),
+            Charge3partyModel::Unknown => return Err(RefundModelError::UnknownProcessor),
+        };
+        self.inner.processor_request_id(charge_ref)
+    }
     pub(crate) fn into_parts(self) -> (RefundReqRslvInnerModel, Charge3partyModel) {
         let Self { inner, chrg3pty } = self;
         (inner, chrg3pty)
@@ -660,31 +850,118 @@ impl RefundReqResolutionModel {
     ) -> Option<(&RefundLineQtyRejectModel, &RefundLineResolveAmountModel)> {
         self.inner.get_status(merchant_id, product_id, attr_seq, time_req)
     }
+
+    /// flatten the lines resolved in current round to history records
+    pub(crate) fn to_history(
+        &self,
+        time_resolved: DateTime<Utc>,
+    ) -> Vec<RefundResolutionHistoryModel> {
+        let inner = &self.inner;
+        inner
+            .lines
+            .iter()
+            .map(|rline| {
+                let curr_round = rline.amount.curr_round();
+                RefundResolutionHistoryModel {
+                    pid: rline.pid.clone(),
+                    attr_set_seq: rline.attr_set_seq,
+                    time_req: rline.time_req,
+                    time_resolved,
+                    buyer_usr_id: inner.buyer_usr_id,
+                    charged_ctime: inner.charged_ctime,
+                    approved: PayLineAmountModel {
+                        unit: curr_round.unit,
+                        total: curr_round.total,
+                        qty: curr_round.qty,
+                    },
+                    rejected: RefundLineQtyRejectModel::from(rline.qty_reject.inner_map()),
+                    currency_buyer: inner.currency_buyer.clone(),
+                    currency_merc: inner.currency_merc.clone(),
+                }
+            })
+            .collect()
+    }
 } // end of impl RefundReqResolutionModel
 
-impl From<Vec<RefundReqResolutionModel>> for RefundCompletionRespDto {
+#[rustfmt::skip]
+type RefundRslvHistoryCvtArgs = (
+    BaseProductIdentity, u16, DateTime<Utc>, DateTime<Utc>, (u32, DateTime<Utc>),
+    PayLineAmountModel, RefundLineQtyRejectModel, [OrderCurrencySnapshot; 2],
+);
+
+impl From<RefundRslvHistoryCvtArgs> for RefundResolutionHistoryModel {
+    #[rustfmt::skip]
+    fn from(value: RefundRslvHistoryCvtArgs) -> Self {
+        let (
+            pid, attr_set_seq, time_req, time_resolved, (buyer_usr_id, charged_ctime),
+            approved, rejected, [currency_buyer, currency_merc],
+        ) = value;
+        Self {
+            pid, attr_set_seq, time_req, time_resolved, buyer_usr_id, charged_ctime,
+            approved, rejected, currency_buyer, currency_merc,
+        }
+    }
+}
+
+impl RefundResolutionHistoryModel {
+    pub fn id(&self) -> (&BaseProductIdentity, u16, DateTime<Utc>) {
+        (&self.pid, self.attr_set_seq, self.time_req)
+    }
+    pub fn time_resolved(&self) -> DateTime<Utc> {
+        self.time_resolved
+    }
+    pub fn charge_id(&self) -> (u32, DateTime<Utc>) {
+        (self.buyer_usr_id, self.charged_ctime)
+    }
+    pub fn approved(&self) -> &PayLineAmountModel {
+        &self.approved
+    }
+    pub fn rejected(&self) -> &RefundLineQtyRejectModel {
+        &self.rejected
+    }
+    pub fn currency(&self) -> [&OrderCurrencySnapshot; 2] {
+        [&self.currency_buyer, &self.currency_merc]
+    }
+
+    /// approved amount converted to merchant's currency
+    pub fn amount_merchant(&self) -> Result<(Decimal, &OrderCurrencySnapshot), String> {
+        let rate = ChargeBuyerModel::calc_target_rate(&self.currency_merc, &self.currency_buyer)?;
+        let scale = self.currency_merc.label.amount_fraction_scale();
+        let amount = rate
+            .checked_mul(self.approved.total)
+            .ok_or(format!(
+                "amount-overflow, rate:{rate}, amount:{}",
+                self.approved.total
+            ))?
+            .trunc_with_scale(scale);
+        Ok((amount, &self.currency_merc))
+    }
+} // end of impl RefundResolutionHistoryModel
+
+#[rustfmt::skip]
+type RefundCompletionAggrItem<'a> = (
+    u64, u16, DateTime<Utc>, &'a PayLineAmountModel, &'a RefundLineQtyRejectModel,
+);
+
+impl RefundCompletionRespDto {
     #[rustfmt::skip]
-    fn from(value: Vec<RefundReqResolutionModel>) -> Self {
+    fn aggregate<'a>(items: impl Iterator<Item = RefundCompletionAggrItem<'a>>) -> Self {
         type MapKeyType = (u64, u16, DateTime<Utc>);
         // the value sequence of each map entry :
         // qty-aprv, amount-total-aprv, qty-rej-damage, qty-rej-fraud
         type MapValType = (u32, Decimal, u32, u32);
         type LineMapType = HashMap<MapKeyType, MapValType>;
         let mut line_map: LineMapType = HashMap::new();
-        value.into_iter().map(|rfnd_m| {
-            rfnd_m.inner.lines.into_iter().map(|rline| {
-                let (r_pid, r_attrseq, r_time_req, mut r_qty_rej, r_amt) = rline.into_parts();
-                let BaseProductIdentity { store_id: _, product_id } = r_pid;
-                let key = (product_id, r_attrseq, r_time_req);
-                let (qty_aprv_dst, amt_tot_aprv_dst, qty_rej_damage_dst, qty_rej_fraud_dst) =
-                    line_map.entry(key).or_insert((0, Decimal::ZERO, 0, 0));
-                *qty_aprv_dst     += r_amt.curr_round().qty;
-                *amt_tot_aprv_dst += r_amt.curr_round().total;
-                let r_n_rej = r_qty_rej.0.remove(&RefundRejectReasonDto::Damaged).unwrap_or(0);
-                *qty_rej_damage_dst += r_n_rej;
-                let r_n_rej = r_qty_rej.0.remove(&RefundRejectReasonDto::Fraudulent).unwrap_or(0);
-                *qty_rej_fraud_dst += r_n_rej;
-            }).count()
+        items.map(|(product_id, r_attrseq, r_time_req, r_aprv, r_qty_rej)| {
+            let key = (product_id, r_attrseq, r_time_req);
+            let (qty_aprv_dst, amt_tot_aprv_dst, qty_rej_damage_dst, qty_rej_fraud_dst) =
+                line_map.entry(key).or_insert((0, Decimal::ZERO, 0, 0));
+            *qty_aprv_dst     += r_aprv.qty;
+            *amt_tot_aprv_dst += r_aprv.total;
+            let r_n_rej = r_qty_rej.0.get(&RefundRejectReasonDto::Damaged).unwrap_or(&0);
+            *qty_rej_damage_dst += *r_n_rej;
+            let r_n_rej = r_qty_rej.0.get(&RefundRejectReasonDto::Fraudulent).unwrap_or(&0);
+            *qty_rej_fraud_dst += *r_n_rej;
         }).count();
 
         let lines = line_map.into_iter()
@@ -703,5 +980,70 @@ impl From<Vec<RefundReqResolutionModel>> for RefundCompletionRespDto {
                 }
             }).collect::<Vec<_>>();
         Self { lines }
+    } // end of fn aggregate
+} // end of impl RefundCompletionRespDto
+
+impl<'a> From<&'a [&'a RefundReqResolutionModel]> for RefundCompletionRespDto {
+    fn from(value: &'a [&'a RefundReqResolutionModel]) -> Self {
+        let items = value.iter().flat_map(|rfnd_m| {
+            rfnd_m.inner.lines.iter().map(|rline| {
+                let pid = &rline.pid;
+                let aprv = rline.amount.curr_round();
+                let t_req = rline.time_req;
+                (
+                    pid.product_id,
+                    rline.attr_set_seq,
+                    t_req,
+                    aprv,
+                    &rline.qty_reject,
+                )
+            })
+        });
+        Self::aggregate(items)
+    }
+}
+
+impl From<(OrderRefundModel, ChargeRefundLineMap)> for RefundStatusRespDto {
+    #[rustfmt::skip]
+    fn from(value: (OrderRefundModel, ChargeRefundLineMap)) -> Self {
+        let (rfnd_m, refunded_map) = value;
+        let (order_id, rlines) = rfnd_m.into_parts();
+        let mut line_map: HashMap<(BaseProductIdentity, u16), Vec<RefundStatusReqRespDto>> =
+            HashMap::new();
+        rlines.into_iter().map(|rline| {
+            let (pid, attr_set_seq, amt_req, time_issued, amt_aprv, rejected) = rline.into_parts();
+            let req = RefundStatusReqRespDto {
+                time_issued,
+                qty_requested: amt_req.qty,
+                amount_requested: PayAmountDto {
+                    unit: amt_req.unit.to_string(), total: amt_req.total.to_string(),
+                },
+                approval: RefundLineApprovalDto {
+                    quantity: amt_aprv.qty, amount_total: amt_aprv.total.to_string(),
+                },
+                reject: rejected.0,
+            };
+            line_map.entry((pid, attr_set_seq)).or_default().push(req);
+        }).count();
+
+        let mut lines = line_map.into_iter()
+            .map(|((pid, attr_set_seq), mut requests)| {
+                requests.sort_by_key(|r| r.time_issued);
+                let refunded = refunded_map
+                    .get(pid.store_id, pid.product_id, attr_set_seq)
+                    .map(|(amt, _num_rej)| RefundLineApprovalDto {
+                        quantity: amt.qty, amount_total: amt.total.to_string(),
+                    })
+                    .unwrap_or(RefundLineApprovalDto {
+                        quantity: 0, amount_total: Decimal::ZERO.to_string(),
+                    });
+                RefundStatusOlineRespDto {
+                    seller_id: pid.store_id, product_id: pid.product_id,
+                    attr_set_seq, requests, refunded,
+                }
+            })
+            .collect::<Vec<_>>();
+        lines.sort_by_key(|l| (l.seller_id, l.product_id, l.attr_set_seq));
+        Self { order_id, lines }
     } // end of fn from
-} // end of fn RefundCompletionRespDto
+} // end of impl RefundStatusRespDto
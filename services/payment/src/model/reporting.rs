@@ -1,11 +1,18 @@
+use chrono::NaiveDate;
 use rust_decimal::Decimal;
 use std::collections::HashMap;
 
 use ecommerce_common::api::dto::CurrencyDto;
 
-use crate::api::web::dto::{ReportChargeLineRespDto, ReportChargeRespDto, ReportTimeRangeDto};
+use crate::api::web::dto::{
+    ReportChargeBreakdownDto, ReportChargeFeeRespDto, ReportChargeLineRespDto, ReportChargeRespDto,
+    ReportPayoutLineRespDto, ReportPayoutRespDto, ReportRefundLineRespDto, ReportRefundRespDto,
+    ReportSettlementRespDto, ReportTimeRangeDto,
+};
 
-use super::ChargeBuyerModel;
+use super::{
+    ChargeBuyerModel, PayoutModel, RefundLineQtyRejectModel, RefundResolutionHistoryModel,
+};
 
 #[derive(Debug)]
 pub enum ReportModelError {
@@ -19,6 +26,8 @@ pub enum ReportModelError {
 
 #[derive(Hash, Eq, PartialEq)]
 struct ReportChargeLineKey {
+    // date of the charges in UTC, only applicable to per-day breakdown
+    date: Option<NaiveDate>,
     // note, is it necessary to add extra field for chosen product attributes
     // in order line
     product_id: u64,
@@ -33,24 +42,50 @@ struct ReportChargeLineEntry {
     // amount per single unit item might change, so I don't put it in report model
 }
 
+#[derive(Default)]
+struct ReportRefundLineEntry {
+    // amount approved to refund in merchant's currency
+    amount: Decimal,
+    qty_approved: u32,
+    rejected: RefundLineQtyRejectModel,
+}
+
+#[derive(Default)]
+struct ReportPayoutEntry {
+    num_payouts: u32,
+    // all amounts are in merchant's currency
+    captured: Decimal,
+    transferred: Decimal,
+    platform_fee: Decimal,
+}
+
 pub struct MerchantReportChargeModel {
     id: u32, // merchant ID
     time_range: ReportTimeRangeDto,
+    breakdown: ReportChargeBreakdownDto,
     linemap: HashMap<ReportChargeLineKey, ReportChargeLineEntry>,
+    // total platform fee charged in the payouts of the merged charges, in
+    // currency applied by merchant at that time
+    feemap: HashMap<CurrencyDto, Decimal>,
 }
 
 impl ReportChargeLineKey {
     #[rustfmt::skip]
-    fn new(product_id:u64, curr_label:&CurrencyDto) -> Self {
-        Self { product_id, currency: curr_label.clone() }
+    fn new(date: Option<NaiveDate>, product_id:u64, curr_label:&CurrencyDto) -> Self {
+        Self { date, product_id, currency: curr_label.clone() }
     }
 }
 
-impl From<(u32, ReportTimeRangeDto)> for MerchantReportChargeModel {
+type ReportChargeCvtArgs = (u32, ReportTimeRangeDto, ReportChargeBreakdownDto);
+
+impl From<ReportChargeCvtArgs> for MerchantReportChargeModel {
     #[rustfmt::skip]
-    fn from(value: (u32,ReportTimeRangeDto)) -> Self {
-        let (id, time_range) = value;
-        Self { id, time_range, linemap: HashMap::new() }
+    fn from(value: ReportChargeCvtArgs) -> Self {
+        let (id, time_range, breakdown) = value;
+        Self {
+            id, time_range, breakdown,
+            linemap: HashMap::new(), feemap: HashMap::new(),
+        }
     }
 }
 
@@ -84,6 +119,10 @@ impl MerchantReportChargeModel {
         }
         let (curr_label, rate) = Self::try_calc_rate(self.id, &charge_m).map_err(|e| vec![e])?;
         let rescale = curr_label.amount_fraction_scale();
+        let charge_date = match &self.breakdown {
+            ReportChargeBreakdownDto::Product => None,
+            ReportChargeBreakdownDto::DayProduct => Some(charge_m.meta.create_time().date_naive()),
+        };
         let mut errors = Vec::new();
         let num_merged = charge_m
             .lines
@@ -96,7 +135,7 @@ impl MerchantReportChargeModel {
                     errors.push(e);
                     return None;
                 }
-                let key = ReportChargeLineKey::new(pid.product_id, &curr_label);
+                let key = ReportChargeLineKey::new(charge_date, pid.product_id, &curr_label);
                 let entry = self.linemap.entry(key).or_default();
                 rate.checked_mul(amt_orig.total)
                     .map(|amt_seller| {
@@ -117,9 +156,29 @@ impl MerchantReportChargeModel {
         }
     } // end of fn try-merge-one
 
+    /// platform fee is taken from each payout of the charge, which covers
+    /// partial captures, the amount is converted to merchant's currency
+    fn merge_fee(&mut self, payout_m: PayoutModel) -> Result<(), ReportModelError> {
+        if payout_m.merchant_id() != self.id {
+            let e = ReportModelError::MerchantNotConsistent(self.id, payout_m.merchant_id());
+            return Err(e);
+        }
+        let fee_buyer = payout_m.platform_fee();
+        let (_transferred, rate, currency_m) = payout_m.amount_merchant();
+        let fee_seller = rate
+            .checked_mul(fee_buyer)
+            .ok_or(ReportModelError::AmountOverflow(rate, fee_buyer))?
+            .trunc_with_scale(currency_m.label.amount_fraction_scale());
+        let entry = self.feemap.entry(currency_m.label.clone()).or_default();
+        *entry += fee_seller;
+        Ok(())
+    }
+
+    /// `payout_ms` should be the payouts captured from the given charges
     pub fn try_merge(
         &mut self,
         charge_ms: Vec<ChargeBuyerModel>,
+        payout_ms: Vec<PayoutModel>,
     ) -> Result<usize, Vec<ReportModelError>> {
         let mut errors = Vec::new();
         let total_merged = charge_ms
@@ -130,6 +189,10 @@ impl MerchantReportChargeModel {
                     .ok()
             })
             .sum();
+        payout_ms
+            .into_iter()
+            .filter_map(|m| self.merge_fee(m).map_err(|e| errors.push(e)).ok())
+            .count();
         if errors.is_empty() {
             Ok(total_merged)
         } else {
@@ -138,11 +201,154 @@ impl MerchantReportChargeModel {
     } // end of fn try-merge
 } // end of impl MerchantReportChargeModel
 
+/// refunds resolved in a time period, grouped by product and merchant's currency
+pub struct MerchantReportRefundModel {
+    id: u32, // merchant ID
+    time_range: ReportTimeRangeDto,
+    linemap: HashMap<ReportChargeLineKey, ReportRefundLineEntry>,
+}
+
+/// payouts transferred in a time period, with net settlement for each currency
+/// applied by the merchant, that is, the transferred amount subtracted by the
+/// refunded amount in the same period
+pub struct MerchantReportPayoutModel {
+    id: u32, // merchant ID
+    time_range: ReportTimeRangeDto,
+    payoutmap: HashMap<CurrencyDto, ReportPayoutEntry>,
+    refundmap: HashMap<CurrencyDto, Decimal>,
+}
+
+impl From<(u32, ReportTimeRangeDto)> for MerchantReportRefundModel {
+    #[rustfmt::skip]
+    fn from(value: (u32, ReportTimeRangeDto)) -> Self {
+        let (id, time_range) = value;
+        Self { id, time_range, linemap: HashMap::new() }
+    }
+}
+
+impl MerchantReportRefundModel {
+    fn try_merge_one(
+        &mut self,
+        rfnd_m: RefundResolutionHistoryModel,
+    ) -> Result<(), ReportModelError> {
+        let (pid, _attr_seq, _time_req) = rfnd_m.id();
+        if pid.store_id != self.id {
+            return Err(ReportModelError::MerchantNotConsistent(
+                self.id,
+                pid.store_id,
+            ));
+        }
+        let (amount, currency_m) = rfnd_m
+            .amount_merchant()
+            .map_err(ReportModelError::RateOverflow)?;
+        let key = ReportChargeLineKey::new(None, pid.product_id, &currency_m.label);
+        let entry = self.linemap.entry(key).or_default();
+        entry.amount += amount;
+        entry.qty_approved += rfnd_m.approved().qty;
+        rfnd_m.rejected().accumulate(&mut entry.rejected);
+        Ok(())
+    }
+
+    pub fn try_merge(
+        &mut self,
+        rfnd_ms: Vec<RefundResolutionHistoryModel>,
+    ) -> Result<usize, Vec<ReportModelError>> {
+        let mut errors = Vec::new();
+        let num_merged = rfnd_ms
+            .into_iter()
+            .filter_map(|m| self.try_merge_one(m).map_err(|e| errors.push(e)).ok())
+            .count();
+        if errors.is_empty() {
+            Ok(num_merged)
+        } else {
+            Err(errors)
+        }
+    }
+} // end of impl MerchantReportRefundModel
+
+impl From<(u32, ReportTimeRangeDto)> for MerchantReportPayoutModel {
+    #[rustfmt::skip]
+    fn from(value: (u32, ReportTimeRangeDto)) -> Self {
+        let (id, time_range) = value;
+        Self { id, time_range, payoutmap: HashMap::new(), refundmap: HashMap::new() }
+    }
+}
+
+impl MerchantReportPayoutModel {
+    fn try_merge_payout(&mut self, payout_m: PayoutModel) -> Result<(), ReportModelError> {
+        if payout_m.merchant_id() != self.id {
+            let e = ReportModelError::MerchantNotConsistent(self.id, payout_m.merchant_id());
+            return Err(e);
+        }
+        let amt_buyer = payout_m.amount_buyer();
+        let (transferred, rate, currency_m) = payout_m.amount_merchant();
+        let rescale = currency_m.label.amount_fraction_scale();
+        let captured = rate
+            .checked_mul(amt_buyer)
+            .ok_or(ReportModelError::AmountOverflow(rate, amt_buyer))?
+            .trunc_with_scale(rescale);
+        // the fee persisted in the payout, instead of difference between the
+        // amounts above, which might be off due to truncation
+        let fee_buyer = payout_m.platform_fee();
+        let platform_fee = rate
+            .checked_mul(fee_buyer)
+            .ok_or(ReportModelError::AmountOverflow(rate, fee_buyer))?
+            .trunc_with_scale(rescale);
+        let entry = self.payoutmap.entry(currency_m.label.clone()).or_default();
+        entry.num_payouts += 1;
+        entry.captured += captured;
+        entry.transferred += transferred;
+        entry.platform_fee += platform_fee;
+        Ok(())
+    }
+
+    fn try_merge_refund(
+        &mut self,
+        rfnd_m: RefundResolutionHistoryModel,
+    ) -> Result<(), ReportModelError> {
+        let (pid, _attr_seq, _time_req) = rfnd_m.id();
+        if pid.store_id != self.id {
+            return Err(ReportModelError::MerchantNotConsistent(
+                self.id,
+                pid.store_id,
+            ));
+        }
+        let (amount, currency_m) = rfnd_m
+            .amount_merchant()
+            .map_err(ReportModelError::RateOverflow)?;
+        let entry = self.refundmap.entry(currency_m.label.clone()).or_default();
+        *entry += amount;
+        Ok(())
+    }
+
+    pub fn try_merge(
+        &mut self,
+        payout_ms: Vec<PayoutModel>,
+        rfnd_ms: Vec<RefundResolutionHistoryModel>,
+    ) -> Result<(usize, usize), Vec<ReportModelError>> {
+        let mut errors = Vec::new();
+        let num_payouts = payout_ms
+            .into_iter()
+            .filter_map(|m| self.try_merge_payout(m).map_err(|e| errors.push(e)).ok())
+            .count();
+        let num_refunds = rfnd_ms
+            .into_iter()
+            .filter_map(|m| self.try_merge_refund(m).map_err(|e| errors.push(e)).ok())
+            .count();
+        if errors.is_empty() {
+            Ok((num_payouts, num_refunds))
+        } else {
+            Err(errors)
+        }
+    }
+} // end of impl MerchantReportPayoutModel
+
 #[rustfmt::skip]
 impl From<(ReportChargeLineKey, ReportChargeLineEntry)> for ReportChargeLineRespDto {
     fn from(value: (ReportChargeLineKey, ReportChargeLineEntry)) -> Self {
         let (k ,v) = value;
         Self {
+            date: k.date.map(|d| d.format("%Y-%m-%d").to_string()),
             product_id: k.product_id, currency: k.currency,
             amount: v.amount.to_string(), qty: v.qty
         }
@@ -152,10 +358,74 @@ impl From<(ReportChargeLineKey, ReportChargeLineEntry)> for ReportChargeLineResp
 #[rustfmt::skip]
 impl From<MerchantReportChargeModel> for ReportChargeRespDto {
     fn from(value: MerchantReportChargeModel) -> Self {
-        let MerchantReportChargeModel { id, time_range, linemap } = value;
-        let lines = linemap.into_iter()
+        let MerchantReportChargeModel {
+            id, time_range, linemap, feemap, breakdown: _,
+        } = value;
+        // sort the lines, so the rows in exported report are in stable order
+        let mut kv_pairs = linemap.into_iter().collect::<Vec<_>>();
+        kv_pairs.sort_by_cached_key(|(k, _)| (k.date, k.product_id, k.currency.to_string()));
+        let lines = kv_pairs.into_iter()
             .map(ReportChargeLineRespDto::from)
             .collect::<Vec<_>>();
-        ReportChargeRespDto {merchant_id: id, time_range, lines}
+        let fee_lines = feemap.into_iter()
+            .map(|(currency, amount)| ReportChargeFeeRespDto {
+                currency, amount: amount.to_string()
+            })
+            .collect::<Vec<_>>();
+        ReportChargeRespDto {merchant_id: id, time_range, lines, fee_lines, next_cursor: None}
+    }
+}
+
+#[rustfmt::skip]
+impl From<MerchantReportRefundModel> for ReportRefundRespDto {
+    fn from(value: MerchantReportRefundModel) -> Self {
+        let MerchantReportRefundModel { id, time_range, linemap } = value;
+        let mut kv_pairs = linemap.into_iter().collect::<Vec<_>>();
+        kv_pairs.sort_by_cached_key(|(k, _)| (k.product_id, k.currency.to_string()));
+        let lines = kv_pairs.into_iter()
+            .map(|(k, v)| ReportRefundLineRespDto {
+                product_id: k.product_id, currency: k.currency,
+                amount: v.amount.to_string(), qty_approved: v.qty_approved,
+                qty_rejected: v.rejected.inner_map().clone(),
+            })
+            .collect::<Vec<_>>();
+        ReportRefundRespDto { merchant_id: id, time_range, lines }
+    }
+}
+
+#[rustfmt::skip]
+impl From<MerchantReportPayoutModel> for ReportPayoutRespDto {
+    fn from(value: MerchantReportPayoutModel) -> Self {
+        let MerchantReportPayoutModel { id, time_range, payoutmap, mut refundmap } = value;
+        let mut settlement = payoutmap.iter()
+            .map(|(currency, v)| {
+                let refunded = refundmap.remove(currency).unwrap_or_default();
+                (currency.clone(), v.transferred, refunded)
+            })
+            .collect::<Vec<_>>();
+        // refunds might be resolved in the period without any payout
+        let iter = refundmap.into_iter()
+            .map(|(currency, refunded)| (currency, Decimal::ZERO, refunded));
+        settlement.extend(iter);
+        settlement.sort_by_cached_key(|(currency, _, _)| currency.to_string());
+        let settlement = settlement.into_iter()
+            .map(|(currency, transferred, refunded)| ReportSettlementRespDto {
+                currency, transferred: transferred.to_string(),
+                refunded: refunded.to_string(),
+                net: (transferred - refunded).to_string(),
+            })
+            .collect::<Vec<_>>();
+        let mut lines = payoutmap.into_iter()
+            .map(|(currency, v)| ReportPayoutLineRespDto {
+                currency, num_payouts: v.num_payouts,
+                amount_captured: v.captured.to_string(),
+                // derived from the 2 amounts, so it always reconciles with
+                // the truncated amounts in the report
+                fee: v.platform_fee.to_string(),
+                amount_transferred: v.transferred.to_string(),
+            })
+            .collect::<Vec<_>>();
+        lines.sort_by_cached_key(|l| l.currency.to_string());
+        ReportPayoutRespDto { merchant_id: id, time_range, lines, settlement }
     }
 }
@@ -0,0 +1,29 @@
+use super::{Label3party, StripeWebhookEventModel};
+
+/// event notified by external processors, each event should be processed
+/// at most once in this application, identified by the 3rd party and the
+/// event ID issued by the 3rd party.
+pub enum Webhook3partyEventModel {
+    Stripe(StripeWebhookEventModel),
+}
+
+impl Webhook3partyEventModel {
+    pub fn id(&self) -> &str {
+        match self {
+            Self::Stripe(e) => e.id.as_str(),
+        }
+    }
+    pub fn type_(&self) -> &str {
+        match self {
+            Self::Stripe(e) => e.type_.as_str(),
+        }
+    }
+}
+
+impl<'a> From<&'a Webhook3partyEventModel> for Label3party {
+    fn from(value: &'a Webhook3partyEventModel) -> Self {
+        match value {
+            Webhook3partyEventModel::Stripe(_) => Self::Stripe,
+        }
+    }
+}
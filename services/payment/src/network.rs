@@ -4,12 +4,15 @@ use actix_http::Request;
 use actix_service::IntoServiceFactory;
 use actix_web::body::MessageBody;
 use actix_web::dev::{AppConfig, Response, ServiceFactory, ServiceRequest, ServiceResponse};
+use actix_web::middleware::Condition;
 use actix_web::web;
 use actix_web::{App, HttpServer};
+use actix_web_httpauth::middleware::HttpAuthentication;
 
 use ecommerce_common::config::{ApiServerCfg, WebApiRouteCfg};
 
 use crate::api::web::AppRouteTable;
+use crate::auth::validate_jwt;
 
 /*
  * the original implementation does not intend to let users transfer `App` object
@@ -27,6 +30,7 @@ use crate::api::web::AppRouteTable;
 pub fn app_web_service(
     mut route_table: AppRouteTable,
     cfg: Vec<WebApiRouteCfg>,
+    authn_enabled: bool,
 ) -> (
     App<
         impl ServiceFactory<
@@ -39,26 +43,33 @@ pub fn app_web_service(
     >,
     usize,
 ) {
+    let path_prefix = format!("/{}", route_table.version.as_str());
+    let mut app = App::new();
+    let mut protected = Vec::new();
     let mut num_applied = 0usize;
-    let num_applied_p = &mut num_applied;
+    for c in cfg {
+        let (path, inner_label) = (c.path, c.handler);
+        // public endpoints are registered ahead of the versioned scope, so
+        // the requests to them never reach the JWT middleware
+        if let Some(found) = route_table.public_entries.remove(inner_label.as_str()) {
+            let fullpath = format!("{path_prefix}{path}");
+            app = app.service(web::resource(fullpath).route(found));
+            num_applied += 1;
+        } else if let Some(found) = route_table.entries.remove(inner_label.as_str()) {
+            protected.push((path, found));
+        }
+    }
+    num_applied += protected.len();
     let cfg_fn = move |c: &mut web::ServiceConfig| {
-        *num_applied_p = cfg
-            .into_iter()
-            .filter_map(|c| {
-                let (path, inner_label) = (c.path, c.handler);
-                route_table
-                    .entries
-                    .remove(inner_label.as_str())
-                    .map(|found| (path, found))
-            })
-            .map(|(path, route_found)| {
-                c.route(path.as_str(), route_found);
-            })
-            .count();
+        protected.into_iter().for_each(|(path, route_found)| {
+            c.route(path.as_str(), route_found);
+        });
     };
-    let path_prefix = format!("/{}", route_table.version.as_str());
-    let v_scope = web::scope(path_prefix.as_str()).configure(cfg_fn);
-    let app = App::new().service(v_scope);
+    let authn = Condition::new(authn_enabled, HttpAuthentication::bearer(validate_jwt));
+    let v_scope = web::scope(path_prefix.as_str())
+        .wrap(authn)
+        .configure(cfg_fn);
+    let app = app.service(v_scope);
     (app, num_applied)
 }
 
@@ -6,7 +6,7 @@ use ecommerce_common::error::AppErrorCode;
 
 use crate::adapter::processor::{AbstractPaymentProcessor, AppProcessorError};
 use crate::adapter::repository::{AbstractChargeRepo, AbstractMerchantRepo, AppRepoError};
-use crate::api::web::dto::CapturePayRespDto;
+use crate::api::web::dto::{CapturePayReqDto, CapturePayRespDto};
 use crate::auth::{AppAuthPermissionCode, AppAuthedClaim};
 use crate::model::{BuyerPayInState, Label3party, PayoutModel, PayoutModelError};
 
@@ -20,6 +20,8 @@ pub enum ChargeCaptureUcError {
     PermissionDenied(u32),
     PayInNotCompleted(BuyerPayInState),
     CorruptedPayMethod(String),
+    // requested lines or amount exceed the remaining capturable balance
+    InvalidCapture(PayoutModelError),
     CorruptedModel(PayoutModelError),
     ThirdParty(AppProcessorError),
     RepoOpFailure(AppRepoError),
@@ -36,8 +38,12 @@ impl ChargeCaptureUseCase {
     pub async fn execute(
         self,
         charge_id: String,
-        store_id: u32,
+        req: CapturePayReqDto,
     ) -> Result<CapturePayRespDto, ChargeCaptureUcError> {
+        let CapturePayReqDto {
+            store_id,
+            lines: req_lines,
+        } = req;
         let merchant_staff_id = self.auth_claim.profile;
         let success = self
             .auth_claim
@@ -77,9 +83,15 @@ impl ChargeCaptureUseCase {
             return Err(e);
         }
 
-        let opt_payout_m = self
+        let fee_sched = self
+            .repo_m
+            .fetch_platform_fee(store_id)
+            .await
+            .map_err(ChargeCaptureUcError::RepoOpFailure)?;
+
+        let old_payouts = self
             .repo_c
-            .fetch_payout(store_id, buyer_id, charge_ctime)
+            .fetch_payouts(store_id, buyer_id, charge_ctime)
             .await
             .map_err(ChargeCaptureUcError::RepoOpFailure)?;
 
@@ -89,9 +101,18 @@ impl ChargeCaptureUseCase {
                 merchant_prof,
                 merchant_3pty,
                 merchant_staff_id,
-                opt_payout_m,
+                old_payouts,
+                req_lines,
+                fee_sched,
             );
-            PayoutModel::try_from(arg).map_err(ChargeCaptureUcError::CorruptedModel)?
+            PayoutModel::try_from(arg).map_err(|e| match &e {
+                PayoutModelError::AmountNotEnough(..)
+                | PayoutModelError::CaptureLineNotExist(..)
+                | PayoutModelError::CaptureQtyExceedLimit(..) => {
+                    ChargeCaptureUcError::InvalidCapture(e)
+                }
+                _others => ChargeCaptureUcError::CorruptedModel(e),
+            })?
         };
 
         let result = self
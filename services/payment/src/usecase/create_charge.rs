@@ -1,22 +1,28 @@
 use std::boxed::Box;
 use std::sync::Arc;
+use std::time::Duration;
 
-use chrono::{DurationRound, Local, TimeDelta};
+use chrono::{DateTime, DurationRound, Local, TimeDelta, Utc};
 use ecommerce_common::api::dto::GenericRangeErrorDto;
 use ecommerce_common::api::rpc::dto::{OrderReplicaPaymentDto, OrderReplicaPaymentReqDto};
 use ecommerce_common::api::web::dto::BillingErrorDto;
+use ecommerce_common::logging::{app_log_event, AppLogContext, AppLogLevel};
 use ecommerce_common::model::order::BillingModel;
+use tokio::time::sleep;
 
-use crate::adapter::cache::{AbstractOrderSyncLockCache, OrderSyncLockError};
+use crate::adapter::cache::{AbstractOrderSyncLockCache, OrderSyncLockError, OrderSyncLockToken};
 use crate::adapter::processor::{AbstractPaymentProcessor, AppProcessorError};
 use crate::adapter::repository::{AbstractChargeRepo, AppRepoError};
 use crate::adapter::rpc::{AbstractRpcContext, AppRpcClientRequest, AppRpcCtxError};
 use crate::api::web::dto::{
     ChargeCreateRespDto, ChargeReqDto, ChargeRespErrorDto, PaymentMethodErrorReason,
 };
+use crate::hard_limit::{ORDERSYNC_LOCK_POLL_MILLIS, ORDERSYNC_LOCK_WAIT_SECS};
 use crate::model::{ChargeBuyerModel, OrderLineModelSet, OrderModelError};
 use crate::{AppAuthPermissionCode, AppAuthQuotaMatCode, AppAuthedClaim};
 
+use super::sync_order_app::{rpc_update_order_payment, SyncOrderAppUcError};
+
 // TODO, consider to add debug function for logging purpose
 pub enum ChargeCreateUcError {
     OrderOwnerMismatch,                   // client error, e.g. status code 403
@@ -80,6 +86,7 @@ pub struct ChargeCreateUseCase {
     pub rpc_ctx: Arc<Box<dyn AbstractRpcContext>>,
     pub ordersync_lockset: Arc<Box<dyn AbstractOrderSyncLockCache>>,
     pub repo: Box<dyn AbstractChargeRepo>,
+    pub logctx: Arc<AppLogContext>,
 }
 
 impl ChargeCreateUseCase {
@@ -89,22 +96,62 @@ impl ChargeCreateUseCase {
         req_body: ChargeReqDto,
     ) -> Result<ChargeCreateRespDto, ChargeCreateUcError> {
         let usr_id = authed_claim.profile;
-        let oid = req_body.order.id.as_str();
-        self.validate_access_control(authed_claim, oid).await?;
+        let oid = req_body.order.id.clone();
+        self.validate_access_control(authed_claim, oid.as_str())
+            .await?;
+        // concurrent charge requests to the same order are serialized, the lock
+        // is held until the order replica is loaded, validated and charged
+        let token = self.acquire_ordersync_lock(usr_id, oid.as_str()).await?;
+        let out = self.load_and_charge(usr_id, oid.as_str(), req_body).await;
+        let result = self
+            .ordersync_lockset
+            .release(usr_id, oid.as_str(), token)
+            .await;
+        if result.is_err() {
+            // the lock will be taken over by others once it expires
+            let logctx = &self.logctx;
+            app_log_event!(
+                logctx,
+                AppLogLevel::WARNING,
+                "lock-release, {usr_id}, {oid}"
+            );
+        }
+        out
+    } // end of fn execute
+
+    async fn acquire_ordersync_lock(
+        &self,
+        usr_id: u32,
+        oid: &str,
+    ) -> Result<OrderSyncLockToken, ChargeCreateUcError> {
+        let period = Duration::from_millis(ORDERSYNC_LOCK_POLL_MILLIS as u64);
+        let num_polls = (ORDERSYNC_LOCK_WAIT_SECS as u32 * 1000) / period.as_millis() as u32;
+        for _ in 0..num_polls {
+            if let Some(token) = self.ordersync_lockset.acquire(usr_id, oid).await? {
+                return Ok(token);
+            }
+            sleep(period).await;
+        }
+        Err(ChargeCreateUcError::LoadOrderConflict)
+    }
+
+    async fn load_and_charge(
+        &self,
+        usr_id: u32,
+        oid: &str,
+        req_body: ChargeReqDto,
+    ) -> Result<ChargeCreateRespDto, ChargeCreateUcError> {
         let result = self.repo.get_unpaid_olines(usr_id, oid).await?;
         let validated_order = if let Some(v) = result {
             v
         } else {
-            let d = self.rpc_sync_order(usr_id, oid).await?;
+            let d = self._rpc_sync_order(oid, usr_id).await?;
             self.try_save_order(usr_id, oid, d).await?
         };
         // TODO, verify whether all merchants/shops in the client request support
         // the specific 3rd-party processor
-        let resp = self
-            .try_execute_processor(validated_order, req_body)
-            .await?;
-        Ok(resp)
-    } // end of fn execute
+        self.try_execute_processor(validated_order, req_body).await
+    }
 
     #[allow(clippy::field_reassign_with_default)]
     async fn validate_access_control(
@@ -141,20 +188,6 @@ impl ChargeCreateUseCase {
         }
     } // end of fn validate_access_control
 
-    async fn rpc_sync_order(
-        &self,
-        usr_id: u32,
-        oid: &str,
-    ) -> Result<OrderReplicaPaymentDto, ChargeCreateUcError> {
-        let success = self.ordersync_lockset.acquire(usr_id, oid).await?;
-        if success {
-            let out = self._rpc_sync_order(oid, usr_id).await;
-            self.ordersync_lockset.release(usr_id, oid).await?;
-            out
-        } else {
-            Err(ChargeCreateUcError::LoadOrderConflict)
-        }
-    }
     async fn _rpc_sync_order(
         &self,
         oid: &str,
@@ -216,12 +249,37 @@ impl ChargeCreateUseCase {
             .await?;
         charge_buyer.meta.update_progress(&result.state);
         charge_buyer.meta.update_3party(method_m);
+        let (usr_id, ctime) = (charge_buyer.meta.owner(), *charge_buyer.meta.create_time());
         self.repo.create_charge(charge_buyer).await?;
         if result.completed {
-            // TODO, if the pay-in process is complete, invoke RPC to order service
-            // for payment status update
+            self.sync_order_app(usr_id, ctime).await;
         }
         let resp = ChargeCreateRespDto::from(result);
         Ok(resp)
     }
+
+    async fn sync_order_app(&self, usr_id: u32, ctime: DateTime<Utc>) {
+        // The charge has been saved, failure at this point is not reported to
+        // the client, the charge stays in `processor-completed` state until it
+        // is synced later by the background job
+        let repo = self.repo.as_ref();
+        let result = match repo.fetch_charge_meta(usr_id, ctime).await {
+            Ok(Some(mut meta)) => {
+                let rpc_ctx = self.rpc_ctx.as_ref().as_ref();
+                match rpc_update_order_payment(repo, rpc_ctx, &mut meta).await {
+                    Ok(()) => repo
+                        .update_charge_progress(meta)
+                        .await
+                        .map_err(SyncOrderAppUcError::DataStore),
+                    Err(e) => Err(e),
+                }
+            }
+            Ok(None) => Ok(()),
+            Err(e) => Err(SyncOrderAppUcError::DataStore(e)),
+        };
+        if let Err(e) = result {
+            let logctx = &self.logctx;
+            app_log_event!(logctx, AppLogLevel::WARNING, "{usr_id}, {ctime}, {:?}", e);
+        }
+    }
 } // end of impl ChargeCreateUseCase
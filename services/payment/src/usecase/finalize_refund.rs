@@ -12,8 +12,11 @@ use crate::adapter::repository::{
     AbstractChargeRepo, AbstractMerchantRepo, AbstractRefundRepo, AppRefundRslvReqCbReturn,
     AppRepoError, AppRepoErrorDetail,
 };
-use crate::api::web::dto::{RefundCompletionReqDto, RefundCompletionRespDto};
-use crate::model::{ChargeBuyerModel, ChargeRefundMap, OrderRefundModel, RefundReqResolutionModel};
+use crate::api::web::dto::RefundCompletionReqDto;
+use crate::model::{
+    ChargeBuyerModel, OrderRefundModel, RefundIdempotencyModel, RefundModelError,
+    RefundReqResolutionModel,
+};
 use crate::{AppAuthPermissionCode, AppAuthedClaim};
 
 #[derive(Debug)]
@@ -23,6 +26,9 @@ pub enum FinalizeRefundUcError {
     PermissionDenied(u32),
     MissingChargeId(String),
     MissingCharge(u32, DateTime<Utc>),
+    InvalidIdempotencyKey(RefundModelError),
+    // the key has been used with different order or completion request
+    IdempotencyConflict(String),
 }
 
 pub struct FinalizeRefundUseCase {
@@ -33,13 +39,16 @@ pub struct FinalizeRefundUseCase {
 }
 
 impl FinalizeRefundUseCase {
+    /// return the serialised response body, which is identical to the one
+    /// saved with the idempotency key, and errors from the processors
     pub async fn execute(
         self,
         oid: String,
         merchant_id: u32,
         authed_claim: AppAuthedClaim,
         cmplt_req: RefundCompletionReqDto,
-    ) -> Result<(RefundCompletionRespDto, Vec<AppProcessorError>), FinalizeRefundUcError> {
+        idempotency_key: Option<String>,
+    ) -> Result<(String, Vec<AppProcessorError>), FinalizeRefundUcError> {
         let staff_usr_id = authed_claim.profile;
         let success = authed_claim.contain_permission(AppAuthPermissionCode::can_finalize_refund);
         if !success {
@@ -59,6 +68,28 @@ impl FinalizeRefundUseCase {
         if !merc_prof.valid_staff(staff_usr_id) {
             return Err(FinalizeRefundUcError::PermissionDenied(staff_usr_id));
         }
+        let idempotency = if let Some(key) = idempotency_key {
+            let arg = (key, merchant_id, oid.as_str(), &cmplt_req);
+            let idem_m = RefundIdempotencyModel::try_from(arg)
+                .map_err(FinalizeRefundUcError::InvalidIdempotencyKey)?;
+            let saved = repo_rfd
+                .fetch_idempotent_result(merchant_id, idem_m.key())
+                .await
+                .map_err(FinalizeRefundUcError::DataStore)?;
+            if let Some((saved_m, resp)) = saved {
+                // replay the response of the request previously resolved
+                return if saved_m.same_request(&idem_m) {
+                    Ok((resp, Vec::new()))
+                } else {
+                    Err(FinalizeRefundUcError::IdempotencyConflict(
+                        idem_m.key().to_string(),
+                    ))
+                };
+            }
+            Some(idem_m)
+        } else {
+            None
+        };
         let (buyer_usr_id, charged_dtimes) = repo_ch
             .fetch_charge_ids(oid.as_str())
             .await
@@ -75,39 +106,35 @@ impl FinalizeRefundUseCase {
             charge_ms.push(charge_m);
         }
 
-        let result_rslv = repo_rfd
+        let (result_rslv, resp_body) = repo_rfd
             .resolve_request(
                 merchant_id,
                 cmplt_req,
+                idempotency,
                 charge_ms,
                 processors,
                 Self::hdlr_load_refund_req,
             )
             .await
-            .map_err(FinalizeRefundUcError::DataStore)?;
-
-        let mut rslv_ms = Vec::new();
-        let mut errs_proc = Vec::new();
-        result_rslv
-            .into_iter()
-            .map(|r| match r {
-                Ok(v) => {
-                    rslv_ms.push(v);
-                }
-                Err(e) => {
-                    errs_proc.push(e);
+            .map_err(|e| match e.detail {
+                // concurrent request with the same key has been resolved
+                AppRepoErrorDetail::IdempotencyKeyInUse(key) => {
+                    FinalizeRefundUcError::IdempotencyConflict(key)
                 }
-            })
-            .count();
+                detail => FinalizeRefundUcError::DataStore(AppRepoError {
+                    fn_label: e.fn_label,
+                    code: e.code,
+                    detail,
+                }),
+            })?;
 
-        // TODO / FIXME, idempotency key implementation
-        let charge_rfd_map = ChargeRefundMap::build(&rslv_ms);
-        repo_ch
-            .update_lines_refund(charge_rfd_map)
-            .await
-            .map_err(FinalizeRefundUcError::DataStore)?;
-        let o = RefundCompletionRespDto::from(rslv_ms);
-        Ok((o, errs_proc))
+        // refunded amount of the charge lines is updated by the repository in
+        // the same transaction, only processor errors are reported here
+        let errs_proc = result_rslv
+            .into_iter()
+            .filter_map(|r| r.err())
+            .collect::<Vec<_>>();
+        Ok((resp_body, errs_proc))
     } // end of fn execute
 
     fn hdlr_load_refund_req<'a>(
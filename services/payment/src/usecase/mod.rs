@@ -2,16 +2,25 @@ mod capture_charge;
 mod create_charge;
 mod finalize_refund;
 mod onboard;
+mod processor_webhook;
 mod refresh_charge_status;
+mod refund_status;
 mod reporting;
+mod sync_order_app;
 mod sync_refund_req;
 
 pub use capture_charge::{ChargeCaptureUcError, ChargeCaptureUseCase};
 pub use create_charge::{ChargeCreateUcError, ChargeCreateUseCase};
 pub use finalize_refund::{FinalizeRefundUcError, FinalizeRefundUseCase};
 pub use onboard::{OnboardStoreUcError, OnboardStoreUseCase, RefreshOnboardStatusUseCase};
+pub use processor_webhook::{ProcessorWebhookUcError, ProcessorWebhookUseCase};
 pub use refresh_charge_status::{ChargeRefreshUcError, ChargeStatusRefreshUseCase};
-pub use reporting::{MerchantReportChargeUcError, MerchantReportChargeUseCase};
+pub use refund_status::{RefundReqStatusUcError, RefundReqStatusUseCase};
+pub use reporting::{
+    MerchantReportChargeUcError, MerchantReportChargeUseCase, MerchantReportPayoutUseCase,
+    MerchantReportRefundUseCase, MerchantReportSettleUcError,
+};
+pub use sync_order_app::{SyncOrderAppUcError, SyncOrderAppUseCase};
 pub use sync_refund_req::{SyncRefundReqUcError, SyncRefundReqUseCase};
 
 use chrono::{DateTime, Utc};
@@ -0,0 +1,408 @@
+use std::boxed::Box;
+use std::result::Result;
+use std::sync::Arc;
+
+use chrono::Local;
+use rust_decimal::Decimal;
+
+use ecommerce_common::error::AppErrorCode;
+use ecommerce_common::logging::{app_log_event, AppLogContext, AppLogLevel};
+
+use crate::adapter::processor::{
+    AbstractPaymentProcessor, AppProcessorError, AppProcessorErrorReason,
+};
+use crate::adapter::repository::{
+    AbstractChargeRepo, AbstractMerchantRepo, AbstractWebhookEventRepo, AppRepoError,
+};
+use crate::adapter::rpc::AbstractRpcContext;
+use crate::model::{
+    ChargeBuyerMetaModel, Label3party, Merchant3partyModel, Merchant3partyStripeModel,
+    Payout3partyModel, StripeCheckoutPaymentStatusModel, StripeSessionStatusModel,
+    StripeWebhookEventDetail, StripeWebhookEventModel, Webhook3partyEventModel,
+};
+
+use super::sync_order_app::rpc_update_order_payment;
+use super::try_parse_charge_id;
+
+pub enum ProcessorWebhookUcError {
+    InvalidEvent(AppProcessorError),
+    ExternalProcessor(AppProcessorError),
+    DataStore(AppRepoError),
+    ChargeIdDecode(AppErrorCode, String),
+    CorruptedMerchant(u32, String),
+}
+
+/// Events pushed from external processors drive the same state transitions
+/// as the client-initiated refresh operations, but each event is applied at
+/// most once, the processors might deliver the same event several times.
+pub struct ProcessorWebhookUseCase {
+    pub processors: Arc<Box<dyn AbstractPaymentProcessor>>,
+    pub repo_evt: Box<dyn AbstractWebhookEventRepo>,
+    pub repo_charge: Box<dyn AbstractChargeRepo>,
+    pub repo_merchant: Box<dyn AbstractMerchantRepo>,
+    pub rpc_ctx: Arc<Box<dyn AbstractRpcContext>>,
+    pub logctx: Arc<AppLogContext>,
+}
+
+impl ProcessorWebhookUseCase {
+    /// return `false` if the event has been processed before
+    pub async fn execute(
+        self,
+        label3pt: Label3party,
+        sig_header: &str,
+        payload: &[u8],
+    ) -> Result<bool, ProcessorWebhookUcError> {
+        let evt = self
+            .processors
+            .webhook_event(label3pt, sig_header, payload)
+            .map_err(|e| match &e.reason {
+                AppProcessorErrorReason::InvalidWebhook(_) => {
+                    ProcessorWebhookUcError::InvalidEvent(e)
+                }
+                _others => ProcessorWebhookUcError::ExternalProcessor(e),
+            })?;
+        let label3pt = Label3party::from(&evt);
+        let (evt_id, evt_type, created, detail) = match evt {
+            Webhook3partyEventModel::Stripe(e) => {
+                let StripeWebhookEventModel {
+                    id,
+                    type_,
+                    created,
+                    detail,
+                } = e;
+                (id, type_, created, detail)
+            }
+        };
+        // record the event ahead of processing it, the unique key in the
+        // repository rejects the same event delivered concurrently
+        let newly_saved = self
+            .repo_evt
+            .save(label3pt, evt_id.as_str(), evt_type.as_str(), created)
+            .await
+            .map_err(ProcessorWebhookUcError::DataStore)?;
+        let logctx = &self.logctx;
+        if !newly_saved {
+            app_log_event!(logctx, AppLogLevel::DEBUG, "duplicate:{evt_id}");
+            return Ok(false);
+        }
+        if let Err(e) = self.handle_stripe(evt_id.as_str(), detail).await {
+            // let the 3rd party deliver the event again later
+            if let Err(e_rm) = self.repo_evt.delete(label3pt, evt_id.as_str()).await {
+                app_log_event!(logctx, AppLogLevel::ERROR, "evt:{evt_id}, {:?}", e_rm);
+            }
+            return Err(e);
+        }
+        Ok(true)
+    } // end of fn execute
+
+    async fn handle_stripe(
+        &self,
+        evt_id: &str,
+        detail: StripeWebhookEventDetail,
+    ) -> Result<(), ProcessorWebhookUcError> {
+        let logctx = &self.logctx;
+        match detail {
+            StripeWebhookEventDetail::CheckoutSession {
+                session_id,
+                client_reference_id,
+                session_state,
+                payment_state,
+            } => {
+                let args = (session_id, session_state, payment_state);
+                self.sync_checkout_session(evt_id, client_reference_id, args)
+                    .await
+            }
+            StripeWebhookEventDetail::AccountUpdated(m) => {
+                self.sync_connect_account(evt_id, m).await
+            }
+            StripeWebhookEventDetail::Transfer {
+                reversed,
+                transfer_id,
+                acct_id,
+                transfer_group,
+                amount,
+            } => {
+                let args = (transfer_id, acct_id, transfer_group, amount);
+                self.verify_transfer(evt_id, reversed, args).await
+            }
+            StripeWebhookEventDetail::ChargeRefunded {
+                payment_intent_id,
+                transfer_group,
+                amount_refunded,
+            } => {
+                let args = (payment_intent_id, transfer_group, amount_refunded);
+                self.sync_charge_refund(evt_id, args).await
+            }
+            StripeWebhookEventDetail::Unsupported => {
+                app_log_event!(logctx, AppLogLevel::DEBUG, "evt:{evt_id}, unsupported");
+                Ok(())
+            }
+        }
+    } // end of fn handle_stripe
+
+    async fn sync_checkout_session(
+        &self,
+        evt_id: &str,
+        client_ref: Option<String>,
+        args: (
+            String,
+            StripeSessionStatusModel,
+            StripeCheckoutPaymentStatusModel,
+        ),
+    ) -> Result<(), ProcessorWebhookUcError> {
+        let logctx = &self.logctx;
+        let (session_id, session_state, payment_state) = args;
+        // the client reference ID is composed of buyer ID and order ID
+        let parsed = client_ref.as_ref().and_then(|v| {
+            let (owner, oid) = v.split_once('-')?;
+            let owner = owner.parse::<u32>().ok()?;
+            Some((owner, oid.to_string()))
+        });
+        let (owner, oid) = if let Some(v) = parsed {
+            v
+        } else {
+            app_log_event!(
+                logctx,
+                AppLogLevel::WARNING,
+                "evt:{evt_id}, invalid-client-ref:{:?}",
+                client_ref
+            );
+            return Ok(());
+        };
+        let maybe_ids = self
+            .repo_charge
+            .fetch_charge_ids(oid.as_str())
+            .await
+            .map_err(ProcessorWebhookUcError::DataStore)?;
+        let ctimes = match maybe_ids {
+            Some((buyer_id, ctimes)) if buyer_id == owner => ctimes,
+            _others => {
+                app_log_event!(
+                    logctx,
+                    AppLogLevel::WARNING,
+                    "evt:{evt_id}, charge-not-found, owner:{owner}, oid:{oid}"
+                );
+                return Ok(());
+            }
+        };
+        // the session usually belongs to the latest charge of the order
+        for ctime in ctimes.into_iter().rev() {
+            let maybe_meta = self
+                .repo_charge
+                .fetch_charge_meta(owner, ctime)
+                .await
+                .map_err(ProcessorWebhookUcError::DataStore)?;
+            let mut meta = if let Some(v) = maybe_meta {
+                v
+            } else {
+                continue;
+            };
+            let t_now = Local::now().to_utc();
+            let matched = meta.sync_stripe_session(
+                session_id.as_str(),
+                session_state.clone(),
+                payment_state.clone(),
+                t_now,
+            );
+            if matched {
+                self.sync_order_app(evt_id, &mut meta).await;
+                return self
+                    .repo_charge
+                    .update_charge_progress(meta)
+                    .await
+                    .map_err(ProcessorWebhookUcError::DataStore);
+            }
+        }
+        app_log_event!(
+            logctx,
+            AppLogLevel::WARNING,
+            "evt:{evt_id}, session-not-found:{session_id}, oid:{oid}"
+        );
+        Ok(())
+    } // end of fn sync_checkout_session
+
+    async fn sync_charge_refund(
+        &self,
+        evt_id: &str,
+        args: (Option<String>, Option<String>, i64),
+    ) -> Result<(), ProcessorWebhookUcError> {
+        let logctx = &self.logctx;
+        let (payment_intent_id, transfer_group, amount_refunded) = args;
+        // the transfer group is the serialized charge ID, a charge without it
+        // was not created by this application
+        let (payment_intent_id, transfer_group) = match (payment_intent_id, transfer_group) {
+            (Some(p), Some(t)) => (p, t),
+            (p, t) => {
+                app_log_event!(
+                    logctx,
+                    AppLogLevel::WARNING,
+                    "evt:{evt_id}, charge-unknown, payment-intent:{:?}, transfer-group:{:?}",
+                    p,
+                    t
+                );
+                return Ok(());
+            }
+        };
+        let (buyer_id, charge_ctime) = try_parse_charge_id(transfer_group.as_str())
+            .map_err(|(code, detail)| ProcessorWebhookUcError::ChargeIdDecode(code, detail))?;
+        let maybe_meta = self
+            .repo_charge
+            .fetch_charge_meta(buyer_id, charge_ctime)
+            .await
+            .map_err(ProcessorWebhookUcError::DataStore)?;
+        let mut meta = if let Some(v) = maybe_meta {
+            v
+        } else {
+            app_log_event!(
+                logctx,
+                AppLogLevel::WARNING,
+                "evt:{evt_id}, charge-not-found:{transfer_group}"
+            );
+            return Ok(());
+        };
+        match meta.sync_stripe_refund(payment_intent_id.as_str(), amount_refunded) {
+            Some(true) => self
+                .repo_charge
+                .update_charge_progress(meta)
+                .await
+                .map_err(ProcessorWebhookUcError::DataStore),
+            Some(false) => Ok(()),
+            None => {
+                app_log_event!(
+                    logctx,
+                    AppLogLevel::WARNING,
+                    "evt:{evt_id}, payment-intent-mismatch:{payment_intent_id}, \
+                     charge:{transfer_group}"
+                );
+                Ok(())
+            }
+        }
+    } // end of fn sync_charge_refund
+
+    async fn sync_order_app(&self, evt_id: &str, meta: &mut ChargeBuyerMetaModel) {
+        let confirmed = meta.method_3party().pay_in_comfirmed().unwrap_or(false);
+        if !confirmed || meta.progress().completed() {
+            return;
+        }
+        // on failure, the charge stays in `processor-completed` state until it
+        // is synced later by the background job
+        let repo = self.repo_charge.as_ref();
+        let rpc_ctx = self.rpc_ctx.as_ref().as_ref();
+        if let Err(e) = rpc_update_order_payment(repo, rpc_ctx, meta).await {
+            let logctx = &self.logctx;
+            app_log_event!(logctx, AppLogLevel::WARNING, "evt:{evt_id}, {:?}", e);
+        }
+    }
+
+    async fn sync_connect_account(
+        &self,
+        evt_id: &str,
+        latest: Merchant3partyStripeModel,
+    ) -> Result<(), ProcessorWebhookUcError> {
+        let logctx = &self.logctx;
+        let label3pt = Label3party::Stripe;
+        let maybe_store_id = self
+            .repo_merchant
+            .fetch_id_by_3party(label3pt, latest.id.as_str())
+            .await
+            .map_err(ProcessorWebhookUcError::DataStore)?;
+        let maybe_saved = if let Some(store_id) = maybe_store_id {
+            self.repo_merchant
+                .fetch(store_id, label3pt)
+                .await
+                .map_err(ProcessorWebhookUcError::DataStore)?
+                .map(|(_prof, m3pty)| (store_id, m3pty))
+        } else {
+            None
+        };
+        let (store_id, mut saved) = match maybe_saved {
+            Some((store_id, Merchant3partyModel::Stripe(s))) => (store_id, s),
+            _others => {
+                app_log_event!(
+                    logctx,
+                    AppLogLevel::WARNING,
+                    "evt:{evt_id}, merchant-not-found:{}",
+                    latest.id
+                );
+                return Ok(());
+            }
+        };
+        let modified = saved
+            .sync_account(latest)
+            .map_err(|msg| ProcessorWebhookUcError::CorruptedMerchant(store_id, msg))?;
+        if modified {
+            let m3pty = Merchant3partyModel::Stripe(saved);
+            self.repo_merchant
+                .update_3party(store_id, m3pty)
+                .await
+                .map_err(ProcessorWebhookUcError::DataStore)?;
+        }
+        Ok(())
+    } // end of fn sync_connect_account
+
+    async fn verify_transfer(
+        &self,
+        evt_id: &str,
+        reversed: bool,
+        args: (String, String, String, Decimal),
+    ) -> Result<(), ProcessorWebhookUcError> {
+        let logctx = &self.logctx;
+        let (transfer_id, acct_id, transfer_group, amount) = args;
+        // the transfer group is the serialized charge ID
+        let (buyer_id, charge_ctime) = try_parse_charge_id(transfer_group.as_str())
+            .map_err(|(code, detail)| ProcessorWebhookUcError::ChargeIdDecode(code, detail))?;
+        let maybe_store_id = self
+            .repo_merchant
+            .fetch_id_by_3party(Label3party::Stripe, acct_id.as_str())
+            .await
+            .map_err(ProcessorWebhookUcError::DataStore)?;
+        let payouts = if let Some(store_id) = maybe_store_id {
+            self.repo_charge
+                .fetch_payouts(store_id, buyer_id, charge_ctime)
+                .await
+                .map_err(ProcessorWebhookUcError::DataStore)?
+        } else {
+            Vec::new()
+        };
+        // a charge may be captured several times, each payout is transferred
+        // separately in Stripe
+        let maybe_payout = payouts.into_iter().find(|p| match p.thirdparty() {
+            Payout3partyModel::Stripe(s) => s.transfer_id() == Some(transfer_id.as_str()),
+            Payout3partyModel::Paypal(_) => false,
+        });
+        let payout_m = if let Some(v) = maybe_payout {
+            v
+        } else {
+            app_log_event!(
+                logctx,
+                AppLogLevel::WARNING,
+                "evt:{evt_id}, payout-not-found, transfer:{transfer_id}, acct:{acct_id}"
+            );
+            return Ok(());
+        };
+        let result = match payout_m.thirdparty() {
+            Payout3partyModel::Stripe(s) => {
+                s.validate_transfer(transfer_id.as_str(), acct_id.as_str(), amount)
+            }
+            Payout3partyModel::Paypal(_) => Err("label3party".to_string()),
+        };
+        if let Err(msg) = result {
+            app_log_event!(
+                logctx,
+                AppLogLevel::ERROR,
+                "evt:{evt_id}, transfer:{transfer_id}, mismatch:{msg}"
+            );
+        }
+        if reversed {
+            app_log_event!(
+                logctx,
+                AppLogLevel::WARNING,
+                "evt:{evt_id}, transfer-reversed:{transfer_id}, store:{}, buyer:{buyer_id}, \
+                 charged:{}",
+                payout_m.merchant_id(),
+                charge_ctime.to_rfc3339()
+            );
+        }
+        Ok(())
+    } // end of fn verify_transfer
+} // end of impl ProcessorWebhookUseCase
@@ -9,11 +9,12 @@ use ecommerce_common::error::AppErrorCode;
 
 use crate::adapter::processor::{AbstractPaymentProcessor, AppProcessorError};
 use crate::adapter::repository::{AbstractChargeRepo, AppRepoError};
-use crate::adapter::rpc::{AbstractRpcContext, AppRpcClientRequest, AppRpcCtxError};
+use crate::adapter::rpc::{AbstractRpcContext, AppRpcCtxError};
 use crate::api::web::dto::ChargeRefreshRespDto;
 use crate::model::{BuyerPayInState, ChargeBuyerMetaModel};
 use crate::{AppAuthPermissionCode, AppAuthedClaim};
 
+use super::sync_order_app::{rpc_update_order_payment, SyncOrderAppUcError};
 use super::try_parse_charge_id;
 
 pub enum ChargeRefreshUcError {
@@ -28,6 +29,17 @@ pub enum ChargeRefreshUcError {
     RpcUpdateOrder(OrderPaymentUpdateErrorDto),
 }
 
+impl From<SyncOrderAppUcError> for ChargeRefreshUcError {
+    fn from(value: SyncOrderAppUcError) -> Self {
+        match value {
+            SyncOrderAppUcError::RpcContext(e) => Self::RpcContext(e),
+            SyncOrderAppUcError::RpcContentSerialisation(msg) => Self::RpcContentSerialisation(msg),
+            SyncOrderAppUcError::RpcUpdateOrder(e) => Self::RpcUpdateOrder(e),
+            SyncOrderAppUcError::DataStore(e) => Self::DataStore(e),
+        }
+    }
+}
+
 pub struct ChargeStatusRefreshUseCase {
     pub processors: Arc<Box<dyn AbstractPaymentProcessor>>,
     pub repo: Box<dyn AbstractChargeRepo>,
@@ -62,7 +74,11 @@ impl ChargeStatusRefreshUseCase {
             None => self.refresh_3pty_processor(&mut saved_meta).await?,
         };
         let result_rpc = if proceed_allowed && !saved_meta.progress().completed() {
-            self.sync_order_app(&mut saved_meta).await
+            let repo = self.repo.as_ref();
+            let rpc_ctx = self.rpc_ctx.as_ref().as_ref();
+            rpc_update_order_payment(repo, rpc_ctx, &mut saved_meta)
+                .await
+                .map_err(ChargeRefreshUcError::from)
             // postpone the error return, always write charge status to database
             // repository if necessary
         } else {
@@ -103,59 +119,4 @@ impl ChargeStatusRefreshUseCase {
             .unwrap_or(false);
         Ok(proceed_allowed)
     }
-
-    async fn sync_order_app(
-        &self,
-        meta: &mut ChargeBuyerMetaModel,
-    ) -> Result<(), ChargeRefreshUcError> {
-        let client = self
-            .rpc_ctx
-            .acquire()
-            .await
-            .map_err(ChargeRefreshUcError::RpcContext)?;
-        let message = self.rpc_build_charge_lines(meta).await?;
-        let props = AppRpcClientRequest {
-            usr_id: meta.owner(),
-            // Note, the reason to specify this `create-time` field instead of current
-            // time is that order-processing service can handle idempotency based on
-            // this create time, TODO, find better design approach
-            time: *meta.create_time(),
-            route: "rpc.order.order_reserved_update_payment".to_string(),
-            message,
-        };
-        let mut event = client
-            .send_request(props)
-            .await
-            .map_err(ChargeRefreshUcError::RpcContext)?;
-        let reply = event
-            .receive_response()
-            .await
-            .map_err(ChargeRefreshUcError::RpcContext)?;
-        let resp_detail = serde_json::from_slice::<OrderPaymentUpdateErrorDto>(&reply.message)
-            .map_err(|e| ChargeRefreshUcError::RpcContentSerialisation(e.to_string()))?;
-        let has_err = resp_detail.charge_time.is_some() | !resp_detail.lines.is_empty();
-        if has_err {
-            Err(ChargeRefreshUcError::RpcUpdateOrder(resp_detail))
-        } else {
-            let now = Local::now().to_utc();
-            let new_state = BuyerPayInState::OrderAppSynced(now);
-            meta.update_progress(&new_state);
-            Ok(())
-        }
-    } // end of fn sync_order_app
-
-    async fn rpc_build_charge_lines(
-        &self,
-        meta: &ChargeBuyerMetaModel,
-    ) -> Result<Vec<u8>, ChargeRefreshUcError> {
-        let chg_lines = self
-            .repo
-            .fetch_all_charge_lines(meta.owner(), *meta.create_time())
-            .await
-            .map_err(ChargeRefreshUcError::DataStore)?;
-        let update_dto = meta.pay_update_dto(chg_lines);
-        let serialised = serde_json::to_vec(&update_dto)
-            .map_err(|e| ChargeRefreshUcError::RpcContentSerialisation(e.to_string()))?;
-        Ok(serialised)
-    }
 } // end of impl ChargeStatusRefreshUseCase
@@ -0,0 +1,52 @@
+use std::boxed::Box;
+use std::result::Result;
+
+use crate::adapter::repository::{AbstractChargeRepo, AbstractRefundRepo, AppRepoError};
+use crate::api::web::dto::RefundStatusRespDto;
+use crate::model::ChargeRefundMap;
+use crate::AppAuthedClaim;
+
+#[derive(Debug)]
+pub enum RefundReqStatusUcError {
+    DataStore(AppRepoError),
+    MissingChargeId(String),
+    OwnerMismatch(u32),
+}
+
+pub struct RefundReqStatusUseCase {
+    pub repo_ch: Box<dyn AbstractChargeRepo>,
+    pub repo_rfd: Box<dyn AbstractRefundRepo>,
+}
+
+impl RefundReqStatusUseCase {
+    pub async fn execute(
+        self,
+        oid: String,
+        authed_claim: AppAuthedClaim,
+    ) -> Result<RefundStatusRespDto, RefundReqStatusUcError> {
+        let auth_usr_id = authed_claim.profile;
+        let Self { repo_ch, repo_rfd } = self;
+        let (buyer_usr_id, charged_dtimes) = repo_ch
+            .fetch_charge_ids(oid.as_str())
+            .await
+            .map_err(RefundReqStatusUcError::DataStore)?
+            .ok_or(RefundReqStatusUcError::MissingChargeId(oid.clone()))?;
+        if buyer_usr_id != auth_usr_id {
+            return Err(RefundReqStatusUcError::OwnerMismatch(auth_usr_id));
+        }
+        let mut charges = Vec::new();
+        for ctime in charged_dtimes {
+            let clines = repo_ch
+                .fetch_all_charge_lines(buyer_usr_id, ctime)
+                .await
+                .map_err(RefundReqStatusUcError::DataStore)?;
+            charges.push((ctime, clines));
+        }
+        let refunded_map = ChargeRefundMap::from((buyer_usr_id, charges)).aggregate_lines();
+        let rfnd_m = repo_rfd
+            .fetch_request_by_order(oid.as_str())
+            .await
+            .map_err(RefundReqStatusUcError::DataStore)?;
+        Ok(RefundStatusRespDto::from((rfnd_m, refunded_map)))
+    } // end of fn execute
+} // end of impl RefundReqStatusUseCase
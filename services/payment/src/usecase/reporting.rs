@@ -1,12 +1,31 @@
 use std::result::Result;
 
+use ecommerce_common::error::AppErrorCode;
+
+use super::try_parse_charge_id;
 use crate::adapter::repository::{AbstractMerchantRepo, AbstractReportingRepo, AppRepoError};
-use crate::api::web::dto::{ReportChargeRespDto, ReportTimeRangeDto};
+use crate::api::web::dto::{
+    ReportChargePageReqDto, ReportChargeRespDto, ReportPayoutRespDto, ReportRefundRespDto,
+    ReportTimeRangeDto,
+};
 use crate::auth::AppAuthedClaim;
-use crate::model::{MerchantReportChargeModel, ReportModelError};
+use crate::hard_limit::{DEFAULT_REPORT_CHARGES_PAGE, MAX_REPORT_CHARGES_PAGE};
+use crate::model::{
+    MerchantReportChargeModel, MerchantReportPayoutModel, MerchantReportRefundModel,
+    ReportModelError,
+};
 
 #[derive(Debug)]
 pub enum MerchantReportChargeUcError {
+    DataStore(AppRepoError),
+    InvalidCursor(AppErrorCode, String),
+    MissingMerchant(u32),
+    PermissionDenied(u32),
+    TransformFailure(Vec<ReportModelError>),
+}
+
+#[derive(Debug)]
+pub enum MerchantReportSettleUcError {
     DataStore(AppRepoError),
     MissingMerchant(u32),
     PermissionDenied(u32),
@@ -36,7 +55,24 @@ impl MerchantReportChargeUseCase {
         self,
         merchant_id: u32,
         time_range: ReportTimeRangeDto,
+        page: ReportChargePageReqDto,
     ) -> Result<ReportChargeRespDto, MerchantReportChargeUcError> {
+        let ReportChargePageReqDto {
+            cursor,
+            limit,
+            breakdown,
+            format: _,
+        } = page;
+        let cursor = cursor
+            .as_deref()
+            .map(try_parse_charge_id)
+            .transpose()
+            .map_err(|(code, detail)| MerchantReportChargeUcError::InvalidCursor(code, detail))?;
+        // the report is always split into pages, including CSV export, in
+        // order not to load all the charges of a long time range at once
+        let limit = limit
+            .unwrap_or(DEFAULT_REPORT_CHARGES_PAGE)
+            .clamp(1, MAX_REPORT_CHARGES_PAGE);
         let staff_usr_id = self.authed_claim.profile;
         let merc_prof = self
             .repo_mc
@@ -49,14 +85,134 @@ impl MerchantReportChargeUseCase {
         }
         let saved_charges = self
             .repo_rpt
-            .fetch_charges_by_merchant(merchant_id, time_range.clone())
+            .fetch_charges_by_merchant(merchant_id, time_range.clone(), cursor, limit)
+            .await
+            .map_err(MerchantReportChargeUcError::DataStore)?;
+        // there might be more charges to load if current page is full
+        let next_cursor = if saved_charges.len() >= limit as usize {
+            saved_charges.last().map(|c| c.meta.token().to_string())
+        } else {
+            None
+        };
+        let charge_ids = saved_charges
+            .iter()
+            .map(|c| (c.meta.owner(), *c.meta.create_time()))
+            .collect::<Vec<_>>();
+        let saved_payouts = self
+            .repo_rpt
+            .fetch_payouts_by_charges(merchant_id, charge_ids)
             .await
             .map_err(MerchantReportChargeUcError::DataStore)?;
-        let mut report_m = MerchantReportChargeModel::from((merchant_id, time_range));
+        let arg = (merchant_id, time_range, breakdown.unwrap_or_default());
+        let mut report_m = MerchantReportChargeModel::from(arg);
         report_m
-            .try_merge(saved_charges)
+            .try_merge(saved_charges, saved_payouts)
             .map_err(MerchantReportChargeUcError::TransformFailure)?;
-        let summary = ReportChargeRespDto::from(report_m);
+        let mut summary = ReportChargeRespDto::from(report_m);
+        summary.next_cursor = next_cursor;
         Ok(summary)
     }
 } // end of impl MerchantReportChargeUseCase
+
+/// refunds resolved by the merchant within given time range
+pub struct MerchantReportRefundUseCase {
+    authed_claim: AppAuthedClaim,
+    repo_mc: Box<dyn AbstractMerchantRepo>,
+    repo_rpt: Box<dyn AbstractReportingRepo>,
+}
+
+/// payouts transferred to the merchant within given time range, with net
+/// settlement after deducting the refunds resolved in the same period
+pub struct MerchantReportPayoutUseCase {
+    authed_claim: AppAuthedClaim,
+    repo_mc: Box<dyn AbstractMerchantRepo>,
+    repo_rpt: Box<dyn AbstractReportingRepo>,
+}
+
+async fn check_merchant_staff(
+    repo_mc: &dyn AbstractMerchantRepo,
+    merchant_id: u32,
+    staff_usr_id: u32,
+) -> Result<(), MerchantReportSettleUcError> {
+    let merc_prof = repo_mc
+        .fetch_profile(merchant_id)
+        .await
+        .map_err(MerchantReportSettleUcError::DataStore)?
+        .ok_or(MerchantReportSettleUcError::MissingMerchant(merchant_id))?;
+    if merc_prof.valid_staff(staff_usr_id) {
+        Ok(())
+    } else {
+        Err(MerchantReportSettleUcError::PermissionDenied(staff_usr_id))
+    }
+}
+
+impl MerchantReportRefundUseCase {
+    pub fn new(
+        authed_claim: AppAuthedClaim,
+        repo_mc: Box<dyn AbstractMerchantRepo>,
+        repo_rpt: Box<dyn AbstractReportingRepo>,
+    ) -> Self {
+        Self {
+            authed_claim,
+            repo_rpt,
+            repo_mc,
+        }
+    }
+
+    pub async fn execute(
+        self,
+        merchant_id: u32,
+        time_range: ReportTimeRangeDto,
+    ) -> Result<ReportRefundRespDto, MerchantReportSettleUcError> {
+        let staff_usr_id = self.authed_claim.profile;
+        check_merchant_staff(self.repo_mc.as_ref(), merchant_id, staff_usr_id).await?;
+        let resolved = self
+            .repo_rpt
+            .fetch_refunds_by_merchant(merchant_id, time_range.clone())
+            .await
+            .map_err(MerchantReportSettleUcError::DataStore)?;
+        let mut report_m = MerchantReportRefundModel::from((merchant_id, time_range));
+        report_m
+            .try_merge(resolved)
+            .map_err(MerchantReportSettleUcError::TransformFailure)?;
+        Ok(ReportRefundRespDto::from(report_m))
+    }
+} // end of impl MerchantReportRefundUseCase
+
+impl MerchantReportPayoutUseCase {
+    pub fn new(
+        authed_claim: AppAuthedClaim,
+        repo_mc: Box<dyn AbstractMerchantRepo>,
+        repo_rpt: Box<dyn AbstractReportingRepo>,
+    ) -> Self {
+        Self {
+            authed_claim,
+            repo_rpt,
+            repo_mc,
+        }
+    }
+
+    pub async fn execute(
+        self,
+        merchant_id: u32,
+        time_range: ReportTimeRangeDto,
+    ) -> Result<ReportPayoutRespDto, MerchantReportSettleUcError> {
+        let staff_usr_id = self.authed_claim.profile;
+        check_merchant_staff(self.repo_mc.as_ref(), merchant_id, staff_usr_id).await?;
+        let payouts = self
+            .repo_rpt
+            .fetch_payouts_by_merchant(merchant_id, time_range.clone())
+            .await
+            .map_err(MerchantReportSettleUcError::DataStore)?;
+        let resolved = self
+            .repo_rpt
+            .fetch_refunds_by_merchant(merchant_id, time_range.clone())
+            .await
+            .map_err(MerchantReportSettleUcError::DataStore)?;
+        let mut report_m = MerchantReportPayoutModel::from((merchant_id, time_range));
+        report_m
+            .try_merge(payouts, resolved)
+            .map_err(MerchantReportSettleUcError::TransformFailure)?;
+        Ok(ReportPayoutRespDto::from(report_m))
+    }
+} // end of impl MerchantReportPayoutUseCase
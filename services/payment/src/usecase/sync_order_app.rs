@@ -0,0 +1,141 @@
+use std::boxed::Box;
+use std::result::Result;
+use std::sync::Arc;
+
+use chrono::{Duration, Local};
+
+use ecommerce_common::api::rpc::dto::OrderPaymentUpdateErrorDto;
+use ecommerce_common::logging::{app_log_event, AppLogContext, AppLogLevel};
+
+use crate::adapter::repository::{AbstractChargeRepo, AppRepoError};
+use crate::adapter::rpc::{AbstractRpcContext, AppRpcClientRequest, AppRpcCtxError};
+use crate::hard_limit;
+use crate::model::{BuyerPayInState, ChargeBuyerMetaModel};
+
+#[derive(Debug)]
+pub enum SyncOrderAppUcError {
+    RpcContext(AppRpcCtxError),
+    RpcContentSerialisation(String),
+    RpcUpdateOrder(OrderPaymentUpdateErrorDto),
+    DataStore(AppRepoError),
+}
+
+/// Report confirmed pay-in to the order-processing service, the charge state
+/// is switched to `OrderAppSynced` on success. Callers are responsible for
+/// saving the latest state of the charge to repository.
+pub(super) async fn rpc_update_order_payment(
+    repo: &dyn AbstractChargeRepo,
+    rpc_ctx: &dyn AbstractRpcContext,
+    meta: &mut ChargeBuyerMetaModel,
+) -> Result<(), SyncOrderAppUcError> {
+    let client = rpc_ctx
+        .acquire()
+        .await
+        .map_err(SyncOrderAppUcError::RpcContext)?;
+    let chg_lines = repo
+        .fetch_all_charge_lines(meta.owner(), *meta.create_time())
+        .await
+        .map_err(SyncOrderAppUcError::DataStore)?;
+    let update_dto = meta.pay_update_dto(chg_lines);
+    let message = serde_json::to_vec(&update_dto)
+        .map_err(|e| SyncOrderAppUcError::RpcContentSerialisation(e.to_string()))?;
+    let props = AppRpcClientRequest {
+        usr_id: meta.owner(),
+        // Note, the reason to specify this `create-time` field instead of current
+        // time is that order-processing service can handle idempotency based on
+        // this create time, TODO, find better design approach
+        time: *meta.create_time(),
+        route: "rpc.order.order_reserved_update_payment".to_string(),
+        message,
+    };
+    let mut event = client
+        .send_request(props)
+        .await
+        .map_err(SyncOrderAppUcError::RpcContext)?;
+    let reply = event
+        .receive_response()
+        .await
+        .map_err(SyncOrderAppUcError::RpcContext)?;
+    let resp_detail = serde_json::from_slice::<OrderPaymentUpdateErrorDto>(&reply.message)
+        .map_err(|e| SyncOrderAppUcError::RpcContentSerialisation(e.to_string()))?;
+    let has_err = resp_detail.charge_time.is_some() | !resp_detail.lines.is_empty();
+    if has_err {
+        Err(SyncOrderAppUcError::RpcUpdateOrder(resp_detail))
+    } else {
+        let now = Local::now().to_utc();
+        let new_state = BuyerPayInState::OrderAppSynced(now);
+        meta.update_progress(&new_state);
+        Ok(())
+    }
+} // end of fn rpc_update_order_payment
+
+/// Retry syncing charges which have been completed by external processors
+/// but never reached the order-processing service, e.g. due to network
+/// failure right after the pay-in completed.
+pub struct SyncOrderAppUseCase {
+    pub repo: Box<dyn AbstractChargeRepo>,
+    pub rpc_ctx: Arc<Box<dyn AbstractRpcContext>>,
+    pub logctx: Arc<AppLogContext>,
+}
+
+impl SyncOrderAppUseCase {
+    /// return number of charges synced successfully and number of failures
+    pub async fn execute(self) -> Result<(usize, usize), SyncOrderAppUcError> {
+        let logctx = &self.logctx;
+        let t_now = Local::now().to_utc();
+        // skip recently completed charges, which might be still handled by
+        // other API calls e.g. refreshing charge status
+        let t_end = t_now - Duration::seconds(hard_limit::ORDERAPP_SYNC_DELAY_SECS as i64);
+        let t_start = t_now - Duration::hours(hard_limit::ORDERAPP_SYNC_MAX_HOURS as i64);
+        let max_items = hard_limit::ORDERAPP_SYNC_MAX_ITEMS;
+        let (mut num_synced, mut num_failed) = (0usize, 0usize);
+        let mut cursor = None;
+        loop {
+            let metas = self
+                .repo
+                .fetch_charges_unsynced(t_start, t_end, cursor, max_items)
+                .await
+                .map_err(SyncOrderAppUcError::DataStore)?;
+            let num_loaded = metas.len();
+            // charges skipped or failed in this round are kept in the range,
+            // move on to next page right after the last one loaded
+            cursor = metas.last().and_then(|m| match m.progress() {
+                BuyerPayInState::ProcessorCompleted(t) => Some((*t, m.owner(), *m.create_time())),
+                _others => None,
+            });
+            for mut meta in metas {
+                // charges rejected by the processors are never reported to order
+                // processing service
+                let confirmed = meta.method_3party().pay_in_comfirmed().unwrap_or(false);
+                if !confirmed {
+                    continue;
+                }
+                let (owner, ctime) = (meta.owner(), meta.create_time().to_rfc3339());
+                let result = rpc_update_order_payment(
+                    self.repo.as_ref(),
+                    self.rpc_ctx.as_ref().as_ref(),
+                    &mut meta,
+                )
+                .await;
+                let result = match result {
+                    Ok(()) => self
+                        .repo
+                        .update_charge_progress(meta)
+                        .await
+                        .map_err(SyncOrderAppUcError::DataStore),
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = result {
+                    app_log_event!(logctx, AppLogLevel::WARNING, "{owner}, {ctime}, {:?}", e);
+                    num_failed += 1;
+                } else {
+                    num_synced += 1;
+                }
+            }
+            if num_loaded < max_items as usize || cursor.is_none() {
+                break;
+            }
+        }
+        Ok((num_synced, num_failed))
+    } // end of fn execute
+} // end of impl SyncOrderAppUseCase
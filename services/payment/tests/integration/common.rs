@@ -40,10 +40,11 @@ pub(crate) async fn itest_setup_app_server() -> (ItestService!(), AppSharedState
     let listener_ref = &cfg.api_server.listen;
     let api_ver = listener_ref.api_version.as_str();
     let route_table = AppRouteTable::get(api_ver);
-    assert_eq!(route_table.entries.len(), 7);
+    assert_eq!(route_table.entries.len(), 10);
+    assert_eq!(route_table.public_entries.len(), 1);
     let cfg_routes = cfg.api_server.listen.routes.clone();
-    let (app, num_applied) = app_web_service(route_table, cfg_routes);
-    assert_eq!(num_applied, 7);
+    let (app, num_applied) = app_web_service(route_table, cfg_routes, false);
+    assert_eq!(num_applied, 11);
     let shr_state = AppSharedState::new(cfg).unwrap();
     let app = app.app_data(WebData::new(shr_state.clone()));
     let srv = init_service(app).await;
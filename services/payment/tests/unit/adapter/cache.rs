@@ -0,0 +1,78 @@
+use std::boxed::Box;
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::rt;
+use payment::adapter::cache::{AbstractOrderSyncLockCache, OrderSyncLockToken};
+
+use crate::{ut_setup_inmem_ordersync_lock, ut_setup_sharestate};
+
+async fn ut_concurrent_acquire(
+    lockset: Arc<Box<dyn AbstractOrderSyncLockCache>>,
+    usr_id: u32,
+    oid: &str,
+) -> OrderSyncLockToken {
+    let (r0, r1) = futures_util::join!(lockset.acquire(usr_id, oid), lockset.acquire(usr_id, oid));
+    let (r0, r1) = (r0.ok().unwrap(), r1.ok().unwrap());
+    // only one of the callers acquires the lock
+    assert!(r0.is_some() ^ r1.is_some());
+    r0.or(r1).unwrap()
+}
+
+#[actix_web::test]
+async fn inmem_lock_acquire_release_ok() {
+    let lockset = ut_setup_inmem_ordersync_lock(20);
+    let (mock_usr_id, mock_oid) = (1234, "0e2f3a4b");
+    let token = ut_concurrent_acquire(lockset.clone(), mock_usr_id, mock_oid).await;
+    // lock on different order of the same buyer, or the same order ID of
+    // different buyer, is not affected
+    let result = lockset.acquire(mock_usr_id, "0e2f3a4c").await;
+    assert!(result.ok().unwrap().is_some());
+    let result = lockset.acquire(mock_usr_id + 1, mock_oid).await;
+    assert!(result.ok().unwrap().is_some());
+    let result = lockset.acquire(mock_usr_id, mock_oid).await;
+    assert!(result.ok().unwrap().is_none());
+    let result = lockset.release(mock_usr_id, mock_oid, token).await;
+    assert!(result.is_ok());
+    let result = lockset.acquire(mock_usr_id, mock_oid).await;
+    assert!(result.ok().unwrap().is_some());
+}
+
+#[actix_web::test]
+async fn inmem_lock_expired() {
+    let lockset = ut_setup_inmem_ordersync_lock(1);
+    let (mock_usr_id, mock_oid) = (1235, "0e2f3a4b");
+    let result = lockset.acquire(mock_usr_id, mock_oid).await;
+    let token_expired = result.ok().unwrap().unwrap();
+    let result = lockset.acquire(mock_usr_id, mock_oid).await;
+    assert!(result.ok().unwrap().is_none());
+    // the lock holder is assumed to be gone without releasing the lock
+    rt::time::sleep(Duration::from_millis(1100)).await;
+    let result = lockset.acquire(mock_usr_id, mock_oid).await;
+    let token = result.ok().unwrap().unwrap();
+    // the former holder cannot release the lock taken over by others
+    let result = lockset.release(mock_usr_id, mock_oid, token_expired).await;
+    assert!(result.is_ok());
+    let result = lockset.acquire(mock_usr_id, mock_oid).await;
+    assert!(result.ok().unwrap().is_none());
+    let result = lockset.release(mock_usr_id, mock_oid, token).await;
+    assert!(result.is_ok());
+    let result = lockset.acquire(mock_usr_id, mock_oid).await;
+    assert!(result.ok().unwrap().is_some());
+}
+
+#[actix_web::test]
+async fn dbserver_lock_acquire_release_ok() {
+    let shr_state = ut_setup_sharestate();
+    let lockset = shr_state.ordersync_lockset();
+    let (mock_usr_id, mock_oid) = (1236, "70e2f3a4b5");
+    let token = ut_concurrent_acquire(lockset.clone(), mock_usr_id, mock_oid).await;
+    let result = lockset.acquire(mock_usr_id, mock_oid).await;
+    assert!(result.ok().unwrap().is_none());
+    let result = lockset.release(mock_usr_id, mock_oid, token).await;
+    assert!(result.is_ok());
+    let result = lockset.acquire(mock_usr_id, mock_oid).await;
+    let token = result.ok().unwrap().unwrap();
+    let result = lockset.release(mock_usr_id, mock_oid, token).await;
+    assert!(result.is_ok());
+}
@@ -1,3 +1,4 @@
+mod cache;
 mod processor;
 mod repository;
 mod rpc;
@@ -1 +1,2 @@
+mod paypal;
 mod stripe;
@@ -0,0 +1,86 @@
+mod pay_in;
+mod refund;
+
+use std::boxed::Box;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use chrono::{DateTime, Duration, Utc};
+use rust_decimal::Decimal;
+
+use ecommerce_common::api::dto::CurrencyDto;
+use ecommerce_common::confidentiality::AbstractConfidentiality;
+use ecommerce_common::config::App3rdPartyCfg;
+use payment::adapter::processor::{
+    app_processor_context, AbstractPaymentProcessor, AppProcessorErrorReason, BaseClientErrorReason,
+};
+use payment::model::{
+    BuyerPayInState, Charge3partyModel, Charge3partyPaypalModel, ChargeBuyerModel,
+    OrderCurrencySnapshot, PaypalOrderStatusModel,
+};
+
+use crate::model::{ut_setup_buyer_charge, UTestChargeLineRawData};
+use crate::{ut_setup_config, ut_setup_logctx, MockConfidential, EXAMPLE_REL_PATH};
+
+const MOCK_BUYER_ID: u32 = 925;
+const MOCK_MERCHANT_ID: u32 = 1998;
+const MOCK_ORDER_ID: &str = "5O190127TN364715T";
+
+/// the mock context replays PayPal responses recorded in the given file
+fn ut_setup_processor(data_filename: &str) -> Box<dyn AbstractPaymentProcessor> {
+    let basepath = ut_setup_config("config_ok.json").basepath.service;
+    let cfgs = vec![
+        App3rdPartyCfg::test {
+            name: "Stripe".to_string(),
+            data_src: "no-mock-data.json".to_string(),
+        },
+        App3rdPartyCfg::test {
+            name: "Paypal".to_string(),
+            data_src: basepath + EXAMPLE_REL_PATH + data_filename,
+        },
+    ];
+    let cfgs = cfgs.into_iter().map(Arc::new).collect::<Vec<_>>();
+    let cfdntl: Arc<Box<dyn AbstractConfidentiality>> = Arc::new(Box::new(MockConfidential));
+    let result = app_processor_context(&Some(cfgs), cfdntl, ut_setup_logctx());
+    result.unwrap()
+}
+
+#[rustfmt::skip]
+fn ut_setup_paypal_charge(
+    time_base: DateTime<Utc>,
+    state: BuyerPayInState,
+    status: PaypalOrderStatusModel,
+    capture_id: Option<&str>,
+) -> ChargeBuyerModel {
+    let charge_ctime = time_base - Duration::minutes(30);
+    let mthd_3pty = Charge3partyPaypalModel {
+        order_id: MOCK_ORDER_ID.to_string(),
+        status,
+        capture_id: capture_id.map(String::from),
+        reference_id: "mock-charge-token".to_string(),
+        expiry: charge_ctime + Duration::hours(3),
+    };
+    let d_lines: Vec<UTestChargeLineRawData> = vec![
+        ((MOCK_MERCHANT_ID, 1001, 0), ((1519, 2), (15190, 2), 10), ((0, 0), (0, 0), 0), 0),
+        ((MOCK_MERCHANT_ID, 1002, 0), ((1528, 2), (4584, 2), 3), ((0, 0), (0, 0), 0), 0),
+    ];
+    let currency_snapshot = HashMap::from([
+        (MOCK_BUYER_ID, OrderCurrencySnapshot { label: CurrencyDto::USD, rate: Decimal::ONE }),
+        (MOCK_MERCHANT_ID, OrderCurrencySnapshot { label: CurrencyDto::INR, rate: Decimal::new(8635, 2) }),
+    ]);
+    ut_setup_buyer_charge(
+        MOCK_BUYER_ID, charge_ctime, "d1e5390dd3".to_string(), state,
+        Charge3partyModel::Paypal(mthd_3pty), d_lines, currency_snapshot,
+    )
+}
+
+fn ut_verify_parse_error(reason: AppProcessorErrorReason, expect_fragment: &str) {
+    let AppProcessorErrorReason::LowLvlNet(be) = reason else {
+        panic!("unexpected-error-reason");
+    };
+    let BaseClientErrorReason::DeserialiseFailure(raw_resp_body, http_status) = be.reason else {
+        panic!("unexpected-lowlvl-error");
+    };
+    assert_eq!(http_status, 200);
+    assert!(raw_resp_body.contains(expect_fragment));
+}
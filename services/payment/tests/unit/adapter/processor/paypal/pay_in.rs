@@ -0,0 +1,129 @@
+use chrono::Local;
+
+use payment::adapter::processor::AppProcessorErrorReason;
+use payment::api::web::dto::{PaymentMethodReqDto, PaymentMethodRespDto, PaypalOrderReqDto};
+use payment::model::{BuyerPayInState, Charge3partyModel, PaypalOrderStatusModel};
+
+use super::{
+    ut_setup_paypal_charge, ut_setup_processor, ut_verify_parse_error, MOCK_BUYER_ID, MOCK_ORDER_ID,
+};
+
+fn ut_setup_order_req() -> PaymentMethodReqDto {
+    let d = PaypalOrderReqDto {
+        return_url: "https://mystore.example.com/checkout/return".to_string(),
+        cancel_url: "https://mystore.example.com/checkout/cancel".to_string(),
+    };
+    PaymentMethodReqDto::Paypal(d)
+}
+
+#[actix_web::test]
+async fn create_order_ok() {
+    let time_now = Local::now().to_utc();
+    let proc_ctx = ut_setup_processor("processor-paypal-ok.json");
+    let state = BuyerPayInState::Initialized;
+    let charge_m = ut_setup_paypal_charge(time_now, state, PaypalOrderStatusModel::Created, None);
+    let result = proc_ctx.pay_in_start(&charge_m, ut_setup_order_req()).await;
+    assert!(result.is_ok());
+    let (payin_res, mthd_3pty) = result.unwrap();
+    assert!(!payin_res.completed);
+    assert!(matches!(
+        payin_res.state,
+        BuyerPayInState::ProcessorAccepted(_)
+    ));
+    let PaymentMethodRespDto::Paypal(d) = payin_res.method else {
+        panic!("unexpected-payment-method");
+    };
+    assert_eq!(d.id.as_str(), MOCK_ORDER_ID);
+    let expect = "https://www.sandbox.paypal.com/checkoutnow?token=5O190127TN364715T";
+    assert_eq!(d.approve_url.as_deref(), Some(expect));
+    let Charge3partyModel::Paypal(m) = mthd_3pty else {
+        panic!("unexpected-payment-method");
+    };
+    assert_eq!(m.order_id.as_str(), MOCK_ORDER_ID);
+    assert!(matches!(m.status, PaypalOrderStatusModel::Created));
+    assert!(m.capture_id.is_none());
+    assert!(m.expiry > time_now);
+} // end of fn create_order_ok
+
+#[actix_web::test]
+async fn create_order_missing_currency() {
+    let time_now = Local::now().to_utc();
+    let proc_ctx = ut_setup_processor("processor-paypal-ok.json");
+    let state = BuyerPayInState::Initialized;
+    let mut charge_m =
+        ut_setup_paypal_charge(time_now, state, PaypalOrderStatusModel::Created, None);
+    let _ = charge_m.currency_snapshot.remove(&MOCK_BUYER_ID);
+    let result = proc_ctx.pay_in_start(&charge_m, ut_setup_order_req()).await;
+    assert!(result.is_err());
+    if let Err(e) = result {
+        let cond = matches!(
+            e.reason,
+            AppProcessorErrorReason::MissingCurrency(v) if v == MOCK_BUYER_ID
+        );
+        assert!(cond);
+    }
+}
+
+#[actix_web::test]
+async fn create_order_corrupted_resp() {
+    let time_now = Local::now().to_utc();
+    let proc_ctx = ut_setup_processor("processor-paypal-corrupted.json");
+    let state = BuyerPayInState::Initialized;
+    let charge_m = ut_setup_paypal_charge(time_now, state, PaypalOrderStatusModel::Created, None);
+    let result = proc_ctx.pay_in_start(&charge_m, ut_setup_order_req()).await;
+    assert!(result.is_err());
+    if let Err(e) = result {
+        // order ID is missing in the response
+        ut_verify_parse_error(e.reason, "CREATED");
+    }
+}
+
+#[actix_web::test]
+async fn capture_order_ok() {
+    let time_now = Local::now().to_utc();
+    let proc_ctx = ut_setup_processor("processor-paypal-ok.json");
+    let state = BuyerPayInState::ProcessorAccepted(time_now);
+    let charge_m = ut_setup_paypal_charge(time_now, state, PaypalOrderStatusModel::Created, None);
+    let result = proc_ctx.pay_in_progress(&charge_m.meta).await;
+    assert!(result.is_ok());
+    let mthd_3pty = result.unwrap();
+    assert_eq!(mthd_3pty.pay_in_comfirmed(), Some(true));
+    let Charge3partyModel::Paypal(m) = mthd_3pty else {
+        panic!("unexpected-payment-method");
+    };
+    assert_eq!(m.order_id.as_str(), MOCK_ORDER_ID);
+    assert!(matches!(m.status, PaypalOrderStatusModel::Completed));
+    assert_eq!(m.capture_id.as_deref(), Some("3C679366HH908993F"));
+    assert_eq!(m.reference_id.as_str(), "mock-charge-token");
+} // end of fn capture_order_ok
+
+#[actix_web::test]
+async fn refresh_order_voided() {
+    let time_now = Local::now().to_utc();
+    let proc_ctx = ut_setup_processor("processor-paypal-rejected.json");
+    let state = BuyerPayInState::ProcessorAccepted(time_now);
+    let charge_m = ut_setup_paypal_charge(time_now, state, PaypalOrderStatusModel::Created, None);
+    let result = proc_ctx.pay_in_progress(&charge_m.meta).await;
+    assert!(result.is_ok());
+    let mthd_3pty = result.unwrap();
+    // voided order is never captured
+    assert_eq!(mthd_3pty.pay_in_comfirmed(), Some(false));
+    let Charge3partyModel::Paypal(m) = mthd_3pty else {
+        panic!("unexpected-payment-method");
+    };
+    assert!(matches!(m.status, PaypalOrderStatusModel::Voided));
+    assert!(m.capture_id.is_none());
+}
+
+#[actix_web::test]
+async fn refresh_order_corrupted_resp() {
+    let time_now = Local::now().to_utc();
+    let proc_ctx = ut_setup_processor("processor-paypal-corrupted.json");
+    let state = BuyerPayInState::ProcessorAccepted(time_now);
+    let charge_m = ut_setup_paypal_charge(time_now, state, PaypalOrderStatusModel::Created, None);
+    let result = proc_ctx.pay_in_progress(&charge_m.meta).await;
+    assert!(result.is_err());
+    if let Err(e) = result {
+        ut_verify_parse_error(e.reason, "UNKNOWN_ORDER_STATUS");
+    }
+}
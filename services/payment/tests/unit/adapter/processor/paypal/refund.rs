@@ -0,0 +1,75 @@
+use chrono::{DateTime, Local, Utc};
+
+use payment::adapter::processor::AppProcessorErrorReason;
+use payment::model::{BuyerPayInState, PaypalOrderStatusModel, RefundReqResolutionModel};
+
+use super::{ut_setup_paypal_charge, ut_setup_processor, ut_verify_parse_error, MOCK_MERCHANT_ID};
+use crate::model::refund::ut_setup_refund_cmplt_dto;
+
+fn ut_setup_refund_resolve(
+    time_now: DateTime<Utc>,
+    capture_id: Option<&str>,
+) -> RefundReqResolutionModel {
+    let state = BuyerPayInState::OrderAppSynced(time_now);
+    let status = PaypalOrderStatusModel::Completed;
+    let charge_m = ut_setup_paypal_charge(time_now, state, status, capture_id);
+    let cmplt_req = ut_setup_refund_cmplt_dto(
+        time_now,
+        vec![((1001, 0), 0, 303, 2, 0, 0), ((1002, 0), 1, 152, 1, 0, 0)],
+    );
+    let arg = (MOCK_MERCHANT_ID, &charge_m, &cmplt_req);
+    RefundReqResolutionModel::try_from(arg).unwrap()
+}
+
+#[actix_web::test]
+async fn refund_ok() {
+    let time_now = Local::now().to_utc();
+    let proc_ctx = ut_setup_processor("processor-paypal-ok.json");
+    let rslv_m = ut_setup_refund_resolve(time_now, Some("3C679366HH908993F"));
+    let result = proc_ctx.refund(rslv_m).await;
+    assert!(result.is_ok());
+    let rslv_m = result.unwrap();
+    // the charge detail is kept for the request ID of next refund round
+    let req_id = rslv_m.processor_request_id().unwrap();
+    assert!(req_id.starts_with("mock-charge-token"));
+}
+
+#[actix_web::test]
+async fn refund_missing_capture_id() {
+    let time_now = Local::now().to_utc();
+    let proc_ctx = ut_setup_processor("processor-paypal-ok.json");
+    let rslv_m = ut_setup_refund_resolve(time_now, None);
+    let result = proc_ctx.refund(rslv_m).await;
+    assert!(result.is_err());
+    if let Err(e) = result {
+        let cond =
+            matches!(e.reason, AppProcessorErrorReason::ThirdParty(s) if s == "missing-capture-id");
+        assert!(cond);
+    }
+}
+
+#[actix_web::test]
+async fn refund_rejected_by_processor() {
+    let time_now = Local::now().to_utc();
+    let proc_ctx = ut_setup_processor("processor-paypal-rejected.json");
+    let rslv_m = ut_setup_refund_resolve(time_now, Some("3C679366HH908993F"));
+    let result = proc_ctx.refund(rslv_m).await;
+    assert!(result.is_err());
+    if let Err(e) = result {
+        let cond = matches!(e.reason, AppProcessorErrorReason::ThirdParty(s) if s == "refund-status:CANCELLED");
+        assert!(cond);
+    }
+}
+
+#[actix_web::test]
+async fn refund_corrupted_resp() {
+    let time_now = Local::now().to_utc();
+    let proc_ctx = ut_setup_processor("processor-paypal-corrupted.json");
+    let rslv_m = ut_setup_refund_resolve(time_now, Some("3C679366HH908993F"));
+    let result = proc_ctx.refund(rslv_m).await;
+    assert!(result.is_err());
+    if let Err(e) = result {
+        // refund ID is not a string
+        ut_verify_parse_error(e.reason, "1234567");
+    }
+}
@@ -110,7 +110,7 @@ macro_rules! ut_verify_charge_stripe_model {
                 let cond = matches!(&m.payment_state, $expect_pay_state);
                 assert!(cond);
             }
-            Charge3partyModel::Unknown => {
+            _others => {
                 assert!(false);
             }
         }
@@ -132,6 +132,7 @@ fn ut_verify_stripe_session_ok(
             assert!(s.redirect_url.is_some());
             assert!(s.client_session.is_none());
         }
+        _others => panic!("unexpected-payment-method"),
     }
     ut_verify_charge_stripe_model!(
         &charge_3pty_m,
@@ -166,6 +167,7 @@ async fn ut_autofill_stripe_hosted_webform(
         .unwrap();
     let url = match &pay_in_res.method {
         PaymentMethodRespDto::Stripe(s) => s.redirect_url.clone().unwrap(),
+        _others => panic!("unexpected-payment-method"),
     };
     let result = controller.goto(url.as_str()).await;
     assert!(result.is_ok());
@@ -57,7 +57,7 @@ fn ut_setup_payout_model(
     let buyer_usr_id = charge_buyer.meta.owner();
     let arg = (
         // Decimal::ONE, Decimal::new(3222, 2),
-        Decimal::new(10344, 1), 
+        Decimal::new(10344, 1), Decimal::ZERO,
         charge_buyer.currency_snapshot.get(&buyer_usr_id).unwrap().clone(),
         charge_buyer.currency_snapshot.get(&mock_merchant_id).unwrap().clone(),
     );
@@ -65,7 +65,7 @@ fn ut_setup_payout_model(
     let mock_order_id = "ouwa-a-A-ha".to_string();
     let arg = (
         mock_merchant_id, Local::now().to_utc(), buyer_usr_id,  *charge_buyer.meta.create_time(),
-        mock_order_id, mock_staff_id, mock_amount, mock_3pty,
+        mock_order_id, mock_staff_id, 0u16, mock_amount, Vec::new(), mock_3pty,
     );
     PayoutModel::from(arg)
 }
@@ -93,6 +93,7 @@ pub(super) async fn ok_exact_once(
                 let amt_serial = s.amount().unwrap();
                 assert_eq!(amt_serial, Decimal::new(3222, 2));
             }
+            _others => panic!("unexpected-payout-3party"),
         }
     }
 } // end of fn ok_exact_once
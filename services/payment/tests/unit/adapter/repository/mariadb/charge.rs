@@ -481,3 +481,73 @@ async fn fetch_all_charge_ids_ok() {
     let diff = expect_ctimes.difference(&actual_ctimes).collect::<Vec<_>>();
     assert!(diff.is_empty());
 } // end of fn fetch_all_charge_ids_ok
+
+#[actix_web::test]
+async fn fetch_unsynced_charges_paging_ok() {
+    let mock_owner = 128;
+    let shr_state = ut_setup_sharestate();
+    let repo = ut_setup_db_charge_repo(shr_state).await;
+    let t_base = (Local::now().to_utc() - Duration::hours(30)).trunc_subsecs(0);
+    let max_items = 200u16;
+    // more stuck charges than the page size, followed by a few confirmed ones
+    let num_stuck = max_items as i64 + 15;
+    let num_confirmed = 3i64;
+    for i in 0..(num_stuck + num_confirmed) {
+        let ctime = t_base + Duration::seconds(i);
+        let cline_set = _ut_setup_buyer_charge(mock_owner, ctime, Duration::seconds(5));
+        let result = repo.create_charge(cline_set).await;
+        assert!(result.is_ok());
+        let mut meta = ut_fetch_existing_charge_meta(repo.clone(), mock_owner, ctime).await;
+        let t_cmplt = ctime + Duration::seconds(60);
+        let mut m3pty = ut_default_charge_method_stripe(&t_cmplt);
+        if let Charge3partyModel::Stripe(s) = &mut m3pty {
+            if i < num_stuck {
+                s.payment_state = StripeCheckoutPaymentStatusModel::unpaid;
+                s.session_state = StripeSessionStatusModel::expired;
+            } else {
+                s.payment_state = StripeCheckoutPaymentStatusModel::paid;
+                s.session_state = StripeSessionStatusModel::complete;
+            }
+        }
+        meta.update_3party(m3pty);
+        meta.update_progress(&BuyerPayInState::ProcessorCompleted(t_cmplt));
+        let result = repo.update_charge_progress(meta).await;
+        assert!(result.is_ok());
+    }
+
+    let (t_start, t_end) = (t_base, t_base + Duration::minutes(10));
+    let (mut num_pages, mut cursor) = (0usize, None);
+    let mut loaded = Vec::new();
+    loop {
+        let result = repo
+            .fetch_charges_unsynced(t_start, t_end, cursor, max_items)
+            .await;
+        let metas = result.unwrap();
+        assert!(metas.len() <= max_items as usize);
+        num_pages += 1;
+        let num_fetched = metas.len();
+        cursor = metas.last().map(|m| {
+            let t_cmplt = match m.progress() {
+                BuyerPayInState::ProcessorCompleted(t) => *t,
+                _others => panic!("unexpected-charge-state"),
+            };
+            (t_cmplt, m.owner(), *m.create_time())
+        });
+        loaded.extend(metas.into_iter().filter(|m| m.owner() == mock_owner));
+        if num_fetched < max_items as usize {
+            break;
+        }
+    }
+    assert!(num_pages >= 2);
+    assert_eq!(loaded.len(), (num_stuck + num_confirmed) as usize);
+    let ctimes = loaded
+        .iter()
+        .map(|m| *m.create_time())
+        .collect::<HashSet<_, RandomState>>();
+    assert_eq!(ctimes.len(), loaded.len());
+    let num_loaded_confirmed = loaded
+        .iter()
+        .filter(|m| m.method_3party().pay_in_comfirmed() == Some(true))
+        .count();
+    assert_eq!(num_loaded_confirmed, num_confirmed as usize);
+} // end of fn fetch_unsynced_charges_paging_ok
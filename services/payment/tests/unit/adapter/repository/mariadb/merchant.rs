@@ -1,6 +1,6 @@
 use chrono::{Duration, Local};
 
-use ecommerce_common::api::dto::CountryCode;
+use ecommerce_common::api::dto::{CountryCode, CurrencyDto};
 use payment::model::{
     Merchant3partyModel, Merchant3partyStripeModel, MerchantProfileModel, StripeAccountCapableState,
 };
@@ -155,3 +155,19 @@ async fn create_fetch_profile_ok() {
     assert!(rd_prof_m.valid_supervisor(mock_staff_usr_id));
     assert!(rd_prof_m.valid_staff(mock_staff_usr_id));
 } // end of fn create_fetch_profile_ok
+
+#[actix_web::test]
+async fn fetch_platform_fee_ok() {
+    let shr_state = ut_setup_sharestate();
+    let repo = ut_setup_db_merchant_repo(shr_state).await;
+    let mock_store_id = 2346u32;
+    let result = repo.fetch_platform_fee(mock_store_id).await;
+    assert!(result.is_ok());
+    let fee_sched = result.unwrap();
+    assert_eq!(fee_sched.merchant_id(), mock_store_id);
+    // rules for other merchants should never be loaded
+    let t_now = Local::now().to_utc();
+    if let Some(rule) = fee_sched.find(&CurrencyDto::TWD, t_now) {
+        assert!(rule.merchant_id().map_or(true, |v| v == mock_store_id));
+    }
+}
@@ -10,7 +10,7 @@ use payment::adapter::repository::{AbstractChargeRepo, AppRepoErrorDetail};
 use payment::hard_limit::CURRENCY_RATE_PRECISION;
 use payment::model::{
     OrderCurrencySnapshot, Payout3partyModel, Payout3partyStripeModel, PayoutAmountModel,
-    PayoutModel,
+    PayoutLineModel, PayoutModel,
 };
 
 use super::super::{ut_setup_order_bill, ut_setup_orderline_set};
@@ -31,11 +31,12 @@ fn ut_setup_payout_model_stripe(
     buyer_id: u32,
     charged_ctime: DateTime<Utc>,
     merchant_id: u32,
+    seq: u16,
 ) -> PayoutModel {
     let p3pty_m = {
         let tx_grp = "mock_charge_id_serial".to_string();
         let acct_id = "acct_no-place-is-perfect".to_string();
-        let transfer_id = Some("tr_hells-you-should-avoid".to_string());
+        let transfer_id = Some(format!("tr_hells-you-should-avoid-{seq}"));
         let amount = Some(Decimal::new(1037, 2));
         let args = (tx_grp, acct_id, transfer_id, amount);
         let s = Payout3partyStripeModel::from(args);
@@ -43,14 +44,17 @@ fn ut_setup_payout_model_stripe(
     };
     let amt_m = {
         let (amt_buyer, [currency_buyer, currency_seller]) = ut_setup_currency_snapshot(); 
-        let args = (amt_buyer, currency_buyer, currency_seller);
+        let args = (amt_buyer, Decimal::new(215, 1), currency_seller, currency_buyer);
         PayoutAmountModel::try_from(args).unwrap()
     };
+    let lines = vec![
+        PayoutLineModel::from((89u64, 0u16, 1u32, Decimal::new(9111, 1))),
+    ];
     let mock_storestaff_id = 904u32;
     let mock_captured_time = charged_ctime + Duration::minutes(49);
     let args = (
         merchant_id, mock_captured_time, buyer_id, charged_ctime,
-        order_id, mock_storestaff_id, amt_m, p3pty_m,
+        order_id, mock_storestaff_id, seq, amt_m, lines, p3pty_m,
     );
     PayoutModel::from(args)
 } // end of fn ut_setup_payout_model_stripe
@@ -100,23 +104,42 @@ async fn create_fetch_ok() {
         mock_merchant_id,
     )
     .await;
+    let payout_m = ut_setup_payout_model_stripe(
+        mock_order_id.clone(),
+        mock_buyer_id,
+        mock_charged_ctime,
+        mock_merchant_id,
+        0,
+    );
+    let result = repo.create_payout(payout_m).await;
+    assert!(result.is_ok());
+    // the merchant captures the same charge again
     let payout_m = ut_setup_payout_model_stripe(
         mock_order_id,
         mock_buyer_id,
         mock_charged_ctime,
         mock_merchant_id,
+        1,
     );
     let result = repo.create_payout(payout_m).await;
     assert!(result.is_ok());
 
     let result = repo
-        .fetch_payout(mock_merchant_id, mock_buyer_id, mock_charged_ctime)
+        .fetch_payouts(mock_merchant_id, mock_buyer_id, mock_charged_ctime)
         .await;
     assert!(result.is_ok());
-    let maybe_payout_m = result.unwrap();
-    assert!(maybe_payout_m.is_some());
-    let read_payout_m = maybe_payout_m.unwrap();
+    let payouts = result.unwrap();
+    assert_eq!(payouts.len(), 2);
+    let seqs = payouts.iter().map(|p| p.seq()).collect::<Vec<_>>();
+    assert_eq!(seqs, vec![0, 1]);
+    let read_payout_m = payouts.last().unwrap();
     assert_eq!(read_payout_m.merchant_id(), mock_merchant_id);
+    assert_eq!(read_payout_m.platform_fee(), Decimal::new(215, 1));
+    assert_eq!(read_payout_m.lines().len(), 1);
+    let line = &read_payout_m.lines()[0];
+    assert_eq!(line.id(), (89u64, 0u16));
+    assert_eq!(line.qty(), 1);
+    assert_eq!(line.amount_buyer(), Decimal::new(9111, 1));
     let read_currency_base = read_payout_m.amount_base();
     assert_eq!(read_currency_base, Decimal::new(1037, 2));
     let (read_amount_merc, read_target_rate, read_currency_merc) = read_payout_m.amount_merchant();
@@ -130,7 +153,15 @@ async fn create_fetch_ok() {
     match read_payout_m.thirdparty() {
         Payout3partyModel::Stripe(s) => {
             assert_eq!(s.amount().unwrap(), Decimal::new(1037, 2));
+            assert!(s
+                .validate_transfer(
+                    "tr_hells-you-should-avoid-1",
+                    "acct_no-place-is-perfect",
+                    Decimal::new(1037, 2)
+                )
+                .is_ok());
         }
+        _others => panic!("unexpected-payout-3party"),
     }
 } // end of fn create_fetch_ok
 
@@ -142,11 +173,11 @@ async fn fetch_empty() {
     let mock_merchant_id = 6741u32;
     let mock_charged_ctime = Local::now().to_utc() - Duration::minutes(999);
     let result = repo
-        .fetch_payout(mock_merchant_id, mock_buyer_id, mock_charged_ctime)
+        .fetch_payouts(mock_merchant_id, mock_buyer_id, mock_charged_ctime)
         .await;
     assert!(result.is_ok());
-    let maybe_payout_m = result.unwrap();
-    assert!(maybe_payout_m.is_none());
+    let payouts = result.unwrap();
+    assert!(payouts.is_empty());
 }
 
 #[actix_web::test]
@@ -162,12 +193,13 @@ async fn create_fetch_missing_currency() {
         mock_buyer_id,
         mock_charged_ctime,
         mock_merchant_id,
+        0,
     );
     let result = repo.create_payout(payout_m).await;
     assert!(result.is_ok());
 
     let result = repo
-        .fetch_payout(mock_merchant_id, mock_buyer_id, mock_charged_ctime)
+        .fetch_payouts(mock_merchant_id, mock_buyer_id, mock_charged_ctime)
         .await;
     assert!(result.is_err());
     if let Err(e) = result {
@@ -10,10 +10,12 @@ use rust_decimal::Decimal;
 use ecommerce_common::model::BaseProductIdentity;
 use payment::adapter::processor::AbstractPaymentProcessor;
 use payment::adapter::repository::{AppRefundRslvReqCbReturn, AppRepoErrorDetail};
-use payment::api::web::dto::{RefundCompletionReqDto, RefundRejectReasonDto};
+use payment::api::web::dto::{
+    RefundCompletionReqDto, RefundCompletionRespDto, RefundRejectReasonDto,
+};
 use payment::model::{
     BuyerPayInState, ChargeBuyerModel, OLineRefundModel, OrderRefundModel, PayLineAmountModel,
-    RefundLineQtyRejectModel, RefundModelError, RefundReqResolutionModel,
+    RefundIdempotencyModel, RefundLineQtyRejectModel, RefundModelError, RefundReqResolutionModel,
 };
 
 use super::{ut_setup_currency_snapshot, ut_setup_db_refund_repo};
@@ -245,6 +247,38 @@ async fn save_refund_req_ok() {
     assert!(result.is_ok());
 } // end of fn save_refund_req_ok
 
+#[rustfmt::skip]
+#[actix_web::test]
+async fn fetch_refund_req_by_order_ok() {
+    let time_now = Local::now().to_utc();
+    let shr_state = ut_setup_sharestate();
+    let repo = ut_setup_db_refund_repo(shr_state).await;
+    let mock_oid = "a0dd1e07f5";
+    let mock_rfd_ms = vec![ut_setup_refund_model(
+        mock_oid, time_now, vec![
+            ((1066, 318, 0), (219, 1), (657, 1), 3, 15),
+            ((1066, 318, 0), (219, 1), (219, 1), 1, 49),
+            ((1071, 2754, 2), (1005, 2), (2010, 2), 2, 58),
+        ],
+    )];
+    let result = repo.save_request(mock_rfd_ms).await;
+    assert!(result.is_ok());
+
+    let result = repo.fetch_request_by_order(mock_oid).await;
+    assert!(result.is_ok());
+    let rfnd_m = result.unwrap();
+    assert_eq!(rfnd_m.num_lines(), 3);
+    let line = rfnd_m.get_line(1071, 2754, 2, time_now - Duration::minutes(58));
+    assert!(line.is_some());
+    let amt_req = line.unwrap().requested();
+    assert_eq!(amt_req.qty, 2);
+    assert_eq!(amt_req.total, Decimal::new(2010, 2));
+
+    let result = repo.fetch_request_by_order("0e0e0e0e").await;
+    assert!(result.is_ok());
+    assert_eq!(result.unwrap().num_lines(), 0);
+} // end of fn fetch_refund_req_by_order_ok
+
 #[rustfmt::skip]
 #[actix_web::test]
 async fn update_resolution_ok() {
@@ -288,14 +322,31 @@ async fn update_resolution_ok() {
             ((29,  0), 22, 6000, 3, 0, 0),
         ]
     );
+    let mock_idem_key = "d1e1723e110f-0001";
+    let mock_idem_m = {
+        let arg = (mock_idem_key.to_string(), mock_merchant_id, mock_oid, &mock_cmplt_req);
+        RefundIdempotencyModel::try_from(arg).unwrap()
+    };
+    let expect_digest = mock_idem_m.req_digest().to_string();
     let result = repo.resolve_request(
         mock_merchant_id,
         mock_cmplt_req,
+        Some(mock_idem_m),
         vec![_ut_setup_buyer_charge(mock_oid, time_base, mock_merchant_id)],
         shr_state.processor_context(),
         ut_rslv_rfnd_cb_modify_success,
     ).await;
-    assert!(result.is_ok());
+    let (_cb_res, resp_body) = result.unwrap();
+
+    let result = repo.fetch_idempotent_result(mock_merchant_id, mock_idem_key).await;
+    let (saved_idem_m, saved_body) = result.unwrap().unwrap();
+    assert_eq!(saved_idem_m.order_id(), mock_oid);
+    assert_eq!(saved_idem_m.req_digest(), expect_digest.as_str());
+    assert_eq!(saved_body, resp_body);
+    let saved_resp = serde_json::from_str::<RefundCompletionRespDto>(saved_body.as_str()).unwrap();
+    assert_eq!(saved_resp.lines.len(), 6);
+    let result = repo.fetch_idempotent_result(mock_merchant_id, "never-used").await;
+    assert!(result.unwrap().is_none());
 
     let mock_cmplt_req = ut_setup_refund_cmplt_dto(
         time_base, vec![
@@ -312,6 +363,7 @@ async fn update_resolution_ok() {
     let result = repo.resolve_request(
         mock_merchant_id,
         mock_cmplt_req,
+        None,
         vec![_ut_setup_buyer_charge(mock_oid, time_base, mock_merchant_id)],
         shr_state.processor_context(),
         ut_rslv_rfnd_cb_verify_modified,
@@ -333,6 +385,7 @@ async fn update_resolution_err_usr_cb() {
     let result = repo.resolve_request(
         mock_merchant_id,
         mock_cmplt_req,
+        None,
         vec![_ut_setup_buyer_charge(mock_oid, time_base, mock_merchant_id)],
         shr_state.processor_context(),
         ut_rslv_rfnd_cb_user_error,
@@ -362,6 +415,7 @@ async fn update_resolution_err_corrupted_charge() {
     let result = repo.resolve_request(
         mock_merchant_id,
         mock_cmplt_req,
+        None,
         mock_charge_ms,
         shr_state.processor_context(),
         ut_rslv_rfnd_cb_user_error,
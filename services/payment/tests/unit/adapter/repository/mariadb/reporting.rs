@@ -2,7 +2,7 @@ use std::boxed::Box;
 use std::str::FromStr;
 use std::sync::Arc;
 
-use chrono::{DateTime, Duration, Local, Utc};
+use chrono::{DateTime, Duration, Local, SubsecRound, Utc};
 use rust_decimal::Decimal;
 
 use ecommerce_common::error::AppErrorCode;
@@ -61,13 +61,41 @@ async fn merchant_fetch_charges_empty() {
     };
     let repo = ut_setup_db_reporting_repo(shr_state).await;
     let result = repo
-        .fetch_charges_by_merchant(mock_store_id, mock_time_range)
+        .fetch_charges_by_merchant(mock_store_id, mock_time_range, None, 50)
         .await;
     assert!(result.is_ok());
     let charge_ms = result.unwrap();
     assert!(charge_ms.is_empty());
 }
 
+#[actix_web::test]
+async fn merchant_fetch_refunds_payouts_empty() {
+    let time_base = Local::now().to_utc();
+    let shr_state = ut_setup_sharestate();
+    let mock_store_id = 9999;
+    let mock_time_range = ReportTimeRangeDto {
+        start_after: time_base - Duration::days(10000),
+        end_before: time_base + Duration::hours(1),
+    };
+    let repo = ut_setup_db_reporting_repo(shr_state).await;
+    let result = repo
+        .fetch_refunds_by_merchant(mock_store_id, mock_time_range.clone())
+        .await;
+    assert!(result.is_ok());
+    assert!(result.unwrap().is_empty());
+    let result = repo
+        .fetch_payouts_by_merchant(mock_store_id, mock_time_range)
+        .await;
+    assert!(result.is_ok());
+    assert!(result.unwrap().is_empty());
+    let charge_ids = vec![(1234u32, time_base - Duration::hours(3))];
+    let result = repo
+        .fetch_payouts_by_charges(mock_store_id, charge_ids)
+        .await;
+    assert!(result.is_ok());
+    assert!(result.unwrap().is_empty());
+}
+
 #[actix_web::test]
 async fn merchant_fetch_charges_ok() {
     let time_base = Local::now().to_utc();
@@ -134,7 +162,7 @@ async fn merchant_fetch_charges_ok() {
         end_before: time_base - Duration::minutes(12),
     };
     let result = repo_rpt
-        .fetch_charges_by_merchant(mock_store_id, mock_time_range)
+        .fetch_charges_by_merchant(mock_store_id, mock_time_range, None, 50)
         .await;
     assert!(result.is_ok());
     let charge_ms = result.unwrap();
@@ -175,6 +203,31 @@ async fn merchant_fetch_charges_ok() {
                 .count();
         })
         .count();
+
+    // load the same charges page by page
+    let mut cursor = None;
+    let mut actual_ctimes = Vec::new();
+    for expect_num_charges in [2usize, 2, 1, 0] {
+        let mock_time_range = ReportTimeRangeDto {
+            start_after: time_base - Duration::minutes(46),
+            end_before: time_base - Duration::minutes(12),
+        };
+        let result = repo_rpt
+            .fetch_charges_by_merchant(mock_store_id, mock_time_range, cursor, 2)
+            .await;
+        let charge_ms = result.unwrap();
+        assert_eq!(charge_ms.len(), expect_num_charges);
+        cursor = charge_ms
+            .last()
+            .map(|c| (c.meta.owner(), *c.meta.create_time()));
+        let iter = charge_ms.iter().map(|c| *c.meta.create_time());
+        actual_ctimes.extend(iter);
+    }
+    let expect_ctimes = [45i64, 38, 23, 17, 13]
+        .into_iter()
+        .map(|d| (time_base - Duration::minutes(d)).trunc_subsecs(0))
+        .collect::<Vec<_>>();
+    assert_eq!(actual_ctimes, expect_ctimes);
 } // end of fn merchant_fetch_charges_ok
 
 #[actix_web::test]
@@ -206,7 +259,7 @@ async fn merchant_fetch_charges_missing_currency() {
         end_before: time_base - Duration::minutes(25),
     };
     let result = repo_rpt
-        .fetch_charges_by_merchant(mock_store_id, mock_time_range)
+        .fetch_charges_by_merchant(mock_store_id, mock_time_range, None, 50)
         .await;
     assert!(result.is_err());
     if let Err(e) = result {
@@ -6,9 +6,17 @@ mod usecase;
 
 use std::collections::HashMap;
 use std::env;
+use std::sync::Arc;
 
-use ecommerce_common::config::{AppCfgHardLimit, AppCfgInitArgs, AppConfig};
+use ecommerce_common::confidentiality::AbstractConfidentiality;
+use ecommerce_common::config::{
+    AppCacheCfg, AppCfgHardLimit, AppCfgInitArgs, AppConfig, AppLockCacheCfg,
+};
 use ecommerce_common::constant::env_vars::{CFG_FILEPATH, EXPECTED_LABELS};
+use ecommerce_common::error::AppConfidentialityError;
+use ecommerce_common::logging::AppLogContext;
+use payment::adapter::cache::{app_cache_order_sync_lock, AbstractOrderSyncLockCache};
+use payment::adapter::datastore::AppDataStoreContext;
 use payment::AppSharedState;
 
 pub(crate) const EXAMPLE_REL_PATH: &'static str = "/tests/unit/examples/";
@@ -33,3 +41,27 @@ fn ut_setup_sharestate() -> AppSharedState {
     let cfg = ut_setup_config("config_ok.json");
     AppSharedState::new(cfg).unwrap()
 }
+
+fn ut_setup_logctx() -> Arc<AppLogContext> {
+    let cfg = ut_setup_config("config_ok.json");
+    let lc = AppLogContext::new(&cfg.basepath, &cfg.api_server.logging);
+    Arc::new(lc)
+}
+
+struct MockConfidential;
+
+impl AbstractConfidentiality for MockConfidential {
+    fn try_get_payload(&self, _id: &str) -> Result<String, AppConfidentialityError> {
+        Ok("unit-test".to_string())
+    }
+}
+
+fn ut_setup_inmem_ordersync_lock(expiry_secs: u16) -> Arc<Box<dyn AbstractOrderSyncLockCache>> {
+    let cfdntl: Arc<Box<dyn AbstractConfidentiality>> = Arc::new(Box::new(MockConfidential));
+    let dstore = AppDataStoreContext::new(&[], cfdntl, ut_setup_logctx()).unwrap();
+    let cfg = AppCacheCfg {
+        order_sync_lock: AppLockCacheCfg::InMemory { expiry_secs },
+    };
+    let lockset = app_cache_order_sync_lock(Some(&cfg), Arc::new(dstore)).unwrap();
+    Arc::new(lockset)
+}
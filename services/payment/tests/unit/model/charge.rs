@@ -10,9 +10,9 @@ use payment::api::web::dto::{
     OrderErrorReason,
 };
 use payment::model::{
-    BuyerPayInState, Charge3partyModel, Charge3partyStripeModel, ChargeBuyerMetaModel,
-    ChargeBuyerModel, ChargeToken, PayLineAmountModel, StripeCheckoutPaymentStatusModel,
-    StripeSessionStatusModel,
+    BuyerPayInState, Charge3partyModel, Charge3partyPaypalModel, Charge3partyStripeModel,
+    ChargeBuyerMetaModel, ChargeBuyerModel, ChargeToken, PayLineAmountModel,
+    PaypalOrderStatusModel, StripeCheckoutPaymentStatusModel, StripeSessionStatusModel,
 };
 
 use super::order_replica::ut_setup_order_replica;
@@ -442,6 +442,7 @@ fn ut_default_charge_3pty_stripe(
         payment_intent_id: "mock-unit-test".to_string(),
         transfer_group: "mock-tx-grp-utest".to_string(),
         expiry: Local::now().to_utc() + Duration::minutes(10),
+        amount_refunded: 0,
     };
     Charge3partyModel::Stripe(s)
 }
@@ -572,6 +573,119 @@ fn buyer_3pty_pay_in_confirm() {
     .count();
 } // end of fn buyer_3pty_pay_in_confirm
 
+fn ut_default_charge_3pty_paypal(
+    status: PaypalOrderStatusModel,
+    capture_id: Option<&str>,
+    expiry: DateTime<Utc>,
+) -> Charge3partyModel {
+    let p = Charge3partyPaypalModel {
+        order_id: "mock-unit-test".to_string(),
+        status,
+        capture_id: capture_id.map(ToString::to_string),
+        reference_id: "mock-ref-utest".to_string(),
+        expiry,
+    };
+    Charge3partyModel::Paypal(p)
+}
+
+#[rustfmt::skip]
+#[test]
+fn buyer_3pty_paypal_status() {
+    let t_now = Local::now().to_utc();
+    let (t_future, t_past) = (t_now + Duration::minutes(5), t_now - Duration::minutes(5));
+    [
+        (PaypalOrderStatusModel::Created, None, t_future, None, ChargeStatusDto::PspProcessing),
+        (PaypalOrderStatusModel::Approved, None, t_future, None, ChargeStatusDto::PspProcessing),
+        (PaypalOrderStatusModel::Approved, None, t_past, None, ChargeStatusDto::SessionExpired),
+        (PaypalOrderStatusModel::PayerActionRequired, None, t_past, None, ChargeStatusDto::SessionExpired),
+        (PaypalOrderStatusModel::Completed, Some("cap-1234"), t_past, Some(true), ChargeStatusDto::InternalSyncing),
+        (PaypalOrderStatusModel::Completed, None, t_future, Some(false), ChargeStatusDto::InternalSyncing),
+        (PaypalOrderStatusModel::Voided, None, t_future, Some(false), ChargeStatusDto::PspRefused),
+    ]
+    .into_iter()
+    .map(|(status, capture_id, expiry, expect_confirm, expect_dto)| {
+        let mock_3pty = ut_default_charge_3pty_paypal(status, capture_id, expiry);
+        let actual = mock_3pty.pay_in_comfirmed();
+        assert_eq!(actual, expect_confirm);
+        let arg = ("b90b273c72".to_string(), 1717u32, t_now - Duration::minutes(9));
+        let mut meta = ChargeBuyerMetaModel::from(arg);
+        meta.update_progress(&BuyerPayInState::ProcessorAccepted(t_now));
+        meta.update_3party(mock_3pty);
+        let resp = ChargeRefreshRespDto::from(&meta);
+        let cond = ut_partial_eq_charge_status_dto(&resp.status, &expect_dto);
+        assert!(cond);
+    })
+    .count();
+} // end of fn buyer_3pty_paypal_status
+
+#[test]
+fn buyer_meta_sync_stripe_session() {
+    let mock_ctime = Local::now().to_utc() - Duration::minutes(2);
+    let mut mock_meta = ChargeBuyerMetaModel::from(("d1e2f3".to_string(), 8964u32, mock_ctime));
+    let value = ut_default_charge_3pty_stripe(
+        StripeSessionStatusModel::open,
+        StripeCheckoutPaymentStatusModel::unpaid,
+    );
+    mock_meta.update_3party(value);
+    let value = BuyerPayInState::ProcessorAccepted(mock_ctime);
+    mock_meta.update_progress(&value);
+    // session ID mismatch
+    let matched = mock_meta.sync_stripe_session(
+        "other-session",
+        StripeSessionStatusModel::complete,
+        StripeCheckoutPaymentStatusModel::paid,
+        mock_ctime + Duration::seconds(40),
+    );
+    assert!(!matched);
+    assert!(matches!(
+        mock_meta.progress(),
+        BuyerPayInState::ProcessorAccepted(_)
+    ));
+    let t_cmplt = mock_ctime + Duration::seconds(41);
+    let matched = mock_meta.sync_stripe_session(
+        "mock-unit-test",
+        StripeSessionStatusModel::complete,
+        StripeCheckoutPaymentStatusModel::paid,
+        t_cmplt,
+    );
+    assert!(matched);
+    assert!(matches!(
+        mock_meta.progress(),
+        BuyerPayInState::ProcessorCompleted(t) if *t == t_cmplt
+    ));
+    assert_eq!(mock_meta.method_3party().pay_in_comfirmed(), Some(true));
+} // end of fn buyer_meta_sync_stripe_session
+
+#[test]
+fn buyer_meta_sync_stripe_refund() {
+    let mock_ctime = Local::now().to_utc() - Duration::minutes(2);
+    let mut mock_meta = ChargeBuyerMetaModel::from(("d1e2f3".to_string(), 8964u32, mock_ctime));
+    let value = ut_default_charge_3pty_stripe(
+        StripeSessionStatusModel::complete,
+        StripeCheckoutPaymentStatusModel::paid,
+    );
+    mock_meta.update_3party(value);
+    assert_eq!(mock_meta.sync_stripe_refund("other-intent", 1200), None);
+    assert_eq!(
+        mock_meta.sync_stripe_refund("mock-unit-test", 1200),
+        Some(true)
+    );
+    // events delivered out of order should not reduce the refunded amount
+    assert_eq!(
+        mock_meta.sync_stripe_refund("mock-unit-test", 700),
+        Some(false)
+    );
+    assert_eq!(
+        mock_meta.sync_stripe_refund("mock-unit-test", 1200),
+        Some(false)
+    );
+    if let Charge3partyModel::Stripe(s) = mock_meta.method_3party() {
+        assert_eq!(s.amount_refunded, 1200);
+    } else {
+        panic!("unexpected 3rd-party model");
+    }
+} // end of fn buyer_meta_sync_stripe_refund
+
 #[rustfmt::skip]
 #[test]
 fn charge_token_encode_ok() {
@@ -1,6 +1,9 @@
 use chrono::{Duration, Local};
-use payment::model::{MerchantModelError, MerchantProfileModel};
+use payment::model::{
+    Merchant3partyStripeModel, MerchantModelError, MerchantProfileModel, StripeAccountCapableState,
+};
 
+use super::ut_default_merchant_3party_stripe;
 use crate::dto::ut_setup_storeprofile_dto;
 
 #[test]
@@ -96,3 +99,36 @@ fn create_skip_staff_time_expired() {
         assert!(!v.valid_staff(expect_staff_id));
     }
 }
+
+fn ut_clone_3party_stripe(m: &Merchant3partyStripeModel) -> Merchant3partyStripeModel {
+    let serial = serde_json::to_value(m).unwrap();
+    serde_json::from_value(serial).unwrap()
+}
+
+#[test]
+fn sync_3party_stripe_account() {
+    let mut saved = ut_default_merchant_3party_stripe();
+    let latest = ut_clone_3party_stripe(&saved);
+    let result = saved.sync_account(latest);
+    assert!(matches!(result, Ok(false)));
+    // account link is kept until the merchant is able to receive payout
+    let mut latest = ut_clone_3party_stripe(&saved);
+    latest.details_submitted = true;
+    latest.update_link = None;
+    let result = saved.sync_account(latest);
+    assert!(matches!(result, Ok(true)));
+    assert!(saved.details_submitted);
+    assert!(saved.update_link.is_some());
+    let mut latest = ut_clone_3party_stripe(&saved);
+    latest.payouts_enabled = true;
+    latest.capabilities.transfers = StripeAccountCapableState::active;
+    let result = saved.sync_account(latest);
+    assert!(matches!(result, Ok(true)));
+    assert!(saved.payouts_enabled);
+    assert!(saved.update_link.is_none());
+    // connected account ID mismatch
+    let mut latest = ut_clone_3party_stripe(&saved);
+    latest.id = "acct_otHer3gwtiy832".to_string();
+    let result = saved.sync_account(latest);
+    assert!(result.is_err());
+}
@@ -2,6 +2,7 @@ mod charge;
 mod merchant;
 mod order_replica;
 pub(super) mod payout;
+pub(super) mod platform_fee;
 pub(super) mod refund;
 mod reporting;
 
@@ -43,6 +44,7 @@ pub(crate) fn ut_default_charge_method_stripe(t0: &DateTime<Utc>) -> Charge3part
         payment_state: StripeCheckoutPaymentStatusModel::no_payment_required,
         session_state: StripeSessionStatusModel::complete,
         expiry: *t0 + Duration::minutes(5),
+        amount_refunded: 0,
     };
     Charge3partyModel::Stripe(sess)
 }
@@ -5,12 +5,15 @@ use rust_decimal::Decimal;
 
 use ecommerce_common::api::dto::CurrencyDto;
 use ecommerce_common::error::AppErrorCode;
+use payment::api::web::dto::CapturePayLineReqDto;
 use payment::model::{
-    BuyerPayInState, Charge3partyModel, ChargeBuyerModel, ChargeLineBuyerModel,
-    Merchant3partyModel, MerchantProfileModel, OrderCurrencySnapshot, PayoutModel,
-    PayoutModelError, StripeAccountCapableState, StripeCheckoutPaymentStatusModel,
+    BuyerPayInState, Charge3partyModel, Charge3partyPaypalModel, ChargeBuyerModel,
+    ChargeLineBuyerModel, Merchant3partyModel, Merchant3partyPaypalModel, MerchantProfileModel,
+    OrderCurrencySnapshot, Payout3partyModel, PayoutModel, PayoutModelError,
+    PaypalOrderStatusModel, StripeAccountCapableState, StripeCheckoutPaymentStatusModel,
 };
 
+use super::platform_fee::{ut_setup_fee_schedule, UTestFeeRuleRaw};
 use super::{
     ut_default_charge_method_stripe, ut_default_merchant_3party_stripe, ut_setup_buyer_charge,
 };
@@ -100,7 +103,9 @@ pub(crate) fn ut_common_create_first_payout(
         mock_merchant_prof,
         mock_merchant_3pty,
         staff_usr_id,
+        Vec::new(),
         None,
+        ut_setup_fee_schedule(mock_store_id, Vec::new()),
     );
     PayoutModel::try_from(arg)
 }
@@ -157,7 +162,8 @@ fn create_after_refund_ok() {
     let mock_merchant_3pty = ut_setup_merchant_3party_stripe();
     let arg = (
         mock_charge_m, mock_merchant_prof,  mock_merchant_3pty,
-        staff_usr_id, None,
+        staff_usr_id, Vec::new(), None,
+        ut_setup_fee_schedule(orig_store_id, Vec::new()),
     );
     let result = PayoutModel::try_from(arg);
     assert!(result.is_ok());
@@ -191,7 +197,8 @@ fn create_merchant_id_mismatch() {
     let mock_merchant_3pty = ut_setup_merchant_3party_stripe();
     let arg = (
         mock_charge_m, mock_merchant_prof, mock_merchant_3pty,
-        staff_usr_id, Some(valid_payout),
+        staff_usr_id, vec![valid_payout], None,
+        ut_setup_fee_schedule(wrong_store_id, Vec::new()),
     );
     let result = PayoutModel::try_from(arg);
     assert!(result.is_err());
@@ -221,7 +228,8 @@ fn create_charge_id_mismatch() {
     let mock_merchant_3pty = ut_setup_merchant_3party_stripe();
     let arg = (
         mock_charge_m, mock_merchant_prof, mock_merchant_3pty,
-        staff_usr_id, Some(valid_payout),
+        staff_usr_id, vec![valid_payout], None,
+        ut_setup_fee_schedule(mock_store_id, Vec::new()),
     );
     let result = PayoutModel::try_from(arg);
     assert!(result.is_err());
@@ -249,7 +257,8 @@ fn create_err_merchant_no_permit() {
     }; // assume 3rd-party Stripe hasn't enabled the payout uet
     let arg = (
         mock_charge_m, mock_merchant_prof, mock_merchant_3pty,
-        staff_usr_id, None,
+        staff_usr_id, Vec::new(), None,
+        ut_setup_fee_schedule(mock_store_id, Vec::new()),
     );
     let result = PayoutModel::try_from(arg);
     assert!(result.is_err());
@@ -273,7 +282,8 @@ fn create_missing_currency_snapshot() {
     let mock_merchant_3pty = ut_setup_merchant_3party_stripe();
     let arg = (
         mock_charge_m, mock_merchant_prof, mock_merchant_3pty,
-        staff_usr_id, None,
+        staff_usr_id, Vec::new(), None,
+        ut_setup_fee_schedule(wrong_store_id, Vec::new()),
     );
     let result = PayoutModel::try_from(arg);
     assert!(result.is_err());
@@ -302,20 +312,178 @@ fn create_err_invalid_amount() {
     let mock_merchant_3pty = ut_setup_merchant_3party_stripe();
     let arg = (
         mock_charge_m, mock_merchant_prof, mock_merchant_3pty,
-        staff_usr_id, Some(valid_payout),
+        staff_usr_id, vec![valid_payout], None,
+        ut_setup_fee_schedule(mock_store_id, Vec::new()),
     );
     let result = PayoutModel::try_from(arg);
     assert!(result.is_err());
     if let Err(e) = result {
-        if let PayoutModelError::AmountNotEnough(amt_done, amt_req) = e {
-            assert_eq!(amt_done.to_string().as_str(), "2909335.02");
-            assert_eq!(amt_req.to_string().as_str(), "2909335.02");
+        if let PayoutModelError::AmountNotEnough(amt_remain, amt_req) = e {
+            assert_eq!(amt_remain, Decimal::ZERO);
+            assert_eq!(amt_req, Decimal::ZERO);
         } else {
             assert!(false);
         }
     }
 }
 
+fn ut_setup_capture_lines(data: Vec<(u64, u16, u32)>) -> Vec<CapturePayLineReqDto> {
+    data.into_iter()
+        .map(
+            |(product_id, attr_set_seq, quantity)| CapturePayLineReqDto {
+                product_id,
+                attr_set_seq,
+                quantity,
+            },
+        )
+        .collect()
+}
+
+#[rustfmt::skip]
+fn ut_common_create_next_payout(
+    charge_ctime: DateTime<Utc>,
+    old_payouts: Vec<PayoutModel>,
+    req_lines: Option<Vec<(u64, u16, u32)>>,
+    fee_rules: Vec<UTestFeeRuleRaw>,
+) -> Result<PayoutModel, PayoutModelError> {
+    let (mock_buyer_id, mock_store_id, staff_usr_id) = (518u32, 1009u32, 2074u32);
+    let payin_state = BuyerPayInState::OrderAppSynced(charge_ctime + Duration::minutes(5));
+    let mock_charge_m = ut_setup_buyer_charge_inner(mock_buyer_id, charge_ctime, payin_state);
+    let mock_merchant_prof = ut_setup_merchant_profile(mock_store_id, staff_usr_id);
+    let mock_merchant_3pty = ut_setup_merchant_3party_stripe();
+    let arg = (
+        mock_charge_m, mock_merchant_prof, mock_merchant_3pty,
+        staff_usr_id, old_payouts, req_lines.map(ut_setup_capture_lines),
+        ut_setup_fee_schedule(mock_store_id, fee_rules),
+    );
+    PayoutModel::try_from(arg)
+}
+
+#[rustfmt::skip]
+#[test]
+fn create_partial_capture_ok() {
+    let charge_ctime = Local::now().to_utc() - Duration::minutes(96);
+    // the merchant ships part of the products in the first batch
+    let req_lines = vec![(9451u64, 0u16, 3u32), (8454, 0, 6), (8454, 0, 4)];
+    let result = ut_common_create_next_payout(charge_ctime, Vec::new(), Some(req_lines.clone()), Vec::new());
+    let payout_1st = result.unwrap();
+    assert_eq!(payout_1st.seq(), 0);
+    assert_eq!(payout_1st.amount_buyer(), Decimal::new(42700, 1));
+    let lines = payout_1st.lines().iter()
+        .map(|l| (l.id(), l.qty(), l.amount_buyer()))
+        .collect::<Vec<_>>();
+    assert_eq!(lines, vec![
+        ((9451, 0), 3, Decimal::new(5100, 1)),
+        ((8454, 0), 10, Decimal::new(37600, 1)),
+    ]);
+    // then capture the rest of the charge
+    let result = ut_common_create_next_payout(charge_ctime, vec![payout_1st], None, Vec::new());
+    let payout_2nd = result.unwrap();
+    assert_eq!(payout_2nd.seq(), 1);
+    assert_eq!(payout_2nd.amount_buyer(), Decimal::new(32360, 1));
+    let lines = payout_2nd.lines().iter()
+        .map(|l| (l.id(), l.qty(), l.amount_buyer()))
+        .collect::<Vec<_>>();
+    assert_eq!(lines, vec![
+        ((9451, 0), 5, Decimal::new(7500, 1)),
+        ((9914, 0), 11, Decimal::new(2486, 0)),
+    ]);
+    // nothing left to capture
+    let payout_1st = ut_common_create_next_payout(charge_ctime, Vec::new(), Some(req_lines), Vec::new()).unwrap();
+    let result = ut_common_create_next_payout(charge_ctime, vec![payout_1st, payout_2nd], None, Vec::new());
+    if let Err(PayoutModelError::AmountNotEnough(amt_remain, amt_req)) = result {
+        assert_eq!(amt_remain, Decimal::ZERO);
+        assert_eq!(amt_req, Decimal::ZERO);
+    } else {
+        panic!("unexpected-result");
+    }
+} // end of fn create_partial_capture_ok
+
+#[rustfmt::skip]
+#[test]
+fn create_with_platform_fee_ok() {
+    let charge_ctime = Local::now().to_utc() - Duration::minutes(96);
+    let fee_rules = vec![
+        (None, CurrencyDto::TWD, (12, 0), (25, 1), charge_ctime - Duration::days(1), None),
+        (None, CurrencyDto::INR, (99, 0), (9, 0), charge_ctime - Duration::days(1), None),
+    ];
+    let req_lines = vec![(9451u64, 0u16, 3u32)];
+    let result = ut_common_create_next_payout(
+        charge_ctime, Vec::new(), Some(req_lines), fee_rules.clone()
+    );
+    let payout_1st = result.unwrap();
+    // flat fee is charged in the first payout, the fee is not transferred to the merchant
+    assert_eq!(payout_1st.amount_buyer(), Decimal::new(5100, 1));
+    assert_eq!(payout_1st.platform_fee(), Decimal::new(2475, 2));
+    assert_eq!(payout_1st.amount_base(), Decimal::new(1523, 2));
+    let result = ut_common_create_next_payout(
+        charge_ctime, vec![payout_1st], None, fee_rules
+    );
+    let payout_2nd = result.unwrap();
+    assert_eq!(payout_2nd.amount_buyer(), Decimal::new(69960, 1));
+    assert_eq!(payout_2nd.platform_fee(), Decimal::new(17490, 2));
+    assert_eq!(payout_2nd.amount_base(), Decimal::new(21416, 2));
+} // end of fn create_with_platform_fee_ok
+
+#[test]
+fn create_err_fee_schedule_mismatch() {
+    let (mock_buyer_id, mock_store_id, staff_usr_id) = (518u32, 1009u32, 2074u32);
+    let charge_ctime = Local::now().to_utc() - Duration::minutes(96);
+    let payin_state = BuyerPayInState::OrderAppSynced(charge_ctime + Duration::minutes(5));
+    let mock_charge_m = ut_setup_buyer_charge_inner(mock_buyer_id, charge_ctime, payin_state);
+    let mock_merchant_prof = ut_setup_merchant_profile(mock_store_id, staff_usr_id);
+    let mock_merchant_3pty = ut_setup_merchant_3party_stripe();
+    let arg = (
+        mock_charge_m,
+        mock_merchant_prof,
+        mock_merchant_3pty,
+        staff_usr_id,
+        Vec::new(),
+        None,
+        ut_setup_fee_schedule(1008, Vec::new()),
+    );
+    let result = PayoutModel::try_from(arg);
+    if let Err(PayoutModelError::MerchantInconsistent(id0, id1)) = result {
+        assert_eq!(id0, mock_store_id);
+        assert_eq!(id1, 1008);
+    } else {
+        panic!("unexpected-result");
+    }
+}
+
+#[rustfmt::skip]
+#[test]
+fn create_err_capture_exceed_qty() {
+    let charge_ctime = Local::now().to_utc() - Duration::minutes(96);
+    let req_lines = vec![(9451u64, 0u16, 3u32)];
+    let result = ut_common_create_next_payout(charge_ctime, Vec::new(), Some(req_lines), Vec::new());
+    let payout_1st = result.unwrap();
+    let req_lines = vec![(9914u64, 0u16, 2u32), (9451, 0, 6)];
+    let result = ut_common_create_next_payout(charge_ctime, vec![payout_1st], Some(req_lines), Vec::new());
+    if let Err(PayoutModelError::CaptureQtyExceedLimit(product_id, attr_seq, qty_avail)) = result {
+        assert_eq!(product_id, 9451);
+        assert_eq!(attr_seq, 0);
+        assert_eq!(qty_avail, 5);
+    } else {
+        panic!("unexpected-result");
+    }
+}
+
+#[rustfmt::skip]
+#[test]
+fn create_err_capture_line_not_exist() {
+    let charge_ctime = Local::now().to_utc() - Duration::minutes(96);
+    // product 19030 belongs to another merchant in the same charge
+    let req_lines = vec![(8454u64, 0u16, 1u32), (19030, 0, 1)];
+    let result = ut_common_create_next_payout(charge_ctime, Vec::new(), Some(req_lines), Vec::new());
+    if let Err(PayoutModelError::CaptureLineNotExist(product_id, attr_seq)) = result {
+        assert_eq!(product_id, 19030);
+        assert_eq!(attr_seq, 0);
+    } else {
+        panic!("unexpected-result");
+    }
+}
+
 #[rustfmt::skip]
 #[test]
 fn create_err_3party_mismatch() {
@@ -332,7 +500,8 @@ fn create_err_3party_mismatch() {
     let mock_merchant_3pty = ut_setup_merchant_3party_stripe();
     let arg = (
         mock_charge_m, mock_merchant_prof, mock_merchant_3pty,
-        staff_usr_id, None,
+        staff_usr_id, Vec::new(), None,
+        ut_setup_fee_schedule(mock_store_id, Vec::new()),
     );
     let result = PayoutModel::try_from(arg);
     assert!(result.is_err());
@@ -371,7 +540,9 @@ fn create_err_3party_stripe_tx_grp_mismatch() {
         mock_merchant_prof,
         mock_merchant_3pty,
         staff_usr_id,
-        Some(valid_payout),
+        vec![valid_payout],
+        None,
+        ut_setup_fee_schedule(mock_store_id, Vec::new()),
     );
     let result = PayoutModel::try_from(arg);
     assert!(result.is_err());
@@ -380,3 +551,87 @@ fn create_err_3party_stripe_tx_grp_mismatch() {
         assert!(cond);
     }
 } // end of fn create_err_3party_stripe_tx_grp_mismatch
+
+fn ut_setup_paypal_charge_merchant(
+    charge_ctime: DateTime<Utc>,
+    merchant_id: Option<&str>,
+) -> (ChargeBuyerModel, Merchant3partyModel) {
+    let (mock_buyer_id, reference_id) = (518u32, "mock-ref-utest".to_string());
+    let payin_state = BuyerPayInState::OrderAppSynced(charge_ctime + Duration::minutes(5));
+    let mut mock_charge_m = ut_setup_buyer_charge_inner(mock_buyer_id, charge_ctime, payin_state);
+    let c3pty = Charge3partyPaypalModel {
+        order_id: "mock-paypal-order".to_string(),
+        status: PaypalOrderStatusModel::Completed,
+        capture_id: Some("mock-paypal-capture".to_string()),
+        reference_id,
+        expiry: charge_ctime + Duration::hours(3),
+    };
+    mock_charge_m
+        .meta
+        .update_3party(Charge3partyModel::Paypal(c3pty));
+    let m3pty = Merchant3partyPaypalModel {
+        tracking_id: "2074-utest".to_string(),
+        merchant_id: merchant_id.map(ToString::to_string),
+        email: Some("utest@paypal.example".to_string()),
+        payments_receivable: merchant_id.is_some(),
+        primary_email_confirmed: merchant_id.is_some(),
+        created: charge_ctime - Duration::days(7),
+        action_link: None,
+    };
+    (mock_charge_m, Merchant3partyModel::Paypal(m3pty))
+}
+
+#[test]
+fn create_paypal_ok() {
+    let (mock_store_id, staff_usr_id) = (1009u32, 2074u32);
+    let charge_ctime = Local::now().to_utc() - Duration::minutes(96);
+    let (mock_charge_m, mock_merchant_3pty) =
+        ut_setup_paypal_charge_merchant(charge_ctime, Some("MOCKPAYEE"));
+    let mock_merchant_prof = ut_setup_merchant_profile(mock_store_id, staff_usr_id);
+    let arg = (
+        mock_charge_m,
+        mock_merchant_prof,
+        mock_merchant_3pty,
+        staff_usr_id,
+        Vec::new(),
+        None,
+        ut_setup_fee_schedule(mock_store_id, Vec::new()),
+    );
+    let result = PayoutModel::try_from(arg);
+    assert!(result.is_ok());
+    let payout_m = result.unwrap();
+    assert_eq!(payout_m.amount_base(), Decimal::new(23566, 2));
+    match payout_m.thirdparty() {
+        Payout3partyModel::Paypal(p) => {
+            assert_eq!(p.payee_id(), "MOCKPAYEE");
+            assert_eq!(p.reference_id(), "mock-ref-utest");
+            assert!(p.batch_id().is_none());
+            assert!(p.amount().is_none());
+        }
+        _others => panic!("unexpected-payout-3party"),
+    }
+}
+
+#[test]
+fn create_err_paypal_onboard_incomplete() {
+    let (mock_store_id, staff_usr_id) = (1009u32, 2074u32);
+    let charge_ctime = Local::now().to_utc() - Duration::minutes(96);
+    let (mock_charge_m, mock_merchant_3pty) = ut_setup_paypal_charge_merchant(charge_ctime, None);
+    let mock_merchant_prof = ut_setup_merchant_profile(mock_store_id, staff_usr_id);
+    let arg = (
+        mock_charge_m,
+        mock_merchant_prof,
+        mock_merchant_3pty,
+        staff_usr_id,
+        Vec::new(),
+        None,
+        ut_setup_fee_schedule(mock_store_id, Vec::new()),
+    );
+    let result = PayoutModel::try_from(arg);
+    assert!(result.is_err());
+    if let Err(e) = result {
+        let cond =
+            matches!(e, PayoutModelError::MerchantPermissionDenied(sid) if sid == mock_store_id);
+        assert!(cond);
+    }
+}
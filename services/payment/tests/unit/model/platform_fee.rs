@@ -0,0 +1,103 @@
+use chrono::{DateTime, Duration, Local, Utc};
+use rust_decimal::Decimal;
+
+use ecommerce_common::api::dto::CurrencyDto;
+use payment::model::{PlatformFeeModelError, PlatformFeeRuleModel, PlatformFeeScheduleModel};
+
+#[rustfmt::skip]
+pub(crate) type UTestFeeRuleRaw = (
+    Option<u32>, CurrencyDto, (i64, u32), (i64, u32),
+    DateTime<Utc>, Option<DateTime<Utc>>,
+);
+
+pub(crate) fn ut_setup_fee_schedule(
+    merchant_id: u32,
+    d_rules: Vec<UTestFeeRuleRaw>,
+) -> PlatformFeeScheduleModel {
+    let rules = d_rules
+        .into_iter()
+        .map(|d| {
+            let flat = Decimal::new(d.2 .0, d.2 .1);
+            let percentage = Decimal::new(d.3 .0, d.3 .1);
+            let arg = (d.0, d.1, flat, percentage, d.4, d.5);
+            PlatformFeeRuleModel::try_from(arg).unwrap()
+        })
+        .collect::<Vec<_>>();
+    PlatformFeeScheduleModel::from((merchant_id, rules))
+}
+
+#[rustfmt::skip]
+#[test]
+fn find_rule_precedence() {
+    let (mock_store_id, another_store_id) = (1009u32, 1010u32);
+    let t_base = Local::now().to_utc();
+    let sched = ut_setup_fee_schedule(mock_store_id, vec![
+        (None, CurrencyDto::TWD, (12, 0), (0, 0), t_base - Duration::days(10), None),
+        (Some(mock_store_id), CurrencyDto::TWD, (5, 0), (15, 1),
+         t_base - Duration::days(5), Some(t_base - Duration::days(1))),
+        (None, CurrencyDto::TWD, (10, 0), (2, 0), t_base - Duration::days(2), None),
+        (Some(another_store_id), CurrencyDto::TWD, (1, 0), (1, 0), t_base - Duration::days(9), None),
+        (None, CurrencyDto::INR, (0, 0), (35, 1), t_base - Duration::days(3), None),
+    ]);
+    assert_eq!(sched.merchant_id(), mock_store_id);
+    // only the default rule is applicable
+    let rule = sched.find(&CurrencyDto::TWD, t_base - Duration::days(7)).unwrap();
+    assert!(rule.merchant_id().is_none());
+    assert_eq!(rule.flat(), Decimal::new(12, 0));
+    // merchant-specific rule takes precedence over default rules
+    let rule = sched.find(&CurrencyDto::TWD, t_base - Duration::days(3)).unwrap();
+    assert_eq!(rule.merchant_id(), Some(mock_store_id));
+    assert_eq!(rule.percentage(), Decimal::new(15, 1));
+    // merchant-specific rule expired, the latest default rule is applied
+    let rule = sched.find(&CurrencyDto::TWD, t_base).unwrap();
+    assert!(rule.merchant_id().is_none());
+    assert_eq!(rule.flat(), Decimal::new(10, 0));
+    let rule = sched.find(&CurrencyDto::INR, t_base).unwrap();
+    assert_eq!(rule.currency(), &CurrencyDto::INR);
+    assert!(sched.find(&CurrencyDto::INR, t_base - Duration::days(4)).is_none());
+    assert!(sched.find(&CurrencyDto::IDR, t_base).is_none());
+    assert!(sched.find(&CurrencyDto::TWD, t_base - Duration::days(11)).is_none());
+} // end of fn find_rule_precedence
+
+#[rustfmt::skip]
+#[test]
+fn estimate_ok() {
+    let mock_store_id = 1009u32;
+    let t_base = Local::now().to_utc();
+    let sched = ut_setup_fee_schedule(mock_store_id, vec![
+        (None, CurrencyDto::TWD, (1200, 2), (25, 1), t_base - Duration::days(10), None),
+    ]);
+    let amount = Decimal::new(100055, 2);
+    let result = sched.estimate(&CurrencyDto::TWD, t_base, amount, true);
+    assert_eq!(result.unwrap(), Decimal::new(3701, 2));
+    let result = sched.estimate(&CurrencyDto::TWD, t_base, amount, false);
+    assert_eq!(result.unwrap(), Decimal::new(2501, 2));
+    let result = sched.estimate(&CurrencyDto::INR, t_base, amount, true);
+    assert_eq!(result.unwrap(), Decimal::ZERO);
+}
+
+#[rustfmt::skip]
+#[test]
+fn create_rule_error() {
+    let t_base = Local::now().to_utc();
+    let cases = [
+        (Some(1009u32), CurrencyDto::Unknown, Decimal::ONE, Decimal::ONE, None),
+        (Some(1009), CurrencyDto::TWD, Decimal::new(-1, 0), Decimal::ONE, None),
+        (None, CurrencyDto::TWD, Decimal::new(1001, 3), Decimal::ONE, None),
+        (None, CurrencyDto::TWD, Decimal::ONE, Decimal::new(-1, 1), None),
+        (None, CurrencyDto::TWD, Decimal::ONE, Decimal::ONE_HUNDRED, None),
+        (None, CurrencyDto::TWD, Decimal::ONE, Decimal::ONE, Some(t_base - Duration::seconds(1))),
+    ];
+    let results = cases.into_iter()
+        .map(|(merchant_id, currency, flat, percentage, end_before)| {
+            let arg = (merchant_id, currency, flat, percentage, t_base, end_before);
+            PlatformFeeRuleModel::try_from(arg)
+        })
+        .collect::<Vec<_>>();
+    assert!(matches!(results[0], Err(PlatformFeeModelError::UnknownCurrency(Some(1009)))));
+    assert!(matches!(results[1], Err(PlatformFeeModelError::InvalidFlat(Some(1009), _))));
+    assert!(matches!(results[2], Err(PlatformFeeModelError::InvalidFlat(None, _))));
+    assert!(matches!(results[3], Err(PlatformFeeModelError::InvalidPercentage(None, _))));
+    assert!(matches!(results[4], Err(PlatformFeeModelError::InvalidPercentage(None, _))));
+    assert!(matches!(results[5], Err(PlatformFeeModelError::InvalidTimeRange(None, _, _))));
+}
@@ -1,5 +1,8 @@
 use std::collections::HashMap;
 
+use std::thread::sleep;
+use std::time::Duration as StdDuration;
+
 use chrono::{DateTime, Duration, Local, Utc};
 use rust_decimal::Decimal;
 
@@ -11,9 +14,10 @@ use payment::api::web::dto::{
     RefundRejectReasonDto,
 };
 use payment::model::{
-    BuyerPayInState, Charge3partyModel, ChargeBuyerModel, ChargeRefundMap, OrderCurrencySnapshot,
-    OrderRefundModel, PayLineAmountError, RefundErrorParseOline, RefundModelError,
-    RefundReqResolutionModel, StripeCheckoutPaymentStatusModel,
+    BuyerPayInState, Charge3partyModel, Charge3partyPaypalModel, ChargeBuyerModel, ChargeRefundMap,
+    OrderCurrencySnapshot, OrderRefundModel, PayLineAmountError, PaypalOrderStatusModel,
+    RefundErrorParseOline, RefundIdempotencyModel, RefundModelError, RefundReqResolutionModel,
+    StripeCheckoutPaymentStatusModel,
 };
 
 use super::{ut_default_charge_method_stripe, ut_setup_buyer_charge, UTestChargeLineRawData};
@@ -616,3 +620,85 @@ fn resolution_to_charge_map() {
         })
         .count();
 } // end of fn resolution_to_charge_map
+
+#[rustfmt::skip]
+#[test]
+fn idempotency_key_same_request() {
+    let time_base = Local::now().to_utc();
+    let (mock_key, mock_merchant_id, mock_oid) = ("a1b2c3-0017", 1009u32, "9b0e7ff3");
+    let req0 = ut_setup_refund_cmplt_dto(time_base, vec![
+        ((6402, 0), 25, 2340, 3, 1, 0),
+        ((6402, 1), 18, 0, 0, 0, 2),
+    ]);
+    // identical content with lines in different order, and trailing zeros
+    // in the approved amount
+    let mut req1 = ut_setup_refund_cmplt_dto(time_base, vec![
+        ((6402, 1), 18, 0, 0, 0, 2),
+        ((6402, 0), 25, 2340, 3, 1, 0),
+    ]);
+    req1.lines[1].approval.amount_total = "234.000".to_string();
+    let req2 = ut_setup_refund_cmplt_dto(time_base, vec![
+        ((6402, 0), 25, 1560, 2, 2, 0),
+        ((6402, 1), 18, 0, 0, 0, 2),
+    ]);
+    let [m0, m1, m2, m3] = [
+        (mock_oid, &req0), (mock_oid, &req1), (mock_oid, &req2), ("9b0e7ff4", &req0),
+    ].map(|(oid, req)| {
+        let arg = (mock_key.to_string(), mock_merchant_id, oid, req);
+        RefundIdempotencyModel::try_from(arg).unwrap()
+    });
+    assert!(m0.same_request(&m1));
+    assert!(!m0.same_request(&m2));
+    assert!(!m0.same_request(&m3));
+    assert_eq!(m0.req_digest().len(), 64);
+
+    let long_key = "k".repeat(RefundIdempotencyModel::MAX_KEY_LEN + 1);
+    ["", "has space", "semi;colon", long_key.as_str()].into_iter()
+        .map(|key| {
+            let arg = (key.to_string(), mock_merchant_id, mock_oid, &req0);
+            let result = RefundIdempotencyModel::try_from(arg);
+            assert!(matches!(result, Err(RefundModelError::InvalidIdempotencyKey(_))));
+        }).count();
+} // end of fn idempotency_key_same_request
+
+#[rustfmt::skip]
+#[test]
+fn processor_request_id_same_round() {
+    let (mock_merchant_id, mock_buyer_id) = (37u32, 9802u32);
+    // time of the refund request lines is persisted, unlike the time a
+    // refund round is sent to the processor
+    let time_base = Local::now().to_utc() - Duration::hours(3);
+    let charge_rawlines = vec![
+        ((mock_merchant_id, 1982, 0), ((1671, 1), (20052, 1), 12), ((0, 0), (0, 0), 0), 0),
+        ((mock_merchant_id, 983, 0), ((1650, 1), (29700, 1), 18), ((1650, 1), (4950, 1), 3), 4),
+    ];
+    let build_request_id = |reference_id: &str, approved: Vec<UTestRefundCmpltDtoRawData>| {
+        let mut charge_m = ut_setup_buyer_charge_inner(
+            time_base, mock_merchant_id, mock_buyer_id, charge_rawlines.clone(),
+        );
+        let paypal_m = Charge3partyPaypalModel {
+            order_id: "mock-paypal-order-id".to_string(),
+            status: PaypalOrderStatusModel::Completed,
+            capture_id: Some("mock-paypal-capture-id".to_string()),
+            reference_id: reference_id.to_string(),
+            expiry: time_base + Duration::minutes(30),
+        };
+        charge_m.meta.update_3party(Charge3partyModel::Paypal(paypal_m));
+        let cmplt_req = ut_setup_refund_cmplt_dto(time_base, approved);
+        let arg = (mock_merchant_id, &charge_m, &cmplt_req);
+        let resolve_m = RefundReqResolutionModel::try_from(arg).unwrap();
+        resolve_m.processor_request_id().unwrap()
+    };
+    let round0 = vec![((1982, 0), 41, 3342, 2, 0, 0), ((983, 0), 55, 1650, 1, 1, 0)];
+    let round1 = vec![((1982, 0), 41, 1671, 1, 0, 0), ((983, 0), 55, 1650, 1, 1, 0)];
+    let req_id0 = build_request_id("mock-ref-0012", round0.clone());
+    assert!(req_id0.starts_with("mock-ref-0012-37-"));
+    // the same round retried later is sent with the same request ID
+    sleep(StdDuration::from_millis(1100));
+    let req_id1 = build_request_id("mock-ref-0012", round0.clone());
+    assert_eq!(req_id0, req_id1);
+    let req_id2 = build_request_id("mock-ref-0012", round1);
+    assert_ne!(req_id0, req_id2);
+    let req_id3 = build_request_id("mock-ref-0013", round0);
+    assert_ne!(req_id0, req_id3);
+} // end of fn processor_request_id_same_round
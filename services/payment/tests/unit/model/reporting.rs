@@ -1,13 +1,20 @@
 use std::collections::HashMap;
+use std::str::FromStr;
 
-use chrono::{DateTime, Duration, Local, Utc};
+use chrono::{DateTime, Duration, Local, TimeZone, Utc};
 use rust_decimal::Decimal;
 
 use ecommerce_common::api::dto::CurrencyDto;
-use payment::api::web::dto::{ReportChargeRespDto, ReportTimeRangeDto};
+use ecommerce_common::model::BaseProductIdentity;
+use payment::api::web::dto::{
+    RefundRejectReasonDto, ReportChargeBreakdownDto, ReportChargeRespDto, ReportPayoutRespDto,
+    ReportRefundRespDto, ReportTimeRangeDto,
+};
 use payment::model::{
-    BuyerPayInState, ChargeBuyerModel, MerchantReportChargeModel, OrderCurrencySnapshot,
-    ReportModelError,
+    BuyerPayInState, ChargeBuyerModel, MerchantReportChargeModel, MerchantReportPayoutModel,
+    MerchantReportRefundModel, OrderCurrencySnapshot, PayLineAmountModel, Payout3partyModel,
+    Payout3partyStripeModel, PayoutAmountModel, PayoutModel, RefundLineQtyRejectModel,
+    RefundResolutionHistoryModel, ReportModelError,
 };
 
 use super::{ut_default_charge_method_stripe, ut_setup_buyer_charge};
@@ -70,7 +77,7 @@ fn merge_charges_empty() {
         start_after: time_base - Duration::hours(1),
         end_before: time_base + Duration::hours(1),
     };
-    let arg = (mock_merchant_id, mock_t_range);
+    let arg = (mock_merchant_id, mock_t_range, Default::default());
     let mut report_m = MerchantReportChargeModel::from(arg);
     let charge_ms = vec![ut_setup_buyer_charge_inner(
         "d1e5390dd2",
@@ -81,7 +88,7 @@ fn merge_charges_empty() {
         (CurrencyDto::TWD, (3184, 2)),
         vec![(463, 0, (201, 1), (1608, 1), 8)],
     )];
-    let result = report_m.try_merge(charge_ms);
+    let result = report_m.try_merge(charge_ms, Vec::new());
     assert!(result.is_ok());
     let num_added = result.unwrap();
     assert_eq!(num_added, 0);
@@ -149,9 +156,41 @@ fn merge_charges_ok() {
         ),
     ]; // end of charge_ms
 
-    let arg = (mock_merchant_id, mock_t_range);
+    // platform fee is taken from the payouts, the first charge is captured
+    // partially in 2 payouts
+    let payout_ms = vec![
+        ut_setup_payout(
+            mock_merchant_id,
+            time_base - Duration::minutes(30),
+            (3000, 2),
+            (90, 2),
+            (CurrencyDto::TWD, (3184, 2)),
+        ),
+        ut_setup_payout(
+            mock_merchant_id,
+            time_base - Duration::minutes(20),
+            (2100, 2),
+            (60, 2),
+            (CurrencyDto::TWD, (3184, 2)),
+        ),
+        ut_setup_payout(
+            mock_merchant_id,
+            time_base - Duration::minutes(10),
+            (3000, 2),
+            (80, 2),
+            (CurrencyDto::TWD, (3179, 2)),
+        ),
+        ut_setup_payout(
+            mock_merchant_id,
+            time_base - Duration::minutes(3),
+            (9000, 2),
+            (200, 2),
+            (CurrencyDto::INR, (8964, 2)),
+        ),
+    ];
+    let arg = (mock_merchant_id, mock_t_range, Default::default());
     let mut report_m = MerchantReportChargeModel::from(arg);
-    let result = report_m.try_merge(charge_ms);
+    let result = report_m.try_merge(charge_ms, payout_ms);
     assert!(result.is_ok());
     let num_added = result.unwrap();
     assert_eq!(num_added, 8);
@@ -171,8 +210,97 @@ fn merge_charges_ok() {
             assert_eq!(d.qty, expect.1);
         })
         .count();
+    assert_eq!(dto.fee_lines.len(), 2);
+    dto.fee_lines
+        .iter()
+        .map(|d| {
+            let expect = match &d.currency {
+                CurrencyDto::TWD => "73.18",
+                CurrencyDto::INR => "179.28",
+                _others => "0.0",
+            };
+            assert_eq!(d.amount.as_str(), expect);
+        })
+        .count();
+    // CSV export carries the same platform fee as JSON does
+    let csv = dto.to_csv();
+    let rows = csv.split_terminator("\r\n").collect::<Vec<_>>();
+    assert_eq!(rows.len(), 1 + dto.lines.len() + 2);
+    assert_eq!(rows[0], "line_type,date,product_id,currency,amount,qty");
+    let num_chrg_rows = rows.iter().filter(|r| r.starts_with("charge,")).count();
+    assert_eq!(num_chrg_rows, dto.lines.len());
+    let mut fee_rows = rows
+        .iter()
+        .filter(|r| r.starts_with("fee,"))
+        .copied()
+        .collect::<Vec<_>>();
+    fee_rows.sort();
+    assert_eq!(fee_rows, vec!["fee,,,INR,179.28,", "fee,,,TWD,73.18,"]);
 } // end of fn merge_charges_ok
 
+#[test]
+fn merge_charges_daily_breakdown_ok() {
+    let time_base = Utc.with_ymd_and_hms(2023, 10, 5, 8, 0, 0).unwrap();
+    let mock_merchant_id = 5566u32;
+    let mock_buyer_usr_id = 8299u32;
+    let mock_t_range = ReportTimeRangeDto {
+        start_after: time_base,
+        end_before: time_base + Duration::days(2),
+    };
+    #[rustfmt::skip]
+    let charge_ms = [
+        (Duration::hours(2), vec![(83, 0, (8312, 2), (16624, 2), 2)]),
+        (Duration::hours(4), vec![(83, 0, (8312, 2), (8312, 2), 1)]),
+        (Duration::hours(25), vec![
+            (99, 0, (515, 1), (1030, 1), 2),
+            (83, 0, (8312, 2), (8312, 2), 1),
+        ]),
+    ]
+    .into_iter()
+    .map(|(t_offset, d_lines)| {
+        ut_setup_buyer_charge_inner(
+            "d1e5390dd2",
+            mock_buyer_usr_id,
+            time_base + t_offset,
+            mock_merchant_id,
+            true,
+            (CurrencyDto::TWD, (3176, 2)),
+            d_lines,
+        )
+    })
+    .collect::<Vec<_>>();
+    let breakdown = ReportChargeBreakdownDto::DayProduct;
+    let arg = (mock_merchant_id, mock_t_range, breakdown);
+    let mut report_m = MerchantReportChargeModel::from(arg);
+    let result = report_m.try_merge(charge_ms, Vec::new());
+    assert_eq!(result.unwrap(), 4);
+    let dto = ReportChargeRespDto::from(report_m);
+    let actual = dto
+        .lines
+        .iter()
+        .map(|d| {
+            (
+                d.date.as_deref().unwrap(),
+                d.product_id,
+                d.amount.as_str(),
+                d.qty,
+            )
+        })
+        .collect::<Vec<_>>();
+    let expect = vec![
+        ("2023-10-05", 83u64, "7919.67", 3u32),
+        ("2023-10-06", 83, "2639.89", 1),
+        ("2023-10-06", 99, "3271.28", 2),
+    ];
+    assert_eq!(actual, expect);
+    assert!(dto.next_cursor.is_none());
+    let expect = "line_type,date,product_id,currency,amount,qty\r\n\
+                  charge,2023-10-05,83,TWD,7919.67,3\r\n\
+                  charge,2023-10-06,83,TWD,2639.89,1\r\n\
+                  charge,2023-10-06,99,TWD,3271.28,2\r\n";
+    assert_eq!(dto.to_csv().as_str(), expect);
+} // end of fn merge_charges_daily_breakdown_ok
+
 #[test]
 fn merge_charges_err_missing_currency() {
     let time_base = Local::now().to_utc();
@@ -195,9 +323,9 @@ fn merge_charges_err_missing_currency() {
         let _discarded = m.currency_snapshot.remove(&mock_merchant_id);
         vec![m]
     };
-    let arg = (mock_merchant_id, mock_t_range);
+    let arg = (mock_merchant_id, mock_t_range, Default::default());
     let mut report_m = MerchantReportChargeModel::from(arg);
-    let result = report_m.try_merge(charge_ms);
+    let result = report_m.try_merge(charge_ms, Vec::new());
     assert!(result.is_err());
     if let Err(mut es) = result {
         assert_eq!(es.len(), 1);
@@ -232,9 +360,9 @@ fn merge_charges_err_amount_overflow() {
         );
         vec![m]
     };
-    let arg = (mock_merchant_id, mock_t_range);
+    let arg = (mock_merchant_id, mock_t_range, Default::default());
     let mut report_m = MerchantReportChargeModel::from(arg);
-    let result = report_m.try_merge(charge_ms);
+    let result = report_m.try_merge(charge_ms, Vec::new());
     assert!(result.is_err());
     if let Err(mut es) = result {
         assert_eq!(es.len(), 1);
@@ -278,12 +406,25 @@ fn merge_charges_err_merchant_inconsistent() {
         m.currency_snapshot.insert(mock_orig_merchant_id, snapshot);
         vec![m]
     };
-    let arg = (mock_orig_merchant_id, mock_t_range);
+    let arg = (mock_orig_merchant_id, mock_t_range, Default::default());
     let mut report_m = MerchantReportChargeModel::from(arg);
-    let result = report_m.try_merge(charge_ms);
+    let payout_ms = vec![ut_setup_payout(
+        mock_another_merchant_id,
+        time_base - Duration::minutes(10),
+        (2000, 2),
+        (60, 2),
+        (CurrencyDto::TWD, (3168, 2)),
+    )];
+    let result = report_m.try_merge(charge_ms, payout_ms);
     assert!(result.is_err());
     if let Err(mut es) = result {
-        assert_eq!(es.len(), 2);
+        assert_eq!(es.len(), 3);
+        let e = es.pop().unwrap();
+        assert!(matches!(
+            e,
+            ReportModelError::MerchantNotConsistent(expect, unexpect)
+            if expect == mock_orig_merchant_id && unexpect == mock_another_merchant_id
+        ));
         let e = es.remove(0);
         if let ReportModelError::MerchantNotConsistent(expect, unexpect) = e {
             assert_eq!(expect, mock_orig_merchant_id);
@@ -293,3 +434,273 @@ fn merge_charges_err_merchant_inconsistent() {
         }
     }
 } // end of fn merge_charges_err_merchant_inconsistent
+
+fn ut_currency_snapshot(label: CurrencyDto, rate: (i64, u32)) -> OrderCurrencySnapshot {
+    let rate = Decimal::new(rate.0, rate.1);
+    OrderCurrencySnapshot { label, rate }
+}
+
+#[rustfmt::skip]
+fn ut_setup_refund_history(
+    merchant_id: u32,
+    product_id: u64,
+    time_resolved: DateTime<Utc>,
+    approved: ((i64, u32), (i64, u32), u32),
+    rejected: (u32, u32),
+    merchant_currency: (CurrencyDto, (i64, u32)),
+) -> RefundResolutionHistoryModel {
+    let pid = BaseProductIdentity { store_id: merchant_id, product_id };
+    let approved = PayLineAmountModel {
+        unit: Decimal::new(approved.0 .0, approved.0 .1),
+        total: Decimal::new(approved.1 .0, approved.1 .1),
+        qty: approved.2,
+    };
+    let rejected = {
+        let d = HashMap::from([
+            (RefundRejectReasonDto::Fraudulent, rejected.0),
+            (RefundRejectReasonDto::Damaged, rejected.1),
+        ]);
+        RefundLineQtyRejectModel::from(&d)
+    };
+    let currency_pair = [
+        ut_currency_snapshot(CurrencyDto::USD, (1, 0)),
+        ut_currency_snapshot(merchant_currency.0, merchant_currency.1),
+    ];
+    let time_req = time_resolved - Duration::hours(5);
+    let charge_id = (1234u32, time_resolved - Duration::days(3));
+    RefundResolutionHistoryModel::from((
+        pid, 0u16, time_req, time_resolved, charge_id, approved, rejected, currency_pair,
+    ))
+}
+
+#[rustfmt::skip]
+fn ut_setup_payout(
+    merchant_id: u32,
+    capture_time: DateTime<Utc>,
+    amount_buyer: (i64, u32),
+    fee_buyer: (i64, u32),
+    merchant_currency: (CurrencyDto, (i64, u32)),
+) -> PayoutModel {
+    let arg = (
+        Decimal::new(amount_buyer.0, amount_buyer.1),
+        Decimal::new(fee_buyer.0, fee_buyer.1),
+        ut_currency_snapshot(merchant_currency.0, merchant_currency.1),
+        ut_currency_snapshot(CurrencyDto::USD, (1, 0)),
+    );
+    let amount = PayoutAmountModel::try_from(arg).unwrap();
+    let p3pty = Payout3partyStripeModel::from((
+        "mock-tx-grp".to_string(), "mock-acct-id".to_string(), None, None,
+    ));
+    PayoutModel::from((
+        merchant_id, capture_time, 1234u32, capture_time - Duration::days(2),
+        "9028a149".to_string(), 2345u32, 1u16, amount, Vec::new(),
+        Payout3partyModel::Stripe(p3pty),
+    ))
+}
+
+fn ut_assert_amount(actual: &str, expect: (i64, u32)) {
+    let actual = Decimal::from_str(actual).unwrap();
+    assert_eq!(actual, Decimal::new(expect.0, expect.1));
+}
+
+#[test]
+fn merge_refunds_ok() {
+    let time_base = Local::now().to_utc();
+    let mock_merchant_id = 5566u32;
+    let mock_t_range = ReportTimeRangeDto {
+        start_after: time_base - Duration::hours(2),
+        end_before: time_base,
+    };
+    let rfnd_ms = vec![
+        ut_setup_refund_history(
+            mock_merchant_id,
+            463,
+            time_base - Duration::minutes(90),
+            ((201, 1), (402, 1), 2),
+            (1, 0),
+            (CurrencyDto::TWD, (3200, 2)),
+        ),
+        ut_setup_refund_history(
+            mock_merchant_id,
+            463,
+            time_base - Duration::minutes(30),
+            ((201, 1), (201, 1), 1),
+            (0, 2),
+            (CurrencyDto::TWD, (3200, 2)),
+        ),
+        ut_setup_refund_history(
+            mock_merchant_id,
+            83,
+            time_base - Duration::minutes(10),
+            ((0, 0), (0, 0), 0),
+            (3, 0),
+            (CurrencyDto::TWD, (3200, 2)),
+        ),
+    ];
+    let mut report_m = MerchantReportRefundModel::from((mock_merchant_id, mock_t_range));
+    let result = report_m.try_merge(rfnd_ms);
+    assert!(result.is_ok());
+    assert_eq!(result.unwrap(), 3);
+    let resp = ReportRefundRespDto::from(report_m);
+    assert_eq!(resp.merchant_id, mock_merchant_id);
+    assert_eq!(resp.lines.len(), 2);
+    let line = &resp.lines[0];
+    assert_eq!(line.product_id, 83);
+    assert_eq!(line.currency, CurrencyDto::TWD);
+    ut_assert_amount(line.amount.as_str(), (0, 0));
+    assert_eq!(line.qty_approved, 0);
+    let qty_rej = line.qty_rejected.get(&RefundRejectReasonDto::Fraudulent);
+    assert_eq!(qty_rej.copied(), Some(3));
+    let line = &resp.lines[1];
+    assert_eq!(line.product_id, 463);
+    ut_assert_amount(line.amount.as_str(), (192960, 2));
+    assert_eq!(line.qty_approved, 3);
+    let qty_rej = line.qty_rejected.get(&RefundRejectReasonDto::Fraudulent);
+    assert_eq!(qty_rej.copied(), Some(1));
+    let qty_rej = line.qty_rejected.get(&RefundRejectReasonDto::Damaged);
+    assert_eq!(qty_rej.copied(), Some(2));
+} // end of fn merge_refunds_ok
+
+#[test]
+fn merge_refunds_err_merchant_inconsistent() {
+    let time_base = Local::now().to_utc();
+    let mock_merchant_id = 5566u32;
+    let mock_t_range = ReportTimeRangeDto {
+        start_after: time_base - Duration::hours(2),
+        end_before: time_base,
+    };
+    let rfnd_ms = vec![ut_setup_refund_history(
+        mock_merchant_id + 1,
+        463,
+        time_base - Duration::minutes(90),
+        ((201, 1), (402, 1), 2),
+        (0, 0),
+        (CurrencyDto::TWD, (3200, 2)),
+    )];
+    let mut report_m = MerchantReportRefundModel::from((mock_merchant_id, mock_t_range));
+    let result = report_m.try_merge(rfnd_ms);
+    if let Err(es) = result {
+        assert_eq!(es.len(), 1);
+        if let ReportModelError::MerchantNotConsistent(expect, unexpect) = &es[0] {
+            assert_eq!(*expect, mock_merchant_id);
+            assert_eq!(*unexpect, mock_merchant_id + 1);
+        } else {
+            panic!("unexpected error type");
+        }
+    } else {
+        panic!("expect error");
+    }
+}
+
+#[test]
+fn merge_payouts_settlement_ok() {
+    let time_base = Local::now().to_utc();
+    let mock_merchant_id = 5566u32;
+    let mock_t_range = ReportTimeRangeDto {
+        start_after: time_base - Duration::hours(2),
+        end_before: time_base,
+    };
+    let twd = (CurrencyDto::TWD, (3200i64, 2u32));
+    let payout_ms = vec![
+        ut_setup_payout(
+            mock_merchant_id,
+            time_base - Duration::minutes(100),
+            (1000, 2),
+            (50, 2),
+            twd.clone(),
+        ),
+        ut_setup_payout(
+            mock_merchant_id,
+            time_base - Duration::minutes(40),
+            (525, 2),
+            (0, 0),
+            twd.clone(),
+        ),
+        ut_setup_payout(
+            mock_merchant_id,
+            time_base - Duration::minutes(20),
+            (200, 2),
+            (10, 2),
+            (CurrencyDto::INR, (8000, 2)),
+        ),
+    ];
+    let rfnd_ms = vec![
+        ut_setup_refund_history(
+            mock_merchant_id,
+            463,
+            time_base - Duration::minutes(70),
+            ((150, 2), (300, 2), 2),
+            (0, 0),
+            twd,
+        ),
+        // refund resolved without any payout in the same currency
+        ut_setup_refund_history(
+            mock_merchant_id,
+            83,
+            time_base - Duration::minutes(5),
+            ((100, 2), (100, 2), 1),
+            (0, 0),
+            (CurrencyDto::THB, (3550, 2)),
+        ),
+    ];
+    let mut report_m = MerchantReportPayoutModel::from((mock_merchant_id, mock_t_range));
+    let result = report_m.try_merge(payout_ms, rfnd_ms);
+    assert!(result.is_ok());
+    assert_eq!(result.unwrap(), (3, 2));
+    let resp = ReportPayoutRespDto::from(report_m);
+    assert_eq!(resp.lines.len(), 2);
+    let line = &resp.lines[0];
+    assert_eq!(line.currency, CurrencyDto::INR);
+    assert_eq!(line.num_payouts, 1);
+    ut_assert_amount(line.amount_captured.as_str(), (16000, 2));
+    ut_assert_amount(line.fee.as_str(), (800, 2));
+    ut_assert_amount(line.amount_transferred.as_str(), (15200, 2));
+    let line = &resp.lines[1];
+    assert_eq!(line.currency, CurrencyDto::TWD);
+    assert_eq!(line.num_payouts, 2);
+    ut_assert_amount(line.amount_captured.as_str(), (48800, 2));
+    ut_assert_amount(line.fee.as_str(), (1600, 2));
+    ut_assert_amount(line.amount_transferred.as_str(), (47200, 2));
+
+    #[rustfmt::skip]
+    let expect_settled = [
+        (CurrencyDto::INR, (15200, 2), (0, 0), (15200, 2)),
+        (CurrencyDto::THB, (0, 0), (3550, 2), (-3550, 2)),
+        (CurrencyDto::TWD, (47200, 2), (9600, 2), (37600, 2)),
+    ];
+    assert_eq!(resp.settlement.len(), expect_settled.len());
+    for (actual, expect) in resp.settlement.iter().zip(expect_settled.iter()) {
+        assert_eq!(actual.currency, expect.0);
+        ut_assert_amount(actual.transferred.as_str(), expect.1);
+        ut_assert_amount(actual.refunded.as_str(), expect.2);
+        ut_assert_amount(actual.net.as_str(), expect.3);
+    }
+} // end of fn merge_payouts_settlement_ok
+
+#[test]
+fn merge_payouts_stored_fee() {
+    let time_base = Local::now().to_utc();
+    let mock_merchant_id = 5566u32;
+    let mock_t_range = ReportTimeRangeDto {
+        start_after: time_base - Duration::hours(2),
+        end_before: time_base,
+    };
+    // captured 319.35, transferred 308.84, the difference is off by
+    // truncation, the stored fee 0.33 is converted to 10.50 instead
+    let payout_ms = vec![ut_setup_payout(
+        mock_merchant_id,
+        time_base - Duration::minutes(30),
+        (1003, 2),
+        (33, 2),
+        (CurrencyDto::TWD, (3184, 2)),
+    )];
+    let mut report_m = MerchantReportPayoutModel::from((mock_merchant_id, mock_t_range));
+    let result = report_m.try_merge(payout_ms, Vec::new());
+    assert_eq!(result.unwrap(), (1, 0));
+    let resp = ReportPayoutRespDto::from(report_m);
+    assert_eq!(resp.lines.len(), 1);
+    let line = &resp.lines[0];
+    ut_assert_amount(line.amount_captured.as_str(), (31935, 2));
+    ut_assert_amount(line.amount_transferred.as_str(), (30884, 2));
+    ut_assert_amount(line.fee.as_str(), (1050, 2));
+} // end of fn merge_payouts_stored_fee
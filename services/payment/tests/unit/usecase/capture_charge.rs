@@ -13,7 +13,7 @@ use payment::adapter::processor::{
 use payment::adapter::repository::{
     AbstractChargeRepo, AbstractMerchantRepo, AppRepoError, AppRepoErrorDetail, AppRepoErrorFnLabel,
 };
-use payment::api::web::dto::CapturePay3partyRespDto;
+use payment::api::web::dto::{CapturePay3partyRespDto, CapturePayLineReqDto, CapturePayReqDto};
 use payment::model::{
     BuyerPayInState, ChargeBuyerModel, Merchant3partyModel, MerchantProfileModel, PayoutModel,
     PayoutModelError,
@@ -57,6 +57,13 @@ fn ut_setup_repo_charge(
     )
 }
 
+fn ut_setup_capture_req(
+    store_id: u32,
+    lines: Option<Vec<CapturePayLineReqDto>>,
+) -> CapturePayReqDto {
+    CapturePayReqDto { store_id, lines }
+}
+
 fn _ut_setup_auth_claim(usr_id: u32) -> AppAuthedClaim {
     let mut claim = ut_setup_auth_claim(usr_id, 85i64);
     claim.perms.clear();
@@ -105,7 +112,7 @@ async fn done_ok() {
     };
     let auth_claim = _ut_setup_auth_claim(mock_staff_id);
     let uc = ChargeCaptureUseCase { auth_claim, processors, repo_c, repo_m };
-    let result = uc.execute(mock_charge_id, mock_store_id).await;
+    let result = uc.execute(mock_charge_id, ut_setup_capture_req(mock_store_id, None)).await;
     assert!(result.is_ok());
     if let Ok(v) = result {
         assert_eq!(v.store_id, mock_store_id);
@@ -127,7 +134,9 @@ async fn err_missing_charge() {
         repo_c,
         repo_m,
     };
-    let result = uc.execute(mock_charge_id, mock_store_id).await;
+    let result = uc
+        .execute(mock_charge_id, ut_setup_capture_req(mock_store_id, None))
+        .await;
     assert!(result.is_err());
     if let Err(e) = result {
         let cond = matches!(e, ChargeCaptureUcError::MissingCharge);
@@ -148,7 +157,7 @@ async fn err_charge_payin_ongoing() {
     let processors = Arc::new(ut_setup_processor(None));
     let auth_claim = _ut_setup_auth_claim(mock_staff_id);
     let uc = ChargeCaptureUseCase { auth_claim, processors, repo_c, repo_m };
-    let result = uc.execute(mock_charge_id, mock_store_id).await;
+    let result = uc.execute(mock_charge_id, ut_setup_capture_req(mock_store_id, None)).await;
     assert!(result.is_err());
     if let Err(ChargeCaptureUcError::PayInNotCompleted(state)) = result {
         let cond = matches!(state, BuyerPayInState::ProcessorAccepted(_));
@@ -171,7 +180,7 @@ async fn err_missing_merchant() {
     let processors = Arc::new(ut_setup_processor(None));
     let auth_claim = _ut_setup_auth_claim(mock_staff_id);
     let uc = ChargeCaptureUseCase { auth_claim, processors, repo_c, repo_m };
-    let result = uc.execute(mock_charge_id, mock_store_id).await;
+    let result = uc.execute(mock_charge_id, ut_setup_capture_req(mock_store_id, None)).await;
     assert!(result.is_err());
     if let Err(e) = result {
         let cond = matches!(e, ChargeCaptureUcError::MissingMerchant);
@@ -202,7 +211,7 @@ async fn err_3party_failure() {
     };
     let auth_claim = _ut_setup_auth_claim(mock_staff_id);
     let uc = ChargeCaptureUseCase { auth_claim, processors, repo_c, repo_m };
-    let result = uc.execute(mock_charge_id, mock_store_id).await;
+    let result = uc.execute(mock_charge_id, ut_setup_capture_req(mock_store_id, None)).await;
     assert!(result.is_err());
     if let Err(ChargeCaptureUcError::ThirdParty(pe)) = result {
         let cond = matches!(pe.fn_label, AppProcessorFnLabel::PayOut);
@@ -246,7 +255,7 @@ async fn err_repo_create_payout() {
     };
     let auth_claim = _ut_setup_auth_claim(mock_staff_id);
     let uc = ChargeCaptureUseCase { auth_claim, processors, repo_c, repo_m };
-    let result = uc.execute(mock_charge_id, mock_store_id).await;
+    let result = uc.execute(mock_charge_id, ut_setup_capture_req(mock_store_id, None)).await;
     assert!(result.is_err());
     if let Err(ChargeCaptureUcError::RepoOpFailure(re)) = result {
         let cond = matches!(re.fn_label, AppRepoErrorFnLabel::CreatePayout);
@@ -282,11 +291,11 @@ async fn err_already_captured() {
     let processors = Arc::new(ut_setup_processor(None));
     let auth_claim = _ut_setup_auth_claim(mock_staff_id);
     let uc = ChargeCaptureUseCase { auth_claim, processors, repo_c, repo_m };
-    let result = uc.execute(mock_charge_id, mock_store_id).await;
+    let result = uc.execute(mock_charge_id, ut_setup_capture_req(mock_store_id, None)).await;
     assert!(result.is_err());
-    if let Err(ChargeCaptureUcError::CorruptedModel(PayoutModelError::AmountNotEnough(p0, p1))) = result {
-        assert!(p0 > Decimal::ZERO);
-        assert_eq!(p0, p1);
+    if let Err(ChargeCaptureUcError::InvalidCapture(PayoutModelError::AmountNotEnough(p0, p1))) = result {
+        assert_eq!(p0, Decimal::ZERO);
+        assert_eq!(p1, Decimal::ZERO);
     } else {
         assert!(false);
     }
@@ -2,6 +2,8 @@ use std::boxed::Box;
 use std::collections::HashMap;
 use std::sync::Arc;
 
+use actix_web::rt;
+use async_trait::async_trait;
 use chrono::{Duration, Local};
 use tokio::sync::Mutex;
 
@@ -9,11 +11,11 @@ use ecommerce_common::api::dto::{
     BillingDto, ContactDto, CurrencyDto, CurrencySnapshotDto, OrderCurrencySnapshotDto,
     OrderLinePayDto, OrderSellerCurrencyDto, PayAmountDto, PhoneNumberDto,
 };
-use ecommerce_common::api::rpc::dto::OrderReplicaPaymentDto;
+use ecommerce_common::api::rpc::dto::{OrderPaymentUpdateErrorDto, OrderReplicaPaymentDto};
 
 use ecommerce_common::error::AppErrorCode;
 use ecommerce_common::model::BaseProductIdentity;
-use payment::adapter::cache::OrderSyncLockError;
+use payment::adapter::cache::{AbstractOrderSyncLockCache, OrderSyncLockError, OrderSyncLockToken};
 use payment::adapter::processor::{
     AbstractPaymentProcessor, AppProcessorError, AppProcessorErrorReason, AppProcessorFnLabel,
     AppProcessorPayInResult,
@@ -28,8 +30,8 @@ use payment::api::web::dto::{
     StripeCheckoutSessionRespDto, StripeCheckoutUImodeDto,
 };
 use payment::model::{
-    BuyerPayInState, Charge3partyModel, OrderCurrencySnapshot, OrderLineModel, OrderLineModelSet,
-    PayLineAmountModel,
+    BuyerPayInState, Charge3partyModel, ChargeBuyerMetaModel, OrderCurrencySnapshot,
+    OrderLineModel, OrderLineModelSet, PayLineAmountModel,
 };
 use payment::usecase::{ChargeCreateUcError, ChargeCreateUseCase};
 use rust_decimal::Decimal;
@@ -39,7 +41,8 @@ use super::{
     MockRpcPublishEvent,
 };
 use crate::auth::ut_setup_auth_claim;
-use crate::model::ut_default_charge_method_stripe;
+use crate::model::{ut_default_charge_method_stripe, ut_setup_buyer_charge_lines};
+use crate::{ut_setup_inmem_ordersync_lock, ut_setup_logctx};
 
 fn ut_saved_oline_set(mock_order_id: String, mock_usr_id: u32) -> OrderLineModelSet {
     let mock_seller_id = 379u32;
@@ -195,6 +198,10 @@ fn ut_setup_repo(
 }
 
 #[rustfmt::skip]
+fn ut_lock_token() -> OrderSyncLockToken {
+    OrderSyncLockToken(Local::now().to_utc() + Duration::seconds(30))
+}
+
 fn ut_setup_processor(
     res: Option<Result<(AppProcessorPayInResult, Charge3partyModel), AppProcessorError>>,
 ) -> Box<dyn AbstractPaymentProcessor> {
@@ -211,8 +218,8 @@ async fn ok_with_existing_order_replica() {
         Some(Ok(Some(mock_oline_set))), None, Some(Ok(())),
     );
     let mock_sync_cache = MockOrderSyncLockCache {
-        _acquire_result: Mutex::new(None),
-        _release_result: Mutex::new(None),
+        _acquire_result: Mutex::new(Some(Ok(Some(ut_lock_token())))),
+        _release_result: Mutex::new(Some(Ok(()))),
     };
     let mock_rpc_ctx = MockRpcContext::build(None);
     let mock_processor = {
@@ -225,6 +232,7 @@ async fn ok_with_existing_order_replica() {
         rpc_ctx: Arc::new(mock_rpc_ctx),
         ordersync_lockset: Arc::new(Box::new(mock_sync_cache)),
         repo: mock_repo,
+        logctx: ut_setup_logctx(),
     };
     let mock_authed_claim = ut_setup_auth_claim(mock_usr_id, 600i64);
     let mock_req = ut_charge_req_dto(mock_order_id.clone());
@@ -241,7 +249,7 @@ async fn ok_with_rpc_replica_order() {
     let mock_order_id = "ut-origin-order-id".to_string();
     let mock_repo = ut_setup_repo(Some(Ok(None)), Some(Ok(())), Some(Ok(())));
     let mock_sync_cache = MockOrderSyncLockCache {
-        _acquire_result: Mutex::new(Some(Ok(true))),
+        _acquire_result: Mutex::new(Some(Ok(Some(ut_lock_token())))),
         _release_result: Mutex::new(Some(Ok(()))),
     };
     let mock_reply = AppRpcReply {
@@ -260,6 +268,7 @@ async fn ok_with_rpc_replica_order() {
         rpc_ctx: Arc::new(mock_rpc_ctx),
         ordersync_lockset: Arc::new(Box::new(mock_sync_cache)),
         repo: mock_repo,
+        logctx: ut_setup_logctx(),
     };
     let mock_authed_claim = ut_setup_auth_claim(mock_usr_id, 600i64);
     let mock_req = ut_charge_req_dto(mock_order_id.clone());
@@ -270,6 +279,102 @@ async fn ok_with_rpc_replica_order() {
     }
 } // end of fn ok_with_rpc_replica_order
 
+fn ut_setup_usecase_rpc_replica(
+    mock_usr_id: u32,
+    mock_order_id: String,
+    ordersync_lockset: Arc<Box<dyn AbstractOrderSyncLockCache>>,
+) -> ChargeCreateUseCase {
+    let mock_repo = ut_setup_repo(Some(Ok(None)), Some(Ok(())), Some(Ok(())));
+    let mock_reply = AppRpcReply {
+        message: ut_orderpay_replica(mock_usr_id, mock_order_id),
+    };
+    let rpc_pub_evt = MockRpcPublishEvent::build(Some(Ok(mock_reply)));
+    let mock_rpc_client = MockRpcClient::build(Some(Ok(rpc_pub_evt)));
+    let mock_rpc_ctx = MockRpcContext::build(Some(Ok(mock_rpc_client)));
+    let mock_processor = {
+        let mock_payin_result = ut_processor_pay_in_result();
+        let mock_payin_mthd_m = ut_default_charge_method_stripe(&Local::now().to_utc());
+        ut_setup_processor(Some(Ok((mock_payin_result, mock_payin_mthd_m))))
+    };
+    ChargeCreateUseCase {
+        processors: Arc::new(mock_processor),
+        rpc_ctx: Arc::new(mock_rpc_ctx),
+        ordersync_lockset,
+        repo: mock_repo,
+        logctx: ut_setup_logctx(),
+    }
+}
+
+struct ProbeOrderSyncLock {
+    inner: Arc<Box<dyn AbstractOrderSyncLockCache>>,
+    // number of callers holding the lock at the same time
+    num_holders: Arc<Mutex<(usize, usize)>>,
+}
+
+#[async_trait]
+impl AbstractOrderSyncLockCache for ProbeOrderSyncLock {
+    async fn acquire(
+        &self,
+        usr_id: u32,
+        oid: &str,
+    ) -> Result<Option<OrderSyncLockToken>, OrderSyncLockError> {
+        let out = self.inner.acquire(usr_id, oid).await?;
+        if out.is_some() {
+            {
+                let mut g = self.num_holders.lock().await;
+                g.0 += 1;
+                g.1 = g.1.max(g.0);
+            }
+            // give other request chance to run while the lock is held
+            rt::time::sleep(std::time::Duration::from_millis(150)).await;
+        }
+        Ok(out)
+    }
+    async fn release(
+        &self,
+        usr_id: u32,
+        oid: &str,
+        token: OrderSyncLockToken,
+    ) -> Result<(), OrderSyncLockError> {
+        self.num_holders.lock().await.0 -= 1;
+        self.inner.release(usr_id, oid, token).await
+    }
+}
+
+#[actix_web::test]
+async fn concurrent_requests_same_order_serialized() {
+    let mock_usr_id = 1234u32;
+    let mock_order_id = "ut-origin-order-id".to_string();
+    let num_holders = Arc::new(Mutex::new((0usize, 0usize)));
+    let probe = ProbeOrderSyncLock {
+        inner: ut_setup_inmem_ordersync_lock(30),
+        num_holders: num_holders.clone(),
+    };
+    let lockset: Arc<Box<dyn AbstractOrderSyncLockCache>> = Arc::new(Box::new(probe));
+    let uc0 = ut_setup_usecase_rpc_replica(mock_usr_id, mock_order_id.clone(), lockset.clone());
+    let uc1 = ut_setup_usecase_rpc_replica(mock_usr_id, mock_order_id.clone(), lockset.clone());
+    let (r0, r1) = futures_util::join!(
+        uc0.execute(
+            ut_setup_auth_claim(mock_usr_id, 600i64),
+            ut_charge_req_dto(mock_order_id.clone())
+        ),
+        uc1.execute(
+            ut_setup_auth_claim(mock_usr_id, 600i64),
+            ut_charge_req_dto(mock_order_id.clone())
+        ),
+    );
+    // the latter request waits until the former one completes, instead of
+    // being refused
+    assert!(r0.is_ok());
+    assert!(r1.is_ok());
+    let (num_holding, max_holders) = *num_holders.lock().await;
+    assert_eq!(num_holding, 0);
+    assert_eq!(max_holders, 1);
+    // the lock has been released by the use case
+    let result = lockset.acquire(mock_usr_id, mock_order_id.as_str()).await;
+    assert!(result.ok().unwrap().is_some());
+} // end of fn concurrent_requests_same_order_serialized
+
 #[actix_web::test]
 async fn load_unpaid_order_failure() {
     let mock_usr_id = 1234u32;
@@ -281,8 +386,8 @@ async fn load_unpaid_order_failure() {
     };
     let mock_repo = ut_setup_repo(Some(Err(repo_expect_error)), None, None);
     let mock_sync_cache = MockOrderSyncLockCache {
-        _acquire_result: Mutex::new(None),
-        _release_result: Mutex::new(None),
+        _acquire_result: Mutex::new(Some(Ok(Some(ut_lock_token())))),
+        _release_result: Mutex::new(Some(Ok(()))),
     };
     let mock_rpc_ctx = MockRpcContext::build(None);
     let mock_processor = ut_setup_processor(None);
@@ -291,6 +396,7 @@ async fn load_unpaid_order_failure() {
         rpc_ctx: Arc::new(mock_rpc_ctx),
         ordersync_lockset: Arc::new(Box::new(mock_sync_cache)),
         repo: mock_repo,
+        logctx: ut_setup_logctx(),
     };
     let mock_authed_claim = ut_setup_auth_claim(mock_usr_id, 600i64);
     let mock_req = ut_charge_req_dto(mock_order_id.clone());
@@ -310,7 +416,7 @@ async fn sync_order_get_lock_failure() {
     let mock_order_id = "ut-origin-order-id".to_string();
     let mock_repo = ut_setup_repo(Some(Ok(None)), None, None);
     let mock_sync_cache = MockOrderSyncLockCache {
-        _acquire_result: Mutex::new(Some(Ok(false))),
+        _acquire_result: Mutex::new(Some(Ok(None))),
         _release_result: Mutex::new(None),
     };
     let mock_rpc_ctx = MockRpcContext::build(None);
@@ -320,6 +426,7 @@ async fn sync_order_get_lock_failure() {
         rpc_ctx: Arc::new(mock_rpc_ctx),
         ordersync_lockset: Arc::new(Box::new(mock_sync_cache)),
         repo: mock_repo,
+        logctx: ut_setup_logctx(),
     };
     let mock_authed_claim = ut_setup_auth_claim(mock_usr_id, 600i64);
     let mock_req = ut_charge_req_dto(mock_order_id.clone());
@@ -335,9 +442,9 @@ async fn sync_order_get_lock_failure() {
 async fn sync_order_release_lock_failure() {
     let mock_usr_id = 1234u32;
     let mock_order_id = "ut-origin-order-id".to_string();
-    let mock_repo = ut_setup_repo(Some(Ok(None)), None, None);
+    let mock_repo = ut_setup_repo(Some(Ok(None)), Some(Ok(())), Some(Ok(())));
     let mock_sync_cache = MockOrderSyncLockCache {
-        _acquire_result: Mutex::new(Some(Ok(true))),
+        _acquire_result: Mutex::new(Some(Ok(Some(ut_lock_token())))),
         _release_result: Mutex::new(Some(Err(OrderSyncLockError))),
     };
     let mock_reply = AppRpcReply {
@@ -346,21 +453,24 @@ async fn sync_order_release_lock_failure() {
     let rpc_pub_evt = MockRpcPublishEvent::build(Some(Ok(mock_reply)));
     let mock_rpc_client = MockRpcClient::build(Some(Ok(rpc_pub_evt)));
     let mock_rpc_ctx = MockRpcContext::build(Some(Ok(mock_rpc_client)));
-    let mock_processor = ut_setup_processor(None);
+    let mock_processor = {
+        let mock_payin_result = ut_processor_pay_in_result();
+        let mock_payin_mthd_m = ut_default_charge_method_stripe(&Local::now().to_utc());
+        ut_setup_processor(Some(Ok((mock_payin_result, mock_payin_mthd_m))))
+    };
     let uc = ChargeCreateUseCase {
         processors: Arc::new(mock_processor),
         rpc_ctx: Arc::new(mock_rpc_ctx),
         ordersync_lockset: Arc::new(Box::new(mock_sync_cache)),
         repo: mock_repo,
+        logctx: ut_setup_logctx(),
     };
     let mock_authed_claim = ut_setup_auth_claim(mock_usr_id, 600i64);
     let mock_req = ut_charge_req_dto(mock_order_id.clone());
     let result = uc.execute(mock_authed_claim, mock_req).await;
-    assert!(result.is_err());
-    if let Err(e) = result {
-        let cond = matches!(e, ChargeCreateUcError::LockCacheError);
-        assert!(cond);
-    }
+    // the charge has been created, the lock will be taken over by others
+    // once it expires
+    assert!(result.is_ok());
 } // end of fn sync_order_release_lock_failure
 
 #[actix_web::test]
@@ -369,7 +479,7 @@ async fn rpc_acquire_conn_error() {
     let mock_order_id = "ut-origin-order-id".to_string();
     let mock_repo = ut_setup_repo(Some(Ok(None)), None, None);
     let mock_sync_cache = MockOrderSyncLockCache {
-        _acquire_result: Mutex::new(Some(Ok(true))),
+        _acquire_result: Mutex::new(Some(Ok(Some(ut_lock_token())))),
         _release_result: Mutex::new(Some(Ok(()))),
     };
     let rpc_expect_error = AppRpcCtxError {
@@ -383,6 +493,7 @@ async fn rpc_acquire_conn_error() {
         rpc_ctx: Arc::new(mock_rpc_ctx),
         ordersync_lockset: Arc::new(Box::new(mock_sync_cache)),
         repo: mock_repo,
+        logctx: ut_setup_logctx(),
     };
     let mock_authed_claim = ut_setup_auth_claim(mock_usr_id, 600i64);
     let mock_req = ut_charge_req_dto(mock_order_id.clone());
@@ -404,7 +515,7 @@ async fn rpc_publish_error_replica_order() {
     let mock_order_id = "ut-origin-order-id".to_string();
     let mock_repo = ut_setup_repo(Some(Ok(None)), None, None);
     let mock_sync_cache = MockOrderSyncLockCache {
-        _acquire_result: Mutex::new(Some(Ok(true))),
+        _acquire_result: Mutex::new(Some(Ok(Some(ut_lock_token())))),
         _release_result: Mutex::new(Some(Ok(()))),
     };
     let rpc_expect_error = AppRpcCtxError {
@@ -419,6 +530,7 @@ async fn rpc_publish_error_replica_order() {
         rpc_ctx: Arc::new(mock_rpc_ctx),
         ordersync_lockset: Arc::new(Box::new(mock_sync_cache)),
         repo: mock_repo,
+        logctx: ut_setup_logctx(),
     };
     let mock_authed_claim = ut_setup_auth_claim(mock_usr_id, 600i64);
     let mock_req = ut_charge_req_dto(mock_order_id.clone());
@@ -440,7 +552,7 @@ async fn rpc_reply_error_replica_order() {
     let mock_order_id = "ut-origin-order-id".to_string();
     let mock_repo = ut_setup_repo(Some(Ok(None)), None, None);
     let mock_sync_cache = MockOrderSyncLockCache {
-        _acquire_result: Mutex::new(Some(Ok(true))),
+        _acquire_result: Mutex::new(Some(Ok(Some(ut_lock_token())))),
         _release_result: Mutex::new(Some(Ok(()))),
     };
     let rpc_expect_error = AppRpcCtxError {
@@ -456,6 +568,7 @@ async fn rpc_reply_error_replica_order() {
         rpc_ctx: Arc::new(mock_rpc_ctx),
         ordersync_lockset: Arc::new(Box::new(mock_sync_cache)),
         repo: mock_repo,
+        logctx: ut_setup_logctx(),
     };
     let mock_authed_claim = ut_setup_auth_claim(mock_usr_id, 600i64);
     let mock_req = ut_charge_req_dto(mock_order_id.clone());
@@ -482,7 +595,7 @@ async fn save_replica_order_failure() {
     };
     let mock_repo = ut_setup_repo(Some(Ok(None)), Some(Err(repo_expect_error)), None);
     let mock_sync_cache = MockOrderSyncLockCache {
-        _acquire_result: Mutex::new(Some(Ok(true))),
+        _acquire_result: Mutex::new(Some(Ok(Some(ut_lock_token())))),
         _release_result: Mutex::new(Some(Ok(()))),
     };
     let mock_reply = AppRpcReply {
@@ -497,6 +610,7 @@ async fn save_replica_order_failure() {
         rpc_ctx: Arc::new(mock_rpc_ctx),
         ordersync_lockset: Arc::new(Box::new(mock_sync_cache)),
         repo: mock_repo,
+        logctx: ut_setup_logctx(),
     };
     let mock_authed_claim = ut_setup_auth_claim(mock_usr_id, 600i64);
     let mock_req = ut_charge_req_dto(mock_order_id.clone());
@@ -516,7 +630,7 @@ async fn processor_start_payin_failure() {
     let mock_order_id = "ut-origin-order-id".to_string();
     let mock_repo = ut_setup_repo(Some(Ok(None)), Some(Ok(())), Some(Ok(())));
     let mock_sync_cache = MockOrderSyncLockCache {
-        _acquire_result: Mutex::new(Some(Ok(true))),
+        _acquire_result: Mutex::new(Some(Ok(Some(ut_lock_token())))),
         _release_result: Mutex::new(Some(Ok(()))),
     };
     let mock_reply = AppRpcReply {
@@ -537,6 +651,7 @@ async fn processor_start_payin_failure() {
         rpc_ctx: Arc::new(mock_rpc_ctx),
         ordersync_lockset: Arc::new(Box::new(mock_sync_cache)),
         repo: mock_repo,
+        logctx: ut_setup_logctx(),
     };
     let mock_authed_claim = ut_setup_auth_claim(mock_usr_id, 600i64);
     let mock_req = ut_charge_req_dto(mock_order_id.clone());
@@ -561,7 +676,7 @@ async fn save_new_chargeline_failure() {
     };
     let mock_repo = ut_setup_repo(Some(Ok(None)), Some(Ok(())), Some(Err(repo_expect_error)));
     let mock_sync_cache = MockOrderSyncLockCache {
-        _acquire_result: Mutex::new(Some(Ok(true))),
+        _acquire_result: Mutex::new(Some(Ok(Some(ut_lock_token())))),
         _release_result: Mutex::new(Some(Ok(()))),
     };
     let mock_reply = AppRpcReply {
@@ -580,6 +695,7 @@ async fn save_new_chargeline_failure() {
         rpc_ctx: Arc::new(mock_rpc_ctx),
         ordersync_lockset: Arc::new(Box::new(mock_sync_cache)),
         repo: mock_repo,
+        logctx: ut_setup_logctx(),
     };
     let mock_authed_claim = ut_setup_auth_claim(mock_usr_id, 600i64);
     let mock_req = ut_charge_req_dto(mock_order_id.clone());
@@ -592,3 +708,83 @@ async fn save_new_chargeline_failure() {
         assert!(false);
     }
 } // end of fn save_new_chargeline_failure
+
+#[rustfmt::skip]
+fn ut_setup_usecase_payin_completed(
+    mock_usr_id: u32,
+    mock_order_id: String,
+    rpc_reply: Result<AppRpcReply, AppRpcCtxError>,
+) -> ChargeCreateUseCase {
+    let t_now = Local::now().to_utc();
+    let mock_oline_set = ut_saved_oline_set(mock_order_id.clone(), mock_usr_id);
+    let mock_meta = {
+        let mut m = ChargeBuyerMetaModel::from((mock_order_id.clone(), mock_usr_id, t_now));
+        m.update_3party(ut_default_charge_method_stripe(&t_now));
+        m.update_progress(&BuyerPayInState::ProcessorCompleted(t_now));
+        m
+    };
+    let d_lines = vec![((8298, 471, 0), ((9028, 2), (36112, 2), 4), ((0,0), (0,0), 0), 0)];
+    let mock_repo = MockChargeRepo::build(
+        Some(Ok(Some(mock_oline_set))), None, Some(Ok(())),
+        Some(Ok(Some(mock_meta))), Some(Ok(ut_setup_buyer_charge_lines(d_lines))), Some(Ok(())),
+        None, None, None,
+        None, None,
+    );
+    let mock_sync_cache = MockOrderSyncLockCache {
+        _acquire_result: Mutex::new(Some(Ok(Some(ut_lock_token())))),
+        _release_result: Mutex::new(Some(Ok(()))),
+    };
+    let rpc_pub_evt = MockRpcPublishEvent::build(Some(rpc_reply));
+    let mock_rpc_client = MockRpcClient::build(Some(Ok(rpc_pub_evt)));
+    let mock_rpc_ctx = MockRpcContext::build(Some(Ok(mock_rpc_client)));
+    let mock_processor = {
+        let mut mock_payin_result = ut_processor_pay_in_result();
+        mock_payin_result.state = BuyerPayInState::ProcessorCompleted(t_now);
+        mock_payin_result.completed = true;
+        let mock_payin_mthd_m = ut_default_charge_method_stripe(&t_now);
+        ut_setup_processor(Some(Ok((mock_payin_result, mock_payin_mthd_m))))
+    };
+    ChargeCreateUseCase {
+        processors: Arc::new(mock_processor),
+        rpc_ctx: Arc::new(mock_rpc_ctx),
+        ordersync_lockset: Arc::new(Box::new(mock_sync_cache)),
+        repo: mock_repo,
+        logctx: ut_setup_logctx(),
+    }
+} // end of fn ut_setup_usecase_payin_completed
+
+#[actix_web::test]
+async fn ok_payin_completed_order_app_synced() {
+    let mock_usr_id = 1234u32;
+    let mock_order_id = "ut-origin-order-id".to_string();
+    let reply = OrderPaymentUpdateErrorDto {
+        oid: mock_order_id.clone(),
+        charge_time: None,
+        lines: Vec::new(),
+    };
+    let mock_reply = AppRpcReply {
+        message: serde_json::to_vec(&reply).unwrap(),
+    };
+    let uc = ut_setup_usecase_payin_completed(mock_usr_id, mock_order_id.clone(), Ok(mock_reply));
+    let mock_authed_claim = ut_setup_auth_claim(mock_usr_id, 600i64);
+    let mock_req = ut_charge_req_dto(mock_order_id);
+    let result = uc.execute(mock_authed_claim, mock_req).await;
+    assert!(result.is_ok());
+} // end of fn ok_payin_completed_order_app_synced
+
+#[actix_web::test]
+async fn payin_completed_order_app_sync_failure() {
+    let mock_usr_id = 1234u32;
+    let mock_order_id = "ut-origin-order-id".to_string();
+    let rpc_expect_error = AppRpcCtxError {
+        fn_label: AppRpcErrorFnLabel::ClientRecvResp,
+        reason: AppRpcErrorReason::ReplyFailure("unit-test".to_string()),
+    };
+    let uc =
+        ut_setup_usecase_payin_completed(mock_usr_id, mock_order_id.clone(), Err(rpc_expect_error));
+    let mock_authed_claim = ut_setup_auth_claim(mock_usr_id, 600i64);
+    let mock_req = ut_charge_req_dto(mock_order_id);
+    let result = uc.execute(mock_authed_claim, mock_req).await;
+    // the charge has been saved, it will be synced later by background job
+    assert!(result.is_ok());
+} // end of fn payin_completed_order_app_sync_failure
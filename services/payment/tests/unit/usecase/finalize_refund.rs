@@ -4,19 +4,23 @@ use std::sync::Arc;
 
 use chrono::{DateTime, Duration, Local, Utc};
 use ecommerce_common::error::AppErrorCode;
-use payment::api::web::dto::{RefundCompletionOlineRespDto, RefundRejectReasonDto};
+use payment::api::web::dto::{
+    RefundCompletionOlineRespDto, RefundCompletionRespDto, RefundLineApprovalDto,
+    RefundRejectReasonDto,
+};
 use rust_decimal::Decimal;
 
 use ecommerce_common::api::dto::{CurrencyDto, PayAmountDto};
 use ecommerce_common::api::rpc::dto::OrderLineReplicaRefundDto;
 use payment::adapter::processor::{AbstractPaymentProcessor, AppProcessorErrorReason};
 use payment::adapter::repository::{
-    AbstractChargeRepo, AbstractMerchantRepo, AbstractRefundRepo, AppRepoError, AppRepoErrorDetail,
-    AppRepoErrorFnLabel,
+    AbstractChargeRepo, AbstractMerchantRepo, AbstractRefundRepo, AppRefundIdempotentReturn,
+    AppRepoError, AppRepoErrorDetail, AppRepoErrorFnLabel,
 };
 use payment::model::{
     BuyerPayInState, Charge3partyModel, ChargeBuyerModel, MerchantProfileModel,
-    OrderCurrencySnapshot, OrderRefundModel, RefundModelError, StripeCheckoutPaymentStatusModel,
+    OrderCurrencySnapshot, OrderRefundModel, RefundIdempotencyModel, RefundModelError,
+    StripeCheckoutPaymentStatusModel,
 };
 use payment::usecase::{FinalizeRefundUcError, FinalizeRefundUseCase};
 use payment::{app_meta, AppAuthClaimPermission, AppAuthPermissionCode, AppAuthedClaim};
@@ -24,7 +28,7 @@ use payment::{app_meta, AppAuthClaimPermission, AppAuthPermissionCode, AppAuthed
 use super::{MockChargeRepo, MockMerchantRepo, MockPaymentProcessor, MockRefundRepo};
 use crate::auth::ut_setup_auth_claim;
 use crate::dto::ut_setup_storeprofile_dto;
-use crate::model::refund::ut_setup_refund_cmplt_dto;
+use crate::model::refund::{ut_setup_refund_cmplt_dto, UTestRefundCmpltDtoRawData};
 use crate::model::{
     ut_default_charge_method_stripe, ut_setup_buyer_charge, UTestChargeLineRawData,
 };
@@ -55,8 +59,11 @@ fn ut_setup_repo_merchant(
     MockMerchantRepo::build(None, None, saved_prof, None)
 }
 
-fn ut_setup_repo_refund(saved_req: Option<OrderRefundModel>) -> Box<dyn AbstractRefundRepo> {
-    MockRefundRepo::build(saved_req)
+fn ut_setup_repo_refund(
+    saved_req: Option<OrderRefundModel>,
+    saved_idempotent: AppRefundIdempotentReturn,
+) -> Box<dyn AbstractRefundRepo> {
+    MockRefundRepo::build(saved_req, saved_idempotent)
 }
 
 fn ut_setup_processor(trigs: Option<Vec<bool>>) -> Arc<Box<dyn AbstractPaymentProcessor>> {
@@ -208,12 +215,12 @@ async fn cmplt_req_done_all() {
     );
     let repo_ch = ut_setup_repo_charge(Some(mock_charge_ms), Some(Ok(())));
     let repo_mc = ut_setup_repo_merchant(Some(mock_merchant_profile));
-    let repo_rfd = ut_setup_repo_refund(Some(mock_rfnd_req_m));
+    let repo_rfd = ut_setup_repo_refund(Some(mock_rfnd_req_m), None);
     let processors = ut_setup_processor(Some(vec![false, false]));
     let uc = FinalizeRefundUseCase { repo_ch, repo_mc, repo_rfd, processors };
     let mock_authed_claim = _ut_setup_auth_claim(mock_staff_usr_id);
     let result = uc.execute(
-        mock_oid.to_string(), mock_merchant_id, mock_authed_claim, mock_cmplt_req
+        mock_oid.to_string(), mock_merchant_id, mock_authed_claim, mock_cmplt_req, None
     ).await;
     assert!(result.is_ok());
     let data_selector = |prod_id:u64, attr_seq:u16, t_diff:i64| -> (i64,u32,u32,u32) {
@@ -229,6 +236,7 @@ async fn cmplt_req_done_all() {
         }
     };
     if let Ok((cmplt_resp, errs3pty)) = result {
+        let cmplt_resp = ut_parse_cmplt_resp(cmplt_resp.as_str());
         assert!(errs3pty.is_empty());
         assert_eq!(cmplt_resp.lines.len(), 7);
         cmplt_resp.lines.into_iter()
@@ -285,12 +293,12 @@ async fn cmplt_req_done_partial() {
     );
     let repo_ch = ut_setup_repo_charge(Some(mock_charge_ms), Some(Ok(())));
     let repo_mc = ut_setup_repo_merchant(Some(mock_merchant_profile));
-    let repo_rfd = ut_setup_repo_refund(Some(mock_rfnd_req_m));
+    let repo_rfd = ut_setup_repo_refund(Some(mock_rfnd_req_m), None);
     let processors = ut_setup_processor(Some(vec![false, false]));
     let uc = FinalizeRefundUseCase { repo_ch, repo_mc, repo_rfd, processors };
     let mock_authed_claim = _ut_setup_auth_claim(mock_staff_usr_id);
     let result = uc.execute(
-        mock_oid.to_string(), mock_merchant_id, mock_authed_claim, mock_cmplt_req
+        mock_oid.to_string(), mock_merchant_id, mock_authed_claim, mock_cmplt_req, None
     ).await;
     assert!(result.is_ok());
     let data_selector = |prod_id:u64, attr_seq:u16, t_diff:i64| -> (i64,u32,u32,u32) {
@@ -304,6 +312,7 @@ async fn cmplt_req_done_partial() {
         }
     };
     if let Ok((cmplt_resp, errs3pty)) = result {
+        let cmplt_resp = ut_parse_cmplt_resp(cmplt_resp.as_str());
         assert!(errs3pty.is_empty());
         assert_eq!(cmplt_resp.lines.len(), 5);
         cmplt_resp.lines.into_iter()
@@ -359,12 +368,12 @@ async fn cmplt_req_done_with_processor_error() {
     );
     let repo_ch = ut_setup_repo_charge(Some(mock_charge_ms), Some(Ok(())));
     let repo_mc = ut_setup_repo_merchant(Some(mock_merchant_profile));
-    let repo_rfd = ut_setup_repo_refund(Some(mock_rfnd_req_m));
+    let repo_rfd = ut_setup_repo_refund(Some(mock_rfnd_req_m), None);
     let processors = ut_setup_processor(Some(vec![false, true]));
     let uc = FinalizeRefundUseCase { repo_ch, repo_mc, repo_rfd, processors };
     let mock_authed_claim = _ut_setup_auth_claim(mock_staff_usr_id);
     let result = uc.execute(
-        mock_oid.to_string(), mock_merchant_id, mock_authed_claim, mock_cmplt_req
+        mock_oid.to_string(), mock_merchant_id, mock_authed_claim, mock_cmplt_req, None
     ).await;
     assert!(result.is_ok());
     let data_selector = |prod_id:u64, attr_seq:u16, t_diff:i64| -> (i64,u32,u32,u32) {
@@ -377,6 +386,7 @@ async fn cmplt_req_done_with_processor_error() {
         } // this is another partial completion case due to 3rd-party error
     };
     if let Ok((cmplt_resp, mut errs3pty)) = result {
+        let cmplt_resp = ut_parse_cmplt_resp(cmplt_resp.as_str());
         assert_eq!(errs3pty.len(), 1);
         let err3pty = errs3pty.remove(0);
         if let AppProcessorErrorReason::InvalidMethod(s) = err3pty.reason {
@@ -408,12 +418,12 @@ async fn missing_charge_ids() {
     let mock_cmplt_req = ut_setup_refund_cmplt_dto(time_base, vec![]);
     let repo_ch = ut_setup_repo_charge(None, None);
     let repo_mc = ut_setup_repo_merchant(Some(mock_merchant_profile));
-    let repo_rfd = ut_setup_repo_refund(None);
+    let repo_rfd = ut_setup_repo_refund(None, None);
     let processors = ut_setup_processor(None);
     let uc = FinalizeRefundUseCase { repo_ch, repo_mc, repo_rfd, processors };
     let mock_authed_claim = _ut_setup_auth_claim(mock_staff_usr_id);
     let result = uc.execute(
-        mock_oid.to_string(), mock_merchant_id, mock_authed_claim , mock_cmplt_req
+        mock_oid.to_string(), mock_merchant_id, mock_authed_claim , mock_cmplt_req, None
     ).await;
     assert!(result.is_err());
     if let Err(FinalizeRefundUcError::MissingChargeId(oid)) = result {
@@ -450,12 +460,12 @@ async fn resolve_failure_repo_refund() {
     );
     let repo_ch = ut_setup_repo_charge(Some(mock_charge_ms), None);
     let repo_mc = ut_setup_repo_merchant(Some(mock_merchant_profile));
-    let repo_rfd = ut_setup_repo_refund(Some(mock_rfnd_req_m));
+    let repo_rfd = ut_setup_repo_refund(Some(mock_rfnd_req_m), None);
     let processors = ut_setup_processor(None);
     let uc = FinalizeRefundUseCase { repo_ch, repo_mc, repo_rfd, processors };
     let mock_authed_claim = _ut_setup_auth_claim(mock_staff_usr_id);
     let result = uc.execute(
-        mock_oid.to_string(), mock_merchant_id, mock_authed_claim, mock_cmplt_req
+        mock_oid.to_string(), mock_merchant_id, mock_authed_claim, mock_cmplt_req, None
     ).await;
     assert!(result.is_err());
     if let Err(FinalizeRefundUcError::DataStore(e)) = result {
@@ -482,7 +492,7 @@ async fn resolve_failure_repo_refund() {
 
 #[rustfmt::skip]
 #[actix_web::test]
-async fn update_failure_chargeline() {
+async fn skip_separate_chargeline_update() {
     let time_base = Local::now().to_utc();
     let mock_oid = "d003bea7";
     let mock_merchant_id = 127u32;
@@ -512,22 +522,172 @@ async fn update_failure_chargeline() {
     );
     let repo_ch = ut_setup_repo_charge(Some(mock_charge_ms), Some(Err(mock_repo_err)));
     let repo_mc = ut_setup_repo_merchant(Some(mock_merchant_profile));
-    let repo_rfd = ut_setup_repo_refund(Some(mock_rfnd_req_m));
+    let repo_rfd = ut_setup_repo_refund(Some(mock_rfnd_req_m), None);
     let processors = ut_setup_processor(Some(vec![false]));
     let uc = FinalizeRefundUseCase { repo_ch, repo_mc, repo_rfd, processors };
     let mock_authed_claim = _ut_setup_auth_claim(mock_staff_usr_id);
     let result = uc.execute(
-        mock_oid.to_string(), mock_merchant_id, mock_authed_claim, mock_cmplt_req
+        mock_oid.to_string(), mock_merchant_id, mock_authed_claim, mock_cmplt_req, None
     ).await;
-    assert!(result.is_err());
-    if let Err(FinalizeRefundUcError::DataStore(e)) = result {
-        assert_eq!(e.code, AppErrorCode::RemoteDbServerFailure);
-        if let AppRepoErrorDetail::DatabaseExec(s) = e.detail {
-            assert_eq!(s.as_str(), "unit-test");
-        } else {
-            assert!(false);
-        }
+    // charge lines are updated within the refund repository transaction,
+    // the use case should never update them separately
+    assert!(result.is_ok());
+    let (_resp_body, errs3pty) = result.unwrap();
+    assert!(errs3pty.is_empty());
+} // end of fn skip_separate_chargeline_update
+
+fn ut_setup_saved_idempotent(
+    key: &str,
+    merchant_id: u32,
+    oid: &str,
+    time_base: DateTime<Utc>,
+    d_lines: Vec<UTestRefundCmpltDtoRawData>,
+) -> AppRefundIdempotentReturn {
+    let cmplt_req = ut_setup_refund_cmplt_dto(time_base, d_lines);
+    let arg = (key.to_string(), merchant_id, oid, &cmplt_req);
+    let idem_m = RefundIdempotencyModel::try_from(arg).unwrap();
+    let resp_line = RefundCompletionOlineRespDto {
+        product_id: 8299,
+        attr_set_seq: 0,
+        time_issued: time_base - Duration::minutes(19),
+        reject: HashMap::from([
+            (RefundRejectReasonDto::Damaged, 0),
+            (RefundRejectReasonDto::Fraudulent, 1),
+        ]),
+        approval: RefundLineApprovalDto {
+            quantity: 4,
+            amount_total: "1300".to_string(),
+        },
+    };
+    let resp = RefundCompletionRespDto {
+        lines: vec![resp_line],
+    };
+    let resp_body = serde_json::to_string(&resp).unwrap();
+    Some((idem_m, resp_body))
+}
+
+fn ut_parse_cmplt_resp(body: &str) -> RefundCompletionRespDto {
+    serde_json::from_str::<RefundCompletionRespDto>(body).unwrap()
+}
+
+#[rustfmt::skip]
+#[actix_web::test]
+async fn cmplt_req_replay_idempotent() {
+    let time_base = Local::now().to_utc();
+    let mock_oid = "d003bea7";
+    let mock_idem_key = "d003bea7-rfnd-0001";
+    let mock_merchant_id = 127u32;
+    let mock_staff_usr_id = 1551u32;
+    let mock_merchant_profile = {
+        let prof_dto = ut_setup_storeprofile_dto(
+            "NVDA", mock_staff_usr_id, vec![mock_staff_usr_id],
+            time_base - Duration::days(100)
+        );
+        MerchantProfileModel::try_from((mock_merchant_id , &prof_dto)).unwrap()
+    };
+    let d_lines = vec![((8299, 0), 19, 1300, 4, 1, 0)];
+    let saved_idem = ut_setup_saved_idempotent(
+        mock_idem_key, mock_merchant_id, mock_oid, time_base, d_lines.clone(),
+    );
+    let saved_body = saved_idem.as_ref().map(|(_m, body)| body.clone());
+    let mock_cmplt_req = ut_setup_refund_cmplt_dto(time_base, d_lines);
+    // the request is not resolved again, neither charges nor refund requests
+    // are loaded from the repositories
+    let repo_ch = ut_setup_repo_charge(None, None);
+    let repo_mc = ut_setup_repo_merchant(Some(mock_merchant_profile));
+    let repo_rfd = ut_setup_repo_refund(None, saved_idem);
+    let processors = ut_setup_processor(None);
+    let uc = FinalizeRefundUseCase { repo_ch, repo_mc, repo_rfd, processors };
+    let mock_authed_claim = _ut_setup_auth_claim(mock_staff_usr_id);
+    let result = uc.execute(
+        mock_oid.to_string(), mock_merchant_id, mock_authed_claim, mock_cmplt_req,
+        Some(mock_idem_key.to_string()),
+    ).await;
+    let (resp_body, errs3pty) = result.unwrap();
+    assert!(errs3pty.is_empty());
+    // the saved body is replayed as it is
+    let expect_body = saved_body.unwrap();
+    assert_eq!(resp_body, expect_body);
+    let cmplt_resp = ut_parse_cmplt_resp(resp_body.as_str());
+    assert_eq!(cmplt_resp.lines.len(), 1);
+    let rline = &cmplt_resp.lines[0];
+    assert_eq!(rline.product_id, 8299);
+    assert_eq!(rline.approval.quantity, 4);
+    assert_eq!(rline.approval.amount_total.as_str(), "1300");
+} // end of fn cmplt_req_replay_idempotent
+
+#[rustfmt::skip]
+#[actix_web::test]
+async fn cmplt_req_idempotency_conflict() {
+    let time_base = Local::now().to_utc();
+    let mock_oid = "d003bea7";
+    let mock_idem_key = "d003bea7-rfnd-0002";
+    let mock_merchant_id = 127u32;
+    let mock_staff_usr_id = 1551u32;
+    let mock_merchant_profile = {
+        let prof_dto = ut_setup_storeprofile_dto(
+            "NVDA", mock_staff_usr_id, vec![mock_staff_usr_id],
+            time_base - Duration::days(100)
+        );
+        MerchantProfileModel::try_from((mock_merchant_id , &prof_dto)).unwrap()
+    };
+    // the key was previously sent with different quantity approved
+    let saved_idem = ut_setup_saved_idempotent(
+        mock_idem_key, mock_merchant_id, mock_oid, time_base,
+        vec![((8299, 0), 19, 1300, 4, 1, 0)],
+    );
+    let mock_cmplt_req = ut_setup_refund_cmplt_dto(
+        time_base,
+        vec![((8299, 0), 19, 975, 3, 1, 0)]
+    );
+    let repo_ch = ut_setup_repo_charge(None, None);
+    let repo_mc = ut_setup_repo_merchant(Some(mock_merchant_profile));
+    let repo_rfd = ut_setup_repo_refund(None, saved_idem);
+    let processors = ut_setup_processor(None);
+    let uc = FinalizeRefundUseCase { repo_ch, repo_mc, repo_rfd, processors };
+    let mock_authed_claim = _ut_setup_auth_claim(mock_staff_usr_id);
+    let result = uc.execute(
+        mock_oid.to_string(), mock_merchant_id, mock_authed_claim, mock_cmplt_req,
+        Some(mock_idem_key.to_string()),
+    ).await;
+    if let Err(FinalizeRefundUcError::IdempotencyConflict(key)) = result {
+        assert_eq!(key.as_str(), mock_idem_key);
     } else {
-        assert!(false);
+        panic!("unexpected result");
+    }
+} // end of fn cmplt_req_idempotency_conflict
+
+#[rustfmt::skip]
+#[actix_web::test]
+async fn cmplt_req_invalid_idempotency_key() {
+    let time_base = Local::now().to_utc();
+    let mock_oid = "d003bea7";
+    let mock_merchant_id = 127u32;
+    let mock_staff_usr_id = 1551u32;
+    let mock_merchant_profile = {
+        let prof_dto = ut_setup_storeprofile_dto(
+            "NVDA", mock_staff_usr_id, vec![mock_staff_usr_id],
+            time_base - Duration::days(100)
+        );
+        MerchantProfileModel::try_from((mock_merchant_id , &prof_dto)).unwrap()
+    };
+    let mock_cmplt_req = ut_setup_refund_cmplt_dto(
+        time_base,
+        vec![((8299, 0), 19, 1300, 4, 1, 0)]
+    );
+    let repo_ch = ut_setup_repo_charge(None, None);
+    let repo_mc = ut_setup_repo_merchant(Some(mock_merchant_profile));
+    let repo_rfd = ut_setup_repo_refund(None, None);
+    let processors = ut_setup_processor(None);
+    let uc = FinalizeRefundUseCase { repo_ch, repo_mc, repo_rfd, processors };
+    let mock_authed_claim = _ut_setup_auth_claim(mock_staff_usr_id);
+    let result = uc.execute(
+        mock_oid.to_string(), mock_merchant_id, mock_authed_claim, mock_cmplt_req,
+        Some("invalid key !".to_string()),
+    ).await;
+    if let Err(FinalizeRefundUcError::InvalidIdempotencyKey(me)) = result {
+        assert!(matches!(me, RefundModelError::InvalidIdempotencyKey(_)));
+    } else {
+        panic!("unexpected result");
     }
-} // end of fn update_failure_chargeline
+} // end of fn cmplt_req_invalid_idempotency_key
@@ -2,7 +2,10 @@ mod capture_charge;
 mod create_charge;
 mod finalize_refund;
 mod onboard;
+mod processor_webhook;
 mod refresh_charge_status;
+mod refund_status;
+mod sync_order_app;
 
 use std::boxed::Box;
 use std::result::Result;
@@ -16,24 +19,28 @@ use ecommerce_common::api::rpc::dto::StoreProfileReplicaDto;
 use ecommerce_common::error::AppErrorCode;
 use ecommerce_common::model::order::BillingModel;
 
-use payment::adapter::cache::{AbstractOrderSyncLockCache, OrderSyncLockError};
+use payment::adapter::cache::{AbstractOrderSyncLockCache, OrderSyncLockError, OrderSyncLockToken};
 use payment::adapter::processor::{
     AbstractPaymentProcessor, AppProcessorError, AppProcessorErrorReason, AppProcessorFnLabel,
     AppProcessorMerchantResult, AppProcessorPayInResult, AppProcessorPayoutResult,
 };
 use payment::adapter::repository::{
-    AbstractChargeRepo, AbstractMerchantRepo, AbstractRefundRepo, AppRefundRslvReqCallback,
+    AbstractChargeRepo, AbstractMerchantRepo, AbstractRefundRepo, AbstractWebhookEventRepo,
+    AppChargeUnsyncedCursor, AppRefundIdempotentReturn, AppRefundRslvReqCallback,
     AppRefundRslvReqOkReturn, AppRepoError, AppRepoErrorDetail, AppRepoErrorFnLabel,
 };
 use payment::adapter::rpc::{
     AbsRpcClientContext, AbstractRpcClient, AbstractRpcContext, AbstractRpcPublishEvent,
     AppRpcClientRequest, AppRpcCtxError, AppRpcReply,
 };
-use payment::api::web::dto::{PaymentMethodReqDto, RefundCompletionReqDto, StoreOnboardReqDto};
+use payment::api::web::dto::{
+    PaymentMethodReqDto, RefundCompletionReqDto, RefundCompletionRespDto, StoreOnboardReqDto,
+};
 use payment::model::{
     Charge3partyModel, ChargeBuyerMetaModel, ChargeBuyerModel, ChargeLineBuyerModel,
     ChargeRefundMap, Label3party, Merchant3partyModel, MerchantProfileModel, OrderLineModelSet,
-    OrderRefundModel, PayoutModel, RefundReqResolutionModel,
+    OrderRefundModel, PayoutModel, PlatformFeeScheduleModel, RefundIdempotencyModel,
+    RefundReqResolutionModel, Webhook3partyEventModel,
 };
 
 struct MockChargeRepo {
@@ -48,6 +55,8 @@ struct MockChargeRepo {
     _create_payout_result: Mutex<Option<Result<(), AppRepoError>>>,
     _read_charge_ids: Mutex<Option<Option<(u32, Vec<DateTime<Utc>>)>>>,
     _update_linerefund_result: Mutex<Option<Result<(), AppRepoError>>>,
+    // each element is one page of unsynced charges
+    _read_unsynced_metas: Mutex<Vec<Result<Vec<ChargeBuyerMetaModel>, AppRepoError>>>,
 }
 
 impl MockChargeRepo {
@@ -76,6 +85,7 @@ impl MockChargeRepo {
             _create_payout_result: Mutex::new(create_payout_res),
             _read_charge_ids: Mutex::new(Some(rd_chrg_ids)),
             _update_linerefund_result: Mutex::new(update_linerfd_res),
+            _read_unsynced_metas: Mutex::new(Vec::new()),
         })
     }
 } // end of impl MockChargeRepo
@@ -142,6 +152,17 @@ impl AbstractChargeRepo for MockChargeRepo {
         out
     }
 
+    async fn fetch_charges_unsynced(
+        &self,
+        _t_start: DateTime<Utc>,
+        _t_end: DateTime<Utc>,
+        _cursor: Option<AppChargeUnsyncedCursor>,
+        _max_items: u16,
+    ) -> Result<Vec<ChargeBuyerMetaModel>, AppRepoError> {
+        let mut g = self._read_unsynced_metas.lock().await;
+        g.remove(0)
+    }
+
     async fn update_lines_refund(&self, _cl_map: ChargeRefundMap) -> Result<(), AppRepoError> {
         let mut g = self._update_linerefund_result.lock().await;
         let out = g.take().unwrap();
@@ -159,14 +180,14 @@ impl AbstractChargeRepo for MockChargeRepo {
         Ok(out)
     }
 
-    async fn fetch_payout(
+    async fn fetch_payouts(
         &self,
         _store_id: u32,
         _buyer_id: u32,
         _create_time: DateTime<Utc>,
-    ) -> Result<Option<PayoutModel>, AppRepoError> {
+    ) -> Result<Vec<PayoutModel>, AppRepoError> {
         let mut g = self._read_payout.lock().await;
-        let out = g.take();
+        let out = g.take().into_iter().collect();
         Ok(out)
     }
 
@@ -182,6 +203,8 @@ struct MockMerchantRepo {
     _fetch_result: Mutex<Option<(MerchantProfileModel, Merchant3partyModel)>>,
     _fetch_profile_result: Mutex<Option<MerchantProfileModel>>,
     _update3pty_result: Mutex<Option<Result<(), AppRepoError>>>,
+    _fetch_id3pty_result: Mutex<Option<u32>>,
+    _fetch_fee_result: Mutex<Option<PlatformFeeScheduleModel>>,
 } // end of trait AbstractMerchantRepo
 
 impl MockMerchantRepo {
@@ -196,6 +219,8 @@ impl MockMerchantRepo {
             _fetch_result: Mutex::new(fetch_res),
             _fetch_profile_result: Mutex::new(fetch_prof_res),
             _update3pty_result: Mutex::new(update3pt_res),
+            _fetch_id3pty_result: Mutex::new(None),
+            _fetch_fee_result: Mutex::new(None),
         };
         Box::new(obj)
     }
@@ -238,16 +263,75 @@ impl AbstractMerchantRepo for MockMerchantRepo {
         let out = g.take();
         Ok(out)
     }
+    async fn fetch_id_by_3party(
+        &self,
+        _label3pty: Label3party,
+        _id3pty: &str,
+    ) -> Result<Option<u32>, AppRepoError> {
+        let g = self._fetch_id3pty_result.lock().await;
+        Ok(*g)
+    }
+    async fn fetch_platform_fee(
+        &self,
+        store_id: u32,
+    ) -> Result<PlatformFeeScheduleModel, AppRepoError> {
+        let mut g = self._fetch_fee_result.lock().await;
+        let out = g
+            .take()
+            .unwrap_or(PlatformFeeScheduleModel::from((store_id, Vec::new())));
+        Ok(out)
+    }
 } // end of impl MockMerchantRepo
 
+struct MockWebhookEventRepo {
+    _save_result: Mutex<Option<Result<bool, AppRepoError>>>,
+    _delete_result: Mutex<Option<Result<(), AppRepoError>>>,
+}
+
+impl MockWebhookEventRepo {
+    fn build(
+        save_res: Option<Result<bool, AppRepoError>>,
+        delete_res: Option<Result<(), AppRepoError>>,
+    ) -> Box<dyn AbstractWebhookEventRepo> {
+        let obj = Self {
+            _save_result: Mutex::new(save_res),
+            _delete_result: Mutex::new(delete_res),
+        };
+        Box::new(obj)
+    }
+}
+
+#[async_trait]
+impl AbstractWebhookEventRepo for MockWebhookEventRepo {
+    async fn save(
+        &self,
+        _label3pty: Label3party,
+        _evt_id: &str,
+        _evt_type: &str,
+        _create_time: DateTime<Utc>,
+    ) -> Result<bool, AppRepoError> {
+        let mut g = self._save_result.lock().await;
+        g.take().unwrap()
+    }
+    async fn delete(&self, _label3pty: Label3party, _evt_id: &str) -> Result<(), AppRepoError> {
+        let mut g = self._delete_result.lock().await;
+        g.take().unwrap()
+    }
+} // end of impl MockWebhookEventRepo
+
 struct MockRefundRepo {
     _saved_req_for_rslv: Mutex<Option<OrderRefundModel>>,
+    _saved_idempotent: Mutex<AppRefundIdempotentReturn>,
 }
 
 impl MockRefundRepo {
-    fn build(saved_req: Option<OrderRefundModel>) -> Box<dyn AbstractRefundRepo> {
+    fn build(
+        saved_req: Option<OrderRefundModel>,
+        saved_idempotent: AppRefundIdempotentReturn,
+    ) -> Box<dyn AbstractRefundRepo> {
         let obj = Self {
             _saved_req_for_rslv: Mutex::new(saved_req),
+            _saved_idempotent: Mutex::new(saved_idempotent),
         };
         Box::new(obj)
     }
@@ -279,38 +363,72 @@ impl AbstractRefundRepo for MockRefundRepo {
             AppRepoErrorFnLabel::RefundSaveReq,
         ))
     }
+    async fn fetch_request_by_order(&self, _oid: &str) -> Result<OrderRefundModel, AppRepoError> {
+        let mut g = self._saved_req_for_rslv.lock().await;
+        g.take().ok_or(Self::_not_implemented_err(
+            AppRepoErrorFnLabel::RefundFetchReqByOrder,
+        ))
+    }
+    async fn fetch_idempotent_result(
+        &self,
+        _merchant_id: u32,
+        _key: &str,
+    ) -> Result<AppRefundIdempotentReturn, AppRepoError> {
+        let mut g = self._saved_idempotent.lock().await;
+        Ok(g.take())
+    }
     async fn resolve_request(
         &self,
         _merchant_id: u32,
         cmplt_req: RefundCompletionReqDto,
+        _idempotency: Option<RefundIdempotencyModel>,
         charge_ms: Vec<ChargeBuyerModel>,
         processor: Arc<Box<dyn AbstractPaymentProcessor>>,
         cb: AppRefundRslvReqCallback,
-    ) -> Result<AppRefundRslvReqOkReturn, AppRepoError> {
+    ) -> Result<(AppRefundRslvReqOkReturn, String), AppRepoError> {
         let mut g = self._saved_req_for_rslv.lock().await;
         let o_rfnd_m = g.as_mut().unwrap();
         let result = cb(o_rfnd_m, cmplt_req, charge_ms, processor).await;
-        result.map_err(|detail| AppRepoError {
+        let cb_res = result.map_err(|detail| AppRepoError {
             fn_label: AppRepoErrorFnLabel::ResolveRefundReq,
             code: AppErrorCode::InvalidInput,
             detail,
-        })
+        })?;
+        let resolved = cb_res
+            .iter()
+            .filter_map(|r| r.as_ref().ok())
+            .collect::<Vec<_>>();
+        let resp = RefundCompletionRespDto::from(resolved.as_slice());
+        let resp_body = serde_json::to_string(&resp).unwrap();
+        Ok((cb_res, resp_body))
     }
 } // end of impl MockRefundRepo
 
 struct MockOrderSyncLockCache {
-    _acquire_result: Mutex<Option<Result<bool, OrderSyncLockError>>>,
+    _acquire_result: Mutex<Option<Result<Option<OrderSyncLockToken>, OrderSyncLockError>>>,
     _release_result: Mutex<Option<Result<(), OrderSyncLockError>>>,
 }
 
 #[async_trait]
 impl AbstractOrderSyncLockCache for MockOrderSyncLockCache {
-    async fn acquire(&self, _usr_id: u32, _oid: &str) -> Result<bool, OrderSyncLockError> {
+    async fn acquire(
+        &self,
+        _usr_id: u32,
+        _oid: &str,
+    ) -> Result<Option<OrderSyncLockToken>, OrderSyncLockError> {
         let mut g = self._acquire_result.lock().await;
-        let out = g.take().unwrap();
-        out
+        // the lock held by others is kept until the caller stops waiting
+        match g.as_ref() {
+            Some(Ok(None)) => Ok(None),
+            _others => g.take().unwrap(),
+        }
     }
-    async fn release(&self, _usr_id: u32, _oid: &str) -> Result<(), OrderSyncLockError> {
+    async fn release(
+        &self,
+        _usr_id: u32,
+        _oid: &str,
+        _token: OrderSyncLockToken,
+    ) -> Result<(), OrderSyncLockError> {
         let mut g = self._release_result.lock().await;
         let out = g.take().unwrap();
         out
@@ -393,6 +511,9 @@ struct MockPaymentProcessor {
     _onboard_merchant_result: Mutex<Option<Result<AppProcessorMerchantResult, AppProcessorError>>>,
     _payout_result: Mutex<Option<Result<AppProcessorPayoutResult, AppProcessorError>>>,
     _refund_error_trigger: Mutex<Vec<bool>>,
+    // the trait method is synchronous
+    _webhook_event_result:
+        std::sync::Mutex<Option<Result<Webhook3partyEventModel, AppProcessorError>>>,
 }
 
 impl MockPaymentProcessor {
@@ -411,6 +532,7 @@ impl MockPaymentProcessor {
             _onboard_merchant_result: Mutex::new(onboard_merchant_arg),
             _payout_result: Mutex::new(payout_res),
             _refund_error_trigger: Mutex::new(refund_err_trigger.unwrap_or(Vec::new())),
+            _webhook_event_result: std::sync::Mutex::new(None),
         })
     }
 }
@@ -480,4 +602,13 @@ impl AbstractPaymentProcessor for MockPaymentProcessor {
             Ok(resolve_m)
         }
     }
+    fn webhook_event(
+        &self,
+        _label3pt: Label3party,
+        _sig_header: &str,
+        _payload: &[u8],
+    ) -> Result<Webhook3partyEventModel, AppProcessorError> {
+        let mut g = self._webhook_event_result.lock().unwrap();
+        g.take().unwrap()
+    }
 } // end of impl MockPaymentProcessor
@@ -0,0 +1,483 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Duration, Local, Utc};
+use tokio::sync::Mutex;
+
+use ecommerce_common::api::rpc::dto::OrderPaymentUpdateErrorDto;
+use ecommerce_common::error::AppErrorCode;
+use payment::adapter::processor::{
+    AbstractPaymentProcessor, AppProcessorError, AppProcessorErrorReason, AppProcessorFnLabel,
+};
+use payment::adapter::repository::{
+    AbstractChargeRepo, AbstractMerchantRepo, AppRepoError, AppRepoErrorDetail, AppRepoErrorFnLabel,
+};
+use payment::adapter::rpc::{AppRpcCtxError, AppRpcErrorFnLabel, AppRpcErrorReason, AppRpcReply};
+use payment::model::{
+    BuyerPayInState, ChargeBuyerMetaModel, ChargeToken, Label3party, Merchant3partyModel,
+    Merchant3partyStripeModel, MerchantProfileModel, StripeAccountCapableState,
+    StripeCheckoutPaymentStatusModel, StripeSessionStatusModel, StripeWebhookEventDetail,
+    StripeWebhookEventModel, Webhook3partyEventModel,
+};
+use payment::usecase::{ProcessorWebhookUcError, ProcessorWebhookUseCase};
+
+use super::{
+    MockChargeRepo, MockMerchantRepo, MockPaymentProcessor, MockRpcClient, MockRpcContext,
+    MockRpcPublishEvent, MockWebhookEventRepo,
+};
+use crate::dto::ut_setup_storeprofile_dto;
+use crate::model::{
+    ut_default_charge_method_stripe, ut_default_merchant_3party_stripe, ut_setup_buyer_charge_lines,
+};
+use crate::ut_setup_logctx;
+
+fn ut_setup_processor(
+    result: Result<Webhook3partyEventModel, AppProcessorError>,
+) -> Arc<Box<dyn AbstractPaymentProcessor>> {
+    let obj = MockPaymentProcessor {
+        _payin_start_result: Mutex::new(None),
+        _payin_progress_result: Mutex::new(None),
+        _onboard_merchant_result: Mutex::new(None),
+        _payout_result: Mutex::new(None),
+        _refund_error_trigger: Mutex::new(Vec::new()),
+        _webhook_event_result: std::sync::Mutex::new(Some(result)),
+    };
+    Arc::new(Box::new(obj))
+}
+
+#[rustfmt::skip]
+fn ut_setup_repo_charge(
+    chargemeta: Option<Result<Option<ChargeBuyerMetaModel>, AppRepoError>>,
+    update_meta_res: Option<Result<(), AppRepoError>>,
+    rd_chrg_ids: Option<(u32, Vec<DateTime<Utc>>)>,
+) -> Box<dyn AbstractChargeRepo> {
+    MockChargeRepo::build(
+        None, None, None,
+        chargemeta, None, update_meta_res,
+        None, None, None,
+        rd_chrg_ids, None,
+    )
+}
+
+// charge lines are loaded for updating order payment
+#[rustfmt::skip]
+fn ut_setup_repo_charge_orderapp(
+    meta: ChargeBuyerMetaModel,
+    rd_chrg_ids: (u32, Vec<DateTime<Utc>>),
+) -> Box<dyn AbstractChargeRepo> {
+    let d_lines = vec![((8298, 471, 0), ((9028, 2), (36112, 2), 4), ((0,0), (0,0), 0), 0)];
+    MockChargeRepo::build(
+        None, None, None,
+        Some(Ok(Some(meta))), Some(Ok(ut_setup_buyer_charge_lines(d_lines))), Some(Ok(())),
+        None, None, None,
+        Some(rd_chrg_ids), None,
+    )
+}
+
+fn ut_setup_repo_merchant(
+    store_id: Option<u32>,
+    fetch_res: Option<(MerchantProfileModel, Merchant3partyModel)>,
+    update3pt_res: Option<Result<(), AppRepoError>>,
+) -> Box<dyn AbstractMerchantRepo> {
+    let obj = MockMerchantRepo {
+        _create_result: Mutex::new(None),
+        _fetch_result: Mutex::new(fetch_res),
+        _fetch_profile_result: Mutex::new(None),
+        _update3pty_result: Mutex::new(update3pt_res),
+        _fetch_id3pty_result: Mutex::new(store_id),
+        _fetch_fee_result: Mutex::new(None),
+    };
+    Box::new(obj)
+}
+
+fn ut_stripe_event(detail: StripeWebhookEventDetail) -> Webhook3partyEventModel {
+    let m = StripeWebhookEventModel {
+        id: "evt_1unitTest0a9b8c7".to_string(),
+        type_: "unit.test".to_string(),
+        created: Local::now().to_utc() - Duration::seconds(5),
+        detail,
+    };
+    Webhook3partyEventModel::Stripe(m)
+}
+
+fn ut_setup_buyer_meta(usr_id: u32, oid: &str, ctime: DateTime<Utc>) -> ChargeBuyerMetaModel {
+    let mut obj = ChargeBuyerMetaModel::from((oid.to_string(), usr_id, ctime));
+    obj.update_3party(ut_default_charge_method_stripe(&ctime));
+    let state = BuyerPayInState::ProcessorAccepted(ctime + Duration::seconds(2));
+    obj.update_progress(&state);
+    obj
+}
+
+fn ut_setup_store_models(
+    store_id: u32,
+    m3pty: Merchant3partyStripeModel,
+) -> (MerchantProfileModel, Merchant3partyModel) {
+    let t_now = Local::now().to_utc();
+    let mock_store_d = ut_setup_storeprofile_dto("Cloud Berry Tavern", 2345, vec![2346], t_now);
+    let arg = (store_id, &mock_store_d);
+    let storeprof_m = MerchantProfileModel::try_from(arg).unwrap();
+    (storeprof_m, Merchant3partyModel::Stripe(m3pty))
+}
+
+fn ut_clone_merchant_3party(m: &Merchant3partyStripeModel) -> Merchant3partyStripeModel {
+    let serial = serde_json::to_value(m).unwrap();
+    serde_json::from_value(serial).unwrap()
+}
+
+#[actix_web::test]
+async fn checkout_session_completed_ok() {
+    let (mock_usr_id, mock_oid) = (1238, "0a1b2c3d");
+    let mock_ctime = Local::now().to_utc() - Duration::minutes(4);
+    let processors = {
+        let detail = StripeWebhookEventDetail::CheckoutSession {
+            session_id: "mock-session-id".to_string(),
+            client_reference_id: Some(format!("{mock_usr_id}-{mock_oid}")),
+            session_state: StripeSessionStatusModel::complete,
+            payment_state: StripeCheckoutPaymentStatusModel::paid,
+        };
+        ut_setup_processor(Ok(ut_stripe_event(detail)))
+    };
+    // the order-processing service is notified once the payment is confirmed
+    let repo_charge = {
+        let meta = ut_setup_buyer_meta(mock_usr_id, mock_oid, mock_ctime);
+        let ids = (mock_usr_id, vec![mock_ctime]);
+        ut_setup_repo_charge_orderapp(meta, ids)
+    };
+    let rpc_ctx = {
+        let reply = OrderPaymentUpdateErrorDto {
+            oid: mock_oid.to_string(),
+            charge_time: None,
+            lines: Vec::new(),
+        };
+        let mock_reply = AppRpcReply {
+            message: serde_json::to_vec(&reply).unwrap(),
+        };
+        let rpc_pub_evt = MockRpcPublishEvent::build(Some(Ok(mock_reply)));
+        let mock_rpc_client = MockRpcClient::build(Some(Ok(rpc_pub_evt)));
+        MockRpcContext::build(Some(Ok(mock_rpc_client)))
+    };
+    let uc = ProcessorWebhookUseCase {
+        processors,
+        repo_evt: MockWebhookEventRepo::build(Some(Ok(true)), None),
+        repo_charge,
+        repo_merchant: ut_setup_repo_merchant(None, None, None),
+        rpc_ctx: Arc::new(rpc_ctx),
+        logctx: ut_setup_logctx(),
+    };
+    let result = uc.execute(Label3party::Stripe, "t=1,v1=ab", b"{}").await;
+    assert!(matches!(result, Ok(true)));
+} // end of fn checkout_session_completed_ok
+
+#[actix_web::test]
+async fn checkout_session_completed_order_app_unreachable() {
+    let (mock_usr_id, mock_oid) = (1238, "0a1b2c3d");
+    let mock_ctime = Local::now().to_utc() - Duration::minutes(4);
+    let processors = {
+        let detail = StripeWebhookEventDetail::CheckoutSession {
+            session_id: "mock-session-id".to_string(),
+            client_reference_id: Some(format!("{mock_usr_id}-{mock_oid}")),
+            session_state: StripeSessionStatusModel::complete,
+            payment_state: StripeCheckoutPaymentStatusModel::paid,
+        };
+        ut_setup_processor(Ok(ut_stripe_event(detail)))
+    };
+    // the charge is still saved with latest state, then synced later by the
+    // background job
+    let repo_charge = {
+        let meta = ut_setup_buyer_meta(mock_usr_id, mock_oid, mock_ctime);
+        let ids = (mock_usr_id, vec![mock_ctime]);
+        ut_setup_repo_charge(Some(Ok(Some(meta))), Some(Ok(())), Some(ids))
+    };
+    let rpc_expect_error = AppRpcCtxError {
+        fn_label: AppRpcErrorFnLabel::AcquireClientConn,
+        reason: AppRpcErrorReason::LowLevelConn("unit-test".to_string()),
+    };
+    let uc = ProcessorWebhookUseCase {
+        processors,
+        repo_evt: MockWebhookEventRepo::build(Some(Ok(true)), None),
+        repo_charge,
+        repo_merchant: ut_setup_repo_merchant(None, None, None),
+        rpc_ctx: Arc::new(MockRpcContext::build(Some(Err(rpc_expect_error)))),
+        logctx: ut_setup_logctx(),
+    };
+    let result = uc.execute(Label3party::Stripe, "t=1,v1=ab", b"{}").await;
+    assert!(matches!(result, Ok(true)));
+} // end of fn checkout_session_completed_order_app_unreachable
+
+#[actix_web::test]
+async fn checkout_session_owner_mismatch() {
+    let (mock_usr_id, mock_oid) = (1238, "0a1b2c3d");
+    let mock_ctime = Local::now().to_utc() - Duration::minutes(4);
+    let processors = {
+        let detail = StripeWebhookEventDetail::CheckoutSession {
+            session_id: "mock-session-id".to_string(),
+            client_reference_id: Some(format!("{mock_usr_id}-{mock_oid}")),
+            session_state: StripeSessionStatusModel::expired,
+            payment_state: StripeCheckoutPaymentStatusModel::unpaid,
+        };
+        ut_setup_processor(Ok(ut_stripe_event(detail)))
+    };
+    // charge metadata should not be loaded or updated
+    let repo_charge = {
+        let ids = (mock_usr_id + 1, vec![mock_ctime]);
+        ut_setup_repo_charge(None, None, Some(ids))
+    };
+    let uc = ProcessorWebhookUseCase {
+        processors,
+        repo_evt: MockWebhookEventRepo::build(Some(Ok(true)), None),
+        repo_charge,
+        repo_merchant: ut_setup_repo_merchant(None, None, None),
+        rpc_ctx: Arc::new(MockRpcContext::build(None)),
+        logctx: ut_setup_logctx(),
+    };
+    let result = uc.execute(Label3party::Stripe, "t=1,v1=ab", b"{}").await;
+    assert!(matches!(result, Ok(true)));
+} // end of fn checkout_session_owner_mismatch
+
+fn ut_charge_refunded_uc(
+    mock_ctime: DateTime<Utc>,
+    payment_intent_id: &str,
+    amount_refunded: i64,
+    update_meta_res: Option<Result<(), AppRepoError>>,
+) -> ProcessorWebhookUseCase {
+    // the event record is removed once the charge cannot be updated
+    let rm_evt_res = match &update_meta_res {
+        Some(Err(_e)) => Some(Ok(())),
+        _others => None,
+    };
+    let mock_usr_id = 1238;
+    let processors = {
+        let tx_grp = ChargeToken::encode(mock_usr_id, mock_ctime).to_string();
+        let detail = StripeWebhookEventDetail::ChargeRefunded {
+            payment_intent_id: Some(payment_intent_id.to_string()),
+            transfer_group: Some(tx_grp),
+            amount_refunded,
+        };
+        ut_setup_processor(Ok(ut_stripe_event(detail)))
+    };
+    let repo_charge = {
+        let meta = ut_setup_buyer_meta(mock_usr_id, "0a1b2c3d", mock_ctime);
+        ut_setup_repo_charge(Some(Ok(Some(meta))), update_meta_res, None)
+    };
+    ProcessorWebhookUseCase {
+        processors,
+        repo_evt: MockWebhookEventRepo::build(Some(Ok(true)), rm_evt_res),
+        repo_charge,
+        repo_merchant: ut_setup_repo_merchant(None, None, None),
+        rpc_ctx: Arc::new(MockRpcContext::build(None)),
+        logctx: ut_setup_logctx(),
+    }
+}
+
+#[actix_web::test]
+async fn charge_refunded_ok() {
+    let mock_ctime = Local::now().to_utc() - Duration::minutes(4);
+    let uc = ut_charge_refunded_uc(mock_ctime, "mock-payment-intent-id", 1500, Some(Ok(())));
+    let result = uc.execute(Label3party::Stripe, "t=1,v1=ab", b"{}").await;
+    assert!(matches!(result, Ok(true)));
+}
+
+#[actix_web::test]
+async fn charge_refunded_unchanged() {
+    let mock_ctime = Local::now().to_utc() - Duration::minutes(4);
+    // the repository should not be updated
+    let uc = ut_charge_refunded_uc(mock_ctime, "mock-payment-intent-id", 0, None);
+    let result = uc.execute(Label3party::Stripe, "t=1,v1=ab", b"{}").await;
+    assert!(matches!(result, Ok(true)));
+}
+
+#[actix_web::test]
+async fn charge_refunded_payment_intent_mismatch() {
+    let mock_ctime = Local::now().to_utc() - Duration::minutes(4);
+    let uc = ut_charge_refunded_uc(mock_ctime, "other-payment-intent-id", 1500, None);
+    let result = uc.execute(Label3party::Stripe, "t=1,v1=ab", b"{}").await;
+    assert!(matches!(result, Ok(true)));
+}
+
+#[actix_web::test]
+async fn charge_refunded_update_error() {
+    let mock_ctime = Local::now().to_utc() - Duration::minutes(4);
+    let e = AppRepoError {
+        fn_label: AppRepoErrorFnLabel::UpdateChargeProgress,
+        code: AppErrorCode::DatabaseServerBusy,
+        detail: AppRepoErrorDetail::Unknown,
+    };
+    let uc = ut_charge_refunded_uc(mock_ctime, "mock-payment-intent-id", 1500, Some(Err(e)));
+    let result = uc.execute(Label3party::Stripe, "t=1,v1=ab", b"{}").await;
+    let cond = matches!(
+        result,
+        Err(ProcessorWebhookUcError::DataStore(AppRepoError {
+            fn_label: AppRepoErrorFnLabel::UpdateChargeProgress,
+            code: _,
+            detail: _
+        }))
+    );
+    assert!(cond);
+}
+
+#[actix_web::test]
+async fn duplicate_event_skipped() {
+    let processors = ut_setup_processor(Ok(ut_stripe_event(StripeWebhookEventDetail::Unsupported)));
+    let uc = ProcessorWebhookUseCase {
+        processors,
+        repo_evt: MockWebhookEventRepo::build(Some(Ok(false)), None),
+        repo_charge: ut_setup_repo_charge(None, None, None),
+        repo_merchant: ut_setup_repo_merchant(None, None, None),
+        rpc_ctx: Arc::new(MockRpcContext::build(None)),
+        logctx: ut_setup_logctx(),
+    };
+    let result = uc.execute(Label3party::Stripe, "t=1,v1=ab", b"{}").await;
+    assert!(matches!(result, Ok(false)));
+}
+
+#[actix_web::test]
+async fn invalid_signature() {
+    let processors = {
+        let e = AppProcessorError {
+            reason: AppProcessorErrorReason::InvalidWebhook("sig-mismatch".to_string()),
+            fn_label: AppProcessorFnLabel::WebhookEvent,
+        };
+        ut_setup_processor(Err(e))
+    };
+    let uc = ProcessorWebhookUseCase {
+        processors,
+        repo_evt: MockWebhookEventRepo::build(None, None),
+        repo_charge: ut_setup_repo_charge(None, None, None),
+        repo_merchant: ut_setup_repo_merchant(None, None, None),
+        rpc_ctx: Arc::new(MockRpcContext::build(None)),
+        logctx: ut_setup_logctx(),
+    };
+    let result = uc.execute(Label3party::Stripe, "t=1,v1=ab", b"{}").await;
+    let cond = matches!(
+        result,
+        Err(ProcessorWebhookUcError::InvalidEvent(AppProcessorError {
+            reason: AppProcessorErrorReason::InvalidWebhook(_),
+            fn_label: _
+        }))
+    );
+    assert!(cond);
+}
+
+#[actix_web::test]
+async fn account_updated_ok() {
+    let mock_store_id = 1019;
+    let saved = ut_default_merchant_3party_stripe();
+    let processors = {
+        let mut latest = ut_clone_merchant_3party(&saved);
+        latest.payouts_enabled = true;
+        latest.details_submitted = true;
+        latest.capabilities.transfers = StripeAccountCapableState::active;
+        let detail = StripeWebhookEventDetail::AccountUpdated(latest);
+        ut_setup_processor(Ok(ut_stripe_event(detail)))
+    };
+    let repo_merchant = {
+        let arg = ut_setup_store_models(mock_store_id, saved);
+        ut_setup_repo_merchant(Some(mock_store_id), Some(arg), Some(Ok(())))
+    };
+    let uc = ProcessorWebhookUseCase {
+        processors,
+        repo_evt: MockWebhookEventRepo::build(Some(Ok(true)), None),
+        repo_charge: ut_setup_repo_charge(None, None, None),
+        repo_merchant,
+        rpc_ctx: Arc::new(MockRpcContext::build(None)),
+        logctx: ut_setup_logctx(),
+    };
+    let result = uc.execute(Label3party::Stripe, "t=1,v1=ab", b"{}").await;
+    assert!(matches!(result, Ok(true)));
+}
+
+#[actix_web::test]
+async fn account_updated_unchanged() {
+    let mock_store_id = 1019;
+    let saved = ut_default_merchant_3party_stripe();
+    let processors = {
+        let latest = ut_clone_merchant_3party(&saved);
+        let detail = StripeWebhookEventDetail::AccountUpdated(latest);
+        ut_setup_processor(Ok(ut_stripe_event(detail)))
+    };
+    // the repository should not be updated
+    let repo_merchant = {
+        let arg = ut_setup_store_models(mock_store_id, saved);
+        ut_setup_repo_merchant(Some(mock_store_id), Some(arg), None)
+    };
+    let uc = ProcessorWebhookUseCase {
+        processors,
+        repo_evt: MockWebhookEventRepo::build(Some(Ok(true)), None),
+        repo_charge: ut_setup_repo_charge(None, None, None),
+        repo_merchant,
+        rpc_ctx: Arc::new(MockRpcContext::build(None)),
+        logctx: ut_setup_logctx(),
+    };
+    let result = uc.execute(Label3party::Stripe, "t=1,v1=ab", b"{}").await;
+    assert!(matches!(result, Ok(true)));
+}
+
+#[actix_web::test]
+async fn save_event_error() {
+    let processors = ut_setup_processor(Ok(ut_stripe_event(StripeWebhookEventDetail::Unsupported)));
+    let repo_evt = {
+        let e = AppRepoError {
+            fn_label: AppRepoErrorFnLabel::WebhookEventSave,
+            code: AppErrorCode::DatabaseServerBusy,
+            detail: AppRepoErrorDetail::Unknown,
+        };
+        MockWebhookEventRepo::build(Some(Err(e)), None)
+    };
+    let uc = ProcessorWebhookUseCase {
+        processors,
+        repo_evt,
+        repo_charge: ut_setup_repo_charge(None, None, None),
+        repo_merchant: ut_setup_repo_merchant(None, None, None),
+        rpc_ctx: Arc::new(MockRpcContext::build(None)),
+        logctx: ut_setup_logctx(),
+    };
+    let result = uc.execute(Label3party::Stripe, "t=1,v1=ab", b"{}").await;
+    let cond = matches!(
+        result,
+        Err(ProcessorWebhookUcError::DataStore(AppRepoError {
+            fn_label: AppRepoErrorFnLabel::WebhookEventSave,
+            code: _,
+            detail: _
+        }))
+    );
+    assert!(cond);
+}
+
+#[actix_web::test]
+async fn account_update_error_event_reverted() {
+    let mock_store_id = 1019;
+    let saved = ut_default_merchant_3party_stripe();
+    let processors = {
+        let mut latest = ut_clone_merchant_3party(&saved);
+        latest.payouts_enabled = true;
+        let detail = StripeWebhookEventDetail::AccountUpdated(latest);
+        ut_setup_processor(Ok(ut_stripe_event(detail)))
+    };
+    let repo_merchant = {
+        let arg = ut_setup_store_models(mock_store_id, saved);
+        let e = AppRepoError {
+            fn_label: AppRepoErrorFnLabel::UpdateMerchant3party,
+            code: AppErrorCode::DatabaseServerBusy,
+            detail: AppRepoErrorDetail::Unknown,
+        };
+        ut_setup_repo_merchant(Some(mock_store_id), Some(arg), Some(Err(e)))
+    };
+    // the event record is removed, so the processor can deliver it again
+    let uc = ProcessorWebhookUseCase {
+        processors,
+        repo_evt: MockWebhookEventRepo::build(Some(Ok(true)), Some(Ok(()))),
+        repo_charge: ut_setup_repo_charge(None, None, None),
+        repo_merchant,
+        rpc_ctx: Arc::new(MockRpcContext::build(None)),
+        logctx: ut_setup_logctx(),
+    };
+    let result = uc.execute(Label3party::Stripe, "t=1,v1=ab", b"{}").await;
+    let cond = matches!(
+        result,
+        Err(ProcessorWebhookUcError::DataStore(AppRepoError {
+            fn_label: AppRepoErrorFnLabel::UpdateMerchant3party,
+            code: _,
+            detail: _
+        }))
+    );
+    assert!(cond);
+}
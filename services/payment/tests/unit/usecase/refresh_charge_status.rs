@@ -36,6 +36,7 @@ fn ut_setup_charge_3pty_stripe(expiry: DateTime<Utc>) -> Charge3partyModel {
         transfer_group: "mock-transfer-group".to_string(),
         payment_intent_id: "mock-payment-intent-id".to_string(),
         expiry,
+        amount_refunded: 0,
     };
     Charge3partyModel::Stripe(stripe3pty)
 }
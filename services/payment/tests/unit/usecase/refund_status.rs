@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use chrono::{DateTime, Duration, Local, Utc};
+use rust_decimal::Decimal;
+
+use ecommerce_common::api::dto::{CurrencyDto, PayAmountDto};
+use ecommerce_common::api::rpc::dto::OrderLineReplicaRefundDto;
+use payment::adapter::repository::{AbstractChargeRepo, AbstractRefundRepo};
+use payment::model::{
+    BuyerPayInState, ChargeLineBuyerModel, OrderCurrencySnapshot, OrderRefundModel,
+};
+use payment::usecase::{RefundReqStatusUcError, RefundReqStatusUseCase};
+
+use super::{MockChargeRepo, MockRefundRepo};
+use crate::auth::ut_setup_auth_claim;
+use crate::model::{
+    ut_default_charge_method_stripe, ut_setup_buyer_charge, UTestChargeLineRawData,
+};
+
+#[rustfmt::skip]
+fn ut_setup_repo_charge(
+    charge_ids: Option<(u32, Vec<DateTime<Utc>>)>,
+    clines: Option<Vec<ChargeLineBuyerModel>>,
+) -> Box<dyn AbstractChargeRepo> {
+    MockChargeRepo::build(
+        None, None, None, None,
+        clines.map(Ok), None, None,
+        None, None, charge_ids, None,
+    )
+}
+
+#[rustfmt::skip]
+fn ut_setup_charge_lines(
+    buyer_usr_id: u32, charge_ctime: DateTime<Utc>, merchant_id: u32,
+) -> Vec<ChargeLineBuyerModel> {
+    let charge_dlines: Vec<UTestChargeLineRawData> = vec![
+        ((merchant_id, 8299, 0), ((325, 1), (3250, 1), 10), ((325, 1), (650, 1), 2), 1),
+        ((merchant_id, 8454, 0), ((909, 1), (9090, 1), 10), ((0, 0), (0, 0), 0), 0),
+        ((7788, 9914, 0), ((8392, 2), (83920, 2), 10), ((8392, 2), (8392, 2), 1), 0),
+    ];
+    let paymethod = ut_default_charge_method_stripe(&charge_ctime);
+    let currency_snapshot = {
+        let iter = [
+            (buyer_usr_id, CurrencyDto::TWD, (3187i64, 2u32)),
+            (merchant_id, CurrencyDto::IDR, (125021, 1)),
+            (7788, CurrencyDto::USD, (1, 0)),
+        ]
+        .map(|(usr_id, label, ratescalar)| {
+            let rate = Decimal::new(ratescalar.0, ratescalar.1);
+            (usr_id, OrderCurrencySnapshot { label, rate })
+        });
+        HashMap::from_iter(iter)
+    };
+    let charge_m = ut_setup_buyer_charge(
+        buyer_usr_id, charge_ctime, "d003bea7".to_string(),
+        BuyerPayInState::OrderAppSynced(charge_ctime),
+        paymethod, charge_dlines, currency_snapshot,
+    );
+    charge_m.lines
+}
+
+// seller ID, product ID, attribute sequence, minutes before time base,
+// unit amount, total amount, quantity
+#[rustfmt::skip]
+type UTestRefundReqRawData = ((u32, u64, u16), i64, (i64, u32), (i64, u32), u32);
+
+#[rustfmt::skip]
+fn ut_setup_order_refund_model(
+    oid: &str, time_base: DateTime<Utc>,
+    d_lines: Vec<UTestRefundReqRawData>,
+) -> OrderRefundModel {
+    let rfnd_dtos = d_lines.into_iter()
+        .map(|d| OrderLineReplicaRefundDto {
+            seller_id: d.0.0, product_id: d.0.1, attr_set_seq: d.0.2,
+            create_time: (time_base - Duration::minutes(d.1)).to_rfc3339(),
+            amount: PayAmountDto {
+                unit: Decimal::new(d.2.0, d.2.1).to_string(),
+                total: Decimal::new(d.3.0, d.3.1).to_string(),
+            },
+            qty: d.4,
+        })
+        .collect::<Vec<_>>();
+    OrderRefundModel::try_from((oid.to_string(), rfnd_dtos)).unwrap()
+}
+
+fn ut_setup_repo_refund(saved_req: Option<OrderRefundModel>) -> Box<dyn AbstractRefundRepo> {
+    MockRefundRepo::build(saved_req, None)
+}
+
+#[rustfmt::skip]
+#[actix_web::test]
+async fn buyer_check_status_ok() {
+    let time_base = Local::now().to_utc();
+    let mock_oid = "d003bea7";
+    let mock_buyer_usr_id = 960u32;
+    let mock_merchant_id = 127u32;
+    let charge_ctime = time_base - Duration::days(3);
+    let repo_ch = ut_setup_repo_charge(
+        Some((mock_buyer_usr_id, vec![charge_ctime])),
+        Some(ut_setup_charge_lines(mock_buyer_usr_id, charge_ctime, mock_merchant_id)),
+    );
+    let repo_rfd = {
+        let rfnd_m = ut_setup_order_refund_model(mock_oid, time_base, vec![
+            ((mock_merchant_id, 8299, 0), 30, (325, 1), (650, 1), 2),
+            ((mock_merchant_id, 8299, 0), 95, (325, 1), (975, 1), 3),
+            ((7788, 9914, 0), 50, (8392, 2), (8392, 2), 1),
+        ]);
+        ut_setup_repo_refund(Some(rfnd_m))
+    };
+    let uc = RefundReqStatusUseCase { repo_ch, repo_rfd };
+    let authed_claim = ut_setup_auth_claim(mock_buyer_usr_id, 450);
+    let result = uc.execute(mock_oid.to_string(), authed_claim).await;
+    assert!(result.is_ok());
+    let resp = result.unwrap();
+    assert_eq!(resp.order_id.as_str(), mock_oid);
+    assert_eq!(resp.lines.len(), 2);
+
+    let line = &resp.lines[0];
+    assert_eq!((line.seller_id, line.product_id, line.attr_set_seq), (mock_merchant_id, 8299, 0));
+    assert_eq!(line.requests.len(), 2);
+    // the earlier request should come first
+    assert_eq!(line.requests[0].qty_requested, 3);
+    assert_eq!(line.requests[1].qty_requested, 2);
+    assert!(line.requests[0].time_issued < line.requests[1].time_issued);
+    assert_eq!(line.requests[0].approval.quantity, 0);
+    assert_eq!(line.refunded.quantity, 2);
+    let actual = Decimal::from_str(line.refunded.amount_total.as_str()).unwrap();
+    assert_eq!(actual, Decimal::new(650, 1));
+
+    let line = &resp.lines[1];
+    assert_eq!((line.seller_id, line.product_id, line.attr_set_seq), (7788, 9914, 0));
+    assert_eq!(line.requests.len(), 1);
+    let actual = Decimal::from_str(line.requests[0].amount_requested.total.as_str()).unwrap();
+    assert_eq!(actual, Decimal::new(8392, 2));
+    assert_eq!(line.refunded.quantity, 1);
+    let actual = Decimal::from_str(line.refunded.amount_total.as_str()).unwrap();
+    assert_eq!(actual, Decimal::new(8392, 2));
+} // end of fn buyer_check_status_ok
+
+#[actix_web::test]
+async fn buyer_check_status_owner_mismatch() {
+    let time_base = Local::now().to_utc();
+    let mock_oid = "d003bea7";
+    let mock_buyer_usr_id = 960u32;
+    let mock_other_usr_id = 961u32;
+    let charge_ctime = time_base - Duration::days(3);
+    let repo_ch = ut_setup_repo_charge(Some((mock_buyer_usr_id, vec![charge_ctime])), None);
+    let repo_rfd = ut_setup_repo_refund(None);
+    let uc = RefundReqStatusUseCase { repo_ch, repo_rfd };
+    let authed_claim = ut_setup_auth_claim(mock_other_usr_id, 450);
+    let result = uc.execute(mock_oid.to_string(), authed_claim).await;
+    if let Err(RefundReqStatusUcError::OwnerMismatch(usr_id)) = result {
+        assert_eq!(usr_id, mock_other_usr_id);
+    } else {
+        panic!("unexpected result");
+    }
+}
+
+#[actix_web::test]
+async fn buyer_check_status_missing_charge() {
+    let mock_oid = "d003bea7";
+    let repo_ch = ut_setup_repo_charge(None, None);
+    let repo_rfd = ut_setup_repo_refund(None);
+    let uc = RefundReqStatusUseCase { repo_ch, repo_rfd };
+    let authed_claim = ut_setup_auth_claim(960, 450);
+    let result = uc.execute(mock_oid.to_string(), authed_claim).await;
+    if let Err(RefundReqStatusUcError::MissingChargeId(oid)) = result {
+        assert_eq!(oid.as_str(), mock_oid);
+    } else {
+        panic!("unexpected result");
+    }
+}
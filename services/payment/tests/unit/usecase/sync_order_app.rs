@@ -0,0 +1,205 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Duration, Local, Utc};
+use tokio::sync::Mutex;
+
+use ecommerce_common::api::rpc::dto::{
+    OrderLinePayUpdateErrorDto, OrderLinePayUpdateErrorReason, OrderPaymentUpdateErrorDto,
+};
+use ecommerce_common::error::AppErrorCode;
+use payment::adapter::repository::{
+    AbstractChargeRepo, AppRepoError, AppRepoErrorDetail, AppRepoErrorFnLabel,
+};
+use payment::adapter::rpc::{AbstractRpcContext, AppRpcReply};
+use payment::hard_limit;
+use payment::model::{
+    BuyerPayInState, Charge3partyModel, ChargeBuyerMetaModel, ChargeLineBuyerModel,
+    StripeCheckoutPaymentStatusModel, StripeSessionStatusModel,
+};
+use payment::usecase::{SyncOrderAppUcError, SyncOrderAppUseCase};
+
+use super::{MockChargeRepo, MockRpcClient, MockRpcContext, MockRpcPublishEvent};
+use crate::model::{ut_default_charge_method_stripe, ut_setup_buyer_charge_lines};
+use crate::ut_setup_logctx;
+
+fn ut_setup_unsynced_meta(usr_id: u32, oid: &str, ctime: DateTime<Utc>) -> ChargeBuyerMetaModel {
+    let mut obj = ChargeBuyerMetaModel::from((oid.to_string(), usr_id, ctime));
+    obj.update_3party(ut_default_charge_method_stripe(&ctime));
+    let state = BuyerPayInState::ProcessorCompleted(ctime + Duration::seconds(30));
+    obj.update_progress(&state);
+    obj
+}
+
+fn ut_setup_repo(
+    unsynced: Vec<Result<Vec<ChargeBuyerMetaModel>, AppRepoError>>,
+    all_chargelines: Option<Result<Vec<ChargeLineBuyerModel>, AppRepoError>>,
+    update_meta_res: Option<Result<(), AppRepoError>>,
+) -> Box<dyn AbstractChargeRepo> {
+    let obj = MockChargeRepo {
+        _expect_unpaid_olines: Mutex::new(None),
+        _create_order_result: Mutex::new(None),
+        _create_charge_result: Mutex::new(None),
+        _read_charge_meta: Mutex::new(None),
+        _read_all_charge_lines: Mutex::new(all_chargelines),
+        _update_chargemeta_result: Mutex::new(update_meta_res),
+        _read_charge_by_merchant: Mutex::new(None),
+        _read_payout: Mutex::new(None),
+        _create_payout_result: Mutex::new(None),
+        _read_charge_ids: Mutex::new(None),
+        _update_linerefund_result: Mutex::new(None),
+        _read_unsynced_metas: Mutex::new(unsynced),
+    };
+    Box::new(obj)
+}
+
+fn ut_setup_rpc_ctx(reply: Option<OrderPaymentUpdateErrorDto>) -> Box<dyn AbstractRpcContext> {
+    let acquire_res = reply.map(|r| {
+        let mock_reply = AppRpcReply {
+            message: serde_json::to_vec(&r).unwrap(),
+        };
+        let rpc_pub_evt = MockRpcPublishEvent::build(Some(Ok(mock_reply)));
+        Ok(MockRpcClient::build(Some(Ok(rpc_pub_evt))))
+    });
+    MockRpcContext::build(acquire_res)
+}
+
+#[rustfmt::skip]
+fn ut_charge_lines() -> Vec<ChargeLineBuyerModel> {
+    let d = vec![
+        ((8298, 471, 0), ((9028, 2), (36112, 2), 4), ((0,0), (0,0), 0), 0),
+        ((2369, 380, 0), ((551, 1), (1102, 1), 2), ((0,0), (0,0), 0), 0),
+    ];
+    ut_setup_buyer_charge_lines(d)
+}
+
+#[actix_web::test]
+async fn ok_charge_synced() {
+    let (mock_usr_id, mock_oid) = (8010095, "0a1b2c3d");
+    let mock_ctime = Local::now().to_utc() - Duration::minutes(25);
+    let metas = vec![ut_setup_unsynced_meta(mock_usr_id, mock_oid, mock_ctime)];
+    let repo = ut_setup_repo(vec![Ok(metas)], Some(Ok(ut_charge_lines())), Some(Ok(())));
+    let reply = OrderPaymentUpdateErrorDto {
+        oid: mock_oid.to_string(),
+        charge_time: None,
+        lines: Vec::new(),
+    };
+    let uc = SyncOrderAppUseCase {
+        repo,
+        rpc_ctx: Arc::new(ut_setup_rpc_ctx(Some(reply))),
+        logctx: ut_setup_logctx(),
+    };
+    let result = uc.execute().await;
+    assert!(matches!(result, Ok((1, 0))));
+}
+
+#[actix_web::test]
+async fn unconfirmed_charge_skipped() {
+    let (mock_usr_id, mock_oid) = (8010095, "0a1b2c3d");
+    let mock_ctime = Local::now().to_utc() - Duration::minutes(25);
+    let mut meta = ut_setup_unsynced_meta(mock_usr_id, mock_oid, mock_ctime);
+    let mut m3pty = ut_default_charge_method_stripe(&mock_ctime);
+    if let Charge3partyModel::Stripe(s) = &mut m3pty {
+        s.session_state = StripeSessionStatusModel::expired;
+        s.payment_state = StripeCheckoutPaymentStatusModel::unpaid;
+    }
+    meta.update_3party(m3pty);
+    // neither order-processing service nor charge repository is accessed
+    let repo = ut_setup_repo(vec![Ok(vec![meta])], None, None);
+    let uc = SyncOrderAppUseCase {
+        repo,
+        rpc_ctx: Arc::new(ut_setup_rpc_ctx(None)),
+        logctx: ut_setup_logctx(),
+    };
+    let result = uc.execute().await;
+    assert!(matches!(result, Ok((0, 0))));
+}
+
+#[actix_web::test]
+async fn unconfirmed_charges_not_block_next_page() {
+    let mock_usr_id = 8010096;
+    let t_base = Local::now().to_utc() - Duration::hours(5);
+    let num_stuck = hard_limit::ORDERAPP_SYNC_MAX_ITEMS as i64;
+    // the first page is full of charges which will never be confirmed
+    let stuck_metas = (0..num_stuck)
+        .map(|i| {
+            let ctime = t_base + Duration::seconds(i);
+            let mut meta = ut_setup_unsynced_meta(mock_usr_id, "0a1b2c3d", ctime);
+            let mut m3pty = ut_default_charge_method_stripe(&ctime);
+            if let Charge3partyModel::Stripe(s) = &mut m3pty {
+                s.session_state = StripeSessionStatusModel::expired;
+                s.payment_state = StripeCheckoutPaymentStatusModel::unpaid;
+            }
+            meta.update_3party(m3pty);
+            meta
+        })
+        .collect::<Vec<_>>();
+    let confirmed_metas = vec![ut_setup_unsynced_meta(
+        mock_usr_id,
+        "0a1b2c3e",
+        t_base + Duration::seconds(num_stuck + 1),
+    )];
+    let pages = vec![Ok(stuck_metas), Ok(confirmed_metas)];
+    let repo = ut_setup_repo(pages, Some(Ok(ut_charge_lines())), Some(Ok(())));
+    let reply = OrderPaymentUpdateErrorDto {
+        oid: "0a1b2c3e".to_string(),
+        charge_time: None,
+        lines: Vec::new(),
+    };
+    let uc = SyncOrderAppUseCase {
+        repo,
+        rpc_ctx: Arc::new(ut_setup_rpc_ctx(Some(reply))),
+        logctx: ut_setup_logctx(),
+    };
+    let result = uc.execute().await;
+    assert!(matches!(result, Ok((1, 0))));
+}
+
+#[actix_web::test]
+async fn order_app_reply_error() {
+    let (mock_usr_id, mock_oid) = (8010095, "0a1b2c3d");
+    let mock_ctime = Local::now().to_utc() - Duration::minutes(25);
+    let metas = vec![ut_setup_unsynced_meta(mock_usr_id, mock_oid, mock_ctime)];
+    // charge state is not updated, the charge will be synced next time
+    let repo = ut_setup_repo(vec![Ok(metas)], Some(Ok(ut_charge_lines())), None);
+    let reply = OrderPaymentUpdateErrorDto {
+        oid: mock_oid.to_string(),
+        charge_time: None,
+        lines: vec![OrderLinePayUpdateErrorDto {
+            seller_id: 8298,
+            product_id: 471,
+            attr_set_seq: 0,
+            reason: OrderLinePayUpdateErrorReason::Cancelled,
+        }],
+    };
+    let uc = SyncOrderAppUseCase {
+        repo,
+        rpc_ctx: Arc::new(ut_setup_rpc_ctx(Some(reply))),
+        logctx: ut_setup_logctx(),
+    };
+    let result = uc.execute().await;
+    assert!(matches!(result, Ok((0, 1))));
+}
+
+#[actix_web::test]
+async fn load_unsynced_charges_failure() {
+    let repo_expect_error = AppRepoError {
+        fn_label: AppRepoErrorFnLabel::FetchChargesUnsynced,
+        code: AppErrorCode::DatabaseServerBusy,
+        detail: AppRepoErrorDetail::Unknown,
+    };
+    let repo = ut_setup_repo(vec![Err(repo_expect_error)], None, None);
+    let uc = SyncOrderAppUseCase {
+        repo,
+        rpc_ctx: Arc::new(ut_setup_rpc_ctx(None)),
+        logctx: ut_setup_logctx(),
+    };
+    let result = uc.execute().await;
+    let cond = matches!(
+        result,
+        Err(SyncOrderAppUcError::DataStore(AppRepoError {
+            fn_label: AppRepoErrorFnLabel::FetchChargesUnsynced,
+            ..
+        }))
+    );
+    assert!(cond);
+}